@@ -0,0 +1,15 @@
+//! Generates the gRPC service from the proto definitions.
+//! They are compiled by `protox`, so `protoc` does not need to be installed.
+
+const PROTO: &str = "proto/stellwerk/internal/v1/internal.proto";
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed={PROTO}");
+
+    let file_descriptors = protox::compile([PROTO], ["proto"])?;
+    tonic_prost_build::configure()
+        .build_client(false)
+        .compile_fds(file_descriptors)?;
+
+    Ok(())
+}
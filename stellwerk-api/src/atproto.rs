@@ -0,0 +1,161 @@
+//! Mirroring of [AT Protocol](https://atproto.com/) accounts, e.g. from Bluesky,
+//! into read-only local users and posts.
+//!
+//! Configured accounts are polled through the XRPC API of an app view, like the public Bluesky one.
+//! Mirrored users are treated like remote users, so they are not exposed over ActivityPub.
+
+use std::{sync::Arc, time::Duration};
+use stellwerk_common::model::{
+    atproto::{AuthorFeed, PostView},
+    post::{CreatePost, PostContent},
+    user::UserHandle,
+};
+use stellwerk_db::client::{DbClient, DbError};
+use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error};
+
+const BRIDGE_POLL_INTERVAL: Duration = Duration::from_mins(5);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// How many of the newest posts of each account are checked per poll.
+const FEED_PAGE_SIZE: u32 = 30;
+
+#[derive(Debug, Error)]
+enum BridgeError {
+    #[error("Error fetching the feed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error(transparent)]
+    Database(#[from] DbError),
+}
+
+pub struct AtprotoBridge {
+    db: Arc<DbClient>,
+    http: reqwest::Client,
+    /// Without a trailing slash.
+    appview_url: String,
+    /// Handles or DIDs.
+    accounts: Vec<String>,
+    post_content_max_len: usize,
+}
+
+impl AtprotoBridge {
+    pub fn new(
+        db: Arc<DbClient>,
+        appview_url: &str,
+        accounts: Vec<String>,
+        public_url: &str,
+        post_content_max_len: usize,
+    ) -> Result<Self, reqwest::Error> {
+        let http = reqwest::Client::builder()
+            .user_agent(format!(
+                "stellwerk/{} (+{public_url})",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+
+        Ok(Self {
+            db,
+            http,
+            appview_url: appview_url.trim_end_matches('/').to_owned(),
+            accounts,
+            post_content_max_len,
+        })
+    }
+
+    /// Returns the number of newly mirrored posts.
+    async fn mirror_account(&self, account: &str) -> Result<usize, BridgeError> {
+        let feed: AuthorFeed = self
+            .http
+            .get(format!(
+                "{}/xrpc/app.bsky.feed.getAuthorFeed",
+                self.appview_url
+            ))
+            .query(&[
+                ("actor", account),
+                ("filter", "posts_no_replies"),
+                ("limit", &FEED_PAGE_SIZE.to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // Reposts are in the feed of the reposting account, but are not its own posts.
+        let posts: Vec<_> = feed
+            .feed
+            .into_iter()
+            .filter(|item| item.reason.is_none())
+            .map(|item| item.post)
+            .collect();
+        let Some(author) = posts.first().map(|post| &post.author) else {
+            return Ok(0);
+        };
+        let Ok(handle) = UserHandle::new(author.handle.clone()) else {
+            debug!(
+                handle = author.handle,
+                "Ignored account with too long handle"
+            );
+            return Ok(0);
+        };
+        let user = self.db.upsert_atproto_account(&author.did, &handle).await?;
+
+        let mut mirrored = 0;
+        // Feeds are newest first, but the mirrored posts should be in the original order.
+        for post in posts.iter().rev() {
+            if post.author.did != author.did {
+                continue;
+            }
+            let Some(content) = self.post_content(post) else {
+                continue;
+            };
+
+            let create = CreatePost {
+                author: user.id,
+                content,
+            };
+            if self
+                .db
+                .create_atproto_post(&create, &post.uri, &post.cid, post.record.created_at)
+                .await?
+                .is_some()
+            {
+                mirrored += 1;
+            }
+        }
+
+        Ok(mirrored)
+    }
+
+    /// The plain text of the post, truncated to the local length limit.
+    fn post_content(&self, post: &PostView) -> Option<PostContent> {
+        let content: String = post
+            .record
+            .text
+            .chars()
+            .take(self.post_content_max_len)
+            .collect();
+
+        PostContent::with_max_len(content, self.post_content_max_len).ok()
+    }
+}
+
+pub async fn atproto_bridge_loop(bridge: AtprotoBridge, cancellation: CancellationToken) {
+    loop {
+        for account in &bridge.accounts {
+            match bridge.mirror_account(account).await {
+                Ok(0) => {}
+                Ok(mirrored) => debug!(account, "Mirrored {mirrored} AT Protocol posts"),
+                Err(error) => error!(account, %error, "Error trying to mirror AT Protocol account"),
+            }
+        }
+        if cancellation
+            .run_until_cancelled(tokio::time::sleep(BRIDGE_POLL_INTERVAL))
+            .await
+            .is_none()
+        {
+            return;
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::cdn::{CachePurger, PurgeError};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+
+/// How many URLs Cloudflare purges in one request.
+const URLS_PER_REQUEST: usize = 30;
+
+/// Purging the cache of a [Cloudflare](https://www.cloudflare.com) zone with an API token
+/// that has the `Cache Purge` permission.
+#[derive(Clone, Debug)]
+pub struct Cloudflare {
+    http: reqwest::Client,
+    zone_id: String,
+    api_token: String,
+}
+
+#[derive(Deserialize)]
+struct PurgeResponse {
+    success: bool,
+    #[serde(default)]
+    errors: Vec<ResponseError>,
+}
+
+#[derive(Deserialize)]
+struct ResponseError {
+    message: String,
+}
+
+impl Cloudflare {
+    #[must_use]
+    pub fn new(http: reqwest::Client, zone_id: String, api_token: String) -> Self {
+        Self {
+            http,
+            zone_id,
+            api_token,
+        }
+    }
+}
+
+#[async_trait]
+impl CachePurger for Cloudflare {
+    async fn purge(&self, urls: &[String]) -> Result<(), PurgeError> {
+        for urls in urls.chunks(URLS_PER_REQUEST) {
+            let response: PurgeResponse = self
+                .http
+                .post(format!(
+                    "https://api.cloudflare.com/client/v4/zones/{}/purge_cache",
+                    self.zone_id
+                ))
+                .bearer_auth(&self.api_token)
+                .json(&json!({ "files": urls }))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            if !response.success {
+                let messages: Vec<_> = response
+                    .errors
+                    .into_iter()
+                    .map(|error| error.message)
+                    .collect();
+                return Err(PurgeError::Rejected(messages.join("; ")));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use crate::cdn::{CachePurger, PurgeError};
+use async_trait::async_trait;
+
+/// Purging single URLs from [Fastly](https://www.fastly.com) with an API token that has the
+/// `purge_select` scope.
+#[derive(Clone, Debug)]
+pub struct Fastly {
+    http: reqwest::Client,
+    api_token: String,
+}
+
+impl Fastly {
+    #[must_use]
+    pub fn new(http: reqwest::Client, api_token: String) -> Self {
+        Self { http, api_token }
+    }
+}
+
+#[async_trait]
+impl CachePurger for Fastly {
+    async fn purge(&self, urls: &[String]) -> Result<(), PurgeError> {
+        for url in urls {
+            // The URL without its scheme.
+            let url = url.split_once("://").map_or(&**url, |(_, url)| url);
+            self.http
+                .post(format!("https://api.fastly.com/purge/{url}"))
+                .header("Fastly-Key", &self.api_token)
+                .send()
+                .await?
+                .error_for_status()?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+//! Purging changed pages from a CDN in front of the server, behind the [`CachePurger`] trait.
+//!
+//! When a post is deleted, restored, pinned, or unpinned, the URLs of its page, its author's
+//! profile page, and its API representation are queued as a [job](crate::jobs), so that a
+//! purge is retried if the CDN is down. Without a configured CDN, nothing is queued.
+
+mod cloudflare;
+mod fastly;
+
+pub use self::{cloudflare::Cloudflare, fastly::Fastly};
+
+use crate::{
+    jobs::{JobError, JobHandler, QueueSettings},
+    server::versioning::CURRENT_VERSION,
+    webhooks,
+};
+use async_trait::async_trait;
+use std::{fmt::Debug, sync::Arc, time::Duration};
+use stellwerk_common::model::{
+    job::{Job, JobKind, JobPayload},
+    page::{post_page_url, profile_page_url},
+    post::Post,
+};
+use stellwerk_db::client::DbClient;
+use thiserror::Error;
+use time::UtcDateTime;
+use tracing::{debug, error};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum PurgeError {
+    #[error("CDN purge request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("The CDN rejected the purge: {0}")]
+    Rejected(String),
+}
+
+/// A CDN whose cached responses can be purged by URL.
+#[async_trait]
+pub trait CachePurger: Debug + Send + Sync {
+    /// Succeeds if the URLs were not cached.
+    async fn purge(&self, urls: &[String]) -> Result<(), PurgeError>;
+}
+
+/// The HTTP client of CDN APIs.
+pub fn http_client() -> Result<reqwest::Client, reqwest::Error> {
+    reqwest::Client::builder()
+        .user_agent(concat!("stellwerk/", env!("CARGO_PKG_VERSION")))
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+}
+
+/// Queues the purges of changed pages, if a CDN is configured.
+#[derive(Clone, Debug)]
+pub struct Cdn {
+    db: Arc<DbClient>,
+    enabled: bool,
+}
+
+impl Cdn {
+    #[must_use]
+    pub fn new(db: Arc<DbClient>, enabled: bool) -> Self {
+        Self { db, enabled }
+    }
+
+    /// Queues purging the pages showing the post. Failing to queue is only logged, since the
+    /// change itself succeeded, and the CDN serves the stale pages until they expire.
+    pub async fn purge_post(&self, public_url: &str, post: &Post) {
+        if !self.enabled {
+            return;
+        }
+
+        let urls = vec![
+            post_page_url(public_url, &post.author.handle, post.id),
+            profile_page_url(public_url, &post.author.handle),
+            format!("{public_url}{CURRENT_VERSION}/posts/{}", post.id),
+            format!("{public_url}/posts/{}", post.id),
+        ];
+        if let Err(error) = self
+            .db
+            .enqueue_jobs(&[JobPayload::CdnPurge { urls }], UtcDateTime::now())
+            .await
+        {
+            error!(%error, post_id = %post.id, "Error trying to queue a CDN purge");
+        }
+    }
+}
+
+/// Runs the [`JobPayload::CdnPurge`] jobs.
+#[derive(Clone, Debug)]
+pub struct CdnPurgeQueue {
+    purger: Arc<dyn CachePurger>,
+}
+
+impl CdnPurgeQueue {
+    #[must_use]
+    pub fn new(purger: Arc<dyn CachePurger>) -> Self {
+        Self { purger }
+    }
+}
+
+impl JobHandler for CdnPurgeQueue {
+    const SETTINGS: QueueSettings = QueueSettings {
+        kind: JobKind::CdnPurge,
+        batch_size: 20,
+        visibility_timeout: Duration::from_mins(5),
+        max_attempts: 10,
+        retry_base_delay: Duration::from_secs(30),
+        retry_max_delay: Duration::from_hours(1),
+    };
+
+    async fn run(&self, job: &Job) -> Result<Option<UtcDateTime>, JobError> {
+        let JobPayload::CdnPurge { urls } = &job.payload else {
+            return Err(JobError::unexpected_payload(&job.payload));
+        };
+
+        match self.purger.purge(urls).await {
+            Ok(()) => {
+                debug!(id = %job.id, ?urls, "Purged URLs from the CDN");
+                Ok(None)
+            }
+            Err(PurgeError::Request(error)) if webhooks::is_permanent(&error) => {
+                Err(JobError::permanent(error))
+            }
+            Err(error @ PurgeError::Rejected(_)) => Err(JobError::permanent(error)),
+            Err(error @ PurgeError::Request(_)) => Err(JobError::transient(error)),
+        }
+    }
+}
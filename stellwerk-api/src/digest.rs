@@ -0,0 +1,138 @@
+//! Email digests of unread notifications, queued by a recurring [job](crate::jobs) and sent by
+//! the [mail queue](crate::mail).
+
+use crate::jobs::{self, JobError, JobHandler, QueueSettings};
+use std::{sync::Arc, time::Duration};
+use stellwerk_common::model::{
+    email::{Digest, Email, EmailDigestSubscription},
+    job::{Job, JobKind, JobPayload},
+};
+use stellwerk_db::client::{DbClient, DbError};
+use time::UtcDateTime;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error};
+
+const DIGEST_CHECK_INTERVAL: Duration = Duration::from_hours(1);
+const DIGEST_BATCH_SIZE: u32 = 100;
+/// Notifications beyond this are only counted, not listed.
+const DIGEST_MAX_NOTIFICATIONS: u32 = 20;
+
+#[derive(Clone, Debug)]
+pub struct DigestSender {
+    db: Arc<DbClient>,
+    public_url: String,
+}
+
+impl DigestSender {
+    pub fn new(db: Arc<DbClient>, public_url: String) -> Self {
+        Self { db, public_url }
+    }
+}
+
+impl JobHandler for DigestSender {
+    const SETTINGS: QueueSettings = QueueSettings {
+        kind: JobKind::EmailDigests,
+        batch_size: 1,
+        visibility_timeout: Duration::from_mins(30),
+        // Never dead-lettered, since no digests would be sent until it is retried.
+        max_attempts: u32::MAX,
+        retry_base_delay: Duration::from_mins(1),
+        retry_max_delay: DIGEST_CHECK_INTERVAL,
+    };
+
+    async fn run(&self, job: &Job) -> Result<Option<UtcDateTime>, JobError> {
+        let JobPayload::EmailDigests = job.payload else {
+            return Err(JobError::unexpected_payload(&job.payload));
+        };
+
+        let queued = queue_due_digests(&self.db, &self.public_url).await?;
+        debug!("Queued {queued} email digests");
+
+        Ok(Some(UtcDateTime::now() + DIGEST_CHECK_INTERVAL))
+    }
+}
+
+/// Schedules the recurring job unless another server did, and runs it when due.
+pub async fn email_digest_loop(sender: Arc<DigestSender>, cancellation: CancellationToken) {
+    let db = sender.db.clone();
+    if let Err(error) = db
+        .schedule_recurring_job(&JobPayload::EmailDigests, UtcDateTime::now())
+        .await
+    {
+        error!(%error, "Error trying to schedule email digests");
+    }
+
+    jobs::job_loop(db, sender, cancellation).await;
+}
+
+/// Returns the number of queued digests.
+async fn queue_due_digests(db: &DbClient, public_url: &str) -> Result<usize, DbError> {
+    let now = UtcDateTime::now();
+    let mut queued = 0;
+
+    // Every handled subscription is rescheduled into the future, so this terminates.
+    loop {
+        let subscriptions = db.fetch_due_email_digests(now, DIGEST_BATCH_SIZE).await?;
+        if subscriptions.is_empty() {
+            return Ok(queued);
+        }
+
+        for subscription in subscriptions {
+            if queue_digest(db, public_url, &subscription, now).await? {
+                queued += 1;
+            }
+        }
+    }
+}
+
+/// Returns whether an email was queued.
+/// Users without new unread notifications do not get an email, and users with
+/// [do not disturb](stellwerk_common::model::do_not_disturb) active get it once it ends.
+async fn queue_digest(
+    db: &DbClient,
+    public_url: &str,
+    subscription: &EmailDigestSubscription,
+    now: UtcDateTime,
+) -> Result<bool, DbError> {
+    // The notifications stay undigested, so they are in the first digest after do not disturb.
+    if let Some(until) = db
+        .fetch_do_not_disturb(subscription.user)
+        .await?
+        .active_until(now)
+    {
+        db.finish_email_digest(subscription.user, None, until)
+            .await?;
+        return Ok(false);
+    }
+
+    let next_digest_at = now + subscription.settings.frequency.period();
+
+    let notifications = db
+        .fetch_notifications(
+            subscription.user,
+            None,
+            subscription.digested_up_to,
+            DIGEST_MAX_NOTIFICATIONS,
+        )
+        .await?;
+    let Some(newest) = notifications.first().map(|notification| notification.id) else {
+        db.finish_email_digest(subscription.user, None, next_digest_at)
+            .await?;
+        return Ok(false);
+    };
+
+    let unread_count = db
+        .fetch_unread_notification_count(subscription.user)
+        .await?;
+    let unsubscribe_url = format!(
+        "{public_url}/v1/email/unsubscribe?token={}",
+        subscription.unsubscribe_token.as_token_str()
+    );
+    let digest = Digest::new(&notifications, unread_count, public_url, &unsubscribe_url);
+    let email = Email::digest(subscription.settings.email.clone(), digest, unsubscribe_url);
+
+    db.finish_email_digest(subscription.user, Some((newest, email)), next_digest_at)
+        .await?;
+
+    Ok(true)
+}
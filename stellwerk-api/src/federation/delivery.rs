@@ -0,0 +1,118 @@
+//! Delivery of queued activities to remote inboxes, run as [jobs](crate::jobs).
+
+use crate::{
+    federation::{ACTIVITY_JSON, Federation, FederationError, sign_request},
+    jobs::{JobError, JobHandler, QueueSettings},
+};
+use axum::http::{StatusCode, header};
+use std::time::Duration;
+use stellwerk_common::{
+    model::{
+        Id,
+        activitypub::{self, actor_id, instance_actor_id},
+        job::{Job, JobKind, JobPayload},
+        user::UserMarker,
+    },
+    signature::SignatureError,
+};
+use thiserror::Error;
+use time::UtcDateTime;
+use tracing::debug;
+
+#[derive(Debug, Error)]
+enum DeliveryError {
+    #[error(transparent)]
+    Federation(#[from] FederationError),
+    #[error("The activity could not be signed: {0}")]
+    Signature(#[from] SignatureError),
+    #[error("The request failed: {0}")]
+    Request(#[from] reqwest::Error),
+}
+
+impl DeliveryError {
+    /// Whether retrying cannot help. Remote client errors are permanent,
+    /// except for timeouts and rate limiting.
+    fn is_permanent(&self) -> bool {
+        match self {
+            DeliveryError::Federation(_) => false,
+            DeliveryError::Signature(_) => true,
+            DeliveryError::Request(error) => {
+                error.is_builder()
+                    || error.status().is_some_and(|status| {
+                        status.is_client_error()
+                            && status != StatusCode::REQUEST_TIMEOUT
+                            && status != StatusCode::TOO_MANY_REQUESTS
+                    })
+            }
+        }
+    }
+}
+
+impl From<DeliveryError> for JobError {
+    fn from(error: DeliveryError) -> Self {
+        if error.is_permanent() {
+            JobError::permanent(error)
+        } else {
+            JobError::transient(error)
+        }
+    }
+}
+
+impl JobHandler for Federation {
+    const SETTINGS: QueueSettings = QueueSettings {
+        kind: JobKind::FederationDelivery,
+        batch_size: 50,
+        visibility_timeout: Duration::from_mins(5),
+        max_attempts: 10,
+        retry_base_delay: Duration::from_mins(1),
+        retry_max_delay: Duration::from_hours(6),
+    };
+
+    async fn run(&self, job: &Job) -> Result<Option<UtcDateTime>, JobError> {
+        let JobPayload::FederationDelivery {
+            sender,
+            inbox,
+            activity,
+        } = &job.payload
+        else {
+            return Err(JobError::unexpected_payload(&job.payload));
+        };
+
+        self.post_activity(*sender, inbox, activity).await?;
+        debug!(id = %job.id, inbox, "Delivered activity");
+
+        Ok(None)
+    }
+}
+
+impl Federation {
+    async fn post_activity(
+        &self,
+        sender: Option<Id<UserMarker>>,
+        inbox: &str,
+        activity: &str,
+    ) -> Result<(), DeliveryError> {
+        let (actor, key_pair) = match sender {
+            Some(sender) => (
+                actor_id(&self.public_url, sender),
+                self.user_key_pair(sender).await?,
+            ),
+            None => (
+                instance_actor_id(&self.public_url),
+                self.instance_key_pair().await?,
+            ),
+        };
+
+        let mut request = self
+            .http
+            .post(inbox)
+            .header(header::CONTENT_TYPE, ACTIVITY_JSON)
+            .body(activity.to_owned())
+            .build()?;
+        sign_request(&mut request, activitypub::key_id(&actor), &key_pair)?;
+
+        self.http.execute(request).await?.error_for_status()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+//! Handling of activities received from other servers.
+
+use crate::federation::{Federation, FederationError};
+use stellwerk_common::{
+    model::{
+        activitypub::{Activity, ActivityObject, Note},
+        notification::{CreateNotification, NotificationKind},
+        post::{CreatePost, PostContent},
+    },
+    text,
+};
+use tracing::debug;
+
+impl Federation {
+    /// Maps an activity received in an inbox onto local models.
+    /// `signer` is the verified actor that sent it.
+    ///
+    /// Activities that are not supported, or that refer to objects unknown to us,
+    /// are ignored, since other servers cannot do anything about those anyway.
+    pub async fn receive(
+        &self,
+        signer: &str,
+        activity: Activity,
+        post_content_max_len: usize,
+    ) -> Result<(), FederationError> {
+        if activity.actor != signer {
+            return Err(FederationError::ActorMismatch {
+                activity_actor: activity.actor,
+                signer: signer.to_owned(),
+            });
+        }
+
+        debug!(id = activity.id, kind = activity.kind, "Received activity");
+        match activity.kind.as_str() {
+            "Follow" => self.receive_follow(activity).await,
+            "Like" => {
+                self.receive_reaction(&activity, NotificationKind::Like)
+                    .await
+            }
+            "Announce" => {
+                self.receive_reaction(&activity, NotificationKind::Announce)
+                    .await
+            }
+            "Create" => match activity.object {
+                ActivityObject::Note(note) => {
+                    self.receive_note(&activity.actor, *note, post_content_max_len)
+                        .await
+                }
+                _ => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Follows the local user and accepts the follow.
+    async fn receive_follow(&self, follow: Activity) -> Result<(), FederationError> {
+        let Some(target_id) = self.local_id(follow.object.id(), "/users/") else {
+            return Ok(());
+        };
+        if self.db.fetch_user(target_id).await?.is_none()
+            || self.db.is_remote_user(target_id).await?
+        {
+            return Ok(());
+        }
+
+        let (follower, actor) = self.fetch_remote_actor(&follow.actor).await?;
+        if self.db.follow_user(follower.id, target_id).await? {
+            self.db
+                .create_notification(&CreateNotification {
+                    user: target_id,
+                    kind: NotificationKind::Follow,
+                    actor: follower.id,
+                    post: None,
+                })
+                .await?;
+        }
+
+        // Repeated follows are accepted again, since the first `Accept` may have been lost.
+        let accept = Activity::accept(follow, follower.id);
+        self.enqueue(
+            Some(target_id),
+            &[actor.delivery_inbox().to_owned()],
+            &accept,
+        )
+        .await
+    }
+
+    /// Notifies the author of a liked or shared local post.
+    async fn receive_reaction(
+        &self,
+        activity: &Activity,
+        kind: NotificationKind,
+    ) -> Result<(), FederationError> {
+        let Some(post_id) = self.local_id(activity.object.id(), "/posts/") else {
+            return Ok(());
+        };
+        let Some(post) = self.db.fetch_post(post_id).await? else {
+            return Ok(());
+        };
+        if self.db.is_remote_user(post.author.id).await? {
+            return Ok(());
+        }
+
+        let actor = self.remote_actor_user(&activity.actor).await?;
+        self.db
+            .create_notification(&CreateNotification {
+                user: post.author.id,
+                kind,
+                actor: actor.id,
+                post: Some(post_id),
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Stores a remote post as plain text, truncated to the local length limit.
+    async fn receive_note(
+        &self,
+        actor_id: &str,
+        note: Note,
+        post_content_max_len: usize,
+    ) -> Result<(), FederationError> {
+        if note.kind != "Note" || note.attributed_to != actor_id {
+            return Ok(());
+        }
+
+        let content: String = text::html_to_text(&note.content)
+            .chars()
+            .take(post_content_max_len)
+            .collect();
+        let Ok(content) = PostContent::with_max_len(content, post_content_max_len) else {
+            return Ok(());
+        };
+
+        let author = self.remote_actor_user(actor_id).await?;
+        let post = CreatePost {
+            author: author.id,
+            content,
+        };
+        if self.db.create_remote_post(&post, &note.id).await?.is_none() {
+            debug!(id = note.id, "Ignored already received note");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,539 @@
+//! Communication with other ActivityPub servers.
+
+use crate::verification;
+use axum::http::{HeaderMap, HeaderValue, Method, StatusCode, Uri, header};
+use headers::HeaderMapExt;
+use reqwest::redirect::Policy;
+use serde::de::DeserializeOwned;
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use stellwerk_common::{
+    model::{
+        Id,
+        activitypub::{
+            self, Activity, KeyDocument, Note, PublicKey, RemoteActor, actor_id, instance_actor_id,
+        },
+        job::JobPayload,
+        post::Post,
+        problem::ErrorCode,
+        user::{User, UserHandle, UserMarker},
+    },
+    signature::{KeyPair, REQUEST_TARGET, Signature, SignatureError, digest},
+};
+use stellwerk_db::client::{DbClient, DbError};
+use thiserror::Error;
+use time::UtcDateTime;
+use tokio::task::JoinError;
+use tracing::{debug, info};
+
+mod delivery;
+mod inbox;
+
+pub const ACTIVITY_JSON: &str = "application/activity+json";
+/// How old the `Date` of a signed request may be.
+const SIGNATURE_MAX_AGE: Duration = Duration::from_hours(12);
+/// How far the `Date` of a signed request may be in the future.
+const CLOCK_SKEW: Duration = Duration::from_hours(1);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Larger fetched objects are rejected.
+const OBJECT_MAX_LEN: usize = 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum FederationError {
+    #[error("The HTTP signature is invalid: {0}")]
+    Signature(#[from] SignatureError),
+    #[error("The Date header is missing or too far from the current time")]
+    InvalidDate,
+    #[error("The key {0} does not belong to a known actor")]
+    UnknownKey(String),
+    /// The error of the request is only logged, since it may reveal the network of the server.
+    #[error("The key {0} could not be fetched")]
+    KeyFetch(String),
+    #[error("The request body does not match the signed Digest header")]
+    DigestMismatch,
+    #[error("The activity is invalid: {0}")]
+    InvalidActivity(serde_json::Error),
+    #[error("The activity by {activity_actor} was not sent by its actor, but by {signer}")]
+    ActorMismatch {
+        activity_actor: String,
+        signer: String,
+    },
+    #[error("The actor {actor_id} could not be fetched: {error}")]
+    ActorFetch {
+        actor_id: String,
+        error: reqwest::Error,
+    },
+    #[error("The actor {0} is not a valid remote actor")]
+    InvalidActor(String),
+    #[error("Key generation panicked: {0}")]
+    KeyGeneration(#[from] JoinError),
+    #[error("The activity could not be serialized: {0}")]
+    Serialize(serde_json::Error),
+    #[error(transparent)]
+    Database(#[from] DbError),
+}
+
+impl FederationError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            FederationError::Database(
+                DbError::PoolExhausted | DbError::StatementTimeout | DbError::Unavailable,
+            ) => StatusCode::SERVICE_UNAVAILABLE,
+            FederationError::Signature(SignatureError::Rsa(_) | SignatureError::PrivateKey(_))
+            | FederationError::KeyGeneration(_)
+            | FederationError::Serialize(_)
+            | FederationError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            FederationError::Signature(_)
+            | FederationError::InvalidDate
+            | FederationError::UnknownKey(_)
+            | FederationError::KeyFetch(_)
+            | FederationError::DigestMismatch => StatusCode::UNAUTHORIZED,
+            FederationError::InvalidActivity(_) => StatusCode::BAD_REQUEST,
+            FederationError::ActorMismatch { .. } => StatusCode::FORBIDDEN,
+            FederationError::InvalidActor(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            FederationError::ActorFetch { .. } => StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            FederationError::Database(
+                DbError::PoolExhausted | DbError::StatementTimeout | DbError::Unavailable,
+            ) => ErrorCode::DatabaseUnavailable,
+            FederationError::Signature(SignatureError::Rsa(_) | SignatureError::PrivateKey(_))
+            | FederationError::KeyGeneration(_)
+            | FederationError::Serialize(_)
+            | FederationError::Database(_) => ErrorCode::InternalError,
+            FederationError::Signature(_)
+            | FederationError::InvalidDate
+            | FederationError::UnknownKey(_)
+            | FederationError::KeyFetch(_)
+            | FederationError::DigestMismatch => ErrorCode::InvalidSignature,
+            FederationError::InvalidActivity(_) => ErrorCode::InvalidActivity,
+            FederationError::ActorMismatch { .. } => ErrorCode::ActorMismatch,
+            FederationError::InvalidActor(_) => ErrorCode::InvalidActor,
+            FederationError::ActorFetch { .. } => ErrorCode::ActorFetchFailed,
+        }
+    }
+}
+
+/// Signs outgoing and verifies incoming federation requests.
+///
+/// URLs come from other servers, so like profile websites, they are only requested from global
+/// addresses, see [`verification`].
+#[derive(Clone, Debug)]
+pub struct Federation {
+    db: Arc<DbClient>,
+    http: reqwest::Client,
+    public_url: String,
+}
+
+impl Federation {
+    pub fn new(db: Arc<DbClient>, public_url: String) -> Result<Self, reqwest::Error> {
+        let http = reqwest::Client::builder()
+            .user_agent(format!(
+                "stellwerk/{} (+{public_url})",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .timeout(REQUEST_TIMEOUT)
+            .redirect(Policy::custom(verification::follow_global_redirect))
+            .dns_resolver(Arc::new(verification::GlobalResolver))
+            .build()?;
+
+        Ok(Self {
+            db,
+            http,
+            public_url,
+        })
+    }
+
+    /// The key pair of a local user, which is generated on first use.
+    pub async fn user_key_pair(&self, user_id: Id<UserMarker>) -> Result<KeyPair, FederationError> {
+        if let Some(key_pair) = self.db.fetch_actor_key_pair(user_id).await? {
+            return Ok(key_pair);
+        }
+
+        let key_pair = generate_key_pair().await?;
+        Ok(self.db.insert_actor_key_pair(user_id, &key_pair).await?)
+    }
+
+    /// The key pair of the instance actor, which is generated on first use.
+    pub async fn instance_key_pair(&self) -> Result<KeyPair, FederationError> {
+        if let Some(key_pair) = self.db.fetch_instance_key_pair().await? {
+            return Ok(key_pair);
+        }
+
+        let key_pair = generate_key_pair().await?;
+        Ok(self.db.insert_instance_key_pair(&key_pair).await?)
+    }
+
+    /// Verifies the HTTP signature of an incoming request and returns the id of the signing actor.
+    ///
+    /// If there is a `body`, the signature must also cover a `Digest` header matching it.
+    pub async fn verify(
+        &self,
+        method: &Method,
+        uri: &Uri,
+        headers: &HeaderMap,
+        body: Option<&[u8]>,
+    ) -> Result<String, FederationError> {
+        let signature: Signature = headers
+            .get("signature")
+            .and_then(|value| value.to_str().ok())
+            .ok_or(SignatureError::MalformedHeader)?
+            .parse()?;
+        signature.require_headers(&[REQUEST_TARGET, "host", "date"])?;
+        check_date(headers)?;
+        if let Some(body) = body {
+            signature.require_headers(&["digest"])?;
+            check_digest(headers, body)?;
+        }
+
+        let path_and_query = uri.path_and_query().map_or("/", |path| path.as_str());
+        let verify = |public_key: &PublicKey| {
+            signature.verify(
+                &public_key.public_key_pem,
+                method.as_str(),
+                path_and_query,
+                |name| header_value(headers, name),
+            )
+        };
+
+        let (public_key, cached) = self.public_key(&signature.key_id).await?;
+        match verify(&public_key) {
+            Ok(()) => Ok(public_key.owner),
+            // The remote actor might have rotated their key since we cached it.
+            Err(SignatureError::Mismatch) if cached => {
+                let public_key = self.fetch_remote_key(&signature.key_id).await?;
+                verify(&public_key)?;
+                Ok(public_key.owner)
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Returns the key and whether it came from the cache of remote keys.
+    async fn public_key(&self, key_id: &str) -> Result<(PublicKey, bool), FederationError> {
+        if let Some(public_key) = self.local_public_key(key_id).await? {
+            return Ok((public_key, false));
+        }
+
+        if let Some(public_key) = self.db.fetch_remote_actor_key(key_id).await? {
+            return Ok((public_key, true));
+        }
+
+        Ok((self.fetch_remote_key(key_id).await?, false))
+    }
+
+    async fn local_public_key(&self, key_id: &str) -> Result<Option<PublicKey>, FederationError> {
+        let Some(actor) = key_id
+            .strip_prefix(&self.public_url)
+            .and_then(|path| path.strip_suffix("#main-key"))
+        else {
+            return Ok(None);
+        };
+
+        let (actor, public_key_pem) = if actor == "/actor" {
+            (
+                instance_actor_id(&self.public_url),
+                self.instance_key_pair().await?.public_key_pem,
+            )
+        } else {
+            let user_id = actor
+                .strip_prefix("/users/")
+                .and_then(parse_id)
+                .ok_or_else(|| FederationError::UnknownKey(key_id.to_owned()))?;
+            let key_pair = self
+                .db
+                .fetch_actor_key_pair(user_id)
+                .await?
+                .ok_or_else(|| FederationError::UnknownKey(key_id.to_owned()))?;
+
+            (actor_id(&self.public_url, user_id), key_pair.public_key_pem)
+        };
+
+        Ok(Some(PublicKey {
+            id: key_id.to_owned(),
+            owner: actor,
+            public_key_pem,
+        }))
+    }
+
+    /// Dereferences `key_id` and caches the key.
+    async fn fetch_remote_key(&self, key_id: &str) -> Result<PublicKey, FederationError> {
+        debug!(key_id, "Fetching remote key");
+
+        let public_key = self
+            .fetch::<KeyDocument>(key_id)
+            .await
+            .map_err(|error| match error {
+                FetchError::InvalidUrl => FederationError::UnknownKey(key_id.to_owned()),
+                FetchError::Federation(error) => error,
+                error => {
+                    info!(key_id, %error, "Error fetching remote key");
+                    FederationError::KeyFetch(key_id.to_owned())
+                }
+            })?
+            .into_public_key();
+        if public_key.id != key_id {
+            return Err(FederationError::UnknownKey(key_id.to_owned()));
+        }
+
+        self.db
+            .upsert_remote_actor_key(&public_key, UtcDateTime::now())
+            .await?;
+
+        Ok(public_key)
+    }
+
+    /// The local user standing in for a remote actor, fetching the actor if it is not known yet.
+    pub async fn remote_actor_user(&self, actor_id: &str) -> Result<User, FederationError> {
+        if let Some(user) = self.db.fetch_remote_actor_user(actor_id).await? {
+            return Ok(user);
+        }
+
+        Ok(self.fetch_remote_actor(actor_id).await?.0)
+    }
+
+    /// Dereferences a remote actor and updates the local user standing in for it.
+    pub async fn fetch_remote_actor(
+        &self,
+        actor_id: &str,
+    ) -> Result<(User, RemoteActor), FederationError> {
+        debug!(actor_id, "Fetching remote actor");
+        let invalid_actor = || FederationError::InvalidActor(actor_id.to_owned());
+
+        let actor = self
+            .fetch::<RemoteActor>(actor_id)
+            .await
+            .map_err(|error| match error {
+                FetchError::InvalidUrl | FetchError::TooLarge | FetchError::InvalidJson(_) => {
+                    invalid_actor()
+                }
+                FetchError::Request(error) => FederationError::ActorFetch {
+                    actor_id: actor_id.to_owned(),
+                    error,
+                },
+                FetchError::Federation(error) => error,
+            })?;
+        if actor.id != actor_id || actor.public_key.owner != actor_id {
+            return Err(invalid_actor());
+        }
+        let handle = actor
+            .handle()
+            .and_then(|handle| UserHandle::new(handle).ok())
+            .ok_or_else(invalid_actor)?;
+
+        let now = UtcDateTime::now();
+        self.db
+            .upsert_remote_actor_key(&actor.public_key, now)
+            .await?;
+        let user = self.db.upsert_remote_actor(&actor, &handle).await?;
+
+        Ok((user, actor))
+    }
+
+    /// Dereferences an ActivityPub object with a request signed by the instance actor,
+    /// since servers may refuse unsigned requests.
+    async fn fetch<T: DeserializeOwned>(&self, id: &str) -> Result<T, FetchError> {
+        let url = reqwest::Url::parse(id)
+            .ok()
+            .filter(verification::is_global)
+            .ok_or(FetchError::InvalidUrl)?;
+        let mut request = self
+            .http
+            .get(url)
+            .header(header::ACCEPT, ACTIVITY_JSON)
+            .build()?;
+        let key_pair = self.instance_key_pair().await?;
+        sign_request(
+            &mut request,
+            activitypub::key_id(&instance_actor_id(&self.public_url)),
+            &key_pair,
+        )
+        .map_err(FederationError::from)?;
+
+        let mut response = self
+            .http
+            .execute(request)
+            .await
+            .and_then(reqwest::Response::error_for_status)?;
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if body.len() + chunk.len() > OBJECT_MAX_LEN {
+                return Err(FetchError::TooLarge);
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        serde_json::from_slice(&body).map_err(FetchError::InvalidJson)
+    }
+
+    /// Queues the creation of a local post for delivery to the remote followers of its author.
+    pub async fn publish_post(&self, post: &Post) -> Result<(), FederationError> {
+        let inboxes = self
+            .db
+            .fetch_remote_follower_inboxes(post.author.id)
+            .await?;
+        let activity = Activity::create(Note::for_post(post, &self.public_url));
+
+        self.enqueue(Some(post.author.id), &inboxes, &activity)
+            .await
+    }
+
+    /// Queues `activity` for delivery to `inboxes`, signed by `sender`,
+    /// or by the instance actor if that is [`None`].
+    async fn enqueue(
+        &self,
+        sender: Option<Id<UserMarker>>,
+        inboxes: &[String],
+        activity: &Activity,
+    ) -> Result<(), FederationError> {
+        if inboxes.is_empty() {
+            return Ok(());
+        }
+
+        let activity = serde_json::to_string(activity).map_err(FederationError::Serialize)?;
+        let jobs: Vec<_> = inboxes
+            .iter()
+            .map(|inbox| JobPayload::FederationDelivery {
+                sender,
+                inbox: inbox.clone(),
+                activity: activity.clone(),
+            })
+            .collect();
+        self.db.enqueue_jobs(&jobs, UtcDateTime::now()).await?;
+
+        Ok(())
+    }
+
+    /// The local object with the id `object_id`, which is `{public_url}{prefix}{id}`.
+    fn local_id<M>(&self, object_id: &str, prefix: &str) -> Option<Id<M>> {
+        object_id
+            .strip_prefix(&self.public_url)
+            .and_then(|path| path.strip_prefix(prefix))
+            .and_then(parse_id)
+    }
+}
+
+#[derive(Debug, Error)]
+enum FetchError {
+    #[error("The id is not an HTTP URL at a global address")]
+    InvalidUrl,
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+    #[error("The object is larger than {OBJECT_MAX_LEN} bytes")]
+    TooLarge,
+    #[error("The object is invalid: {0}")]
+    InvalidJson(serde_json::Error),
+    #[error(transparent)]
+    Federation(#[from] FederationError),
+}
+
+/// Adds `Host`, `Date`, and for requests with a body `Digest` headers to an outgoing request
+/// and signs them with `key_pair`.
+pub fn sign_request(
+    request: &mut reqwest::Request,
+    key_id: String,
+    key_pair: &KeyPair,
+) -> Result<(), SignatureError> {
+    let url = request.url().clone();
+    let host = match (url.host_str(), url.port()) {
+        (Some(host), Some(port)) => format!("{host}:{port}"),
+        (Some(host), None) => host.to_owned(),
+        (None, _) => String::new(),
+    };
+    let path_and_query = match url.query() {
+        Some(query) => format!("{}?{query}", url.path()),
+        None => url.path().to_owned(),
+    };
+    let body_digest = request.body().and_then(reqwest::Body::as_bytes).map(digest);
+
+    let headers = request.headers_mut();
+    headers.insert(
+        header::HOST,
+        HeaderValue::try_from(host).map_err(|_| SignatureError::MalformedHeader)?,
+    );
+    headers.typed_insert(headers::Date::from(SystemTime::now()));
+    let mut signed_headers = [REQUEST_TARGET, "host", "date"].map(str::to_owned).to_vec();
+    if let Some(body_digest) = body_digest {
+        headers.insert(
+            "digest",
+            HeaderValue::try_from(body_digest).map_err(|_| SignatureError::MalformedHeader)?,
+        );
+        signed_headers.push("digest".to_owned());
+    }
+
+    let method = request.method().clone();
+    let signature = Signature::sign(
+        key_id,
+        &key_pair.private_key_pem,
+        signed_headers,
+        method.as_str(),
+        &path_and_query,
+        |name| header_value(request.headers(), name),
+    )?;
+    request.headers_mut().insert(
+        "signature",
+        HeaderValue::try_from(signature.to_string())
+            .map_err(|_| SignatureError::MalformedHeader)?,
+    );
+
+    Ok(())
+}
+
+async fn generate_key_pair() -> Result<KeyPair, FederationError> {
+    Ok(tokio::task::spawn_blocking(KeyPair::generate).await??)
+}
+
+fn check_date(headers: &HeaderMap) -> Result<(), FederationError> {
+    let date = SystemTime::from(
+        headers
+            .typed_get::<headers::Date>()
+            .ok_or(FederationError::InvalidDate)?,
+    );
+    let now = SystemTime::now();
+
+    let too_old = now
+        .duration_since(date)
+        .is_ok_and(|age| age > SIGNATURE_MAX_AGE);
+    let too_new = date
+        .duration_since(now)
+        .is_ok_and(|ahead| ahead > CLOCK_SKEW);
+    if too_old || too_new {
+        return Err(FederationError::InvalidDate);
+    }
+
+    Ok(())
+}
+
+/// Whether any of the digests in the `Digest` header matches `body`.
+fn check_digest(headers: &HeaderMap, body: &[u8]) -> Result<(), FederationError> {
+    let expected = digest(body);
+    let matches = header_value(headers, "digest")
+        .is_some_and(|digests| digests.split(',').any(|digest| digest.trim() == expected));
+
+    if matches {
+        Ok(())
+    } else {
+        Err(FederationError::DigestMismatch)
+    }
+}
+
+fn parse_id<M>(id: &str) -> Option<Id<M>> {
+    id.parse::<u64>().ok().map(Id::from)
+}
+
+/// All values of the header, joined as required for the signing string.
+fn header_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    let values: Vec<_> = headers
+        .get_all(name)
+        .iter()
+        .map(|value| value.to_str().map(str::trim))
+        .collect::<Result<_, _>>()
+        .ok()?;
+
+    (!values.is_empty()).then(|| values.join(", "))
+}
@@ -0,0 +1,185 @@
+//! A gRPC server for internal services, on its own port.
+//!
+//! It serves lookups of users, posts and auth tokens, defined in
+//! `proto/stellwerk/internal/v1/internal.proto`. There is no authentication,
+//! so the port must only be reachable from the internal network.
+
+use crate::server::{ServerError, auth};
+use proto::{
+    AuthenticateRequest, Authentication, GetPostRequest, GetUserRequest, Post, User, UserProfile,
+    UserRole, UserStats,
+    internal_server::{Internal, InternalServer},
+};
+use std::sync::Arc;
+use stellwerk_common::model::{post, user};
+use stellwerk_db::store::Store;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{
+    Code, Request, Response, Status,
+    metadata::MetadataValue,
+    transport::{self, Server},
+};
+use tracing::{debug_span, error};
+
+#[allow(clippy::pedantic)]
+pub mod proto {
+    tonic::include_proto!("stellwerk.internal.v1");
+}
+
+/// The metadata key of the [`ErrorCode`](stellwerk_common::model::problem::ErrorCode)
+/// of failed calls, like the `code` of problem details in the HTTP API.
+const ERROR_CODE_KEY: &str = "stellwerk-error-code";
+
+#[derive(Clone, Debug)]
+pub struct InternalService {
+    store: Arc<dyn Store>,
+}
+
+impl InternalService {
+    #[must_use]
+    pub fn new(store: Arc<dyn Store>) -> Self {
+        Self { store }
+    }
+}
+
+#[tonic::async_trait]
+impl Internal for InternalService {
+    async fn get_user(
+        &self,
+        request: Request<GetUserRequest>,
+    ) -> Result<Response<UserProfile>, Status> {
+        let id = request.into_inner().id.into();
+        let profile = self
+            .store
+            .fetch_user_profile(id)
+            .await
+            .map_err(ServerError::from)?
+            .ok_or(ServerError::UserByIdNotFound(id))?;
+
+        Ok(Response::new(profile.into()))
+    }
+
+    async fn get_post(&self, request: Request<GetPostRequest>) -> Result<Response<Post>, Status> {
+        let id = request.into_inner().id.into();
+        let post = self
+            .store
+            .fetch_post(id)
+            .await
+            .map_err(ServerError::from)?
+            .ok_or(ServerError::PostByIdNotFound(id))?;
+
+        Ok(Response::new(post.into()))
+    }
+
+    async fn authenticate(
+        &self,
+        request: Request<AuthenticateRequest>,
+    ) -> Result<Response<Authentication>, Status> {
+        // Internal services are not assigned a tenant, so tokens of every tenant are valid.
+        let user_id = auth::authenticate(&*self.store, &request.into_inner().token, None)
+            .await?
+            .user;
+        // A valid token belongs to an existing user.
+        let role = self
+            .store
+            .fetch_user_role(user_id)
+            .await
+            .map_err(ServerError::from)?
+            .ok_or(ServerError::UserByIdNotFound(user_id))?;
+
+        Ok(Response::new(Authentication {
+            user_id: user_id.into(),
+            role: UserRole::from(role).into(),
+        }))
+    }
+}
+
+/// Serves the [`InternalService`] until `shutdown` completes.
+pub async fn serve(
+    listener: TcpListener,
+    service: InternalService,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), transport::Error> {
+    Server::builder()
+        .trace_fn(|request| debug_span!("grpc", path = %request.uri().path()))
+        .add_service(InternalServer::new(service))
+        .serve_with_incoming_shutdown(TcpListenerStream::new(listener), shutdown)
+        .await
+}
+
+impl From<ServerError> for Status {
+    fn from(error: ServerError) -> Self {
+        let status = error.status();
+
+        error!(%error, %status, "Replying with gRPC error");
+
+        let code = match status.as_u16() {
+            400 | 422 => Code::InvalidArgument,
+            401 => Code::Unauthenticated,
+            403 => Code::PermissionDenied,
+            404 => Code::NotFound,
+            503 => Code::Unavailable,
+            _ => Code::Internal,
+        };
+        // Like problem details, server errors do not leak internals.
+        let message = if status.is_server_error() {
+            status.canonical_reason().unwrap_or_default().to_owned()
+        } else {
+            error.to_string()
+        };
+
+        let mut grpc_status = Status::new(code, message);
+        grpc_status.metadata_mut().insert(
+            ERROR_CODE_KEY,
+            MetadataValue::from_static(error.code().as_str()),
+        );
+        grpc_status
+    }
+}
+
+impl From<user::User> for User {
+    fn from(user: user::User) -> Self {
+        Self {
+            id: user.id.into(),
+            handle: user.handle.into_inner(),
+            verified: user.verified,
+        }
+    }
+}
+
+impl From<user::UserProfile> for UserProfile {
+    fn from(profile: user::UserProfile) -> Self {
+        Self {
+            user: Some(profile.user.into()),
+            stats: Some(UserStats {
+                post_count: profile.stats.post_count,
+                follower_count: profile.stats.follower_count,
+                following_count: profile.stats.following_count,
+            }),
+            website: profile.website.map(user::ProfileWebsite::into_inner),
+        }
+    }
+}
+
+impl From<user::UserRole> for UserRole {
+    fn from(role: user::UserRole) -> Self {
+        match role {
+            user::UserRole::User => UserRole::User,
+            user::UserRole::Moderator => UserRole::Moderator,
+            user::UserRole::Admin => UserRole::Admin,
+        }
+    }
+}
+
+impl From<post::Post> for Post {
+    fn from(post: post::Post) -> Self {
+        Self {
+            id: post.id.into(),
+            author: Some(post.author.into()),
+            content: post.content.into_inner(),
+            content_html: post.content_html,
+            pinned: post.pinned,
+        }
+    }
+}
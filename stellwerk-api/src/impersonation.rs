@@ -0,0 +1,57 @@
+//! Admins signing in as a user for support, see
+//! [`Impersonation`](stellwerk_common::model::admin::Impersonation).
+//!
+//! Impersonation tokens expire after [`IMPERSONATION_LIFETIME`], and every request made with
+//! one is logged. When the token expires, a queued [job](crate::jobs) notifies the user that an
+//! admin was signed in to their account, even if the impersonation was ended early.
+
+use crate::jobs::{JobError, JobHandler, QueueSettings};
+use std::{sync::Arc, time::Duration};
+use stellwerk_common::model::{
+    job::{Job, JobKind, JobPayload},
+    notification::{CreateNotification, NotificationKind},
+};
+use stellwerk_db::client::DbClient;
+use time::UtcDateTime;
+
+pub const IMPERSONATION_LIFETIME: time::Duration = time::Duration::minutes(30);
+
+#[derive(Clone, Debug)]
+pub struct ImpersonationNotifier {
+    db: Arc<DbClient>,
+}
+
+impl ImpersonationNotifier {
+    pub fn new(db: Arc<DbClient>) -> Self {
+        Self { db }
+    }
+}
+
+impl JobHandler for ImpersonationNotifier {
+    const SETTINGS: QueueSettings = QueueSettings {
+        kind: JobKind::ImpersonationNotice,
+        batch_size: 20,
+        visibility_timeout: Duration::from_mins(1),
+        max_attempts: 10,
+        retry_base_delay: Duration::from_secs(30),
+        retry_max_delay: Duration::from_hours(1),
+    };
+
+    async fn run(&self, job: &Job) -> Result<Option<UtcDateTime>, JobError> {
+        let JobPayload::ImpersonationNotice { user } = job.payload else {
+            return Err(JobError::unexpected_payload(&job.payload));
+        };
+
+        // The admin is not named, so the actor is the user themselves.
+        self.db
+            .create_notification(&CreateNotification {
+                user,
+                kind: NotificationKind::Impersonation,
+                actor: user,
+                post: None,
+            })
+            .await?;
+
+        Ok(None)
+    }
+}
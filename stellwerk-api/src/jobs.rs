@@ -0,0 +1,170 @@
+//! Running the jobs queued in the database, see [`stellwerk_common::model::job`].
+//!
+//! Every kind of job has its own loop, which claims due jobs in batches and runs them
+//! concurrently. Failed jobs are retried with exponential backoff, and dead-lettered once they
+//! failed permanently or too often, so that administrators can inspect and retry them.
+
+use std::{fmt::Display, sync::Arc, time::Duration};
+use stellwerk_common::model::job::{Job, JobKind, JobPayload};
+use stellwerk_db::client::{DbClient, DbError};
+use thiserror::Error;
+use time::UtcDateTime;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, warn};
+
+const JOB_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How the jobs of one kind are claimed and retried.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct QueueSettings {
+    pub kind: JobKind,
+    pub batch_size: u32,
+    /// Claimed jobs are not claimed again for this long, in case this process dies.
+    pub visibility_timeout: Duration,
+    /// After this many failed attempts, a job is dead-lettered.
+    pub max_attempts: u32,
+    pub retry_base_delay: Duration,
+    pub retry_max_delay: Duration,
+}
+
+#[derive(Debug, Error)]
+#[error("{message}")]
+pub struct JobError {
+    message: String,
+    /// Whether retrying cannot help.
+    permanent: bool,
+}
+
+impl JobError {
+    pub fn transient(error: impl Display) -> Self {
+        Self {
+            message: error.to_string(),
+            permanent: false,
+        }
+    }
+
+    pub fn permanent(error: impl Display) -> Self {
+        Self {
+            message: error.to_string(),
+            permanent: true,
+        }
+    }
+
+    /// For a payload of another kind than the queue runs, which cannot happen unless the
+    /// database was edited by hand.
+    pub fn unexpected_payload(payload: &JobPayload) -> Self {
+        Self::permanent(format!("Unexpected {} job", payload.kind()))
+    }
+}
+
+impl From<DbError> for JobError {
+    fn from(error: DbError) -> Self {
+        Self::transient(error)
+    }
+}
+
+/// Runs the jobs of one kind.
+pub trait JobHandler: Send + Sync + 'static {
+    const SETTINGS: QueueSettings;
+
+    /// Returns when to run the job again if it is recurring or postponed, or `None` if it is done.
+    fn run(&self, job: &Job) -> impl Future<Output = Result<Option<UtcDateTime>, JobError>> + Send;
+}
+
+pub async fn job_loop<H: JobHandler>(
+    db: Arc<DbClient>,
+    handler: Arc<H>,
+    cancellation: CancellationToken,
+) {
+    let kind = H::SETTINGS.kind;
+    loop {
+        match run_due(&db, &handler).await {
+            Ok(0) => {}
+            Ok(attempted) => debug!(%kind, "Attempted {attempted} jobs"),
+            Err(error) => error!(%error, %kind, "Error trying to run jobs"),
+        }
+        if cancellation
+            .run_until_cancelled(tokio::time::sleep(JOB_CHECK_INTERVAL))
+            .await
+            .is_none()
+        {
+            return;
+        }
+    }
+}
+
+/// Returns the number of attempted jobs.
+async fn run_due<H: JobHandler>(db: &Arc<DbClient>, handler: &Arc<H>) -> Result<usize, DbError> {
+    let settings = H::SETTINGS;
+    let now = UtcDateTime::now();
+    let mut attempted = 0;
+
+    // Every claimed job is hidden until after `now`, so this terminates.
+    loop {
+        let jobs = db
+            .claim_jobs(
+                settings.kind,
+                now,
+                now + settings.visibility_timeout,
+                settings.batch_size,
+            )
+            .await?;
+        if jobs.is_empty() {
+            return Ok(attempted);
+        }
+
+        attempted += jobs.len();
+        let mut attempts: JoinSet<_> = jobs
+            .into_iter()
+            .map(|job| {
+                let db = db.clone();
+                let handler = handler.clone();
+                async move { attempt_job(&db, &*handler, job).await }
+            })
+            .collect();
+
+        while let Some(result) = attempts.join_next().await {
+            match result {
+                Ok(result) => result?,
+                // The job becomes visible again eventually, so it will be retried.
+                Err(error) => error!(%error, kind = %settings.kind, "Job task panicked"),
+            }
+        }
+    }
+}
+
+/// Runs a job and records the outcome.
+async fn attempt_job<H: JobHandler>(db: &DbClient, handler: &H, job: Job) -> Result<(), DbError> {
+    let settings = H::SETTINGS;
+    let error = match handler.run(&job).await {
+        Ok(None) => return db.finish_job(job.id).await,
+        Ok(Some(run_at)) => return db.reschedule_job(job.id, run_at).await,
+        Err(error) => error,
+    };
+
+    let attempts = job.attempts + 1;
+    let now = UtcDateTime::now();
+    if error.permanent || attempts >= settings.max_attempts {
+        warn!(%error, id = %job.id, kind = %settings.kind, attempts, "Giving up on job");
+        db.dead_letter_job(job.id, &error.message, now).await
+    } else {
+        debug!(%error, id = %job.id, kind = %settings.kind, attempts, "Job failed");
+        db.retry_job(
+            job.id,
+            &error.message,
+            now + retry_delay(&settings, job.attempts),
+        )
+        .await
+    }
+}
+
+/// Exponential backoff after `attempts` previous failures.
+fn retry_delay(settings: &QueueSettings, attempts: u32) -> Duration {
+    2_u32
+        .checked_pow(attempts)
+        .and_then(|factor| settings.retry_base_delay.checked_mul(factor))
+        .map_or(settings.retry_max_delay, |delay| {
+            delay.min(settings.retry_max_delay)
+        })
+}
@@ -1,92 +1,797 @@
 #![feature(duration_constructors)]
+#![feature(ip)]
+#![feature(nonpoison_mutex)]
+#![feature(sync_nonpoison)]
 
+mod atproto;
+mod cdn;
+mod config;
+mod digest;
+mod federation;
+mod grpc;
+mod impersonation;
+mod jobs;
+mod mail;
+mod oidc;
+mod search;
 mod server;
+mod shutdown;
+mod tls;
+mod verification;
+mod webhooks;
 
-use crate::server::ServerState;
-use serde::Deserialize;
+use crate::{
+    atproto::AtprotoBridge,
+    cdn::{CachePurger, Cdn, CdnPurgeQueue, Cloudflare, Fastly},
+    config::{
+        AnalyticsConfig, CdnConfig, CdnProvider, Config, ConfigError, ContentFilterConfig,
+        CorsConfig, DatabaseCacheConfig, DatabaseConfig, DatabasePoolConfig, GrpcConfig,
+        InstanceConfig, LogFormat, MailConfig, OidcConfig, RateLimitsConfig, SearchBackend,
+        SearchConfig, ServerConfig, TenantConfig,
+    },
+    digest::DigestSender,
+    federation::Federation,
+    grpc::InternalService,
+    impersonation::ImpersonationNotifier,
+    mail::{LogMailer, MailQueue, Mailer, SmtpMailer},
+    oidc::{GitHub, Oidc, OpenIdConnect},
+    search::{Meilisearch, OpenSearch, PostgresSearch, SearchIndex, SearchIndexer},
+    server::{
+        ServerState,
+        analytics::{self, Analytics},
+        body_limit::BodyLimits,
+        client_ip,
+        content_filter::{ContentFilters, LinkDomainFilter, PatternFilter, WebhookFilter},
+        events::{self, EventHub},
+        feature_flags::{self, FeatureFlags},
+        i18n, logging,
+        quota::Quotas,
+        rate_limit::{self, RateLimiter},
+        read_only::ReadOnly,
+        request_id,
+        response_cache::ResponseCache,
+        route_group::RouteGroup,
+        routes::sitemap::Sitemaps,
+        spam::SpamGuard,
+        tenant::{self, Tenant, Tenants},
+        views::{self, PostViews},
+    },
+    shutdown::{BackgroundTasks, Shutdown},
+    verification::LinkVerifier,
+    webhooks::WebhookDispatcher,
+};
+use axum::{
+    Router,
+    http::{HeaderName, HeaderValue, Method, header},
+    middleware,
+};
+use axum_server::tls_rustls::RustlsConfig;
 use std::{
-    net::{IpAddr, SocketAddr},
-    sync::Arc,
+    collections::HashMap, net::SocketAddr, process::ExitCode, str::FromStr, sync::Arc,
+    time::Duration,
+};
+use stellwerk_common::{
+    model::{
+        instance::{InstanceFeatures, InstanceInfo, InstanceLimits},
+        oidc::OidcProvider,
+        post::POST_CONTENT_MAX_LEN,
+        tenant::TenantId,
+    },
+    util::PositiveDuration,
+};
+use stellwerk_db::{
+    breaker::BreakerSettings,
+    cache::{CacheBackend, CacheSettings, DbCache},
+    client::{DbClient, DbError, PoolSettings, RetryPolicy, WorkerLease},
+    store::Store,
 };
-use stellwerk_common::snowflake::{ProcessId, WorkerId};
-use stellwerk_db::client::{DbClient, DbError};
 use thiserror::Error;
-use tokio::{signal, signal::unix::SignalKind, task::JoinError};
+use time::UtcDateTime;
+use tokio::{net::TcpListener, signal, signal::unix::SignalKind, task::JoinError};
 use tokio_util::sync::CancellationToken;
-use tower_http::trace::TraceLayer;
-use tracing::{debug, error, info};
+use tower_http::{
+    cors::{AllowOrigin, Any, CorsLayer},
+    trace::TraceLayer,
+};
+use tracing::{debug, error, info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+/// How long a leased worker and process ID stays reserved without being renewed.
+const WORKER_LEASE_DURATION: Duration = Duration::from_mins(1);
+const WORKER_LEASE_RENEWAL_INTERVAL: Duration = Duration::from_secs(15);
+
 #[derive(Debug, Error)]
 enum InitError {
     #[error("Error parsing .env file: {0}")]
     Dotenv(#[from] dotenvy::Error),
-    #[error("Error parsing environment: {0}")]
-    Envy(#[from] envy::Error),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error("Error loading the TLS certificate: {0}")]
+    Tls(std::io::Error),
     #[error("Error binding tcp listener: {0}")]
     TcpBind(std::io::Error),
     #[error("Error serving server: {0}")]
     TcpServe(std::io::Error),
+    #[error("Error serving gRPC server: {0}")]
+    GrpcServe(tonic::transport::Error),
     #[error("Error installing shutdown signal handler: {0}")]
     SignalHandler(std::io::Error),
+    #[error(
+        "instance.post_content_max_len must be between 1 and {POST_CONTENT_MAX_LEN}, but was {0}"
+    )]
+    PostContentMaxLen(usize),
+    #[error(
+        "tenants.post_content_max_len of tenant {0} must be between 1 and {POST_CONTENT_MAX_LEN}, but was {1}"
+    )]
+    TenantPostContentMaxLen(TenantId, usize),
+    #[error("Tenant {0} is configured twice, or is the default tenant")]
+    DuplicateTenant(TenantId),
+    #[error("Tenant {0} has no hosts")]
+    TenantWithoutHosts(TenantId),
+    #[error("Host {0} is configured for more than one tenant")]
+    DuplicateTenantHost(String),
+    #[error(
+        "database.pool.max_connections must be positive and at least database.pool.min_connections, but was {max} with {min} minimum connections"
+    )]
+    DatabasePoolConnections { min: u32, max: u32 },
     #[error("Database connection and migration failed: {0}")]
     DatabaseInitialization(DbError),
+    #[error("database.worker_id and database.process_id must either both be given or neither")]
+    PartialWorkerId,
+    #[error("All worker and process IDs are leased by other servers")]
+    NoFreeWorkerId,
+    #[cfg(not(feature = "redis"))]
+    #[error("database.cache.redis_url needs stellwerk-api to be built with the redis feature")]
+    RedisUnsupported,
+    #[error("Connecting to the database cache failed: {0}")]
+    DatabaseCache(DbError),
+    #[cfg(not(feature = "redis"))]
+    #[error("limits.rate.redis_url needs stellwerk-api to be built with the redis feature")]
+    RateLimitRedisUnsupported,
+    #[cfg(feature = "redis")]
+    #[error("Connecting to Redis for rate limits failed: {0}")]
+    RateLimitRedis(redis::RedisError),
+    #[error("Error building the federation HTTP client: {0}")]
+    HttpClient(reqwest::Error),
+    #[error("Error building the AT Protocol bridge HTTP client: {0}")]
+    BridgeHttpClient(reqwest::Error),
+    #[error("content_filter.blocked_patterns contains an invalid regular expression: {0}")]
+    BlockedPattern(regex::Error),
+    #[error("Error building the content filter webhook HTTP client: {0}")]
+    WebhookHttpClient(reqwest::Error),
+    #[error("Error building the webhook delivery HTTP client: {0}")]
+    WebhookDeliveryHttpClient(reqwest::Error),
+    #[error("Error building the search engine HTTP client: {0}")]
+    SearchHttpClient(reqwest::Error),
+    #[error("cdn.zone_id must be given for Cloudflare")]
+    CdnZoneIdMissing,
+    #[error("Error building the CDN HTTP client: {0}")]
+    CdnHttpClient(reqwest::Error),
+    #[error("Error connecting to the StatsD server: {0}")]
+    Statsd(std::io::Error),
+    #[error("mail.from must be given together with mail.smtp_url")]
+    MailFromMissing,
+    #[error("mail.from is not a valid mailbox: {0}")]
+    MailFrom(lettre::address::AddressError),
+    #[error("mail.smtp_url is invalid: {0}")]
+    SmtpUrl(lettre::transport::smtp::Error),
+    #[error("Error building the identity provider HTTP client: {0}")]
+    OidcHttpClient(reqwest::Error),
+    #[error("Error building the link verification HTTP client: {0}")]
+    VerificationHttpClient(reqwest::Error),
+    #[error("oidc.token_lifetime_days must be positive")]
+    OidcTokenLifetime,
+    #[error("cors.allowed_origins contains an invalid origin: {0}")]
+    CorsOrigin(String),
+    #[error("cors.allowed_headers contains an invalid header name: {0}")]
+    CorsHeader(String),
+    #[error("cors.allow_credentials cannot be combined with allowing any origin or header")]
+    CorsWildcardCredentials,
+    #[error("Shutdown took longer than the drain timeout of {0} seconds")]
+    DrainTimeout(u64),
     #[error("A background task had issues: {0}")]
     Join(#[from] JoinError),
 }
 
-#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize)]
-struct Env {
-    server_address: IpAddr,
-    server_port: u16,
-    database_url: Box<str>,
-    worker_id: WorkerId,
-    process_id: ProcessId,
-}
-
-fn install_tracing() {
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
-                "stellwerk_api=debug,\
-                stellwerk_common=debug,\
-                stellwerk_db=debug,\
-                tower_http=debug,axum::rejection=trace,sqlx=debug"
-                    .into()
-            }),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+#[cfg_attr(
+    not(feature = "redis"),
+    expect(clippy::unused_async, reason = "Only the Redis store connects")
+)]
+async fn rate_limiter(config: &RateLimitsConfig) -> Result<RateLimiter, InitError> {
+    let policies = [
+        (RouteGroup::Auth, config.auth.0),
+        (RouteGroup::Write, config.write.0),
+        (RouteGroup::Read, config.read.0),
+        (RouteGroup::Media, config.media.0),
+    ]
+    .into_iter()
+    .filter_map(|(group, policy)| Some((group, policy?)))
+    .collect::<HashMap<_, _>>();
+
+    match &config.redis_url {
+        #[cfg(feature = "redis")]
+        Some(url) => RateLimiter::with_redis(policies, url)
+            .await
+            .map_err(InitError::RateLimitRedis),
+        #[cfg(not(feature = "redis"))]
+        Some(_) => Err(InitError::RateLimitRedisUnsupported),
+        None => Ok(RateLimiter::new(policies)),
+    }
 }
 
-fn get_env() -> Result<Env, InitError> {
-    if let Err(e) = dotenvy::dotenv() {
-        if e.not_found() {
-            debug!("No .env file found");
-        } else {
-            return Err(e.into());
+fn install_tracing(format: LogFormat) {
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        "stellwerk_api=debug,\
+        stellwerk_common=debug,\
+        stellwerk_db=debug,\
+        tower_http=debug,axum::rejection=trace,sqlx=debug"
+            .into()
+    });
+    let registry = tracing_subscriber::registry().with(filter);
+
+    match format {
+        LogFormat::Pretty => registry.with(tracing_subscriber::fmt::layer()).init(),
+        // The fields of the request span, like the request id, are in `span`.
+        LogFormat::Json => registry
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(false),
+            )
+            .init(),
+    }
+}
+
+/// Returns whether a .env file was found.
+fn load_dotenv() -> Result<bool, InitError> {
+    match dotenvy::dotenv() {
+        Ok(_) => Ok(true),
+        Err(e) if e.not_found() => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn pool_settings(config: &DatabasePoolConfig) -> PoolSettings {
+    let seconds = |seconds| (seconds != 0).then(|| Duration::from_secs(seconds));
+
+    PoolSettings {
+        max_connections: config.max_connections,
+        min_connections: config.min_connections,
+        acquire_timeout: Duration::from_secs(config.acquire_timeout),
+        idle_timeout: seconds(config.idle_timeout),
+        max_lifetime: seconds(config.max_lifetime),
+        test_before_acquire: config.test_before_acquire,
+        statement_timeout: seconds(config.statement_timeout),
+    }
+}
+
+/// Patterns first, since they are cheapest, and the webhook last.
+fn content_filters(config: &ContentFilterConfig) -> Result<ContentFilters, InitError> {
+    let mut filters = ContentFilters::new();
+    if !config.blocked_patterns.is_empty() {
+        filters = filters
+            .with(PatternFilter::new(&config.blocked_patterns).map_err(InitError::BlockedPattern)?);
+    }
+    if !config.denied_link_domains.is_empty() {
+        filters = filters.with(LinkDomainFilter::new(&config.denied_link_domains));
+    }
+    if let Some(url) = &config.webhook_url {
+        filters = filters.with(
+            WebhookFilter::new(url.clone(), Duration::from_secs(config.webhook_timeout))
+                .map_err(InitError::WebhookHttpClient)?,
+        );
+    }
+
+    Ok(filters)
+}
+
+/// The defaults of the [feature flags](server::feature_flags).
+fn instance_features(config: &InstanceConfig) -> InstanceFeatures {
+    InstanceFeatures {
+        public_timeline: config.public_timeline_enabled,
+        mastodon_api: config.mastodon_api_enabled,
+    }
+}
+
+fn instance_info(config: &InstanceConfig, public_url: String) -> InstanceInfo {
+    InstanceInfo {
+        public_url,
+        limits: InstanceLimits {
+            post_content_max_len: config.post_content_max_len,
+            max_pinned_posts: config.max_pinned_posts,
+        },
+        features: instance_features(config),
+        registrations: config.registrations,
+    }
+}
+
+/// The configured tenants, and the default one with the `instance` settings.
+fn tenants(config: &Config, default_info: &InstanceInfo) -> Result<Tenants, InitError> {
+    let post_content_max_len = default_info.limits.post_content_max_len;
+    if !(1..=POST_CONTENT_MAX_LEN).contains(&post_content_max_len) {
+        return Err(InitError::PostContentMaxLen(post_content_max_len));
+    }
+
+    let mut tenants = Tenants::new(Tenant {
+        id: TenantId::default(),
+        info: default_info.clone(),
+    });
+    let mut ids = vec![TenantId::default()];
+    for TenantConfig {
+        id,
+        hosts,
+        public_url,
+        post_content_max_len,
+        max_pinned_posts,
+        registrations,
+    } in &config.tenants
+    {
+        if ids.contains(id) {
+            return Err(InitError::DuplicateTenant(id.clone()));
+        }
+        ids.push(id.clone());
+        let Some(first_host) = hosts.first() else {
+            return Err(InitError::TenantWithoutHosts(id.clone()));
+        };
+        let limits = &default_info.limits;
+        let post_content_max_len = post_content_max_len.unwrap_or(limits.post_content_max_len);
+        if !(1..=POST_CONTENT_MAX_LEN).contains(&post_content_max_len) {
+            return Err(InitError::TenantPostContentMaxLen(
+                id.clone(),
+                post_content_max_len,
+            ));
+        }
+
+        let info = InstanceInfo {
+            public_url: public_url.as_deref().map_or_else(
+                || format!("https://{first_host}"),
+                |url| url.trim_end_matches('/').to_owned(),
+            ),
+            limits: InstanceLimits {
+                post_content_max_len,
+                max_pinned_posts: max_pinned_posts.unwrap_or(limits.max_pinned_posts),
+            },
+            features: default_info.features,
+            registrations: registrations.unwrap_or(default_info.registrations),
+        };
+        let tenant = Tenant {
+            id: id.clone(),
+            info,
+        };
+        tenants = tenants
+            .with_tenant(tenant, hosts)
+            .map_err(InitError::DuplicateTenantHost)?;
+    }
+
+    Ok(tenants)
+}
+
+/// The configured search engine, whose indexes are prepared, or the database.
+async fn search_index(
+    config: Option<&SearchConfig>,
+    db_client: &Arc<DbClient>,
+) -> Result<Arc<dyn SearchIndex>, InitError> {
+    let Some(config) = config else {
+        return Ok(Arc::new(PostgresSearch::new(db_client.clone())));
+    };
+
+    let http = search::http_client().map_err(InitError::SearchHttpClient)?;
+    let index: Arc<dyn SearchIndex> = match config.backend {
+        SearchBackend::Meilisearch => Arc::new(Meilisearch::new(
+            http,
+            &config.url,
+            config.api_key.clone(),
+            &config.index_prefix,
+        )),
+        SearchBackend::Opensearch => Arc::new(OpenSearch::new(
+            http,
+            &config.url,
+            config
+                .username
+                .clone()
+                .map(|username| (username, config.password.clone())),
+            &config.index_prefix,
+        )),
+    };
+    // Searches fail until the engine is reachable, but indexing jobs are retried.
+    if let Err(error) = index.prepare().await {
+        error!(%error, "Error trying to prepare the search indexes");
+    }
+
+    Ok(index)
+}
+
+fn analytics(config: &AnalyticsConfig) -> Result<Analytics, InitError> {
+    let analytics = Analytics::new();
+    match &config.statsd_address {
+        Some(address) => analytics
+            .with_statsd(address, config.statsd_prefix.clone())
+            .map_err(InitError::Statsd),
+        None => Ok(analytics),
+    }
+}
+
+fn cache_purger(config: &CdnConfig) -> Result<Arc<dyn CachePurger>, InitError> {
+    let http = cdn::http_client().map_err(InitError::CdnHttpClient)?;
+
+    Ok(match config.provider {
+        CdnProvider::Cloudflare => Arc::new(Cloudflare::new(
+            http,
+            config.zone_id.clone().ok_or(InitError::CdnZoneIdMissing)?,
+            config.api_token.clone(),
+        )),
+        CdnProvider::Fastly => Arc::new(Fastly::new(http, config.api_token.clone())),
+    })
+}
+
+/// Sends to the configured SMTP server, or logs emails if there is none.
+fn mailer(config: &MailConfig) -> Result<Arc<dyn Mailer>, InitError> {
+    let Some(smtp_url) = &config.smtp_url else {
+        return Ok(Arc::new(LogMailer));
+    };
+
+    let from = config
+        .from
+        .as_ref()
+        .ok_or(InitError::MailFromMissing)?
+        .parse()
+        .map_err(InitError::MailFrom)?;
+    let mailer = SmtpMailer::new(smtp_url, from).map_err(InitError::SmtpUrl)?;
+
+    Ok(Arc::new(mailer))
+}
+
+/// The providers with a configured client.
+fn oidc(config: &OidcConfig, public_url: &str) -> Result<Oidc, InitError> {
+    let redirect_url = config
+        .redirect_url
+        .clone()
+        .unwrap_or_else(|| format!("{public_url}/oidc/callback"));
+    let token_lifetime = PositiveDuration::new(time::Duration::days(
+        config.token_lifetime_days.try_into().unwrap_or(i64::MAX),
+    ))
+    .ok_or(InitError::OidcTokenLifetime)?;
+    let mut oidc = Oidc::new(redirect_url, token_lifetime);
+
+    let http = oidc::http_client().map_err(InitError::OidcHttpClient)?;
+    if let Some(google) = &config.google {
+        oidc = oidc.with_provider(
+            OidcProvider::Google,
+            Arc::new(OpenIdConnect::new(
+                http.clone(),
+                "Google".to_owned(),
+                "https://accounts.google.com",
+                google.client_id.clone(),
+                google.client_secret.clone(),
+            )),
+        );
+    }
+    if let Some(github) = &config.github {
+        oidc = oidc.with_provider(
+            OidcProvider::Github,
+            Arc::new(GitHub::new(
+                http.clone(),
+                github.client_id.clone(),
+                github.client_secret.clone(),
+            )),
+        );
+    }
+    if let Some(custom) = &config.custom {
+        oidc = oidc.with_provider(
+            OidcProvider::Custom,
+            Arc::new(OpenIdConnect::new(
+                http,
+                custom.name.clone(),
+                &custom.issuer_url,
+                custom.client_id.clone(),
+                custom.client_secret.clone(),
+            )),
+        );
+    }
+
+    Ok(oidc)
+}
+
+fn cache_settings(config: &DatabaseCacheConfig) -> CacheSettings {
+    let memory = CacheBackend::Memory {
+        capacity: config.capacity,
+    };
+    #[cfg(feature = "redis")]
+    let backend = config
+        .redis_url
+        .as_ref()
+        .map_or(memory, |url| CacheBackend::Redis { url: url.clone() });
+    #[cfg(not(feature = "redis"))]
+    let backend = memory;
+    let ttl = |seconds| (seconds != 0).then(|| Duration::from_secs(seconds));
+
+    CacheSettings {
+        backend,
+        user_ttl: ttl(config.user_ttl),
+        post_ttl: ttl(config.post_ttl),
+        auth_ttl: ttl(config.auth_ttl),
+    }
+}
+
+/// Leases a worker and process ID pair if none was configured.
+async fn worker_lease(
+    db_client: &DbClient,
+    config: &DatabaseConfig,
+) -> Result<Option<WorkerLease>, InitError> {
+    match (config.worker_id, config.process_id) {
+        (Some(_), Some(_)) => Ok(None),
+        (None, None) => {
+            let now = UtcDateTime::now();
+            let lease = db_client
+                .lease_worker_id(now, now + WORKER_LEASE_DURATION)
+                .await
+                .map_err(InitError::DatabaseInitialization)?
+                .ok_or(InitError::NoFreeWorkerId)?;
+            info!(
+                "Leased worker ID {} and process ID {}",
+                lease.worker_id.get(),
+                lease.process_id.get()
+            );
+            Ok(Some(lease))
         }
+        _ => Err(InitError::PartialWorkerId),
     }
+}
 
-    envy::from_env().map_err(InitError::from)
+/// Applies the options of the client that need no connection.
+fn db_client_options(db_client: DbClient, config: &Config) -> DbClient {
+    let database = &config.database;
+    let retry = database.retry;
+    let db_client = db_client.with_retry_policy(RetryPolicy {
+        max_retries: retry.max_retries,
+        base_delay: Duration::from_millis(retry.base_delay),
+        max_delay: Duration::from_millis(retry.max_delay),
+    });
+    let db_client = match database.circuit_breaker.failure_threshold {
+        0 => db_client,
+        failure_threshold => db_client.with_circuit_breaker(BreakerSettings {
+            failure_threshold,
+            open_duration: Duration::from_secs(database.circuit_breaker.open_duration),
+        }),
+    };
+    let db_client = match database.slow_query_threshold {
+        0 => db_client,
+        threshold => db_client.with_slow_query_threshold(Duration::from_millis(threshold)),
+    };
+    let db_client = if config.instance.archive_expired_tokens {
+        db_client.with_token_archive()
+    } else {
+        db_client
+    };
+    if config.search.is_some() {
+        db_client.with_search_indexing()
+    } else {
+        db_client
+    }
 }
 
-async fn init_state(env: &Env) -> Result<ServerState, InitError> {
-    let db_client = DbClient::connect_and_migrate(&env.database_url, env.worker_id, env.process_id)
+/// Also returns the lease of the worker and process ID, which has to be renewed,
+/// if they were not configured.
+async fn init_state(
+    config: &Config,
+    shutdown: CancellationToken,
+) -> Result<(ServerState, Option<WorkerLease>), InitError> {
+    let instance = &config.instance;
+    let public_url = public_url(&config.server);
+    let instance_info = instance_info(instance, public_url.clone());
+    let tenants = tenants(config, &instance_info)?;
+
+    let database = &config.database;
+    let pool = database.pool;
+    if pool.max_connections == 0 || pool.min_connections > pool.max_connections {
+        return Err(InitError::DatabasePoolConnections {
+            min: pool.min_connections,
+            max: pool.max_connections,
+        });
+    }
+    let db_client = DbClient::connect(
+        &database.url,
+        &database.replica_urls,
+        &pool_settings(&pool),
+        database.worker_id.unwrap_or_default(),
+        database.process_id.unwrap_or_default(),
+    )
+    .await
+    .map_err(InitError::DatabaseInitialization)?;
+    if database.skip_migrations {
+        info!("Skipping database migrations");
+    } else {
+        db_client
+            .migrate()
+            .await
+            .map_err(InitError::DatabaseInitialization)?;
+    }
+    let worker_lease = worker_lease(&db_client, database).await?;
+    let db_client = match worker_lease {
+        Some(lease) => db_client.with_snowflake_ids(lease.worker_id, lease.process_id),
+        None => db_client,
+    };
+    let db_client = db_client_options(db_client, config);
+    let db_client = match &database.cache {
+        Some(cache) => {
+            #[cfg(not(feature = "redis"))]
+            if cache.redis_url.is_some() {
+                return Err(InitError::RedisUnsupported);
+            }
+            let cache = DbCache::new(&cache_settings(cache))
+                .await
+                .map_err(InitError::DatabaseCache)?;
+            db_client.with_cache(cache)
+        }
+        None => db_client,
+    };
+
+    let feature_flags = db_client
+        .fetch_feature_flags()
         .await
         .map_err(InitError::DatabaseInitialization)?;
 
-    Ok(ServerState {
-        db_client: Arc::new(db_client),
-    })
+    let db_client = Arc::new(db_client);
+    let search = search_index(config.search.as_ref(), &db_client).await?;
+    let oidc = oidc(&config.oidc, &public_url)?;
+    let federation =
+        Federation::new(db_client.clone(), public_url).map_err(InitError::HttpClient)?;
+    let cdn = Cdn::new(db_client.clone(), config.cdn.is_some());
+
+    let state = ServerState {
+        store: db_client.clone(),
+        db_client,
+        instance: Arc::new(instance_info),
+        tenants: Arc::new(tenants),
+        events: Arc::new(EventHub::new()),
+        federation: Arc::new(federation),
+        read_only: Arc::new(ReadOnly::new(
+            config.server.read_only,
+            Duration::from_secs(config.server.read_only_retry_after),
+        )),
+        feature_flags: Arc::new(FeatureFlags::new(
+            instance_features(instance),
+            feature_flags,
+        )),
+        response_cache: Arc::new(config.response_cache.map_or_else(
+            ResponseCache::disabled,
+            |cache| {
+                ResponseCache::new(Duration::from_secs(cache.ttl), cache.capacity)
+                    .with_stale_if_unavailable(Duration::from_secs(cache.stale_if_unavailable))
+            },
+        )),
+        content_filters: Arc::new(content_filters(&config.content_filter)?),
+        spam: Arc::new(SpamGuard::new(config.spam)),
+        quotas: Arc::new(Quotas::new(config.limits.quotas)),
+        search,
+        analytics: Arc::new(analytics(&config.analytics)?),
+        cdn: Arc::new(cdn),
+        oidc: Arc::new(oidc),
+        well_known: Arc::new(config.well_known.clone()),
+        sitemaps: Arc::new(if config.sitemap.enabled {
+            Sitemaps::new(Duration::from_secs(config.sitemap.ttl))
+        } else {
+            Sitemaps::disabled()
+        }),
+        link_verifier: Arc::new(LinkVerifier::new().map_err(InitError::VerificationHttpClient)?),
+        post_views: Arc::new(PostViews::new()),
+        shutdown,
+    };
+
+    Ok((state, worker_lease))
+}
+
+/// Returns `None` if no origins are allowed.
+fn cors_layer(config: &CorsConfig) -> Result<Option<CorsLayer>, InitError> {
+    let origins: Vec<_> = config
+        .allowed_origins
+        .iter()
+        .map(|origin| origin.trim())
+        .filter(|origin| !origin.is_empty())
+        .collect();
+    if origins.is_empty() {
+        return Ok(None);
+    }
+
+    let any_origin = origins.contains(&"*");
+    let any_header = config
+        .allowed_headers
+        .iter()
+        .any(|header| header.trim() == "*");
+    if config.allow_credentials && (any_origin || any_header) {
+        return Err(InitError::CorsWildcardCredentials);
+    }
+
+    let allow_origin = if any_origin {
+        AllowOrigin::any()
+    } else {
+        let origins = origins
+            .into_iter()
+            .map(|origin| {
+                HeaderValue::from_str(origin).map_err(|_| InitError::CorsOrigin(origin.to_owned()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        AllowOrigin::list(origins)
+    };
+
+    let mut layer = CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([
+            Method::GET,
+            Method::HEAD,
+            Method::POST,
+            Method::PUT,
+            Method::DELETE,
+        ])
+        .expose_headers([
+            header::ETAG,
+            header::LINK,
+            header::LOCATION,
+            header::RETRY_AFTER,
+            HeaderName::from_static("deprecation"),
+            request_id::X_REQUEST_ID,
+        ])
+        .allow_credentials(config.allow_credentials)
+        .max_age(Duration::from_secs(config.max_age));
+
+    layer = if any_header {
+        layer.allow_headers(Any)
+    } else {
+        let headers = config
+            .allowed_headers
+            .iter()
+            .map(|header| {
+                HeaderName::from_str(header.trim())
+                    .map_err(|_| InitError::CorsHeader(header.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        layer.allow_headers(headers)
+    };
+
+    Ok(Some(layer))
+}
+
+fn public_url(config: &ServerConfig) -> String {
+    config.public_url.as_deref().map_or_else(
+        || {
+            let scheme = if config.tls.is_some() {
+                "https"
+            } else {
+                "http"
+            };
+            format!(
+                "{scheme}://{}",
+                SocketAddr::new(config.address, config.port)
+            )
+        },
+        |public_url| public_url.trim_end_matches('/').to_owned(),
+    )
 }
 
-async fn db_prune_loop(db: Arc<DbClient>, cancellation: CancellationToken) {
+/// Drops expired tokens, purges posts deleted longer than `post_retention` ago, and purges
+/// quota usage of past days.
+async fn db_prune_loop(
+    db: Arc<DbClient>,
+    post_retention: Duration,
+    cancellation: CancellationToken,
+) {
     loop {
         match db.drop_expired_tokens().await {
             Ok(dropped_rows) => debug!("Dropped {dropped_rows} expired tokens"),
             Err(error) => error!(%error, "Error trying to drop expired tokens"),
         }
+        match db
+            .purge_deleted_posts(UtcDateTime::now() - post_retention)
+            .await
+        {
+            Ok(purged_rows) => debug!("Purged {purged_rows} deleted posts"),
+            Err(error) => error!(%error, "Error trying to purge deleted posts"),
+        }
+        match db.purge_quota_usage(UtcDateTime::now().date()).await {
+            Ok(purged_rows) => debug!("Purged {purged_rows} rows of past quota usage"),
+            Err(error) => error!(%error, "Error trying to purge past quota usage"),
+        }
         if cancellation
             .run_until_cancelled(tokio::time::sleep(std::time::Duration::from_days(1)))
             .await
@@ -97,61 +802,390 @@ async fn db_prune_loop(db: Arc<DbClient>, cancellation: CancellationToken) {
     }
 }
 
-fn await_shutdown() -> Result<impl Future<Output = ()>, InitError> {
-    #[cfg(unix)]
-    let mut ctrl_c_signal =
-        signal::unix::signal(SignalKind::interrupt()).map_err(InitError::SignalHandler)?;
+/// Purges tokens archived longer than `retention` ago. Runs even if archiving is disabled,
+/// so that tokens archived before are still purged.
+async fn token_archive_purge_loop(
+    db: Arc<DbClient>,
+    retention: Duration,
+    cancellation: CancellationToken,
+) {
+    loop {
+        match db
+            .purge_archived_tokens(UtcDateTime::now() - retention)
+            .await
+        {
+            Ok(purged_rows) => debug!("Purged {purged_rows} archived tokens"),
+            Err(error) => error!(%error, "Error trying to purge archived tokens"),
+        }
+        if cancellation
+            .run_until_cancelled(tokio::time::sleep(std::time::Duration::from_days(1)))
+            .await
+            .is_none()
+        {
+            return;
+        }
+    }
+}
 
-    #[cfg(not(unix))]
-    let mut ctrl_c_signal = signal::windows::ctrl_c().map_err(InitError::SignalHandler)?;
+/// Renews the lease until cancelled, then releases it. Shuts the server down if the lease expired,
+/// since another server may generate IDs with the same worker and process ID by then.
+async fn worker_lease_loop(
+    db: Arc<DbClient>,
+    lease: WorkerLease,
+    shutdown: Shutdown,
+    cancellation: CancellationToken,
+) {
+    let mut leased_until = UtcDateTime::now() + WORKER_LEASE_DURATION;
+    while cancellation
+        .run_until_cancelled(tokio::time::sleep(WORKER_LEASE_RENEWAL_INTERVAL))
+        .await
+        .is_some()
+    {
+        let now = UtcDateTime::now();
+        match db
+            .renew_worker_lease(&lease, now + WORKER_LEASE_DURATION)
+            .await
+        {
+            Ok(true) => {
+                debug!("Renewed the worker ID lease");
+                leased_until = now + WORKER_LEASE_DURATION;
+            }
+            Ok(false) => {
+                error!("The worker ID lease was taken over by another server, shutting down");
+                shutdown.begin();
+                return;
+            }
+            Err(error) if UtcDateTime::now() >= leased_until => {
+                error!(%error, "Could not renew the worker ID lease before it expired, shutting down");
+                shutdown.begin();
+                return;
+            }
+            Err(error) => error!(%error, "Error trying to renew the worker ID lease"),
+        }
+    }
 
-    #[cfg(unix)]
-    let mut terminate_signal =
-        signal::unix::signal(SignalKind::terminate()).map_err(InitError::SignalHandler)?;
+    match db.release_worker_lease(&lease).await {
+        Ok(()) => info!("Released the worker ID lease"),
+        Err(error) => error!(%error, "Error trying to release the worker ID lease"),
+    }
+}
 
-    #[cfg(not(unix))]
-    let terminate_future = std::future::pending::<()>();
+fn app(
+    config: &Config,
+    state: ServerState,
+    rate_limiter: Arc<RateLimiter>,
+) -> Result<Router, InitError> {
+    let tracing_layer = TraceLayer::new_for_http().make_span_with(logging::make_span);
+    let body_limits = Arc::new(BodyLimits::new(
+        config.limits.body,
+        config.limits.media_body,
+    ));
+    let app = server::routes(
+        rate_limiter,
+        body_limits,
+        state.read_only.clone(),
+        state.response_cache.clone(),
+        config.server.static_dir.as_deref(),
+    );
+    let app = match cors_layer(&config.cors)? {
+        Some(cors_layer) => app.layer(cors_layer),
+        None => app,
+    };
+    let trusted_proxies = Arc::new(config.server.trusted_proxies.clone());
+    Ok(app
+        .layer(middleware::from_fn_with_state(
+            state.tenants.clone(),
+            tenant::resolve,
+        ))
+        .layer(middleware::from_fn_with_state(
+            trusted_proxies,
+            client_ip::resolve,
+        ))
+        .layer(tracing_layer)
+        .layer(middleware::from_fn(request_id::assign))
+        .layer(middleware::from_fn(i18n::negotiate))
+        .with_state(state))
+}
 
-    Ok(async move {
-        #[cfg(unix)]
-        let terminate_future = terminate_signal.recv();
+/// Serves HTTPS if `rustls_config` is given, plain HTTP otherwise, until shutdown.
+async fn serve(
+    listener: TcpListener,
+    app: Router,
+    rustls_config: Option<RustlsConfig>,
+    shutdown: Shutdown,
+) -> Result<(), InitError> {
+    let make_service = app.into_make_service_with_connect_info::<SocketAddr>();
+    if let Some(rustls_config) = rustls_config {
+        let handle = axum_server::Handle::new();
+        tokio::spawn({
+            let handle = handle.clone();
+            async move {
+                shutdown.signalled().await;
+                // The drain deadline is enforced by the caller.
+                handle.graceful_shutdown(None);
+            }
+        });
 
-        tokio::select! {
-            _ = ctrl_c_signal.recv() => {},
-            _ = terminate_future => {},
-        }
+        let listener = listener.into_std().map_err(InitError::TcpBind)?;
+        axum_server::from_tcp_rustls(listener, rustls_config)
+            .map_err(InitError::TcpBind)?
+            .handle(handle)
+            .serve(make_service)
+            .await
+            .map_err(InitError::TcpServe)?;
+    } else {
+        axum::serve(listener, make_service)
+            .with_graceful_shutdown(async move { shutdown.signalled().await })
+            .await
+            .map_err(InitError::TcpServe)?;
+    }
+
+    Ok(())
+}
+
+/// Returns `None` if the gRPC server is disabled.
+async fn bind_grpc(config: Option<&GrpcConfig>) -> Result<Option<TcpListener>, InitError> {
+    let Some(config) = config else {
+        return Ok(None);
+    };
+
+    let address = SocketAddr::new(config.address, config.port);
+    let listener = TcpListener::bind(address)
+        .await
+        .map_err(InitError::TcpBind)?;
+    info!("gRPC listening on {address}");
+
+    Ok(Some(listener))
+}
+
+/// Serves the gRPC server, if enabled, until shutdown.
+async fn serve_grpc(
+    listener: Option<TcpListener>,
+    store: Arc<dyn Store>,
+    shutdown: Shutdown,
+) -> Result<(), InitError> {
+    let Some(listener) = listener else {
+        return Ok(());
+    };
 
-        info!("Shutdown signal received");
+    grpc::serve(listener, InternalService::new(store), async move {
+        shutdown.signalled().await;
     })
+    .await
+    .map_err(InitError::GrpcServe)
 }
 
 #[tokio::main]
-async fn main() -> Result<(), InitError> {
-    install_tracing();
-    let env = get_env()?;
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            // Tracing is not installed yet if the configuration is invalid.
+            eprintln!("Error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Spawns the loops that remove data past its retention from the database.
+fn spawn_db_cleanup(
+    tasks: &mut BackgroundTasks,
+    db_client: &Arc<DbClient>,
+    instance: &InstanceConfig,
+) {
+    let post_retention = Duration::from_days(instance.deleted_post_retention_days);
+    tasks.spawn("database prune loop", |cancellation| {
+        db_prune_loop(db_client.clone(), post_retention, cancellation)
+    });
+    let token_archive_retention = Duration::from_days(instance.token_archive_retention_days);
+    tasks.spawn("token archive purge loop", |cancellation| {
+        token_archive_purge_loop(db_client.clone(), token_archive_retention, cancellation)
+    });
+}
+
+/// Spawns the loops that refresh, flush, or prune the in-memory state of this process.
+fn spawn_state_loops(
+    tasks: &mut BackgroundTasks,
+    state: &ServerState,
+    rate_limiter: Arc<RateLimiter>,
+) {
+    let db_client = &state.db_client;
+    tasks.spawn("feature flag refresh loop", |cancellation| {
+        feature_flags::feature_flag_refresh_loop(
+            db_client.clone(),
+            state.feature_flags.clone(),
+            cancellation,
+        )
+    });
+    tasks.spawn("analytics flush loop", |cancellation| {
+        analytics::analytics_flush_loop(db_client.clone(), state.analytics.clone(), cancellation)
+    });
+    tasks.spawn("post views flush loop", |cancellation| {
+        views::post_views_flush_loop(db_client.clone(), state.post_views.clone(), cancellation)
+    });
+    tasks.spawn("rate limit prune loop", |cancellation| {
+        rate_limit::rate_limit_prune_loop(rate_limiter, cancellation)
+    });
+}
 
-    let state = init_state(&env).await?;
+/// Spawns the loops that run the queued [jobs] of each kind.
+/// Search indexing jobs are only run if `search` is an external engine.
+fn spawn_job_loops(
+    tasks: &mut BackgroundTasks,
+    db_client: &Arc<DbClient>,
+    federation: Arc<Federation>,
+    search: Option<Arc<dyn SearchIndex>>,
+    cdn: Option<&CdnConfig>,
+    mail: &MailConfig,
+    public_url: String,
+) -> Result<(), InitError> {
+    let webhook_dispatcher = Arc::new(
+        WebhookDispatcher::new(db_client.clone()).map_err(InitError::WebhookDeliveryHttpClient)?,
+    );
+    let digest_sender = Arc::new(DigestSender::new(db_client.clone(), public_url));
+    let mail_queue = Arc::new(MailQueue::new(mailer(mail)?, mail.recipient_rate_limit.0));
+
+    tasks.spawn("email digest loop", |cancellation| {
+        digest::email_digest_loop(digest_sender, cancellation)
+    });
+    tasks.spawn("mail queue loop", |cancellation| {
+        jobs::job_loop(db_client.clone(), mail_queue, cancellation)
+    });
+    tasks.spawn("webhook delivery loop", |cancellation| {
+        jobs::job_loop(db_client.clone(), webhook_dispatcher, cancellation)
+    });
+    tasks.spawn("federation delivery loop", |cancellation| {
+        jobs::job_loop(db_client.clone(), federation, cancellation)
+    });
+    let impersonation_notifier = Arc::new(ImpersonationNotifier::new(db_client.clone()));
+    tasks.spawn("impersonation notice loop", |cancellation| {
+        jobs::job_loop(db_client.clone(), impersonation_notifier, cancellation)
+    });
+    if let Some(search) = search {
+        let indexer = Arc::new(SearchIndexer::new(db_client.clone(), search));
+        tasks.spawn("search indexing loop", |cancellation| {
+            jobs::job_loop(db_client.clone(), indexer, cancellation)
+        });
+    }
+    if let Some(cdn) = cdn {
+        let purge_queue = Arc::new(CdnPurgeQueue::new(cache_purger(cdn)?));
+        tasks.spawn("CDN purge loop", |cancellation| {
+            jobs::job_loop(db_client.clone(), purge_queue, cancellation)
+        });
+    }
+
+    Ok(())
+}
+
+/// Loads the configuration from `.env`, the config file and the environment, and installs
+/// tracing as configured.
+fn load_config() -> Result<Config, InitError> {
+    let dotenv_found = load_dotenv()?;
+    let config_path = config::config_path()?;
+    let config = Config::load(config_path.as_deref())?;
+    install_tracing(config.server.log_format);
+    if !dotenv_found {
+        debug!("No .env file found");
+    }
+    if let Some(config_path) = &config_path {
+        info!("Loaded config file {}", config_path.display());
+    }
+
+    Ok(config)
+}
+
+async fn run() -> Result<(), InitError> {
+    let config = load_config()?;
+    i18n::load();
+
+    let shutdown = Shutdown::listen(Duration::from_secs(config.server.drain_timeout))
+        .map_err(InitError::SignalHandler)?;
+    let (state, worker_lease) = init_state(&config, shutdown.token()).await?;
     let db_client = state.db_client.clone();
-    let tracing_layer = TraceLayer::new_for_http();
-    let app = server::routes().layer(tracing_layer).with_state(state);
+    let store = state.store.clone();
+    let public_url = state.instance.public_url.clone();
+    let event_hub = state.events.clone();
+    let federation = state.federation.clone();
+    let search = config.search.is_some().then(|| state.search.clone());
+    let atproto_bridge = (!config.atproto.accounts.is_empty())
+        .then(|| {
+            AtprotoBridge::new(
+                db_client.clone(),
+                &config.atproto.appview_url,
+                config.atproto.accounts.clone(),
+                &public_url,
+                config.instance.post_content_max_len,
+            )
+        })
+        .transpose()
+        .map_err(InitError::BridgeHttpClient)?;
+    let rate_limiter = Arc::new(rate_limiter(&config.limits.rate).await?);
+    let app = app(&config, state.clone(), rate_limiter.clone())?;
 
-    let server_address = SocketAddr::new(env.server_address, env.server_port);
+    let rustls_config = match &config.server.tls {
+        Some(tls_config) => Some(tls::load(tls_config).await.map_err(InitError::Tls)?),
+        None => None,
+    };
+
+    let server_address = SocketAddr::new(config.server.address, config.server.port);
     let listener = tokio::net::TcpListener::bind(server_address)
         .await
         .map_err(InitError::TcpBind)?;
     info!("Listening on {server_address}");
+    let grpc_listener = bind_grpc(config.grpc.as_ref()).await?;
 
-    let cancellation_token = CancellationToken::new();
-    let db_prune_loop_handle = tokio::spawn(db_prune_loop(db_client, cancellation_token.clone()));
-    info!("Started database prune loop");
+    let mut tasks = BackgroundTasks::default();
+    if let Some(lease) = worker_lease {
+        tasks.spawn("worker ID lease loop", |cancellation| {
+            worker_lease_loop(db_client.clone(), lease, shutdown.clone(), cancellation)
+        });
+    }
+    spawn_db_cleanup(&mut tasks, &db_client, &config.instance);
+    spawn_state_loops(&mut tasks, &state, rate_limiter);
+    spawn_job_loops(
+        &mut tasks,
+        &db_client,
+        federation,
+        search,
+        config.cdn.as_ref(),
+        &config.mail,
+        public_url,
+    )?;
+    tasks.spawn("database event bridge", |cancellation| {
+        events::db_event_bridge(db_client, event_hub, cancellation)
+    });
+    if let Some(bridge) = atproto_bridge {
+        tasks.spawn("AT Protocol bridge loop", |cancellation| {
+            atproto::atproto_bridge_loop(bridge, cancellation)
+        });
+    }
+    if let Some((rustls_config, tls_config)) = rustls_config.clone().zip(config.server.tls.clone())
+    {
+        let hangup =
+            signal::unix::signal(SignalKind::hangup()).map_err(InitError::SignalHandler)?;
+        tasks.spawn("TLS certificate reload handler", |cancellation| {
+            tls::reload_on_hangup(rustls_config, tls_config, hangup, cancellation)
+        });
+    }
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(await_shutdown()?)
+    let drained = shutdown
+        .drain(async {
+            tokio::try_join!(
+                serve(listener, app, rustls_config, shutdown.clone()),
+                serve_grpc(grpc_listener, store, shutdown.clone()),
+            )
+        })
         .await
-        .map_err(InitError::TcpServe)?;
+        .transpose()?
+        .is_some();
+    if !drained {
+        warn!("Requests were still in flight at the drain deadline");
+    }
 
-    cancellation_token.cancel();
-    db_prune_loop_handle.await?;
+    let unfinished = tasks.stop(&shutdown).await?;
+    if !drained || !unfinished.is_empty() {
+        return Err(InitError::DrainTimeout(config.server.drain_timeout));
+    }
 
     Ok(())
 }
@@ -0,0 +1,111 @@
+use crate::oidc::{ExternalIdentity, IdentityProvider, IdentityProviderError, with_query};
+use async_trait::async_trait;
+use axum::http::header::ACCEPT;
+use serde::Deserialize;
+use stellwerk_common::model::oidc::{OidcToken, PendingOidcLogin};
+
+const AUTHORIZATION_ENDPOINT: &str = "https://github.com/login/oauth/authorize";
+const TOKEN_ENDPOINT: &str = "https://github.com/login/oauth/access_token";
+const USER_ENDPOINT: &str = "https://api.github.com/user";
+
+/// Signing in with a GitHub OAuth app. The nonce is not used, since there is no ID token.
+#[derive(Clone, Debug)]
+pub struct GitHub {
+    http: reqwest::Client,
+    client_id: String,
+    client_secret: String,
+}
+
+/// GitHub answers errors with status 200 too.
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GitHubUser {
+    id: u64,
+    login: String,
+}
+
+impl GitHub {
+    #[must_use]
+    pub fn new(http: reqwest::Client, client_id: String, client_secret: String) -> Self {
+        Self {
+            http,
+            client_id,
+            client_secret,
+        }
+    }
+}
+
+#[async_trait]
+impl IdentityProvider for GitHub {
+    fn name(&self) -> &'static str {
+        "GitHub"
+    }
+
+    async fn authorization_url(
+        &self,
+        state: &OidcToken,
+        login: &PendingOidcLogin,
+        redirect_url: &str,
+    ) -> Result<String, IdentityProviderError> {
+        Ok(with_query(
+            AUTHORIZATION_ENDPOINT,
+            &[
+                ("client_id", &self.client_id),
+                ("redirect_uri", redirect_url),
+                // Only public information is needed.
+                ("scope", ""),
+                ("state", &state.as_token_str()),
+                ("code_challenge", &login.code_verifier.code_challenge()),
+                ("code_challenge_method", "S256"),
+            ],
+        ))
+    }
+
+    async fn identify(
+        &self,
+        code: &str,
+        login: &PendingOidcLogin,
+        redirect_url: &str,
+    ) -> Result<ExternalIdentity, IdentityProviderError> {
+        let response: TokenResponse = self
+            .http
+            .post(TOKEN_ENDPOINT)
+            .header(ACCEPT, "application/json")
+            .form(&[
+                ("client_id", &*self.client_id),
+                ("client_secret", &self.client_secret),
+                ("code", code),
+                ("redirect_uri", redirect_url),
+                ("code_verifier", &login.code_verifier.as_token_str()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let access_token = response
+            .access_token
+            .ok_or_else(|| IdentityProviderError::Rejected(response.error.unwrap_or_default()))?;
+
+        let user: GitHubUser = self
+            .http
+            .get(USER_ENDPOINT)
+            .bearer_auth(access_token)
+            .header(ACCEPT, "application/vnd.github+json")
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(ExternalIdentity {
+            subject: user.id.to_string(),
+            username: Some(user.login),
+        })
+    }
+}
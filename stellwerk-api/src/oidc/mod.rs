@@ -0,0 +1,142 @@
+//! Logging in with external identity providers, see [`stellwerk_common::model::oidc`].
+//!
+//! Logins use the authorization code flow with PKCE. Providers redirect the browser to the
+//! redirect URL, a page of the frontend that passes the query on to `POST /auth/oidc/callback`,
+//! which exchanges the code for the identity. Google and custom providers are OpenID Connect
+//! providers, discovered from their issuer. GitHub only supports OAuth, so its user API tells
+//! who signed in.
+
+mod github;
+mod openid_connect;
+
+pub use self::{github::GitHub, openid_connect::OpenIdConnect};
+
+use async_trait::async_trait;
+use std::{collections::BTreeMap, fmt::Debug, sync::Arc, time::Duration};
+use stellwerk_common::{
+    model::oidc::{OidcProvider, OidcProviderInfo, OidcToken, PendingOidcLogin},
+    util::PositiveDuration,
+};
+use thiserror::Error;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long users have to sign in at the provider.
+pub const LOGIN_LIFETIME: Duration = Duration::from_mins(10);
+
+#[derive(Debug, Error)]
+pub enum IdentityProviderError {
+    #[error("Identity provider request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("The identity provider rejected the login: {0}")]
+    Rejected(String),
+    #[error("The ID token is invalid: {0}")]
+    InvalidIdToken(&'static str),
+}
+
+/// Who signed in at the provider.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct ExternalIdentity {
+    /// Stable, unlike the username.
+    pub subject: String,
+    /// Suggested as handle of a new account.
+    pub username: Option<String>,
+}
+
+#[async_trait]
+pub trait IdentityProvider: Debug + Send + Sync {
+    /// Shown to users, like `Google`.
+    fn name(&self) -> &str;
+
+    /// Where to send the browser to sign in.
+    async fn authorization_url(
+        &self,
+        state: &OidcToken,
+        login: &PendingOidcLogin,
+        redirect_url: &str,
+    ) -> Result<String, IdentityProviderError>;
+
+    /// Exchanges the code the provider redirected back with for the identity.
+    async fn identify(
+        &self,
+        code: &str,
+        login: &PendingOidcLogin,
+        redirect_url: &str,
+    ) -> Result<ExternalIdentity, IdentityProviderError>;
+}
+
+/// The configured providers.
+#[derive(Clone, Debug)]
+pub struct Oidc {
+    providers: BTreeMap<OidcProvider, Arc<dyn IdentityProvider>>,
+    redirect_url: String,
+    token_lifetime: PositiveDuration,
+}
+
+impl Oidc {
+    /// Without any provider, so logging in is not possible.
+    #[must_use]
+    pub fn new(redirect_url: String, token_lifetime: PositiveDuration) -> Self {
+        Self {
+            providers: BTreeMap::new(),
+            redirect_url,
+            token_lifetime,
+        }
+    }
+
+    #[must_use]
+    pub fn with_provider(
+        mut self,
+        provider: OidcProvider,
+        identity_provider: Arc<dyn IdentityProvider>,
+    ) -> Self {
+        self.providers.insert(provider, identity_provider);
+        self
+    }
+
+    #[must_use]
+    pub fn provider(&self, provider: OidcProvider) -> Option<&dyn IdentityProvider> {
+        self.providers.get(&provider).map(AsRef::as_ref)
+    }
+
+    #[must_use]
+    pub fn provider_infos(&self) -> Vec<OidcProviderInfo> {
+        self.providers
+            .iter()
+            .map(|(&provider, identity_provider)| OidcProviderInfo {
+                provider,
+                name: identity_provider.name().to_owned(),
+            })
+            .collect()
+    }
+
+    #[must_use]
+    pub fn redirect_url(&self) -> &str {
+        &self.redirect_url
+    }
+
+    /// Of the auth tokens issued when logging in.
+    #[must_use]
+    pub fn token_lifetime(&self) -> PositiveDuration {
+        self.token_lifetime
+    }
+}
+
+/// The HTTP client of identity providers.
+pub fn http_client() -> Result<reqwest::Client, reqwest::Error> {
+    reqwest::Client::builder()
+        .user_agent(concat!("stellwerk/", env!("CARGO_PKG_VERSION")))
+        .timeout(REQUEST_TIMEOUT)
+        // Token endpoints must not be followed anywhere else.
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+}
+
+/// `endpoint` with the parameters appended to its query.
+fn with_query(endpoint: &str, parameters: &[(&str, &str)]) -> String {
+    let query = form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(parameters)
+        .finish();
+    let separator = if endpoint.contains('?') { '&' } else { '?' };
+
+    format!("{endpoint}{separator}{query}")
+}
@@ -0,0 +1,201 @@
+use crate::oidc::{ExternalIdentity, IdentityProvider, IdentityProviderError, with_query};
+use async_trait::async_trait;
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use serde::Deserialize;
+use stellwerk_common::model::oidc::{OidcToken, PendingOidcLogin};
+use time::UtcDateTime;
+use tokio::sync::OnceCell;
+
+const SCOPE: &str = "openid email profile";
+
+/// An OpenID Connect provider, like Google with the issuer `https://accounts.google.com`.
+/// Its endpoints are discovered on first use.
+///
+/// The signature of ID tokens is not checked, since they are received directly from the token
+/// endpoint over TLS, which OpenID Connect Core 1.0, section 3.1.3.7 allows.
+#[derive(Debug)]
+pub struct OpenIdConnect {
+    http: reqwest::Client,
+    name: String,
+    /// Without trailing slash.
+    issuer_url: String,
+    client_id: String,
+    client_secret: String,
+    metadata: OnceCell<ProviderMetadata>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Deserialize)]
+struct IdTokenClaims {
+    iss: String,
+    aud: Audience,
+    exp: i64,
+    nonce: Option<String>,
+    sub: String,
+    preferred_username: Option<String>,
+    email: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OpenIdConnect {
+    #[must_use]
+    pub fn new(
+        http: reqwest::Client,
+        name: String,
+        issuer_url: &str,
+        client_id: String,
+        client_secret: String,
+    ) -> Self {
+        Self {
+            http,
+            name,
+            issuer_url: issuer_url.trim_end_matches('/').to_owned(),
+            client_id,
+            client_secret,
+            metadata: OnceCell::new(),
+        }
+    }
+
+    /// Retried on the next login if discovery failed.
+    async fn metadata(&self) -> Result<&ProviderMetadata, IdentityProviderError> {
+        self.metadata
+            .get_or_try_init(|| async {
+                let metadata = self
+                    .http
+                    .get(format!(
+                        "{}/.well-known/openid-configuration",
+                        self.issuer_url
+                    ))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+
+                Ok(metadata)
+            })
+            .await
+    }
+
+    fn validate(
+        &self,
+        metadata: &ProviderMetadata,
+        claims: &IdTokenClaims,
+        nonce: &OidcToken,
+    ) -> Result<(), IdentityProviderError> {
+        if claims.iss != metadata.issuer {
+            return Err(IdentityProviderError::InvalidIdToken("Unexpected issuer"));
+        }
+        let audience_matches = match &claims.aud {
+            Audience::One(audience) => *audience == self.client_id,
+            Audience::Many(audiences) => audiences.contains(&self.client_id),
+        };
+        if !audience_matches {
+            return Err(IdentityProviderError::InvalidIdToken("Unexpected audience"));
+        }
+        if claims.exp <= UtcDateTime::now().unix_timestamp() {
+            return Err(IdentityProviderError::InvalidIdToken("Expired"));
+        }
+        if claims.nonce.as_deref() != Some(&*nonce.as_token_str()) {
+            return Err(IdentityProviderError::InvalidIdToken("Unexpected nonce"));
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl IdentityProvider for OpenIdConnect {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn authorization_url(
+        &self,
+        state: &OidcToken,
+        login: &PendingOidcLogin,
+        redirect_url: &str,
+    ) -> Result<String, IdentityProviderError> {
+        let metadata = self.metadata().await?;
+
+        Ok(with_query(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", &self.client_id),
+                ("redirect_uri", redirect_url),
+                ("scope", SCOPE),
+                ("state", &state.as_token_str()),
+                ("nonce", &login.nonce.as_token_str()),
+                ("code_challenge", &login.code_verifier.code_challenge()),
+                ("code_challenge_method", "S256"),
+            ],
+        ))
+    }
+
+    async fn identify(
+        &self,
+        code: &str,
+        login: &PendingOidcLogin,
+        redirect_url: &str,
+    ) -> Result<ExternalIdentity, IdentityProviderError> {
+        let metadata = self.metadata().await?;
+
+        let response = self
+            .http
+            .post(&metadata.token_endpoint)
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", redirect_url),
+                ("client_id", &self.client_id),
+                ("client_secret", &self.client_secret),
+                ("code_verifier", &login.code_verifier.as_token_str()),
+            ])
+            .send()
+            .await?;
+        if response.status().is_client_error() {
+            return Err(IdentityProviderError::Rejected(response.text().await?));
+        }
+        let TokenResponse { id_token } = response.error_for_status()?.json().await?;
+
+        let payload = id_token
+            .split('.')
+            .nth(1)
+            .ok_or(IdentityProviderError::InvalidIdToken("Not a JWT"))?;
+        let payload = BASE64_URL_SAFE_NO_PAD
+            .decode(payload)
+            .map_err(|_| IdentityProviderError::InvalidIdToken("Invalid base64"))?;
+        let claims: IdTokenClaims = serde_json::from_slice(&payload)
+            .map_err(|_| IdentityProviderError::InvalidIdToken("Invalid claims"))?;
+        self.validate(metadata, &claims, &login.nonce)?;
+
+        let username = claims.preferred_username.or_else(|| {
+            claims
+                .email
+                .and_then(|email| email.split_once('@').map(|(local, _)| local.to_owned()))
+        });
+
+        Ok(ExternalIdentity {
+            subject: claims.sub,
+            username,
+        })
+    }
+}
@@ -0,0 +1,170 @@
+use crate::search::{SearchError, SearchIndex};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::json;
+use stellwerk_common::model::{
+    Id,
+    post::PostMarker,
+    search::{IndexedPost, IndexedUser, SearchDocument, SearchQuery},
+    tenant::TenantId,
+    user::UserMarker,
+};
+
+/// A [Meilisearch](https://www.meilisearch.com) server, with an index for posts and one for
+/// users. Meilisearch applies changes asynchronously, so they are searchable a moment after
+/// they were accepted.
+#[derive(Clone, Debug)]
+pub struct Meilisearch {
+    http: reqwest::Client,
+    /// Without trailing slash.
+    url: String,
+    api_key: Option<String>,
+    posts_index: String,
+    users_index: String,
+}
+
+#[derive(Deserialize)]
+struct SearchResponse<T> {
+    hits: Vec<Hit<T>>,
+}
+
+#[derive(Deserialize)]
+struct Hit<T> {
+    id: T,
+}
+
+impl Meilisearch {
+    /// The indexes are named `{index_prefix}-posts` and `{index_prefix}-users`.
+    #[must_use]
+    pub fn new(
+        http: reqwest::Client,
+        url: &str,
+        api_key: Option<String>,
+        index_prefix: &str,
+    ) -> Self {
+        Self {
+            http,
+            url: url.trim_end_matches('/').to_owned(),
+            api_key,
+            posts_index: format!("{index_prefix}-posts"),
+            users_index: format!("{index_prefix}-users"),
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.http.request(method, format!("{}{path}", self.url));
+        match &self.api_key {
+            Some(api_key) => request.bearer_auth(api_key),
+            None => request,
+        }
+    }
+
+    async fn update_settings(
+        &self,
+        index: &str,
+        searchable_attribute: &str,
+    ) -> Result<(), SearchError> {
+        self.request(
+            reqwest::Method::PATCH,
+            &format!("/indexes/{index}/settings"),
+        )
+        .json(&json!({
+            "searchableAttributes": [searchable_attribute],
+            "filterableAttributes": ["tenant"],
+        }))
+        .send()
+        .await?
+        .error_for_status()?;
+
+        Ok(())
+    }
+
+    async fn add_document(
+        &self,
+        index: &str,
+        document: &impl Serialize,
+    ) -> Result<(), SearchError> {
+        self.request(
+            reqwest::Method::POST,
+            &format!("/indexes/{index}/documents?primaryKey=id"),
+        )
+        .json(&[document])
+        .send()
+        .await?
+        .error_for_status()?;
+
+        Ok(())
+    }
+
+    async fn search<T: DeserializeOwned>(
+        &self,
+        index: &str,
+        tenant: &TenantId,
+        query: &SearchQuery,
+        limit: u32,
+    ) -> Result<Vec<T>, SearchError> {
+        // Tenant ids never contain quotes.
+        let response: SearchResponse<T> = self
+            .request(reqwest::Method::POST, &format!("/indexes/{index}/search"))
+            .json(&json!({
+                "q": query.get(),
+                "limit": limit,
+                "filter": format!("tenant = \"{tenant}\""),
+                "attributesToRetrieve": ["id"],
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(response.hits.into_iter().map(|hit| hit.id).collect())
+    }
+}
+
+#[async_trait]
+impl SearchIndex for Meilisearch {
+    async fn prepare(&self) -> Result<(), SearchError> {
+        self.update_settings(&self.posts_index, "content").await?;
+        self.update_settings(&self.users_index, "handle").await
+    }
+
+    async fn index_post(&self, post: &IndexedPost) -> Result<(), SearchError> {
+        self.add_document(&self.posts_index, post).await
+    }
+
+    async fn index_user(&self, user: &IndexedUser) -> Result<(), SearchError> {
+        self.add_document(&self.users_index, user).await
+    }
+
+    async fn remove(&self, document: SearchDocument) -> Result<(), SearchError> {
+        let path = match document {
+            SearchDocument::Post(id) => format!("/indexes/{}/documents/{id}", self.posts_index),
+            SearchDocument::User(id) => format!("/indexes/{}/documents/{id}", self.users_index),
+        };
+        self.request(reqwest::Method::DELETE, &path)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    async fn search_posts(
+        &self,
+        tenant: &TenantId,
+        query: &SearchQuery,
+        limit: u32,
+    ) -> Result<Vec<Id<PostMarker>>, SearchError> {
+        self.search(&self.posts_index, tenant, query, limit).await
+    }
+
+    async fn search_users(
+        &self,
+        tenant: &TenantId,
+        query: &SearchQuery,
+        limit: u32,
+    ) -> Result<Vec<Id<UserMarker>>, SearchError> {
+        self.search(&self.users_index, tenant, query, limit).await
+    }
+}
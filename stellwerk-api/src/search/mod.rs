@@ -0,0 +1,149 @@
+//! Searching posts and users, see [`stellwerk_common::model::search`].
+//!
+//! External engines are kept up to date by [jobs](crate::jobs) the database queues in the
+//! transaction of every change of a post or user, so a change is searchable shortly after, and
+//! never lost if the engine is down. Only changes made after indexing was enabled are indexed.
+//! Without an external engine, the full-text search of the database is used.
+
+mod meilisearch;
+mod opensearch;
+mod postgres;
+
+pub use self::{meilisearch::Meilisearch, opensearch::OpenSearch, postgres::PostgresSearch};
+
+use crate::{
+    jobs::{JobError, JobHandler, QueueSettings},
+    server::ServerError,
+    webhooks,
+};
+use async_trait::async_trait;
+use std::{fmt::Debug, sync::Arc, time::Duration};
+use stellwerk_common::model::{
+    Id,
+    job::{Job, JobKind, JobPayload},
+    post::PostMarker,
+    search::{IndexedPost, IndexedUser, SearchDocument, SearchQuery},
+    tenant::TenantId,
+    user::UserMarker,
+};
+use stellwerk_db::client::{DbClient, DbError};
+use thiserror::Error;
+use time::UtcDateTime;
+use tracing::debug;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum SearchError {
+    #[error("Search engine request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error(transparent)]
+    Database(#[from] DbError),
+}
+
+impl From<SearchError> for ServerError {
+    fn from(error: SearchError) -> Self {
+        match error {
+            SearchError::Request(error) => ServerError::SearchUnavailable(error),
+            SearchError::Database(error) => ServerError::Database(error),
+        }
+    }
+}
+
+/// Where posts and users are searched. Results are ids, which the caller loads from the
+/// database, so that they are never stale.
+#[async_trait]
+pub trait SearchIndex: Debug + Send + Sync {
+    /// Creates the indexes and their settings if they do not exist yet.
+    async fn prepare(&self) -> Result<(), SearchError>;
+
+    /// Adds the post, or replaces it if it was indexed before.
+    async fn index_post(&self, post: &IndexedPost) -> Result<(), SearchError>;
+
+    /// Adds the user, or replaces them if they were indexed before.
+    async fn index_user(&self, user: &IndexedUser) -> Result<(), SearchError>;
+
+    /// Succeeds if the document was not indexed.
+    async fn remove(&self, document: SearchDocument) -> Result<(), SearchError>;
+
+    /// Best matches first.
+    async fn search_posts(
+        &self,
+        tenant: &TenantId,
+        query: &SearchQuery,
+        limit: u32,
+    ) -> Result<Vec<Id<PostMarker>>, SearchError>;
+
+    /// Best matches first.
+    async fn search_users(
+        &self,
+        tenant: &TenantId,
+        query: &SearchQuery,
+        limit: u32,
+    ) -> Result<Vec<Id<UserMarker>>, SearchError>;
+}
+
+/// The HTTP client of external engines.
+pub fn http_client() -> Result<reqwest::Client, reqwest::Error> {
+    reqwest::Client::builder()
+        .user_agent(concat!("stellwerk/", env!("CARGO_PKG_VERSION")))
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+}
+
+/// Runs the [`JobPayload::SearchIndexing`] jobs.
+#[derive(Clone, Debug)]
+pub struct SearchIndexer {
+    db: Arc<DbClient>,
+    index: Arc<dyn SearchIndex>,
+}
+
+impl SearchIndexer {
+    #[must_use]
+    pub fn new(db: Arc<DbClient>, index: Arc<dyn SearchIndex>) -> Self {
+        Self { db, index }
+    }
+
+    /// Indexes the current state of the document, so it does not matter in which order the jobs
+    /// of one document run.
+    async fn update(&self, document: SearchDocument) -> Result<(), SearchError> {
+        match document {
+            SearchDocument::Post(id) => match self.db.fetch_indexed_post(id).await? {
+                Some(post) => self.index.index_post(&post).await,
+                None => self.index.remove(document).await,
+            },
+            SearchDocument::User(id) => match self.db.fetch_indexed_user(id).await? {
+                Some(user) => self.index.index_user(&user).await,
+                None => self.index.remove(document).await,
+            },
+        }
+    }
+}
+
+impl JobHandler for SearchIndexer {
+    const SETTINGS: QueueSettings = QueueSettings {
+        kind: JobKind::SearchIndexing,
+        batch_size: 100,
+        visibility_timeout: Duration::from_mins(5),
+        max_attempts: 10,
+        retry_base_delay: Duration::from_secs(10),
+        retry_max_delay: Duration::from_mins(30),
+    };
+
+    async fn run(&self, job: &Job) -> Result<Option<UtcDateTime>, JobError> {
+        let JobPayload::SearchIndexing { document } = &job.payload else {
+            return Err(JobError::unexpected_payload(&job.payload));
+        };
+
+        match self.update(*document).await {
+            Ok(()) => {
+                debug!(id = %job.id, ?document, "Indexed search document");
+                Ok(None)
+            }
+            Err(SearchError::Request(error)) if webhooks::is_permanent(&error) => {
+                Err(JobError::permanent(error))
+            }
+            Err(error) => Err(JobError::transient(error)),
+        }
+    }
+}
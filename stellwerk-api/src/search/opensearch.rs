@@ -0,0 +1,195 @@
+use crate::search::{SearchError, SearchIndex};
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use std::fmt::Display;
+use stellwerk_common::model::{
+    Id,
+    post::PostMarker,
+    search::{IndexedPost, IndexedUser, SearchDocument, SearchQuery},
+    tenant::TenantId,
+    user::UserMarker,
+};
+
+/// An [OpenSearch](https://opensearch.org) or Elasticsearch cluster, with an index for posts
+/// and one for users.
+#[derive(Clone, Debug)]
+pub struct OpenSearch {
+    http: reqwest::Client,
+    /// Without trailing slash.
+    url: String,
+    /// Username and password for basic authentication.
+    credentials: Option<(String, Option<String>)>,
+    posts_index: String,
+    users_index: String,
+}
+
+#[derive(Deserialize)]
+struct SearchResponse<T> {
+    hits: Hits<T>,
+}
+
+#[derive(Deserialize)]
+struct Hits<T> {
+    hits: Vec<Hit<T>>,
+}
+
+#[derive(Deserialize)]
+struct Hit<T> {
+    #[serde(rename = "_id")]
+    id: T,
+}
+
+impl OpenSearch {
+    /// The indexes are named `{index_prefix}-posts` and `{index_prefix}-users`.
+    #[must_use]
+    pub fn new(
+        http: reqwest::Client,
+        url: &str,
+        credentials: Option<(String, Option<String>)>,
+        index_prefix: &str,
+    ) -> Self {
+        Self {
+            http,
+            url: url.trim_end_matches('/').to_owned(),
+            credentials,
+            posts_index: format!("{index_prefix}-posts"),
+            users_index: format!("{index_prefix}-users"),
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.http.request(method, format!("{}{path}", self.url));
+        match &self.credentials {
+            Some((username, password)) => request.basic_auth(username, password.as_ref()),
+            None => request,
+        }
+    }
+
+    async fn create_index(&self, index: &str, properties: Value) -> Result<(), SearchError> {
+        let response = self
+            .request(reqwest::Method::HEAD, &format!("/{index}"))
+            .send()
+            .await?;
+        if response.status() != StatusCode::NOT_FOUND {
+            response.error_for_status()?;
+            return Ok(());
+        }
+
+        self.request(reqwest::Method::PUT, &format!("/{index}"))
+            .json(&json!({ "mappings": { "properties": properties } }))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    async fn put_document(
+        &self,
+        index: &str,
+        id: impl Display,
+        document: &impl Serialize,
+    ) -> Result<(), SearchError> {
+        self.request(reqwest::Method::PUT, &format!("/{index}/_doc/{id}"))
+            .json(document)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    async fn search<T: DeserializeOwned>(
+        &self,
+        index: &str,
+        tenant: &TenantId,
+        query: Value,
+        limit: u32,
+    ) -> Result<Vec<T>, SearchError> {
+        let response: SearchResponse<T> = self
+            .request(reqwest::Method::POST, &format!("/{index}/_search"))
+            .json(&json!({
+                "size": limit,
+                "_source": false,
+                "query": {
+                    "bool": {
+                        "must": query,
+                        "filter": { "term": { "tenant": tenant.get() } },
+                    },
+                },
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(response.hits.hits.into_iter().map(|hit| hit.id).collect())
+    }
+}
+
+#[async_trait]
+impl SearchIndex for OpenSearch {
+    async fn prepare(&self) -> Result<(), SearchError> {
+        self.create_index(
+            &self.posts_index,
+            json!({
+                "tenant": { "type": "keyword" },
+                "author": { "type": "keyword" },
+                "content": { "type": "text" },
+            }),
+        )
+        .await?;
+        self.create_index(
+            &self.users_index,
+            json!({
+                "tenant": { "type": "keyword" },
+                "handle": { "type": "keyword" },
+            }),
+        )
+        .await
+    }
+
+    async fn index_post(&self, post: &IndexedPost) -> Result<(), SearchError> {
+        self.put_document(&self.posts_index, post.id, post).await
+    }
+
+    async fn index_user(&self, user: &IndexedUser) -> Result<(), SearchError> {
+        self.put_document(&self.users_index, user.id, user).await
+    }
+
+    async fn remove(&self, document: SearchDocument) -> Result<(), SearchError> {
+        let path = match document {
+            SearchDocument::Post(id) => format!("/{}/_doc/{id}", self.posts_index),
+            SearchDocument::User(id) => format!("/{}/_doc/{id}", self.users_index),
+        };
+        let response = self.request(reqwest::Method::DELETE, &path).send().await?;
+        if response.status() != StatusCode::NOT_FOUND {
+            response.error_for_status()?;
+        }
+
+        Ok(())
+    }
+
+    async fn search_posts(
+        &self,
+        tenant: &TenantId,
+        query: &SearchQuery,
+        limit: u32,
+    ) -> Result<Vec<Id<PostMarker>>, SearchError> {
+        let query = json!({ "match": { "content": { "query": query.get() } } });
+        self.search(&self.posts_index, tenant, query, limit).await
+    }
+
+    async fn search_users(
+        &self,
+        tenant: &TenantId,
+        query: &SearchQuery,
+        limit: u32,
+    ) -> Result<Vec<Id<UserMarker>>, SearchError> {
+        let query = json!({ "prefix": { "handle": { "value": query.get() } } });
+        self.search(&self.users_index, tenant, query, limit).await
+    }
+}
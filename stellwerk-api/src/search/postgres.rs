@@ -0,0 +1,61 @@
+use crate::search::{SearchError, SearchIndex};
+use async_trait::async_trait;
+use std::sync::Arc;
+use stellwerk_common::model::{
+    Id,
+    post::PostMarker,
+    search::{IndexedPost, IndexedUser, SearchDocument, SearchQuery},
+    tenant::TenantId,
+    user::UserMarker,
+};
+use stellwerk_db::client::DbClient;
+
+/// The full-text search of the database, which is always up to date, so indexing does nothing.
+#[derive(Clone, Debug)]
+pub struct PostgresSearch {
+    db: Arc<DbClient>,
+}
+
+impl PostgresSearch {
+    #[must_use]
+    pub fn new(db: Arc<DbClient>) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl SearchIndex for PostgresSearch {
+    async fn prepare(&self) -> Result<(), SearchError> {
+        Ok(())
+    }
+
+    async fn index_post(&self, _post: &IndexedPost) -> Result<(), SearchError> {
+        Ok(())
+    }
+
+    async fn index_user(&self, _user: &IndexedUser) -> Result<(), SearchError> {
+        Ok(())
+    }
+
+    async fn remove(&self, _document: SearchDocument) -> Result<(), SearchError> {
+        Ok(())
+    }
+
+    async fn search_posts(
+        &self,
+        tenant: &TenantId,
+        query: &SearchQuery,
+        limit: u32,
+    ) -> Result<Vec<Id<PostMarker>>, SearchError> {
+        Ok(self.db.search_post_ids(tenant, query, limit).await?)
+    }
+
+    async fn search_users(
+        &self,
+        tenant: &TenantId,
+        query: &SearchQuery,
+        limit: u32,
+    ) -> Result<Vec<Id<UserMarker>>, SearchError> {
+        Ok(self.db.search_user_ids(tenant, query, limit).await?)
+    }
+}
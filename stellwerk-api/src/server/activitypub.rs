@@ -0,0 +1,146 @@
+use crate::{
+    federation::{ACTIVITY_JSON, Federation, FederationError},
+    server::{Result, ServerError, json::Json},
+};
+use axum::{
+    body::Bytes,
+    extract::{FromRef, FromRequest, FromRequestParts, Request},
+    http::{
+        HeaderMap, HeaderName, HeaderValue,
+        header::{ACCEPT, CONTENT_TYPE, VARY},
+        request::Parts,
+    },
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use std::sync::Arc;
+use stellwerk_common::model::activitypub::{ACTIVITY_STREAMS_CONTEXT, Activity};
+use tracing::debug;
+
+/// Like [`Json`], but with the `application/activity+json` content type.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ActivityJson<T>(pub T);
+
+impl<T: Serialize> IntoResponse for ActivityJson<T> {
+    fn into_response(self) -> Response {
+        match serde_json::to_vec(&self.0) {
+            Ok(json) => (
+                [(CONTENT_TYPE, HeaderValue::from_static(ACTIVITY_JSON))],
+                json,
+            )
+                .into_response(),
+            Err(err) => ServerError::JsonResponse(err).into_response(),
+        }
+    }
+}
+
+/// Whether the `Accept` header asks for an ActivityPub representation,
+/// either as `application/activity+json` or as JSON-LD with the ActivityStreams profile.
+pub fn is_requested(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|media_range| {
+            let mut parts = media_range.split(';').map(str::trim);
+            match parts.next() {
+                Some(ACTIVITY_JSON) => true,
+                Some("application/ld+json") => parts.any(|parameter| {
+                    parameter.strip_prefix("profile=").is_some_and(|profile| {
+                        profile.trim_matches('"') == ACTIVITY_STREAMS_CONTEXT
+                    })
+                }),
+                _ => false,
+            }
+        })
+}
+
+/// Responses that are negotiated with [`negotiate`] depend on the `Accept` header.
+pub const VARY_ACCEPT: [(HeaderName, HeaderValue); 1] =
+    [(VARY, HeaderValue::from_static("accept"))];
+
+/// Responds with the ActivityPub representation of `value` if the client asked for it,
+/// and with the regular JSON otherwise.
+pub async fn negotiate<T: Serialize, A: Serialize>(
+    headers: &HeaderMap,
+    value: T,
+    to_activity: impl AsyncFnOnce(&T) -> Result<A>,
+) -> Result<Response> {
+    if is_requested(headers) {
+        Ok((VARY_ACCEPT, ActivityJson(to_activity(&value).await?)).into_response())
+    } else {
+        Ok((VARY_ACCEPT, Json(value)).into_response())
+    }
+}
+
+/// Verifies the HTTP signature of requests that carry one.
+/// As an optional extractor, unsigned requests are let through.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
+pub struct VerifiedSignature;
+
+impl<S> FromRequestParts<S> for VerifiedSignature
+where
+    Arc<Federation>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ServerError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let actor = Arc::<Federation>::from_ref(state)
+            .verify(&parts.method, &parts.uri, &parts.headers, None)
+            .await?;
+        debug!(actor, "Verified HTTP signature");
+
+        Ok(Self)
+    }
+}
+
+impl<S> axum::extract::OptionalFromRequestParts<S> for VerifiedSignature
+where
+    Arc<Federation>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ServerError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        if !parts.headers.contains_key("signature") {
+            return Ok(None);
+        }
+
+        <Self as FromRequestParts<S>>::from_request_parts(parts, state)
+            .await
+            .map(Some)
+    }
+}
+
+/// An activity in a request body whose HTTP signature and `Digest` were verified.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct SignedActivity {
+    /// The id of the signing actor.
+    pub signer: String,
+    pub activity: Activity,
+}
+
+impl<S> FromRequest<S> for SignedActivity
+where
+    Arc<Federation>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ServerError;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let (parts, body) = request.into_parts();
+        let body = Bytes::from_request(Request::from_parts(parts.clone(), body), state).await?;
+
+        let signer = Arc::<Federation>::from_ref(state)
+            .verify(&parts.method, &parts.uri, &parts.headers, Some(&body))
+            .await?;
+        let activity = serde_json::from_slice(&body).map_err(FederationError::InvalidActivity)?;
+
+        Ok(Self { signer, activity })
+    }
+}
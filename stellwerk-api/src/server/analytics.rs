@@ -0,0 +1,164 @@
+//! Aggregate usage counters, see [`stellwerk_common::model::analytics`].
+//!
+//! Counters are kept in memory and added to the rollups in the database periodically, so that
+//! recording them does not query the database. Active users are remembered until the end of the
+//! day, to report each once per day, and only the day they were last active on is stored.
+//! Counters are optionally also sent to a `StatsD` server as they happen.
+
+use std::{
+    collections::{HashMap, HashSet},
+    net::UdpSocket,
+    sync::{Arc, nonpoison::Mutex},
+    time::Duration,
+};
+use stellwerk_common::model::{Id, analytics::AnalyticsMetric, user::UserMarker};
+use stellwerk_db::client::DbClient;
+use time::UtcDateTime;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error};
+
+const FLUSH_INTERVAL: Duration = Duration::from_mins(1);
+
+#[derive(Debug)]
+pub struct Analytics {
+    pending: Mutex<Pending>,
+    statsd: Option<Statsd>,
+}
+
+/// What was recorded since the last flush.
+#[derive(Debug, Default)]
+struct Pending {
+    /// The start of the day of `seen`.
+    day_start: Option<UtcDateTime>,
+    /// The users seen active today, to not report them again.
+    seen: HashSet<Id<UserMarker>>,
+    /// Users to report, by the start of the day they were active on.
+    active_users: HashMap<UtcDateTime, Vec<Id<UserMarker>>>,
+    /// Totals and samples of the hourly metrics, by bucket.
+    buckets: HashMap<(AnalyticsMetric, UtcDateTime), (u64, u64)>,
+}
+
+/// Sends counters and timers as UDP datagrams, dropping them if the socket would block.
+#[derive(Debug)]
+struct Statsd {
+    socket: UdpSocket,
+    prefix: String,
+}
+
+impl Analytics {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            pending: Mutex::new(Pending::default()),
+            statsd: None,
+        }
+    }
+
+    /// Also sends every counter to the `StatsD` server at `address`, prefixed with `{prefix}.`.
+    pub fn with_statsd(self, address: &str, prefix: String) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        socket.connect(address)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            statsd: Some(Statsd { socket, prefix }),
+            ..self
+        })
+    }
+
+    /// Counts the user as active today, once per day.
+    pub fn record_active(&self, user: Id<UserMarker>) {
+        let day_start = AnalyticsMetric::DailyActiveUsers.bucket_start(UtcDateTime::now());
+        let mut pending = self.pending.lock();
+        if pending.day_start != Some(day_start) {
+            pending.day_start = Some(day_start);
+            pending.seen.clear();
+        }
+        if pending.seen.insert(user) {
+            pending
+                .active_users
+                .entry(day_start)
+                .or_default()
+                .push(user);
+        }
+    }
+
+    pub fn record_post(&self) {
+        self.add(AnalyticsMetric::Posts, 1);
+        self.send("posts:1|c");
+    }
+
+    pub fn record_timeline_latency(&self, latency: Duration) {
+        let millis = u64::try_from(latency.as_millis()).unwrap_or(u64::MAX);
+        self.add(AnalyticsMetric::TimelineLatency, millis);
+        self.send(&format!("timeline_latency:{millis}|ms"));
+    }
+
+    fn add(&self, metric: AnalyticsMetric, value: u64) {
+        let bucket_start = metric.bucket_start(UtcDateTime::now());
+        let mut pending = self.pending.lock();
+        let (total, samples) = pending.buckets.entry((metric, bucket_start)).or_default();
+        *total = total.saturating_add(value);
+        *samples += 1;
+    }
+
+    fn send(&self, metric: &str) {
+        if let Some(statsd) = &self.statsd
+            && let Err(error) = statsd
+                .socket
+                .send(format!("{}.{metric}", statsd.prefix).as_bytes())
+        {
+            debug!(%error, "Error trying to send a metric to StatsD");
+        }
+    }
+
+    /// Adds what was recorded since the last flush to the rollups. Counters that could not be
+    /// added are dropped, since they are approximate anyway.
+    async fn flush(&self, db: &DbClient) {
+        let (active_users, buckets) = {
+            let mut pending = self.pending.lock();
+            (
+                std::mem::take(&mut pending.active_users),
+                std::mem::take(&mut pending.buckets),
+            )
+        };
+
+        for (day_start, users) in active_users {
+            match db.record_active_users(&users, day_start).await {
+                Ok(0) => {}
+                Ok(newly_active) => self.send(&format!("daily_active_users:{newly_active}|c")),
+                Err(error) => error!(%error, "Error trying to record active users"),
+            }
+        }
+        for ((metric, bucket_start), (total, samples)) in buckets {
+            if let Err(error) = db
+                .record_analytics(metric, bucket_start, total, samples)
+                .await
+            {
+                error!(%error, %metric, "Error trying to record analytics");
+            }
+        }
+    }
+}
+
+impl Default for Analytics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Flushes the counters periodically, and once more when cancelled.
+pub async fn analytics_flush_loop(
+    db: Arc<DbClient>,
+    analytics: Arc<Analytics>,
+    cancellation: CancellationToken,
+) {
+    while cancellation
+        .run_until_cancelled(tokio::time::sleep(FLUSH_INTERVAL))
+        .await
+        .is_some()
+    {
+        analytics.flush(&db).await;
+    }
+    analytics.flush(&db).await;
+}
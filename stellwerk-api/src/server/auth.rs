@@ -1,25 +1,41 @@
-use crate::server::ServerError;
+use crate::server::{
+    ServerError, analytics::Analytics, logging, tenant::CurrentTenant, versioning::CURRENT_VERSION,
+};
 use axum::{
-    extract::{FromRef, FromRequestParts},
-    http::{StatusCode, request::Parts},
+    extract::{FromRef, FromRequestParts, MatchedPath},
+    http::{StatusCode, header::AUTHORIZATION, request::Parts},
 };
 use axum_extra::{TypedHeader, typed_header::TypedHeaderRejection};
 use headers::{Authorization, authorization::Bearer};
 use std::{hash::Hash, sync::Arc};
 use stellwerk_common::model::{
     Id,
-    auth::{AuthToken, AuthTokenDecodeError, AuthTokenHashError},
-    user::UserMarker,
+    auth::{AuthToken, AuthTokenDecodeError, AuthTokenHashError, Authentication},
+    problem::ErrorCode,
+    tenant::TenantId,
+    user::{UserMarker, UserRole},
 };
-use stellwerk_db::client::DbClient;
+use stellwerk_db::store::Store;
 use thiserror::Error;
 use time::UtcDateTime;
+use tracing::info;
 
 type AuthorizationHeader = TypedHeader<Authorization<Bearer>>;
 
+/// Route templates without the version prefix that change state, but are allowed while the user
+/// has policies to accept, so that they can accept them and admins can correct them.
+const POLICY_EXEMPT_ROUTES: &[&str] = &["/policies/{kind}/accept", "/admin/policies/{kind}"];
+
+/// Extracting it for a request with an unsafe method fails with
+/// [`ServerError::PolicyAcceptanceRequired`] until the user accepted the latest version of every
+/// policy.
+///
+/// Requests with an [impersonation](stellwerk_common::model::admin::Impersonation) token are
+/// logged with the admin, and are not counted as activity of the user.
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
 pub struct AuthenticatedUser {
     id: Id<UserMarker>,
+    impersonator: Option<Id<UserMarker>>,
 }
 
 impl AuthenticatedUser {
@@ -27,6 +43,47 @@ impl AuthenticatedUser {
     pub fn user_id(self) -> Id<UserMarker> {
         self.id
     }
+
+    /// The admin acting as the user, if the token was minted for an impersonation.
+    #[must_use]
+    pub fn impersonator(self) -> Option<Id<UserMarker>> {
+        self.impersonator
+    }
+
+    /// Rejects impersonation tokens, for routes that change how the user logs in or where their
+    /// data is sent, so that admins cannot keep access after the impersonation ends.
+    pub fn not_impersonated(self) -> Result<Self, ServerError> {
+        match self.impersonator {
+            Some(_) => Err(ServerError::ImpersonationNotAllowed),
+            None => Ok(self),
+        }
+    }
+}
+
+/// An [`AuthenticatedUser`] with at least the [`UserRole::Moderator`] role.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
+pub struct AuthenticatedModerator {
+    user: AuthenticatedUser,
+}
+
+impl AuthenticatedModerator {
+    #[must_use]
+    pub fn user_id(self) -> Id<UserMarker> {
+        self.user.user_id()
+    }
+}
+
+/// An [`AuthenticatedUser`] with the [`UserRole::Admin`] role.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
+pub struct AuthenticatedAdmin {
+    user: AuthenticatedUser,
+}
+
+impl AuthenticatedAdmin {
+    #[must_use]
+    pub fn user_id(self) -> Id<UserMarker> {
+        self.user.user_id()
+    }
 }
 
 #[derive(Debug, Error)]
@@ -41,6 +98,12 @@ pub enum AuthenticationRejection {
     AuthTokenHash(#[from] AuthTokenHashError),
     #[error("Provided token was invalid")]
     InvalidToken,
+    #[error("The auth token belongs to another tenant")]
+    OtherTenant,
+    #[error("The user does not have the required role {required}")]
+    InsufficientRole { required: UserRole },
+    #[error("Impersonation tokens cannot be used for routes requiring the role {required}")]
+    Impersonating { required: UserRole },
 }
 
 impl AuthenticationRejection {
@@ -55,50 +118,383 @@ impl AuthenticationRejection {
             }
             AuthenticationRejection::AuthTokenFormat(_) => StatusCode::BAD_REQUEST,
             AuthenticationRejection::AuthTokenUserMismatch
-            | AuthenticationRejection::InvalidToken => StatusCode::UNAUTHORIZED,
+            | AuthenticationRejection::InvalidToken
+            | AuthenticationRejection::OtherTenant => StatusCode::UNAUTHORIZED,
             AuthenticationRejection::AuthTokenHash(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AuthenticationRejection::InsufficientRole { .. }
+            | AuthenticationRejection::Impersonating { .. } => StatusCode::FORBIDDEN,
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AuthenticationRejection::InvalidAuthorizationHeader(rejection) => {
+                if rejection.is_missing() {
+                    ErrorCode::AuthenticationRequired
+                } else {
+                    ErrorCode::InvalidAuthorizationHeader
+                }
+            }
+            AuthenticationRejection::AuthTokenFormat(_)
+            | AuthenticationRejection::AuthTokenUserMismatch
+            | AuthenticationRejection::InvalidToken
+            | AuthenticationRejection::OtherTenant => ErrorCode::InvalidToken,
+            AuthenticationRejection::AuthTokenHash(_) => ErrorCode::InternalError,
+            AuthenticationRejection::InsufficientRole { .. }
+            | AuthenticationRejection::Impersonating { .. } => ErrorCode::InsufficientRole,
         }
     }
 }
 
 impl<S> FromRequestParts<S> for AuthenticatedUser
 where
-    Arc<DbClient>: FromRef<S>,
+    Arc<dyn Store>: FromRef<S>,
+    Arc<Analytics>: FromRef<S>,
     S: Send + Sync,
 {
     type Rejection = ServerError;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let request_token: AuthToken = AuthorizationHeader::from_request_parts(parts, state)
+        let header = AuthorizationHeader::from_request_parts(parts, state)
             .await
-            .map_err(AuthenticationRejection::InvalidAuthorizationHeader)?
-            .token()
-            .parse()
-            .map_err(AuthenticationRejection::from)?;
+            .map_err(AuthenticationRejection::InvalidAuthorizationHeader)?;
+        let CurrentTenant(tenant) = CurrentTenant::from_request_parts(parts, state).await?;
+        let store = Arc::<dyn Store>::from_ref(state);
+        let authentication = authenticate(&*store, header.token(), Some(&tenant.id)).await?;
+        let id = authentication.user;
+        let impersonator = authentication.impersonator;
+
+        logging::record_user(id);
+        match impersonator {
+            Some(impersonator) => {
+                logging::record_impersonator(impersonator);
+                info!(
+                    user_id = %id,
+                    impersonator_id = %impersonator,
+                    method = %parts.method,
+                    uri = %parts.uri,
+                    "Request with impersonation token"
+                );
+            }
+            None => Arc::<Analytics>::from_ref(state).record_active(id),
+        }
+
+        if !parts.method.is_safe()
+            && !is_policy_exempt(parts)
+            && store.has_pending_policies(id).await?
+        {
+            return Err(ServerError::PolicyAcceptanceRequired);
+        }
 
-        let token_hash = request_token
-            .hash()
-            .map_err(AuthenticationRejection::from)?;
+        Ok(Self { id, impersonator })
+    }
+}
+
+fn is_policy_exempt(parts: &Parts) -> bool {
+    parts
+        .extensions
+        .get::<MatchedPath>()
+        .is_some_and(|matched_path| {
+            let path = matched_path.as_str();
+            let path = path.strip_prefix(CURRENT_VERSION).unwrap_or(path);
+            POLICY_EXEMPT_ROUTES.contains(&path)
+        })
+}
+
+/// Returns the authentication of the encoded auth token, if it is valid.
+/// Tokens are only valid for the `tenant` of their user, if one is given.
+pub async fn authenticate(
+    store: &dyn Store,
+    token: &str,
+    tenant: Option<&TenantId>,
+) -> Result<Authentication, ServerError> {
+    let request_token: AuthToken = token.parse().map_err(AuthenticationRejection::from)?;
 
-        let authentication = Arc::<DbClient>::from_ref(state)
-            .fetch_auth(&token_hash)
-            .await?
-            .ok_or(AuthenticationRejection::InvalidToken)?;
+    let token_hash = request_token
+        .hash()
+        .map_err(AuthenticationRejection::from)?;
 
-        assert_eq!(authentication.token_hash, token_hash);
+    let authentication = store
+        .fetch_auth(&token_hash)
+        .await?
+        .ok_or(AuthenticationRejection::InvalidToken)?;
 
-        if authentication.user != request_token.user_id {
-            return Err(AuthenticationRejection::AuthTokenUserMismatch.into());
+    assert_eq!(authentication.token_hash, token_hash);
+
+    if authentication.user != request_token.user_id {
+        return Err(AuthenticationRejection::AuthTokenUserMismatch.into());
+    }
+
+    if tenant.is_some_and(|tenant| *tenant != authentication.tenant) {
+        return Err(AuthenticationRejection::OtherTenant.into());
+    }
+
+    if let Some(expires_after) = authentication.expires_after
+        && authentication.created_at + expires_after.get() < UtcDateTime::now()
+    {
+        return Err(AuthenticationRejection::InvalidToken.into());
+    }
+
+    Ok(authentication)
+}
+
+/// Anonymous requests are allowed, but if credentials are given, they must be valid.
+impl<S> axum::extract::OptionalFromRequestParts<S> for AuthenticatedUser
+where
+    Arc<dyn Store>: FromRef<S>,
+    Arc<Analytics>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ServerError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &S,
+    ) -> Result<Option<Self>, Self::Rejection> {
+        if !parts.headers.contains_key(AUTHORIZATION) {
+            return Ok(None);
         }
 
-        if let Some(expires_after) = authentication.expires_after
-            && authentication.created_at + expires_after.get() < UtcDateTime::now()
-        {
-            return Err(AuthenticationRejection::InvalidToken.into());
+        <Self as FromRequestParts<S>>::from_request_parts(parts, state)
+            .await
+            .map(Some)
+    }
+}
+
+impl<S> FromRequestParts<S> for AuthenticatedModerator
+where
+    Arc<dyn Store>: FromRef<S>,
+    Arc<Analytics>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ServerError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let user = authenticate_with_role(parts, state, UserRole::Moderator).await?;
+
+        Ok(Self { user })
+    }
+}
+
+impl<S> FromRequestParts<S> for AuthenticatedAdmin
+where
+    Arc<dyn Store>: FromRef<S>,
+    Arc<Analytics>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ServerError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let user = authenticate_with_role(parts, state, UserRole::Admin).await?;
+
+        Ok(Self { user })
+    }
+}
+
+/// Authenticates the user and checks that they have at least the `required` role.
+/// Admins cannot gain roles by impersonating, so impersonation tokens never pass.
+async fn authenticate_with_role<S>(
+    parts: &mut Parts,
+    state: &S,
+    required: UserRole,
+) -> Result<AuthenticatedUser, ServerError>
+where
+    Arc<dyn Store>: FromRef<S>,
+    Arc<Analytics>: FromRef<S>,
+    S: Send + Sync,
+{
+    let user = <AuthenticatedUser as FromRequestParts<S>>::from_request_parts(parts, state).await?;
+    if user.impersonator.is_some() {
+        return Err(AuthenticationRejection::Impersonating { required }.into());
+    }
+
+    let role = Arc::<dyn Store>::from_ref(state)
+        .fetch_user_role(user.user_id())
+        .await?
+        .ok_or(AuthenticationRejection::InvalidToken)?;
+
+    if role < required {
+        return Err(AuthenticationRejection::InsufficientRole { required }.into());
+    }
+
+    Ok(user)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::{
+        ServerError,
+        analytics::Analytics,
+        auth::{AuthenticatedUser, AuthenticationRejection},
+        tenant::{CurrentTenant, Tenant},
+    };
+    use axum::{
+        extract::{FromRef, FromRequestParts},
+        http::{Request, header::AUTHORIZATION, request::Parts},
+    };
+    use std::sync::Arc;
+    use stellwerk_common::{
+        model::{
+            Id,
+            auth::{AuthToken, Authentication},
+            instance::InstanceInfo,
+            tenant::TenantId,
+            user::{CreateUser, UserHandle, UserMarker},
+        },
+        util::PositiveDuration,
+    };
+    use stellwerk_db::{memory::MemoryStore, store::Store};
+    use time::{Duration, UtcDateTime};
+
+    #[derive(Clone, FromRef)]
+    struct TestState {
+        store: Arc<dyn Store>,
+        analytics: Arc<Analytics>,
+    }
+
+    struct Fixture {
+        state: TestState,
+        memory: Arc<MemoryStore>,
+        user: Id<UserMarker>,
+    }
+
+    impl Fixture {
+        async fn new() -> Self {
+            let memory = Arc::new(MemoryStore::new());
+            let user = memory
+                .create_user(
+                    &TenantId::default(),
+                    &CreateUser {
+                        handle: UserHandle::new("alice".to_owned()).unwrap(),
+                    },
+                )
+                .await
+                .unwrap();
+            let state = TestState {
+                store: memory.clone(),
+                analytics: Arc::new(Analytics::new()),
+            };
+
+            Self {
+                state,
+                memory,
+                user,
+            }
         }
 
-        Ok(Self {
-            id: authentication.user,
-        })
+        /// Returns the encoded token.
+        fn insert_token(
+            &self,
+            tenant: TenantId,
+            created_at: UtcDateTime,
+            impersonator: Option<Id<UserMarker>>,
+        ) -> String {
+            let token = AuthToken::generate_random(self.user);
+            self.memory.insert_auth(Authentication {
+                user: self.user,
+                tenant,
+                token_hash: token.hash().unwrap(),
+                created_at,
+                expires_after: PositiveDuration::new(Duration::hours(1)),
+                impersonator,
+            });
+
+            token.as_token_str()
+        }
+
+        async fn authenticate(
+            &self,
+            token: Option<&str>,
+            tenant: TenantId,
+        ) -> Result<AuthenticatedUser, ServerError> {
+            let mut request = Request::get("/posts").body(()).unwrap();
+            if let Some(token) = token {
+                request
+                    .headers_mut()
+                    .insert(AUTHORIZATION, format!("Bearer {token}").try_into().unwrap());
+            }
+            request
+                .extensions_mut()
+                .insert(CurrentTenant(Arc::new(Tenant {
+                    id: tenant,
+                    info: InstanceInfo::default(),
+                })));
+            let (mut parts, ()): (Parts, ()) = request.into_parts();
+
+            AuthenticatedUser::from_request_parts(&mut parts, &self.state).await
+        }
+    }
+
+    #[tokio::test]
+    async fn accepts_tokens_of_the_tenant() {
+        let fixture = Fixture::new().await;
+        let token = fixture.insert_token(TenantId::default(), UtcDateTime::now(), None);
+
+        let user = fixture
+            .authenticate(Some(&token), TenantId::default())
+            .await
+            .unwrap();
+        assert_eq!(user.user_id(), fixture.user);
+        assert!(user.not_impersonated().is_ok());
+    }
+
+    #[tokio::test]
+    async fn impersonation_tokens_cannot_change_credentials() {
+        let fixture = Fixture::new().await;
+        let admin = Id::from(fixture.user.snowflake().get() + 1);
+        let token = fixture.insert_token(TenantId::default(), UtcDateTime::now(), Some(admin));
+
+        let user = fixture
+            .authenticate(Some(&token), TenantId::default())
+            .await
+            .unwrap();
+        assert_eq!(user.user_id(), fixture.user);
+        assert_eq!(user.impersonator(), Some(admin));
+        assert!(matches!(
+            user.not_impersonated(),
+            Err(ServerError::ImpersonationNotAllowed)
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_tokens_of_other_tenants() {
+        let fixture = Fixture::new().await;
+        let token = fixture.insert_token(TenantId::default(), UtcDateTime::now(), None);
+        let other_tenant = TenantId::new("other".to_owned()).unwrap();
+
+        assert!(matches!(
+            fixture.authenticate(Some(&token), other_tenant).await,
+            Err(ServerError::AuthenticationRejection(
+                AuthenticationRejection::OtherTenant
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_missing_unknown_and_expired_tokens() {
+        let fixture = Fixture::new().await;
+        let unknown = AuthToken::generate_random(fixture.user).as_token_str();
+        let expired = fixture.insert_token(
+            TenantId::default(),
+            UtcDateTime::now() - Duration::hours(2),
+            None,
+        );
+
+        assert!(matches!(
+            fixture.authenticate(None, TenantId::default()).await,
+            Err(ServerError::AuthenticationRejection(
+                AuthenticationRejection::InvalidAuthorizationHeader(_)
+            ))
+        ));
+        for token in [unknown, expired] {
+            assert!(matches!(
+                fixture
+                    .authenticate(Some(&token), TenantId::default())
+                    .await,
+                Err(ServerError::AuthenticationRejection(
+                    AuthenticationRejection::InvalidToken
+                ))
+            ));
+        }
     }
 }
@@ -0,0 +1,70 @@
+//! Request body size limits, with a larger limit for [`RouteGroup::Media`] routes.
+
+use crate::server::{
+    ServerError,
+    route_group::{RouteGroup, RouteGroups},
+};
+use axum::{
+    body::Body,
+    extract::{MatchedPath, Request, State},
+    http::header::CONTENT_LENGTH,
+    middleware::Next,
+    response::Response,
+};
+use http_body_util::Limited;
+use std::sync::Arc;
+
+#[derive(Debug)]
+pub struct BodyLimits {
+    /// In bytes.
+    default: usize,
+    /// In bytes.
+    media: usize,
+    route_groups: RouteGroups,
+}
+
+impl BodyLimits {
+    #[must_use]
+    pub fn new(default: usize, media: usize) -> Self {
+        Self {
+            default,
+            media,
+            route_groups: RouteGroups::new(),
+        }
+    }
+}
+
+/// Rejects requests with bodies larger than the limit of the route's group.
+/// Must be a route layer, so that the matched route is known.
+///
+/// Bodies that announce their length are rejected right away.
+/// Others are cut off once they exceed the limit, which makes extracting them fail.
+pub async fn limit(
+    State(limits): State<Arc<BodyLimits>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ServerError> {
+    let Some(matched_path) = request.extensions().get::<MatchedPath>() else {
+        return Ok(next.run(request).await);
+    };
+
+    let limit = match limits
+        .route_groups
+        .get(request.method(), matched_path.as_str())
+    {
+        RouteGroup::Media => limits.media,
+        RouteGroup::Auth | RouteGroup::Write | RouteGroup::Read => limits.default,
+    };
+
+    let content_length = request
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if content_length.is_some_and(|content_length| content_length > limit) {
+        return Err(ServerError::PayloadTooLarge(limit));
+    }
+
+    let request = request.map(|body| Body::new(Limited::new(body, limit)));
+    Ok(next.run(request).await)
+}
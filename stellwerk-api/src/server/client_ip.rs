@@ -0,0 +1,162 @@
+//! The IP address of the client, for rate limiting and logs.
+//!
+//! Behind reverse proxies, the peer of the connection is the proxy, not the client.
+//! Proxies pass the client on in the `Forwarded` or `X-Forwarded-For` header, which anyone can
+//! send though. So these headers are only read if the peer is a configured trusted proxy.
+//! The chain of addresses is walked from the closest hop, skipping trusted proxies,
+//! and the first address that is not trusted is the client.
+
+use crate::server::ServerError;
+use axum::{
+    extract::{ConnectInfo, FromRequestParts, Request, State},
+    http::{HeaderMap, HeaderName, header::FORWARDED, request::Parts},
+    middleware::Next,
+    response::Response,
+};
+use ipnet::IpNet;
+use serde::{Deserialize, Deserializer, de::Error};
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+use tracing::Span;
+
+pub const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+
+/// The resolved IP address of the client, see the [module docs](self).
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct ClientIp(pub IpAddr);
+
+/// Networks of reverse proxies whose forwarded headers are trusted.
+/// Single addresses are networks with the longest prefix.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub struct TrustedProxies(Vec<IpNet>);
+
+impl TrustedProxies {
+    fn contains(&self, address: IpAddr) -> bool {
+        self.0.iter().any(|network| network.contains(&address))
+    }
+
+    /// The client of a request from `peer`, see the [module docs](self).
+    #[must_use]
+    pub fn resolve(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let peer = peer.to_canonical();
+        if !self.contains(peer) {
+            return peer;
+        }
+
+        // Forwarded is preferred, a proxy sending it may pass on X-Forwarded-For unchanged.
+        let hops = if headers.contains_key(FORWARDED) {
+            forwarded_hops(headers)
+        } else {
+            x_forwarded_for_hops(headers)
+        };
+
+        let mut client = peer;
+        for hop in hops.iter().rev() {
+            // Obfuscated or unknown hops cannot be followed further.
+            let Some(hop) = hop.as_ref().map(IpAddr::to_canonical) else {
+                break;
+            };
+            client = hop;
+            if !self.contains(hop) {
+                break;
+            }
+        }
+
+        client
+    }
+}
+
+impl<'de> Deserialize<'de> for TrustedProxies {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|network| {
+                let network = network.trim();
+                network
+                    .parse()
+                    .or_else(|_| network.parse::<IpAddr>().map(IpNet::from))
+                    .map_err(|_| {
+                        D::Error::custom(format!(
+                            "Expected an IP address or a network like `10.0.0.0/8`, but got `{network}`"
+                        ))
+                    })
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// The `for` parameters of all `Forwarded` elements, in order.
+fn forwarded_hops(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    headers
+        .get_all(FORWARDED)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|element| {
+            element.split(';').find_map(|pair| {
+                let (name, value) = pair.split_once('=')?;
+                name.trim()
+                    .eq_ignore_ascii_case("for")
+                    .then(|| parse_node(value.trim().trim_matches('"')))
+            })
+        })
+        .collect()
+}
+
+/// All addresses of all `X-Forwarded-For` headers, in order.
+fn x_forwarded_for_hops(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|node| parse_node(node.trim()))
+        .collect()
+}
+
+/// Parses `192.0.2.1`, `192.0.2.1:4711`, `2001:db8::1` or `[2001:db8::1]:4711`.
+/// Returns `None` for anything else, like `unknown` or obfuscated identifiers.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    if let Ok(address) = node.parse() {
+        return Some(address);
+    }
+    if let Ok(address) = node.parse::<SocketAddr>() {
+        return Some(address.ip());
+    }
+
+    node.strip_prefix('[')?.strip_suffix(']')?.parse().ok()
+}
+
+/// Resolves the [`ClientIp`] and records it into the request span.
+/// Must be wrapped by the trace layer, see [`logging::make_span`](crate::server::logging::make_span).
+pub async fn resolve(
+    State(trusted_proxies): State<Arc<TrustedProxies>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if let Some(&ConnectInfo(peer)) = request.extensions().get::<ConnectInfo<SocketAddr>>() {
+        let client = trusted_proxies.resolve(peer.ip(), request.headers());
+        Span::current().record("client_ip", client.to_string());
+        request.extensions_mut().insert(ClientIp(client));
+    }
+
+    next.run(request).await
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = ServerError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Self>()
+            .copied()
+            .ok_or(ServerError::ClientIpUnknown)
+    }
+}
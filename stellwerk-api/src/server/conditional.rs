@@ -0,0 +1,111 @@
+//! Conditional requests with `ETag` and `If-None-Match`, see
+//! <https://www.rfc-editor.org/rfc/rfc9110#name-conditional-requests>.
+//!
+//! Entity tags are derived from ids and version counters stored with the resources,
+//! so whether a client's copy is current can be checked without building the response.
+
+use crate::server::fields::Fields;
+use axum::{
+    extract::FromRequestParts,
+    http::{
+        HeaderValue, StatusCode,
+        header::{ETAG, IF_NONE_MATCH},
+        request::Parts,
+    },
+    response::{IntoResponse, IntoResponseParts, Response, ResponseParts},
+};
+use std::convert::Infallible;
+use stellwerk_common::model::{
+    Id,
+    post::{PostMarker, PostVersion},
+    user::UserMarker,
+};
+
+/// A strong entity tag identifying one representation of a resource.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct ETag(String);
+
+impl ETag {
+    /// `activity` distinguishes the ActivityPub representation from the regular JSON.
+    #[must_use]
+    pub fn for_post(id: Id<PostMarker>, version: PostVersion, activity: bool) -> Self {
+        Self::new(
+            &format!("post-{id}-{}-{}", version.post, version.author),
+            activity,
+        )
+    }
+
+    /// `activity` distinguishes the ActivityPub representation from the regular JSON.
+    #[must_use]
+    pub fn for_user(id: Id<UserMarker>, version: u64, activity: bool) -> Self {
+        Self::new(&format!("user-{id}-{version}"), activity)
+    }
+
+    /// Distinguishes sparse representations, see [`Fields`].
+    #[must_use]
+    pub fn with_fields(self, fields: &Fields) -> Self {
+        if fields.is_all() {
+            return self;
+        }
+
+        let tag = self.0.trim_end_matches('"');
+        Self(format!("{tag}-fields-{fields}\""))
+    }
+
+    fn new(tag: &str, activity: bool) -> Self {
+        if activity {
+            Self(format!("\"{tag}-activity\""))
+        } else {
+            Self(format!("\"{tag}\""))
+        }
+    }
+}
+
+impl IntoResponseParts for ETag {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        let value =
+            HeaderValue::try_from(self.0).expect("ETag consists of ascii characters and quotes");
+        res.headers_mut().insert(ETAG, value);
+        Ok(res)
+    }
+}
+
+/// The `If-None-Match` header of a request, if any.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub struct IfNoneMatch(Option<HeaderValue>);
+
+impl IfNoneMatch {
+    /// Whether the client already has the representation tagged with `etag`.
+    /// Uses the weak comparison, as required for `If-None-Match`.
+    #[must_use]
+    pub fn matches(&self, etag: &ETag) -> bool {
+        let Some(value) = self.0.as_ref().and_then(|value| value.to_str().ok()) else {
+            return false;
+        };
+
+        value.split(',').map(str::trim).any(|candidate| {
+            candidate == "*" || candidate.strip_prefix("W/").unwrap_or(candidate) == etag.0
+        })
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for IfNoneMatch {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self(parts.headers.get(IF_NONE_MATCH).cloned()))
+    }
+}
+
+/// The response to a request whose `If-None-Match` header matched the current [`ETag`].
+/// Otherwise, the [`ETag`] is added to the regular response.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct NotModified(pub ETag);
+
+impl IntoResponse for NotModified {
+    fn into_response(self) -> Response {
+        (StatusCode::NOT_MODIFIED, self.0, ()).into_response()
+    }
+}
@@ -0,0 +1,218 @@
+//! Filters that can reject posts before they are created, configured per instance.
+//!
+//! [`ContentFilters`] runs the filters in order and stops at the first rejection,
+//! which is answered with [`ServerError::ContentRejected`](crate::server::ServerError::ContentRejected)
+//! naming the category of the rule. Posts mirrored from other servers are not filtered.
+
+use async_trait::async_trait;
+use regex::RegexSet;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, fmt::Debug, time::Duration};
+use stellwerk_common::{
+    model::{Id, post::CreatePost, user::UserMarker},
+    text,
+};
+use tracing::warn;
+
+/// The rule of a filter that matched, for the author to see.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct FilterRejection {
+    /// Like `blocked_pattern`, or whatever the webhook answered.
+    pub category: Cow<'static, str>,
+}
+
+impl FilterRejection {
+    #[must_use]
+    pub const fn new(category: &'static str) -> Self {
+        Self {
+            category: Cow::Borrowed(category),
+        }
+    }
+}
+
+/// A check of posts before they are created.
+///
+/// Filters decide on their own how to handle their failures, since [`ContentFilters`] has
+/// no way to tell whether the post should be let through.
+#[async_trait]
+pub trait ContentFilter: Debug + Send + Sync {
+    async fn check(&self, post: &CreatePost) -> Result<(), FilterRejection>;
+}
+
+/// The filters of the instance, in the order they run.
+#[derive(Debug, Default)]
+pub struct ContentFilters {
+    filters: Vec<Box<dyn ContentFilter>>,
+}
+
+impl ContentFilters {
+    /// Lets every post through.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with(mut self, filter: impl ContentFilter + 'static) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// Returns the rejection of the first filter that rejects the post.
+    pub async fn check(&self, post: &CreatePost) -> Result<(), FilterRejection> {
+        for filter in &self.filters {
+            filter.check(post).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Rejects content matching any of a set of regular expressions,
+/// with the category `blocked_pattern`.
+#[derive(Clone, Debug)]
+pub struct PatternFilter {
+    patterns: RegexSet,
+}
+
+impl PatternFilter {
+    pub fn new(patterns: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Self, regex::Error> {
+        Ok(Self {
+            patterns: RegexSet::new(patterns)?,
+        })
+    }
+}
+
+#[async_trait]
+impl ContentFilter for PatternFilter {
+    async fn check(&self, post: &CreatePost) -> Result<(), FilterRejection> {
+        if self.patterns.is_match(post.content.get()) {
+            return Err(FilterRejection::new("blocked_pattern"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Rejects content linking to any of a set of domains or their subdomains,
+/// with the category `denied_link_domain`.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct LinkDomainFilter {
+    /// Lowercase, without leading or trailing dots.
+    domains: Vec<String>,
+}
+
+impl LinkDomainFilter {
+    pub fn new(domains: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        Self {
+            domains: domains
+                .into_iter()
+                .map(|domain| domain.as_ref().trim().trim_matches('.').to_lowercase())
+                .filter(|domain| !domain.is_empty())
+                .collect(),
+        }
+    }
+
+    fn is_denied(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_lowercase();
+        self.domains.iter().any(|domain| {
+            host.strip_suffix(domain.as_str())
+                .is_some_and(|subdomain| subdomain.is_empty() || subdomain.ends_with('.'))
+        })
+    }
+}
+
+/// The host of an `http` or `https` URL, without user info and port.
+fn link_host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = &rest[..rest.find(['/', '?', '#']).unwrap_or(rest.len())];
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    host.rsplit_once(':')
+        .filter(|(_, port)| port.bytes().all(|byte| byte.is_ascii_digit()))
+        .map_or(host, |(host, _)| host)
+}
+
+#[async_trait]
+impl ContentFilter for LinkDomainFilter {
+    async fn check(&self, post: &CreatePost) -> Result<(), FilterRejection> {
+        if text::links(post.content.get())
+            .into_iter()
+            .any(|url| self.is_denied(link_host(url)))
+        {
+            return Err(FilterRejection::new("denied_link_domain"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Asks an external service about every post.
+///
+/// The service receives a [`WebhookRequest`] and answers with a [`WebhookVerdict`].
+/// Posts are let through if it fails or does not answer in time,
+/// so that posting does not depend on it.
+#[derive(Clone, Debug)]
+pub struct WebhookFilter {
+    http: reqwest::Client,
+    url: String,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct WebhookRequest<'a> {
+    pub author: Id<UserMarker>,
+    pub content: &'a str,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct WebhookVerdict {
+    pub allowed: bool,
+    /// Defaults to `webhook` for rejections.
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+impl WebhookFilter {
+    pub fn new(url: String, timeout: Duration) -> Result<Self, reqwest::Error> {
+        let http = reqwest::Client::builder()
+            .user_agent(concat!("stellwerk/", env!("CARGO_PKG_VERSION")))
+            .timeout(timeout)
+            .build()?;
+
+        Ok(Self { http, url })
+    }
+
+    async fn verdict(&self, post: &CreatePost) -> Result<WebhookVerdict, reqwest::Error> {
+        self.http
+            .post(&self.url)
+            .json(&WebhookRequest {
+                author: post.author,
+                content: post.content.get(),
+            })
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    }
+}
+
+#[async_trait]
+impl ContentFilter for WebhookFilter {
+    async fn check(&self, post: &CreatePost) -> Result<(), FilterRejection> {
+        match self.verdict(post).await {
+            Ok(WebhookVerdict { allowed: true, .. }) => Ok(()),
+            Ok(WebhookVerdict {
+                allowed: false,
+                category,
+            }) => Err(FilterRejection {
+                category: category.map_or(Cow::Borrowed("webhook"), Cow::Owned),
+            }),
+            Err(error) => {
+                warn!(%error, url = self.url, "Content filter webhook failed, letting the post through");
+                Ok(())
+            }
+        }
+    }
+}
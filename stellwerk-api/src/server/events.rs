@@ -0,0 +1,151 @@
+use std::sync::Arc;
+use stellwerk_common::{
+    event::{self as db_event, NotificationCreated, PostCreated, PostDeleted},
+    model::{
+        Id,
+        notification::Notification,
+        post::{Post, PostMarker},
+        tenant::TenantId,
+        user::UserMarker,
+    },
+};
+use stellwerk_db::{
+    client::{DbClient, DbError},
+    events::DbEventListener,
+};
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use tracing::error;
+
+/// Number of events a slow subscriber can fall behind before it starts missing events.
+const EVENT_HUB_CAPACITY: usize = 1024;
+/// Delay before listening again after the database connection failed.
+const LISTEN_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Something that happened on this instance that streaming clients are told about.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Event {
+    PostCreated {
+        post: Post,
+        /// Of the author, since the public stream of a tenant only has its posts.
+        tenant: TenantId,
+        /// The post is only in the public stream of its author then.
+        author_shadowbanned: bool,
+    },
+    PostDeleted {
+        post: Id<PostMarker>,
+        author: Id<UserMarker>,
+    },
+    NotificationCreated {
+        user: Id<UserMarker>,
+        notification: Notification,
+    },
+}
+
+/// Distributes [`Event`]s to all streaming connections.
+#[derive(Debug)]
+pub struct EventHub {
+    sender: broadcast::Sender<Event>,
+}
+
+impl EventHub {
+    #[must_use]
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_HUB_CAPACITY);
+        Self { sender }
+    }
+
+    /// Whether anyone is listening, so producers can skip building events nobody receives.
+    #[must_use]
+    pub fn has_subscribers(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+
+    pub fn publish(&self, event: Event) {
+        // Sending only fails if there are no subscribers, in which case the event is irrelevant.
+        let _ = self.sender.send(event);
+    }
+
+    #[must_use]
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Feeds [`db_event::Event`]s from all instances into the local `events` hub until cancelled.
+pub async fn db_event_bridge(
+    db: Arc<DbClient>,
+    events: Arc<EventHub>,
+    cancellation: CancellationToken,
+) {
+    // Events are notified on commit, when replicas may not have the changes yet.
+    let db = db.read_your_writes();
+    let mut listener: Option<DbEventListener> = None;
+
+    loop {
+        let result = cancellation
+            .run_until_cancelled(bridge_next_event(&db, &events, &mut listener))
+            .await;
+
+        match result {
+            None => return,
+            Some(Ok(())) => {}
+            Some(Err(error)) => {
+                error!(%error, "Error bridging database events");
+                if cancellation
+                    .run_until_cancelled(tokio::time::sleep(LISTEN_RETRY_DELAY))
+                    .await
+                    .is_none()
+                {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+async fn bridge_next_event(
+    db: &DbClient,
+    events: &EventHub,
+    listener: &mut Option<DbEventListener>,
+) -> Result<(), DbError> {
+    let listener = match listener {
+        Some(listener) => listener,
+        None => listener.insert(db.listen().await?),
+    };
+
+    let event = listener.recv().await?;
+    if !events.has_subscribers() {
+        return Ok(());
+    }
+
+    match event {
+        db_event::Event::PostCreated(PostCreated { post, tenant, .. }) => {
+            if let Some(post) = db.fetch_post(post).await? {
+                let author_shadowbanned = db.is_shadowbanned(post.author.id).await?;
+                events.publish(Event::PostCreated {
+                    post,
+                    tenant,
+                    author_shadowbanned,
+                });
+            }
+        }
+        db_event::Event::PostDeleted(PostDeleted { post, author }) => {
+            events.publish(Event::PostDeleted { post, author });
+        }
+        db_event::Event::NotificationCreated(NotificationCreated { user, notification }) => {
+            if let Some(notification) = db.fetch_notification(notification).await? {
+                events.publish(Event::NotificationCreated { user, notification });
+            }
+        }
+        db_event::Event::FollowCreated(_) => {}
+    }
+
+    Ok(())
+}
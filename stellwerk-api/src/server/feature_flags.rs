@@ -0,0 +1,111 @@
+//! Feature flags gating functionality that operators may want to turn off without redeploying,
+//! see [`stellwerk_common::model::feature`].
+//!
+//! The configuration decides the defaults. Flags set through the admin API are stored in the
+//! database and cached here, so that checking a flag does not query the database. Every server
+//! reloads them periodically, so a change reaches all of them within the refresh interval.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, nonpoison::Mutex},
+    time::Duration,
+};
+use stellwerk_common::model::{
+    feature::{FeatureFlag, FeatureFlagState},
+    instance::InstanceFeatures,
+};
+use stellwerk_db::client::DbClient;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info};
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+pub struct FeatureFlags {
+    /// From the configuration.
+    defaults: InstanceFeatures,
+    overrides: Mutex<HashMap<FeatureFlag, bool>>,
+}
+
+impl FeatureFlags {
+    #[must_use]
+    pub fn new(defaults: InstanceFeatures, overrides: HashMap<FeatureFlag, bool>) -> Self {
+        Self {
+            defaults,
+            overrides: Mutex::new(overrides),
+        }
+    }
+
+    #[must_use]
+    pub fn is_enabled(&self, flag: FeatureFlag) -> bool {
+        self.overrides
+            .lock()
+            .get(&flag)
+            .copied()
+            .unwrap_or_else(|| flag.is_enabled_in(&self.defaults))
+    }
+
+    /// The features as they currently are, for the instance information.
+    #[must_use]
+    pub fn features(&self) -> InstanceFeatures {
+        let overrides = self.overrides.lock();
+        let mut features = self.defaults;
+        for (flag, enabled) in overrides.iter() {
+            flag.set_in(&mut features, *enabled);
+        }
+
+        features
+    }
+
+    #[must_use]
+    pub fn state(&self, flag: FeatureFlag) -> FeatureFlagState {
+        let overridden = self.overrides.lock().get(&flag).copied();
+
+        FeatureFlagState {
+            flag,
+            enabled: overridden.unwrap_or_else(|| flag.is_enabled_in(&self.defaults)),
+            overridden: overridden.is_some(),
+        }
+    }
+
+    #[must_use]
+    pub fn states(&self) -> Vec<FeatureFlagState> {
+        FeatureFlag::ALL.map(|flag| self.state(flag)).to_vec()
+    }
+
+    /// Overrides the default of the flag, or resets it to the default if `enabled` is `None`.
+    /// Only changes this server, the caller stores it in the database for the others.
+    pub fn set(&self, flag: FeatureFlag, enabled: Option<bool>) {
+        let mut overrides = self.overrides.lock();
+        match enabled {
+            Some(enabled) => overrides.insert(flag, enabled),
+            None => overrides.remove(&flag),
+        };
+    }
+
+    fn replace_overrides(&self, overrides: HashMap<FeatureFlag, bool>) {
+        let mut current = self.overrides.lock();
+        if *current != overrides {
+            info!(?overrides, "Feature flags changed");
+            *current = overrides;
+        }
+    }
+}
+
+/// Reloads the flags from the database, to pick up changes made through other servers.
+pub async fn feature_flag_refresh_loop(
+    db: Arc<DbClient>,
+    flags: Arc<FeatureFlags>,
+    cancellation: CancellationToken,
+) {
+    while cancellation
+        .run_until_cancelled(tokio::time::sleep(REFRESH_INTERVAL))
+        .await
+        .is_some()
+    {
+        match db.fetch_feature_flags().await {
+            Ok(overrides) => flags.replace_overrides(overrides),
+            Err(error) => error!(%error, "Error trying to reload feature flags"),
+        }
+    }
+}
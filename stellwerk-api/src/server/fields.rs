@@ -0,0 +1,154 @@
+//! Sparse fieldsets, so that clients only receive the fields they need.
+//!
+//! The `fields` query parameter lists the fields to keep, separated by commas.
+//! Fields of nested objects are selected with dots, like `id,author.handle`.
+//! Selections apply to every element of arrays, and unknown fields are ignored,
+//! since fields skipped for being empty cannot be told apart from them.
+//!
+//! Routes opt in by extracting [`Fields`] and responding with [`Sparse`].
+
+use crate::server::{ServerError, query::Query};
+use axum::{extract::FromRequestParts, http::request::Parts};
+use serde::{Deserialize, Serialize, Serializer, ser::Error};
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+};
+
+/// A selection of fields. Empty if all fields are selected.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub struct Fields(BTreeMap<Box<str>, Fields>);
+
+#[derive(Deserialize)]
+struct FieldsQuery {
+    fields: Option<String>,
+}
+
+impl Fields {
+    /// Whether all fields are selected.
+    #[must_use]
+    pub fn is_all(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn parse(list: &str) -> Result<Self, ServerError> {
+        let mut fields = Self::default();
+        for path in list.split(',') {
+            let names: Vec<_> = path.trim().split('.').collect();
+            let valid = names.iter().all(|name| {
+                !name.is_empty()
+                    && name
+                        .bytes()
+                        .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+            });
+            if !valid {
+                return Err(ServerError::InvalidFields(list.to_owned()));
+            }
+
+            fields.insert(&names);
+        }
+
+        Ok(fields)
+    }
+
+    fn insert(&mut self, names: &[&str]) {
+        let Some((name, rest)) = names.split_first() else {
+            return;
+        };
+
+        match self.0.get_mut(*name) {
+            // The whole field is already selected.
+            Some(nested) if nested.is_all() => {}
+            Some(nested) if rest.is_empty() => *nested = Self::default(),
+            Some(nested) => nested.insert(rest),
+            None => {
+                let mut nested = Self::default();
+                nested.insert(rest);
+                self.0.insert((*name).into(), nested);
+            }
+        }
+    }
+
+    /// Removes the fields that are not selected.
+    #[must_use]
+    pub fn prune(&self, value: Value) -> Value {
+        if self.is_all() {
+            return value;
+        }
+
+        match value {
+            Value::Object(object) => Value::Object(
+                object
+                    .into_iter()
+                    .filter_map(|(name, value)| {
+                        let nested = self.0.get(name.as_str())?;
+                        Some((name, nested.prune(value)))
+                    })
+                    .collect(),
+            ),
+            Value::Array(items) => {
+                Value::Array(items.into_iter().map(|item| self.prune(item)).collect())
+            }
+            value => value,
+        }
+    }
+}
+
+/// The selection in the same syntax as the query parameter, in a canonical order.
+/// Consists of letters, digits, `_`, `.` and `,` only.
+impl Display for Fields {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (index, (name, nested)) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_str(",")?;
+            }
+
+            if nested.is_all() {
+                f.write_str(name)?;
+            } else {
+                let nested = nested.to_string();
+                let mut paths = nested.split(',').peekable();
+                while let Some(path) = paths.next() {
+                    write!(f, "{name}.{path}")?;
+                    if paths.peek().is_some() {
+                        f.write_str(",")?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Fields {
+    type Rejection = ServerError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Query(FieldsQuery { fields }) = Query::try_from_uri(&parts.uri)?;
+
+        fields.as_deref().map_or(Ok(Self::default()), Self::parse)
+    }
+}
+
+/// Serializes `value` with only the selected `fields`.
+#[derive(Clone, Debug)]
+pub struct Sparse<T> {
+    pub value: T,
+    pub fields: Fields,
+}
+
+impl<T: Serialize> Serialize for Sparse<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if self.fields.is_all() {
+            return self.value.serialize(serializer);
+        }
+
+        let value = serde_json::to_value(&self.value).map_err(S::Error::custom)?;
+        self.fields.prune(value).serialize(serializer)
+    }
+}
@@ -0,0 +1,127 @@
+//! Localized messages of error codes, in the language the client prefers.
+//!
+//! The messages are in `locales/{language}.toml`, compiled into the binary.
+//! The language is negotiated from `Accept-Language` once per request, see [`negotiate`].
+
+use axum::{
+    extract::Request,
+    http::{HeaderValue, header::ACCEPT_LANGUAGE},
+    middleware::Next,
+    response::Response,
+};
+use std::{collections::HashMap, sync::LazyLock};
+use stellwerk_common::model::problem::ErrorCode;
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
+pub enum Language {
+    /// Used if the client accepts none of the supported languages, and for missing messages.
+    #[default]
+    English,
+    German,
+}
+
+type Messages = HashMap<ErrorCode, String>;
+
+static ENGLISH: LazyLock<Messages> = LazyLock::new(|| parse(include_str!("../../locales/en.toml")));
+static GERMAN: LazyLock<Messages> = LazyLock::new(|| parse(include_str!("../../locales/de.toml")));
+
+tokio::task_local! {
+    static LANGUAGE: Language;
+}
+
+fn parse(messages: &str) -> Messages {
+    toml::from_str(messages).expect("Translation files only contain messages of error codes")
+}
+
+impl Language {
+    const ALL: [Language; 2] = [Language::English, Language::German];
+
+    /// The primary language subtag, as in `Accept-Language` and `Content-Language`.
+    #[must_use]
+    pub fn tag(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::German => "de",
+        }
+    }
+
+    fn messages(self) -> &'static Messages {
+        match self {
+            Language::English => &ENGLISH,
+            Language::German => &GERMAN,
+        }
+    }
+
+    /// The message of `code`, in English if there is no translation.
+    #[must_use]
+    pub fn message(self, code: ErrorCode) -> Option<&'static str> {
+        self.messages()
+            .get(&code)
+            .or_else(|| Language::English.messages().get(&code))
+            .map(String::as_str)
+    }
+
+    /// The most preferred supported language of an `Accept-Language` header.
+    /// Regional variants like `de-AT` match their language.
+    #[must_use]
+    pub fn from_accept_language(accept_language: &str) -> Option<Self> {
+        let mut best: Option<(Self, f32)> = None;
+        for range in accept_language.split(',') {
+            let mut params = range.split(';');
+            let tag = params.next().unwrap_or_default().trim();
+            let quality = params
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |quality| quality.trim().parse::<f32>().ok());
+            let Some(quality) = quality.filter(|&quality| quality > 0.0) else {
+                continue;
+            };
+
+            let primary = tag.split('-').next().unwrap_or_default();
+            let language = if primary == "*" {
+                Some(Language::default())
+            } else {
+                Self::ALL
+                    .into_iter()
+                    .find(|language| language.tag().eq_ignore_ascii_case(primary))
+            };
+
+            // Earlier ranges win ties.
+            if let Some(language) = language
+                && best.is_none_or(|(_, best_quality)| quality > best_quality)
+            {
+                best = Some((language, quality));
+            }
+        }
+
+        best.map(|(language, _)| language)
+    }
+}
+
+/// Loads the translation files, so that broken ones fail on startup instead of on the first error.
+pub fn load() {
+    LazyLock::force(&ENGLISH);
+    LazyLock::force(&GERMAN);
+}
+
+/// The language of the request currently being handled, or the default outside of requests.
+pub fn current() -> Language {
+    LANGUAGE.try_with(|&language| language).unwrap_or_default()
+}
+
+/// Negotiates the language of the request, see [`current`].
+pub async fn negotiate(request: Request, next: Next) -> Response {
+    let language = request
+        .headers()
+        .get(ACCEPT_LANGUAGE)
+        .and_then(|accept_language| accept_language.to_str().ok())
+        .and_then(Language::from_accept_language)
+        .unwrap_or_default();
+
+    LANGUAGE.scope(language, next.run(request)).await
+}
+
+impl From<Language> for HeaderValue {
+    fn from(language: Language) -> Self {
+        HeaderValue::from_static(language.tag())
+    }
+}
@@ -0,0 +1,56 @@
+//! Structured fields of the span of every request, for filtering and aggregating logs.
+//!
+//! The request id is known when the span is created. The client IP, the tenant, the route and the
+//! user, with the admin impersonating them if any, are only known after resolving forwarded headers
+//! and the host, routing and authentication, so they are recorded into the span later.
+
+use crate::server::request_id::X_REQUEST_ID;
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use stellwerk_common::model::{Id, user::UserMarker};
+use tracing::{Span, debug_span, field::Empty};
+
+/// The span of the trace layer, like the default one, but with the fields of this module.
+pub fn make_span(request: &Request) -> Span {
+    let request_id = request
+        .headers()
+        .get(X_REQUEST_ID)
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or_default();
+
+    debug_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        version = ?request.version(),
+        request_id,
+        client_ip = Empty,
+        tenant = Empty,
+        route = Empty,
+        user_id = Empty,
+        impersonator_id = Empty,
+    )
+}
+
+/// Records the matched route, like `/v1/posts/{post_id}`, into the request span.
+/// Must be a route layer, so that the matched route is known.
+pub async fn record_route(request: Request, next: Next) -> Response {
+    if let Some(matched_path) = request.extensions().get::<MatchedPath>() {
+        Span::current().record("route", matched_path.as_str());
+    }
+
+    next.run(request).await
+}
+
+/// Records the authenticated user into the request span.
+pub fn record_user(user: Id<UserMarker>) {
+    Span::current().record("user_id", u64::from(user));
+}
+
+/// Records the admin impersonating the authenticated user into the request span.
+pub fn record_impersonator(impersonator: Id<UserMarker>) {
+    Span::current().record("impersonator_id", u64::from(impersonator));
+}
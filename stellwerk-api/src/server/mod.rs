@@ -1,34 +1,175 @@
-use crate::server::auth::AuthenticationRejection;
+use crate::{
+    cdn::Cdn,
+    federation::{Federation, FederationError},
+    oidc::Oidc,
+    search::SearchIndex,
+    server::{
+        analytics::Analytics,
+        auth::AuthenticationRejection,
+        body_limit::BodyLimits,
+        content_filter::{ContentFilters, FilterRejection},
+        events::EventHub,
+        feature_flags::FeatureFlags,
+        query::QueryError,
+        quota::Quotas,
+        rate_limit::RateLimiter,
+        read_only::ReadOnly,
+        response_cache::ResponseCache,
+        route_group::RouteGroup,
+        routes::{
+            admin::AdminError, oidc::OidcError, sitemap::Sitemaps, well_known::WellKnownSettings,
+        },
+        spam::SpamGuard,
+        tenant::Tenants,
+        views::PostViews,
+    },
+    verification::LinkVerifier,
+};
 use axum::{
     Router,
     extract::{
-        FromRef, Request,
-        rejection::{JsonRejection, PathRejection},
+        DefaultBodyLimit, FromRef, Request,
+        rejection::{BytesRejection, JsonRejection, PathRejection},
     },
-    http::{StatusCode, Uri},
+    handler::HandlerWithoutStateExt,
+    http::{
+        HeaderValue, StatusCode, Uri,
+        header::{CONTENT_LANGUAGE, CONTENT_TYPE},
+    },
+    middleware,
     response::{IntoResponse, Response},
 };
-use json::Json;
-use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use stellwerk_common::model::{Id, post::PostMarker, user::UserMarker};
-use stellwerk_db::client::{DbClient, DbError};
+use std::{error::Error as _, fmt::Display, iter, path::Path, sync::Arc};
+use stellwerk_common::model::{
+    Id, ModelValidationError,
+    announcement::AnnouncementMarker,
+    conversation::ConversationMarker,
+    filter::FilterMarker,
+    instance::InstanceInfo,
+    policy::PolicyKind,
+    post::PostMarker,
+    problem::{ErrorCode, FieldError, PROBLEM_JSON, Problem},
+    quota::QuotaUsage,
+    report::ReportMarker,
+    sitemap::SitemapFile,
+    user::{UserHandle, UserMarker},
+    webhook::WebhookMarker,
+};
+use stellwerk_db::{
+    client::{DbClient, DbError},
+    store::Store,
+};
 use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+use tower_http::services::ServeDir;
 use tracing::error;
 
-mod auth;
+mod activitypub;
+pub mod analytics;
+pub mod auth;
+pub mod body_limit;
+pub mod client_ip;
+mod conditional;
+pub mod content_filter;
+pub mod events;
+pub mod feature_flags;
+mod fields;
+pub mod i18n;
 mod json;
-mod routes;
+pub mod logging;
+mod pagination;
+mod query;
+pub mod quota;
+pub mod rate_limit;
+pub mod read_only;
+pub mod request_id;
+pub mod response_cache;
+pub mod route_group;
+pub mod routes;
+pub mod spam;
+pub mod tenant;
+pub mod versioning;
+pub mod views;
 
 pub type ServerRouter = Router<ServerState>;
 
 #[derive(Clone, Debug, FromRef)]
 pub struct ServerState {
     pub db_client: Arc<DbClient>,
+    /// The same client as `db_client`, for handlers that only need users, posts and auth tokens,
+    /// so that they can be tested with a [`MemoryStore`](stellwerk_db::memory::MemoryStore).
+    pub store: Arc<dyn Store>,
+    /// Of the default tenant, for everything that is not assigned a tenant, like federation.
+    pub instance: Arc<InstanceInfo>,
+    pub tenants: Arc<Tenants>,
+    pub events: Arc<EventHub>,
+    pub federation: Arc<Federation>,
+    pub read_only: Arc<ReadOnly>,
+    /// Decide whether the features of `instance` are available, which may change at runtime.
+    pub feature_flags: Arc<FeatureFlags>,
+    pub response_cache: Arc<ResponseCache>,
+    /// Run on posts before they are created.
+    pub content_filters: Arc<ContentFilters>,
+    /// Scores posts and follows of local users.
+    pub spam: Arc<SpamGuard>,
+    /// Daily quotas of posts and follows.
+    pub quotas: Arc<Quotas>,
+    /// The external search engine, or the full-text search of the database.
+    pub search: Arc<dyn SearchIndex>,
+    /// Aggregate usage counters.
+    pub analytics: Arc<Analytics>,
+    /// Queues purges of changed pages from the CDN, if there is one.
+    pub cdn: Arc<Cdn>,
+    /// The identity providers users can log in with.
+    pub oidc: Arc<Oidc>,
+    pub well_known: Arc<WellKnownSettings>,
+    /// Cached sitemaps of the tenants.
+    pub sitemaps: Arc<Sitemaps>,
+    /// Checks the websites of users before they are verified.
+    pub link_verifier: Arc<LinkVerifier>,
+    /// View counts of posts, flushed to the database periodically.
+    pub post_views: Arc<PostViews>,
+    /// Cancelled once shutdown began. Responses that never end by themselves must end with it.
+    pub shutdown: CancellationToken,
 }
 
-pub fn routes() -> ServerRouter {
-    routes::routes().fallback(fallback)
+pub fn routes(
+    rate_limiter: Arc<RateLimiter>,
+    body_limits: Arc<BodyLimits>,
+    read_only: Arc<ReadOnly>,
+    response_cache: Arc<ResponseCache>,
+    static_dir: Option<&Path>,
+) -> ServerRouter {
+    let router = routes::routes()
+        .route_layer(middleware::from_fn_with_state(
+            response_cache,
+            response_cache::cache,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            body_limits,
+            body_limit::limit,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            read_only,
+            read_only::reject_writes,
+        ))
+        .route_layer(middleware::from_fn_with_state(
+            rate_limiter,
+            rate_limit::limit,
+        ))
+        .route_layer(middleware::from_fn(logging::record_route))
+        // Replaced by the body limit layer.
+        .layer(DefaultBodyLimit::disable());
+
+    match static_dir {
+        // Files are only served for paths that are not routes.
+        Some(static_dir) => router.fallback_service(
+            ServeDir::new(static_dir)
+                .call_fallback_on_method_not_allowed(true)
+                .fallback(fallback.into_service()),
+        ),
+        None => router.fallback(fallback),
+    }
 }
 
 pub async fn fallback(request: Request) -> ServerError {
@@ -43,39 +184,313 @@ pub enum ServerError {
     UnknownRoute(Uri),
     #[error("Path rejected: {0}")]
     PathRejection(#[from] PathRejection),
+    #[error("Expected field names separated by commas and nested with dots in `{0}`")]
+    InvalidFields(String),
     #[error("Incoming JSON rejected: {0}")]
     JsonRejection(#[from] JsonRejection),
+    #[error("Query string rejected: {0}")]
+    QueryRejection(QueryError),
+    #[error("Query parameters failed validation: {}", .0.to_string().trim_end())]
+    QueryValidation(garde::Report),
+    #[error("Request body rejected: {0}")]
+    BytesRejection(#[from] BytesRejection),
     #[error("JSON response could not be serialized: {0}")]
     JsonResponse(#[from] serde_json::Error),
+    #[error("Response body could not be read: {0}")]
+    ResponseBody(axum::Error),
     #[error(transparent)]
     AuthenticationRejection(#[from] AuthenticationRejection),
     #[error(transparent)]
     Database(#[from] DbError),
+    #[error(transparent)]
+    Federation(#[from] FederationError),
+    #[error(transparent)]
+    Admin(#[from] AdminError),
+    #[error(transparent)]
+    Oidc(#[from] OidcError),
+    #[error("Validation failed: {0}")]
+    Validation(#[from] ModelValidationError),
     #[error("Post with id {0} was not found.")]
     PostByIdNotFound(Id<PostMarker>),
     #[error("User with id {0} was not found.")]
     UserByIdNotFound(Id<UserMarker>),
+    #[error("User @{} was not found.", .0.get())]
+    UserByHandleNotFound(UserHandle),
+    #[error("Report with id {0} was not found.")]
+    ReportByIdNotFound(Id<ReportMarker>),
+    #[error("Conversation with id {0} was not found.")]
+    ConversationByIdNotFound(Id<ConversationMarker>),
+    #[error("User {0} has not published encryption keys.")]
+    KeysNotFound(Id<UserMarker>),
+    #[error("Filter with id {0} was not found.")]
+    FilterByIdNotFound(Id<FilterMarker>),
+    #[error("Webhook with id {0} was not found.")]
+    WebhookByIdNotFound(Id<WebhookMarker>),
+    #[error("Announcement with id {0} was not found or has expired.")]
+    AnnouncementByIdNotFound(Id<AnnouncementMarker>),
+    #[error("No {0} policy has been published.")]
+    PolicyNotFound(PolicyKind),
+    #[error("No embeddable resource is located at {0}.")]
+    UnknownOEmbedUrl(String),
+    #[error("The oEmbed format {0} is not supported.")]
+    UnsupportedOEmbedFormat(String),
+    #[error("Streams other than the public stream require authentication.")]
+    StreamRequiresAuthentication,
+    #[error("The Last-Event-ID header is not a valid id.")]
+    InvalidLastEventId,
+    #[error("The user is not subscribed to email digests.")]
+    EmailDigestNotFound,
+    #[error("The unsubscribe token does not belong to any email digest.")]
+    UnknownUnsubscribeToken,
+    #[error("The public timeline is disabled on this instance.")]
+    PublicTimelineDisabled,
+    #[error("The Mastodon API is disabled on this instance.")]
+    MastodonApiDisabled,
+    #[error("{0} is not configured on this instance.")]
+    WellKnownNotConfigured(&'static str),
+    #[error("The sitemap file {0} was not found.")]
+    SitemapNotFound(SitemapFile),
+    #[error("The authenticated user is not the author of post {0}.")]
+    NotPostAuthor(Id<PostMarker>),
+    #[error("Report {0} is assigned to another moderator.")]
+    ReportAlreadyClaimed(Id<ReportMarker>),
+    #[error("Report {0} is not about a post.")]
+    ReportWithoutPost(Id<ReportMarker>),
+    #[error("Reports can only be assigned to moderators, which user {0} is not.")]
+    AssigneeNotModerator(Id<UserMarker>),
+    #[error("User with id {0} cannot be suspended.")]
+    CannotSuspend(Id<UserMarker>),
+    #[error("User with id {0} cannot be shadowbanned.")]
+    CannotShadowban(Id<UserMarker>),
+    #[error("The post was rejected by a content filter rule of category {}.", .0.category)]
+    ContentRejected(FilterRejection),
+    #[error("At most {0} posts can be pinned.")]
+    PinnedPostLimitReached(usize),
+    #[error("Only version {current_version} of the {kind} policy can be accepted.")]
+    PolicyVersionOutdated {
+        kind: PolicyKind,
+        current_version: u64,
+    },
+    #[error("An updated policy has to be accepted first.")]
+    PolicyAcceptanceRequired,
+    #[error("Impersonation tokens cannot change credentials of the user.")]
+    ImpersonationNotAllowed,
+    #[error("Only the creator of conversation {0} can remove other members.")]
+    NotConversationCreator(Id<ConversationMarker>),
+    #[error("Conversations can have at most {0} members.")]
+    ConversationMemberLimitReached(usize),
+    #[error("At most {0} one-time prekeys can be stored.")]
+    OneTimePrekeyLimitReached(usize),
+    #[error("Users can have at most {0} webhooks.")]
+    WebhookLimitReached(usize),
+    #[error("Users cannot follow themselves.")]
+    SelfFollow,
+    #[error("Request bodies are limited to {0} bytes.")]
+    PayloadTooLarge(usize),
+    #[error("Too many requests to {0} routes.")]
+    RateLimited(RouteGroup),
+    #[error("Too many posts or follows in a short time, the account is throttled.")]
+    Throttled,
+    #[error("The daily quota of {} {} is used up.", .0.limit, .0.kind)]
+    QuotaExceeded(QuotaUsage),
+    #[error("The client IP address was not resolved.")]
+    ClientIpUnknown,
+    #[error("The tenant of the request was not resolved.")]
+    TenantUnknown,
+    #[error("The server is in read-only mode.")]
+    ReadOnly,
+    #[error("The search engine failed: {0}")]
+    SearchUnavailable(reqwest::Error),
 }
 
 impl ServerError {
     pub fn status(&self) -> StatusCode {
         match self {
             ServerError::AuthenticationRejection(rejection) => rejection.status(),
+            ServerError::Federation(error) => error.status(),
+            ServerError::Admin(error) => error.status(),
+            ServerError::Oidc(error) => error.status(),
+            ServerError::BytesRejection(rejection) => rejection.status(),
             ServerError::UnknownRoute(_)
             | ServerError::PathRejection(_)
             | ServerError::PostByIdNotFound(_)
-            | ServerError::UserByIdNotFound(_) => StatusCode::NOT_FOUND,
-            ServerError::JsonRejection(_) => StatusCode::BAD_REQUEST,
-            ServerError::JsonResponse(_) | ServerError::Database(_) => {
-                StatusCode::INTERNAL_SERVER_ERROR
+            | ServerError::UserByIdNotFound(_)
+            | ServerError::UserByHandleNotFound(_)
+            | ServerError::ReportByIdNotFound(_)
+            | ServerError::ConversationByIdNotFound(_)
+            | ServerError::KeysNotFound(_)
+            | ServerError::FilterByIdNotFound(_)
+            | ServerError::WebhookByIdNotFound(_)
+            | ServerError::AnnouncementByIdNotFound(_)
+            | ServerError::PolicyNotFound(_)
+            | ServerError::UnknownOEmbedUrl(_)
+            | ServerError::EmailDigestNotFound
+            | ServerError::UnknownUnsubscribeToken
+            | ServerError::PublicTimelineDisabled
+            | ServerError::MastodonApiDisabled
+            | ServerError::WellKnownNotConfigured(_)
+            | ServerError::SitemapNotFound(_) => StatusCode::NOT_FOUND,
+            // Bodies cut off by the body limit layer fail to be extracted.
+            ServerError::JsonRejection(rejection)
+                if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE =>
+            {
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
+            ServerError::JsonRejection(_)
+            | ServerError::QueryRejection(_)
+            | ServerError::InvalidFields(_)
+            | ServerError::InvalidLastEventId => StatusCode::BAD_REQUEST,
+            ServerError::StreamRequiresAuthentication => StatusCode::UNAUTHORIZED,
+            ServerError::Validation(_)
+            | ServerError::QueryValidation(_)
+            | ServerError::ReportWithoutPost(_)
+            | ServerError::AssigneeNotModerator(_)
+            | ServerError::ContentRejected(_)
+            | ServerError::SelfFollow => StatusCode::UNPROCESSABLE_ENTITY,
+            ServerError::NotPostAuthor(_)
+            | ServerError::NotConversationCreator(_)
+            | ServerError::CannotSuspend(_)
+            | ServerError::CannotShadowban(_)
+            | ServerError::PolicyAcceptanceRequired
+            | ServerError::ImpersonationNotAllowed => StatusCode::FORBIDDEN,
+            ServerError::PinnedPostLimitReached(_)
+            | ServerError::ConversationMemberLimitReached(_)
+            | ServerError::OneTimePrekeyLimitReached(_)
+            | ServerError::WebhookLimitReached(_)
+            | ServerError::ReportAlreadyClaimed(_)
+            | ServerError::PolicyVersionOutdated { .. }
+            | ServerError::Database(DbError::Conflict { .. }) => StatusCode::CONFLICT,
+            ServerError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ServerError::RateLimited(_)
+            | ServerError::Throttled
+            | ServerError::QuotaExceeded(_) => StatusCode::TOO_MANY_REQUESTS,
+            ServerError::ReadOnly
+            | ServerError::SearchUnavailable(_)
+            | ServerError::Database(
+                DbError::PoolExhausted | DbError::StatementTimeout | DbError::Unavailable,
+            ) => StatusCode::SERVICE_UNAVAILABLE,
+            ServerError::UnsupportedOEmbedFormat(_) => StatusCode::NOT_IMPLEMENTED,
+            ServerError::JsonResponse(_)
+            | ServerError::ResponseBody(_)
+            | ServerError::Database(_)
+            | ServerError::ClientIpUnknown
+            | ServerError::TenantUnknown => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ServerError::AuthenticationRejection(rejection) => rejection.code(),
+            ServerError::Federation(error) => error.code(),
+            ServerError::Admin(error) => error.code(),
+            ServerError::Oidc(error) => error.code(),
+            ServerError::UnknownRoute(_) | ServerError::SitemapNotFound(_) => {
+                ErrorCode::UnknownRoute
             }
+            ServerError::PathRejection(_) => ErrorCode::InvalidPath,
+            ServerError::InvalidFields(_) => ErrorCode::InvalidFields,
+            ServerError::JsonRejection(_) | ServerError::BytesRejection(_)
+                if self.status() == StatusCode::PAYLOAD_TOO_LARGE =>
+            {
+                ErrorCode::PayloadTooLarge
+            }
+            ServerError::JsonRejection(JsonRejection::JsonDataError(_))
+            | ServerError::Validation(_)
+            | ServerError::QueryValidation(_) => ErrorCode::ValidationFailed,
+            ServerError::QueryRejection(_) => ErrorCode::InvalidQuery,
+            ServerError::JsonRejection(_) => ErrorCode::InvalidJson,
+            ServerError::BytesRejection(_) => ErrorCode::InvalidBody,
+            ServerError::Database(
+                DbError::PoolExhausted | DbError::StatementTimeout | DbError::Unavailable,
+            ) => ErrorCode::DatabaseUnavailable,
+            ServerError::Database(DbError::Conflict { .. })
+            | ServerError::PolicyVersionOutdated { .. } => ErrorCode::VersionConflict,
+            ServerError::JsonResponse(_)
+            | ServerError::ResponseBody(_)
+            | ServerError::Database(_)
+            | ServerError::ClientIpUnknown
+            | ServerError::TenantUnknown => ErrorCode::InternalError,
+            ServerError::PostByIdNotFound(_) => ErrorCode::PostNotFound,
+            ServerError::UserByIdNotFound(_) | ServerError::UserByHandleNotFound(_) => {
+                ErrorCode::UserNotFound
+            }
+            ServerError::ReportByIdNotFound(_) => ErrorCode::ReportNotFound,
+            ServerError::ConversationByIdNotFound(_) => ErrorCode::ConversationNotFound,
+            ServerError::KeysNotFound(_) => ErrorCode::KeysNotFound,
+            ServerError::FilterByIdNotFound(_) => ErrorCode::FilterNotFound,
+            ServerError::WebhookByIdNotFound(_) => ErrorCode::WebhookNotFound,
+            ServerError::AnnouncementByIdNotFound(_) => ErrorCode::AnnouncementNotFound,
+            ServerError::PolicyNotFound(_) => ErrorCode::PolicyNotFound,
+            ServerError::UnknownOEmbedUrl(_) => ErrorCode::UnknownOembedUrl,
+            ServerError::UnsupportedOEmbedFormat(_) => ErrorCode::UnsupportedOembedFormat,
+            ServerError::StreamRequiresAuthentication => ErrorCode::AuthenticationRequired,
+            ServerError::InvalidLastEventId => ErrorCode::InvalidLastEventId,
+            ServerError::EmailDigestNotFound => ErrorCode::EmailDigestNotFound,
+            ServerError::UnknownUnsubscribeToken => ErrorCode::UnknownUnsubscribeToken,
+            ServerError::PublicTimelineDisabled => ErrorCode::PublicTimelineDisabled,
+            ServerError::MastodonApiDisabled => ErrorCode::MastodonApiDisabled,
+            ServerError::WellKnownNotConfigured(_) => ErrorCode::NotConfigured,
+            ServerError::NotPostAuthor(_) => ErrorCode::NotPostAuthor,
+            ServerError::ReportAlreadyClaimed(_) => ErrorCode::ReportAlreadyClaimed,
+            ServerError::ReportWithoutPost(_) => ErrorCode::ReportWithoutPost,
+            ServerError::AssigneeNotModerator(_) => ErrorCode::AssigneeNotModerator,
+            ServerError::CannotSuspend(_) => ErrorCode::CannotSuspend,
+            ServerError::CannotShadowban(_) => ErrorCode::CannotShadowban,
+            ServerError::ContentRejected(_) => ErrorCode::ContentRejected,
+            ServerError::PinnedPostLimitReached(_) => ErrorCode::PinnedPostLimitReached,
+            ServerError::NotConversationCreator(_) => ErrorCode::NotConversationCreator,
+            ServerError::PolicyAcceptanceRequired => ErrorCode::PolicyAcceptanceRequired,
+            ServerError::ImpersonationNotAllowed => ErrorCode::ImpersonationNotAllowed,
+            ServerError::ConversationMemberLimitReached(_) => {
+                ErrorCode::ConversationMemberLimitReached
+            }
+            ServerError::OneTimePrekeyLimitReached(_) => ErrorCode::OneTimePrekeyLimitReached,
+            ServerError::WebhookLimitReached(_) => ErrorCode::WebhookLimitReached,
+            ServerError::SelfFollow => ErrorCode::SelfFollow,
+            ServerError::PayloadTooLarge(_) => ErrorCode::PayloadTooLarge,
+            ServerError::RateLimited(_) | ServerError::Throttled => ErrorCode::RateLimited,
+            ServerError::QuotaExceeded(_) => ErrorCode::QuotaExceeded,
+            ServerError::ReadOnly => ErrorCode::ReadOnly,
+            ServerError::SearchUnavailable(_) => ErrorCode::SearchUnavailable,
+        }
+    }
+
+    /// The fields that failed to deserialize or validate,
+    /// if the request body or query was rejected because of them.
+    fn field_errors(&self) -> Vec<FieldError> {
+        match self {
+            ServerError::JsonRejection(JsonRejection::JsonDataError(rejection)) => {
+                // The path error is wrapped by axum, so it is somewhere down the chain of sources.
+                iter::successors(rejection.source(), |&error| error.source())
+                    .find_map(|error| {
+                        error.downcast_ref::<serde_path_to_error::Error<serde_json::Error>>()
+                    })
+                    .and_then(|error| path_field_error(error.path(), error.inner()))
+                    .into_iter()
+                    .collect()
+            }
+            ServerError::QueryRejection(error) => path_field_error(error.path(), error.inner())
+                .into_iter()
+                .collect(),
+            ServerError::QueryValidation(report) => report
+                .iter()
+                .map(|(path, error)| FieldError {
+                    field: path.to_string(),
+                    message: error.message().to_owned(),
+                })
+                .collect(),
+            _ => Vec::new(),
         }
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
-struct ErrorResponse {
-    status: u16,
+/// `None` if `path` is the body or query itself, not one of its fields.
+fn path_field_error(path: &serde_path_to_error::Path, error: &impl Display) -> Option<FieldError> {
+    let field = path.to_string();
+    (field != ".").then(|| FieldError {
+        field,
+        message: error.to_string(),
+    })
 }
 
 impl IntoResponse for ServerError {
@@ -84,9 +499,47 @@ impl IntoResponse for ServerError {
 
         error!(error = %self, %status, "Replying with error");
 
-        let error_response = ErrorResponse {
-            status: status.as_u16(),
-        };
-        (status, Json(error_response)).into_response()
+        let mut problem = Problem::new(
+            self.code(),
+            status.as_u16(),
+            status.canonical_reason().unwrap_or_default().to_owned(),
+        );
+        if !status.is_server_error() {
+            problem.detail = Some(self.to_string());
+        }
+        let language = i18n::current();
+        problem.message = language.message(problem.code).map(ToOwned::to_owned);
+        problem.errors = self.field_errors();
+        problem.request_id = request_id::current();
+        let mut quota = None;
+        match self {
+            ServerError::Database(DbError::Conflict { current_version })
+            | ServerError::PolicyVersionOutdated {
+                current_version, ..
+            } => {
+                problem.current_version = Some(current_version);
+            }
+            ServerError::ContentRejected(rejection) => {
+                problem.rule_category = Some(rejection.category.into_owned());
+            }
+            ServerError::QuotaExceeded(usage) => quota = Some(usage),
+            _ => {}
+        }
+
+        let body = serde_json::to_vec(&problem).expect("Problem is always serializable");
+        let mut response = (
+            status,
+            [
+                (CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON)),
+                (CONTENT_LANGUAGE, language.into()),
+            ],
+            body,
+        )
+            .into_response();
+        if let Some(usage) = quota {
+            quota::insert_headers(response.headers_mut(), &usage);
+        }
+
+        response
     }
 }
@@ -0,0 +1,24 @@
+use axum::http::{HeaderMap, HeaderValue, header::LINK};
+use stellwerk_common::model::{
+    Id,
+    pagination::{Cursor, next_cursor},
+};
+
+/// Builds a `Link` header referencing the next (older) page, if the page was full.
+///
+/// `path` is the path the page was requested at, including any API version prefix.
+/// `ids` are the ids of the returned page in order.
+/// There is no previous page: `since_id` returns the newest items, not the ones right above the
+/// page, so newer items are polled with `since_id` instead.
+pub fn link_headers<Marker>(path: &str, limit: u32, ids: &[Id<Marker>]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Some(Cursor::Older(oldest)) = next_cursor(limit, ids) {
+        let value = HeaderValue::try_from(format!(
+            "<{path}?limit={limit}&max_id={oldest}>; rel=\"next\""
+        ))
+        .expect("Link header consists of a path and ascii characters");
+        headers.insert(LINK, value);
+    }
+
+    headers
+}
@@ -0,0 +1,56 @@
+//! Query string extractors whose rejections are [`ServerError`]s,
+//! so that they are problem details listing the invalid parameter, like rejected JSON bodies.
+
+use crate::server::ServerError;
+use axum::{
+    extract::FromRequestParts,
+    http::{Uri, request::Parts},
+};
+use garde::Validate;
+use serde::de::DeserializeOwned;
+
+pub type QueryError = serde_path_to_error::Error<serde_urlencoded::de::Error>;
+
+/// Like [`axum::extract::Query`], but rejected with [`ServerError::QueryRejection`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Query<T>(pub T);
+
+impl<T: DeserializeOwned> Query<T> {
+    pub fn try_from_uri(uri: &Uri) -> Result<Self, ServerError> {
+        let query = uri.query().unwrap_or_default();
+        let deserializer =
+            serde_urlencoded::Deserializer::new(form_urlencoded::parse(query.as_bytes()));
+
+        serde_path_to_error::deserialize(deserializer)
+            .map(Self)
+            .map_err(ServerError::QueryRejection)
+    }
+}
+
+impl<T: DeserializeOwned, S: Send + Sync> FromRequestParts<S> for Query<T> {
+    type Rejection = ServerError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Self::try_from_uri(&parts.uri)
+    }
+}
+
+/// A [`Query`] that additionally passes the [`garde`] rules of `T`,
+/// or is rejected with [`ServerError::QueryValidation`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidatedQuery<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for ValidatedQuery<T>
+where
+    T: DeserializeOwned + Validate<Context: Default>,
+    S: Send + Sync,
+{
+    type Rejection = ServerError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<T>::try_from_uri(&parts.uri)?;
+        query.validate().map_err(ServerError::QueryValidation)?;
+
+        Ok(Self(query))
+    }
+}
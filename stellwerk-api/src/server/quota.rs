@@ -0,0 +1,70 @@
+//! Daily quotas of local users, see [`stellwerk_common::model::quota`].
+//!
+//! A quota is counted right before the post or follow is written, after the content filters and
+//! spam heuristics, so that rejected requests do not count. Requests over the quota are rejected
+//! with [`ServerError::QuotaExceeded`], whose response tells the limit and when it resets in the
+//! `X-Quota-Limit`, `X-Quota-Remaining` and `X-Quota-Reset` headers.
+
+use crate::server::{Result, ServerError};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, header::RETRY_AFTER};
+use stellwerk_common::model::{
+    Id,
+    quota::{QuotaKind, QuotaSettings, QuotaUsage},
+    user::UserMarker,
+};
+use stellwerk_db::client::DbClient;
+use time::UtcDateTime;
+
+const QUOTA_LIMIT: HeaderName = HeaderName::from_static("x-quota-limit");
+const QUOTA_REMAINING: HeaderName = HeaderName::from_static("x-quota-remaining");
+/// Seconds until the quota resets.
+const QUOTA_RESET: HeaderName = HeaderName::from_static("x-quota-reset");
+
+#[derive(Clone, Debug)]
+pub struct Quotas {
+    settings: QuotaSettings,
+}
+
+impl Quotas {
+    #[must_use]
+    pub fn new(settings: QuotaSettings) -> Self {
+        Self { settings }
+    }
+
+    /// Counts one post or follow of the user, or rejects it if the quota is used up.
+    pub async fn consume(
+        &self,
+        db: &DbClient,
+        user_id: Id<UserMarker>,
+        kind: QuotaKind,
+    ) -> Result<()> {
+        let overrides = db.fetch_user_quotas(user_id).await?;
+        let limit = self.settings.with_overrides(overrides).limit(kind);
+        if limit == 0 {
+            return Ok(());
+        }
+
+        let now = UtcDateTime::now();
+        if !db.consume_quota(user_id, kind, now.date(), limit).await? {
+            return Err(ServerError::QuotaExceeded(QuotaUsage::new(
+                kind, limit, now,
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// The headers of a response rejected because of `usage`.
+pub fn insert_headers(headers: &mut HeaderMap, usage: &QuotaUsage) {
+    let reset = (usage.resets_at - UtcDateTime::now())
+        .whole_seconds()
+        .max(0)
+        .to_string();
+    let reset = HeaderValue::try_from(reset).expect("Integers are valid header values");
+
+    headers.insert(QUOTA_LIMIT, usage.limit.into());
+    headers.insert(QUOTA_REMAINING, 0.into());
+    headers.insert(QUOTA_RESET, reset.clone());
+    headers.insert(RETRY_AFTER, reset);
+}
@@ -0,0 +1,406 @@
+//! Per-client rate limiting, with separate limits for each [`RouteGroup`].
+//! Clients are identified by their [`ClientIp`], and IPv6 clients by their `/64` network,
+//! since that is usually what one host gets.
+//!
+//! The buckets are kept in memory by default, so each server process limits on its own.
+//! With the `redis` feature, they can be kept in Redis instead, where they are shared by all
+//! processes behind the same load balancer.
+
+use crate::server::{
+    ServerError,
+    client_ip::ClientIp,
+    route_group::{RouteGroup, RouteGroups},
+};
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{HeaderValue, header::RETRY_AFTER},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Deserializer, de::Error};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv6Addr},
+    str::FromStr,
+    sync::{Arc, nonpoison::Mutex},
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+
+/// How often in-memory buckets that are full again are dropped.
+const PRUNE_INTERVAL: Duration = Duration::from_mins(1);
+
+/// Allows `requests` per `period` on average, with bursts of up to `burst` requests.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct RateLimitPolicy {
+    pub requests: u32,
+    pub period: Duration,
+    pub burst: u32,
+}
+
+impl RateLimitPolicy {
+    #[must_use]
+    pub const fn new(requests: u32, period: Duration, burst: u32) -> Self {
+        Self {
+            requests,
+            period,
+            burst,
+        }
+    }
+
+    fn refill_per_second(self) -> f64 {
+        f64::from(self.requests) / self.period.as_secs_f64()
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Error)]
+#[error("Expected a rate limit like `60/60s` or `60/60s:20`, or `off`, but got `{0}`")]
+pub struct InvalidRateLimitPolicyError(String);
+
+/// Parses `<requests>/<seconds>s`, optionally followed by `:<burst>`.
+/// The burst defaults to `requests`.
+impl FromStr for RateLimitPolicy {
+    type Err = InvalidRateLimitPolicyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || InvalidRateLimitPolicyError(s.to_owned());
+
+        let (rate, burst) = s
+            .split_once(':')
+            .map_or((s, None), |(rate, burst)| (rate, Some(burst)));
+        let (requests, period) = rate.split_once('/').ok_or_else(error)?;
+        let requests: u32 = requests.trim().parse().map_err(|_| error())?;
+        let seconds: u64 = period
+            .trim()
+            .strip_suffix('s')
+            .ok_or_else(error)?
+            .parse()
+            .map_err(|_| error())?;
+        let burst = burst
+            .map(|burst| burst.trim().parse())
+            .transpose()
+            .map_err(|_| error())?
+            .unwrap_or(requests);
+
+        if requests == 0 || seconds == 0 || burst == 0 {
+            return Err(error());
+        }
+
+        Ok(Self::new(requests, Duration::from_secs(seconds), burst))
+    }
+}
+
+/// A configured [`RateLimitPolicy`], or no limit if configured as `off`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct RateLimit(pub Option<RateLimitPolicy>);
+
+impl<'de> Deserialize<'de> for RateLimit {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let policy = String::deserialize(deserializer)?;
+        if policy.trim() == "off" {
+            return Ok(Self(None));
+        }
+
+        policy
+            .parse()
+            .map(|policy| Self(Some(policy)))
+            .map_err(D::Error::custom)
+    }
+}
+
+/// A token bucket of one client, or of anything else limited by a [`RateLimitPolicy`].
+#[derive(Copy, Clone, Debug)]
+pub struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    #[must_use]
+    pub fn full(policy: RateLimitPolicy, now: Instant) -> Self {
+        Self {
+            tokens: f64::from(policy.burst),
+            updated_at: now,
+        }
+    }
+
+    /// Whether the bucket refilled completely, so that dropping it changes nothing.
+    pub fn is_full(&mut self, policy: RateLimitPolicy, now: Instant) -> bool {
+        self.refill(policy, now);
+        self.tokens >= f64::from(policy.burst)
+    }
+
+    fn refill(&mut self, policy: RateLimitPolicy, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * policy.refill_per_second()).min(f64::from(policy.burst));
+        self.updated_at = now;
+    }
+
+    /// Returns how long to wait if no token is left.
+    pub fn take(&mut self, policy: RateLimitPolicy, now: Instant) -> Result<(), Duration> {
+        self.refill(policy, now);
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - self.tokens) / policy.refill_per_second(),
+            ))
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Store {
+    Memory(Mutex<HashMap<(RouteGroup, IpAddr), Bucket>>),
+    #[cfg(feature = "redis")]
+    Redis(redis_store::RedisStore),
+}
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// Groups without a policy are not limited.
+    policies: HashMap<RouteGroup, RateLimitPolicy>,
+    route_groups: RouteGroups,
+    store: Store,
+}
+
+impl RateLimiter {
+    /// Keeps the buckets in memory.
+    #[must_use]
+    pub fn new(policies: HashMap<RouteGroup, RateLimitPolicy>) -> Self {
+        Self {
+            policies,
+            route_groups: RouteGroups::new(),
+            store: Store::Memory(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Keeps the buckets in the Redis server at `url`.
+    #[cfg(feature = "redis")]
+    pub async fn with_redis(
+        policies: HashMap<RouteGroup, RateLimitPolicy>,
+        url: &str,
+    ) -> redis::RedisResult<Self> {
+        Ok(Self {
+            policies,
+            route_groups: RouteGroups::new(),
+            store: Store::Redis(redis_store::RedisStore::connect(url).await?),
+        })
+    }
+
+    /// Returns how long to wait if the client exceeded the limit.
+    #[cfg_attr(
+        not(feature = "redis"),
+        expect(clippy::unused_async, reason = "Only the Redis store awaits")
+    )]
+    async fn check(&self, group: RouteGroup, client: IpAddr) -> Result<(), Duration> {
+        let Some(&policy) = self.policies.get(&group) else {
+            return Ok(());
+        };
+        let client = client_network(client);
+
+        match &self.store {
+            Store::Memory(buckets) => Self::check_memory(buckets, group, client, policy),
+            #[cfg(feature = "redis")]
+            Store::Redis(store) => store.take(group, client, policy).await,
+        }
+    }
+
+    fn check_memory(
+        buckets: &Mutex<HashMap<(RouteGroup, IpAddr), Bucket>>,
+        group: RouteGroup,
+        client: IpAddr,
+        policy: RateLimitPolicy,
+    ) -> Result<(), Duration> {
+        let now = Instant::now();
+        buckets
+            .lock()
+            .entry((group, client))
+            .or_insert_with(|| Bucket::full(policy, now))
+            .take(policy, now)
+    }
+
+    /// Drops the in-memory buckets that are full again. Redis expires them by itself.
+    fn prune(&self) {
+        match &self.store {
+            Store::Memory(buckets) => {
+                let now = Instant::now();
+                buckets.lock().retain(|&(group, _), bucket| {
+                    let Some(&policy) = self.policies.get(&group) else {
+                        return false;
+                    };
+                    !bucket.is_full(policy, now)
+                });
+            }
+            #[cfg(feature = "redis")]
+            Store::Redis(_) => {}
+        }
+    }
+}
+
+/// Prunes the buckets periodically, rather than on requests while holding the lock.
+pub async fn rate_limit_prune_loop(limiter: Arc<RateLimiter>, cancellation: CancellationToken) {
+    while cancellation
+        .run_until_cancelled(tokio::time::sleep(PRUNE_INTERVAL))
+        .await
+        .is_some()
+    {
+        limiter.prune();
+    }
+}
+
+/// The client itself for IPv4, and its `/64` network for IPv6,
+/// so that a host cannot get fresh buckets by switching addresses.
+fn client_network(client: IpAddr) -> IpAddr {
+    match client.to_canonical() {
+        IpAddr::V6(address) => IpAddr::V6(Ipv6Addr::from_bits(
+            address.to_bits() & !u128::from(u64::MAX),
+        )),
+        client @ IpAddr::V4(_) => client,
+    }
+}
+
+/// Rejects requests of clients that exceeded the limit of the route's group.
+/// Must be a route layer, so that the matched route is known.
+pub async fn limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let extensions = request.extensions();
+    let (Some(matched_path), Some(&ClientIp(client))) = (
+        extensions.get::<MatchedPath>(),
+        extensions.get::<ClientIp>(),
+    ) else {
+        return next.run(request).await;
+    };
+
+    let group = limiter
+        .route_groups
+        .get(request.method(), matched_path.as_str());
+    if let Err(retry_after) = limiter.check(group, client).await {
+        let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+        let retry_after = HeaderValue::from(seconds.max(1));
+        return (
+            [(RETRY_AFTER, retry_after)],
+            ServerError::RateLimited(group),
+        )
+            .into_response();
+    }
+
+    next.run(request).await
+}
+
+#[cfg(feature = "redis")]
+mod redis_store {
+    //! Each bucket is a hash with keys like `stellwerk:rate_limit:write:192.0.2.1`, updated
+    //! atomically by a Lua script. The time of the Redis server is used, so that the clocks of
+    //! the server processes do not matter. Buckets expire once they would be full again.
+    //!
+    //! Failing requests are logged, and the request is allowed.
+
+    use crate::server::{rate_limit::RateLimitPolicy, route_group::RouteGroup};
+    use redis::{Script, aio::ConnectionManager};
+    use std::{net::IpAddr, time::Duration};
+    use tracing::warn;
+
+    /// Takes a token from the bucket `KEYS[1]` with the burst `ARGV[1]` that refills
+    /// `ARGV[2]` tokens per millisecond. Returns how many milliseconds to wait, or 0 if a
+    /// token was taken.
+    const TAKE_SCRIPT: &str = r"
+        local burst = tonumber(ARGV[1])
+        local refill_per_ms = tonumber(ARGV[2])
+        local time = redis.call('TIME')
+        local now = time[1] * 1000 + math.floor(time[2] / 1000)
+        local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+        local tokens = tonumber(bucket[1]) or burst
+        local updated_at = tonumber(bucket[2]) or now
+        tokens = math.min(burst, tokens + math.max(0, now - updated_at) * refill_per_ms)
+        local wait = 0
+        if tokens >= 1 then
+            tokens = tokens - 1
+        else
+            wait = math.ceil((1 - tokens) / refill_per_ms)
+        end
+        redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at', now)
+        redis.call('PEXPIRE', KEYS[1], math.ceil((burst - tokens) / refill_per_ms) + 1)
+        return wait
+    ";
+
+    pub(super) struct RedisStore {
+        connection: ConnectionManager,
+        script: Script,
+    }
+
+    impl std::fmt::Debug for RedisStore {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("RedisStore").finish_non_exhaustive()
+        }
+    }
+
+    impl RedisStore {
+        pub(super) async fn connect(url: &str) -> redis::RedisResult<Self> {
+            let client = redis::Client::open(url)?;
+            let connection = ConnectionManager::new(client).await?;
+
+            Ok(Self {
+                connection,
+                script: Script::new(TAKE_SCRIPT),
+            })
+        }
+
+        pub(super) async fn take(
+            &self,
+            group: RouteGroup,
+            client: IpAddr,
+            policy: RateLimitPolicy,
+        ) -> Result<(), Duration> {
+            let refill_per_ms = policy.refill_per_second() / 1000.0;
+            let wait: redis::RedisResult<u64> = self
+                .script
+                .key(format!("stellwerk:rate_limit:{group}:{client}"))
+                .arg(policy.burst)
+                .arg(refill_per_ms)
+                .invoke_async(&mut self.connection.clone())
+                .await;
+
+            match wait {
+                Ok(0) => Ok(()),
+                Ok(wait) => Err(Duration::from_millis(wait)),
+                Err(error) => {
+                    warn!(%error, "Error checking the rate limit in Redis");
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::rate_limit::client_network;
+    use std::net::IpAddr;
+
+    #[test]
+    fn ipv6_clients_are_grouped_by_network() {
+        let network = |client: &str| client_network(client.parse().unwrap());
+        assert_eq!(
+            network("2001:db8:1:2:3:4:5:6"),
+            "2001:db8:1:2::".parse::<IpAddr>().unwrap(),
+        );
+        assert_eq!(
+            network("2001:db8:1:2::ffff"),
+            network("2001:db8:1:2:abcd::")
+        );
+        assert_ne!(network("2001:db8:1:2::"), network("2001:db8:1:3::"));
+        assert_eq!(network("::ffff:192.0.2.1"), network("192.0.2.1"));
+        assert_ne!(network("192.0.2.1"), network("192.0.2.2"));
+    }
+}
@@ -0,0 +1,82 @@
+//! Read-only mode, e.g. for migrations or incident response.
+//!
+//! While enabled, requests with unsafe methods are rejected with [`ServerError::ReadOnly`]
+//! and a `Retry-After` header, while reads continue to work.
+//! It is enabled on startup by the configuration and toggled at runtime through the admin API.
+//! Background tasks, like deliveries and email digests, keep running.
+
+use crate::server::{ServerError, versioning::CURRENT_VERSION};
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{HeaderValue, header::RETRY_AFTER},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+/// Route templates without the version prefix that change state, but are not rejected,
+/// so that read-only mode can be disabled again.
+const EXEMPT_ROUTES: &[&str] = &["/admin/read-only"];
+
+#[derive(Debug)]
+pub struct ReadOnly {
+    enabled: AtomicBool,
+    retry_after: HeaderValue,
+}
+
+impl ReadOnly {
+    /// `retry_after` is what rejected clients are told to wait, rounded to seconds.
+    #[must_use]
+    pub fn new(enabled: bool, retry_after: Duration) -> Self {
+        Self {
+            enabled: AtomicBool::new(enabled),
+            retry_after: HeaderValue::from(retry_after.as_secs()),
+        }
+    }
+
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Returns whether it was enabled before.
+    pub fn set_enabled(&self, enabled: bool) -> bool {
+        self.enabled.swap(enabled, Ordering::Relaxed)
+    }
+}
+
+/// Rejects requests with unsafe methods while read-only mode is enabled.
+/// Must be a route layer, so that the matched route is known.
+pub async fn reject_writes(
+    State(read_only): State<Arc<ReadOnly>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !read_only.is_enabled() || request.method().is_safe() {
+        return next.run(request).await;
+    }
+
+    let exempt = request
+        .extensions()
+        .get::<MatchedPath>()
+        .is_some_and(|matched_path| {
+            let path = matched_path.as_str();
+            let path = path.strip_prefix(CURRENT_VERSION).unwrap_or(path);
+            EXEMPT_ROUTES.contains(&path)
+        });
+    if exempt {
+        return next.run(request).await;
+    }
+
+    (
+        [(RETRY_AFTER, read_only.retry_after.clone())],
+        ServerError::ReadOnly,
+    )
+        .into_response()
+}
@@ -0,0 +1,53 @@
+//! Request ids, so that users can report a request that operators can find in the logs.
+//!
+//! A valid `X-Request-Id` sent by the client, e.g. by a reverse proxy, is kept.
+//! Otherwise, a random one is assigned. Either way, it is echoed in the response.
+
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+/// Longer ids sent by clients are replaced, so they cannot flood the logs.
+const REQUEST_ID_MAX_LEN: usize = 64;
+
+tokio::task_local! {
+    static REQUEST_ID: HeaderValue;
+}
+
+/// The id of the request currently being handled, if any.
+pub fn current() -> Option<String> {
+    REQUEST_ID
+        .try_with(|id| id.to_str().map(ToOwned::to_owned).ok())
+        .ok()
+        .flatten()
+}
+
+fn is_valid(id: &HeaderValue) -> bool {
+    id.len() <= REQUEST_ID_MAX_LEN
+        && id
+            .as_bytes()
+            .iter()
+            .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.'))
+}
+
+/// Assigns the request id. Must wrap the trace layer, so that the id is part of its span,
+/// see [`logging::make_span`](crate::server::logging::make_span).
+pub async fn assign(mut request: Request, next: Next) -> Response {
+    let id = match request.headers().get(X_REQUEST_ID) {
+        Some(id) if !id.is_empty() && is_valid(id) => id.clone(),
+        _ => {
+            let id = HeaderValue::try_from(format!("{:032x}", rand::random::<u128>()))
+                .expect("Hex digits are valid header characters");
+            request.headers_mut().insert(X_REQUEST_ID, id.clone());
+            id
+        }
+    };
+
+    let mut response = REQUEST_ID.scope(id.clone(), next.run(request)).await;
+    response.headers_mut().insert(X_REQUEST_ID, id);
+    response
+}
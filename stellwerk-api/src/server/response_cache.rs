@@ -0,0 +1,326 @@
+//! An in-process cache of responses to anonymous reads of public posts, profiles, and timelines,
+//! so that traffic spikes, like on viral posts, do not all reach the database.
+//!
+//! Responses are cached by path and query, so every cursor and field selection has its own entry,
+//! for a short time to live. Entries are grouped by the resource's id, whether it was requested
+//! in decimal or base62. Write routes invalidate the entries of the resources they change,
+//! so changes made through this instance are visible right away.
+//! Changes made through other instances or received from other servers are only visible
+//! once the entries expired.
+//!
+//! Requests with credentials, signatures, or `If-None-Match` are not served from the cache.
+//! Every tenant has its own entries, since the public timeline and links differ between them.
+//!
+//! Optionally, expired entries are kept for a while longer and served when the route answers with
+//! `503 Service Unavailable`, like while the database is unavailable.
+
+use crate::server::{ServerError, activitypub, tenant::CurrentTenant, versioning::CURRENT_VERSION};
+use axum::{
+    body::{self, Bytes},
+    extract::{FromRequestParts, MatchedPath, OriginalUri, RawPathParams, Request, State},
+    http::{
+        HeaderMap, HeaderName, HeaderValue, Method, StatusCode,
+        header::{AUTHORIZATION, IF_NONE_MATCH},
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::de::{IntoDeserializer, value::Error as ValueError};
+use std::{
+    collections::HashMap,
+    sync::{Arc, nonpoison::Mutex},
+    time::{Duration, Instant},
+};
+use stellwerk_common::{
+    model::{Id, post::PostMarker, tenant::TenantId, user::UserMarker},
+    snowflake,
+};
+
+/// Route templates without the version prefix whose responses are cached.
+const CACHED_ROUTES: &[&str] = &[
+    "/posts/{id}",
+    "/users/{id}",
+    "/users/{id}/posts",
+    "/timeline/public",
+];
+const PUBLIC_TIMELINE: &str = "/timeline/public";
+
+/// Whether the response was served from the cache, on responses of cached routes.
+const X_CACHE: HeaderName = HeaderName::from_static("x-cache");
+
+#[derive(Clone, Debug)]
+struct Entry {
+    headers: HeaderMap,
+    body: Bytes,
+    expires_at: Instant,
+}
+
+/// Distinguishes the entries of one resource.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+struct Representation {
+    /// Including the version prefix, since unversioned responses are marked as deprecated.
+    path_and_query: Box<str>,
+    activity: bool,
+    tenant: TenantId,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    /// By [resource path](resource_path), so that all entries of a resource are invalidated at once.
+    by_path: HashMap<Box<str>, HashMap<Representation, Entry>>,
+    len: usize,
+}
+
+impl Entries {
+    /// Also prunes entries expired longer than `stale` ago.
+    fn prune_expired(&mut self, now: Instant, stale: Duration) {
+        self.by_path.retain(|_, representations| {
+            representations.retain(|_, entry| entry.expires_at + stale > now);
+            !representations.is_empty()
+        });
+        self.len = self.by_path.values().map(HashMap::len).sum();
+    }
+
+    fn remove(&mut self, path: &str) {
+        if let Some(representations) = self.by_path.remove(path) {
+            self.len -= representations.len();
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ResponseCache {
+    /// Nothing is cached if `None`.
+    ttl: Option<Duration>,
+    /// In entries.
+    capacity: usize,
+    /// How long expired entries are served instead of `503 Service Unavailable`.
+    stale_if_unavailable: Duration,
+    entries: Mutex<Entries>,
+}
+
+impl ResponseCache {
+    #[must_use]
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl: Some(ttl),
+            capacity,
+            stale_if_unavailable: Duration::ZERO,
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    #[must_use]
+    pub fn with_stale_if_unavailable(self, stale_if_unavailable: Duration) -> Self {
+        Self {
+            stale_if_unavailable,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn disabled() -> Self {
+        Self {
+            ttl: None,
+            capacity: 0,
+            stale_if_unavailable: Duration::ZERO,
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    pub fn invalidate_post(&self, id: Id<PostMarker>) {
+        if self.ttl.is_some() {
+            self.entries.lock().remove(&format!("/posts/{id}"));
+        }
+    }
+
+    /// Invalidates the user's profile and posts.
+    pub fn invalidate_user(&self, id: Id<UserMarker>) {
+        if self.ttl.is_some() {
+            let mut entries = self.entries.lock();
+            entries.remove(&format!("/users/{id}"));
+            entries.remove(&format!("/users/{id}/posts"));
+        }
+    }
+
+    pub fn invalidate_public_timeline(&self) {
+        if self.ttl.is_some() {
+            self.entries.lock().remove(PUBLIC_TIMELINE);
+        }
+    }
+
+    /// Includes entries that expired less than `stale` ago.
+    fn get(
+        &self,
+        path: &str,
+        representation: &Representation,
+        now: Instant,
+        stale: Duration,
+    ) -> Option<Entry> {
+        self.entries
+            .lock()
+            .by_path
+            .get(path)?
+            .get(representation)
+            .filter(|entry| entry.expires_at + stale > now)
+            .cloned()
+    }
+
+    /// Does nothing if the cache is full of entries that did not expire yet.
+    fn insert(&self, path: &str, representation: Representation, entry: Entry, now: Instant) {
+        let mut entries = self.entries.lock();
+        if entries.len >= self.capacity {
+            entries.prune_expired(now, self.stale_if_unavailable);
+            if entries.len >= self.capacity {
+                return;
+            }
+        }
+
+        let previous = entries
+            .by_path
+            .entry(path.into())
+            .or_default()
+            .insert(representation, entry);
+        if previous.is_none() {
+            entries.len += 1;
+        }
+    }
+}
+
+/// Serves anonymous reads of [`CACHED_ROUTES`] from the cache, and caches successful responses.
+/// Must be a route layer, so that the matched route is known.
+pub async fn cache(
+    State(cache): State<Arc<ResponseCache>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ServerError> {
+    let Some(ttl) = cache.ttl else {
+        return Ok(next.run(request).await);
+    };
+
+    let headers = request.headers();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .and_then(|matched_path| {
+            let route = unversioned(matched_path.as_str());
+            CACHED_ROUTES.iter().find(|cached| **cached == route)
+        })
+        .filter(|_| {
+            request.method() == Method::GET
+                && !headers.contains_key(AUTHORIZATION)
+                && !headers.contains_key("signature")
+                && !headers.contains_key(IF_NONE_MATCH)
+        });
+    let Some(route) = route else {
+        return Ok(next.run(request).await);
+    };
+
+    let (mut parts, body) = request.into_parts();
+    let params = RawPathParams::from_request_parts(&mut parts, &()).await;
+    let request = Request::from_parts(parts, body);
+    let path = params.ok().and_then(|params| {
+        let id = params.iter().find(|(name, _)| *name == "id");
+        resource_path(route, id.map(|(_, id)| id))
+    });
+    let tenant = request.extensions().get::<CurrentTenant>();
+    let (Some(path), Some(CurrentTenant(tenant))) = (path, tenant) else {
+        return Ok(next.run(request).await);
+    };
+
+    let headers = request.headers();
+    let uri = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map_or(request.uri(), |OriginalUri(uri)| uri);
+    let representation = Representation {
+        path_and_query: uri
+            .path_and_query()
+            .map_or(uri.path(), |path_and_query| path_and_query.as_str())
+            .into(),
+        activity: activitypub::is_requested(headers),
+        tenant: tenant.id.clone(),
+    };
+
+    let now = Instant::now();
+    if let Some(entry) = cache.get(&path, &representation, now, Duration::ZERO) {
+        return Ok(cached_response(entry, "hit"));
+    }
+
+    let response = next.run(request).await;
+    if response.status() == StatusCode::SERVICE_UNAVAILABLE
+        && !cache.stale_if_unavailable.is_zero()
+        && let Some(entry) = cache.get(&path, &representation, now, cache.stale_if_unavailable)
+    {
+        return Ok(cached_response(entry, "stale"));
+    }
+    if response.status() != StatusCode::OK {
+        return Ok(response);
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let body = body::to_bytes(body, usize::MAX)
+        .await
+        .map_err(ServerError::ResponseBody)?;
+    cache.insert(
+        &path,
+        representation,
+        Entry {
+            headers: parts.headers.clone(),
+            body: body.clone(),
+            expires_at: now + ttl,
+        },
+        now,
+    );
+
+    parts
+        .headers
+        .insert(X_CACHE, HeaderValue::from_static("miss"));
+    Ok(Response::from_parts(parts, body.into()))
+}
+
+fn cached_response(entry: Entry, x_cache: &'static str) -> Response {
+    let mut response = (StatusCode::OK, entry.headers, entry.body).into_response();
+    response
+        .headers_mut()
+        .insert(X_CACHE, HeaderValue::from_static(x_cache));
+    response
+}
+
+/// `route` with the id in decimal, like the paths [`ResponseCache::invalidate_post`] and
+/// [`ResponseCache::invalidate_user`] remove. `None` if the id is invalid,
+/// in which case the route responds with an error anyway.
+fn resource_path(route: &str, id: Option<&str>) -> Option<Box<str>> {
+    let Some(id) = id else {
+        return Some(route.into());
+    };
+    let id = IntoDeserializer::<ValueError>::into_deserializer(id);
+    let id: u64 = snowflake::string::deserialize(id).ok()?;
+    Some(route.replace("{id}", &id.to_string()).into())
+}
+
+fn unversioned(path: &str) -> &str {
+    path.strip_prefix(CURRENT_VERSION).unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::response_cache::resource_path;
+    use stellwerk_common::model::StellwerkSnowflake;
+
+    #[test]
+    fn resource_paths_use_decimal_ids() {
+        let id = StellwerkSnowflake::new(1_234_567_890_123);
+        for requested in [id.to_string(), id.to_base62()] {
+            assert_eq!(
+                resource_path("/users/{id}/posts", Some(&requested)).as_deref(),
+                Some("/users/1234567890123/posts"),
+            );
+        }
+        assert_eq!(resource_path("/posts/{id}", Some("4fZ-9")), None);
+        assert_eq!(
+            resource_path("/timeline/public", None).as_deref(),
+            Some("/timeline/public"),
+        );
+    }
+}
@@ -0,0 +1,78 @@
+//! Groups of routes that are treated alike, e.g. by rate limits and body size limits.
+//!
+//! Every route belongs to a [`RouteGroup`], by default determined by its method.
+//! Routes can be assigned to other groups by their typed path, see [`routes::route_groups`].
+
+use crate::server::{routes, versioning::CURRENT_VERSION};
+use axum::http::Method;
+use std::{
+    collections::HashMap,
+    fmt::{Display, Formatter},
+};
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
+pub enum RouteGroup {
+    /// Routes checking secrets supplied by the client, which could otherwise be guessed.
+    Auth,
+    /// Routes changing state.
+    Write,
+    /// Routes only reading state.
+    Read,
+    /// Routes handling uploads or other large bodies.
+    Media,
+}
+
+impl RouteGroup {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RouteGroup::Auth => "auth",
+            RouteGroup::Write => "write",
+            RouteGroup::Read => "read",
+            RouteGroup::Media => "media",
+        }
+    }
+
+    fn for_method(method: &Method) -> Self {
+        if method.is_safe() {
+            RouteGroup::Read
+        } else {
+            RouteGroup::Write
+        }
+    }
+}
+
+impl Display for RouteGroup {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Routes in other groups than the default for their method.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct RouteGroups {
+    /// Route templates without the version prefix.
+    groups: HashMap<&'static str, RouteGroup>,
+}
+
+impl RouteGroups {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            groups: routes::route_groups().into_iter().collect(),
+        }
+    }
+
+    /// `matched_path` is the template of the route, as in [`MatchedPath`](axum::extract::MatchedPath).
+    #[must_use]
+    pub fn get(&self, method: &Method, matched_path: &str) -> RouteGroup {
+        let path = matched_path
+            .strip_prefix(CURRENT_VERSION)
+            .unwrap_or(matched_path);
+
+        self.groups
+            .get(path)
+            .copied()
+            .unwrap_or_else(|| RouteGroup::for_method(method))
+    }
+}
@@ -0,0 +1,971 @@
+//! Routes for administrators under `/admin`, managing users, reports, auth tokens and
+//! the instance, including [read-only mode](crate::server::read_only),
+//! [feature flags](crate::server::feature_flags), the [spam heuristics](crate::server::spam),
+//! dead-lettered [jobs](crate::jobs), [analytics](crate::server::analytics), and per-user
+//! [quotas](crate::server::quota), [impersonation](crate::impersonation) and
+//! [verification](crate::verification) of users.
+//! All of them require the [`UserRole::Admin`] role.
+
+use crate::{
+    impersonation::IMPERSONATION_LIFETIME,
+    server::{
+        ServerError, ServerRouter, auth::AuthenticatedAdmin, client_ip::ClientIp,
+        feature_flags::FeatureFlags, json::Json, pagination::link_headers, query::Query,
+        read_only::ReadOnly, response_cache::ResponseCache, spam::SpamGuard, tenant::CurrentTenant,
+    },
+    verification::LinkVerifier,
+};
+use axum::{
+    extract::{OriginalUri, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use axum_extra::routing::{RouterExt, TypedPath};
+use serde::Deserialize;
+use std::{sync::Arc, time::Duration};
+use stellwerk_common::{
+    model::{
+        Id,
+        admin::{
+            ActivityStats, CreateUserAccount, Impersonation, InstanceOverview, QueryStats,
+            ReadOnlyMode, TokenPurge, UserAccount,
+        },
+        analytics::{AnalyticsQuery, AnalyticsRollup},
+        announcement::{Announcement, AnnouncementMarker, CreateAnnouncement},
+        auth::{AuthToken, AuthTokenHashError, Authentication},
+        feature::{FeatureFlag, FeatureFlagState, SetFeatureFlag},
+        instance::InstanceInfo,
+        job::{DeadJob, JobMarker, JobPayload},
+        moderation::{
+            CreateModerationLogEntry, ModerationAction, ModerationLogEntry, ModerationLogMarker,
+        },
+        page::profile_page_url,
+        pagination::PageRequest,
+        policy::{Policy, PolicyKind, PublishPolicy},
+        problem::ErrorCode,
+        quota::UserQuotas,
+        report::{Report, ReportMarker},
+        spam::SpamSettings,
+        tenant::TenantId,
+        user::{User, UserHandle, UserMarker, UserRole},
+        verification::GrantVerification,
+    },
+    util::PositiveDuration,
+};
+use stellwerk_db::client::{DbClient, DbError};
+use thiserror::Error;
+use time::UtcDateTime;
+use tracing::{info, warn};
+
+pub fn routes() -> ServerRouter {
+    ServerRouter::new()
+        .typed_get(get_users)
+        .typed_post(create_user)
+        .typed_get(get_user)
+        .typed_put(set_user_role)
+        .typed_delete(delete_user_tokens)
+        .typed_post(impersonate_user)
+        .typed_delete(end_impersonation)
+        .typed_put(grant_verification)
+        .typed_delete(revoke_verification)
+        .typed_get(get_user_quotas)
+        .typed_put(set_user_quotas)
+        .typed_get(get_reports)
+        .typed_get(get_open_reports)
+        .typed_delete(delete_report)
+        .typed_get(get_moderation_log)
+        .typed_get(get_announcements)
+        .typed_post(create_announcement)
+        .typed_delete(delete_announcement)
+        .typed_post(publish_policy)
+        .typed_get(get_instance)
+        .typed_get(get_stats)
+        .typed_get(get_analytics)
+        .typed_get(get_read_only)
+        .typed_put(set_read_only)
+        .typed_get(get_feature_flags)
+        .typed_put(set_feature_flag)
+        .typed_delete(reset_feature_flag)
+        .typed_get(get_spam_settings)
+        .typed_put(set_spam_settings)
+        .typed_post(purge_expired_tokens)
+        .typed_get(get_query_stats)
+        .typed_get(get_dead_jobs)
+        .typed_post(retry_dead_job)
+        .typed_delete(delete_dead_job)
+}
+
+type Result<T, E = AdminError> = std::result::Result<T, E>;
+
+#[derive(Debug, Error)]
+pub enum AdminError {
+    #[error(transparent)]
+    Database(#[from] DbError),
+    #[error(transparent)]
+    AuthTokenHash(#[from] AuthTokenHashError),
+    #[error("User with id {0} was not found.")]
+    UserNotFound(Id<UserMarker>),
+    #[error("Report with id {0} was not found.")]
+    ReportNotFound(Id<ReportMarker>),
+    #[error("Announcement with id {0} was not found.")]
+    AnnouncementNotFound(Id<AnnouncementMarker>),
+    #[error("Dead-lettered job with id {0} was not found.")]
+    JobNotFound(Id<JobMarker>),
+    #[error("The handle {} is already taken.", .0.get())]
+    HandleTaken(UserHandle),
+    #[error("Administrators cannot change their own role.")]
+    CannotChangeOwnRole,
+    #[error("User with id {0} cannot be impersonated.")]
+    CannotImpersonate(Id<UserMarker>),
+    #[error("User with id {0} has no website on their profile.")]
+    WebsiteRequired(Id<UserMarker>),
+    #[error("The website of user with id {0} could not be fetched.")]
+    WebsiteUnreachable(Id<UserMarker>),
+    #[error("The website of user with id {0} does not link back to their profile.")]
+    LinkBackNotFound(Id<UserMarker>),
+}
+
+impl AdminError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AdminError::Database(
+                DbError::PoolExhausted | DbError::StatementTimeout | DbError::Unavailable,
+            ) => StatusCode::SERVICE_UNAVAILABLE,
+            AdminError::Database(_) | AdminError::AuthTokenHash(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            AdminError::UserNotFound(_)
+            | AdminError::ReportNotFound(_)
+            | AdminError::AnnouncementNotFound(_)
+            | AdminError::JobNotFound(_) => StatusCode::NOT_FOUND,
+            AdminError::HandleTaken(_) => StatusCode::CONFLICT,
+            AdminError::CannotChangeOwnRole | AdminError::CannotImpersonate(_) => {
+                StatusCode::FORBIDDEN
+            }
+            AdminError::WebsiteRequired(_) | AdminError::LinkBackNotFound(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            AdminError::WebsiteUnreachable(_) => StatusCode::BAD_GATEWAY,
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AdminError::Database(
+                DbError::PoolExhausted | DbError::StatementTimeout | DbError::Unavailable,
+            ) => ErrorCode::DatabaseUnavailable,
+            AdminError::Database(_) | AdminError::AuthTokenHash(_) => ErrorCode::InternalError,
+            AdminError::UserNotFound(_) => ErrorCode::UserNotFound,
+            AdminError::ReportNotFound(_) => ErrorCode::ReportNotFound,
+            AdminError::AnnouncementNotFound(_) => ErrorCode::AnnouncementNotFound,
+            AdminError::JobNotFound(_) => ErrorCode::JobNotFound,
+            AdminError::HandleTaken(_) => ErrorCode::HandleTaken,
+            AdminError::CannotChangeOwnRole => ErrorCode::CannotChangeOwnRole,
+            AdminError::CannotImpersonate(_) => ErrorCode::CannotImpersonate,
+            AdminError::WebsiteRequired(_) => ErrorCode::WebsiteRequired,
+            AdminError::WebsiteUnreachable(_) => ErrorCode::WebsiteUnreachable,
+            AdminError::LinkBackNotFound(_) => ErrorCode::LinkBackNotFound,
+        }
+    }
+}
+
+/// Replies with problem details, like any other [`ServerError`].
+impl IntoResponse for AdminError {
+    fn into_response(self) -> Response {
+        ServerError::Admin(self).into_response()
+    }
+}
+
+#[derive(TypedPath)]
+#[typed_path("/admin/users")]
+struct UsersPath;
+
+/// In the tenant of the request.
+async fn get_users(
+    _: UsersPath,
+    _: AuthenticatedAdmin,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<PageRequest<UserMarker>>,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+) -> Result<(HeaderMap, Json<Vec<UserAccount>>)> {
+    let limit = query.limit();
+    let accounts = db
+        .fetch_user_accounts(&tenant.id, query.max_id, query.since_id, limit)
+        .await?;
+
+    let ids: Vec<_> = accounts.iter().map(|account| account.user.id).collect();
+    let headers = link_headers(uri.path(), limit, &ids);
+
+    Ok((headers, Json(accounts)))
+}
+
+/// In the tenant of the request.
+async fn create_user(
+    _: UsersPath,
+    _: AuthenticatedAdmin,
+    ClientIp(client_ip): ClientIp,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+    Json(account): Json<CreateUserAccount>,
+) -> Result<(StatusCode, Json<UserAccount>)> {
+    let id = db
+        .create_user_account(&tenant.id, &account)
+        .await?
+        .ok_or_else(|| AdminError::HandleTaken(account.handle.clone()))?;
+    // The admin and the tenant are part of the request span.
+    info!(user_id = %id, role = %account.role, %client_ip, "Created user");
+
+    Ok((
+        StatusCode::CREATED,
+        Json(UserAccount {
+            user: User {
+                id,
+                handle: account.handle,
+                verified: false,
+            },
+            role: account.role,
+            remote: false,
+        }),
+    ))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/admin/users/{id}", rejection(ServerError))]
+struct UserPath {
+    id: Id<UserMarker>,
+}
+
+/// Admins only administer the users of their own tenant, so users of other tenants are not found.
+async fn require_tenant_user(db: &DbClient, tenant: &TenantId, id: Id<UserMarker>) -> Result<()> {
+    if db.fetch_user_tenant(id).await?.as_ref() != Some(tenant) {
+        return Err(AdminError::UserNotFound(id));
+    }
+
+    Ok(())
+}
+
+async fn get_user(
+    UserPath { id }: UserPath,
+    _: AuthenticatedAdmin,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<UserAccount>> {
+    require_tenant_user(&db, &tenant.id, id).await?;
+    let account = db
+        .fetch_user_account(id)
+        .await?
+        .ok_or(AdminError::UserNotFound(id))?;
+
+    Ok(Json(account))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/admin/users/{id}/role", rejection(ServerError))]
+struct UserRolePath {
+    id: Id<UserMarker>,
+}
+
+#[derive(Deserialize)]
+struct SetUserRoleBody {
+    role: UserRole,
+}
+
+/// Admins cannot change their own role, so that the instance is not left without an admin.
+async fn set_user_role(
+    UserRolePath { id }: UserRolePath,
+    admin: AuthenticatedAdmin,
+    ClientIp(client_ip): ClientIp,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+    Json(SetUserRoleBody { role }): Json<SetUserRoleBody>,
+) -> Result<StatusCode> {
+    if id == admin.user_id() {
+        return Err(AdminError::CannotChangeOwnRole);
+    }
+    require_tenant_user(&db, &tenant.id, id).await?;
+
+    if !db.set_user_role(id, role).await? {
+        return Err(AdminError::UserNotFound(id));
+    }
+    info!(user_id = %id, %role, %client_ip, "Changed user role");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/admin/users/{id}/tokens", rejection(ServerError))]
+struct UserTokensPath {
+    id: Id<UserMarker>,
+}
+
+/// Signs the user out of all sessions.
+async fn delete_user_tokens(
+    UserTokensPath { id }: UserTokensPath,
+    _: AuthenticatedAdmin,
+    ClientIp(client_ip): ClientIp,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<TokenPurge>> {
+    require_tenant_user(&db, &tenant.id, id).await?;
+
+    let deleted = db.delete_user_tokens(id).await?;
+    info!(user_id = %id, deleted, %client_ip, "Deleted user tokens");
+
+    Ok(Json(TokenPurge { deleted }))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/admin/users/{id}/impersonation", rejection(ServerError))]
+struct ImpersonationPath {
+    id: Id<UserMarker>,
+}
+
+/// Mints a token to act as the user for [`IMPERSONATION_LIFETIME`].
+/// Only other local users without the moderator or admin role can be impersonated, and the user
+/// is notified once the token expires.
+async fn impersonate_user(
+    ImpersonationPath { id }: ImpersonationPath,
+    admin: AuthenticatedAdmin,
+    ClientIp(client_ip): ClientIp,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+) -> Result<(StatusCode, Json<Impersonation>)> {
+    require_tenant_user(&db, &tenant.id, id).await?;
+    let account = db
+        .fetch_user_account(id)
+        .await?
+        .ok_or(AdminError::UserNotFound(id))?;
+    if id == admin.user_id() || account.role != UserRole::User || account.remote {
+        return Err(AdminError::CannotImpersonate(id));
+    }
+
+    let token = AuthToken::generate_random(id);
+    let now = UtcDateTime::now();
+    let expires_at = now + IMPERSONATION_LIFETIME;
+    let authentication = Authentication {
+        user: id,
+        tenant: tenant.id.clone(),
+        token_hash: token.hash()?,
+        created_at: now,
+        expires_after: Some(PositiveDuration::new_unchecked(IMPERSONATION_LIFETIME)),
+        impersonator: Some(admin.user_id()),
+    };
+    db.transaction(async |db| {
+        db.create_auth(&authentication).await?;
+        db.create_moderation_log_entry(
+            &CreateModerationLogEntry::new(admin.user_id(), ModerationAction::ImpersonateUser)
+                .with_user(id),
+        )
+        .await?;
+        db.enqueue_jobs(&[JobPayload::ImpersonationNotice { user: id }], expires_at)
+            .await?;
+
+        Ok::<_, AdminError>(())
+    })
+    .await?;
+    info!(user_id = %id, %expires_at, %client_ip, "Started impersonation");
+
+    Ok((
+        StatusCode::CREATED,
+        Json(Impersonation {
+            user: account.user,
+            token: token.as_token_str(),
+            expires_at,
+        }),
+    ))
+}
+
+/// Deletes the impersonation tokens of the user before they expire.
+/// The user is still notified when they would have expired.
+async fn end_impersonation(
+    ImpersonationPath { id }: ImpersonationPath,
+    admin: AuthenticatedAdmin,
+    ClientIp(client_ip): ClientIp,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<TokenPurge>> {
+    require_tenant_user(&db, &tenant.id, id).await?;
+
+    let deleted = db
+        .transaction(async |db| {
+            let deleted = db.delete_impersonation_tokens(id).await?;
+            if deleted > 0 {
+                db.create_moderation_log_entry(
+                    &CreateModerationLogEntry::new(
+                        admin.user_id(),
+                        ModerationAction::EndImpersonation,
+                    )
+                    .with_user(id),
+                )
+                .await?;
+            }
+
+            Ok::<_, AdminError>(deleted)
+        })
+        .await?;
+    info!(user_id = %id, deleted, %client_ip, "Ended impersonation");
+
+    Ok(Json(TokenPurge { deleted }))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/admin/users/{id}/verification", rejection(ServerError))]
+struct VerificationPath {
+    id: Id<UserMarker>,
+}
+
+/// With [`GrantVerification::require_link_back`], the website on the profile of the user must link
+/// back to their profile page in the tenant.
+#[allow(clippy::too_many_arguments)] // Each argument is an extractor.
+async fn grant_verification(
+    VerificationPath { id }: VerificationPath,
+    _: AuthenticatedAdmin,
+    ClientIp(client_ip): ClientIp,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+    State(cache): State<Arc<ResponseCache>>,
+    State(link_verifier): State<Arc<LinkVerifier>>,
+    Json(grant): Json<GrantVerification>,
+) -> Result<StatusCode> {
+    require_tenant_user(&db, &tenant.id, id).await?;
+    if grant.require_link_back {
+        let profile = db
+            .fetch_user_profile(id)
+            .await?
+            .ok_or(AdminError::UserNotFound(id))?;
+        let website = profile.website.ok_or(AdminError::WebsiteRequired(id))?;
+        let profile_url = profile_page_url(&tenant.info.public_url, &profile.user.handle);
+
+        // The error may tell about the network of the server, so it is only logged.
+        let links_back = link_verifier
+            .links_back(&website, &profile_url)
+            .await
+            .map_err(|error| {
+                info!(user_id = %id, %error, "Error fetching the website of the user");
+                AdminError::WebsiteUnreachable(id)
+            })?;
+        if !links_back {
+            return Err(AdminError::LinkBackNotFound(id));
+        }
+    }
+
+    if !db.set_user_verified(id, true).await? {
+        return Err(AdminError::UserNotFound(id));
+    }
+    cache.invalidate_user(id);
+    info!(user_id = %id, link_back = grant.require_link_back, %client_ip, "Verified user");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn revoke_verification(
+    VerificationPath { id }: VerificationPath,
+    _: AuthenticatedAdmin,
+    ClientIp(client_ip): ClientIp,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+    State(cache): State<Arc<ResponseCache>>,
+) -> Result<StatusCode> {
+    require_tenant_user(&db, &tenant.id, id).await?;
+    if !db.set_user_verified(id, false).await? {
+        return Err(AdminError::UserNotFound(id));
+    }
+    cache.invalidate_user(id);
+    info!(user_id = %id, %client_ip, "Revoked user verification");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/admin/users/{id}/quotas", rejection(ServerError))]
+struct UserQuotasPath {
+    id: Id<UserMarker>,
+}
+
+/// Only the overrides of the user, the instance defaults apply where they are `None`.
+async fn get_user_quotas(
+    UserQuotasPath { id }: UserQuotasPath,
+    _: AuthenticatedAdmin,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<UserQuotas>> {
+    require_tenant_user(&db, &tenant.id, id).await?;
+
+    let quotas = db.fetch_user_quotas(id).await?;
+
+    Ok(Json(quotas))
+}
+
+async fn set_user_quotas(
+    UserQuotasPath { id }: UserQuotasPath,
+    _: AuthenticatedAdmin,
+    ClientIp(client_ip): ClientIp,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+    Json(quotas): Json<UserQuotas>,
+) -> Result<StatusCode> {
+    require_tenant_user(&db, &tenant.id, id).await?;
+
+    db.set_user_quotas(id, quotas).await?;
+    info!(user_id = %id, ?quotas, %client_ip, "Set user quotas");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/admin/reports")]
+struct ReportsPath;
+
+/// Returns all reports about users of the tenant, including resolved ones.
+async fn get_reports(
+    _: ReportsPath,
+    _: AuthenticatedAdmin,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<PageRequest<ReportMarker>>,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+) -> Result<(HeaderMap, Json<Vec<Report>>)> {
+    report_page(&db, &tenant.id, None, uri.path(), &query).await
+}
+
+#[derive(TypedPath)]
+#[typed_path("/admin/reports/open")]
+struct OpenReportsPath;
+
+/// Returns the queue of unresolved reports about users of the tenant.
+async fn get_open_reports(
+    _: OpenReportsPath,
+    _: AuthenticatedAdmin,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<PageRequest<ReportMarker>>,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+) -> Result<(HeaderMap, Json<Vec<Report>>)> {
+    report_page(&db, &tenant.id, Some(false), uri.path(), &query).await
+}
+
+async fn report_page(
+    db: &DbClient,
+    tenant: &TenantId,
+    resolved: Option<bool>,
+    path: &str,
+    query: &PageRequest<ReportMarker>,
+) -> Result<(HeaderMap, Json<Vec<Report>>)> {
+    let limit = query.limit();
+    let reports = db
+        .fetch_reports(tenant, resolved, query.max_id, query.since_id, limit)
+        .await?;
+
+    let ids: Vec<_> = reports.iter().map(|report| report.id).collect();
+    let headers = link_headers(path, limit, &ids);
+
+    Ok((headers, Json(reports)))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/admin/reports/{id}", rejection(ServerError))]
+struct ReportPath {
+    id: Id<ReportMarker>,
+}
+
+async fn delete_report(
+    ReportPath { id }: ReportPath,
+    _: AuthenticatedAdmin,
+    ClientIp(client_ip): ClientIp,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+) -> Result<StatusCode> {
+    if db.fetch_report(&tenant.id, id).await?.is_none() || !db.delete_report(id).await? {
+        return Err(AdminError::ReportNotFound(id));
+    }
+    info!(report_id = %id, %client_ip, "Deleted report");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/admin/moderation-log")]
+struct ModerationLogPath;
+
+/// Returns what moderators of the tenant did, newest first.
+async fn get_moderation_log(
+    _: ModerationLogPath,
+    _: AuthenticatedAdmin,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<PageRequest<ModerationLogMarker>>,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+) -> Result<(HeaderMap, Json<Vec<ModerationLogEntry>>)> {
+    let limit = query.limit();
+    let entries = db
+        .fetch_moderation_log(&tenant.id, query.max_id, query.since_id, limit)
+        .await?;
+
+    let ids: Vec<_> = entries.iter().map(|entry| entry.id).collect();
+    let headers = link_headers(uri.path(), limit, &ids);
+
+    Ok((headers, Json(entries)))
+}
+
+#[derive(TypedPath)]
+#[typed_path("/admin/announcements")]
+struct AnnouncementsPath;
+
+/// All announcements, including expired ones, newest first.
+async fn get_announcements(
+    _: AnnouncementsPath,
+    _: AuthenticatedAdmin,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<PageRequest<AnnouncementMarker>>,
+    State(db): State<Arc<DbClient>>,
+) -> Result<(HeaderMap, Json<Vec<Announcement>>)> {
+    let limit = query.limit();
+    let announcements = db
+        .fetch_announcements(query.max_id, query.since_id, limit)
+        .await?;
+
+    let ids: Vec<_> = announcements
+        .iter()
+        .map(|announcement| announcement.id)
+        .collect();
+    let headers = link_headers(uri.path(), limit, &ids);
+
+    Ok((headers, Json(announcements)))
+}
+
+async fn create_announcement(
+    _: AnnouncementsPath,
+    _: AuthenticatedAdmin,
+    ClientIp(client_ip): ClientIp,
+    State(db): State<Arc<DbClient>>,
+    Json(announcement): Json<CreateAnnouncement>,
+) -> Result<(StatusCode, Json<Announcement>)> {
+    let id = db.create_announcement(&announcement).await?;
+    info!(announcement_id = %id, %client_ip, "Created announcement");
+
+    Ok((
+        StatusCode::CREATED,
+        Json(Announcement {
+            id,
+            title: announcement.title,
+            body: announcement.body,
+            expires_at: announcement.expires_at,
+        }),
+    ))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/admin/announcements/{id}", rejection(ServerError))]
+struct AnnouncementPath {
+    id: Id<AnnouncementMarker>,
+}
+
+async fn delete_announcement(
+    AnnouncementPath { id }: AnnouncementPath,
+    _: AuthenticatedAdmin,
+    ClientIp(client_ip): ClientIp,
+    State(db): State<Arc<DbClient>>,
+) -> Result<StatusCode> {
+    if !db.delete_announcement(id).await? {
+        return Err(AdminError::AnnouncementNotFound(id));
+    }
+    info!(announcement_id = %id, %client_ip, "Deleted announcement");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/admin/policies/{kind}", rejection(ServerError))]
+struct PolicyPath {
+    kind: PolicyKind,
+}
+
+/// Publishes the next version of the policy.
+/// Users cannot change anything until they accept it, see [`policies`](super::policies).
+async fn publish_policy(
+    PolicyPath { kind }: PolicyPath,
+    _: AuthenticatedAdmin,
+    ClientIp(client_ip): ClientIp,
+    State(db): State<Arc<DbClient>>,
+    Json(PublishPolicy { content }): Json<PublishPolicy>,
+) -> Result<(StatusCode, Json<Policy>)> {
+    let policy = db.publish_policy(kind, &content).await?;
+    info!(%kind, version = policy.version, %client_ip, "Published policy");
+
+    Ok((StatusCode::CREATED, Json(policy)))
+}
+
+#[derive(TypedPath)]
+#[typed_path("/admin/instance")]
+struct InstancePath;
+
+/// The settings are those of the tenant of the request, the stats those of all tenants.
+async fn get_instance(
+    _: InstancePath,
+    _: AuthenticatedAdmin,
+    CurrentTenant(tenant): CurrentTenant,
+    State(feature_flags): State<Arc<FeatureFlags>>,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<InstanceOverview>> {
+    let stats = db.fetch_instance_stats().await?;
+
+    Ok(Json(InstanceOverview {
+        settings: InstanceInfo {
+            features: feature_flags.features(),
+            ..tenant.info.clone()
+        },
+        stats,
+        clock_regressions: db.snowflake_generator().clock_regressions(),
+    }))
+}
+
+#[derive(TypedPath)]
+#[typed_path("/admin/stats")]
+struct StatsPath;
+
+/// Signups, active users, posts and auth tokens over the last day and week.
+async fn get_stats(
+    _: StatsPath,
+    _: AuthenticatedAdmin,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<ActivityStats>> {
+    let stats = db.fetch_activity_stats().await?;
+
+    Ok(Json(stats))
+}
+
+#[derive(TypedPath)]
+#[typed_path("/admin/analytics")]
+struct AnalyticsPath;
+
+/// The rollups of the last `days` days, including today, by metric and oldest first.
+/// Buckets without any activity are left out.
+async fn get_analytics(
+    _: AnalyticsPath,
+    _: AuthenticatedAdmin,
+    Query(query): Query<AnalyticsQuery>,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<Vec<AnalyticsRollup>>> {
+    let today = UtcDateTime::now().date().midnight().as_utc();
+    let since = today - Duration::from_days(u64::from(query.days()) - 1);
+    let rollups = db.fetch_analytics(query.metric, since).await?;
+
+    Ok(Json(rollups))
+}
+
+#[derive(TypedPath)]
+#[typed_path("/admin/tokens/purge")]
+struct PurgeTokensPath;
+
+/// Deletes or archives expired tokens now, instead of waiting for the periodic cleanup.
+async fn purge_expired_tokens(
+    _: PurgeTokensPath,
+    _: AuthenticatedAdmin,
+    ClientIp(client_ip): ClientIp,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<TokenPurge>> {
+    let deleted = db.drop_expired_tokens().await?;
+    info!(deleted, %client_ip, "Purged expired tokens");
+
+    Ok(Json(TokenPurge { deleted }))
+}
+
+#[derive(TypedPath)]
+#[typed_path("/admin/database/queries")]
+struct QueryStatsPath;
+
+/// Statistics of the database queries of this process since it started,
+/// see [`DbClient::query_stats`].
+async fn get_query_stats(
+    _: QueryStatsPath,
+    _: AuthenticatedAdmin,
+    State(db): State<Arc<DbClient>>,
+) -> Json<Vec<QueryStats>> {
+    Json(db.query_stats())
+}
+
+/// Exempt from read-only mode, see [`read_only`](crate::server::read_only).
+#[derive(TypedPath)]
+#[typed_path("/admin/read-only")]
+struct ReadOnlyPath;
+
+async fn get_read_only(
+    _: ReadOnlyPath,
+    _: AuthenticatedAdmin,
+    State(read_only): State<Arc<ReadOnly>>,
+) -> Json<ReadOnlyMode> {
+    Json(ReadOnlyMode {
+        enabled: read_only.is_enabled(),
+    })
+}
+
+async fn set_read_only(
+    _: ReadOnlyPath,
+    _: AuthenticatedAdmin,
+    ClientIp(client_ip): ClientIp,
+    State(read_only): State<Arc<ReadOnly>>,
+    Json(ReadOnlyMode { enabled }): Json<ReadOnlyMode>,
+) -> Json<ReadOnlyMode> {
+    if read_only.set_enabled(enabled) != enabled {
+        if enabled {
+            warn!(%client_ip, "Enabled read-only mode");
+        } else {
+            warn!(%client_ip, "Disabled read-only mode");
+        }
+    }
+
+    Json(ReadOnlyMode { enabled })
+}
+
+#[derive(TypedPath)]
+#[typed_path("/admin/features")]
+struct FeatureFlagsPath;
+
+async fn get_feature_flags(
+    _: FeatureFlagsPath,
+    _: AuthenticatedAdmin,
+    State(feature_flags): State<Arc<FeatureFlags>>,
+) -> Json<Vec<FeatureFlagState>> {
+    Json(feature_flags.states())
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/admin/features/{flag}", rejection(ServerError))]
+struct FeatureFlagPath {
+    flag: FeatureFlag,
+}
+
+/// Overrides the configuration on all servers, which pick it up within their refresh interval.
+async fn set_feature_flag(
+    FeatureFlagPath { flag }: FeatureFlagPath,
+    _: AuthenticatedAdmin,
+    ClientIp(client_ip): ClientIp,
+    State(db): State<Arc<DbClient>>,
+    State(feature_flags): State<Arc<FeatureFlags>>,
+    State(response_cache): State<Arc<ResponseCache>>,
+    Json(SetFeatureFlag { enabled }): Json<SetFeatureFlag>,
+) -> Result<Json<FeatureFlagState>> {
+    db.set_feature_flag(flag, enabled).await?;
+    feature_flags.set(flag, Some(enabled));
+    invalidate_gated_responses(&response_cache, flag);
+    warn!(%flag, enabled, %client_ip, "Set feature flag");
+
+    Ok(Json(feature_flags.state(flag)))
+}
+
+/// Lets the flag follow the configuration of each server again.
+async fn reset_feature_flag(
+    FeatureFlagPath { flag }: FeatureFlagPath,
+    _: AuthenticatedAdmin,
+    ClientIp(client_ip): ClientIp,
+    State(db): State<Arc<DbClient>>,
+    State(feature_flags): State<Arc<FeatureFlags>>,
+    State(response_cache): State<Arc<ResponseCache>>,
+) -> Result<StatusCode> {
+    db.reset_feature_flag(flag).await?;
+    feature_flags.set(flag, None);
+    invalidate_gated_responses(&response_cache, flag);
+    warn!(%flag, %client_ip, "Reset feature flag");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// So that this server does not keep serving cached responses of a disabled feature.
+fn invalidate_gated_responses(response_cache: &ResponseCache, flag: FeatureFlag) {
+    match flag {
+        FeatureFlag::PublicTimeline => response_cache.invalidate_public_timeline(),
+        FeatureFlag::MastodonApi => {}
+    }
+}
+
+#[derive(TypedPath)]
+#[typed_path("/admin/spam")]
+struct SpamSettingsPath;
+
+async fn get_spam_settings(
+    _: SpamSettingsPath,
+    _: AuthenticatedAdmin,
+    State(spam): State<Arc<SpamGuard>>,
+) -> Json<SpamSettings> {
+    Json(spam.settings())
+}
+
+/// Replaces the settings until the process restarts. Omitted fields are reset to their defaults.
+async fn set_spam_settings(
+    _: SpamSettingsPath,
+    _: AuthenticatedAdmin,
+    ClientIp(client_ip): ClientIp,
+    State(spam): State<Arc<SpamGuard>>,
+    Json(settings): Json<SpamSettings>,
+) -> Json<SpamSettings> {
+    spam.set_settings(settings);
+    info!(?settings, %client_ip, "Changed spam settings");
+
+    Json(settings)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/admin/jobs/dead")]
+struct DeadJobsPath;
+
+/// Jobs that failed permanently or too often, newest first.
+async fn get_dead_jobs(
+    _: DeadJobsPath,
+    _: AuthenticatedAdmin,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<PageRequest<JobMarker>>,
+    State(db): State<Arc<DbClient>>,
+) -> Result<(HeaderMap, Json<Vec<DeadJob>>)> {
+    let limit = query.limit();
+    let jobs = db
+        .fetch_dead_jobs(query.max_id, query.since_id, limit)
+        .await?;
+
+    let ids: Vec<_> = jobs.iter().map(|job| job.id).collect();
+    let headers = link_headers(uri.path(), limit, &ids);
+
+    Ok((headers, Json(jobs)))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/admin/jobs/dead/{id}", rejection(ServerError))]
+struct DeadJobPath {
+    id: Id<JobMarker>,
+}
+
+/// Deletes the job without running it again.
+async fn delete_dead_job(
+    DeadJobPath { id }: DeadJobPath,
+    _: AuthenticatedAdmin,
+    ClientIp(client_ip): ClientIp,
+    State(db): State<Arc<DbClient>>,
+) -> Result<StatusCode> {
+    if !db.delete_dead_job(id).await? {
+        return Err(AdminError::JobNotFound(id));
+    }
+    info!(job_id = %id, %client_ip, "Deleted dead-lettered job");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/admin/jobs/dead/{id}/retry", rejection(ServerError))]
+struct RetryDeadJobPath {
+    id: Id<JobMarker>,
+}
+
+/// Queues the job again, to be run right away with all its attempts.
+async fn retry_dead_job(
+    RetryDeadJobPath { id }: RetryDeadJobPath,
+    _: AuthenticatedAdmin,
+    ClientIp(client_ip): ClientIp,
+    State(db): State<Arc<DbClient>>,
+) -> Result<StatusCode> {
+    if !db.retry_dead_job(id, UtcDateTime::now()).await? {
+        return Err(AdminError::JobNotFound(id));
+    }
+    info!(job_id = %id, %client_ip, "Retried dead-lettered job");
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,82 @@
+//! Announcements of the admins as seen by each user, see [`admin`](super::admin) to manage them.
+
+use crate::server::{
+    Result, ServerError, ServerRouter, auth::AuthenticatedUser, json::Json, query::Query,
+};
+use axum::{extract::State, http::StatusCode};
+use axum_extra::routing::{RouterExt, TypedPath};
+use serde::Deserialize;
+use std::sync::Arc;
+use stellwerk_common::model::{
+    Id,
+    announcement::{AnnouncementMarker, UserAnnouncement},
+};
+use stellwerk_db::client::DbClient;
+
+pub fn routes() -> ServerRouter {
+    ServerRouter::new()
+        .typed_get(get_announcements)
+        .typed_post(read_announcement)
+        .typed_post(dismiss_announcement)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/announcements")]
+struct AnnouncementsPath;
+
+#[derive(Deserialize)]
+struct AnnouncementsQuery {
+    #[serde(default)]
+    with_dismissed: bool,
+}
+
+/// The announcements that have not expired, newest first.
+async fn get_announcements(
+    _: AnnouncementsPath,
+    Query(AnnouncementsQuery { with_dismissed }): Query<AnnouncementsQuery>,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<Vec<UserAnnouncement>>> {
+    let announcements = db
+        .fetch_user_announcements(user.user_id(), with_dismissed)
+        .await?;
+
+    Ok(Json(announcements))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/announcements/{id}/read", rejection(ServerError))]
+struct ReadAnnouncementPath {
+    id: Id<AnnouncementMarker>,
+}
+
+async fn read_announcement(
+    ReadAnnouncementPath { id }: ReadAnnouncementPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<StatusCode> {
+    if !db.read_announcement(user.user_id(), id).await? {
+        return Err(ServerError::AnnouncementByIdNotFound(id));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/announcements/{id}/dismiss", rejection(ServerError))]
+struct DismissAnnouncementPath {
+    id: Id<AnnouncementMarker>,
+}
+
+/// Hides the announcement from the user, unless they ask for dismissed ones.
+async fn dismiss_announcement(
+    DismissAnnouncementPath { id }: DismissAnnouncementPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<StatusCode> {
+    if !db.dismiss_announcement(user.user_id(), id).await? {
+        return Err(ServerError::AnnouncementByIdNotFound(id));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,243 @@
+use crate::server::{
+    Result, ServerError, ServerRouter, auth::AuthenticatedUser, json::Json,
+    pagination::link_headers, query::Query,
+};
+use axum::{
+    extract::{OriginalUri, State},
+    http::{HeaderMap, StatusCode},
+};
+use axum_extra::routing::{RouterExt, TypedPath};
+use serde::Deserialize;
+use std::{collections::BTreeSet, sync::Arc};
+use stellwerk_common::model::{
+    Id,
+    conversation::{
+        CONVERSATION_MAX_MEMBERS, Conversation, ConversationMarker, CreateMessage, Message,
+        MessageBody, MessageMarker,
+    },
+    pagination::PageRequest,
+    user::UserMarker,
+};
+use stellwerk_db::client::DbClient;
+
+pub fn routes() -> ServerRouter {
+    ServerRouter::new()
+        .typed_get(get_conversations)
+        .typed_post(create_conversation)
+        .typed_get(get_conversation)
+        .typed_post(add_member)
+        .typed_delete(remove_member)
+        .typed_get(get_messages)
+        .typed_post(create_message)
+        .typed_post(mark_read)
+}
+
+/// Fails with [`ServerError::ConversationByIdNotFound`] if `user` is not a member,
+/// so that non-members cannot learn which conversations exist.
+async fn fetch_own_conversation(
+    db: &DbClient,
+    user: AuthenticatedUser,
+    id: Id<ConversationMarker>,
+) -> Result<Conversation> {
+    db.fetch_conversation(id, user.user_id())
+        .await?
+        .ok_or(ServerError::ConversationByIdNotFound(id))
+}
+
+#[derive(TypedPath)]
+#[typed_path("/conversations")]
+struct ConversationsPath;
+
+async fn get_conversations(
+    _: ConversationsPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<Vec<Conversation>>> {
+    let conversations = db.fetch_conversations(user.user_id()).await?;
+
+    Ok(Json(conversations))
+}
+
+#[derive(Deserialize)]
+struct CreateConversationBody {
+    /// The other members. The creator is always a member.
+    members: BTreeSet<Id<UserMarker>>,
+}
+
+async fn create_conversation(
+    _: ConversationsPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+    Json(CreateConversationBody { mut members }): Json<CreateConversationBody>,
+) -> Result<(StatusCode, Json<Conversation>)> {
+    members.remove(&user.user_id());
+    if members.len() + 1 > CONVERSATION_MAX_MEMBERS {
+        return Err(ServerError::ConversationMemberLimitReached(
+            CONVERSATION_MAX_MEMBERS,
+        ));
+    }
+    for &member in &members {
+        if db.fetch_user(member).await?.is_none() {
+            return Err(ServerError::UserByIdNotFound(member));
+        }
+    }
+
+    let members: Vec<_> = members.into_iter().collect();
+    let id = db.create_conversation(user.user_id(), &members).await?;
+    let conversation = fetch_own_conversation(&db.read_your_writes(), user, id).await?;
+
+    Ok((StatusCode::CREATED, Json(conversation)))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/conversations/{id}", rejection(ServerError))]
+struct ConversationPath {
+    id: Id<ConversationMarker>,
+}
+
+async fn get_conversation(
+    ConversationPath { id }: ConversationPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<Conversation>> {
+    let conversation = fetch_own_conversation(&db, user, id).await?;
+
+    Ok(Json(conversation))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/conversations/{id}/members", rejection(ServerError))]
+struct MembersPath {
+    id: Id<ConversationMarker>,
+}
+
+#[derive(Deserialize)]
+struct AddMemberBody {
+    user: Id<UserMarker>,
+}
+
+/// Any member can add other users.
+async fn add_member(
+    MembersPath { id }: MembersPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+    Json(AddMemberBody { user: new_member }): Json<AddMemberBody>,
+) -> Result<StatusCode> {
+    fetch_own_conversation(&db, user, id).await?;
+    if db.fetch_user(new_member).await?.is_none() {
+        return Err(ServerError::UserByIdNotFound(new_member));
+    }
+
+    if !db
+        .add_conversation_member(id, new_member, CONVERSATION_MAX_MEMBERS)
+        .await?
+    {
+        return Err(ServerError::ConversationMemberLimitReached(
+            CONVERSATION_MAX_MEMBERS,
+        ));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/conversations/{id}/members/{user_id}", rejection(ServerError))]
+struct MemberPath {
+    id: Id<ConversationMarker>,
+    user_id: Id<UserMarker>,
+}
+
+/// Members can remove themselves, the creator can remove anyone.
+async fn remove_member(
+    MemberPath { id, user_id }: MemberPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<StatusCode> {
+    let conversation = fetch_own_conversation(&db, user, id).await?;
+    if user_id != user.user_id() && conversation.creator != user.user_id() {
+        return Err(ServerError::NotConversationCreator(id));
+    }
+
+    if !db.remove_conversation_member(id, user_id).await? {
+        return Err(ServerError::UserByIdNotFound(user_id));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/conversations/{id}/messages", rejection(ServerError))]
+struct MessagesPath {
+    id: Id<ConversationMarker>,
+}
+
+async fn get_messages(
+    path: MessagesPath,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<PageRequest<MessageMarker>>,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<(HeaderMap, Json<Vec<Message>>)> {
+    fetch_own_conversation(&db, user, path.id).await?;
+
+    let limit = query.limit();
+    let messages = db
+        .fetch_messages(path.id, query.max_id, query.since_id, limit)
+        .await?;
+
+    let ids: Vec<_> = messages.iter().map(|message| message.id).collect();
+    let headers = link_headers(uri.path(), limit, &ids);
+
+    Ok((headers, Json(messages)))
+}
+
+async fn create_message(
+    MessagesPath { id }: MessagesPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+    Json(body): Json<MessageBody>,
+) -> Result<(StatusCode, Json<Message>)> {
+    fetch_own_conversation(&db, user, id).await?;
+
+    let message = CreateMessage {
+        conversation: id,
+        author: user.user_id(),
+        body,
+    };
+    let message_id = db.create_message(&message).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(Message {
+            id: message_id,
+            author: message.author,
+            body: message.body,
+        }),
+    ))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/conversations/{id}/read", rejection(ServerError))]
+struct MarkReadPath {
+    id: Id<ConversationMarker>,
+}
+
+#[derive(Deserialize)]
+struct MarkReadQuery {
+    /// If absent, all messages are marked as read.
+    max_id: Option<Id<MessageMarker>>,
+}
+
+async fn mark_read(
+    MarkReadPath { id }: MarkReadPath,
+    Query(MarkReadQuery { max_id }): Query<MarkReadQuery>,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<StatusCode> {
+    fetch_own_conversation(&db, user, id).await?;
+
+    db.mark_conversation_read(id, user.user_id(), max_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,89 @@
+use crate::server::{
+    Result, ServerError, ServerRouter, auth::AuthenticatedUser, json::Json, query::Query,
+    route_group::RouteGroup,
+};
+use axum::{extract::State, http::StatusCode};
+use axum_extra::routing::{RouterExt, TypedPath};
+use serde::Deserialize;
+use std::sync::Arc;
+use stellwerk_common::model::email::{EmailDigestSettings, UnsubscribeToken};
+use stellwerk_db::client::DbClient;
+
+/// Unsubscribe tokens must not be guessable.
+pub const ROUTE_GROUPS: &[(&str, RouteGroup)] = &[(UnsubscribePath::PATH, RouteGroup::Auth)];
+
+pub fn routes() -> ServerRouter {
+    ServerRouter::new()
+        .typed_get(get_digest_settings)
+        .typed_put(set_digest_settings)
+        .typed_delete(delete_digest_settings)
+        .typed_get(unsubscribe)
+        .typed_post(unsubscribe)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/email/digest")]
+struct DigestSettingsPath;
+
+async fn get_digest_settings(
+    _: DigestSettingsPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<EmailDigestSettings>> {
+    let settings = db
+        .fetch_email_digest_settings(user.user_id())
+        .await?
+        .ok_or(ServerError::EmailDigestNotFound)?;
+
+    Ok(Json(settings))
+}
+
+async fn set_digest_settings(
+    _: DigestSettingsPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+    Json(settings): Json<EmailDigestSettings>,
+) -> Result<Json<EmailDigestSettings>> {
+    db.set_email_digest_settings(user.user_id(), &settings)
+        .await?;
+
+    Ok(Json(settings))
+}
+
+async fn delete_digest_settings(
+    _: DigestSettingsPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<StatusCode> {
+    if !db.delete_email_digest(user.user_id()).await? {
+        return Err(ServerError::EmailDigestNotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/email/unsubscribe")]
+struct UnsubscribePath;
+
+#[derive(Deserialize)]
+struct UnsubscribeQuery {
+    token: String,
+}
+
+/// Linked from digest emails. Supports both opening the link and one-click unsubscribing (RFC 8058).
+async fn unsubscribe(
+    _: UnsubscribePath,
+    Query(UnsubscribeQuery { token }): Query<UnsubscribeQuery>,
+    State(db): State<Arc<DbClient>>,
+) -> Result<StatusCode> {
+    let token: UnsubscribeToken = token
+        .parse()
+        .map_err(|_| ServerError::UnknownUnsubscribeToken)?;
+
+    if !db.unsubscribe_email_digest(&token).await? {
+        return Err(ServerError::UnknownUnsubscribeToken);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,107 @@
+use crate::server::{Result, ServerError, ServerRouter, auth::AuthenticatedUser, json::Json};
+use axum::{extract::State, http::StatusCode};
+use axum_extra::routing::{RouterExt, TypedPath};
+use serde::Deserialize;
+use std::sync::Arc;
+use stellwerk_common::model::{
+    Id, ModelValidationError,
+    filter::{Filter, FilterMarker, FilterSettings, UpdateFilter},
+};
+use stellwerk_db::client::DbClient;
+
+pub fn routes() -> ServerRouter {
+    ServerRouter::new()
+        .typed_get(get_filters)
+        .typed_post(create_filter)
+        .typed_get(get_filter)
+        .typed_put(update_filter)
+        .typed_delete(delete_filter)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/filters")]
+struct FiltersPath;
+
+async fn get_filters(
+    _: FiltersPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<Vec<Filter>>> {
+    let filters = db.fetch_filters(user.user_id()).await?;
+
+    Ok(Json(filters))
+}
+
+async fn create_filter(
+    _: FiltersPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+    Json(settings): Json<FilterSettings>,
+) -> Result<(StatusCode, Json<Filter>)> {
+    settings.compile().map_err(ModelValidationError::from)?;
+
+    let id = db.create_filter(user.user_id(), &settings).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(Filter {
+            id,
+            user: user.user_id(),
+            settings,
+            version: 0,
+        }),
+    ))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/filters/{id}", rejection(ServerError))]
+struct FilterPath {
+    id: Id<FilterMarker>,
+}
+
+async fn get_filter(
+    FilterPath { id }: FilterPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<Filter>> {
+    let filter = db
+        .fetch_filter(id)
+        .await?
+        .filter(|filter| filter.user == user.user_id())
+        .ok_or(ServerError::FilterByIdNotFound(id))?;
+
+    Ok(Json(filter))
+}
+
+async fn update_filter(
+    FilterPath { id }: FilterPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+    Json(UpdateFilter { settings, version }): Json<UpdateFilter>,
+) -> Result<Json<Filter>> {
+    settings.compile().map_err(ModelValidationError::from)?;
+
+    let version = db
+        .update_filter(user.user_id(), id, &settings, version)
+        .await?
+        .ok_or(ServerError::FilterByIdNotFound(id))?;
+
+    Ok(Json(Filter {
+        id,
+        user: user.user_id(),
+        settings,
+        version,
+    }))
+}
+
+async fn delete_filter(
+    FilterPath { id }: FilterPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<StatusCode> {
+    if !db.delete_filter(user.user_id(), id).await? {
+        return Err(ServerError::FilterByIdNotFound(id));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,45 @@
+//! `/readyz`, for load balancers and orchestrators to check whether the server can take requests.
+
+use crate::server::{ServerRouter, json::Json};
+use axum::{extract::State, http::StatusCode};
+use axum_extra::routing::{RouterExt, TypedPath};
+use std::sync::Arc;
+use stellwerk_common::model::health::Readiness;
+use stellwerk_db::client::DbClient;
+use tracing::debug;
+
+pub fn routes() -> ServerRouter {
+    ServerRouter::new().typed_get(get_readiness)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/readyz")]
+struct ReadinessPath;
+
+/// Pings the database, so that an open circuit breaker is also checked without other traffic.
+/// Answers with `503 Service Unavailable` if the database did not answer.
+async fn get_readiness(
+    _: ReadinessPath,
+    State(db): State<Arc<DbClient>>,
+) -> (StatusCode, Json<Readiness>) {
+    let ready = match db.ping().await {
+        Ok(()) => true,
+        Err(error) => {
+            debug!(%error, "Not ready, since the database did not answer");
+            false
+        }
+    };
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        Json(Readiness {
+            ready,
+            circuit_breaker: db.circuit_breaker_stats(),
+        }),
+    )
+}
@@ -0,0 +1,71 @@
+use crate::{
+    federation::Federation,
+    server::{Result, ServerError, ServerRouter, activitypub::SignedActivity},
+};
+use axum::{extract::State, http::StatusCode};
+use axum_extra::routing::{RouterExt, TypedPath};
+use serde::Deserialize;
+use std::sync::Arc;
+use stellwerk_common::model::{Id, instance::InstanceInfo, user::UserMarker};
+
+pub fn routes() -> ServerRouter {
+    ServerRouter::new()
+        .typed_post(post_shared_inbox)
+        .typed_post(post_user_inbox)
+        .typed_post(post_instance_actor_inbox)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/inbox")]
+struct SharedInboxPath;
+
+async fn post_shared_inbox(
+    _: SharedInboxPath,
+    State(federation): State<Arc<Federation>>,
+    State(instance): State<Arc<InstanceInfo>>,
+    activity: SignedActivity,
+) -> Result<StatusCode> {
+    receive(&federation, &instance, activity).await
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/users/{id}/inbox", rejection(ServerError))]
+struct UserInboxPath {
+    id: Id<UserMarker>,
+}
+
+/// Activities are addressed by their content, so they are handled the same as in the shared
+/// inbox.
+async fn post_user_inbox(
+    _: UserInboxPath,
+    State(federation): State<Arc<Federation>>,
+    State(instance): State<Arc<InstanceInfo>>,
+    activity: SignedActivity,
+) -> Result<StatusCode> {
+    receive(&federation, &instance, activity).await
+}
+
+#[derive(TypedPath)]
+#[typed_path("/actor/inbox")]
+struct InstanceActorInboxPath;
+
+async fn post_instance_actor_inbox(
+    _: InstanceActorInboxPath,
+    State(federation): State<Arc<Federation>>,
+    State(instance): State<Arc<InstanceInfo>>,
+    activity: SignedActivity,
+) -> Result<StatusCode> {
+    receive(&federation, &instance, activity).await
+}
+
+async fn receive(
+    federation: &Federation,
+    instance: &InstanceInfo,
+    SignedActivity { signer, activity }: SignedActivity,
+) -> Result<StatusCode> {
+    federation
+        .receive(&signer, activity, instance.limits.post_content_max_len)
+        .await?;
+
+    Ok(StatusCode::ACCEPTED)
+}
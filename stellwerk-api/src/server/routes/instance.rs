@@ -0,0 +1,55 @@
+use crate::{
+    federation::Federation,
+    server::{
+        Result, ServerRouter, activitypub::ActivityJson, feature_flags::FeatureFlags, json::Json,
+        tenant::CurrentTenant,
+    },
+};
+use axum::extract::State;
+use axum_extra::routing::{RouterExt, TypedPath};
+use std::sync::Arc;
+use stellwerk_common::model::{activitypub::Actor, instance::InstanceInfo};
+
+pub fn routes() -> ServerRouter {
+    ServerRouter::new().typed_get(get_instance)
+}
+
+pub fn activitypub_routes() -> ServerRouter {
+    ServerRouter::new().typed_get(get_instance_actor)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/instance")]
+struct GetInstancePath;
+
+/// Of the tenant of the request,
+/// with the features as the [feature flags](crate::server::feature_flags) currently are.
+async fn get_instance(
+    _: GetInstancePath,
+    CurrentTenant(tenant): CurrentTenant,
+    State(feature_flags): State<Arc<FeatureFlags>>,
+) -> Json<InstanceInfo> {
+    Json(InstanceInfo {
+        features: feature_flags.features(),
+        ..tenant.info.clone()
+    })
+}
+
+#[derive(TypedPath)]
+#[typed_path("/actor")]
+struct GetInstanceActorPath;
+
+/// Signatures are deliberately not verified here, since verifying them requires fetching the
+/// signer's key, which other servers would sign with their instance actor in turn.
+async fn get_instance_actor(
+    _: GetInstanceActorPath,
+    State(instance): State<Arc<InstanceInfo>>,
+    State(federation): State<Arc<Federation>>,
+) -> Result<ActivityJson<Actor>> {
+    let key_pair = federation.instance_key_pair().await?;
+
+    Ok(ActivityJson(Actor::for_instance(
+        &instance.public_url,
+        key_pair.public_key_pem,
+    )))
+}
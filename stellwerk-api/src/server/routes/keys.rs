@@ -0,0 +1,48 @@
+use crate::server::{Result, ServerError, ServerRouter, auth::AuthenticatedUser, json::Json};
+use axum::{extract::State, http::StatusCode};
+use axum_extra::routing::{RouterExt, TypedPath};
+use std::sync::Arc;
+use stellwerk_common::model::keys::{KeyStatus, ONE_TIME_PREKEYS_MAX, PublishKeys};
+use stellwerk_db::client::DbClient;
+
+pub fn routes() -> ServerRouter {
+    ServerRouter::new()
+        .typed_get(get_key_status)
+        .typed_put(publish_keys)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/keys")]
+struct KeysPath;
+
+async fn get_key_status(
+    _: KeysPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<KeyStatus>> {
+    let status = db
+        .fetch_key_status(user.user_id())
+        .await?
+        .ok_or(ServerError::KeysNotFound(user.user_id()))?;
+
+    Ok(Json(status))
+}
+
+/// Impersonation tokens cannot publish keys, which would let admins read later messages.
+async fn publish_keys(
+    _: KeysPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+    Json(keys): Json<PublishKeys>,
+) -> Result<StatusCode> {
+    let user = user.not_impersonated()?;
+    if keys.one_time_prekeys.len() > ONE_TIME_PREKEYS_MAX
+        || !db
+            .publish_keys(user.user_id(), &keys, ONE_TIME_PREKEYS_MAX)
+            .await?
+    {
+        return Err(ServerError::OneTimePrekeyLimitReached(ONE_TIME_PREKEYS_MAX));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,425 @@
+//! A subset of the [Mastodon client API](https://docs.joinmastodon.org/methods/),
+//! translated onto stellwerk's routes, so that existing Mastodon apps can be used.
+//!
+//! Only available if the [`FeatureFlag::MastodonApi`] is enabled.
+//! There is no OAuth flow, so clients authenticate with regular stellwerk tokens.
+
+use crate::{
+    federation::Federation,
+    server::{
+        Result, ServerError, ServerRouter,
+        analytics::Analytics,
+        auth::AuthenticatedUser,
+        content_filter::ContentFilters,
+        feature_flags::FeatureFlags,
+        json::Json,
+        pagination::link_headers,
+        query::Query,
+        quota::Quotas,
+        response_cache::ResponseCache,
+        route_group::RouteGroup,
+        routes::{posts, users},
+        spam::SpamGuard,
+        tenant::CurrentTenant,
+    },
+};
+use axum::{
+    extract::{FromRef, FromRequestParts, State},
+    http::{HeaderMap, request::Parts},
+};
+use axum_extra::routing::{RouterExt, TypedPath};
+use serde::Deserialize;
+use std::{collections::HashMap, sync::Arc, time::Instant};
+use stellwerk_common::model::{
+    Id,
+    feature::FeatureFlag,
+    filter::{FilterContext, FilterMatcher},
+    instance::InstanceInfo,
+    mastodon::{Account, CredentialAccount, Relationship, Status},
+    pagination::PageRequest,
+    post::{Post, PostContent, PostMarker},
+    user::UserMarker,
+};
+use stellwerk_db::client::DbClient;
+use time::UtcDateTime;
+
+/// Clients use this to check tokens.
+pub const ROUTE_GROUPS: &[(&str, RouteGroup)] = &[(VerifyCredentialsPath::PATH, RouteGroup::Auth)];
+
+pub fn routes() -> ServerRouter {
+    ServerRouter::new()
+        .typed_get(verify_credentials)
+        .typed_get(get_account)
+        .typed_get(get_account_statuses)
+        .typed_post(follow_account)
+        .typed_post(unfollow_account)
+        .typed_get(get_home_timeline)
+        .typed_get(get_public_timeline)
+        .typed_get(get_status)
+        .typed_post(create_status)
+}
+
+/// Rejects requests if the Mastodon API is disabled on this instance.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
+struct MastodonApi;
+
+impl<S> FromRequestParts<S> for MastodonApi
+where
+    Arc<FeatureFlags>: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = ServerError;
+
+    async fn from_request_parts(_parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if Arc::<FeatureFlags>::from_ref(state).is_enabled(FeatureFlag::MastodonApi) {
+            Ok(Self)
+        } else {
+            Err(ServerError::MastodonApiDisabled)
+        }
+    }
+}
+
+async fn account(db: &DbClient, instance: &InstanceInfo, id: Id<UserMarker>) -> Result<Account> {
+    let profile = db
+        .fetch_user_profile(id)
+        .await?
+        .ok_or(ServerError::UserByIdNotFound(id))?;
+
+    Ok(Account::for_profile(&profile, &instance.public_url))
+}
+
+/// Converts posts to statuses, fetching the profiles of their authors.
+async fn statuses(
+    db: &DbClient,
+    instance: &InstanceInfo,
+    posts: Vec<Post>,
+    context: FilterContext,
+) -> Result<Vec<Status>> {
+    let mut author_ids: Vec<_> = posts.iter().map(|post| post.author.id).collect();
+    author_ids.sort_unstable();
+    author_ids.dedup();
+
+    let accounts: HashMap<_, _> = db
+        .fetch_user_profiles(&author_ids)
+        .await?
+        .into_iter()
+        .map(|profile| {
+            (
+                profile.user.id,
+                Account::for_profile(&profile, &instance.public_url),
+            )
+        })
+        .collect();
+
+    Ok(posts
+        .iter()
+        .filter_map(|post| {
+            let account = accounts.get(&post.author.id)?.clone();
+            Some(Status::for_post(
+                post,
+                account,
+                context,
+                &instance.public_url,
+            ))
+        })
+        .collect())
+}
+
+/// Link headers with absolute URLs, which Mastodon clients expect.
+fn absolute_link_headers<Marker>(
+    instance: &InstanceInfo,
+    path: &str,
+    limit: u32,
+    ids: &[Id<Marker>],
+) -> HeaderMap {
+    link_headers(&format!("{}{path}", instance.public_url), limit, ids)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/api/v1/accounts/verify_credentials")]
+struct VerifyCredentialsPath;
+
+async fn verify_credentials(
+    _: VerifyCredentialsPath,
+    _: MastodonApi,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+    CurrentTenant(tenant): CurrentTenant,
+) -> Result<Json<CredentialAccount>> {
+    let account = account(&db, &tenant.info, user.user_id()).await?;
+
+    Ok(Json(CredentialAccount::new(account)))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/api/v1/accounts/{id}", rejection(ServerError))]
+struct GetAccountPath {
+    id: Id<UserMarker>,
+}
+
+async fn get_account(
+    GetAccountPath { id }: GetAccountPath,
+    _: MastodonApi,
+    State(db): State<Arc<DbClient>>,
+    CurrentTenant(tenant): CurrentTenant,
+) -> Result<Json<Account>> {
+    Ok(Json(account(&db, &tenant.info, id).await?))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/api/v1/accounts/{id}/statuses", rejection(ServerError))]
+struct GetAccountStatusesPath {
+    id: Id<UserMarker>,
+}
+
+#[derive(Deserialize)]
+struct AccountStatusesQuery {
+    /// Clients fetch pinned statuses separately, so they are only returned if this is set.
+    #[serde(default)]
+    pinned: bool,
+}
+
+async fn get_account_statuses(
+    path: GetAccountStatusesPath,
+    _: MastodonApi,
+    Query(query): Query<PageRequest<PostMarker>>,
+    Query(AccountStatusesQuery { pinned }): Query<AccountStatusesQuery>,
+    State(db): State<Arc<DbClient>>,
+    CurrentTenant(tenant): CurrentTenant,
+) -> Result<(HeaderMap, Json<Vec<Status>>)> {
+    let id = path.id;
+    let limit = query.limit();
+    let profile = db
+        .fetch_user_profile(id)
+        .await?
+        .ok_or(ServerError::UserByIdNotFound(id))?;
+    let account = Account::for_profile(&profile, &tenant.info.public_url);
+
+    let posts: Vec<_> = db
+        .fetch_user_posts(id, query.max_id, query.since_id, limit)
+        .await?
+        .ok_or(ServerError::UserByIdNotFound(id))?
+        .into_iter()
+        .filter(|post| post.pinned == pinned)
+        .collect();
+
+    let headers = if pinned {
+        HeaderMap::new()
+    } else {
+        let ids: Vec<_> = posts.iter().map(|post| post.id).collect();
+        absolute_link_headers(&tenant.info, &path.to_string(), limit, &ids)
+    };
+    let statuses = posts
+        .into_iter()
+        .map(|post| {
+            let post = Post {
+                id: post.id,
+                author: profile.user.clone(),
+                content: post.content,
+                content_html: post.content_html,
+                pinned: post.pinned,
+                filtered: Vec::new(),
+            };
+            Status::for_post(
+                &post,
+                account.clone(),
+                FilterContext::Public,
+                &tenant.info.public_url,
+            )
+        })
+        .collect();
+
+    Ok((headers, Json(statuses)))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/api/v1/accounts/{id}/follow", rejection(ServerError))]
+struct FollowAccountPath {
+    id: Id<UserMarker>,
+}
+
+async fn follow_account(
+    FollowAccountPath { id }: FollowAccountPath,
+    _: MastodonApi,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+    State(cache): State<Arc<ResponseCache>>,
+    State(spam): State<Arc<SpamGuard>>,
+    State(quotas): State<Arc<Quotas>>,
+) -> Result<Json<Relationship>> {
+    users::follow(&db, &cache, &spam, &quotas, user.user_id(), id).await?;
+    let followed_by = db.is_following(id, user.user_id()).await?;
+
+    Ok(Json(Relationship::new(id.to_string(), true, followed_by)))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/api/v1/accounts/{id}/unfollow", rejection(ServerError))]
+struct UnfollowAccountPath {
+    id: Id<UserMarker>,
+}
+
+async fn unfollow_account(
+    UnfollowAccountPath { id }: UnfollowAccountPath,
+    _: MastodonApi,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+    State(cache): State<Arc<ResponseCache>>,
+) -> Result<Json<Relationship>> {
+    users::unfollow(&db, &cache, user.user_id(), id).await?;
+    let followed_by = db.is_following(id, user.user_id()).await?;
+
+    Ok(Json(Relationship::new(id.to_string(), false, followed_by)))
+}
+
+#[derive(TypedPath)]
+#[typed_path("/api/v1/timelines/home")]
+struct GetHomeTimelinePath;
+
+async fn get_home_timeline(
+    _: GetHomeTimelinePath,
+    _: MastodonApi,
+    Query(query): Query<PageRequest<PostMarker>>,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+    State(analytics): State<Arc<Analytics>>,
+    CurrentTenant(tenant): CurrentTenant,
+) -> Result<(HeaderMap, Json<Vec<Status>>)> {
+    let started = Instant::now();
+    let limit = query.limit();
+    let posts = db
+        .fetch_home_posts(user.user_id(), query.max_id, query.since_id, limit)
+        .await?;
+
+    // Cursors refer to the unfiltered page so that hidden posts do not end pagination early.
+    let ids: Vec<_> = posts.iter().map(|post| post.id).collect();
+    let headers = absolute_link_headers(&tenant.info, GetHomeTimelinePath::PATH, limit, &ids);
+
+    let filters = db.fetch_filters(user.user_id()).await?;
+    let posts = FilterMatcher::new(&filters, FilterContext::Home, UtcDateTime::now()).apply(posts);
+    let statuses = statuses(&db, &tenant.info, posts, FilterContext::Home).await?;
+    analytics.record_timeline_latency(started.elapsed());
+
+    Ok((headers, Json(statuses)))
+}
+
+#[derive(TypedPath)]
+#[typed_path("/api/v1/timelines/public")]
+struct GetPublicTimelinePath;
+
+#[allow(clippy::too_many_arguments)] // Each argument is an extractor.
+async fn get_public_timeline(
+    _: GetPublicTimelinePath,
+    _: MastodonApi,
+    Query(query): Query<PageRequest<PostMarker>>,
+    user: Option<AuthenticatedUser>,
+    State(db): State<Arc<DbClient>>,
+    State(feature_flags): State<Arc<FeatureFlags>>,
+    State(analytics): State<Arc<Analytics>>,
+    CurrentTenant(tenant): CurrentTenant,
+) -> Result<(HeaderMap, Json<Vec<Status>>)> {
+    if !feature_flags.is_enabled(FeatureFlag::PublicTimeline) {
+        return Err(ServerError::PublicTimelineDisabled);
+    }
+    let started = Instant::now();
+
+    let limit = query.limit();
+    let posts = db
+        .fetch_public_posts(
+            &tenant.id,
+            user.map(AuthenticatedUser::user_id),
+            query.max_id,
+            query.since_id,
+            limit,
+        )
+        .await?;
+
+    // Cursors refer to the unfiltered page so that hidden posts do not end pagination early.
+    let ids: Vec<_> = posts.iter().map(|post| post.id).collect();
+    let headers = absolute_link_headers(&tenant.info, GetPublicTimelinePath::PATH, limit, &ids);
+
+    let posts = match user {
+        Some(user) => {
+            let filters = db.fetch_filters(user.user_id()).await?;
+            FilterMatcher::new(&filters, FilterContext::Public, UtcDateTime::now()).apply(posts)
+        }
+        None => posts,
+    };
+    let statuses = statuses(&db, &tenant.info, posts, FilterContext::Public).await?;
+    analytics.record_timeline_latency(started.elapsed());
+
+    Ok((headers, Json(statuses)))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/api/v1/statuses/{id}", rejection(ServerError))]
+struct GetStatusPath {
+    id: Id<PostMarker>,
+}
+
+async fn get_status(
+    GetStatusPath { id }: GetStatusPath,
+    _: MastodonApi,
+    State(db): State<Arc<DbClient>>,
+    CurrentTenant(tenant): CurrentTenant,
+) -> Result<Json<Status>> {
+    let post = db
+        .fetch_post(id)
+        .await?
+        .ok_or(ServerError::PostByIdNotFound(id))?;
+    let account = account(&db, &tenant.info, post.author.id).await?;
+
+    Ok(Json(Status::for_post(
+        &post,
+        account,
+        FilterContext::Public,
+        &tenant.info.public_url,
+    )))
+}
+
+#[derive(TypedPath)]
+#[typed_path("/api/v1/statuses")]
+struct CreateStatusPath;
+
+#[derive(Deserialize)]
+struct CreateStatusBody {
+    status: PostContent,
+}
+
+#[allow(clippy::too_many_arguments)] // Each argument is an extractor.
+async fn create_status(
+    _: CreateStatusPath,
+    _: MastodonApi,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+    CurrentTenant(tenant): CurrentTenant,
+    State(federation): State<Arc<Federation>>,
+    State(cache): State<Arc<ResponseCache>>,
+    State(filters): State<Arc<ContentFilters>>,
+    State(spam): State<Arc<SpamGuard>>,
+    State(quotas): State<Arc<Quotas>>,
+    State(analytics): State<Arc<Analytics>>,
+    Json(CreateStatusBody { status }): Json<CreateStatusBody>,
+) -> Result<Json<Status>> {
+    let post = posts::publish_post(
+        &db,
+        &tenant,
+        &federation,
+        &cache,
+        &filters,
+        &spam,
+        &quotas,
+        &analytics,
+        user.user_id(),
+        status,
+    )
+    .await?;
+    let account = account(&db, &tenant.info, user.user_id()).await?;
+
+    Ok(Json(Status::for_post(
+        &post,
+        account,
+        FilterContext::Home,
+        &tenant.info.public_url,
+    )))
+}
@@ -1,10 +1,84 @@
-use crate::server::ServerRouter;
+use crate::server::{
+    ServerRouter,
+    route_group::RouteGroup,
+    versioning::{self, CURRENT_VERSION},
+};
+use axum::middleware;
 
+pub mod admin;
+mod announcements;
+mod conversations;
+mod email;
+mod filters;
+mod health;
+mod inbox;
+mod instance;
+mod keys;
+mod mastodon;
+mod moderation;
+mod notifications;
+mod oembed;
+pub mod oidc;
+mod pages;
+mod policies;
 mod posts;
+mod search;
+pub mod sitemap;
+mod streaming;
+mod timelines;
 mod users;
+mod webhooks;
+pub mod well_known;
 
 pub fn routes() -> ServerRouter {
     ServerRouter::new()
+        .nest(CURRENT_VERSION, v1())
+        .merge(v1().layer(middleware::from_fn(versioning::deprecated_unversioned)))
+        .merge(unversioned())
+}
+
+/// Routes in other groups than the default for their method, by their path without version.
+pub fn route_groups() -> Vec<(&'static str, RouteGroup)> {
+    [
+        email::ROUTE_GROUPS,
+        mastodon::ROUTE_GROUPS,
+        oidc::ROUTE_GROUPS,
+    ]
+    .concat()
+}
+
+/// Version 1 of the client API.
+fn v1() -> ServerRouter {
+    ServerRouter::new()
+        .merge(admin::routes())
+        .merge(announcements::routes())
+        .merge(conversations::routes())
+        .merge(email::routes())
+        .merge(filters::routes())
+        .merge(instance::routes())
+        .merge(keys::routes())
+        .merge(moderation::routes())
+        .merge(notifications::routes())
+        .merge(oidc::routes())
+        .merge(policies::routes())
         .merge(posts::routes())
+        .merge(search::routes())
+        .merge(streaming::routes())
+        .merge(timelines::routes())
         .merge(users::routes())
+        .merge(webhooks::routes())
+}
+
+/// Routes whose paths are defined by other protocols, referenced by other servers,
+/// or shared as links, so they are not versioned.
+fn unversioned() -> ServerRouter {
+    ServerRouter::new()
+        .merge(health::routes())
+        .merge(inbox::routes())
+        .merge(instance::activitypub_routes())
+        .merge(mastodon::routes())
+        .merge(oembed::routes())
+        .merge(pages::routes())
+        .merge(sitemap::routes())
+        .merge(well_known::routes())
 }
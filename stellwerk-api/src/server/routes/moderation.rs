@@ -0,0 +1,646 @@
+use crate::{
+    cdn::Cdn,
+    server::{
+        Result, ServerError, ServerRouter, auth::AuthenticatedModerator, client_ip::ClientIp,
+        json::Json, response_cache::ResponseCache, routes::posts, tenant::CurrentTenant,
+    },
+};
+use axum::{extract::State, http::StatusCode};
+use axum_extra::routing::{RouterExt, TypedPath};
+use serde::Deserialize;
+use std::sync::Arc;
+use stellwerk_common::model::{
+    Id,
+    moderation::{CreateModerationLogEntry, ModerationAction},
+    notification::{CreateNotification, NotificationKind},
+    post::{ModeratedPost, PostMarker},
+    report::{
+        CreateReport, CreateReportNote, QueuedReport, Report, ReportAction, ReportCategory,
+        ReportComment, ReportMarker, ReportNote, ReportNoteContent,
+    },
+    tenant::TenantId,
+    user::{ModeratedUser, UserMarker, UserRole},
+};
+use stellwerk_db::client::DbClient;
+use tracing::info;
+
+pub fn routes() -> ServerRouter {
+    ServerRouter::new()
+        .typed_get(get_open_reports)
+        .typed_get(get_report)
+        .typed_post(claim_report)
+        .typed_post(assign_report)
+        .typed_get(get_report_notes)
+        .typed_post(add_report_note)
+        .typed_post(resolve_report)
+        .typed_get(get_post)
+        .typed_delete(delete_post)
+        .typed_post(restore_post)
+        .typed_get(get_user)
+        .typed_put(shadowban_user)
+        .typed_delete(unshadowban_user)
+}
+
+/// Request body for the report routes of posts and users.
+#[derive(Deserialize)]
+pub struct CreateReportBody {
+    pub category: ReportCategory,
+    #[serde(default)]
+    pub comment: ReportComment,
+}
+
+/// Creates the report and returns it with a [`StatusCode::CREATED`].
+pub async fn create_report(
+    db: &DbClient,
+    body: CreateReportBody,
+    reporter: Id<UserMarker>,
+    target_user: Id<UserMarker>,
+    target_post: Option<Id<PostMarker>>,
+) -> Result<(StatusCode, Json<Report>)> {
+    let report = CreateReport {
+        reporter: Some(reporter),
+        target_user,
+        target_post,
+        category: body.category,
+        comment: body.comment,
+    };
+    let id = db.create_report(&report).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(Report {
+            id,
+            reporter: report.reporter,
+            target_user: report.target_user,
+            target_post: report.target_post,
+            category: report.category,
+            comment: report.comment,
+            assignee: None,
+            resolved_at: None,
+            resolution: None,
+        }),
+    ))
+}
+
+/// Moderators only moderate the users of their own tenant, so users of other tenants are not found.
+/// Reports and posts are in the tenant of the user they are about.
+async fn require_tenant_user(db: &DbClient, tenant: &TenantId, id: Id<UserMarker>) -> Result<()> {
+    if db.fetch_user_tenant(id).await?.as_ref() != Some(tenant) {
+        return Err(ServerError::UserByIdNotFound(id));
+    }
+
+    Ok(())
+}
+
+async fn fetch_tenant_post(
+    db: &DbClient,
+    tenant: &TenantId,
+    id: Id<PostMarker>,
+) -> Result<ModeratedPost> {
+    let post = db
+        .fetch_moderated_post(id)
+        .await?
+        .ok_or(ServerError::PostByIdNotFound(id))?;
+    require_tenant_user(db, tenant, post.post.author.id)
+        .await
+        .map_err(|_| ServerError::PostByIdNotFound(id))?;
+
+    Ok(post)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/moderation/reports")]
+struct GetOpenReportsPath;
+
+/// Returns the queue of unresolved reports, oldest first, with the reported posts inlined.
+async fn get_open_reports(
+    _: GetOpenReportsPath,
+    _: AuthenticatedModerator,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<Vec<QueuedReport>>> {
+    let reports = db.fetch_open_reports(&tenant.id).await?;
+
+    let post_ids: Vec<_> = reports
+        .iter()
+        .filter_map(|report| report.target_post)
+        .collect();
+    let posts = db.fetch_moderated_posts(&post_ids).await?;
+
+    let queue = reports
+        .into_iter()
+        .map(|report| QueuedReport {
+            post: report
+                .target_post
+                .and_then(|post_id| posts.iter().find(|post| post.post.id == post_id).cloned()),
+            report,
+        })
+        .collect();
+
+    Ok(Json(queue))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/moderation/reports/{id}", rejection(ServerError))]
+struct GetReportPath {
+    id: Id<ReportMarker>,
+}
+
+async fn get_report(
+    GetReportPath { id }: GetReportPath,
+    _: AuthenticatedModerator,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<Report>> {
+    let report = db
+        .fetch_report(&tenant.id, id)
+        .await?
+        .ok_or(ServerError::ReportByIdNotFound(id))?;
+
+    Ok(Json(report))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/moderation/reports/{id}/claim", rejection(ServerError))]
+struct ClaimReportPath {
+    id: Id<ReportMarker>,
+}
+
+/// Assigns the report to the moderator, unless another moderator already claimed it.
+/// Claiming a report again succeeds without changes.
+async fn claim_report(
+    ClaimReportPath { id }: ClaimReportPath,
+    moderator: AuthenticatedModerator,
+    ClientIp(client_ip): ClientIp,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+) -> Result<StatusCode> {
+    let moderator = moderator.user_id();
+    let report = db
+        .fetch_report(&tenant.id, id)
+        .await?
+        .ok_or(ServerError::ReportByIdNotFound(id))?;
+    if report.assignee == Some(moderator) {
+        return Ok(StatusCode::NO_CONTENT);
+    }
+
+    db.transaction(async |db| {
+        if !db.claim_report(id, moderator).await? {
+            return Err(ServerError::ReportAlreadyClaimed(id));
+        }
+        db.create_moderation_log_entry(
+            &CreateModerationLogEntry::new(moderator, ModerationAction::AssignReport)
+                .with_report(id)
+                .with_user(moderator),
+        )
+        .await?;
+
+        Ok(())
+    })
+    .await?;
+    info!(report_id = %id, %client_ip, "Claimed report");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/moderation/reports/{id}/assign", rejection(ServerError))]
+struct AssignReportPath {
+    id: Id<ReportMarker>,
+}
+
+#[derive(Deserialize)]
+struct AssignReportBody {
+    /// Unassigns the report if `None`.
+    assignee: Option<Id<UserMarker>>,
+}
+
+async fn assign_report(
+    AssignReportPath { id }: AssignReportPath,
+    moderator: AuthenticatedModerator,
+    ClientIp(client_ip): ClientIp,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+    Json(AssignReportBody { assignee }): Json<AssignReportBody>,
+) -> Result<StatusCode> {
+    if db.fetch_report(&tenant.id, id).await?.is_none() {
+        return Err(ServerError::ReportByIdNotFound(id));
+    }
+    if let Some(assignee) = assignee {
+        require_tenant_user(&db, &tenant.id, assignee).await?;
+        let role = db
+            .fetch_user_role(assignee)
+            .await?
+            .ok_or(ServerError::UserByIdNotFound(assignee))?;
+        if role < UserRole::Moderator {
+            return Err(ServerError::AssigneeNotModerator(assignee));
+        }
+    }
+
+    db.transaction(async |db| {
+        if !db.assign_report(id, assignee).await? {
+            return Err(ServerError::ReportByIdNotFound(id));
+        }
+        let entry =
+            CreateModerationLogEntry::new(moderator.user_id(), ModerationAction::AssignReport)
+                .with_report(id);
+        let entry = match assignee {
+            Some(assignee) => entry.with_user(assignee),
+            None => entry,
+        };
+        db.create_moderation_log_entry(&entry).await?;
+
+        Ok(())
+    })
+    .await?;
+    // The moderator is part of the request span.
+    info!(report_id = %id, assignee = ?assignee.map(u64::from), %client_ip, "Assigned report");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/moderation/reports/{id}/notes", rejection(ServerError))]
+struct ReportNotesPath {
+    id: Id<ReportMarker>,
+}
+
+/// Returns the notes of moderators about the report, oldest first.
+async fn get_report_notes(
+    ReportNotesPath { id }: ReportNotesPath,
+    _: AuthenticatedModerator,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<Vec<ReportNote>>> {
+    if db.fetch_report(&tenant.id, id).await?.is_none() {
+        return Err(ServerError::ReportByIdNotFound(id));
+    }
+
+    let notes = db.fetch_report_notes(id).await?;
+
+    Ok(Json(notes))
+}
+
+#[derive(Deserialize)]
+struct AddReportNoteBody {
+    content: ReportNoteContent,
+}
+
+async fn add_report_note(
+    ReportNotesPath { id }: ReportNotesPath,
+    moderator: AuthenticatedModerator,
+    ClientIp(client_ip): ClientIp,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+    Json(AddReportNoteBody { content }): Json<AddReportNoteBody>,
+) -> Result<(StatusCode, Json<ReportNote>)> {
+    if db.fetch_report(&tenant.id, id).await?.is_none() {
+        return Err(ServerError::ReportByIdNotFound(id));
+    }
+
+    let note = CreateReportNote {
+        report: id,
+        author: moderator.user_id(),
+        content,
+    };
+    let note_id = db
+        .transaction(async |db| {
+            let note_id = db.create_report_note(&note).await?;
+            db.create_moderation_log_entry(
+                &CreateModerationLogEntry::new(note.author, ModerationAction::AddNote)
+                    .with_report(id),
+            )
+            .await?;
+
+            Ok::<_, ServerError>(note_id)
+        })
+        .await?;
+    info!(report_id = %id, note_id = %note_id, %client_ip, "Added report note");
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ReportNote {
+            id: note_id,
+            report: note.report,
+            author: note.author,
+            content: note.content,
+        }),
+    ))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/moderation/reports/{id}/resolve", rejection(ServerError))]
+struct ResolveReportPath {
+    id: Id<ReportMarker>,
+}
+
+#[derive(Deserialize)]
+struct ResolveReportBody {
+    action: ReportAction,
+}
+
+/// Resolves the report and takes the action against its target.
+/// Resolving a resolved report succeeds without changes, so actions are never taken twice.
+/// Moderators and admins cannot be suspended or shadowbanned.
+#[allow(clippy::too_many_arguments)] // Each argument is an extractor.
+async fn resolve_report(
+    ResolveReportPath { id }: ResolveReportPath,
+    moderator: AuthenticatedModerator,
+    ClientIp(client_ip): ClientIp,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+    State(cache): State<Arc<ResponseCache>>,
+    State(cdn): State<Arc<Cdn>>,
+    Json(ResolveReportBody { action }): Json<ResolveReportBody>,
+) -> Result<StatusCode> {
+    let report = db
+        .fetch_report(&tenant.id, id)
+        .await?
+        .ok_or(ServerError::ReportByIdNotFound(id))?;
+    if report.resolved_at.is_some() {
+        return Ok(StatusCode::NO_CONTENT);
+    }
+    let deleted_post = match (action, report.target_post) {
+        (ReportAction::DeleteContent, None) => return Err(ServerError::ReportWithoutPost(id)),
+        (ReportAction::DeleteContent, Some(post_id)) => db.fetch_moderated_post(post_id).await?,
+        _ => None,
+    };
+    if matches!(action, ReportAction::Suspend | ReportAction::Shadowban) {
+        let role = db
+            .fetch_user_role(report.target_user)
+            .await?
+            .ok_or(ServerError::UserByIdNotFound(report.target_user))?;
+        if role >= UserRole::Moderator {
+            return Err(match action {
+                ReportAction::Suspend => ServerError::CannotSuspend(report.target_user),
+                _ => ServerError::CannotShadowban(report.target_user),
+            });
+        }
+    }
+
+    let resolved = db
+        .transaction(async |db| {
+            if !db.resolve_report(id, action).await? {
+                // Another moderator resolved it concurrently.
+                return Ok::<_, ServerError>(false);
+            }
+
+            match action {
+                ReportAction::Dismiss => {}
+                ReportAction::DeleteContent => {
+                    if let Some(post) = &deleted_post {
+                        db.delete_post(post.post.id).await?;
+                    }
+                }
+                ReportAction::Warn => {
+                    db.create_notification(&CreateNotification {
+                        user: report.target_user,
+                        kind: NotificationKind::Warning,
+                        actor: report.target_user,
+                        post: report.target_post,
+                    })
+                    .await?;
+                }
+                ReportAction::Suspend => {
+                    db.suspend_user(report.target_user).await?;
+                }
+                ReportAction::Shadowban => {
+                    db.shadowban_user(report.target_user).await?;
+                }
+            }
+
+            let entry = CreateModerationLogEntry::new(moderator.user_id(), action.into())
+                .with_report(id)
+                .with_user(report.target_user);
+            let entry = match report.target_post {
+                Some(post_id) => entry.with_post(post_id),
+                None => entry,
+            };
+            db.create_moderation_log_entry(&entry).await?;
+
+            Ok(true)
+        })
+        .await?;
+
+    if resolved {
+        if let Some(post) = &deleted_post {
+            posts::invalidate_post(&cache, &cdn, &tenant, &post.post).await;
+        }
+        if matches!(action, ReportAction::Suspend | ReportAction::Shadowban) {
+            cache.invalidate_public_timeline();
+        }
+        info!(report_id = %id, %action, %client_ip, "Resolved report");
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/moderation/posts/{id}", rejection(ServerError))]
+struct PostPath {
+    id: Id<PostMarker>,
+}
+
+/// Also returns deleted posts, until they are purged.
+async fn get_post(
+    PostPath { id }: PostPath,
+    _: AuthenticatedModerator,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<ModeratedPost>> {
+    let post = fetch_tenant_post(&db, &tenant.id, id).await?;
+
+    Ok(Json(post))
+}
+
+/// Deletes the post softly, like its author can. Deleting a deleted post succeeds without changes.
+async fn delete_post(
+    PostPath { id }: PostPath,
+    moderator: AuthenticatedModerator,
+    ClientIp(client_ip): ClientIp,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+    State(cache): State<Arc<ResponseCache>>,
+    State(cdn): State<Arc<Cdn>>,
+) -> Result<StatusCode> {
+    let post = fetch_tenant_post(&db, &tenant.id, id).await?;
+
+    let deleted = db
+        .transaction(async |db| {
+            let deleted = db.delete_post(id).await?;
+            if deleted {
+                db.create_moderation_log_entry(
+                    &CreateModerationLogEntry::new(
+                        moderator.user_id(),
+                        ModerationAction::DeletePost,
+                    )
+                    .with_user(post.post.author.id)
+                    .with_post(id),
+                )
+                .await?;
+            }
+
+            Ok::<_, ServerError>(deleted)
+        })
+        .await?;
+    if deleted {
+        posts::invalidate_post(&cache, &cdn, &tenant, &post.post).await;
+        info!(post_id = %id, %client_ip, "Deleted post");
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/moderation/posts/{id}/restore", rejection(ServerError))]
+struct RestorePostPath {
+    id: Id<PostMarker>,
+}
+
+/// Restores a deleted post that was not purged yet.
+/// Restoring a post that is not deleted succeeds without changes.
+async fn restore_post(
+    RestorePostPath { id }: RestorePostPath,
+    moderator: AuthenticatedModerator,
+    ClientIp(client_ip): ClientIp,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+    State(cache): State<Arc<ResponseCache>>,
+    State(cdn): State<Arc<Cdn>>,
+) -> Result<StatusCode> {
+    let post = fetch_tenant_post(&db, &tenant.id, id).await?;
+
+    let restored = db
+        .transaction(async |db| {
+            let restored = db.restore_post(id).await?;
+            if restored {
+                db.create_moderation_log_entry(
+                    &CreateModerationLogEntry::new(
+                        moderator.user_id(),
+                        ModerationAction::RestorePost,
+                    )
+                    .with_user(post.post.author.id)
+                    .with_post(id),
+                )
+                .await?;
+            }
+
+            Ok::<_, ServerError>(restored)
+        })
+        .await?;
+    if restored {
+        posts::invalidate_post(&cache, &cdn, &tenant, &post.post).await;
+        info!(post_id = %id, %client_ip, "Restored post");
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/moderation/users/{id}", rejection(ServerError))]
+struct UserPath {
+    id: Id<UserMarker>,
+}
+
+/// Also returns suspended and shadowbanned users, with when that happened.
+async fn get_user(
+    UserPath { id }: UserPath,
+    _: AuthenticatedModerator,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<ModeratedUser>> {
+    require_tenant_user(&db, &tenant.id, id).await?;
+    let user = db
+        .fetch_moderated_user(id)
+        .await?
+        .ok_or(ServerError::UserByIdNotFound(id))?;
+
+    Ok(Json(user))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/moderation/users/{id}/shadowban", rejection(ServerError))]
+struct ShadowbanPath {
+    id: Id<UserMarker>,
+}
+
+/// Hides the posts of the user from public timelines, search, and the notifications of others,
+/// without telling them. Moderators and admins cannot be shadowbanned.
+/// Shadowbanning a shadowbanned user succeeds without changes.
+async fn shadowban_user(
+    ShadowbanPath { id }: ShadowbanPath,
+    moderator: AuthenticatedModerator,
+    ClientIp(client_ip): ClientIp,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+    State(cache): State<Arc<ResponseCache>>,
+) -> Result<StatusCode> {
+    require_tenant_user(&db, &tenant.id, id).await?;
+    let role = db
+        .fetch_user_role(id)
+        .await?
+        .ok_or(ServerError::UserByIdNotFound(id))?;
+    if role >= UserRole::Moderator {
+        return Err(ServerError::CannotShadowban(id));
+    }
+
+    let shadowbanned = db
+        .transaction(async |db| {
+            let shadowbanned = db.shadowban_user(id).await?;
+            if shadowbanned {
+                db.create_moderation_log_entry(
+                    &CreateModerationLogEntry::new(
+                        moderator.user_id(),
+                        ModerationAction::ShadowbanUser,
+                    )
+                    .with_user(id),
+                )
+                .await?;
+            }
+
+            Ok::<_, ServerError>(shadowbanned)
+        })
+        .await?;
+    if shadowbanned {
+        cache.invalidate_public_timeline();
+        info!(user_id = %id, %client_ip, "Shadowbanned user");
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Lifting a shadowban that is not in place succeeds without changes.
+async fn unshadowban_user(
+    ShadowbanPath { id }: ShadowbanPath,
+    moderator: AuthenticatedModerator,
+    ClientIp(client_ip): ClientIp,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+    State(cache): State<Arc<ResponseCache>>,
+) -> Result<StatusCode> {
+    require_tenant_user(&db, &tenant.id, id).await?;
+
+    let unshadowbanned = db
+        .transaction(async |db| {
+            let unshadowbanned = db.unshadowban_user(id).await?;
+            if unshadowbanned {
+                db.create_moderation_log_entry(
+                    &CreateModerationLogEntry::new(
+                        moderator.user_id(),
+                        ModerationAction::UnshadowbanUser,
+                    )
+                    .with_user(id),
+                )
+                .await?;
+            }
+
+            Ok::<_, ServerError>(unshadowbanned)
+        })
+        .await?;
+    if unshadowbanned {
+        cache.invalidate_public_timeline();
+        info!(user_id = %id, %client_ip, "Lifted shadowban");
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
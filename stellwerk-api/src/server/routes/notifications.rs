@@ -0,0 +1,125 @@
+use crate::server::{
+    Result, ServerRouter, auth::AuthenticatedUser, json::Json, pagination::link_headers,
+    query::Query,
+};
+use axum::{
+    extract::{OriginalUri, State},
+    http::{HeaderMap, StatusCode},
+};
+use axum_extra::routing::{RouterExt, TypedPath};
+use serde::Deserialize;
+use std::sync::Arc;
+use stellwerk_common::model::{
+    Id,
+    do_not_disturb::DoNotDisturb,
+    notification::{Notification, NotificationMarker, UnreadNotificationCount},
+    pagination::PageRequest,
+};
+use stellwerk_db::client::DbClient;
+
+pub fn routes() -> ServerRouter {
+    ServerRouter::new()
+        .typed_get(get_notifications)
+        .typed_get(get_unread_count)
+        .typed_post(mark_read)
+        .typed_get(get_do_not_disturb)
+        .typed_put(set_do_not_disturb)
+        .typed_delete(delete_do_not_disturb)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/notifications")]
+struct GetNotificationsPath;
+
+async fn get_notifications(
+    _: GetNotificationsPath,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<PageRequest<NotificationMarker>>,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<(HeaderMap, Json<Vec<Notification>>)> {
+    let limit = query.limit();
+    let notifications = db
+        .fetch_notifications(user.user_id(), query.max_id, query.since_id, limit)
+        .await?;
+
+    let ids: Vec<_> = notifications
+        .iter()
+        .map(|notification| notification.id)
+        .collect();
+    let headers = link_headers(uri.path(), limit, &ids);
+
+    Ok((headers, Json(notifications)))
+}
+
+#[derive(TypedPath)]
+#[typed_path("/notifications/unread-count")]
+struct GetUnreadCountPath;
+
+async fn get_unread_count(
+    _: GetUnreadCountPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<UnreadNotificationCount>> {
+    let count = db.fetch_unread_notification_count(user.user_id()).await?;
+
+    Ok(Json(UnreadNotificationCount { count }))
+}
+
+#[derive(TypedPath)]
+#[typed_path("/notifications/read")]
+struct MarkReadPath;
+
+#[derive(Deserialize)]
+struct MarkReadQuery {
+    /// If absent, all notifications are marked as read.
+    max_id: Option<Id<NotificationMarker>>,
+}
+
+async fn mark_read(
+    _: MarkReadPath,
+    Query(MarkReadQuery { max_id }): Query<MarkReadQuery>,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<StatusCode> {
+    db.mark_notifications_read(user.user_id(), max_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/notifications/do-not-disturb")]
+struct DoNotDisturbPath;
+
+/// The defaults if the user has not set do not disturb.
+async fn get_do_not_disturb(
+    _: DoNotDisturbPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<DoNotDisturb>> {
+    let do_not_disturb = db.fetch_do_not_disturb(user.user_id()).await?;
+
+    Ok(Json(do_not_disturb))
+}
+
+async fn set_do_not_disturb(
+    _: DoNotDisturbPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+    Json(do_not_disturb): Json<DoNotDisturb>,
+) -> Result<Json<DoNotDisturb>> {
+    db.set_do_not_disturb(user.user_id(), do_not_disturb)
+        .await?;
+
+    Ok(Json(do_not_disturb))
+}
+
+async fn delete_do_not_disturb(
+    _: DoNotDisturbPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<StatusCode> {
+    db.delete_do_not_disturb(user.user_id()).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,56 @@
+use crate::server::{Result, ServerError, ServerRouter, json::Json, query::ValidatedQuery};
+use axum::extract::State;
+use axum_extra::routing::{RouterExt, TypedPath};
+use garde::Validate;
+use serde::Deserialize;
+use std::sync::Arc;
+use stellwerk_common::model::{
+    instance::InstanceInfo,
+    oembed::{OEmbed, parse_post_url},
+};
+use stellwerk_db::client::DbClient;
+
+pub fn routes() -> ServerRouter {
+    ServerRouter::new().typed_get(get_oembed)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/oembed")]
+struct GetOEmbedPath;
+
+#[derive(Deserialize, Validate)]
+struct OEmbedQuery {
+    #[garde(skip)]
+    url: String,
+    /// Named like the parameter, because validation errors name the field.
+    #[garde(range(min = 1))]
+    maxwidth: Option<u32>,
+    #[garde(skip)]
+    format: Option<String>,
+}
+
+async fn get_oembed(
+    _: GetOEmbedPath,
+    ValidatedQuery(query): ValidatedQuery<OEmbedQuery>,
+    State(db): State<Arc<DbClient>>,
+    State(instance): State<Arc<InstanceInfo>>,
+) -> Result<Json<OEmbed>> {
+    if let Some(format) = query.format
+        && format != "json"
+    {
+        return Err(ServerError::UnsupportedOEmbedFormat(format));
+    }
+
+    let id = parse_post_url(&instance.public_url, &query.url)
+        .ok_or(ServerError::UnknownOEmbedUrl(query.url))?;
+    let post = db
+        .fetch_post(id)
+        .await?
+        .ok_or(ServerError::PostByIdNotFound(id))?;
+
+    Ok(Json(OEmbed::for_post(
+        &post,
+        &instance.public_url,
+        query.maxwidth,
+    )))
+}
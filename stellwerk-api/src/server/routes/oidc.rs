@@ -0,0 +1,343 @@
+use crate::{
+    oidc::{IdentityProviderError, LOGIN_LIFETIME, Oidc},
+    server::{
+        ServerError, ServerRouter, auth::AuthenticatedUser, json::Json, route_group::RouteGroup,
+        tenant::CurrentTenant,
+    },
+};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use axum_extra::routing::{RouterExt, TypedPath};
+use serde::Deserialize;
+use std::sync::Arc;
+use stellwerk_common::model::{
+    Id,
+    auth::{AuthToken, AuthTokenHashError, Authentication},
+    oidc::{
+        OidcAuthorization, OidcCallback, OidcIdentity, OidcLogin, OidcOutcome, OidcProvider,
+        OidcProviderInfo, OidcToken, PendingOidcLogin, StartOidcLogin,
+    },
+    problem::ErrorCode,
+    tenant::{RegistrationMode, TenantId},
+    user::{UserHandle, UserMarker},
+};
+use stellwerk_db::client::{DbClient, DbError};
+use thiserror::Error;
+use time::UtcDateTime;
+
+/// States and codes must not be guessable.
+pub const ROUTE_GROUPS: &[(&str, RouteGroup)] = &[
+    (StartLoginPath::PATH, RouteGroup::Auth),
+    (CallbackPath::PATH, RouteGroup::Auth),
+];
+
+pub fn routes() -> ServerRouter {
+    ServerRouter::new()
+        .typed_get(get_providers)
+        .typed_post(start_login)
+        .typed_post(callback)
+        .typed_get(get_identities)
+        .typed_delete(unlink_identity)
+}
+
+type Result<T, E = OidcError> = std::result::Result<T, E>;
+
+#[derive(Debug, Error)]
+pub enum OidcError {
+    #[error(transparent)]
+    Database(#[from] DbError),
+    #[error(transparent)]
+    AuthTokenHash(#[from] AuthTokenHashError),
+    #[error("Logging in with {0} is not configured on this instance.")]
+    NotConfigured(OidcProvider),
+    #[error("The login was not started, already finished, or expired.")]
+    LoginExpired,
+    #[error(transparent)]
+    IdentityProvider(#[from] IdentityProviderError),
+    #[error("Registrations are not open on this instance.")]
+    RegistrationsClosed,
+    #[error("No valid handle was given or derived from the identity.")]
+    HandleRequired,
+    #[error("The handle {} is already taken.", .0.get())]
+    HandleTaken(UserHandle),
+    #[error("The identity or another identity at {0} is already linked to an account.")]
+    AlreadyLinked(OidcProvider),
+    #[error("The identity can only be linked by the user who started the login.")]
+    LinkUserMismatch,
+    #[error("Impersonation tokens cannot link or unlink identities.")]
+    Impersonating,
+    #[error("No identity at {0} is linked to the user.")]
+    IdentityNotFound(OidcProvider),
+    #[error("User with id {0} was not found.")]
+    UserNotFound(Id<UserMarker>),
+}
+
+impl OidcError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            OidcError::Database(
+                DbError::PoolExhausted | DbError::StatementTimeout | DbError::Unavailable,
+            ) => StatusCode::SERVICE_UNAVAILABLE,
+            OidcError::Database(_) | OidcError::AuthTokenHash(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            OidcError::NotConfigured(_)
+            | OidcError::IdentityNotFound(_)
+            | OidcError::UserNotFound(_) => StatusCode::NOT_FOUND,
+            OidcError::LoginExpired => StatusCode::BAD_REQUEST,
+            OidcError::IdentityProvider(_) => StatusCode::BAD_GATEWAY,
+            OidcError::RegistrationsClosed
+            | OidcError::LinkUserMismatch
+            | OidcError::Impersonating => StatusCode::FORBIDDEN,
+            OidcError::HandleRequired => StatusCode::UNPROCESSABLE_ENTITY,
+            OidcError::HandleTaken(_) | OidcError::AlreadyLinked(_) => StatusCode::CONFLICT,
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            OidcError::Database(
+                DbError::PoolExhausted | DbError::StatementTimeout | DbError::Unavailable,
+            ) => ErrorCode::DatabaseUnavailable,
+            OidcError::Database(_) | OidcError::AuthTokenHash(_) => ErrorCode::InternalError,
+            OidcError::NotConfigured(_) => ErrorCode::NotConfigured,
+            OidcError::LoginExpired => ErrorCode::OidcLoginExpired,
+            OidcError::IdentityProvider(_) => ErrorCode::IdentityProviderFailed,
+            OidcError::RegistrationsClosed => ErrorCode::RegistrationsClosed,
+            OidcError::HandleRequired => ErrorCode::HandleRequired,
+            OidcError::HandleTaken(_) => ErrorCode::HandleTaken,
+            OidcError::AlreadyLinked(_) => ErrorCode::IdentityAlreadyLinked,
+            OidcError::LinkUserMismatch => ErrorCode::OidcLinkUserMismatch,
+            OidcError::Impersonating => ErrorCode::ImpersonationNotAllowed,
+            OidcError::IdentityNotFound(_) => ErrorCode::OidcIdentityNotFound,
+            OidcError::UserNotFound(_) => ErrorCode::UserNotFound,
+        }
+    }
+}
+
+/// Replies with problem details, like any other [`ServerError`].
+impl IntoResponse for OidcError {
+    fn into_response(self) -> Response {
+        ServerError::Oidc(self).into_response()
+    }
+}
+
+#[derive(TypedPath)]
+#[typed_path("/auth/oidc")]
+struct ProvidersPath;
+
+async fn get_providers(
+    _: ProvidersPath,
+    State(oidc): State<Arc<Oidc>>,
+) -> Json<Vec<OidcProviderInfo>> {
+    Json(oidc.provider_infos())
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/auth/oidc/{provider}")]
+struct StartLoginPath {
+    provider: OidcProvider,
+}
+
+/// Links the identity to the user instead of logging in, if authenticated.
+/// Impersonation tokens cannot link identities, which would let admins log in as the user later.
+async fn start_login(
+    StartLoginPath { provider }: StartLoginPath,
+    user: Option<AuthenticatedUser>,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+    State(oidc): State<Arc<Oidc>>,
+    Json(start): Json<StartOidcLogin>,
+) -> Result<Json<OidcAuthorization>> {
+    if user.is_some_and(|user| user.impersonator().is_some()) {
+        return Err(OidcError::Impersonating);
+    }
+    let identity_provider = oidc
+        .provider(provider)
+        .ok_or(OidcError::NotConfigured(provider))?;
+
+    let state = OidcToken::generate_random();
+    let login = PendingOidcLogin {
+        tenant: tenant.id.clone(),
+        provider,
+        nonce: OidcToken::generate_random(),
+        code_verifier: OidcToken::generate_random(),
+        handle: start.handle,
+        link_user: user.map(AuthenticatedUser::user_id),
+    };
+    let authorization_url = identity_provider
+        .authorization_url(&state, &login, oidc.redirect_url())
+        .await?;
+
+    let now = UtcDateTime::now();
+    db.create_oidc_login(&state, &login, now, now - LOGIN_LIFETIME)
+        .await?;
+
+    Ok(Json(OidcAuthorization { authorization_url }))
+}
+
+#[derive(TypedPath)]
+#[typed_path("/auth/oidc/callback")]
+struct CallbackPath;
+
+/// Logs in, links the identity, or creates an account for it, see [`OidcOutcome`].
+/// Linking has to be finished by the user who started it, so that nobody can link their account
+/// to the identity of whoever they send the authorization URL to.
+async fn callback(
+    _: CallbackPath,
+    user: Option<AuthenticatedUser>,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+    State(oidc): State<Arc<Oidc>>,
+    Json(callback): Json<OidcCallback>,
+) -> Result<(StatusCode, Json<OidcLogin>)> {
+    let now = UtcDateTime::now();
+    let login = db
+        .take_oidc_login(&callback.state, now - LOGIN_LIFETIME)
+        .await?
+        .filter(|login| login.tenant == tenant.id)
+        .ok_or(OidcError::LoginExpired)?;
+    if let Some(link_user) = login.link_user {
+        let user = user.ok_or(OidcError::LinkUserMismatch)?;
+        if user.user_id() != link_user {
+            return Err(OidcError::LinkUserMismatch);
+        }
+        if user.impersonator().is_some() {
+            return Err(OidcError::Impersonating);
+        }
+    }
+    let provider = login.provider;
+    let identity = oidc
+        .provider(provider)
+        .ok_or(OidcError::NotConfigured(provider))?
+        .identify(&callback.code, &login, oidc.redirect_url())
+        .await?;
+
+    if let Some(user_id) = login.link_user {
+        if !db
+            .link_oidc_identity(&tenant.id, provider, &identity.subject, user_id, now)
+            .await?
+        {
+            return Err(OidcError::AlreadyLinked(provider));
+        }
+        let user = db
+            .fetch_user(user_id)
+            .await?
+            .ok_or(OidcError::UserNotFound(user_id))?;
+
+        return Ok((
+            StatusCode::OK,
+            Json(OidcLogin {
+                outcome: OidcOutcome::Linked,
+                user,
+                token: None,
+            }),
+        ));
+    }
+
+    if let Some(user) = db
+        .fetch_oidc_identity_user(&tenant.id, provider, &identity.subject)
+        .await?
+    {
+        let token = issue_token(&db, &oidc, &tenant.id, user.id, now).await?;
+
+        return Ok((
+            StatusCode::OK,
+            Json(OidcLogin {
+                outcome: OidcOutcome::LoggedIn,
+                user,
+                token: Some(token),
+            }),
+        ));
+    }
+
+    if tenant.info.registrations != RegistrationMode::Open {
+        return Err(OidcError::RegistrationsClosed);
+    }
+    let handle = login
+        .handle
+        .or_else(|| {
+            identity
+                .username
+                .and_then(|name| UserHandle::new(name).ok())
+        })
+        .ok_or(OidcError::HandleRequired)?;
+    let user_id = db
+        .create_oidc_user(&tenant.id, &handle, provider, &identity.subject, now)
+        .await?
+        .ok_or_else(|| OidcError::HandleTaken(handle.clone()))?;
+    let token = issue_token(&db, &oidc, &tenant.id, user_id, now).await?;
+    let user = db
+        .fetch_user(user_id)
+        .await?
+        .ok_or(OidcError::UserNotFound(user_id))?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(OidcLogin {
+            outcome: OidcOutcome::Registered,
+            user,
+            token: Some(token),
+        }),
+    ))
+}
+
+/// Returns the encoded token.
+async fn issue_token(
+    db: &DbClient,
+    oidc: &Oidc,
+    tenant: &TenantId,
+    user_id: Id<UserMarker>,
+    now: UtcDateTime,
+) -> Result<String> {
+    let token = AuthToken::generate_random(user_id);
+    db.create_auth(&Authentication {
+        user: user_id,
+        tenant: tenant.clone(),
+        token_hash: token.hash()?,
+        created_at: now,
+        expires_after: Some(oidc.token_lifetime()),
+        impersonator: None,
+    })
+    .await?;
+
+    Ok(token.as_token_str())
+}
+
+#[derive(TypedPath)]
+#[typed_path("/auth/oidc/identities")]
+struct IdentitiesPath;
+
+async fn get_identities(
+    _: IdentitiesPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<Vec<OidcIdentity>>> {
+    let identities = db.fetch_oidc_identities(user.user_id()).await?;
+
+    Ok(Json(identities))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/auth/oidc/identities/{provider}")]
+struct IdentityPath {
+    provider: OidcProvider,
+}
+
+async fn unlink_identity(
+    IdentityPath { provider }: IdentityPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<StatusCode> {
+    if user.impersonator().is_some() {
+        return Err(OidcError::Impersonating);
+    }
+    if !db.unlink_oidc_identity(user.user_id(), provider).await? {
+        return Err(OidcError::IdentityNotFound(provider));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
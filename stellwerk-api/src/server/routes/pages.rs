@@ -0,0 +1,79 @@
+//! Minimal HTML pages of profiles and posts, so that shared links unfurl
+//! before a full frontend exists. See [`Page`].
+
+use crate::server::{
+    Result, ServerError, ServerRouter,
+    client_ip::ClientIp,
+    tenant::CurrentTenant,
+    views::{PostViews, Viewer},
+};
+use axum::{
+    extract::{Path, State},
+    response::Html,
+    routing::get,
+};
+use axum_extra::extract::WithRejection;
+use serde::Deserialize;
+use std::sync::Arc;
+use stellwerk_common::model::{Id, page::Page, post::PostMarker, user::UserHandle};
+use stellwerk_db::store::Store;
+
+/// Not typed paths, since those only support parameters spanning whole segments.
+pub fn routes() -> ServerRouter {
+    ServerRouter::new()
+        .route("/@{handle}", get(get_profile_page))
+        .route("/@{handle}/{id}", get(get_post_page))
+}
+
+type PagePath<T> = WithRejection<Path<T>, ServerError>;
+
+#[derive(Deserialize)]
+struct ProfilePagePath {
+    handle: UserHandle,
+}
+
+async fn get_profile_page(
+    WithRejection(Path(ProfilePagePath { handle }), _): PagePath<ProfilePagePath>,
+    State(store): State<Arc<dyn Store>>,
+    CurrentTenant(tenant): CurrentTenant,
+) -> Result<Html<String>> {
+    let user = store
+        .fetch_user_by_handle(&tenant.id, &handle)
+        .await?
+        .ok_or(ServerError::UserByHandleNotFound(handle))?;
+    let profile = store
+        .fetch_user_profile(user.id)
+        .await?
+        .ok_or(ServerError::UserByIdNotFound(user.id))?;
+
+    Ok(Html(
+        Page::for_profile(&profile, &tenant.info.public_url).render(),
+    ))
+}
+
+#[derive(Deserialize)]
+struct PostPagePath {
+    handle: UserHandle,
+    id: Id<PostMarker>,
+}
+
+/// Counts a view of the post by the IP address, since pages are not authenticated.
+async fn get_post_page(
+    WithRejection(Path(PostPagePath { handle, id }), _): PagePath<PostPagePath>,
+    client_ip: ClientIp,
+    State(store): State<Arc<dyn Store>>,
+    State(views): State<Arc<PostViews>>,
+    CurrentTenant(tenant): CurrentTenant,
+) -> Result<Html<String>> {
+    // Posts are only found under the handle of their author.
+    let post = store
+        .fetch_post(id)
+        .await?
+        .filter(|post| post.author.handle == handle)
+        .ok_or(ServerError::PostByIdNotFound(id))?;
+    views.record(&post, Viewer::new(None, client_ip));
+
+    Ok(Html(
+        Page::for_post(&post, &tenant.info.public_url).render(),
+    ))
+}
@@ -0,0 +1,75 @@
+//! The policies of the instance, like the terms of service, and which versions users accepted.
+//! Admins publish new versions through [`admin`](super::admin), after which users have to
+//! accept them before they can change anything, see [`AuthenticatedUser`].
+
+use crate::server::{Result, ServerError, ServerRouter, auth::AuthenticatedUser, json::Json};
+use axum::{extract::State, http::StatusCode};
+use axum_extra::routing::{RouterExt, TypedPath};
+use serde::Deserialize;
+use std::sync::Arc;
+use stellwerk_common::model::policy::{AcceptPolicy, Policy, PolicyKind};
+use stellwerk_db::client::{DbClient, DbError};
+
+pub fn routes() -> ServerRouter {
+    ServerRouter::new()
+        .typed_get(get_policies)
+        .typed_get(get_pending_policies)
+        .typed_post(accept_policy)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/policies")]
+struct PoliciesPath;
+
+/// The latest version of each published policy.
+async fn get_policies(
+    _: PoliciesPath,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<Vec<Policy>>> {
+    let policies = db.fetch_current_policies().await?;
+
+    Ok(Json(policies))
+}
+
+#[derive(TypedPath)]
+#[typed_path("/policies/pending")]
+struct PendingPoliciesPath;
+
+/// The policies the user has to accept before they can change anything.
+async fn get_pending_policies(
+    _: PendingPoliciesPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<Vec<Policy>>> {
+    let policies = db.fetch_pending_policies(user.user_id()).await?;
+
+    Ok(Json(policies))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/policies/{kind}/accept", rejection(ServerError))]
+struct AcceptPolicyPath {
+    kind: PolicyKind,
+}
+
+/// Only the latest version can be accepted, older ones are rejected with a version conflict.
+async fn accept_policy(
+    AcceptPolicyPath { kind }: AcceptPolicyPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+    Json(AcceptPolicy { version }): Json<AcceptPolicy>,
+) -> Result<StatusCode> {
+    match db.accept_policy(user.user_id(), kind, version).await {
+        Ok(true) => {}
+        Ok(false) => return Err(ServerError::PolicyNotFound(kind)),
+        Err(DbError::Conflict { current_version }) => {
+            return Err(ServerError::PolicyVersionOutdated {
+                kind,
+                current_version,
+            });
+        }
+        Err(error) => return Err(error.into()),
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
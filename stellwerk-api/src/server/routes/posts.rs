@@ -1,16 +1,57 @@
-use crate::server::{Result, ServerError, ServerRouter, json::Json};
-use axum::extract::State;
+use crate::{
+    cdn::Cdn,
+    federation::Federation,
+    server::{
+        Result, ServerError, ServerRouter,
+        activitypub::{self, VerifiedSignature},
+        analytics::Analytics,
+        auth::AuthenticatedUser,
+        client_ip::ClientIp,
+        conditional::{ETag, IfNoneMatch, NotModified},
+        content_filter::ContentFilters,
+        fields::{Fields, Sparse},
+        json::Json,
+        quota::Quotas,
+        response_cache::ResponseCache,
+        routes::moderation::{self, CreateReportBody},
+        spam::{self, SpamGuard},
+        tenant::{CurrentTenant, Tenant},
+        views::{PostViews, Viewer},
+    },
+};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
 use axum_extra::routing::{RouterExt, TypedPath};
 use serde::Deserialize;
 use std::sync::Arc;
-use stellwerk_common::model::{
-    Id,
-    post::{Post, PostMarker},
+use stellwerk_common::{
+    model::{
+        Id, ModelValidationError,
+        activitypub::Note,
+        instance::InstanceInfo,
+        notification::{CreateNotification, NotificationKind},
+        post::{CreatePost, PartialPost, Post, PostContent, PostMarker, PostStats},
+        quota::QuotaKind,
+        report::Report,
+        spam::SpamVerdict,
+        user::{UserHandle, UserMarker},
+    },
+    text,
 };
-use stellwerk_db::client::DbClient;
+use stellwerk_db::{client::DbClient, store::Store};
 
 pub fn routes() -> ServerRouter {
-    ServerRouter::new().typed_get(get_post)
+    ServerRouter::new()
+        .typed_get(get_post)
+        .typed_get(get_post_stats)
+        .typed_post(create_post)
+        .typed_delete(delete_post)
+        .typed_post(pin_post)
+        .typed_delete(unpin_post)
+        .typed_post(report_post)
 }
 
 #[derive(TypedPath, Deserialize)]
@@ -19,14 +60,429 @@ struct GetPostPath {
     id: Id<PostMarker>,
 }
 
+/// Counts a view of the post, unless it is fetched by another server.
+#[allow(clippy::too_many_arguments)] // Each argument is an extractor.
 async fn get_post(
     GetPostPath { id }: GetPostPath,
+    _: Option<VerifiedSignature>,
+    user: Option<AuthenticatedUser>,
+    client_ip: ClientIp,
+    headers: HeaderMap,
+    if_none_match: IfNoneMatch,
+    fields: Fields,
+    State(store): State<Arc<dyn Store>>,
+    State(instance): State<Arc<InstanceInfo>>,
+    State(views): State<Arc<PostViews>>,
+) -> Result<Response> {
+    let version = store
+        .fetch_post_version(id)
+        .await?
+        .ok_or(ServerError::PostByIdNotFound(id))?;
+    let etag =
+        ETag::for_post(id, version, activitypub::is_requested(&headers)).with_fields(&fields);
+    if if_none_match.matches(&etag) {
+        return Ok((activitypub::VARY_ACCEPT, NotModified(etag)).into_response());
+    }
+
+    // The version was fetched first, so a concurrent change can only make the tag outdated,
+    // which causes an unnecessary full response later, but never a missed change.
+    let post: Post = store
+        .fetch_post(id)
+        .await?
+        .ok_or(ServerError::PostByIdNotFound(id))?;
+    if !activitypub::is_requested(&headers) {
+        views.record(&post, Viewer::new(user, client_ip));
+    }
+
+    let post = Sparse {
+        value: post,
+        fields,
+    };
+    let response =
+        activitypub::negotiate(&headers, post, async |Sparse { value: post, .. }| {
+            // Other servers have to ask the server the post actually belongs to.
+            if store.is_remote_user(post.author.id).await? {
+                return Err(ServerError::PostByIdNotFound(id));
+            }
+
+            Ok(Note::for_post(post, &instance.public_url))
+        })
+        .await?;
+
+    Ok((etag, response).into_response())
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/posts/{id}/stats", rejection(ServerError))]
+struct PostStatsPath {
+    id: Id<PostMarker>,
+}
+
+/// Only for the author of the post. Views are added a few minutes late.
+async fn get_post_stats(
+    PostStatsPath { id }: PostStatsPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<PostStats>> {
+    fetch_own_post(&*db, user, id).await?;
+    let stats = db.fetch_post_stats(id).await?;
+
+    Ok(Json(stats))
+}
+
+#[derive(TypedPath)]
+#[typed_path("/posts")]
+struct CreatePostPath;
+
+#[derive(Deserialize)]
+struct CreatePostBody {
+    content: PostContent,
+}
+
+#[allow(clippy::too_many_arguments)] // Each argument is an extractor.
+async fn create_post(
+    _: CreatePostPath,
+    user: AuthenticatedUser,
     State(db): State<Arc<DbClient>>,
-) -> Result<Json<Post>> {
+    CurrentTenant(tenant): CurrentTenant,
+    State(federation): State<Arc<Federation>>,
+    State(cache): State<Arc<ResponseCache>>,
+    State(filters): State<Arc<ContentFilters>>,
+    State(spam): State<Arc<SpamGuard>>,
+    State(quotas): State<Arc<Quotas>>,
+    State(analytics): State<Arc<Analytics>>,
+    Json(CreatePostBody { content }): Json<CreatePostBody>,
+) -> Result<(StatusCode, Json<PartialPost>)> {
+    let post = publish_post(
+        &db,
+        &tenant,
+        &federation,
+        &cache,
+        &filters,
+        &spam,
+        &quotas,
+        &analytics,
+        user.user_id(),
+        content,
+    )
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(PartialPost {
+            id: post.id,
+            content: post.content,
+            content_html: post.content_html,
+            pinned: false,
+        }),
+    ))
+}
+
+/// Checks the post against the content filters, spam heuristics and quota, creates it,
+/// notifies mentioned users, and delivers it to remote followers.
+///
+/// Posts held for review are created deleted and reported instead,
+/// so that their author cannot tell them apart.
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn publish_post(
+    db: &DbClient,
+    tenant: &Tenant,
+    federation: &Federation,
+    cache: &ResponseCache,
+    filters: &ContentFilters,
+    spam: &SpamGuard,
+    quotas: &Quotas,
+    analytics: &Analytics,
+    author: Id<UserMarker>,
+    content: PostContent,
+) -> Result<Post> {
+    content
+        .check_max_len(tenant.info.limits.post_content_max_len)
+        .map_err(ModelValidationError::from)?;
+    let create = CreatePost { author, content };
+    filters
+        .check(&create)
+        .await
+        .map_err(ServerError::ContentRejected)?;
+    let assessment = spam.assess_post(db, &create).await?;
+    quotas.consume(db, author, QuotaKind::Posts).await?;
+
+    if assessment.verdict == SpamVerdict::Review {
+        return db
+            .transaction(async |db| {
+                let id = db.create_post(&create).await?;
+                let post = db
+                    .fetch_post(id)
+                    .await?
+                    .ok_or(ServerError::PostByIdNotFound(id))?;
+                db.delete_post(id).await?;
+                spam::report(db, author, Some(id), &assessment).await?;
+
+                Ok::<_, ServerError>(post)
+            })
+            .await;
+    }
+
+    // The notifications are only created if the post is.
     let post = db
+        .transaction(async |db| {
+            let id = db.create_post(&create).await?;
+            let post = db
+                .fetch_post(id)
+                .await?
+                .ok_or(ServerError::PostByIdNotFound(id))?;
+
+            for handle in text::mentions(post.content.get()) {
+                let Ok(handle) = UserHandle::new(handle.to_owned()) else {
+                    continue;
+                };
+                if let Some(mentioned) = db.fetch_user_by_handle(&tenant.id, &handle).await?
+                    && mentioned.id != author
+                {
+                    db.create_notification(&CreateNotification {
+                        user: mentioned.id,
+                        kind: NotificationKind::Mention,
+                        actor: author,
+                        post: Some(id),
+                    })
+                    .await?;
+                }
+            }
+
+            Ok::<_, ServerError>(post)
+        })
+        .await?;
+    cache.invalidate_user(author);
+    cache.invalidate_public_timeline();
+    spam::report(db, author, Some(post.id), &assessment).await?;
+
+    federation.publish_post(&post).await?;
+    analytics.record_post();
+
+    Ok(post)
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/posts/{id}", rejection(ServerError))]
+struct DeletePostPath {
+    id: Id<PostMarker>,
+}
+
+/// Deletes the post softly, so that moderators can still see and restore it
+/// until it is purged.
+async fn delete_post(
+    DeletePostPath { id }: DeletePostPath,
+    user: AuthenticatedUser,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+    State(cache): State<Arc<ResponseCache>>,
+    State(cdn): State<Arc<Cdn>>,
+) -> Result<StatusCode> {
+    let post = fetch_own_post(&*db, user, id).await?;
+    db.delete_post(id).await?;
+    invalidate_post(&cache, &cdn, &tenant, &post).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/posts/{id}/pin", rejection(ServerError))]
+struct PinPostPath {
+    id: Id<PostMarker>,
+}
+
+async fn fetch_own_post(
+    store: &dyn Store,
+    user: AuthenticatedUser,
+    id: Id<PostMarker>,
+) -> Result<Post> {
+    let post = store
         .fetch_post(id)
         .await?
         .ok_or(ServerError::PostByIdNotFound(id))?;
 
-    Ok(Json(post))
+    if post.author.id != user.user_id() {
+        return Err(ServerError::NotPostAuthor(id));
+    }
+
+    Ok(post)
+}
+
+/// Pinning, deleting and restoring change the post, and the posts of its author.
+pub(super) async fn invalidate_post(
+    cache: &ResponseCache,
+    cdn: &Cdn,
+    tenant: &Tenant,
+    post: &Post,
+) {
+    cache.invalidate_post(post.id);
+    cache.invalidate_user(post.author.id);
+    cache.invalidate_public_timeline();
+    cdn.purge_post(&tenant.info.public_url, post).await;
+}
+
+async fn pin_post(
+    PinPostPath { id }: PinPostPath,
+    user: AuthenticatedUser,
+    State(store): State<Arc<dyn Store>>,
+    CurrentTenant(tenant): CurrentTenant,
+    State(cache): State<Arc<ResponseCache>>,
+    State(cdn): State<Arc<Cdn>>,
+) -> Result<StatusCode> {
+    let post = fetch_own_post(&*store, user, id).await?;
+
+    let max_pinned = tenant.info.limits.max_pinned_posts;
+    if !store.pin_post(id, max_pinned).await? {
+        return Err(ServerError::PinnedPostLimitReached(max_pinned));
+    }
+    invalidate_post(&cache, &cdn, &tenant, &post).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn unpin_post(
+    PinPostPath { id }: PinPostPath,
+    user: AuthenticatedUser,
+    State(store): State<Arc<dyn Store>>,
+    CurrentTenant(tenant): CurrentTenant,
+    State(cache): State<Arc<ResponseCache>>,
+    State(cdn): State<Arc<Cdn>>,
+) -> Result<StatusCode> {
+    let post = fetch_own_post(&*store, user, id).await?;
+    store.unpin_post(id).await?;
+    invalidate_post(&cache, &cdn, &tenant, &post).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/posts/{id}/report", rejection(ServerError))]
+struct ReportPostPath {
+    id: Id<PostMarker>,
+}
+
+async fn report_post(
+    ReportPostPath { id }: ReportPostPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+    Json(body): Json<CreateReportBody>,
+) -> Result<(StatusCode, Json<Report>)> {
+    let post = db
+        .fetch_post(id)
+        .await?
+        .ok_or(ServerError::PostByIdNotFound(id))?;
+
+    moderation::create_report(&db, body, user.user_id(), post.author.id, Some(id)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::{
+        ServerError,
+        client_ip::ClientIp,
+        conditional::IfNoneMatch,
+        fields::Fields,
+        routes::posts::{GetPostPath, get_post},
+        views::PostViews,
+    };
+    use axum::{
+        extract::State,
+        http::{HeaderMap, HeaderValue, StatusCode, header::ACCEPT},
+        response::Response,
+    };
+    use http_body_util::BodyExt;
+    use std::{net::Ipv4Addr, sync::Arc};
+    use stellwerk_common::model::{
+        Id,
+        instance::InstanceInfo,
+        post::{CreatePost, Post, PostContent, PostMarker},
+        tenant::TenantId,
+        user::{CreateUser, UserHandle, UserMarker},
+    };
+    use stellwerk_db::{memory::MemoryStore, store::Store};
+
+    async fn create_post(store: &MemoryStore) -> (Id<UserMarker>, Id<PostMarker>) {
+        let author = store
+            .create_user(
+                &TenantId::default(),
+                &CreateUser {
+                    handle: UserHandle::new("alice".to_owned()).unwrap(),
+                },
+            )
+            .await
+            .unwrap();
+        let post = store
+            .create_post(&CreatePost {
+                author,
+                content: PostContent::new("Hello @bob".to_owned()).unwrap(),
+            })
+            .await
+            .unwrap();
+
+        (author, post)
+    }
+
+    async fn get(
+        store: Arc<MemoryStore>,
+        id: Id<PostMarker>,
+        headers: HeaderMap,
+    ) -> Result<Response, ServerError> {
+        get_post(
+            GetPostPath { id },
+            None,
+            None,
+            ClientIp(Ipv4Addr::LOCALHOST.into()),
+            headers,
+            IfNoneMatch::default(),
+            Fields::default(),
+            State(store),
+            State(Arc::new(InstanceInfo::default())),
+            State(Arc::new(PostViews::new())),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn returns_posts() {
+        let store = Arc::new(MemoryStore::new());
+        let (author, id) = create_post(&store).await;
+
+        let response = get(store, id, HeaderMap::new()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let post: Post = serde_json::from_slice(&body).unwrap();
+        assert_eq!(post.id, id);
+        assert_eq!(post.author.id, author);
+        assert_eq!(post.content.get(), "Hello @bob");
+    }
+
+    #[tokio::test]
+    async fn rejects_missing_posts() {
+        let store = Arc::new(MemoryStore::new());
+        let (_, id) = create_post(&store).await;
+        let missing = Id::from(id.snowflake().get() + 1);
+
+        assert!(matches!(
+            get(store, missing, HeaderMap::new()).await,
+            Err(ServerError::PostByIdNotFound(not_found)) if not_found == missing
+        ));
+    }
+
+    #[tokio::test]
+    async fn hides_posts_of_remote_users_from_other_servers() {
+        let store = Arc::new(MemoryStore::new());
+        let (author, id) = create_post(&store).await;
+        store.set_remote(author, true);
+        let mut activitypub = HeaderMap::new();
+        activitypub.insert(
+            ACCEPT,
+            HeaderValue::from_static("application/activity+json"),
+        );
+
+        assert!(get(store.clone(), id, HeaderMap::new()).await.is_ok());
+        assert!(matches!(
+            get(store, id, activitypub).await,
+            Err(ServerError::PostByIdNotFound(_))
+        ));
+    }
 }
@@ -0,0 +1,66 @@
+use crate::{
+    search::SearchIndex,
+    server::{
+        Result, ServerRouter, auth::AuthenticatedUser, json::Json, query::Query,
+        tenant::CurrentTenant,
+    },
+};
+use axum::extract::State;
+use axum_extra::routing::{RouterExt, TypedPath};
+use std::sync::Arc;
+use stellwerk_common::model::{
+    filter::{FilterContext, FilterMatcher},
+    search::{SearchRequest, SearchResults},
+};
+use stellwerk_db::client::DbClient;
+use time::UtcDateTime;
+
+pub fn routes() -> ServerRouter {
+    ServerRouter::new().typed_get(search)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/search")]
+struct SearchPath;
+
+/// Searches the posts and users of the tenant, see [`search`](crate::search).
+async fn search(
+    _: SearchPath,
+    Query(request): Query<SearchRequest>,
+    user: Option<AuthenticatedUser>,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+    State(index): State<Arc<dyn SearchIndex>>,
+) -> Result<Json<SearchResults>> {
+    let limit = request.limit();
+    let (post_ids, user_ids) = tokio::try_join!(
+        index.search_posts(&tenant.id, &request.q, limit),
+        index.search_users(&tenant.id, &request.q, limit),
+    )?;
+
+    // The engine may lag behind, so deleted documents are skipped, and the order is restored.
+    let mut posts = db.fetch_posts(&post_ids).await?;
+    posts.sort_by_key(|post| post_ids.iter().position(|id| *id == post.id));
+    // Shadowbanned authors still find their own posts.
+    let viewer = user.map(AuthenticatedUser::user_id);
+    let authors: Vec<_> = posts.iter().map(|post| post.author.id).collect();
+    let shadowbanned = db.fetch_shadowbanned_user_ids(&authors).await?;
+    posts.retain(|post| viewer == Some(post.author.id) || !shadowbanned.contains(&post.author.id));
+    let mut users = db.fetch_user_profiles(&user_ids).await?;
+    users.sort_by_key(|profile| user_ids.iter().position(|id| *id == profile.user.id));
+    // Filtered after searching, so fewer than `limit` results may be left.
+    if request.verified {
+        posts.retain(|post| post.author.verified);
+        users.retain(|profile| profile.user.verified);
+    }
+
+    let posts = match viewer {
+        Some(viewer) => {
+            let filters = db.fetch_filters(viewer).await?;
+            FilterMatcher::new(&filters, FilterContext::Public, UtcDateTime::now()).apply(posts)
+        }
+        None => posts,
+    };
+
+    Ok(Json(SearchResults { posts, users }))
+}
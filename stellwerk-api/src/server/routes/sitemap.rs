@@ -0,0 +1,195 @@
+//! `/sitemap.xml` and the files it lists, see [`sitemap`](stellwerk_common::model::sitemap).
+//!
+//! Generating a file streams up to [`SITEMAP_MAX_URLS`] rows from the database, so generated files
+//! are cached by [`Sitemaps`] for a while, and only one is generated at a time by each instance.
+
+use crate::server::{Result, ServerError, ServerRouter, tenant::CurrentTenant};
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::header::CONTENT_TYPE,
+    response::{IntoResponse, Response},
+};
+use axum_extra::routing::{RouterExt, TypedPath};
+use futures_util::TryStreamExt;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    pin::pin,
+    sync::{Arc, nonpoison::Mutex},
+    time::{Duration, Instant},
+};
+use stellwerk_common::model::{
+    sitemap::{SITEMAP_MAX_URLS, SitemapFile, SitemapKind, UrlSet, render_sitemap_index},
+    tenant::TenantId,
+};
+use stellwerk_db::client::DbClient;
+use tokio::sync::Mutex as AsyncMutex;
+
+/// The index if the file is `None`.
+type Key = (TenantId, Option<SitemapFile>);
+
+#[derive(Clone, Debug)]
+struct Entry {
+    xml: Bytes,
+    expires_at: Instant,
+}
+
+#[derive(Debug)]
+pub struct Sitemaps {
+    /// Sitemaps are not served if `None`.
+    ttl: Option<Duration>,
+    entries: Mutex<HashMap<Key, Entry>>,
+    /// Held while generating, so crawlers fetching many files at once do not each stream rows.
+    generating: AsyncMutex<()>,
+}
+
+impl Sitemaps {
+    #[must_use]
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl: Some(ttl),
+            entries: Mutex::new(HashMap::new()),
+            generating: AsyncMutex::new(()),
+        }
+    }
+
+    #[must_use]
+    pub fn disabled() -> Self {
+        Self {
+            ttl: None,
+            entries: Mutex::new(HashMap::new()),
+            generating: AsyncMutex::new(()),
+        }
+    }
+
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.ttl.is_some()
+    }
+
+    fn cached(&self, key: &Key, now: Instant) -> Option<Bytes> {
+        self.entries
+            .lock()
+            .get(key)
+            .filter(|entry| entry.expires_at > now)
+            .map(|entry| entry.xml.clone())
+    }
+
+    /// Returns the cached XML of `key`, or generates and caches it.
+    async fn get_or_generate(
+        &self,
+        key: Key,
+        generate: impl Future<Output = Result<String>>,
+    ) -> Result<Bytes> {
+        let ttl = self
+            .ttl
+            .ok_or(ServerError::WellKnownNotConfigured("sitemap.xml"))?;
+        if let Some(xml) = self.cached(&key, Instant::now()) {
+            return Ok(xml);
+        }
+
+        let _generating = self.generating.lock().await;
+        // Another request may have generated it while waiting.
+        let now = Instant::now();
+        if let Some(xml) = self.cached(&key, now) {
+            return Ok(xml);
+        }
+        let xml = Bytes::from(generate.await?);
+
+        let mut entries = self.entries.lock();
+        entries.retain(|_, entry| entry.expires_at > now);
+        entries.insert(
+            key,
+            Entry {
+                xml: xml.clone(),
+                expires_at: now + ttl,
+            },
+        );
+
+        Ok(xml)
+    }
+}
+
+pub fn routes() -> ServerRouter {
+    ServerRouter::new()
+        .typed_get(get_sitemap_index)
+        .typed_get(get_sitemap)
+}
+
+fn xml_response(xml: Bytes) -> Response {
+    ([(CONTENT_TYPE, "application/xml")], xml).into_response()
+}
+
+#[derive(TypedPath)]
+#[typed_path("/sitemap.xml")]
+struct SitemapIndexPath;
+
+async fn get_sitemap_index(
+    _: SitemapIndexPath,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+    State(sitemaps): State<Arc<Sitemaps>>,
+) -> Result<Response> {
+    let xml = sitemaps
+        .get_or_generate((tenant.id.clone(), None), async {
+            let counts = db.fetch_sitemap_counts(&tenant.id).await?;
+            Ok(render_sitemap_index(
+                &counts.files(),
+                &tenant.info.public_url,
+            ))
+        })
+        .await?;
+
+    Ok(xml_response(xml))
+}
+
+/// Like `/sitemaps/posts-1.xml`.
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/sitemaps/{file}")]
+struct SitemapPath {
+    file: SitemapFile,
+}
+
+/// Files without URLs are not found, since the index only lists files with URLs.
+async fn get_sitemap(
+    SitemapPath { file }: SitemapPath,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+    State(sitemaps): State<Arc<Sitemaps>>,
+) -> Result<Response> {
+    let xml = sitemaps
+        .get_or_generate((tenant.id.clone(), Some(file)), async {
+            let public_url = &tenant.info.public_url;
+            let mut urls = UrlSet::new();
+            let mut empty = true;
+            match file.kind {
+                SitemapKind::Profiles => {
+                    let users =
+                        db.fetch_sitemap_users_stream(&tenant.id, file.offset(), SITEMAP_MAX_URLS);
+                    let mut users = pin!(users);
+                    while let Some(user) = users.try_next().await? {
+                        urls.push_profile(&user, public_url);
+                        empty = false;
+                    }
+                }
+                SitemapKind::Posts => {
+                    let posts =
+                        db.fetch_sitemap_posts_stream(&tenant.id, file.offset(), SITEMAP_MAX_URLS);
+                    let mut posts = pin!(posts);
+                    while let Some(post) = posts.try_next().await? {
+                        urls.push_post(&post, public_url);
+                        empty = false;
+                    }
+                }
+            }
+            if empty {
+                return Err(ServerError::SitemapNotFound(file));
+            }
+
+            Ok(urls.finish())
+        })
+        .await?;
+
+    Ok(xml_response(xml))
+}
@@ -0,0 +1,255 @@
+use crate::server::{
+    Result, ServerError, ServerRouter,
+    auth::AuthenticatedUser,
+    events::{Event, EventHub},
+    feature_flags::FeatureFlags,
+    tenant::CurrentTenant,
+};
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    response::sse::{self, KeepAlive, Sse},
+};
+use axum_extra::routing::{RouterExt, TypedPath};
+use serde::Deserialize;
+use std::{
+    collections::HashSet,
+    fmt::{Display, Formatter},
+    sync::Arc,
+};
+use stellwerk_common::model::{
+    Id,
+    feature::FeatureFlag,
+    filter::{FilterContext, FilterMatcher},
+    pagination::MAX_LIMIT,
+    post::{Post, PostMarker},
+    tenant::TenantId,
+    user::UserMarker,
+};
+use stellwerk_db::client::DbClient;
+use time::UtcDateTime;
+use tokio_stream::{Stream, StreamExt, wrappers::BroadcastStream};
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+pub fn routes() -> ServerRouter {
+    ServerRouter::new().typed_get(get_stream)
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum StreamKind {
+    /// All new posts of the tenant, like `GET /timeline/public`.
+    Public,
+    /// New posts of the authenticated user and the users they follow, and their notifications.
+    User,
+}
+
+impl Display for StreamKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            StreamKind::Public => "public",
+            StreamKind::User => "user",
+        })
+    }
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/streaming/{stream}", rejection(ServerError))]
+struct GetStreamPath {
+    stream: StreamKind,
+}
+
+/// Which events a stream delivers to one client.
+struct EventSelection {
+    /// `None` for all authors.
+    authors: Option<HashSet<Id<UserMarker>>>,
+    /// `None` for the posts of all tenants.
+    tenant: Option<TenantId>,
+    matcher: FilterMatcher,
+    /// The user whose notifications are delivered, if any.
+    notified_user: Option<Id<UserMarker>>,
+    /// Whether posts of shadowbanned authors are left out, unless the author is `viewer`.
+    hide_shadowbanned: bool,
+    viewer: Option<Id<UserMarker>>,
+}
+
+impl EventSelection {
+    fn includes_author(&self, author: Id<UserMarker>) -> bool {
+        self.authors
+            .as_ref()
+            .is_none_or(|authors| authors.contains(&author))
+    }
+
+    fn select(&self, post: Post, tenant: &TenantId, author_shadowbanned: bool) -> Option<Post> {
+        if !self.includes_author(post.author.id)
+            || self
+                .tenant
+                .as_ref()
+                .is_some_and(|selected| selected != tenant)
+            || (self.hide_shadowbanned
+                && author_shadowbanned
+                && self.viewer != Some(post.author.id))
+        {
+            return None;
+        }
+
+        self.matcher.apply_one(post)
+    }
+
+    /// `newest_missed` posts were already sent before, see [`get_stream`].
+    fn sse_event(
+        &self,
+        event: Event,
+        newest_missed: Option<Id<PostMarker>>,
+    ) -> Option<Result<sse::Event, axum::Error>> {
+        match event {
+            Event::PostCreated {
+                post,
+                tenant,
+                author_shadowbanned,
+            } => {
+                if newest_missed.is_some_and(|newest_missed| post.id <= newest_missed) {
+                    return None;
+                }
+                self.select(post, &tenant, author_shadowbanned)
+                    .map(post_event)
+            }
+            Event::PostDeleted { post, author } => {
+                if !self.includes_author(author) {
+                    return None;
+                }
+                // Without an id, like notifications.
+                Some(Ok(sse::Event::default()
+                    .event("delete")
+                    .data(post.to_string())))
+            }
+            Event::NotificationCreated { user, notification } => {
+                if self.notified_user != Some(user) {
+                    return None;
+                }
+                // Without an id, so that Last-Event-ID keeps referring to the last post.
+                Some(
+                    sse::Event::default()
+                        .event("notification")
+                        .json_data(notification),
+                )
+            }
+        }
+    }
+}
+
+/// Streams new posts as server-sent events with the post id as event id,
+/// and the ids of deleted posts as `delete` events.
+///
+/// Clients reconnecting with `Last-Event-ID` first receive up to [`MAX_LIMIT`] posts they missed,
+/// oldest first. Clients that missed more should reload the corresponding timeline.
+#[allow(clippy::too_many_arguments)] // Each argument is an extractor.
+async fn get_stream(
+    GetStreamPath { stream }: GetStreamPath,
+    headers: HeaderMap,
+    user: Option<AuthenticatedUser>,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+    State(feature_flags): State<Arc<FeatureFlags>>,
+    State(events): State<Arc<EventHub>>,
+    State(shutdown): State<CancellationToken>,
+) -> Result<Sse<impl Stream<Item = Result<sse::Event, axum::Error>>>> {
+    let last_event_id = headers
+        .get("last-event-id")
+        .map(|value| {
+            value
+                .to_str()
+                .ok()
+                .and_then(|value| value.parse::<u64>().ok())
+                .map(Id::<PostMarker>::from)
+                .ok_or(ServerError::InvalidLastEventId)
+        })
+        .transpose()?;
+
+    // Subscribe before loading missed posts so that nothing is lost in between.
+    let receiver = events.subscribe();
+
+    let (selection, missed) = match stream {
+        StreamKind::Public => {
+            if !feature_flags.is_enabled(FeatureFlag::PublicTimeline) {
+                return Err(ServerError::PublicTimelineDisabled);
+            }
+
+            let viewer = user.map(AuthenticatedUser::user_id);
+            let filters = match viewer {
+                Some(viewer) => db.fetch_filters(viewer).await?,
+                None => Vec::new(),
+            };
+            let missed = match last_event_id {
+                Some(last_event_id) => {
+                    db.fetch_public_posts(&tenant.id, viewer, None, Some(last_event_id), MAX_LIMIT)
+                        .await?
+                }
+                None => Vec::new(),
+            };
+
+            let selection = EventSelection {
+                authors: None,
+                tenant: Some(tenant.id.clone()),
+                notified_user: None,
+                hide_shadowbanned: true,
+                viewer,
+                matcher: FilterMatcher::new(&filters, FilterContext::Public, UtcDateTime::now()),
+            };
+            (selection, missed)
+        }
+        StreamKind::User => {
+            let user_id = user
+                .ok_or(ServerError::StreamRequiresAuthentication)?
+                .user_id();
+
+            let filters = db.fetch_filters(user_id).await?;
+            let mut authors: HashSet<_> =
+                db.fetch_followed_ids(user_id).await?.into_iter().collect();
+            authors.insert(user_id);
+            let missed = match last_event_id {
+                Some(last_event_id) => {
+                    db.fetch_home_posts(user_id, None, Some(last_event_id), MAX_LIMIT)
+                        .await?
+                }
+                None => Vec::new(),
+            };
+
+            let selection = EventSelection {
+                authors: Some(authors),
+                tenant: None,
+                notified_user: Some(user_id),
+                hide_shadowbanned: false,
+                viewer: Some(user_id),
+                matcher: FilterMatcher::new(&filters, FilterContext::Home, UtcDateTime::now()),
+            };
+            (selection, missed)
+        }
+    };
+
+    let missed = selection.matcher.apply(missed);
+    // Posts created while loading missed posts can be both loaded and received.
+    let newest_missed = missed.first().map(|post| post.id);
+
+    let missed = tokio_stream::iter(missed.into_iter().rev()).map(post_event);
+    let live = BroadcastStream::new(receiver).filter_map(move |event| match event {
+        Ok(event) => selection.sse_event(event, newest_missed),
+        Err(error) => {
+            debug!(%error, "Streaming client lagged behind");
+            None
+        }
+    });
+    // Streams would keep connections open forever, which would prevent draining them on shutdown.
+    let events =
+        futures_util::StreamExt::take_until(missed.chain(live), shutdown.cancelled_owned());
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+fn post_event(post: Post) -> Result<sse::Event, axum::Error> {
+    sse::Event::default()
+        .event("post")
+        .id(post.id.to_string())
+        .json_data(post)
+}
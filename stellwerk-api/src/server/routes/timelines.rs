@@ -0,0 +1,83 @@
+use crate::server::{
+    Result, ServerError, ServerRouter,
+    analytics::Analytics,
+    auth::AuthenticatedUser,
+    feature_flags::FeatureFlags,
+    fields::{Fields, Sparse},
+    json::Json,
+    pagination::link_headers,
+    query::Query,
+    tenant::CurrentTenant,
+};
+use axum::{
+    extract::{OriginalUri, State},
+    http::HeaderMap,
+};
+use axum_extra::routing::{RouterExt, TypedPath};
+use std::{sync::Arc, time::Instant};
+use stellwerk_common::model::{
+    feature::FeatureFlag,
+    filter::{FilterContext, FilterMatcher},
+    pagination::PageRequest,
+    post::{Post, PostMarker},
+};
+use stellwerk_db::client::DbClient;
+use time::UtcDateTime;
+
+pub fn routes() -> ServerRouter {
+    ServerRouter::new().typed_get(get_public_timeline)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/timeline/public")]
+struct GetPublicTimelinePath;
+
+#[allow(clippy::too_many_arguments)] // Each argument is an extractor.
+async fn get_public_timeline(
+    _: GetPublicTimelinePath,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<PageRequest<PostMarker>>,
+    fields: Fields,
+    user: Option<AuthenticatedUser>,
+    CurrentTenant(tenant): CurrentTenant,
+    State(db): State<Arc<DbClient>>,
+    State(feature_flags): State<Arc<FeatureFlags>>,
+    State(analytics): State<Arc<Analytics>>,
+) -> Result<(HeaderMap, Json<Sparse<Vec<Post>>>)> {
+    if !feature_flags.is_enabled(FeatureFlag::PublicTimeline) {
+        return Err(ServerError::PublicTimelineDisabled);
+    }
+    let started = Instant::now();
+
+    let limit = query.limit();
+    let posts = db
+        .fetch_public_posts(
+            &tenant.id,
+            user.map(AuthenticatedUser::user_id),
+            query.max_id,
+            query.since_id,
+            limit,
+        )
+        .await?;
+
+    // Cursors refer to the unfiltered page so that hidden posts do not end pagination early.
+    let ids: Vec<_> = posts.iter().map(|post| post.id).collect();
+    let headers = link_headers(uri.path(), limit, &ids);
+
+    let posts = match user {
+        Some(user) => {
+            let filters = db.fetch_filters(user.user_id()).await?;
+            FilterMatcher::new(&filters, FilterContext::Public, UtcDateTime::now()).apply(posts)
+        }
+        None => posts,
+    };
+    analytics.record_timeline_latency(started.elapsed());
+
+    Ok((
+        headers,
+        Json(Sparse {
+            value: posts,
+            fields,
+        }),
+    ))
+}
@@ -1,19 +1,52 @@
-use crate::server::{Result, ServerError, ServerRouter, json::Json};
-use axum::extract::State;
+use crate::{
+    federation::Federation,
+    server::{
+        Result, ServerError, ServerRouter,
+        activitypub::{self, VerifiedSignature},
+        auth::AuthenticatedUser,
+        conditional::{ETag, IfNoneMatch, NotModified},
+        fields::{Fields, Sparse},
+        json::Json,
+        pagination::link_headers,
+        query::Query,
+        quota::Quotas,
+        response_cache::ResponseCache,
+        routes::moderation::{self, CreateReportBody},
+        spam::{self, SpamGuard},
+    },
+};
+use axum::{
+    extract::{OriginalUri, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
 use axum_extra::routing::{RouterExt, TypedPath};
 use serde::Deserialize;
 use std::sync::Arc;
 use stellwerk_common::model::{
     Id,
-    post::PartialPost,
-    user::{User, UserMarker},
+    activitypub::Actor,
+    instance::InstanceInfo,
+    keys::KeyBundle,
+    notification::{CreateNotification, NotificationKind},
+    pagination::PageRequest,
+    post::{PartialPost, PostMarker},
+    quota::QuotaKind,
+    report::Report,
+    user::{SetProfileWebsite, UserMarker, UserProfile},
 };
-use stellwerk_db::client::DbClient;
+use stellwerk_db::{client::DbClient, store::Store};
 
 pub fn routes() -> ServerRouter {
     ServerRouter::new()
         .typed_get(get_user)
         .typed_get(get_user_posts)
+        .typed_post(follow_user)
+        .typed_delete(unfollow_user)
+        .typed_post(report_user)
+        .typed_get(get_user_keys)
+        .typed_put(set_profile_website)
+        .typed_delete(delete_profile_website)
 }
 
 #[derive(TypedPath, Deserialize)]
@@ -22,16 +55,56 @@ struct GetUserPath {
     id: Id<UserMarker>,
 }
 
+#[allow(clippy::too_many_arguments)] // Each argument is an extractor.
 async fn get_user(
     GetUserPath { id }: GetUserPath,
-    State(db): State<Arc<DbClient>>,
-) -> Result<Json<User>> {
-    let user = db
-        .fetch_user(id)
+    _: Option<VerifiedSignature>,
+    headers: HeaderMap,
+    if_none_match: IfNoneMatch,
+    fields: Fields,
+    State(store): State<Arc<dyn Store>>,
+    State(instance): State<Arc<InstanceInfo>>,
+    State(federation): State<Arc<Federation>>,
+) -> Result<Response> {
+    let version = store
+        .fetch_user_version(id)
+        .await?
+        .ok_or(ServerError::UserByIdNotFound(id))?;
+    let etag =
+        ETag::for_user(id, version, activitypub::is_requested(&headers)).with_fields(&fields);
+    if if_none_match.matches(&etag) {
+        return Ok((activitypub::VARY_ACCEPT, NotModified(etag)).into_response());
+    }
+
+    let profile: UserProfile = store
+        .fetch_user_profile(id)
         .await?
         .ok_or(ServerError::UserByIdNotFound(id))?;
 
-    Ok(Json(user))
+    let profile = Sparse {
+        value: profile,
+        fields,
+    };
+    let response = activitypub::negotiate(
+        &headers,
+        profile,
+        async |Sparse { value: profile, .. }| {
+            // Other servers have to ask the server the actor actually belongs to.
+            if store.is_remote_user(id).await? {
+                return Err(ServerError::UserByIdNotFound(id));
+            }
+
+            let key_pair = federation.user_key_pair(id).await?;
+            Ok(Actor::for_user(
+                &profile.user,
+                &instance.public_url,
+                key_pair.public_key_pem,
+            ))
+        },
+    )
+    .await?;
+
+    Ok((etag, response).into_response())
 }
 
 #[derive(TypedPath, Deserialize)]
@@ -41,13 +114,188 @@ struct GetUserPostsPath {
 }
 
 async fn get_user_posts(
-    GetUserPostsPath { id }: GetUserPostsPath,
-    State(db): State<Arc<DbClient>>,
-) -> Result<Json<Vec<PartialPost>>> {
-    let posts = db
-        .fetch_user_posts(id)
+    path: GetUserPostsPath,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<PageRequest<PostMarker>>,
+    fields: Fields,
+    State(store): State<Arc<dyn Store>>,
+) -> Result<(HeaderMap, Json<Sparse<Vec<PartialPost>>>)> {
+    let id = path.id;
+    let limit = query.limit();
+    let posts = store
+        .fetch_user_posts(id, query.max_id, query.since_id, limit)
         .await?
         .ok_or(ServerError::UserByIdNotFound(id))?;
 
-    Ok(Json(posts))
+    // Pinned posts are not part of the chronological order the cursors refer to.
+    let ids: Vec<_> = posts
+        .iter()
+        .filter(|post| !post.pinned)
+        .map(|post| post.id)
+        .collect();
+    let headers = link_headers(uri.path(), limit, &ids);
+
+    Ok((
+        headers,
+        Json(Sparse {
+            value: posts,
+            fields,
+        }),
+    ))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/users/{id}/follow", rejection(ServerError))]
+struct FollowUserPath {
+    id: Id<UserMarker>,
+}
+
+async fn follow_user(
+    FollowUserPath { id }: FollowUserPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+    State(cache): State<Arc<ResponseCache>>,
+    State(spam): State<Arc<SpamGuard>>,
+    State(quotas): State<Arc<Quotas>>,
+) -> Result<StatusCode> {
+    follow(&db, &cache, &spam, &quotas, user.user_id(), id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Follows `target` and notifies them, unless `follower` already follows them.
+/// Follows that look like mass-following are throttled or reported, and new follows count
+/// towards the quota.
+pub(super) async fn follow(
+    db: &DbClient,
+    cache: &ResponseCache,
+    spam: &SpamGuard,
+    quotas: &Quotas,
+    follower: Id<UserMarker>,
+    target: Id<UserMarker>,
+) -> Result<()> {
+    if target == follower {
+        return Err(ServerError::SelfFollow);
+    }
+    if db.fetch_user(target).await?.is_none() {
+        return Err(ServerError::UserByIdNotFound(target));
+    }
+    if db.is_following(follower, target).await? {
+        return Ok(());
+    }
+    let assessment = spam.assess_follow(db, follower).await?;
+    quotas.consume(db, follower, QuotaKind::Follows).await?;
+
+    if db.follow_user(follower, target).await? {
+        invalidate_follow(cache, follower, target);
+        spam::report(db, follower, None, &assessment).await?;
+        db.create_notification(&CreateNotification {
+            user: target,
+            kind: NotificationKind::Follow,
+            actor: follower,
+            post: None,
+        })
+        .await?;
+    }
+
+    Ok(())
+}
+
+async fn unfollow_user(
+    FollowUserPath { id }: FollowUserPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+    State(cache): State<Arc<ResponseCache>>,
+) -> Result<StatusCode> {
+    unfollow(&db, &cache, user.user_id(), id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub(super) async fn unfollow(
+    db: &DbClient,
+    cache: &ResponseCache,
+    follower: Id<UserMarker>,
+    target: Id<UserMarker>,
+) -> Result<()> {
+    db.unfollow_user(follower, target).await?;
+    invalidate_follow(cache, follower, target);
+
+    Ok(())
+}
+
+/// Following changes the follower counts of both profiles.
+fn invalidate_follow(cache: &ResponseCache, follower: Id<UserMarker>, target: Id<UserMarker>) {
+    cache.invalidate_user(follower);
+    cache.invalidate_user(target);
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/users/{id}/report", rejection(ServerError))]
+struct ReportUserPath {
+    id: Id<UserMarker>,
+}
+
+async fn report_user(
+    ReportUserPath { id }: ReportUserPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+    Json(body): Json<CreateReportBody>,
+) -> Result<(StatusCode, Json<Report>)> {
+    if db.fetch_user(id).await?.is_none() {
+        return Err(ServerError::UserByIdNotFound(id));
+    }
+
+    moderation::create_report(&db, body, user.user_id(), id, None).await
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/users/{id}/keys", rejection(ServerError))]
+struct GetUserKeysPath {
+    id: Id<UserMarker>,
+}
+
+/// Each call hands out a different one-time prekey, so this requires authentication.
+async fn get_user_keys(
+    GetUserKeysPath { id }: GetUserKeysPath,
+    _user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<KeyBundle>> {
+    let bundle = db
+        .claim_key_bundle(id)
+        .await?
+        .ok_or(ServerError::KeysNotFound(id))?;
+
+    Ok(Json(bundle))
+}
+
+#[derive(TypedPath)]
+#[typed_path("/profile/website")]
+struct ProfileWebsitePath;
+
+/// Shown on the profile page with `rel="me"`, and checked for a link back before admins grant a
+/// [verified badge](stellwerk_common::model::verification).
+async fn set_profile_website(
+    _: ProfileWebsitePath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+    State(cache): State<Arc<ResponseCache>>,
+    Json(SetProfileWebsite { url }): Json<SetProfileWebsite>,
+) -> Result<StatusCode> {
+    db.set_profile_website(user.user_id(), Some(&url)).await?;
+    cache.invalidate_user(user.user_id());
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn delete_profile_website(
+    _: ProfileWebsitePath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+    State(cache): State<Arc<ResponseCache>>,
+) -> Result<StatusCode> {
+    db.set_profile_website(user.user_id(), None).await?;
+    cache.invalidate_user(user.user_id());
+
+    Ok(StatusCode::NO_CONTENT)
 }
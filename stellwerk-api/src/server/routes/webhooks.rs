@@ -0,0 +1,96 @@
+//! Webhooks of the authenticated user, which are delivered by [`webhooks`](crate::webhooks).
+
+use crate::server::{Result, ServerError, ServerRouter, auth::AuthenticatedUser, json::Json};
+use axum::{extract::State, http::StatusCode};
+use axum_extra::routing::{RouterExt, TypedPath};
+use serde::Deserialize;
+use std::sync::Arc;
+use stellwerk_common::model::{
+    Id, ModelValidationError,
+    webhook::{CreateWebhook, CreatedWebhook, Webhook, WebhookMarker, WebhookSecret},
+};
+use stellwerk_db::client::DbClient;
+
+const MAX_WEBHOOKS: usize = 10;
+
+pub fn routes() -> ServerRouter {
+    ServerRouter::new()
+        .typed_get(get_webhooks)
+        .typed_post(create_webhook)
+        .typed_delete(delete_webhook)
+        .typed_post(enable_webhook)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/webhooks")]
+struct WebhooksPath;
+
+async fn get_webhooks(
+    _: WebhooksPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<Json<Vec<Webhook>>> {
+    let webhooks = db.fetch_webhooks(user.user_id()).await?;
+
+    Ok(Json(webhooks))
+}
+
+/// The response has the secret requests are signed with, which is not shown again.
+/// Impersonation tokens cannot create webhooks.
+async fn create_webhook(
+    _: WebhooksPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+    Json(webhook): Json<CreateWebhook>,
+) -> Result<(StatusCode, Json<CreatedWebhook>)> {
+    let user = user.not_impersonated()?;
+    webhook.validate().map_err(ModelValidationError::from)?;
+
+    let secret = WebhookSecret::generate_random();
+    let webhook = db
+        .create_webhook(user.user_id(), &webhook, &secret, MAX_WEBHOOKS)
+        .await?
+        .ok_or(ServerError::WebhookLimitReached(MAX_WEBHOOKS))?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedWebhook { webhook, secret }),
+    ))
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/webhooks/{id}", rejection(ServerError))]
+struct WebhookPath {
+    id: Id<WebhookMarker>,
+}
+
+async fn delete_webhook(
+    WebhookPath { id }: WebhookPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<StatusCode> {
+    if !db.delete_webhook(user.user_id(), id).await? {
+        return Err(ServerError::WebhookByIdNotFound(id));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(TypedPath, Deserialize)]
+#[typed_path("/webhooks/{id}/enable", rejection(ServerError))]
+struct EnableWebhookPath {
+    id: Id<WebhookMarker>,
+}
+
+/// Enables a webhook that was disabled after too many failed requests.
+async fn enable_webhook(
+    EnableWebhookPath { id }: EnableWebhookPath,
+    user: AuthenticatedUser,
+    State(db): State<Arc<DbClient>>,
+) -> Result<StatusCode> {
+    if !db.enable_webhook(user.user_id(), id).await? {
+        return Err(ServerError::WebhookByIdNotFound(id));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
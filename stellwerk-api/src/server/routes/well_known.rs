@@ -0,0 +1,121 @@
+//! `robots.txt` and the [well-known URIs](https://www.rfc-editor.org/rfc/rfc8615)
+//! of the instance, configured by [`WellKnownSettings`].
+
+use crate::server::{
+    Result, ServerError, ServerRouter, routes::sitemap::Sitemaps, tenant::CurrentTenant,
+};
+use axum::{extract::State, response::Redirect};
+use axum_extra::routing::{RouterExt, TypedPath};
+use serde::Deserialize;
+use std::{fmt::Write, sync::Arc};
+use time::{Duration, UtcDateTime, format_description::well_known::Rfc3339};
+
+/// How long a served `security.txt` is valid, which RFC 9116 recommends to be less than a year.
+const SECURITY_TXT_VALIDITY: Duration = Duration::days(180);
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WellKnownSettings {
+    /// Path prefixes that crawlers must not visit.
+    pub robots_disallow: Vec<String>,
+    /// URIs, like `mailto:` or `https:`, to report vulnerabilities to.
+    /// `security.txt` is not served if empty.
+    pub security_contacts: Vec<String>,
+    /// URL of the security policy.
+    pub security_policy: Option<String>,
+    /// Where users change their password. `change-password` is not served if not given.
+    pub change_password_url: Option<String>,
+}
+
+impl Default for WellKnownSettings {
+    fn default() -> Self {
+        Self {
+            robots_disallow: vec!["/v1/".to_owned(), "/api/".to_owned()],
+            security_contacts: Vec::new(),
+            security_policy: None,
+            change_password_url: None,
+        }
+    }
+}
+
+pub fn routes() -> ServerRouter {
+    ServerRouter::new()
+        .typed_get(get_robots_txt)
+        .typed_get(get_security_txt)
+        .typed_get(get_change_password)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/robots.txt")]
+struct RobotsTxtPath;
+
+/// Points crawlers to the sitemap of the tenant, if sitemaps are enabled.
+async fn get_robots_txt(
+    _: RobotsTxtPath,
+    CurrentTenant(tenant): CurrentTenant,
+    State(settings): State<Arc<WellKnownSettings>>,
+    State(sitemaps): State<Arc<Sitemaps>>,
+) -> String {
+    let mut robots_txt = "User-agent: *\n".to_owned();
+    for path in &settings.robots_disallow {
+        writeln!(robots_txt, "Disallow: {path}").expect("Writing to String cannot fail");
+    }
+    if sitemaps.is_enabled() {
+        writeln!(
+            robots_txt,
+            "Sitemap: {}/sitemap.xml",
+            tenant.info.public_url
+        )
+        .expect("Writing to String cannot fail");
+    }
+
+    robots_txt
+}
+
+#[derive(TypedPath)]
+#[typed_path("/.well-known/security.txt")]
+struct SecurityTxtPath;
+
+/// See <https://www.rfc-editor.org/rfc/rfc9116>.
+async fn get_security_txt(
+    _: SecurityTxtPath,
+    State(settings): State<Arc<WellKnownSettings>>,
+) -> Result<String> {
+    if settings.security_contacts.is_empty() {
+        return Err(ServerError::WellKnownNotConfigured("security.txt"));
+    }
+
+    let mut security_txt = String::new();
+    for contact in &settings.security_contacts {
+        writeln!(security_txt, "Contact: {contact}").expect("Writing to String cannot fail");
+    }
+    // The contacts are configured, so they are current as long as the instance runs.
+    let expires = (UtcDateTime::now() + SECURITY_TXT_VALIDITY)
+        .replace_nanosecond(0)
+        .expect("0 is a valid nanosecond")
+        .format(&Rfc3339)
+        .expect("Current dates can be formatted as RFC 3339");
+    writeln!(security_txt, "Expires: {expires}").expect("Writing to String cannot fail");
+    if let Some(policy) = &settings.security_policy {
+        writeln!(security_txt, "Policy: {policy}").expect("Writing to String cannot fail");
+    }
+
+    Ok(security_txt)
+}
+
+#[derive(TypedPath)]
+#[typed_path("/.well-known/change-password")]
+struct ChangePasswordPath;
+
+/// See <https://w3c.github.io/webappsec-change-password-url/>.
+async fn get_change_password(
+    _: ChangePasswordPath,
+    State(settings): State<Arc<WellKnownSettings>>,
+) -> Result<Redirect> {
+    let url = settings
+        .change_password_url
+        .as_deref()
+        .ok_or(ServerError::WellKnownNotConfigured("change-password"))?;
+
+    Ok(Redirect::to(url))
+}
@@ -0,0 +1,133 @@
+//! Spam heuristics, scored with the [`SpamSettings`] whenever local users post or follow.
+//!
+//! Depending on the [`SpamVerdict`], the account is reported automatically, its post is held
+//! for review by deleting it softly until a moderator restores it, or the request is rejected
+//! with [`ServerError::Throttled`]. The settings start from the configuration and can be changed
+//! through the admin API until the process restarts.
+
+use crate::server::{Result, ServerError};
+use std::{sync::nonpoison::Mutex, time::Duration};
+use stellwerk_common::model::{
+    Id, ModelValidationError,
+    post::{CreatePost, PostMarker},
+    report::{CreateReport, ReportCategory},
+    spam::{SpamAssessment, SpamSettings, SpamSignal, SpamVerdict},
+    user::UserMarker,
+};
+use stellwerk_db::client::DbClient;
+use time::UtcDateTime;
+use tracing::info;
+
+#[derive(Debug)]
+pub struct SpamGuard {
+    settings: Mutex<SpamSettings>,
+}
+
+impl SpamGuard {
+    #[must_use]
+    pub fn new(settings: SpamSettings) -> Self {
+        Self {
+            settings: Mutex::new(settings),
+        }
+    }
+
+    #[must_use]
+    pub fn settings(&self) -> SpamSettings {
+        *self.settings.lock()
+    }
+
+    pub fn set_settings(&self, settings: SpamSettings) {
+        *self.settings.lock() = settings;
+    }
+
+    /// Scores the post before it is created.
+    /// Rejects it if the verdict is [`SpamVerdict::Throttle`].
+    pub async fn assess_post(&self, db: &DbClient, post: &CreatePost) -> Result<SpamAssessment> {
+        let settings = self.settings();
+        let now = UtcDateTime::now();
+
+        let mut observations = Vec::with_capacity(2);
+        if settings.burst_posts != 0 {
+            let since = now - Duration::from_secs(settings.burst_window);
+            let posts = db.count_posts_since(post.author, since).await?;
+            observations.push((SpamSignal::BurstPosting, posts + 1));
+        }
+        if settings.duplicate_accounts != 0 {
+            let since = now - Duration::from_secs(settings.duplicate_window);
+            let posters = db
+                .count_duplicate_posters(post.author, &post.content, since)
+                .await?;
+            observations.push((SpamSignal::DuplicateContent, posters));
+        }
+
+        Self::reject_throttled(post.author, settings.assess(&observations))
+    }
+
+    /// Scores the follow before it is made.
+    /// Rejects it if the verdict is [`SpamVerdict::Throttle`].
+    pub async fn assess_follow(
+        &self,
+        db: &DbClient,
+        follower: Id<UserMarker>,
+    ) -> Result<SpamAssessment> {
+        let settings = self.settings();
+        if settings.follows == 0 {
+            return Ok(SpamAssessment::default());
+        }
+
+        let since = UtcDateTime::now() - Duration::from_secs(settings.follow_window);
+        let follows = db.count_follows_since(follower, since).await?;
+        let assessment = settings.assess(&[(SpamSignal::MassFollowing, follows + 1)]);
+
+        Self::reject_throttled(follower, assessment)
+    }
+
+    fn reject_throttled(
+        user_id: Id<UserMarker>,
+        assessment: SpamAssessment,
+    ) -> Result<SpamAssessment> {
+        if assessment.verdict == SpamVerdict::Throttle {
+            info!(%user_id, score = assessment.score, signals = ?assessment.signals, "Throttled suspected spam");
+            return Err(ServerError::Throttled);
+        }
+
+        Ok(assessment)
+    }
+}
+
+/// Reports the user for the assessment unless the verdict is [`SpamVerdict::Allow`].
+/// Reports for [`SpamVerdict::Report`] are skipped while an automatic report about the user
+/// is still open, but held posts are always reported, since moderators find them that way.
+pub async fn report(
+    db: &DbClient,
+    user_id: Id<UserMarker>,
+    post: Option<Id<PostMarker>>,
+    assessment: &SpamAssessment,
+) -> Result<()> {
+    let held = post.is_some() && assessment.verdict == SpamVerdict::Review;
+    if assessment.verdict == SpamVerdict::Allow
+        || (!held && db.has_open_automatic_report(user_id).await?)
+    {
+        return Ok(());
+    }
+
+    let signals: Vec<_> = assessment
+        .signals
+        .iter()
+        .map(|signal| signal.as_str())
+        .collect();
+    let report = CreateReport::automatic(user_id, ReportCategory::Spam).with_comment(format!(
+        "Flagged automatically with a spam score of {}: {}",
+        assessment.score,
+        signals.join(", ")
+    ));
+    let report = report.map_err(ModelValidationError::from)?;
+    let report = match post {
+        Some(post) => report.with_post(post),
+        None => report,
+    };
+    let report_id = db.create_report(&report).await?;
+    info!(%user_id, %report_id, score = assessment.score, signals = ?assessment.signals, "Reported suspected spam");
+
+    Ok(())
+}
@@ -0,0 +1,133 @@
+//! The tenant of each request, see [`stellwerk_common::model::tenant`].
+//!
+//! Tenants are configured with the hosts they serve. Requests are assigned the tenant of the host
+//! they were sent to, or the default tenant if no tenant serves it, so that deployments serving a
+//! single community need no configuration. Reverse proxies have to pass the `Host` header on.
+//!
+//! Accounts, authentication, handles and the public timeline are separate for every tenant, and
+//! moderators and admins only see the users, posts, reports and moderation log of their own.
+//! Federation and the settings of the deployment, like read-only mode, feature flags and jobs,
+//! are shared by all tenants.
+
+use crate::server::ServerError;
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{header::HOST, request::Parts},
+    middleware::Next,
+    response::Response,
+};
+use std::{collections::HashMap, sync::Arc};
+use stellwerk_common::model::{instance::InstanceInfo, tenant::TenantId};
+use tracing::Span;
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Tenant {
+    pub id: TenantId,
+    /// The features are the defaults of the [feature flags](crate::server::feature_flags),
+    /// which apply to all tenants.
+    pub info: InstanceInfo,
+}
+
+/// The tenant a request was assigned, see the [module docs](self).
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct CurrentTenant(pub Arc<Tenant>);
+
+#[derive(Clone, Debug)]
+pub struct Tenants {
+    default: Arc<Tenant>,
+    by_host: HashMap<Box<str>, Arc<Tenant>>,
+}
+
+impl Tenants {
+    #[must_use]
+    pub fn new(default: Tenant) -> Self {
+        Self {
+            default: Arc::new(default),
+            by_host: HashMap::new(),
+        }
+    }
+
+    /// Serves `tenant` for `hosts`. Returns the first host that is already served by another
+    /// tenant as error.
+    pub fn with_tenant(mut self, tenant: Tenant, hosts: &[String]) -> Result<Self, String> {
+        let tenant = Arc::new(tenant);
+        for host in hosts {
+            let host = host.to_ascii_lowercase();
+            let host = normalize_host(&host);
+            if self.by_host.insert(host.into(), tenant.clone()).is_some() {
+                return Err(host.to_owned());
+            }
+        }
+
+        Ok(self)
+    }
+
+    #[must_use]
+    pub fn default_tenant(&self) -> &Arc<Tenant> {
+        &self.default
+    }
+
+    /// The tenant with `id`, or the default tenant if it is no longer configured.
+    #[must_use]
+    pub fn by_id(&self, id: &TenantId) -> &Arc<Tenant> {
+        self.by_host
+            .values()
+            .find(|tenant| tenant.id == *id)
+            .unwrap_or(&self.default)
+    }
+
+    /// The tenant serving `host`, which may include a port.
+    #[must_use]
+    pub fn resolve(&self, host: Option<&str>) -> &Arc<Tenant> {
+        host.and_then(|host| self.by_host.get(normalize_host(host)))
+            .unwrap_or(&self.default)
+    }
+}
+
+/// Without port and trailing dot. Callers lowercase the host first.
+fn normalize_host(host: &str) -> &str {
+    let host = match host.rsplit_once(':') {
+        // IPv6 addresses contain colons themselves, but are enclosed in brackets.
+        Some((host, port)) if !port.contains(']') => host,
+        _ => host,
+    };
+
+    host.trim_end_matches('.')
+}
+
+/// Assigns the request its [`CurrentTenant`] and records it into the request span.
+/// Must be wrapped by the trace layer, see [`logging::make_span`](crate::server::logging::make_span).
+pub async fn resolve(
+    State(tenants): State<Arc<Tenants>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    // HTTP/2 requests have the host in the URI instead of the header.
+    let host = request.uri().host().or_else(|| {
+        request
+            .headers()
+            .get(HOST)
+            .and_then(|host| host.to_str().ok())
+    });
+    let tenant = match host {
+        Some(host) => tenants.resolve(Some(&host.to_ascii_lowercase())).clone(),
+        None => tenants.default_tenant().clone(),
+    };
+
+    Span::current().record("tenant", tenant.id.get());
+    request.extensions_mut().insert(CurrentTenant(tenant));
+
+    next.run(request).await
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for CurrentTenant {
+    type Rejection = ServerError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Self>()
+            .cloned()
+            .ok_or(ServerError::TenantUnknown)
+    }
+}
@@ -0,0 +1,56 @@
+//! The client API is versioned by path prefix, e.g. `/v1/posts`.
+//!
+//! Every version has its own router, built from the shared handlers.
+//! Routes from before versioning are still served unversioned, but marked as deprecated.
+
+use crate::federation::ACTIVITY_JSON;
+use axum::{
+    extract::{OriginalUri, Request},
+    http::{
+        HeaderName, HeaderValue,
+        header::{CONTENT_TYPE, LINK},
+    },
+    middleware::Next,
+    response::Response,
+};
+
+/// The prefix of the current API version.
+pub const CURRENT_VERSION: &str = "/v1";
+
+/// See <https://www.rfc-editor.org/rfc/rfc9745>.
+const DEPRECATION: HeaderName = HeaderName::from_static("deprecation");
+/// When the unversioned routes were deprecated, 2025-11-16T00:00:00Z.
+const UNVERSIONED_DEPRECATED_AT: HeaderValue = HeaderValue::from_static("@1763251200");
+
+/// Marks responses of unversioned routes as deprecated and links to the current version.
+///
+/// ActivityPub representations are left alone, since their URLs are the ids of the objects,
+/// which must not change.
+pub async fn deprecated_unversioned(
+    OriginalUri(uri): OriginalUri,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+
+    let is_activity = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .is_some_and(|content_type| content_type == ACTIVITY_JSON);
+    if is_activity {
+        return response;
+    }
+
+    let headers = response.headers_mut();
+    headers.insert(DEPRECATION, UNVERSIONED_DEPRECATED_AT);
+    let successor = uri
+        .path_and_query()
+        .map_or_else(|| uri.path(), |path_and_query| path_and_query.as_str());
+    if let Ok(link) = HeaderValue::try_from(format!(
+        "<{CURRENT_VERSION}{successor}>; rel=\"successor-version\""
+    )) {
+        headers.append(LINK, link);
+    }
+
+    response
+}
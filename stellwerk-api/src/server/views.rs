@@ -0,0 +1,115 @@
+//! View counts of posts, see [`PostStats`](stellwerk_common::model::post::PostStats).
+//!
+//! Like [analytics](crate::server::analytics), views are counted in memory and added to the counts
+//! in the database periodically, so that viewing a post does not write to the database.
+//! Each viewer is counted once per post and day, by their user if authenticated and by their IP
+//! address otherwise. Authors viewing their own posts are not counted.
+//!
+//! Only views of the API and the post pages that reach the server are counted, so responses served
+//! from the [response cache](crate::server::response_cache) or a CDN are not.
+
+use crate::server::{auth::AuthenticatedUser, client_ip::ClientIp};
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+    sync::{Arc, nonpoison::Mutex},
+    time::Duration,
+};
+use stellwerk_common::model::{
+    Id,
+    post::{Post, PostMarker},
+    user::UserMarker,
+};
+use stellwerk_db::client::DbClient;
+use time::{Date, UtcDateTime};
+use tokio_util::sync::CancellationToken;
+use tracing::error;
+
+const FLUSH_INTERVAL: Duration = Duration::from_mins(1);
+/// Viewers are forgotten early once this many were seen in a day, so that memory stays bounded.
+/// Viewers seen again after that are counted again.
+const SEEN_MAX_LEN: usize = 100_000;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Viewer {
+    User(Id<UserMarker>),
+    Ip(IpAddr),
+}
+
+impl Viewer {
+    /// The user if authenticated, their IP address otherwise.
+    #[must_use]
+    pub fn new(user: Option<AuthenticatedUser>, ClientIp(ip): ClientIp) -> Self {
+        user.map_or(Viewer::Ip(ip), |user| Viewer::User(user.user_id()))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PostViews {
+    pending: Mutex<Pending>,
+}
+
+/// What was recorded since the last flush.
+#[derive(Debug, Default)]
+struct Pending {
+    /// The day of `seen`, in UTC.
+    day: Option<Date>,
+    /// The viewers of each post today, to not count them again.
+    seen: HashSet<(Id<PostMarker>, Viewer)>,
+    views: HashMap<Id<PostMarker>, u64>,
+}
+
+impl PostViews {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts the view, once per viewer and day.
+    pub fn record(&self, post: &Post, viewer: Viewer) {
+        if viewer == Viewer::User(post.author.id) {
+            return;
+        }
+
+        let day = UtcDateTime::now().date();
+        let mut pending = self.pending.lock();
+        if pending.day != Some(day) || pending.seen.len() >= SEEN_MAX_LEN {
+            pending.day = Some(day);
+            pending.seen.clear();
+        }
+        if pending.seen.insert((post.id, viewer)) {
+            *pending.views.entry(post.id).or_default() += 1;
+        }
+    }
+
+    /// Adds the views counted since the last flush to the database. Views that could not be added
+    /// are dropped, since they are approximate anyway.
+    async fn flush(&self, db: &DbClient) {
+        let views: Vec<_> = std::mem::take(&mut self.pending.lock().views)
+            .into_iter()
+            .collect();
+        if views.is_empty() {
+            return;
+        }
+
+        if let Err(error) = db.record_post_views(&views).await {
+            error!(%error, posts = views.len(), "Error trying to record post views");
+        }
+    }
+}
+
+/// Flushes the views periodically, and once more when cancelled.
+pub async fn post_views_flush_loop(
+    db: Arc<DbClient>,
+    views: Arc<PostViews>,
+    cancellation: CancellationToken,
+) {
+    while cancellation
+        .run_until_cancelled(tokio::time::sleep(FLUSH_INTERVAL))
+        .await
+        .is_some()
+    {
+        views.flush(&db).await;
+    }
+    views.flush(&db).await;
+}
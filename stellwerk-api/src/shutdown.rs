@@ -0,0 +1,142 @@
+//! Coordinated shutdown on `SIGINT` or `SIGTERM`.
+//!
+//! Once signalled, no new connections are accepted and in-flight requests are drained.
+//! Afterwards, background tasks are stopped, which lets them finish their current work.
+//! Both have to be done within the drain timeout, counted from the signal.
+
+use std::{
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+use tokio::{
+    signal::{self, unix::SignalKind},
+    task::{JoinError, JoinHandle},
+    time::Instant,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+#[derive(Clone, Debug)]
+pub struct Shutdown {
+    /// Cancelled once the signal was received.
+    signalled: CancellationToken,
+    /// Set before `signalled` is cancelled.
+    deadline: Arc<OnceLock<Instant>>,
+    drain_timeout: Duration,
+}
+
+impl Shutdown {
+    /// Installs the signal handlers.
+    pub fn listen(drain_timeout: Duration) -> std::io::Result<Self> {
+        #[cfg(unix)]
+        let mut ctrl_c_signal = signal::unix::signal(SignalKind::interrupt())?;
+
+        #[cfg(not(unix))]
+        let mut ctrl_c_signal = signal::windows::ctrl_c()?;
+
+        #[cfg(unix)]
+        let mut terminate_signal = signal::unix::signal(SignalKind::terminate())?;
+
+        #[cfg(not(unix))]
+        let terminate_future = std::future::pending::<()>();
+
+        let shutdown = Self {
+            signalled: CancellationToken::new(),
+            deadline: Arc::new(OnceLock::new()),
+            drain_timeout,
+        };
+
+        tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move {
+                #[cfg(unix)]
+                let terminate_future = terminate_signal.recv();
+
+                tokio::select! {
+                    _ = ctrl_c_signal.recv() => {},
+                    _ = terminate_future => {},
+                }
+
+                info!("Shutdown signal received");
+                shutdown.begin();
+            }
+        });
+
+        Ok(shutdown)
+    }
+
+    /// Shuts down as if the signal was received, for when the server cannot continue safely.
+    pub fn begin(&self) {
+        if self
+            .deadline
+            .set(Instant::now() + self.drain_timeout)
+            .is_ok()
+        {
+            info!("Draining for up to {:?}", self.drain_timeout);
+        }
+        self.signalled.cancel();
+    }
+
+    /// Cancelled once the signal was received.
+    /// Responses that never end by themselves, like event streams, must end with it.
+    pub fn token(&self) -> CancellationToken {
+        self.signalled.clone()
+    }
+
+    pub async fn signalled(&self) {
+        self.signalled.cancelled().await;
+    }
+
+    /// Runs `future` to completion, unless it is still running at the drain deadline.
+    pub async fn drain<F: Future>(&self, future: F) -> Option<F::Output> {
+        tokio::select! {
+            output = future => Some(output),
+            () = self.deadline_reached() => None,
+        }
+    }
+
+    async fn deadline_reached(&self) {
+        self.signalled().await;
+        let deadline = *self
+            .deadline
+            .get()
+            .expect("The deadline is set before signalling");
+        tokio::time::sleep_until(deadline).await;
+    }
+}
+
+/// Long-running tasks that are stopped after requests were drained.
+#[derive(Debug, Default)]
+pub struct BackgroundTasks {
+    cancellation: CancellationToken,
+    handles: Vec<(&'static str, JoinHandle<()>)>,
+}
+
+impl BackgroundTasks {
+    pub fn spawn<F>(&mut self, name: &'static str, task: impl FnOnce(CancellationToken) -> F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let handle = tokio::spawn(task(self.cancellation.clone()));
+        info!("Started {name}");
+        self.handles.push((name, handle));
+    }
+
+    /// Cancels all tasks, and waits for them to finish their current work until the drain deadline.
+    /// Returns the names of the tasks that did not finish in time.
+    pub async fn stop(self, shutdown: &Shutdown) -> Result<Vec<&'static str>, JoinError> {
+        self.cancellation.cancel();
+
+        let mut unfinished = Vec::new();
+        for (name, handle) in self.handles {
+            if let Some(result) = shutdown.drain(handle).await {
+                result?;
+            } else {
+                warn!("The {name} did not finish before the drain deadline");
+                unfinished.push(name);
+            }
+        }
+
+        Ok(unfinished)
+    }
+}
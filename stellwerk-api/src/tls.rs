@@ -0,0 +1,37 @@
+//! Optional TLS termination, for deployments without a reverse proxy.
+//!
+//! Certificates are reloaded on `SIGHUP`, so renewed ones can be picked up without a restart.
+//! Connections keep the certificate they were established with.
+
+use crate::config::TlsConfig;
+use axum_server::tls_rustls::RustlsConfig;
+use tokio::signal::unix::Signal;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info};
+
+pub async fn load(config: &TlsConfig) -> std::io::Result<RustlsConfig> {
+    RustlsConfig::from_pem_file(&config.cert_path, &config.key_path).await
+}
+
+pub async fn reload_on_hangup(
+    rustls: RustlsConfig,
+    config: TlsConfig,
+    mut hangup: Signal,
+    cancellation: CancellationToken,
+) {
+    while cancellation
+        .run_until_cancelled(hangup.recv())
+        .await
+        .flatten()
+        .is_some()
+    {
+        // A failed reload keeps the previous certificate.
+        match rustls
+            .reload_from_pem_file(&config.cert_path, &config.key_path)
+            .await
+        {
+            Ok(()) => info!(cert = %config.cert_path.display(), "Reloaded TLS certificate"),
+            Err(error) => error!(%error, "Error trying to reload TLS certificate"),
+        }
+    }
+}
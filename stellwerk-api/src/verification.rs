@@ -0,0 +1,153 @@
+//! Checking that the website on a profile links back to it, before an admin grants a
+//! [verified badge](stellwerk_common::model::verification).
+//!
+//! The website is chosen by the user, so it is only fetched from global addresses over http or
+//! https, also after redirects, so that it cannot reach services in the network of the server.
+//! Other clients fetching URLs chosen by others use the same [`GlobalResolver`] and
+//! [`follow_global_redirect`].
+
+use reqwest::{
+    Url,
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect::{self, Attempt, Policy},
+};
+use std::{net::IpAddr, sync::Arc, time::Duration};
+use stellwerk_common::model::{user::ProfileWebsite, verification::links_back};
+use thiserror::Error;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_REDIRECTS: usize = 3;
+/// Rest of larger pages is not searched for the link.
+const BODY_MAX_LEN: usize = 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum LinkCheckError {
+    #[error("The website is not at a global http or https address")]
+    NotGlobal,
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+}
+
+#[derive(Clone, Debug)]
+pub struct LinkVerifier {
+    http: reqwest::Client,
+}
+
+impl LinkVerifier {
+    pub fn new() -> Result<Self, reqwest::Error> {
+        let http = reqwest::Client::builder()
+            .user_agent(concat!("stellwerk/", env!("CARGO_PKG_VERSION")))
+            .timeout(REQUEST_TIMEOUT)
+            .redirect(Policy::custom(follow_global_redirect))
+            .dns_resolver(Arc::new(GlobalResolver))
+            .build()?;
+
+        Ok(Self { http })
+    }
+
+    /// Whether `website` has a `rel="me"` link to `profile_url`.
+    pub async fn links_back(
+        &self,
+        website: &ProfileWebsite,
+        profile_url: &str,
+    ) -> Result<bool, LinkCheckError> {
+        let url = Url::parse(website.get()).map_err(|_| LinkCheckError::NotGlobal)?;
+        if !is_global(&url) {
+            return Err(LinkCheckError::NotGlobal);
+        }
+
+        let mut response = self.http.get(url).send().await?.error_for_status()?;
+
+        let mut body = Vec::new();
+        while body.len() < BODY_MAX_LEN
+            && let Some(chunk) = response.chunk().await?
+        {
+            body.extend_from_slice(&chunk);
+        }
+        body.truncate(BODY_MAX_LEN);
+
+        Ok(links_back(&String::from_utf8_lossy(&body), profile_url))
+    }
+}
+
+/// Whether `url` is http or https, and not at an IP address that is not global.
+/// Host names are checked when they are resolved, by [`GlobalResolver`].
+pub(crate) fn is_global(url: &Url) -> bool {
+    let ip = url.host_str().and_then(|host| {
+        // IPv6 addresses are enclosed in brackets.
+        host.trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .ok()
+    });
+
+    matches!(url.scheme(), "http" | "https") && ip.is_none_or(|ip| ip.is_global())
+}
+
+/// Follows at most 3 redirects, and only to URLs passing [`is_global`].
+pub(crate) fn follow_global_redirect(attempt: Attempt) -> redirect::Action {
+    if attempt.previous().len() >= MAX_REDIRECTS {
+        attempt.error("too many redirects")
+    } else if !is_global(attempt.url()) {
+        attempt.error(LinkCheckError::NotGlobal)
+    } else {
+        attempt.follow()
+    }
+}
+
+/// Resolves host names like the system, but only to global addresses.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct GlobalResolver;
+
+impl Resolve for GlobalResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addresses: Vec<_> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|address| address.ip().is_global())
+                .collect();
+            if addresses.is_empty() {
+                return Err(LinkCheckError::NotGlobal.into());
+            }
+
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::verification::{LinkCheckError, LinkVerifier, is_global};
+    use reqwest::Url;
+    use std::{error::Error, iter};
+    use stellwerk_common::model::user::ProfileWebsite;
+
+    #[test]
+    fn only_global_http_urls() {
+        let is_global = |url: &str| is_global(&Url::parse(url).unwrap());
+
+        assert!(is_global("https://alice.example/about"));
+        assert!(is_global("http://93.184.215.14/"));
+        assert!(!is_global("ftp://alice.example/"));
+        assert!(!is_global("http://127.0.0.1:8080/"));
+        assert!(!is_global("http://10.0.0.1/"));
+        assert!(!is_global("http://169.254.169.254/latest/meta-data"));
+        assert!(!is_global("http://[::1]/"));
+        assert!(!is_global("http://[::ffff:127.0.0.1]/"));
+    }
+
+    #[tokio::test]
+    async fn rejects_host_names_of_local_addresses() {
+        let website = ProfileWebsite::new("http://localhost/".to_owned()).unwrap();
+        let error = LinkVerifier::new()
+            .unwrap()
+            .links_back(&website, "https://stellwerk.example/@alice")
+            .await
+            .unwrap_err();
+
+        assert!(
+            iter::successors(Some::<&dyn Error>(&error), |&error| error.source())
+                .any(|error| matches!(error.downcast_ref(), Some(LinkCheckError::NotGlobal)))
+        );
+    }
+}
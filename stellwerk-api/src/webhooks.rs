@@ -0,0 +1,148 @@
+//! Delivery of queued webhook payloads, see [`stellwerk_common::model::webhook`].
+//!
+//! Payloads are queued as [jobs](crate::jobs) by the database in the transaction of the change
+//! they are about. Webhooks whose requests keep failing are disabled until their owner enables
+//! them again, which drops the deliveries queued for them.
+//!
+//! Users choose the URLs, so like profile websites, they are only requested from global
+//! addresses, see [`verification`].
+
+use crate::{
+    jobs::{JobError, JobHandler, QueueSettings},
+    verification,
+};
+use axum::http::{StatusCode, header};
+use reqwest::{Url, redirect::Policy};
+use std::{sync::Arc, time::Duration};
+use stellwerk_common::model::{
+    job::{Job, JobKind, JobPayload},
+    webhook::{
+        WEBHOOK_DELIVERY_HEADER, WEBHOOK_SIGNATURE_HEADER, WEBHOOK_TIMESTAMP_HEADER, WebhookTarget,
+    },
+};
+use stellwerk_db::client::DbClient;
+use thiserror::Error;
+use time::UtcDateTime;
+use tracing::{debug, info};
+
+/// After this many failed requests in a row across all its deliveries, a webhook is disabled.
+const WEBHOOK_MAX_FAILURES: u32 = 25;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+enum DeliveryError {
+    #[error("The webhook URL is not at a global address")]
+    NotGlobal,
+    #[error("The request failed: {0}")]
+    Request(#[from] reqwest::Error),
+}
+
+impl DeliveryError {
+    fn is_permanent(&self) -> bool {
+        match self {
+            DeliveryError::NotGlobal => true,
+            DeliveryError::Request(error) => is_permanent(error),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WebhookDispatcher {
+    db: Arc<DbClient>,
+    http: reqwest::Client,
+}
+
+impl WebhookDispatcher {
+    pub fn new(db: Arc<DbClient>) -> Result<Self, reqwest::Error> {
+        let http = reqwest::Client::builder()
+            .user_agent(concat!("stellwerk/", env!("CARGO_PKG_VERSION")))
+            .timeout(REQUEST_TIMEOUT)
+            .redirect(Policy::custom(verification::follow_global_redirect))
+            .dns_resolver(Arc::new(verification::GlobalResolver))
+            .build()?;
+
+        Ok(Self { db, http })
+    }
+
+    async fn post_payload(
+        &self,
+        job: &Job,
+        target: &WebhookTarget,
+        payload: &str,
+    ) -> Result<(), DeliveryError> {
+        let url = Url::parse(&target.url)
+            .ok()
+            .filter(verification::is_global)
+            .ok_or(DeliveryError::NotGlobal)?;
+        let timestamp = UtcDateTime::now().unix_timestamp();
+        let signature = target.secret.sign(timestamp, payload.as_bytes());
+
+        self.http
+            .post(url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(WEBHOOK_DELIVERY_HEADER, job.id.to_string())
+            .header(WEBHOOK_TIMESTAMP_HEADER, timestamp)
+            .header(WEBHOOK_SIGNATURE_HEADER, signature)
+            .body(payload.to_owned())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+impl JobHandler for WebhookDispatcher {
+    const SETTINGS: QueueSettings = QueueSettings {
+        kind: JobKind::WebhookDelivery,
+        batch_size: 50,
+        visibility_timeout: Duration::from_mins(5),
+        max_attempts: 8,
+        retry_base_delay: Duration::from_secs(30),
+        retry_max_delay: Duration::from_hours(1),
+    };
+
+    async fn run(&self, job: &Job) -> Result<Option<UtcDateTime>, JobError> {
+        let JobPayload::WebhookDelivery { webhook, payload } = &job.payload else {
+            return Err(JobError::unexpected_payload(&job.payload));
+        };
+        let Some(target) = self.db.fetch_webhook_target(*webhook).await? else {
+            debug!(id = %job.id, %webhook, "Dropped delivery to deleted or disabled webhook");
+            return Ok(None);
+        };
+
+        match self.post_payload(job, &target, payload).await {
+            Ok(()) => {
+                debug!(id = %job.id, %webhook, "Delivered webhook");
+                self.db.reset_webhook_failures(*webhook).await?;
+                Ok(None)
+            }
+            Err(error) => {
+                let disabled = self
+                    .db
+                    .record_webhook_failure(*webhook, UtcDateTime::now(), WEBHOOK_MAX_FAILURES)
+                    .await?;
+                if disabled {
+                    info!(%webhook, "Disabled webhook after repeated failures");
+                }
+
+                Err(if error.is_permanent() {
+                    JobError::permanent(error)
+                } else {
+                    JobError::transient(error)
+                })
+            }
+        }
+    }
+}
+
+/// Whether retrying cannot help. Client errors are permanent,
+/// except for timeouts and rate limiting.
+pub fn is_permanent(error: &reqwest::Error) -> bool {
+    error.is_builder()
+        || error.status().is_some_and(|status| {
+            status.is_client_error()
+                && status != StatusCode::REQUEST_TIMEOUT
+                && status != StatusCode::TOO_MANY_REQUESTS
+        })
+}
@@ -0,0 +1,917 @@
+//! A command-line client of the REST API, for scripting and administration.
+//!
+//! Results are printed for humans by default, or as JSON with `--json`.
+//! Paginated commands print the cursor of the next page to stderr, so that stdout only has the items.
+
+use clap::{Args, Parser, Subcommand};
+use reqwest::Url;
+use serde::Serialize;
+use std::process::ExitCode;
+use stellwerk_client::client::{Client, ClientError};
+use stellwerk_common::model::{
+    Id,
+    admin::{CreateUserAccount, Impersonation, UserAccount},
+    announcement::{
+        AnnouncementBody, AnnouncementTitle, CreateAnnouncement, InvalidAnnouncementError,
+        UserAnnouncement,
+    },
+    feature::{FeatureFlag, FeatureFlagState},
+    job::{DeadJob, JobMarker},
+    pagination::{Cursor, Page, PageRequest},
+    policy::{InvalidPolicyContentError, Policy, PolicyContent, PolicyKind},
+    post::{InvalidPostContentError, ModeratedPost, PartialPost, Post, PostContent, PostMarker},
+    report::{
+        InvalidReportNoteError, QueuedReport, Report, ReportAction, ReportNote, ReportNoteContent,
+    },
+    user::{
+        InvalidProfileWebsiteError, InvalidUserHandleError, ModeratedUser, ProfileWebsite,
+        UserProfile, UserRole,
+    },
+    webhook::{CreateWebhook, InvalidWebhookError, Webhook, WebhookEventKind, WebhookUrl},
+};
+use thiserror::Error;
+use time::{UtcDateTime, format_description::well_known::Rfc3339};
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Cli {
+    /// The base URL of the instance.
+    #[arg(long, env = "STELLWERK_URL", default_value = "http://localhost:8080")]
+    url: Url,
+    /// The auth token to authenticate with.
+    #[arg(long, env = "STELLWERK_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    /// Print results as JSON.
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    #[command(subcommand)]
+    User(UserCommand),
+    #[command(subcommand)]
+    Post(PostCommand),
+    #[command(subcommand)]
+    Timeline(TimelineCommand),
+    /// Manage auth tokens. Requires the admin role.
+    #[command(subcommand)]
+    Token(TokenCommand),
+    /// Handle reports and posts. Requires the moderator role.
+    #[command(subcommand)]
+    Moderation(ModerationCommand),
+    #[command(subcommand)]
+    Announcement(AnnouncementCommand),
+    #[command(subcommand)]
+    Policy(PolicyCommand),
+    #[command(subcommand)]
+    Webhook(WebhookCommand),
+    /// Toggle features at runtime. Requires the admin role.
+    #[command(subcommand)]
+    Feature(FeatureCommand),
+    /// Inspect and retry dead-lettered background jobs. Requires the admin role.
+    #[command(subcommand)]
+    Job(JobCommand),
+}
+
+#[derive(Debug, Subcommand)]
+enum UserCommand {
+    /// Create a user. Requires the admin role.
+    Create {
+        handle: String,
+        #[arg(long, default_value_t = UserRole::User)]
+        role: UserRole,
+    },
+    /// Show the profile of a user.
+    Show {
+        id: u64,
+    },
+    /// Show the account of a user. Requires the admin role.
+    Account {
+        id: u64,
+    },
+    /// Change the role of a user. Requires the admin role.
+    SetRole {
+        id: u64,
+        role: UserRole,
+    },
+    /// Grant a user the verified badge. Requires the admin role.
+    Verify {
+        id: u64,
+        /// Only if the website on their profile links back to it with `rel="me"`.
+        #[arg(long)]
+        link_back: bool,
+    },
+    /// Revoke the verified badge of a user. Requires the admin role.
+    Unverify {
+        id: u64,
+    },
+    /// Set the website on your profile, or remove it without a URL.
+    SetWebsite {
+        url: Option<String>,
+    },
+    Follow {
+        id: u64,
+    },
+    Unfollow {
+        id: u64,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum PostCommand {
+    Create {
+        content: String,
+    },
+    /// Delete a post of your own.
+    Delete {
+        id: u64,
+    },
+    /// Show how often a post of your own was viewed.
+    Stats {
+        id: u64,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum TimelineCommand {
+    Public(PageArgs),
+    /// The posts of a user, pinned ones first.
+    User {
+        id: u64,
+        #[command(flatten)]
+        page: PageArgs,
+    },
+}
+
+#[derive(Copy, Clone, Debug, Args)]
+struct PageArgs {
+    #[arg(long)]
+    limit: Option<u32>,
+    /// The cursor printed with the previous page.
+    #[arg(long)]
+    cursor: Option<Cursor<PostMarker>>,
+}
+
+#[derive(Debug, Subcommand)]
+enum TokenCommand {
+    /// Sign a user out of all sessions.
+    Revoke { user_id: u64 },
+    /// Delete or archive expired tokens now.
+    Purge,
+    /// Get a short-lived token to act as a user for support. The user is notified afterwards.
+    Impersonate { user_id: u64 },
+    /// Delete the impersonation tokens of a user before they expire.
+    EndImpersonation { user_id: u64 },
+}
+
+#[derive(Debug, Subcommand)]
+enum ModerationCommand {
+    /// List the open reports with the reported posts.
+    Reports,
+    Report {
+        id: u64,
+    },
+    /// Assign a report to yourself, unless another moderator claimed it.
+    Claim {
+        id: u64,
+    },
+    /// Assign a report to a moderator, or unassign it without `--to`.
+    Assign {
+        id: u64,
+        #[arg(long)]
+        to: Option<u64>,
+    },
+    /// Resolve a report and take the action against its target.
+    Resolve {
+        id: u64,
+        #[arg(long, default_value_t = ReportAction::Dismiss)]
+        action: ReportAction,
+    },
+    /// List the notes of moderators about a report.
+    Notes {
+        id: u64,
+    },
+    /// Add a note about a report for other moderators.
+    Note {
+        id: u64,
+        content: String,
+    },
+    /// Show a post, even if it is deleted.
+    ShowPost {
+        id: u64,
+    },
+    DeletePost {
+        id: u64,
+    },
+    RestorePost {
+        id: u64,
+    },
+    /// Show a user, with whether they are suspended or shadowbanned.
+    ShowUser {
+        id: u64,
+    },
+    /// Hide the posts of a user from everyone but them.
+    Shadowban {
+        id: u64,
+    },
+    Unshadowban {
+        id: u64,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum AnnouncementCommand {
+    /// List the announcements that have not expired.
+    List {
+        /// Include dismissed announcements.
+        #[arg(long)]
+        dismissed: bool,
+    },
+    Read {
+        id: u64,
+    },
+    /// Hide an announcement.
+    Dismiss {
+        id: u64,
+    },
+    /// Announce something to all users. Requires the admin role.
+    Create {
+        title: String,
+        body: String,
+        /// Like `2026-01-01T12:00:00Z`.
+        #[arg(long, value_parser = parse_rfc3339)]
+        expires_at: Option<UtcDateTime>,
+    },
+    /// Requires the admin role.
+    Delete {
+        id: u64,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum PolicyCommand {
+    /// Show the latest version of each policy.
+    List,
+    /// Show the policies that have to be accepted before anything can be changed.
+    Pending,
+    Accept {
+        kind: PolicyKind,
+        version: u64,
+    },
+    /// Publish the next version of a policy. Requires the admin role.
+    Publish {
+        kind: PolicyKind,
+        content: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum WebhookCommand {
+    List,
+    /// Register a webhook. Prints the secret its requests are signed with, which is not shown
+    /// again.
+    Create {
+        url: String,
+        /// `followed_post` or `mention`. Can be given multiple times.
+        #[arg(long = "event", required = true)]
+        events: Vec<WebhookEventKind>,
+    },
+    Delete {
+        id: u64,
+    },
+    /// Enable a webhook that was disabled after too many failed requests.
+    Enable {
+        id: u64,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum FeatureCommand {
+    List,
+    Enable {
+        flag: FeatureFlag,
+    },
+    Disable {
+        flag: FeatureFlag,
+    },
+    /// Let a flag follow the configuration of each server again.
+    Reset {
+        flag: FeatureFlag,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum JobCommand {
+    /// List the jobs that failed permanently or too often, newest first.
+    Dead {
+        #[arg(long)]
+        limit: Option<u32>,
+        /// The cursor printed with the previous page.
+        #[arg(long)]
+        cursor: Option<Cursor<JobMarker>>,
+    },
+    /// Queue a dead-lettered job again.
+    Retry { id: u64 },
+    /// Delete a dead-lettered job without running it again.
+    Delete { id: u64 },
+}
+
+#[derive(Debug, Error)]
+enum CliError {
+    #[error(transparent)]
+    Client(#[from] ClientError),
+    #[error(transparent)]
+    PostContent(#[from] InvalidPostContentError),
+    #[error(transparent)]
+    UserHandle(#[from] InvalidUserHandleError),
+    #[error(transparent)]
+    ProfileWebsite(#[from] InvalidProfileWebsiteError),
+    #[error(transparent)]
+    ReportNote(#[from] InvalidReportNoteError),
+    #[error(transparent)]
+    Announcement(#[from] InvalidAnnouncementError),
+    #[error(transparent)]
+    PolicyContent(#[from] InvalidPolicyContentError),
+    #[error(transparent)]
+    Webhook(#[from] InvalidWebhookError),
+}
+
+type Result<T, E = CliError> = std::result::Result<T, E>;
+
+/// Prints results in the format chosen by `--json`.
+struct Output {
+    json: bool,
+}
+
+impl Output {
+    fn print<T: Serialize>(&self, value: &T, human: impl FnOnce(&T) -> String) {
+        if self.json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(value).expect("Models serialize infallibly")
+            );
+        } else {
+            println!("{}", human(value));
+        }
+    }
+
+    fn print_page<T: Serialize, M>(&self, page: &Page<T, M>, human: impl Fn(&T) -> String) {
+        if self.json {
+            self.print(page, |_| String::new());
+            return;
+        }
+
+        for item in &page.items {
+            println!("{}", human(item));
+        }
+        if let Some(cursor) = &page.next_cursor {
+            eprintln!("Next page: --cursor {cursor}");
+        }
+    }
+}
+
+fn format_post(post: &Post) -> String {
+    format!(
+        "{}  @{}  {}",
+        post.id,
+        post.author.handle.get(),
+        post.content.get()
+    )
+}
+
+fn format_partial_post(post: &PartialPost) -> String {
+    let pinned = if post.pinned { "[pinned] " } else { "" };
+    format!("{}  {pinned}{}", post.id, post.content.get())
+}
+
+fn format_account(account: &UserAccount) -> String {
+    let remote = if account.remote { "  remote" } else { "" };
+    let verified = if account.user.verified {
+        "  verified"
+    } else {
+        ""
+    };
+    format!(
+        "{}  @{}  {}{remote}{verified}",
+        account.user.id,
+        account.user.handle.get(),
+        account.role
+    )
+}
+
+fn format_impersonation(impersonation: &Impersonation) -> String {
+    let expires_at = impersonation
+        .expires_at
+        .format(&Rfc3339)
+        .unwrap_or_default();
+    format!(
+        "{}  @{}  expires {expires_at}\n{}",
+        impersonation.user.id,
+        impersonation.user.handle.get(),
+        impersonation.token
+    )
+}
+
+fn format_profile(profile: &UserProfile) -> String {
+    let verified = if profile.user.verified {
+        "  verified"
+    } else {
+        ""
+    };
+    let website = profile
+        .website
+        .as_ref()
+        .map(|website| format!("\n{}", website.get()))
+        .unwrap_or_default();
+    format!(
+        "{}  @{}{verified}{website}\n{} posts, {} followers, {} following",
+        profile.user.id,
+        profile.user.handle.get(),
+        profile.stats.post_count,
+        profile.stats.follower_count,
+        profile.stats.following_count
+    )
+}
+
+fn format_report(report: &Report) -> String {
+    let target = match report.target_post {
+        Some(post) => format!("post {post} by user {}", report.target_user),
+        None => format!("user {}", report.target_user),
+    };
+    let assignee = report.assignee.map_or_else(
+        || "unassigned".to_owned(),
+        |assignee| format!("assigned to {assignee}"),
+    );
+    let state = if report.resolved_at.is_some() {
+        "resolved"
+    } else {
+        "open"
+    };
+    let reporter = report.reporter.map_or_else(
+        || "reported automatically".to_owned(),
+        |reporter| format!("reported by {reporter}"),
+    );
+
+    format!(
+        "{}  {}  {target}  {reporter}  {assignee}  {state}\n{}",
+        report.id,
+        report.category,
+        report.comment.get()
+    )
+}
+
+fn format_queued_report(report: &QueuedReport) -> String {
+    match &report.post {
+        Some(post) => format!(
+            "{}
+{}",
+            format_report(&report.report),
+            format_moderated_post(post)
+        ),
+        None => format_report(&report.report),
+    }
+}
+
+fn format_report_note(note: &ReportNote) -> String {
+    format!("{}  user {}  {}", note.id, note.author, note.content.get())
+}
+
+fn format_moderated_post(post: &ModeratedPost) -> String {
+    let deleted = if post.deleted_at.is_some() {
+        "[deleted] "
+    } else {
+        ""
+    };
+    format!("{deleted}{}", format_post(&post.post))
+}
+
+fn format_moderated_user(user: &ModeratedUser) -> String {
+    let since = |label: &str, at: Option<UtcDateTime>| {
+        at.and_then(|at| at.format(&Rfc3339).ok())
+            .map(|at| format!("  {label} {at}"))
+            .unwrap_or_default()
+    };
+    format!(
+        "{}  @{}{}{}",
+        user.user.id,
+        user.user.handle.get(),
+        since("suspended", user.suspended_at),
+        since("shadowbanned", user.shadowbanned_at)
+    )
+}
+
+fn format_announcement(
+    UserAnnouncement {
+        announcement,
+        read,
+        dismissed,
+    }: &UserAnnouncement,
+) -> String {
+    let state = if *dismissed {
+        "[dismissed] "
+    } else if *read {
+        ""
+    } else {
+        "[unread] "
+    };
+    let expires = announcement
+        .expires_at
+        .and_then(|expires_at| expires_at.format(&Rfc3339).ok())
+        .map_or_else(String::new, |expires_at| format!("  expires {expires_at}"));
+
+    format!(
+        "{}  {state}{}{expires}\n{}",
+        announcement.id,
+        announcement.title.get(),
+        announcement.body.get()
+    )
+}
+
+fn format_policy(policy: &Policy) -> String {
+    let published = policy.published_at.format(&Rfc3339).unwrap_or_default();
+
+    format!(
+        "{} version {}  published {published}\n{}",
+        policy.kind,
+        policy.version,
+        policy.content.get()
+    )
+}
+
+fn format_webhook(webhook: &Webhook) -> String {
+    let events: Vec<_> = webhook.events.iter().map(|event| event.as_str()).collect();
+    let disabled = if webhook.disabled_at.is_some() {
+        "[disabled] "
+    } else {
+        ""
+    };
+
+    format!(
+        "{}  {disabled}{}  {}",
+        webhook.id,
+        webhook.url.get(),
+        events.join(", ")
+    )
+}
+
+fn parse_rfc3339(date_time: &str) -> Result<UtcDateTime, time::error::Parse> {
+    UtcDateTime::parse(date_time, &Rfc3339)
+}
+
+fn format_feature_flag(state: FeatureFlagState) -> String {
+    let enabled = if state.enabled { "enabled" } else { "disabled" };
+    let overridden = if state.overridden {
+        "  [overridden]"
+    } else {
+        ""
+    };
+
+    format!("{}  {enabled}{overridden}", state.flag)
+}
+
+fn format_dead_job(job: &DeadJob) -> String {
+    let dead_at = job.dead_at.format(&Rfc3339).unwrap_or_default();
+    let error = job.last_error.as_deref().unwrap_or_default();
+
+    format!(
+        "{}  {}  {} attempts  dead since {dead_at}\n{error}",
+        job.id,
+        job.payload.kind(),
+        job.attempts
+    )
+}
+
+fn page_request(args: PageArgs) -> PageRequest<PostMarker> {
+    let cursor = args.cursor.map(PageRequest::from);
+    PageRequest {
+        limit: args.limit,
+        ..cursor.unwrap_or(PageRequest {
+            limit: None,
+            max_id: None,
+            since_id: None,
+        })
+    }
+}
+
+async fn run_user(client: &Client, output: &Output, command: UserCommand) -> Result<()> {
+    match command {
+        UserCommand::Create { handle, role } => {
+            let account = CreateUserAccount::new(handle)?.with_role(role);
+            let account = client.create_user(&account).await?;
+            output.print(&account, format_account);
+        }
+        UserCommand::Show { id } => {
+            let profile = client.user(id.into()).await?;
+            output.print(&profile, format_profile);
+        }
+        UserCommand::Account { id } => {
+            let account = client.user_account(id.into()).await?;
+            output.print(&account, format_account);
+        }
+        UserCommand::SetRole { id, role } => client.set_user_role(id.into(), role).await?,
+        UserCommand::Verify { id, link_back } => {
+            client.grant_verification(id.into(), link_back).await?;
+        }
+        UserCommand::Unverify { id } => client.revoke_verification(id.into()).await?,
+        UserCommand::SetWebsite { url: Some(url) } => {
+            client
+                .set_profile_website(ProfileWebsite::new(url)?)
+                .await?;
+        }
+        UserCommand::SetWebsite { url: None } => client.delete_profile_website().await?,
+        UserCommand::Follow { id } => client.follow(id.into()).await?,
+        UserCommand::Unfollow { id } => client.unfollow(id.into()).await?,
+    }
+
+    Ok(())
+}
+
+async fn run_post(client: &Client, output: &Output, command: PostCommand) -> Result<()> {
+    match command {
+        PostCommand::Create { content } => {
+            let post = client.create_post(&PostContent::new(content)?).await?;
+            output.print(&post, format_partial_post);
+        }
+        PostCommand::Delete { id } => client.delete_post(id.into()).await?,
+        PostCommand::Stats { id } => {
+            let stats = client.post_stats(id.into()).await?;
+            output.print(&stats, |stats| format!("{} views", stats.views));
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_timeline(client: &Client, output: &Output, command: TimelineCommand) -> Result<()> {
+    match command {
+        TimelineCommand::Public(page) => {
+            let page = client.public_timeline(&page_request(page)).await?;
+            output.print_page(&page, format_post);
+        }
+        TimelineCommand::User { id, page } => {
+            let page = client.user_posts(id.into(), &page_request(page)).await?;
+            output.print_page(&page, format_partial_post);
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_token(client: &Client, output: &Output, command: TokenCommand) -> Result<()> {
+    let purge = match command {
+        TokenCommand::Revoke { user_id } => client.revoke_user_tokens(user_id.into()).await?,
+        TokenCommand::Purge => client.purge_expired_tokens().await?,
+        TokenCommand::Impersonate { user_id } => {
+            let impersonation = client.impersonate_user(user_id.into()).await?;
+            output.print(&impersonation, format_impersonation);
+            return Ok(());
+        }
+        TokenCommand::EndImpersonation { user_id } => {
+            client.end_impersonation(user_id.into()).await?
+        }
+    };
+    output.print(&purge, |purge| format!("Deleted {} tokens", purge.deleted));
+
+    Ok(())
+}
+
+async fn run_moderation(
+    client: &Client,
+    output: &Output,
+    command: ModerationCommand,
+) -> Result<()> {
+    match command {
+        ModerationCommand::Reports => {
+            let reports = client.open_reports().await?;
+            output.print(&reports, |reports| {
+                reports
+                    .iter()
+                    .map(format_queued_report)
+                    .collect::<Vec<_>>()
+                    .join("\n\n")
+            });
+        }
+        ModerationCommand::Report { id } => {
+            let report = client.report(id.into()).await?;
+            output.print(&report, format_report);
+        }
+        ModerationCommand::Assign { id, to } => {
+            client.assign_report(id.into(), to.map(Id::from)).await?;
+        }
+        ModerationCommand::Claim { id } => client.claim_report(id.into()).await?,
+        ModerationCommand::Resolve { id, action } => {
+            client.resolve_report(id.into(), action).await?;
+        }
+        ModerationCommand::Notes { id } => {
+            let notes = client.report_notes(id.into()).await?;
+            output.print(&notes, |notes| {
+                notes
+                    .iter()
+                    .map(format_report_note)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            });
+        }
+        ModerationCommand::Note { id, content } => {
+            let note = client
+                .add_report_note(id.into(), &ReportNoteContent::new(content)?)
+                .await?;
+            output.print(&note, format_report_note);
+        }
+        ModerationCommand::ShowPost { id } => {
+            let post = client.moderated_post(id.into()).await?;
+            output.print(&post, format_moderated_post);
+        }
+        ModerationCommand::DeletePost { id } => client.moderate_post(id.into()).await?,
+        ModerationCommand::RestorePost { id } => client.restore_post(id.into()).await?,
+        ModerationCommand::ShowUser { id } => {
+            let user = client.moderated_user(id.into()).await?;
+            output.print(&user, format_moderated_user);
+        }
+        ModerationCommand::Shadowban { id } => client.shadowban_user(id.into()).await?,
+        ModerationCommand::Unshadowban { id } => client.unshadowban_user(id.into()).await?,
+    }
+
+    Ok(())
+}
+
+async fn run_announcement(
+    client: &Client,
+    output: &Output,
+    command: AnnouncementCommand,
+) -> Result<()> {
+    match command {
+        AnnouncementCommand::List { dismissed } => {
+            let announcements = client.announcements(dismissed).await?;
+            output.print(&announcements, |announcements| {
+                announcements
+                    .iter()
+                    .map(format_announcement)
+                    .collect::<Vec<_>>()
+                    .join("\n\n")
+            });
+        }
+        AnnouncementCommand::Read { id } => client.read_announcement(id.into()).await?,
+        AnnouncementCommand::Dismiss { id } => client.dismiss_announcement(id.into()).await?,
+        AnnouncementCommand::Create {
+            title,
+            body,
+            expires_at,
+        } => {
+            let announcement = client
+                .create_announcement(&CreateAnnouncement {
+                    title: AnnouncementTitle::new(title)?,
+                    body: AnnouncementBody::new(body)?,
+                    expires_at,
+                })
+                .await?;
+            output.print(&announcement, |announcement| {
+                format!("{}  {}", announcement.id, announcement.title.get())
+            });
+        }
+        AnnouncementCommand::Delete { id } => client.delete_announcement(id.into()).await?,
+    }
+
+    Ok(())
+}
+
+async fn run_policy(client: &Client, output: &Output, command: PolicyCommand) -> Result<()> {
+    let print_policies = |policies: &Vec<Policy>| {
+        policies
+            .iter()
+            .map(format_policy)
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    };
+
+    match command {
+        PolicyCommand::List => output.print(&client.policies().await?, print_policies),
+        PolicyCommand::Pending => output.print(&client.pending_policies().await?, print_policies),
+        PolicyCommand::Accept { kind, version } => client.accept_policy(kind, version).await?,
+        PolicyCommand::Publish { kind, content } => {
+            let policy = client
+                .publish_policy(kind, PolicyContent::new(content)?)
+                .await?;
+            output.print(&policy, |policy| {
+                format!("{} version {}", policy.kind, policy.version)
+            });
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_webhook(client: &Client, output: &Output, command: WebhookCommand) -> Result<()> {
+    match command {
+        WebhookCommand::List => {
+            let webhooks = client.webhooks().await?;
+            output.print(&webhooks, |webhooks| {
+                webhooks
+                    .iter()
+                    .map(format_webhook)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            });
+        }
+        WebhookCommand::Create { url, events } => {
+            let webhook = client
+                .create_webhook(&CreateWebhook {
+                    url: WebhookUrl::new(url)?,
+                    events,
+                })
+                .await?;
+            output.print(&webhook, |webhook| {
+                format!(
+                    "{}\nsecret: {}",
+                    format_webhook(&webhook.webhook),
+                    webhook.secret.0
+                )
+            });
+        }
+        WebhookCommand::Delete { id } => client.delete_webhook(id.into()).await?,
+        WebhookCommand::Enable { id } => client.enable_webhook(id.into()).await?,
+    }
+
+    Ok(())
+}
+
+async fn run_feature(client: &Client, output: &Output, command: FeatureCommand) -> Result<()> {
+    let state = match command {
+        FeatureCommand::List => {
+            let states = client.feature_flags().await?;
+            output.print(&states, |states| {
+                states
+                    .iter()
+                    .map(|state| format_feature_flag(*state))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            });
+            return Ok(());
+        }
+        FeatureCommand::Enable { flag } => client.set_feature_flag(flag, true).await?,
+        FeatureCommand::Disable { flag } => client.set_feature_flag(flag, false).await?,
+        FeatureCommand::Reset { flag } => return Ok(client.reset_feature_flag(flag).await?),
+    };
+    output.print(&state, |state| format_feature_flag(*state));
+
+    Ok(())
+}
+
+async fn run_job(client: &Client, output: &Output, command: JobCommand) -> Result<()> {
+    match command {
+        JobCommand::Dead { limit, cursor } => {
+            let page = PageRequest {
+                limit,
+                ..cursor.map_or(
+                    PageRequest {
+                        limit: None,
+                        max_id: None,
+                        since_id: None,
+                    },
+                    PageRequest::from,
+                )
+            };
+            let page = client.dead_jobs(&page).await?;
+            output.print_page(&page, format_dead_job);
+        }
+        JobCommand::Retry { id } => client.retry_dead_job(id.into()).await?,
+        JobCommand::Delete { id } => client.delete_dead_job(id.into()).await?,
+    }
+
+    Ok(())
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let mut client = Client::new(cli.url)?;
+    if let Some(token) = cli.token {
+        client = client.with_token(token);
+    }
+    let output = Output { json: cli.json };
+
+    match cli.command {
+        Command::User(command) => run_user(&client, &output, command).await,
+        Command::Post(command) => run_post(&client, &output, command).await,
+        Command::Timeline(command) => run_timeline(&client, &output, command).await,
+        Command::Token(command) => run_token(&client, &output, command).await,
+        Command::Moderation(command) => run_moderation(&client, &output, command).await,
+        Command::Announcement(command) => run_announcement(&client, &output, command).await,
+        Command::Policy(command) => run_policy(&client, &output, command).await,
+        Command::Webhook(command) => run_webhook(&client, &output, command).await,
+        Command::Feature(command) => run_feature(&client, &output, command).await,
+        Command::Job(command) => run_job(&client, &output, command).await,
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    match run(Cli::parse()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,581 @@
+use reqwest::{Method, RequestBuilder, Response, StatusCode, Url};
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::json;
+use stellwerk_common::model::{
+    Id,
+    admin::{
+        ActivityStats, CreateUserAccount, Impersonation, InstanceOverview, TokenPurge, UserAccount,
+    },
+    announcement::{Announcement, AnnouncementMarker, CreateAnnouncement, UserAnnouncement},
+    feature::{FeatureFlag, FeatureFlagState, SetFeatureFlag},
+    job::{DeadJob, JobMarker},
+    moderation::{ModerationLogEntry, ModerationLogMarker},
+    pagination::{Page, PageRequest, next_cursor},
+    policy::{AcceptPolicy, Policy, PolicyContent, PolicyKind, PublishPolicy},
+    post::{ModeratedPost, PartialPost, Post, PostContent, PostMarker, PostStats},
+    problem::{PROBLEM_JSON, Problem},
+    report::{QueuedReport, Report, ReportAction, ReportMarker, ReportNote, ReportNoteContent},
+    spam::SpamSettings,
+    user::{ModeratedUser, ProfileWebsite, SetProfileWebsite, UserMarker, UserProfile, UserRole},
+    verification::GrantVerification,
+    webhook::{CreateWebhook, CreatedWebhook, Webhook, WebhookMarker},
+};
+use thiserror::Error;
+
+/// The version of the API this client speaks.
+const API_VERSION: &str = "v1";
+
+pub type Result<T, E = ClientError> = std::result::Result<T, E>;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("The base URL cannot have paths appended: {0}")]
+    InvalidBaseUrl(Url),
+    #[error("The request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    /// The server replied with problem details.
+    #[error("The server replied with {} {}: {}", .0.status, .0.code, .0.detail.as_deref().unwrap_or(&.0.title))]
+    Problem(Box<Problem>),
+    /// The server replied with an error that is not problem details, e.g. from a reverse proxy.
+    #[error("The server replied with {0}")]
+    Status(StatusCode),
+}
+
+/// A connection to one instance, optionally authenticated with an auth token.
+///
+/// Cloning is cheap, clones share their connection pool.
+#[derive(Clone, Debug)]
+pub struct Client {
+    http: reqwest::Client,
+    /// Ends with the API version, like `https://example.com/v1`.
+    api_url: Url,
+    token: Option<String>,
+}
+
+impl Client {
+    /// A client of the instance at `base_url`, like `https://example.com`.
+    pub fn new(base_url: Url) -> Result<Self> {
+        Self::with_http_client(reqwest::Client::new(), base_url)
+    }
+
+    /// Like [`Client::new`], but sending requests with a configured `http` client.
+    pub fn with_http_client(http: reqwest::Client, mut base_url: Url) -> Result<Self> {
+        if base_url.cannot_be_a_base() {
+            return Err(ClientError::InvalidBaseUrl(base_url));
+        }
+        base_url
+            .path_segments_mut()
+            .expect("Base URLs can have paths appended")
+            .pop_if_empty()
+            .push(API_VERSION);
+
+        Ok(Self {
+            http,
+            api_url: base_url,
+            token: None,
+        })
+    }
+
+    /// Authenticates all requests with `token`.
+    #[must_use]
+    pub fn with_token(self, token: String) -> Self {
+        Self {
+            token: Some(token),
+            ..self
+        }
+    }
+
+    fn request(&self, method: Method, path: &[&str]) -> RequestBuilder {
+        let mut url = self.api_url.clone();
+        url.path_segments_mut()
+            .expect("The API URL can have paths appended")
+            .extend(path);
+
+        let request = self.http.request(method, url);
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Sends the request and checks the status, turning problem details into [`ClientError::Problem`].
+    async fn send(request: RequestBuilder) -> Result<Response> {
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let is_problem = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .is_some_and(|content_type| content_type == PROBLEM_JSON);
+        if !is_problem {
+            return Err(ClientError::Status(status));
+        }
+
+        let problem: Problem = response.json().await?;
+        Err(ClientError::Problem(Box::new(problem)))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &[&str]) -> Result<T> {
+        let response = Self::send(self.request(Method::GET, path)).await?;
+        Ok(response.json().await?)
+    }
+
+    async fn get_page<T: DeserializeOwned, Marker>(
+        &self,
+        path: &[&str],
+        page: &PageRequest<Marker>,
+    ) -> Result<Vec<T>> {
+        let response = Self::send(self.request(Method::GET, path).query(page)).await?;
+        Ok(response.json().await?)
+    }
+
+    async fn post<T: DeserializeOwned>(&self, path: &[&str], body: &impl Serialize) -> Result<T> {
+        let response = Self::send(self.request(Method::POST, path).json(body)).await?;
+        Ok(response.json().await?)
+    }
+
+    /// Sends a request whose successful response has no body.
+    async fn execute(request: RequestBuilder) -> Result<()> {
+        Self::send(request).await?;
+        Ok(())
+    }
+
+    pub async fn user(&self, id: Id<UserMarker>) -> Result<UserProfile> {
+        self.get(&["users", &id.to_string()]).await
+    }
+
+    /// Pinned posts come first on every page, and are not part of the pagination.
+    pub async fn user_posts(
+        &self,
+        id: Id<UserMarker>,
+        page: &PageRequest<PostMarker>,
+    ) -> Result<Page<PartialPost, PostMarker>> {
+        let items: Vec<PartialPost> = self
+            .get_page(&["users", &id.to_string(), "posts"], page)
+            .await?;
+
+        let ids: Vec<_> = items
+            .iter()
+            .filter(|post| !post.pinned)
+            .map(|post| post.id)
+            .collect();
+        let next_cursor = next_cursor(page.limit(), &ids);
+
+        Ok(Page { items, next_cursor })
+    }
+
+    pub async fn public_timeline(
+        &self,
+        page: &PageRequest<PostMarker>,
+    ) -> Result<Page<Post, PostMarker>> {
+        let items = self.get_page(&["timeline", "public"], page).await?;
+        Ok(Page::new(items, page.limit(), |post: &Post| post.id))
+    }
+
+    pub async fn create_post(&self, content: &PostContent) -> Result<PartialPost> {
+        self.post(&["posts"], &json!({ "content": content })).await
+    }
+
+    /// Deletes a post of the authenticated user.
+    pub async fn delete_post(&self, id: Id<PostMarker>) -> Result<()> {
+        Self::execute(self.request(Method::DELETE, &["posts", &id.to_string()])).await
+    }
+
+    /// The view count of a post of the authenticated user.
+    pub async fn post_stats(&self, id: Id<PostMarker>) -> Result<PostStats> {
+        self.get(&["posts", &id.to_string(), "stats"]).await
+    }
+
+    pub async fn follow(&self, id: Id<UserMarker>) -> Result<()> {
+        Self::execute(self.request(Method::POST, &["users", &id.to_string(), "follow"])).await
+    }
+
+    pub async fn unfollow(&self, id: Id<UserMarker>) -> Result<()> {
+        Self::execute(self.request(Method::DELETE, &["users", &id.to_string(), "follow"])).await
+    }
+
+    /// Shown on the own profile, see [`ProfileWebsite`].
+    pub async fn set_profile_website(&self, url: ProfileWebsite) -> Result<()> {
+        Self::execute(
+            self.request(Method::PUT, &["profile", "website"])
+                .json(&SetProfileWebsite { url }),
+        )
+        .await
+    }
+
+    pub async fn delete_profile_website(&self) -> Result<()> {
+        Self::execute(self.request(Method::DELETE, &["profile", "website"])).await
+    }
+
+    /// The announcements that have not expired, newest first.
+    /// Dismissed ones are only included if `with_dismissed` is set.
+    pub async fn announcements(&self, with_dismissed: bool) -> Result<Vec<UserAnnouncement>> {
+        let response = Self::send(
+            self.request(Method::GET, &["announcements"])
+                .query(&[("with_dismissed", with_dismissed)]),
+        )
+        .await?;
+        Ok(response.json().await?)
+    }
+
+    pub async fn read_announcement(&self, id: Id<AnnouncementMarker>) -> Result<()> {
+        Self::execute(self.request(Method::POST, &["announcements", &id.to_string(), "read"])).await
+    }
+
+    pub async fn dismiss_announcement(&self, id: Id<AnnouncementMarker>) -> Result<()> {
+        Self::execute(self.request(Method::POST, &["announcements", &id.to_string(), "dismiss"]))
+            .await
+    }
+
+    pub async fn webhooks(&self) -> Result<Vec<Webhook>> {
+        self.get(&["webhooks"]).await
+    }
+
+    /// The response has the secret requests are signed with, which is not shown again.
+    pub async fn create_webhook(&self, webhook: &CreateWebhook) -> Result<CreatedWebhook> {
+        self.post(&["webhooks"], webhook).await
+    }
+
+    pub async fn delete_webhook(&self, id: Id<WebhookMarker>) -> Result<()> {
+        Self::execute(self.request(Method::DELETE, &["webhooks", &id.to_string()])).await
+    }
+
+    /// Enables a webhook that was disabled after too many failed requests.
+    pub async fn enable_webhook(&self, id: Id<WebhookMarker>) -> Result<()> {
+        Self::execute(self.request(Method::POST, &["webhooks", &id.to_string(), "enable"])).await
+    }
+
+    /// The latest version of each published policy.
+    pub async fn policies(&self) -> Result<Vec<Policy>> {
+        self.get(&["policies"]).await
+    }
+
+    /// The policies the authenticated user has to accept before they can change anything.
+    pub async fn pending_policies(&self) -> Result<Vec<Policy>> {
+        self.get(&["policies", "pending"]).await
+    }
+
+    /// Only the latest version can be accepted.
+    pub async fn accept_policy(&self, kind: PolicyKind, version: u64) -> Result<()> {
+        Self::execute(
+            self.request(Method::POST, &["policies", kind.as_str(), "accept"])
+                .json(&AcceptPolicy { version }),
+        )
+        .await
+    }
+
+    /// The reported posts are inlined. Requires the moderator role.
+    pub async fn open_reports(&self) -> Result<Vec<QueuedReport>> {
+        self.get(&["moderation", "reports"]).await
+    }
+
+    /// Requires the moderator role.
+    pub async fn report(&self, id: Id<ReportMarker>) -> Result<Report> {
+        self.get(&["moderation", "reports", &id.to_string()]).await
+    }
+
+    /// Unassigns the report if `assignee` is `None`. Requires the moderator role.
+    pub async fn assign_report(
+        &self,
+        id: Id<ReportMarker>,
+        assignee: Option<Id<UserMarker>>,
+    ) -> Result<()> {
+        Self::execute(
+            self.request(
+                Method::POST,
+                &["moderation", "reports", &id.to_string(), "assign"],
+            )
+            .json(&json!({ "assignee": assignee })),
+        )
+        .await
+    }
+
+    /// Assigns the report to the authenticated moderator, unless another moderator claimed it.
+    /// Requires the moderator role.
+    pub async fn claim_report(&self, id: Id<ReportMarker>) -> Result<()> {
+        Self::execute(self.request(
+            Method::POST,
+            &["moderation", "reports", &id.to_string(), "claim"],
+        ))
+        .await
+    }
+
+    /// Requires the moderator role.
+    pub async fn report_notes(&self, id: Id<ReportMarker>) -> Result<Vec<ReportNote>> {
+        self.get(&["moderation", "reports", &id.to_string(), "notes"])
+            .await
+    }
+
+    /// Requires the moderator role.
+    pub async fn add_report_note(
+        &self,
+        id: Id<ReportMarker>,
+        content: &ReportNoteContent,
+    ) -> Result<ReportNote> {
+        self.post(
+            &["moderation", "reports", &id.to_string(), "notes"],
+            &json!({ "content": content }),
+        )
+        .await
+    }
+
+    /// Resolves the report and takes the action against its target.
+    /// Requires the moderator role.
+    pub async fn resolve_report(&self, id: Id<ReportMarker>, action: ReportAction) -> Result<()> {
+        Self::execute(
+            self.request(
+                Method::POST,
+                &["moderation", "reports", &id.to_string(), "resolve"],
+            )
+            .json(&json!({ "action": action })),
+        )
+        .await
+    }
+
+    /// Also returns deleted posts. Requires the moderator role.
+    pub async fn moderated_post(&self, id: Id<PostMarker>) -> Result<ModeratedPost> {
+        self.get(&["moderation", "posts", &id.to_string()]).await
+    }
+
+    /// Deletes any post softly. Requires the moderator role.
+    pub async fn moderate_post(&self, id: Id<PostMarker>) -> Result<()> {
+        Self::execute(self.request(Method::DELETE, &["moderation", "posts", &id.to_string()])).await
+    }
+
+    /// Requires the moderator role.
+    pub async fn restore_post(&self, id: Id<PostMarker>) -> Result<()> {
+        Self::execute(self.request(
+            Method::POST,
+            &["moderation", "posts", &id.to_string(), "restore"],
+        ))
+        .await
+    }
+
+    /// Also returns suspended and shadowbanned users. Requires the moderator role.
+    pub async fn moderated_user(&self, id: Id<UserMarker>) -> Result<ModeratedUser> {
+        self.get(&["moderation", "users", &id.to_string()]).await
+    }
+
+    /// Hides the posts of the user from everyone but them. Requires the moderator role.
+    pub async fn shadowban_user(&self, id: Id<UserMarker>) -> Result<()> {
+        Self::execute(self.request(
+            Method::PUT,
+            &["moderation", "users", &id.to_string(), "shadowban"],
+        ))
+        .await
+    }
+
+    /// Requires the moderator role.
+    pub async fn unshadowban_user(&self, id: Id<UserMarker>) -> Result<()> {
+        Self::execute(self.request(
+            Method::DELETE,
+            &["moderation", "users", &id.to_string(), "shadowban"],
+        ))
+        .await
+    }
+
+    /// Requires the admin role.
+    pub async fn create_user(&self, account: &CreateUserAccount) -> Result<UserAccount> {
+        self.post(&["admin", "users"], account).await
+    }
+
+    /// Requires the admin role.
+    pub async fn user_account(&self, id: Id<UserMarker>) -> Result<UserAccount> {
+        self.get(&["admin", "users", &id.to_string()]).await
+    }
+
+    /// Requires the admin role.
+    pub async fn set_user_role(&self, id: Id<UserMarker>, role: UserRole) -> Result<()> {
+        Self::execute(
+            self.request(Method::PUT, &["admin", "users", &id.to_string(), "role"])
+                .json(&json!({ "role": role })),
+        )
+        .await
+    }
+
+    /// Signs the user out of all sessions. Requires the admin role.
+    pub async fn revoke_user_tokens(&self, id: Id<UserMarker>) -> Result<TokenPurge> {
+        let response = Self::send(self.request(
+            Method::DELETE,
+            &["admin", "users", &id.to_string(), "tokens"],
+        ))
+        .await?;
+        Ok(response.json().await?)
+    }
+
+    /// Returns a token to act as the user for a short while, who is notified afterwards.
+    /// Requires the admin role.
+    pub async fn impersonate_user(&self, id: Id<UserMarker>) -> Result<Impersonation> {
+        let response = Self::send(self.request(
+            Method::POST,
+            &["admin", "users", &id.to_string(), "impersonation"],
+        ))
+        .await?;
+        Ok(response.json().await?)
+    }
+
+    /// Deletes the impersonation tokens of the user. Requires the admin role.
+    pub async fn end_impersonation(&self, id: Id<UserMarker>) -> Result<TokenPurge> {
+        let response = Self::send(self.request(
+            Method::DELETE,
+            &["admin", "users", &id.to_string(), "impersonation"],
+        ))
+        .await?;
+        Ok(response.json().await?)
+    }
+
+    /// Grants the verified badge, if `require_link_back` only if the website on the profile of the
+    /// user links back to it. Requires the admin role.
+    pub async fn grant_verification(
+        &self,
+        id: Id<UserMarker>,
+        require_link_back: bool,
+    ) -> Result<()> {
+        Self::execute(
+            self.request(
+                Method::PUT,
+                &["admin", "users", &id.to_string(), "verification"],
+            )
+            .json(&GrantVerification { require_link_back }),
+        )
+        .await
+    }
+
+    /// Requires the admin role.
+    pub async fn revoke_verification(&self, id: Id<UserMarker>) -> Result<()> {
+        Self::execute(self.request(
+            Method::DELETE,
+            &["admin", "users", &id.to_string(), "verification"],
+        ))
+        .await
+    }
+
+    /// Requires the admin role.
+    pub async fn purge_expired_tokens(&self) -> Result<TokenPurge> {
+        let response =
+            Self::send(self.request(Method::POST, &["admin", "tokens", "purge"])).await?;
+        Ok(response.json().await?)
+    }
+
+    /// Newest first. Requires the admin role.
+    pub async fn moderation_log(
+        &self,
+        page: &PageRequest<ModerationLogMarker>,
+    ) -> Result<Page<ModerationLogEntry, ModerationLogMarker>> {
+        let items = self.get_page(&["admin", "moderation-log"], page).await?;
+        Ok(Page::new(
+            items,
+            page.limit(),
+            |entry: &ModerationLogEntry| entry.id,
+        ))
+    }
+
+    /// Requires the admin role.
+    pub async fn instance_overview(&self) -> Result<InstanceOverview> {
+        self.get(&["admin", "instance"]).await
+    }
+
+    /// Requires the admin role.
+    pub async fn activity_stats(&self) -> Result<ActivityStats> {
+        self.get(&["admin", "stats"]).await
+    }
+
+    /// Requires the admin role.
+    pub async fn spam_settings(&self) -> Result<SpamSettings> {
+        self.get(&["admin", "spam"]).await
+    }
+
+    /// Applies until the server restarts. Requires the admin role.
+    pub async fn set_spam_settings(&self, settings: &SpamSettings) -> Result<SpamSettings> {
+        let response =
+            Self::send(self.request(Method::PUT, &["admin", "spam"]).json(settings)).await?;
+        Ok(response.json().await?)
+    }
+
+    /// Requires the admin role.
+    pub async fn feature_flags(&self) -> Result<Vec<FeatureFlagState>> {
+        self.get(&["admin", "features"]).await
+    }
+
+    /// Overrides the configuration on all servers. Requires the admin role.
+    pub async fn set_feature_flag(
+        &self,
+        flag: FeatureFlag,
+        enabled: bool,
+    ) -> Result<FeatureFlagState> {
+        let response = Self::send(
+            self.request(Method::PUT, &["admin", "features", flag.as_str()])
+                .json(&SetFeatureFlag { enabled }),
+        )
+        .await?;
+        Ok(response.json().await?)
+    }
+
+    /// Lets the flag follow the configuration again. Requires the admin role.
+    pub async fn reset_feature_flag(&self, flag: FeatureFlag) -> Result<()> {
+        Self::execute(self.request(Method::DELETE, &["admin", "features", flag.as_str()])).await
+    }
+
+    /// Including expired announcements, newest first. Requires the admin role.
+    pub async fn all_announcements(
+        &self,
+        page: &PageRequest<AnnouncementMarker>,
+    ) -> Result<Page<Announcement, AnnouncementMarker>> {
+        let items = self.get_page(&["admin", "announcements"], page).await?;
+        Ok(Page::new(
+            items,
+            page.limit(),
+            |announcement: &Announcement| announcement.id,
+        ))
+    }
+
+    /// Requires the admin role.
+    pub async fn create_announcement(
+        &self,
+        announcement: &CreateAnnouncement,
+    ) -> Result<Announcement> {
+        self.post(&["admin", "announcements"], announcement).await
+    }
+
+    /// Requires the admin role.
+    pub async fn delete_announcement(&self, id: Id<AnnouncementMarker>) -> Result<()> {
+        Self::execute(self.request(Method::DELETE, &["admin", "announcements", &id.to_string()]))
+            .await
+    }
+
+    /// Jobs that failed permanently or too often, newest first. Requires the admin role.
+    pub async fn dead_jobs(
+        &self,
+        page: &PageRequest<JobMarker>,
+    ) -> Result<Page<DeadJob, JobMarker>> {
+        let items = self.get_page(&["admin", "jobs", "dead"], page).await?;
+        Ok(Page::new(items, page.limit(), |job: &DeadJob| job.id))
+    }
+
+    /// Queues a dead-lettered job again. Requires the admin role.
+    pub async fn retry_dead_job(&self, id: Id<JobMarker>) -> Result<()> {
+        Self::execute(self.request(
+            Method::POST,
+            &["admin", "jobs", "dead", &id.to_string(), "retry"],
+        ))
+        .await
+    }
+
+    /// Requires the admin role.
+    pub async fn delete_dead_job(&self, id: Id<JobMarker>) -> Result<()> {
+        Self::execute(self.request(Method::DELETE, &["admin", "jobs", "dead", &id.to_string()]))
+            .await
+    }
+
+    /// Publishes the next version of the policy. Requires the admin role.
+    pub async fn publish_policy(&self, kind: PolicyKind, content: PolicyContent) -> Result<Policy> {
+        self.post(
+            &["admin", "policies", kind.as_str()],
+            &PublishPolicy { content },
+        )
+        .await
+    }
+}
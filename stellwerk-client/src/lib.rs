@@ -0,0 +1,3 @@
+//! A client of the stellwerk REST API, using the models of `stellwerk-common`.
+
+pub mod client;
@@ -0,0 +1,134 @@
+//! Benchmarks of code that runs on most requests.
+//!
+//! Snowflake generation compares the [`AtomicSnowflakeGenerator`] the server uses against a
+//! [`SnowflakeGenerator`] behind a mutex, both uncontended and shared between threads.
+
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+use std::{
+    hint::black_box,
+    sync::{Arc, Barrier, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use stellwerk_common::{
+    model::{
+        StellwerkAtomicSnowflakeGenerator, StellwerkSnowflakeGenerator, auth::AuthToken,
+        post::PostContent,
+    },
+    snowflake::{ProcessId, WorkerId},
+    text,
+};
+
+const THREADS: [usize; 3] = [1, 4, 16];
+
+const POST_CONTENT: &str = "Signal failure at @dispatcher's junction, trains to the **depot** are \
+    delayed by *about* 20 minutes.\nDetails: https://example.com/status #delay #freight\n\n\
+    Platform `7` is closed until further notice, please ask @conductor or @station_staff.";
+
+/// Runs `generate` `iterations` times spread over `threads` threads, and returns how long it took.
+fn generate_concurrently(
+    threads: usize,
+    iterations: u64,
+    generate: impl Fn() + Send + Sync + 'static,
+) -> Duration {
+    let generate = Arc::new(generate);
+    let barrier = Arc::new(Barrier::new(threads + 1));
+    let per_thread = iterations.div_ceil(threads as u64);
+
+    let handles: Vec<_> = (0..threads)
+        .map(|_| {
+            let generate = Arc::clone(&generate);
+            let barrier = Arc::clone(&barrier);
+            thread::spawn(move || {
+                barrier.wait();
+                for _ in 0..per_thread {
+                    generate();
+                }
+            })
+        })
+        .collect();
+
+    barrier.wait();
+    let start = Instant::now();
+    for handle in handles {
+        handle.join().expect("Generating does not panic");
+    }
+    start.elapsed()
+}
+
+fn snowflake_generation(c: &mut Criterion) {
+    let worker_id = WorkerId::new_unchecked(1);
+    let process_id = ProcessId::new_unchecked(1);
+    let mut group = c.benchmark_group("snowflake_generation");
+
+    for threads in THREADS {
+        group.bench_with_input(
+            BenchmarkId::new("mutex", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|iterations| {
+                    let generator =
+                        Mutex::new(StellwerkSnowflakeGenerator::new(worker_id, process_id));
+                    generate_concurrently(threads, iterations, move || {
+                        black_box(generator.lock().expect("Not poisoned").generate());
+                    })
+                });
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("atomic", threads),
+            &threads,
+            |b, &threads| {
+                b.iter_custom(|iterations| {
+                    let generator = StellwerkAtomicSnowflakeGenerator::new(worker_id, process_id);
+                    generate_concurrently(threads, iterations, move || {
+                        // Only fails if the clock moves backwards.
+                        let _ = black_box(generator.generate());
+                    })
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn auth_token(c: &mut Criterion) {
+    let token = AuthToken::generate_random(1.into());
+    let token_str = token.as_token_str();
+
+    c.bench_function("auth_token/parse", |b| {
+        b.iter(|| black_box(&token_str).parse::<AuthToken>());
+    });
+
+    // Argon2 is slow on purpose, so fewer samples suffice.
+    let mut group = c.benchmark_group("auth_token");
+    group.sample_size(10);
+    group.bench_function("hash", |b| b.iter(|| black_box(&token).hash()));
+    group.finish();
+}
+
+fn post_content(c: &mut Criterion) {
+    let body = serde_json::to_string(POST_CONTENT).expect("Strings serialize infallibly");
+    let content = PostContent::new(POST_CONTENT.to_owned()).expect("The content is valid");
+
+    c.bench_function("post_content/deserialize", |b| {
+        b.iter(|| serde_json::from_str::<PostContent>(black_box(&body)));
+    });
+    c.bench_function("post_content/validate", |b| {
+        b.iter_batched(
+            || POST_CONTENT.to_owned(),
+            PostContent::new,
+            BatchSize::SmallInput,
+        );
+    });
+    c.bench_function("post_content/render_html", |b| {
+        b.iter(|| black_box(&content).render_html());
+    });
+    c.bench_function("post_content/mentions", |b| {
+        b.iter(|| text::mentions(black_box(content.get())).len());
+    });
+}
+
+criterion_group!(benches, snowflake_generation, auth_token, post_content);
+criterion_main!(benches);
@@ -0,0 +1,120 @@
+//! Things that happened, in the schema shared by the instances producing and consuming them.
+//!
+//! Events only refer to the objects they are about, which consumers load if they need them,
+//! so that they stay small and never carry stale copies.
+
+use crate::model::{
+    Id, notification::NotificationMarker, post::PostMarker, tenant::TenantId, user::UserMarker,
+};
+use serde::{Deserialize, Serialize};
+
+/// Serialized with the snake case variant name as `type`, next to the fields of the payload.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    PostCreated(PostCreated),
+    PostDeleted(PostDeleted),
+    FollowCreated(FollowCreated),
+    NotificationCreated(NotificationCreated),
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct PostCreated {
+    pub post: Id<PostMarker>,
+    pub author: Id<UserMarker>,
+    /// Missing in events of instances without tenants, whose posts all had the default one.
+    #[serde(default)]
+    pub tenant: TenantId,
+}
+
+/// The post was soft-deleted, and may still be restored by moderators.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct PostDeleted {
+    pub post: Id<PostMarker>,
+    pub author: Id<UserMarker>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct FollowCreated {
+    pub follower: Id<UserMarker>,
+    pub followed: Id<UserMarker>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct NotificationCreated {
+    /// The user who was notified.
+    pub user: Id<UserMarker>,
+    pub notification: Id<NotificationMarker>,
+}
+
+impl From<PostCreated> for Event {
+    fn from(event: PostCreated) -> Self {
+        Event::PostCreated(event)
+    }
+}
+
+impl From<PostDeleted> for Event {
+    fn from(event: PostDeleted) -> Self {
+        Event::PostDeleted(event)
+    }
+}
+
+impl From<FollowCreated> for Event {
+    fn from(event: FollowCreated) -> Self {
+        Event::FollowCreated(event)
+    }
+}
+
+impl From<NotificationCreated> for Event {
+    fn from(event: NotificationCreated) -> Self {
+        Event::NotificationCreated(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        event::{Event, FollowCreated, PostCreated},
+        model::tenant::TenantId,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn event_serialization() {
+        let event = Event::from(PostCreated {
+            post: 236_513_256_749_924_352.into(),
+            author: 1.into(),
+            tenant: TenantId::default(),
+        });
+        let expected = json!({
+            "type": "post_created",
+            "post": "236513256749924352",
+            "author": "1",
+            "tenant": "default",
+        });
+        assert_eq!(serde_json::to_value(&event).unwrap(), expected);
+        assert_eq!(serde_json::from_value::<Event>(expected).unwrap(), event);
+        assert_eq!(
+            serde_json::from_value::<Event>(json!({
+                "type": "post_created",
+                "post": "236513256749924352",
+                "author": "1",
+            }))
+            .unwrap(),
+            event
+        );
+
+        let event = Event::from(FollowCreated {
+            follower: 1.into(),
+            followed: 2.into(),
+        });
+        assert_eq!(
+            serde_json::to_value(event).unwrap(),
+            json!({ "type": "follow_created", "follower": "1", "followed": "2" })
+        );
+
+        assert!(
+            serde_json::from_value::<Event>(json!({ "type": "post_edited", "post": "1" })).is_err()
+        );
+    }
+}
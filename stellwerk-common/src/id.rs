@@ -0,0 +1,103 @@
+//! Generating IDs independently of their scheme.
+//!
+//! Stellwerk itself uses [snowflakes](crate::snowflake). Embedders with their own ID scheme can use
+//! the UUID version 7 and ULID generators behind the `uuid` and `ulid` features, or implement [`IdGenerator`].
+
+use crate::{
+    model::StellwerkAtomicSnowflakeGenerator,
+    snowflake::{AtomicSnowflakeGenerator, ClockMovedBackwardsError, Epoch, Snowflake},
+};
+
+/// The generator stellwerk uses.
+pub type DefaultIdGenerator = StellwerkAtomicSnowflakeGenerator;
+
+/// Generates unique IDs, in increasing order if the scheme allows it.
+/// Generators are shared, so generating takes `&self`.
+pub trait IdGenerator {
+    type Id;
+    type Error: std::error::Error;
+
+    fn generate_id(&self) -> Result<Self::Id, Self::Error>;
+}
+
+impl<SnowflakeEpoch: Epoch> IdGenerator for AtomicSnowflakeGenerator<SnowflakeEpoch> {
+    type Id = Snowflake<SnowflakeEpoch>;
+    type Error = ClockMovedBackwardsError;
+
+    fn generate_id(&self) -> Result<Self::Id, Self::Error> {
+        self.generate()
+    }
+}
+
+/// Generates version 7 UUIDs, which are increasing within the process.
+#[cfg(feature = "uuid")]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub struct UuidV7Generator;
+
+#[cfg(feature = "uuid")]
+impl IdGenerator for UuidV7Generator {
+    type Id = uuid::Uuid;
+    type Error = std::convert::Infallible;
+
+    fn generate_id(&self) -> Result<Self::Id, Self::Error> {
+        Ok(uuid::Uuid::now_v7())
+    }
+}
+
+/// Generates ULIDs, which are increasing for the same generator.
+#[cfg(feature = "ulid")]
+#[derive(Default)]
+pub struct UlidGenerator {
+    generator: std::sync::Mutex<ulid::Generator>,
+}
+
+#[cfg(feature = "ulid")]
+impl IdGenerator for UlidGenerator {
+    type Id = ulid::Ulid;
+    /// More ULIDs were generated within a millisecond than the random part can count.
+    type Error = ulid::MonotonicError;
+
+    fn generate_id(&self) -> Result<Self::Id, Self::Error> {
+        self.generator
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .generate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        id::{DefaultIdGenerator, IdGenerator},
+        snowflake::{ProcessId, WorkerId},
+    };
+
+    fn assert_increasing<G: IdGenerator<Id: Ord + Copy>>(generator: &G) {
+        let mut previous = generator.generate_id().unwrap();
+        for _ in 0..10_000 {
+            let id = generator.generate_id().unwrap();
+            assert!(id > previous);
+            previous = id;
+        }
+    }
+
+    #[test]
+    fn snowflake_ids() {
+        assert_increasing(&DefaultIdGenerator::new(
+            WorkerId::new_unchecked(1),
+            ProcessId::new_unchecked(2),
+        ));
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn uuid_v7_ids() {
+        assert_increasing(&super::UuidV7Generator);
+    }
+
+    #[cfg(feature = "ulid")]
+    #[test]
+    fn ulid_ids() {
+        assert_increasing(&super::UlidGenerator::default());
+    }
+}
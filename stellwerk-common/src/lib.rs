@@ -1,3 +1,7 @@
+pub mod event;
+pub mod id;
 pub mod model;
+pub mod signature;
 pub mod snowflake;
+pub mod text;
 pub mod util;
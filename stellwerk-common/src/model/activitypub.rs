@@ -0,0 +1,516 @@
+//! [ActivityPub](https://www.w3.org/TR/activitypub/) representations of local objects.
+//!
+//! Ids are the public URLs of the objects, derived from their snowflakes,
+//! so they stay stable even if e.g. a user's handle changes.
+//!
+//! Types that are also received from other servers only deserialize the properties we use,
+//! and accept the common variations in how they are represented.
+
+use crate::{
+    model::{
+        Id,
+        oembed::post_url,
+        post::Post,
+        user::{User, UserMarker},
+    },
+    text::absolute_links,
+    util::rfc3339,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use time::UtcDateTime;
+
+pub const ACTIVITY_STREAMS_CONTEXT: &str = "https://www.w3.org/ns/activitystreams";
+/// Defines the `publicKey` property of actors.
+pub const SECURITY_CONTEXT: &str = "https://w3id.org/security/v1";
+/// The special collection addressing everyone.
+pub const PUBLIC_COLLECTION: &str = "https://www.w3.org/ns/activitystreams#Public";
+
+/// The ActivityPub id of a user.
+#[must_use]
+pub fn actor_id(public_url: &str, id: Id<UserMarker>) -> String {
+    format!("{public_url}/users/{id}")
+}
+
+/// The ActivityPub id of the instance actor, which signs requests made on behalf of the
+/// instance as a whole, like fetching the keys of other actors.
+#[must_use]
+pub fn instance_actor_id(public_url: &str) -> String {
+    format!("{public_url}/actor")
+}
+
+/// The id of the key of the actor with the id `actor_id`, used in HTTP signatures.
+#[must_use]
+pub fn key_id(actor_id: &str) -> String {
+    format!("{actor_id}#main-key")
+}
+
+/// The inbox shared by all local actors.
+#[must_use]
+pub fn shared_inbox(public_url: &str) -> String {
+    format!("{public_url}/inbox")
+}
+
+/// A local actor, i.e. a user as an ActivityPub `Person` or the instance actor.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Actor {
+    #[serde(rename = "@context")]
+    pub context: Vec<String>,
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub preferred_username: String,
+    pub inbox: String,
+    pub outbox: String,
+    /// The human-readable profile page.
+    pub url: String,
+    pub endpoints: Endpoints,
+    pub public_key: PublicKey,
+}
+
+impl Actor {
+    /// `public_url` must not have a trailing slash.
+    #[must_use]
+    pub fn for_user(user: &User, public_url: &str, public_key_pem: String) -> Self {
+        Self::new(
+            actor_id(public_url, user.id),
+            "Person",
+            user.handle.get().to_owned(),
+            format!("{public_url}/@{}", user.handle.get()),
+            public_url,
+            public_key_pem,
+        )
+    }
+
+    /// `public_url` must not have a trailing slash.
+    #[must_use]
+    pub fn for_instance(public_url: &str, public_key_pem: String) -> Self {
+        let host = public_url
+            .split_once("://")
+            .map_or(public_url, |(_, host)| host);
+
+        Self::new(
+            instance_actor_id(public_url),
+            "Application",
+            host.to_owned(),
+            public_url.to_owned(),
+            public_url,
+            public_key_pem,
+        )
+    }
+
+    fn new(
+        id: String,
+        kind: &str,
+        preferred_username: String,
+        url: String,
+        public_url: &str,
+        public_key_pem: String,
+    ) -> Self {
+        Self {
+            context: vec![
+                ACTIVITY_STREAMS_CONTEXT.to_owned(),
+                SECURITY_CONTEXT.to_owned(),
+            ],
+            inbox: format!("{id}/inbox"),
+            outbox: format!("{id}/outbox"),
+            endpoints: Endpoints {
+                shared_inbox: Some(shared_inbox(public_url)),
+            },
+            public_key: PublicKey {
+                id: key_id(&id),
+                owner: id.clone(),
+                public_key_pem,
+            },
+            id,
+            kind: kind.to_owned(),
+            preferred_username,
+            url,
+        }
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Endpoints {
+    /// An inbox receiving activities for all actors of the server, so that activities
+    /// addressed to several of them only need to be delivered once.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shared_inbox: Option<String>,
+}
+
+/// An actor of another server.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteActor {
+    pub id: String,
+    pub preferred_username: String,
+    pub inbox: String,
+    #[serde(default)]
+    pub endpoints: Endpoints,
+    pub public_key: PublicKey,
+}
+
+impl RemoteActor {
+    /// The handle of the local user representing the actor, e.g. `alice@remote.example`.
+    #[must_use]
+    pub fn handle(&self) -> Option<String> {
+        let host = self
+            .id
+            .split_once("://")
+            .and_then(|(_, rest)| rest.split('/').next())
+            .filter(|host| !host.is_empty())?;
+
+        Some(format!("{}@{host}", self.preferred_username))
+    }
+
+    /// Where to deliver activities for the actor, preferring the shared inbox.
+    #[must_use]
+    pub fn delivery_inbox(&self) -> &str {
+        self.endpoints
+            .shared_inbox
+            .as_deref()
+            .unwrap_or(&self.inbox)
+    }
+}
+
+/// The key an actor signs its requests with.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicKey {
+    pub id: String,
+    /// The id of the actor.
+    pub owner: String,
+    pub public_key_pem: String,
+}
+
+/// A document retrieved by dereferencing a key id.
+/// Usually that is the owning actor, but some implementations serve the key on its own.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize)]
+#[serde(untagged)]
+pub enum KeyDocument {
+    #[serde(rename_all = "camelCase")]
+    Actor {
+        public_key: PublicKey,
+    },
+    Key(PublicKey),
+}
+
+impl KeyDocument {
+    #[must_use]
+    pub fn into_public_key(self) -> PublicKey {
+        match self {
+            KeyDocument::Actor { public_key } | KeyDocument::Key(public_key) => public_key,
+        }
+    }
+}
+
+/// A post as an ActivityPub `Note`.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Note {
+    /// Omitted when the note is embedded in an activity.
+    #[serde(
+        rename = "@context",
+        skip_deserializing,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub context: Option<String>,
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub attributed_to: String,
+    /// Sanitized HTML, with absolute links.
+    pub content: String,
+    #[serde(with = "rfc3339")]
+    pub published: UtcDateTime,
+    #[serde(default, deserialize_with = "one_or_many")]
+    pub to: Vec<String>,
+    /// Other servers use differing representations for this, so it is not deserialized.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl Note {
+    /// `public_url` must not have a trailing slash.
+    #[must_use]
+    pub fn for_post(post: &Post, public_url: &str) -> Self {
+        let id = post_url(public_url, post.id);
+
+        Self {
+            context: Some(ACTIVITY_STREAMS_CONTEXT.to_owned()),
+            url: Some(id.clone()),
+            id,
+            kind: "Note".to_owned(),
+            attributed_to: actor_id(public_url, post.author.id),
+            content: absolute_links(&post.content_html, public_url),
+            published: post.id.snowflake().timestamp().into(),
+            to: vec![PUBLIC_COLLECTION.to_owned()],
+        }
+    }
+}
+
+/// An activity, either received in an inbox or delivered to remote followers.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Activity {
+    #[serde(
+        rename = "@context",
+        skip_deserializing,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub context: Option<String>,
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub actor: String,
+    pub object: ActivityObject,
+    #[serde(
+        default,
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub to: Vec<String>,
+}
+
+impl Activity {
+    /// Announces the creation of a local post.
+    #[must_use]
+    pub fn create(mut note: Note) -> Self {
+        note.context = None;
+
+        Self {
+            context: Some(ACTIVITY_STREAMS_CONTEXT.to_owned()),
+            id: format!("{}/activity", note.id),
+            kind: "Create".to_owned(),
+            actor: note.attributed_to.clone(),
+            to: note.to.clone(),
+            object: ActivityObject::Note(Box::new(note)),
+        }
+    }
+
+    /// Accepts a `Follow` of a local user by a remote actor.
+    /// `follower` is the local user representing the remote actor.
+    #[must_use]
+    pub fn accept(mut follow: Activity, follower: Id<UserMarker>) -> Self {
+        let actor = follow.object.id().to_owned();
+        follow.context = None;
+
+        Self {
+            context: Some(ACTIVITY_STREAMS_CONTEXT.to_owned()),
+            id: format!("{actor}#accepts/{follower}"),
+            kind: "Accept".to_owned(),
+            to: vec![follow.actor.clone()],
+            actor,
+            object: ActivityObject::Activity(Box::new(follow)),
+        }
+    }
+}
+
+/// The `object` of an [`Activity`], which may be embedded or referenced by its id.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ActivityObject {
+    Id(String),
+    Note(Box<Note>),
+    Activity(Box<Activity>),
+}
+
+impl ActivityObject {
+    #[must_use]
+    pub fn id(&self) -> &str {
+        match self {
+            ActivityObject::Id(id) => id,
+            ActivityObject::Note(note) => &note.id,
+            ActivityObject::Activity(activity) => &activity.id,
+        }
+    }
+}
+
+/// Audience properties may be a single value or an array.
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{
+        Id, StellwerkSnowflake,
+        activitypub::{Activity, ActivityObject, Actor, Note, PUBLIC_COLLECTION, RemoteActor},
+        post::{Post, PostContent},
+        user::{User, UserHandle},
+    };
+    use time::macros::utc_datetime;
+
+    fn alice() -> User {
+        User {
+            id: Id::from(1),
+            handle: UserHandle::new("alice".to_owned()).unwrap(),
+            verified: false,
+        }
+    }
+
+    #[test]
+    fn user_actor() {
+        let actor = Actor::for_user(&alice(), "https://stellwerk.example", "pem".to_owned());
+
+        assert_eq!(actor.id, "https://stellwerk.example/users/1");
+        assert_eq!(actor.kind, "Person");
+        assert_eq!(actor.preferred_username, "alice");
+        assert_eq!(actor.inbox, "https://stellwerk.example/users/1/inbox");
+        assert_eq!(actor.outbox, "https://stellwerk.example/users/1/outbox");
+        assert_eq!(actor.url, "https://stellwerk.example/@alice");
+        assert_eq!(
+            actor.endpoints.shared_inbox.as_deref(),
+            Some("https://stellwerk.example/inbox")
+        );
+        assert_eq!(
+            actor.public_key.id,
+            "https://stellwerk.example/users/1#main-key"
+        );
+        assert_eq!(actor.public_key.owner, actor.id);
+    }
+
+    #[test]
+    fn instance_actor() {
+        let actor = Actor::for_instance("https://stellwerk.example", "pem".to_owned());
+
+        assert_eq!(actor.id, "https://stellwerk.example/actor");
+        assert_eq!(actor.kind, "Application");
+        assert_eq!(actor.preferred_username, "stellwerk.example");
+        assert_eq!(
+            actor.public_key.id,
+            "https://stellwerk.example/actor#main-key"
+        );
+    }
+
+    #[test]
+    fn post_note() {
+        let content = PostContent::new("hi #tag".to_owned()).unwrap();
+        // One second after the epoch.
+        let id = StellwerkSnowflake::new(1000 << 22).into();
+        let post = Post {
+            id,
+            author: alice(),
+            content_html: content.render_html(),
+            content,
+            pinned: false,
+            filtered: Vec::new(),
+        };
+
+        let note = Note::for_post(&post, "https://stellwerk.example");
+
+        assert_eq!(note.id, format!("https://stellwerk.example/posts/{id}"));
+        assert_eq!(note.attributed_to, "https://stellwerk.example/users/1");
+        assert_eq!(
+            note.content,
+            "<p>hi <a href=\"https://stellwerk.example/tags/tag\" class=\"hashtag\">#tag</a></p>"
+        );
+        assert_eq!(note.published, utc_datetime!(2025-01-01 00:00:01));
+        assert_eq!(note.to, [PUBLIC_COLLECTION]);
+    }
+
+    #[test]
+    fn create_activity() {
+        let content = PostContent::new("hi".to_owned()).unwrap();
+        let post = Post {
+            id: Id::from(2),
+            author: alice(),
+            content_html: content.render_html(),
+            content,
+            pinned: false,
+            filtered: Vec::new(),
+        };
+
+        let activity = Activity::create(Note::for_post(&post, "https://stellwerk.example"));
+
+        assert_eq!(activity.id, "https://stellwerk.example/posts/2/activity");
+        assert_eq!(activity.actor, "https://stellwerk.example/users/1");
+        assert_eq!(activity.to, [PUBLIC_COLLECTION]);
+        let ActivityObject::Note(note) = &activity.object else {
+            panic!("The object is not a note: {:?}", activity.object);
+        };
+        assert_eq!(note.context, None);
+    }
+
+    #[test]
+    fn remote_activities() {
+        let follow: Activity = serde_json::from_str(
+            r#"{
+                "@context": "https://www.w3.org/ns/activitystreams",
+                "id": "https://remote.example/follows/1",
+                "type": "Follow",
+                "actor": "https://remote.example/users/bob",
+                "object": "https://stellwerk.example/users/1"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            follow.object,
+            ActivityObject::Id("https://stellwerk.example/users/1".to_owned())
+        );
+
+        let accept = Activity::accept(follow.clone(), Id::from(3));
+        assert_eq!(accept.id, "https://stellwerk.example/users/1#accepts/3");
+        assert_eq!(accept.actor, "https://stellwerk.example/users/1");
+        assert_eq!(accept.to, ["https://remote.example/users/bob"]);
+        assert_eq!(accept.object.id(), follow.id);
+
+        let create: Activity = serde_json::from_str(
+            r#"{
+                "id": "https://remote.example/notes/1/activity",
+                "type": "Create",
+                "actor": "https://remote.example/users/bob",
+                "object": {
+                    "id": "https://remote.example/notes/1",
+                    "type": "Note",
+                    "attributedTo": "https://remote.example/users/bob",
+                    "content": "<p>hi</p>",
+                    "published": "2025-01-01T00:00:01Z",
+                    "to": "https://www.w3.org/ns/activitystreams#Public",
+                    "url": {"type": "Link", "href": "https://remote.example/@bob/1"}
+                }
+            }"#,
+        )
+        .unwrap();
+        let ActivityObject::Note(note) = create.object else {
+            panic!("The object is not a note: {:?}", create.object);
+        };
+        assert_eq!(note.to, [PUBLIC_COLLECTION]);
+        assert_eq!(note.url, None);
+    }
+
+    #[test]
+    fn remote_actor_handle() {
+        let actor: RemoteActor = serde_json::from_str(
+            r#"{
+                "id": "https://remote.example/users/bob",
+                "type": "Person",
+                "preferredUsername": "bob",
+                "inbox": "https://remote.example/users/bob/inbox",
+                "publicKey": {
+                    "id": "https://remote.example/users/bob#main-key",
+                    "owner": "https://remote.example/users/bob",
+                    "publicKeyPem": "pem"
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(actor.handle().as_deref(), Some("bob@remote.example"));
+        assert_eq!(
+            actor.delivery_inbox(),
+            "https://remote.example/users/bob/inbox"
+        );
+    }
+}
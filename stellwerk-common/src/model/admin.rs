@@ -0,0 +1,185 @@
+//! Models of the admin API, which are only visible to administrators.
+
+use crate::{
+    model::{
+        instance::InstanceInfo,
+        user::{InvalidUserHandleError, User, UserHandle, UserRole},
+    },
+    snowflake::ClockRegressions,
+    util::rfc3339,
+};
+use serde::{Deserialize, Serialize};
+use time::UtcDateTime;
+
+/// A [`User`] with the information administrators manage.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct UserAccount {
+    #[serde(flatten)]
+    pub user: User,
+    pub role: UserRole,
+    /// Whether the user is mirrored from another server, via ActivityPub or the AT Protocol.
+    pub remote: bool,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct CreateUserAccount {
+    pub handle: UserHandle,
+    #[serde(default)]
+    pub role: UserRole,
+}
+
+impl CreateUserAccount {
+    /// An account with the [`UserRole::User`] role.
+    pub fn new(handle: impl Into<String>) -> Result<Self, InvalidUserHandleError> {
+        Ok(Self {
+            handle: UserHandle::new(handle.into())?,
+            role: UserRole::User,
+        })
+    }
+
+    #[must_use]
+    pub fn with_role(self, role: UserRole) -> Self {
+        Self { role, ..self }
+    }
+}
+
+/// The configured settings of the instance, with statistics.
+/// Settings are changed in the configuration, not through the admin API.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct InstanceOverview {
+    pub settings: InstanceInfo,
+    pub stats: InstanceStats,
+    /// Of the ID generator of the answering process since it started.
+    pub clock_regressions: ClockRegressions,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct InstanceStats {
+    /// Including remote users.
+    pub user_count: u64,
+    pub remote_user_count: u64,
+    pub post_count: u64,
+    /// Posted in the last 24 hours.
+    pub recent_post_count: u64,
+    pub open_report_count: u64,
+}
+
+/// The activity on the instance over the last day and week, for operators.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct ActivityStats {
+    /// The last 24 hours.
+    pub day: ActivityWindow,
+    /// The last 7 days.
+    pub week: ActivityWindow,
+    /// Auth tokens that have not expired yet.
+    pub active_token_count: u64,
+    /// Expired auth tokens kept in the archive.
+    pub archived_token_count: u64,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct ActivityWindow {
+    /// Local users created in the window.
+    pub signup_count: u64,
+    /// Users who posted or signed in during the window.
+    pub active_user_count: u64,
+    /// Including posts deleted since.
+    pub post_count: u64,
+    /// Auth tokens created in the window, including archived ones.
+    pub created_token_count: u64,
+}
+
+/// Whether the server is in read-only mode, rejecting requests that change state.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct ReadOnlyMode {
+    pub enabled: bool,
+}
+
+/// The result of deleting auth tokens.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct TokenPurge {
+    pub deleted: u64,
+}
+
+/// A short-lived auth token for an admin to act as a user, to debug problems with their account.
+/// Every request made with it is logged, and the user is notified once it expired.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct Impersonation {
+    pub user: User,
+    pub token: String,
+    #[serde(with = "rfc3339")]
+    pub expires_at: UtcDateTime,
+}
+
+/// Statistics of a database query since the server started.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct QueryStats {
+    /// The static name of the query, like `fetch_user` or `pin_post.update`.
+    pub query: String,
+    /// Including failed calls.
+    pub calls: u64,
+    pub errors: u64,
+    /// Calls repeated after a transient failure.
+    pub retries: u64,
+    /// Calls that took at least the slow query threshold.
+    pub slow_calls: u64,
+    /// Rows returned or affected by the successful calls.
+    pub rows: u64,
+    pub total_duration_us: u64,
+    pub max_duration_us: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{
+        admin::{CreateUserAccount, UserAccount},
+        user::{USER_HANDLE_MAX_LEN, User, UserHandle, UserRole},
+    };
+    use serde_json::json;
+
+    #[test]
+    fn user_account_serialization() {
+        let account = UserAccount {
+            user: User {
+                id: 1.into(),
+                handle: UserHandle::new("alice".to_owned()).unwrap(),
+                verified: false,
+            },
+            role: UserRole::Moderator,
+            remote: false,
+        };
+
+        let expected = json!({
+            "id": "1",
+            "created_at": "2025-01-01T00:00:00Z",
+            "handle": "alice",
+            "verified": false,
+            "role": "moderator",
+            "remote": false,
+        });
+        assert_eq!(serde_json::to_value(&account).unwrap(), expected);
+        assert_eq!(
+            serde_json::from_value::<UserAccount>(expected).unwrap(),
+            account
+        );
+    }
+
+    #[test]
+    fn create_user_account_role_defaults_to_user() {
+        let create: CreateUserAccount = serde_json::from_value(json!({ "handle": "bob" })).unwrap();
+
+        assert_eq!(create.handle.get(), "bob");
+        assert_eq!(create.role, UserRole::User);
+    }
+
+    #[test]
+    fn create_user_account_builder() {
+        let create = CreateUserAccount::new("carol")
+            .unwrap()
+            .with_role(UserRole::Admin);
+
+        assert_eq!(create.handle.get(), "carol");
+        assert_eq!(create.role, UserRole::Admin);
+        assert!(CreateUserAccount::new("c".repeat(USER_HANDLE_MAX_LEN + 1)).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+//! Aggregate usage counters, rolled up into buckets of an hour or a day.
+//!
+//! Only the totals of each bucket are stored, never which user did what, so the rollups
+//! describe the instance without describing anyone's behavior.
+
+use crate::util::rfc3339;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+    time::Duration,
+};
+use thiserror::Error;
+use time::UtcDateTime;
+
+/// The default of [`AnalyticsQuery::days`].
+pub const DEFAULT_ANALYTICS_DAYS: u32 = 7;
+pub const MAX_ANALYTICS_DAYS: u32 = 90;
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnalyticsMetric {
+    /// Local users who made an authenticated request, per day.
+    DailyActiveUsers,
+    /// Posts created through the API, per hour.
+    Posts,
+    /// How long timeline requests took in milliseconds, on average per hour.
+    TimelineLatency,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Error)]
+#[error("The analytics metric is invalid: {0}")]
+pub struct InvalidAnalyticsMetricError(String);
+
+/// The value of a metric in one bucket, summed over all servers.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct AnalyticsRollup {
+    pub metric: AnalyticsMetric,
+    /// The bucket lasts [`AnalyticsMetric::bucket`] from here.
+    #[serde(with = "rfc3339")]
+    pub bucket_start: UtcDateTime,
+    pub value: u64,
+    /// The number of users, posts, or timeline requests the value is based on.
+    pub samples: u64,
+}
+
+/// The query parameters of listing rollups.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct AnalyticsQuery {
+    /// All metrics if not given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metric: Option<AnalyticsMetric>,
+    /// How many days back, including today. Defaults to [`DEFAULT_ANALYTICS_DAYS`],
+    /// capped at [`MAX_ANALYTICS_DAYS`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub days: Option<u32>,
+}
+
+impl AnalyticsMetric {
+    pub const ALL: [AnalyticsMetric; 3] = [
+        AnalyticsMetric::DailyActiveUsers,
+        AnalyticsMetric::Posts,
+        AnalyticsMetric::TimelineLatency,
+    ];
+
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AnalyticsMetric::DailyActiveUsers => "daily_active_users",
+            AnalyticsMetric::Posts => "posts",
+            AnalyticsMetric::TimelineLatency => "timeline_latency",
+        }
+    }
+
+    /// How long one bucket of the metric lasts.
+    #[must_use]
+    pub fn bucket(self) -> Duration {
+        match self {
+            AnalyticsMetric::DailyActiveUsers => Duration::from_hours(24),
+            AnalyticsMetric::Posts | AnalyticsMetric::TimelineLatency => Duration::from_hours(1),
+        }
+    }
+
+    /// The start of the bucket `time` falls into. Buckets start at midnight or the full hour, UTC.
+    #[must_use]
+    pub fn bucket_start(self, time: UtcDateTime) -> UtcDateTime {
+        match self {
+            AnalyticsMetric::DailyActiveUsers => time.date().midnight().as_utc(),
+            AnalyticsMetric::Posts | AnalyticsMetric::TimelineLatency => time
+                .replace_minute(0)
+                .and_then(|time| time.replace_second(0))
+                .and_then(|time| time.replace_nanosecond(0))
+                .expect("zero is a valid minute, second and nanosecond"),
+        }
+    }
+}
+
+impl Display for AnalyticsMetric {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AnalyticsMetric {
+    type Err = InvalidAnalyticsMetricError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "daily_active_users" => Ok(AnalyticsMetric::DailyActiveUsers),
+            "posts" => Ok(AnalyticsMetric::Posts),
+            "timeline_latency" => Ok(AnalyticsMetric::TimelineLatency),
+            _ => Err(InvalidAnalyticsMetricError(s.to_owned())),
+        }
+    }
+}
+
+impl AnalyticsQuery {
+    #[must_use]
+    pub fn days(&self) -> u32 {
+        self.days
+            .unwrap_or(DEFAULT_ANALYTICS_DAYS)
+            .clamp(1, MAX_ANALYTICS_DAYS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::analytics::AnalyticsMetric;
+    use serde_json::json;
+    use time::macros::utc_datetime;
+
+    #[test]
+    fn analytics_metric_round_trip() {
+        for metric in AnalyticsMetric::ALL {
+            assert_eq!(metric.as_str().parse(), Ok(metric));
+            assert_eq!(
+                serde_json::to_value(metric).unwrap(),
+                json!(metric.as_str())
+            );
+        }
+        assert!("sessions".parse::<AnalyticsMetric>().is_err());
+    }
+
+    #[test]
+    fn bucket_start() {
+        let time = utc_datetime!(2025-12-01 13:45:12.5);
+
+        assert_eq!(
+            AnalyticsMetric::DailyActiveUsers.bucket_start(time),
+            utc_datetime!(2025-12-01 0:00)
+        );
+        assert_eq!(
+            AnalyticsMetric::Posts.bucket_start(time),
+            utc_datetime!(2025-12-01 13:00)
+        );
+    }
+}
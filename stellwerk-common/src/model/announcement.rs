@@ -0,0 +1,188 @@
+use crate::{model::Id, util::rfc3339};
+use serde::{Deserialize, Deserializer, Serialize, de::Error};
+use thiserror::Error;
+use time::UtcDateTime;
+
+pub const ANNOUNCEMENT_TITLE_MAX_LEN: usize = 200;
+pub const ANNOUNCEMENT_BODY_MAX_LEN: usize = 5000;
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
+pub struct AnnouncementMarker;
+
+/// Non-empty, trimmed.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Serialize)]
+#[serde(transparent)]
+pub struct AnnouncementTitle(String);
+
+/// Non-empty, trimmed.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Serialize)]
+#[serde(transparent)]
+pub struct AnnouncementBody(String);
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Error)]
+pub enum InvalidAnnouncementError {
+    #[error("The announcement title is empty")]
+    EmptyTitle,
+    #[error("The announcement title is longer than {ANNOUNCEMENT_TITLE_MAX_LEN} characters")]
+    TitleTooLong,
+    #[error("The announcement body is empty")]
+    EmptyBody,
+    #[error("The announcement body is longer than {ANNOUNCEMENT_BODY_MAX_LEN} characters")]
+    BodyTooLong,
+}
+
+/// A notice of the admins to all local users, like planned maintenance or changed rules.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct Announcement {
+    #[serde(flatten, with = "crate::model::id_with_created_at")]
+    pub id: Id<AnnouncementMarker>,
+    pub title: AnnouncementTitle,
+    pub body: AnnouncementBody,
+    /// If `None`, the announcement is shown until it is deleted.
+    #[serde(default, with = "rfc3339::option")]
+    pub expires_at: Option<UtcDateTime>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct CreateAnnouncement {
+    pub title: AnnouncementTitle,
+    pub body: AnnouncementBody,
+    #[serde(default, with = "rfc3339::option")]
+    pub expires_at: Option<UtcDateTime>,
+}
+
+/// An [`Announcement`] as seen by one user.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct UserAnnouncement {
+    #[serde(flatten)]
+    pub announcement: Announcement,
+    pub read: bool,
+    /// Dismissed announcements are also read.
+    pub dismissed: bool,
+}
+
+impl AnnouncementTitle {
+    /// Trims the title.
+    pub fn new(title: String) -> Result<Self, InvalidAnnouncementError> {
+        trimmed(
+            title,
+            ANNOUNCEMENT_TITLE_MAX_LEN,
+            InvalidAnnouncementError::EmptyTitle,
+            InvalidAnnouncementError::TitleTooLong,
+        )
+        .map(Self)
+    }
+
+    #[must_use]
+    pub fn get(&self) -> &str {
+        &self.0
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl AnnouncementBody {
+    /// Trims the body.
+    pub fn new(body: String) -> Result<Self, InvalidAnnouncementError> {
+        trimmed(
+            body,
+            ANNOUNCEMENT_BODY_MAX_LEN,
+            InvalidAnnouncementError::EmptyBody,
+            InvalidAnnouncementError::BodyTooLong,
+        )
+        .map(Self)
+    }
+
+    #[must_use]
+    pub fn get(&self) -> &str {
+        &self.0
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+fn trimmed(
+    text: String,
+    max_len: usize,
+    empty: InvalidAnnouncementError,
+    too_long: InvalidAnnouncementError,
+) -> Result<String, InvalidAnnouncementError> {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return Err(empty);
+    }
+    if trimmed.chars().count() > max_len {
+        return Err(too_long);
+    }
+
+    if trimmed.len() == text.len() {
+        Ok(text)
+    } else {
+        Ok(trimmed.to_owned())
+    }
+}
+
+impl<'de> Deserialize<'de> for AnnouncementTitle {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let inner = String::deserialize(deserializer)?;
+        Self::new(inner).map_err(Error::custom)
+    }
+}
+
+impl<'de> Deserialize<'de> for AnnouncementBody {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let inner = String::deserialize(deserializer)?;
+        Self::new(inner).map_err(Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::announcement::{
+        ANNOUNCEMENT_TITLE_MAX_LEN, AnnouncementBody, AnnouncementTitle, CreateAnnouncement,
+        InvalidAnnouncementError,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn announcement_validation() {
+        assert_eq!(
+            AnnouncementTitle::new(" Maintenance ".to_owned())
+                .unwrap()
+                .get(),
+            "Maintenance"
+        );
+        assert_eq!(
+            AnnouncementTitle::new("a".repeat(ANNOUNCEMENT_TITLE_MAX_LEN + 1)),
+            Err(InvalidAnnouncementError::TitleTooLong)
+        );
+        assert_eq!(
+            AnnouncementBody::new("\n ".to_owned()),
+            Err(InvalidAnnouncementError::EmptyBody)
+        );
+
+        let create: CreateAnnouncement = serde_json::from_value(json!({
+            "title": "Maintenance",
+            "body": "Down for an hour.",
+            "expires_at": "2026-01-01T12:00:00Z",
+        }))
+        .unwrap();
+        assert!(create.expires_at.is_some());
+        assert!(
+            serde_json::from_value::<CreateAnnouncement>(json!({"title": "", "body": "b"}))
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,133 @@
+//! Responses of the [AT Protocol](https://atproto.com/) XRPC methods used to mirror
+//! Bluesky accounts, reduced to the properties we use.
+
+use crate::util::rfc3339;
+use serde::{Deserialize, Serialize};
+use time::UtcDateTime;
+
+/// The collection of posts in an AT Protocol repository.
+pub const POST_COLLECTION: &str = "app.bsky.feed.post";
+
+/// The response of `app.bsky.feed.getAuthorFeed`.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct AuthorFeed {
+    pub feed: Vec<FeedViewPost>,
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+/// A post in a feed, possibly shown because of a repost.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct FeedViewPost {
+    pub post: PostView,
+    /// Set if the post is in the feed for another reason than being authored by its owner,
+    /// e.g. because they reposted it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<FeedReason>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct FeedReason {
+    #[serde(rename = "$type")]
+    pub kind: String,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostView {
+    /// An `at://` URI identifying the post.
+    pub uri: String,
+    /// The content hash of the record, which changes if the post is edited.
+    pub cid: String,
+    pub author: ProfileViewBasic,
+    pub record: PostRecord,
+}
+
+impl PostView {
+    /// The post on the Bluesky web app, for linking back to the original.
+    #[must_use]
+    pub fn web_url(&self) -> Option<String> {
+        let record_key = self
+            .uri
+            .strip_prefix("at://")?
+            .strip_prefix(&self.author.did)?
+            .strip_prefix('/')?
+            .strip_prefix(POST_COLLECTION)?
+            .strip_prefix('/')?;
+
+        Some(format!(
+            "https://bsky.app/profile/{}/post/{record_key}",
+            self.author.did
+        ))
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct ProfileViewBasic {
+    /// The stable identifier of the account.
+    pub did: String,
+    /// The current, domain-based handle, which may change.
+    pub handle: String,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostRecord {
+    /// Plain text.
+    pub text: String,
+    #[serde(with = "rfc3339")]
+    pub created_at: UtcDateTime,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::atproto::AuthorFeed;
+    use time::macros::utc_datetime;
+
+    #[test]
+    fn author_feed() {
+        let feed: AuthorFeed = serde_json::from_str(
+            r#"{
+                "feed": [
+                    {
+                        "post": {
+                            "uri": "at://did:plc:abc/app.bsky.feed.post/3k2a",
+                            "cid": "bafyreia",
+                            "author": {"did": "did:plc:abc", "handle": "alice.bsky.social"},
+                            "record": {
+                                "$type": "app.bsky.feed.post",
+                                "text": "hello from bluesky",
+                                "createdAt": "2025-11-15T10:00:00.000Z"
+                            },
+                            "indexedAt": "2025-11-15T10:00:01.000Z"
+                        }
+                    },
+                    {
+                        "post": {
+                            "uri": "at://did:plc:xyz/app.bsky.feed.post/3k2b",
+                            "cid": "bafyreib",
+                            "author": {"did": "did:plc:xyz", "handle": "bob.bsky.social"},
+                            "record": {"text": "reposted", "createdAt": "2025-11-15T09:00:00Z"}
+                        },
+                        "reason": {"$type": "app.bsky.feed.defs#reasonRepost"}
+                    }
+                ],
+                "cursor": "2025-11-15T09:00:00Z"
+            }"#,
+        )
+        .unwrap();
+
+        let post = &feed.feed[0].post;
+        assert_eq!(post.record.text, "hello from bluesky");
+        assert_eq!(post.record.created_at, utc_datetime!(2025-11-15 10:00));
+        assert_eq!(
+            post.web_url().as_deref(),
+            Some("https://bsky.app/profile/did:plc:abc/post/3k2a")
+        );
+        assert!(feed.feed[0].reason.is_none());
+        assert_eq!(
+            feed.feed[1].reason.as_ref().unwrap().kind,
+            "app.bsky.feed.defs#reasonRepost"
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    model::{Id, user::UserMarker},
+    model::{Id, tenant::TenantId, user::UserMarker},
     util::PositiveDuration,
 };
 use argon2::{Algorithm, Argon2, Params, Version};
@@ -69,9 +69,13 @@ pub struct AuthTokenHash(pub [u8; AUTH_TOKEN_HASH_LEN]);
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
 pub struct Authentication {
     pub user: Id<UserMarker>,
+    /// The tenant of the user, the only one the token is valid for.
+    pub tenant: TenantId,
     pub token_hash: AuthTokenHash,
     pub created_at: UtcDateTime,
     pub expires_after: Option<PositiveDuration>,
+    /// The admin acting as the user with this token, if it was minted for an impersonation.
+    pub impersonator: Option<Id<UserMarker>>,
 }
 
 impl AuthToken {
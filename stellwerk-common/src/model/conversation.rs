@@ -0,0 +1,219 @@
+use crate::{
+    model::{
+        Id,
+        user::{User, UserMarker},
+    },
+    util::base64_bytes,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+use thiserror::Error;
+
+pub const MESSAGE_CONTENT_MAX_LEN: usize = 5_000;
+pub const ENCRYPTED_PAYLOAD_MAX_LEN: usize = 64 * 1024;
+/// Maximum number of members of a conversation, including its creator.
+pub const CONVERSATION_MAX_MEMBERS: usize = 32;
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
+pub struct ConversationMarker;
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
+pub struct MessageMarker;
+
+/// A conversation as seen by one of its members.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct Conversation {
+    pub id: Id<ConversationMarker>,
+    /// The only member that can remove other members.
+    pub creator: Id<UserMarker>,
+    pub members: Vec<User>,
+    pub last_message: Option<Message>,
+    /// Messages by other members newer than the viewing member's read marker.
+    pub unread_count: u64,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct Message {
+    pub id: Id<MessageMarker>,
+    pub author: Id<UserMarker>,
+    #[serde(flatten)]
+    pub body: MessageBody,
+}
+
+/// Serialized as either a `content` or an `encrypted` field.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageBody {
+    Content(MessageContent),
+    /// Ciphertext only the members' clients can decrypt, see [`keys`](crate::model::keys).
+    Encrypted(EncryptedPayload),
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct CreateMessage {
+    pub conversation: Id<ConversationMarker>,
+    pub author: Id<UserMarker>,
+    pub body: MessageBody,
+}
+
+/// Message content with leading and trailing whitespace removed.
+/// It is never empty and never longer than [`MESSAGE_CONTENT_MAX_LEN`] characters.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Serialize)]
+#[serde(transparent)]
+pub struct MessageContent(String);
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Error)]
+pub enum InvalidMessageContentError {
+    #[error("The message content is empty")]
+    Empty,
+    #[error("The message content is longer than {MESSAGE_CONTENT_MAX_LEN} characters")]
+    TooLong,
+}
+
+/// Opaque bytes, never empty and never longer than [`ENCRYPTED_PAYLOAD_MAX_LEN`].
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
+pub struct EncryptedPayload(Vec<u8>);
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Error)]
+pub enum InvalidEncryptedPayloadError {
+    #[error("The encrypted payload is empty")]
+    Empty,
+    #[error("The encrypted payload is longer than {ENCRYPTED_PAYLOAD_MAX_LEN} bytes")]
+    TooLong,
+}
+
+impl CreateMessage {
+    /// A plaintext message.
+    pub fn new(
+        conversation: Id<ConversationMarker>,
+        author: Id<UserMarker>,
+        content: impl Into<String>,
+    ) -> Result<Self, InvalidMessageContentError> {
+        Ok(Self {
+            conversation,
+            author,
+            body: MessageBody::Content(MessageContent::new(content.into())?),
+        })
+    }
+
+    /// A message encrypted by the author's client.
+    pub fn encrypted(
+        conversation: Id<ConversationMarker>,
+        author: Id<UserMarker>,
+        payload: Vec<u8>,
+    ) -> Result<Self, InvalidEncryptedPayloadError> {
+        Ok(Self {
+            conversation,
+            author,
+            body: MessageBody::Encrypted(EncryptedPayload::new(payload)?),
+        })
+    }
+}
+
+impl MessageContent {
+    pub fn new(content: String) -> Result<Self, InvalidMessageContentError> {
+        let trimmed = content.trim();
+        if trimmed.is_empty() {
+            return Err(InvalidMessageContentError::Empty);
+        }
+        if trimmed.chars().count() > MESSAGE_CONTENT_MAX_LEN {
+            return Err(InvalidMessageContentError::TooLong);
+        }
+
+        if trimmed.len() == content.len() {
+            Ok(Self(content))
+        } else {
+            Ok(Self(trimmed.to_owned()))
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self) -> &str {
+        &self.0
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for MessageContent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let inner = String::deserialize(deserializer)?;
+        Self::new(inner).map_err(Error::custom)
+    }
+}
+
+impl EncryptedPayload {
+    pub fn new(payload: Vec<u8>) -> Result<Self, InvalidEncryptedPayloadError> {
+        if payload.is_empty() {
+            Err(InvalidEncryptedPayloadError::Empty)
+        } else if payload.len() > ENCRYPTED_PAYLOAD_MAX_LEN {
+            Err(InvalidEncryptedPayloadError::TooLong)
+        } else {
+            Ok(Self(payload))
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self) -> &[u8] {
+        &self.0
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl Serialize for EncryptedPayload {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        base64_bytes::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for EncryptedPayload {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let inner = base64_bytes::deserialize(deserializer)?;
+        Self::new(inner).map_err(Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::conversation::{
+        ENCRYPTED_PAYLOAD_MAX_LEN, EncryptedPayload, InvalidEncryptedPayloadError,
+        InvalidMessageContentError, MESSAGE_CONTENT_MAX_LEN, MessageContent,
+    };
+
+    #[test]
+    fn message_content_validation() {
+        assert_eq!(MessageContent::new(" hi ".to_owned()).unwrap().get(), "hi");
+        assert_eq!(
+            MessageContent::new("\n".to_owned()),
+            Err(InvalidMessageContentError::Empty)
+        );
+        assert_eq!(
+            MessageContent::new("a".repeat(MESSAGE_CONTENT_MAX_LEN + 1)),
+            Err(InvalidMessageContentError::TooLong)
+        );
+    }
+
+    #[test]
+    fn encrypted_payload_validation() {
+        assert_eq!(
+            EncryptedPayload::new(Vec::new()),
+            Err(InvalidEncryptedPayloadError::Empty)
+        );
+        assert_eq!(
+            EncryptedPayload::new(vec![0; ENCRYPTED_PAYLOAD_MAX_LEN + 1]),
+            Err(InvalidEncryptedPayloadError::TooLong)
+        );
+    }
+}
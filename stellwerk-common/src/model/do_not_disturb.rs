@@ -0,0 +1,176 @@
+//! Do-not-disturb settings of users, which hold back the webhooks about mentions and the email
+//! digests while they are active. Notifications are still created, so they can be read in the
+//! meantime. Webhooks are delivered when do not disturb ends as it was set at the time of the
+//! mention, and digests are sent once it ends.
+
+use crate::util::rfc3339;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use time::{Time, UtcDateTime, UtcOffset};
+
+time::serde::format_description!(hour_minute, Time, "[hour]:[minute]");
+time::serde::format_description!(
+    offset,
+    UtcOffset,
+    "[offset_hour sign:mandatory]:[offset_minute]"
+);
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct DoNotDisturb {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quiet_hours: Option<QuietHours>,
+    /// Active until then, in addition to the quiet hours.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "rfc3339::option"
+    )]
+    pub snoozed_until: Option<UtcDateTime>,
+}
+
+/// A daily window like `22:00` to `07:00`, which spans midnight if `start` is after `end`.
+/// Daylight saving time is not taken into account, so the offset has to be changed with it.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct QuietHours {
+    #[serde(with = "hour_minute")]
+    pub start: Time,
+    #[serde(with = "hour_minute")]
+    pub end: Time,
+    /// Of `start` and `end` from UTC, like `+02:00`.
+    #[serde(default = "utc", with = "offset")]
+    pub utc_offset: UtcOffset,
+}
+
+fn utc() -> UtcOffset {
+    UtcOffset::UTC
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Error)]
+#[error("The UTC offset of {0} seconds is out of range")]
+pub struct InvalidUtcOffsetError(pub i32);
+
+impl DoNotDisturb {
+    #[must_use]
+    pub fn is_active(&self, now: UtcDateTime) -> bool {
+        self.active_until(now).is_some()
+    }
+
+    /// When do not disturb ends, if it is active at `now`.
+    #[must_use]
+    pub fn active_until(&self, now: UtcDateTime) -> Option<UtcDateTime> {
+        let mut until = now;
+        // The snooze and the quiet hours may overlap in either order, but each window only
+        // extends the other once.
+        for _ in 0..3 {
+            if let Some(snoozed_until) = self.snoozed_until
+                && snoozed_until > until
+            {
+                until = snoozed_until;
+            }
+            match self.quiet_hours.and_then(|hours| hours.ends_at(until)) {
+                Some(end) if end > until => until = end,
+                _ => break,
+            }
+        }
+
+        (until > now).then_some(until)
+    }
+}
+
+impl QuietHours {
+    pub fn utc_offset_from_seconds(seconds: i32) -> Result<UtcOffset, InvalidUtcOffsetError> {
+        UtcOffset::from_whole_seconds(seconds).map_err(|_| InvalidUtcOffsetError(seconds))
+    }
+
+    /// When the window that `time` falls into ends, if any.
+    #[must_use]
+    pub fn ends_at(&self, time: UtcDateTime) -> Option<UtcDateTime> {
+        let local = time.to_offset(self.utc_offset);
+        let in_window = if self.start <= self.end {
+            self.start <= local.time() && local.time() < self.end
+        } else {
+            self.start <= local.time() || local.time() < self.end
+        };
+        if !in_window {
+            return None;
+        }
+
+        let end_date = if local.time() < self.end {
+            local.date()
+        } else {
+            local.date().next_day()?
+        };
+
+        Some(
+            end_date
+                .with_time(self.end)
+                .assume_offset(self.utc_offset)
+                .to_utc(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::do_not_disturb::{DoNotDisturb, QuietHours};
+    use time::macros::{offset, time, utc_datetime};
+
+    const NIGHT: QuietHours = QuietHours {
+        start: time!(22:00),
+        end: time!(07:00),
+        utc_offset: offset!(+2),
+    };
+
+    #[test]
+    fn quiet_hours_span_midnight() {
+        // 23:30 local time.
+        assert_eq!(
+            NIGHT.ends_at(utc_datetime!(2025-12-01 21:30)),
+            Some(utc_datetime!(2025-12-02 05:00))
+        );
+        // 03:00 local time.
+        assert_eq!(
+            NIGHT.ends_at(utc_datetime!(2025-12-02 01:00)),
+            Some(utc_datetime!(2025-12-02 05:00))
+        );
+        // 07:00 and 12:00 local time.
+        assert_eq!(NIGHT.ends_at(utc_datetime!(2025-12-02 05:00)), None);
+        assert_eq!(NIGHT.ends_at(utc_datetime!(2025-12-02 10:00)), None);
+    }
+
+    #[test]
+    fn snooze_extends_quiet_hours() {
+        let do_not_disturb = DoNotDisturb {
+            quiet_hours: Some(NIGHT),
+            snoozed_until: Some(utc_datetime!(2025-12-02 08:00)),
+        };
+
+        assert_eq!(
+            do_not_disturb.active_until(utc_datetime!(2025-12-02 01:00)),
+            Some(utc_datetime!(2025-12-02 08:00))
+        );
+        // Snoozed into the next quiet hours.
+        let do_not_disturb = DoNotDisturb {
+            snoozed_until: Some(utc_datetime!(2025-12-02 21:00)),
+            ..do_not_disturb
+        };
+        assert_eq!(
+            do_not_disturb.active_until(utc_datetime!(2025-12-02 12:00)),
+            Some(utc_datetime!(2025-12-03 05:00))
+        );
+        assert!(!DoNotDisturb::default().is_active(utc_datetime!(2025-12-02 12:00)));
+    }
+
+    #[test]
+    fn serialization() {
+        let do_not_disturb: DoNotDisturb = serde_json::from_str(
+            r#"{"quiet_hours": {"start": "22:00", "end": "07:00", "utc_offset": "+02:00"}}"#,
+        )
+        .unwrap();
+        assert_eq!(do_not_disturb.quiet_hours, Some(NIGHT));
+        assert_eq!(
+            serde_json::to_string(&do_not_disturb).unwrap(),
+            r#"{"quiet_hours":{"start":"22:00","end":"07:00","utc_offset":"+02:00"}}"#
+        );
+    }
+}
@@ -0,0 +1,436 @@
+use crate::model::{
+    Id,
+    notification::{Notification, NotificationKind, NotificationMarker},
+    oembed::post_url,
+    user::{UserHandle, UserMarker},
+};
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use serde::{
+    Deserialize, Deserializer, Serialize,
+    de::{Error, Unexpected},
+};
+use std::{
+    fmt::{Debug, Display, Formatter, Write},
+    str::FromStr,
+};
+use thiserror::Error;
+use time::Duration;
+
+/// The maximum length of an email address as per RFC 5321.
+pub const EMAIL_ADDRESS_MAX_LEN: usize = 254;
+const UNSUBSCRIBE_TOKEN_LEN: usize = 24;
+
+/// A syntactically plausible email address.
+/// Whether it is deliverable can only be known by sending to it.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Serialize)]
+#[serde(transparent)]
+pub struct EmailAddress(String);
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Error)]
+#[error("The email address is invalid: {0}")]
+pub struct InvalidEmailAddressError(String);
+
+#[derive(
+    Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Deserialize, Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum DigestFrequency {
+    #[default]
+    Daily,
+    Weekly,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Error)]
+#[error("The digest frequency is invalid: {0}")]
+pub struct InvalidDigestFrequencyError(String);
+
+/// The user-editable part of an [`EmailDigestSubscription`].
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct EmailDigestSettings {
+    pub email: EmailAddress,
+    pub frequency: DigestFrequency,
+}
+
+/// A user's subscription to email digests of their unread notifications.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct EmailDigestSubscription {
+    pub user: Id<UserMarker>,
+    pub settings: EmailDigestSettings,
+    pub unsubscribe_token: UnsubscribeToken,
+    /// Notifications up to this one are already read or included in a previous digest.
+    pub digested_up_to: Option<Id<NotificationMarker>>,
+}
+
+/// Allows unsubscribing from digests without logging in.
+/// It is not a credential, so unlike auth tokens it is stored as is.
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct UnsubscribeToken(pub [u8; UNSUBSCRIBE_TOKEN_LEN]);
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Error)]
+#[error("The unsubscribe token is invalid")]
+pub struct InvalidUnsubscribeTokenError;
+
+/// Subject and plain text body of a digest email.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub struct Digest {
+    pub subject: String,
+    pub body: String,
+}
+
+/// A plain text email, stored in the [job](crate::model::job::JobPayload::Email) sending it.
+/// Built from one of the templates, like [`Email::verification`].
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct Email {
+    pub to: EmailAddress,
+    pub subject: String,
+    pub body: String,
+    /// Sent as `List-Unsubscribe` together with `List-Unsubscribe-Post` for one-click unsubscribing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unsubscribe_url: Option<String>,
+}
+
+impl EmailAddress {
+    pub fn new(address: String) -> Result<Self, InvalidEmailAddressError> {
+        let plausible = address.len() <= EMAIL_ADDRESS_MAX_LEN
+            && !address.contains(|c: char| c.is_whitespace() || c.is_control())
+            && address
+                .rsplit_once('@')
+                .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.'));
+
+        if plausible {
+            Ok(Self(address))
+        } else {
+            Err(InvalidEmailAddressError(address))
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self) -> &str {
+        &self.0
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for EmailAddress {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let inner = String::deserialize(deserializer)?;
+        Self::new(inner)
+            .map_err(|err| Error::invalid_value(Unexpected::Str(&err.0), &"EmailAddress"))
+    }
+}
+
+impl DigestFrequency {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DigestFrequency::Daily => "daily",
+            DigestFrequency::Weekly => "weekly",
+        }
+    }
+
+    /// The time between two digests.
+    #[must_use]
+    pub fn period(self) -> Duration {
+        match self {
+            DigestFrequency::Daily => Duration::days(1),
+            DigestFrequency::Weekly => Duration::weeks(1),
+        }
+    }
+}
+
+impl Display for DigestFrequency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DigestFrequency {
+    type Err = InvalidDigestFrequencyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "daily" => Ok(DigestFrequency::Daily),
+            "weekly" => Ok(DigestFrequency::Weekly),
+            _ => Err(InvalidDigestFrequencyError(s.to_owned())),
+        }
+    }
+}
+
+impl UnsubscribeToken {
+    #[must_use]
+    pub fn generate_random() -> Self {
+        Self(rand::random())
+    }
+
+    #[must_use]
+    pub fn as_token_str(&self) -> String {
+        BASE64_URL_SAFE_NO_PAD.encode(self.0)
+    }
+}
+
+impl FromStr for UnsubscribeToken {
+    type Err = InvalidUnsubscribeTokenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BASE64_URL_SAFE_NO_PAD
+            .decode(s)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(Self)
+            .ok_or(InvalidUnsubscribeTokenError)
+    }
+}
+
+impl TryFrom<Box<[u8]>> for UnsubscribeToken {
+    type Error = InvalidUnsubscribeTokenError;
+
+    fn try_from(value: Box<[u8]>) -> Result<Self, Self::Error> {
+        <[u8; UNSUBSCRIBE_TOKEN_LEN]>::try_from(value.as_ref())
+            .map(Self)
+            .map_err(|_| InvalidUnsubscribeTokenError)
+    }
+}
+
+impl Debug for UnsubscribeToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("UnsubscribeToken")
+            .field(&"<redacted>")
+            .finish()
+    }
+}
+
+impl Digest {
+    /// Summarizes `notifications`, newest first.
+    /// `unread_count` may be larger than the number of notifications if not all are included.
+    #[must_use]
+    pub fn new(
+        notifications: &[Notification],
+        unread_count: u64,
+        public_url: &str,
+        unsubscribe_url: &str,
+    ) -> Self {
+        let subject = if unread_count == 1 {
+            "You have 1 unread notification on Stellwerk".to_owned()
+        } else {
+            format!("You have {unread_count} unread notifications on Stellwerk")
+        };
+
+        let mut body = String::new();
+        for notification in notifications {
+            let actor = notification.actor.handle.get();
+            match (notification.kind, notification.post) {
+                (NotificationKind::Mention, Some(post)) => writeln!(
+                    body,
+                    "- @{actor} mentioned you: {}",
+                    post_url(public_url, post)
+                ),
+                (NotificationKind::Mention, None) => writeln!(body, "- @{actor} mentioned you"),
+                (NotificationKind::Like, Some(post)) => writeln!(
+                    body,
+                    "- @{actor} liked your post: {}",
+                    post_url(public_url, post)
+                ),
+                (NotificationKind::Like, None) => writeln!(body, "- @{actor} liked your post"),
+                (NotificationKind::Announce, Some(post)) => writeln!(
+                    body,
+                    "- @{actor} shared your post: {}",
+                    post_url(public_url, post)
+                ),
+                (NotificationKind::Announce, None) => {
+                    writeln!(body, "- @{actor} shared your post")
+                }
+                (NotificationKind::Follow, _) => writeln!(body, "- @{actor} followed you"),
+                (NotificationKind::Warning, Some(post)) => writeln!(
+                    body,
+                    "- A moderator warned you about your post: {}",
+                    post_url(public_url, post)
+                ),
+                (NotificationKind::Warning, None) => writeln!(body, "- A moderator warned you"),
+                (NotificationKind::Impersonation, _) => writeln!(
+                    body,
+                    "- An administrator signed in to your account for support"
+                ),
+            }
+            .expect("Writing to String cannot fail");
+        }
+
+        let omitted = unread_count.saturating_sub(notifications.len() as u64);
+        if omitted > 0 {
+            writeln!(body, "- and {omitted} more").expect("Writing to String cannot fail");
+        }
+
+        write!(
+            body,
+            "\nSee all notifications at {public_url}/notifications\n\
+            Unsubscribe from these emails: {unsubscribe_url}\n"
+        )
+        .expect("Writing to String cannot fail");
+
+        Self { subject, body }
+    }
+}
+
+impl Email {
+    /// Asks `handle` to confirm that `to` is their address by following `verification_url`.
+    #[must_use]
+    pub fn verification(
+        to: EmailAddress,
+        handle: &UserHandle,
+        verification_url: &str,
+        valid_for: Duration,
+    ) -> Self {
+        Self {
+            to,
+            subject: "Confirm your email address on Stellwerk".to_owned(),
+            body: format!(
+                "Hi @{handle},\n\n\
+                please confirm that this is your email address by following this link \
+                within {hours} hours:\n{verification_url}\n\n\
+                If you did not add this address to a Stellwerk account, you can ignore this email.\n",
+                handle = handle.get(),
+                hours = valid_for.whole_hours(),
+            ),
+            unsubscribe_url: None,
+        }
+    }
+
+    /// Lets `handle` choose a new password by following `reset_url`.
+    #[must_use]
+    pub fn password_reset(
+        to: EmailAddress,
+        handle: &UserHandle,
+        reset_url: &str,
+        valid_for: Duration,
+    ) -> Self {
+        Self {
+            to,
+            subject: "Reset your password on Stellwerk".to_owned(),
+            body: format!(
+                "Hi @{handle},\n\n\
+                you can choose a new password by following this link \
+                within {hours} hours:\n{reset_url}\n\n\
+                If you did not ask for this, you can ignore this email \
+                and your password stays the same.\n",
+                handle = handle.get(),
+                hours = valid_for.whole_hours(),
+            ),
+            unsubscribe_url: None,
+        }
+    }
+
+    #[must_use]
+    pub fn digest(to: EmailAddress, digest: Digest, unsubscribe_url: String) -> Self {
+        Self {
+            to,
+            subject: digest.subject,
+            body: digest.body,
+            unsubscribe_url: Some(unsubscribe_url),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{
+        Id,
+        email::{Digest, Email, EmailAddress, UnsubscribeToken},
+        notification::{Notification, NotificationKind},
+        user::{User, UserHandle},
+    };
+
+    #[test]
+    fn email_address_validation() {
+        assert!(EmailAddress::new("alice@example.com".to_owned()).is_ok());
+        assert!(EmailAddress::new("a@b@example.com".to_owned()).is_ok());
+        assert!(EmailAddress::new("alice".to_owned()).is_err());
+        assert!(EmailAddress::new("@example.com".to_owned()).is_err());
+        assert!(EmailAddress::new("alice@localhost".to_owned()).is_err());
+        assert!(EmailAddress::new("alice @example.com".to_owned()).is_err());
+    }
+
+    #[test]
+    fn unsubscribe_token_round_trip() {
+        let token = UnsubscribeToken::generate_random();
+
+        assert_eq!(token.as_token_str().parse(), Ok(token));
+        assert!("too-short".parse::<UnsubscribeToken>().is_err());
+    }
+
+    #[test]
+    fn digest() {
+        let bob = User {
+            id: Id::from(2),
+            handle: UserHandle::new("bob".to_owned()).unwrap(),
+            verified: false,
+        };
+        let notifications = [
+            Notification {
+                id: Id::from(11),
+                kind: NotificationKind::Mention,
+                actor: bob.clone(),
+                post: Some(Id::from(5)),
+            },
+            Notification {
+                id: Id::from(10),
+                kind: NotificationKind::Like,
+                actor: bob.clone(),
+                post: Some(Id::from(4)),
+            },
+            Notification {
+                id: Id::from(9),
+                kind: NotificationKind::Follow,
+                actor: bob,
+                post: None,
+            },
+        ];
+
+        let digest = Digest::new(
+            &notifications,
+            4,
+            "https://stellwerk.example",
+            "https://stellwerk.example/email/unsubscribe?token=abc",
+        );
+
+        assert_eq!(
+            digest.subject,
+            "You have 4 unread notifications on Stellwerk"
+        );
+        assert_eq!(
+            digest.body,
+            "- @bob mentioned you: https://stellwerk.example/posts/5\n\
+            - @bob liked your post: https://stellwerk.example/posts/4\n\
+            - @bob followed you\n\
+            - and 1 more\n\
+            \n\
+            See all notifications at https://stellwerk.example/notifications\n\
+            Unsubscribe from these emails: https://stellwerk.example/email/unsubscribe?token=abc\n"
+        );
+    }
+
+    #[test]
+    fn verification_email() {
+        let email = Email::verification(
+            EmailAddress::new("alice@example.com".to_owned()).unwrap(),
+            &UserHandle::new("alice".to_owned()).unwrap(),
+            "https://stellwerk.example/verify?token=abc",
+            time::Duration::days(1),
+        );
+
+        assert_eq!(email.subject, "Confirm your email address on Stellwerk");
+        assert_eq!(
+            email.body,
+            "Hi @alice,\n\n\
+            please confirm that this is your email address by following this link within 24 hours:\n\
+            https://stellwerk.example/verify?token=abc\n\n\
+            If you did not add this address to a Stellwerk account, you can ignore this email.\n"
+        );
+        assert_eq!(email.unsubscribe_url, None);
+    }
+}
@@ -0,0 +1,111 @@
+//! Feature flags, which operators toggle at runtime without redeploying.
+//!
+//! A flag follows the configuration until an administrator sets it, which is stored in the
+//! database and applies to all servers, until it is reset.
+
+use crate::model::instance::InstanceFeatures;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+use thiserror::Error;
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureFlag {
+    PublicTimeline,
+    MastodonApi,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Error)]
+#[error("The feature flag is invalid: {0}")]
+pub struct InvalidFeatureFlagError(String);
+
+/// A feature flag as administrators see it.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct FeatureFlagState {
+    pub flag: FeatureFlag,
+    pub enabled: bool,
+    /// Whether an administrator set the flag, instead of it following the configuration.
+    pub overridden: bool,
+}
+
+/// The body of setting a feature flag.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct SetFeatureFlag {
+    pub enabled: bool,
+}
+
+impl FeatureFlag {
+    pub const ALL: [FeatureFlag; 2] = [FeatureFlag::PublicTimeline, FeatureFlag::MastodonApi];
+
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FeatureFlag::PublicTimeline => "public_timeline",
+            FeatureFlag::MastodonApi => "mastodon_api",
+        }
+    }
+
+    /// Whether the flag is enabled in `features`.
+    #[must_use]
+    pub fn is_enabled_in(self, features: &InstanceFeatures) -> bool {
+        match self {
+            FeatureFlag::PublicTimeline => features.public_timeline,
+            FeatureFlag::MastodonApi => features.mastodon_api,
+        }
+    }
+
+    pub fn set_in(self, features: &mut InstanceFeatures, enabled: bool) {
+        match self {
+            FeatureFlag::PublicTimeline => features.public_timeline = enabled,
+            FeatureFlag::MastodonApi => features.mastodon_api = enabled,
+        }
+    }
+}
+
+impl Display for FeatureFlag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for FeatureFlag {
+    type Err = InvalidFeatureFlagError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "public_timeline" => Ok(FeatureFlag::PublicTimeline),
+            "mastodon_api" => Ok(FeatureFlag::MastodonApi),
+            _ => Err(InvalidFeatureFlagError(s.to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{feature::FeatureFlag, instance::InstanceFeatures};
+    use serde_json::json;
+
+    #[test]
+    fn feature_flag_round_trip() {
+        for flag in FeatureFlag::ALL {
+            assert_eq!(flag.as_str().parse(), Ok(flag));
+            assert_eq!(serde_json::to_value(flag).unwrap(), json!(flag.as_str()));
+        }
+        assert!("quote_posts".parse::<FeatureFlag>().is_err());
+    }
+
+    #[test]
+    fn set_in_features() {
+        let mut features = InstanceFeatures::default();
+        for flag in FeatureFlag::ALL {
+            flag.set_in(&mut features, true);
+            assert!(flag.is_enabled_in(&features));
+        }
+        FeatureFlag::MastodonApi.set_in(&mut features, false);
+        assert!(features.public_timeline);
+        assert!(!features.mastodon_api);
+    }
+}
@@ -0,0 +1,351 @@
+use crate::{
+    model::{Id, post::Post, user::UserMarker},
+    util::rfc3339,
+};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+use thiserror::Error;
+use time::UtcDateTime;
+
+pub const FILTER_PHRASE_MAX_LEN: usize = 200;
+/// Limits the compiled size of regex filters so users cannot make matching arbitrarily expensive.
+const FILTER_REGEX_SIZE_LIMIT: usize = 1 << 16;
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
+pub struct FilterMarker;
+
+/// Where a filter is applied.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterContext {
+    Home,
+    Notifications,
+    Public,
+}
+
+/// What happens to posts matching a filter.
+#[derive(
+    Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Deserialize, Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterAction {
+    /// Drop matching posts from responses.
+    Hide,
+    /// Return matching posts, but mark them as filtered.
+    #[default]
+    Warn,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct Filter {
+    pub id: Id<FilterMarker>,
+    pub user: Id<UserMarker>,
+    #[serde(flatten)]
+    pub settings: FilterSettings,
+    /// Incremented by every update, see [`UpdateFilter::version`].
+    pub version: u64,
+}
+
+/// The user-editable part of a [`Filter`].
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct FilterSettings {
+    pub phrase: String,
+    /// If true, `phrase` is a regular expression. Otherwise, it matches case-insensitive whole words.
+    #[serde(default)]
+    pub regex: bool,
+    pub contexts: Vec<FilterContext>,
+    #[serde(default)]
+    pub action: FilterAction,
+    #[serde(default, with = "rfc3339::option")]
+    pub expires_at: Option<UtcDateTime>,
+}
+
+/// The body of updating a [`Filter`].
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct UpdateFilter {
+    #[serde(flatten)]
+    pub settings: FilterSettings,
+    /// The version of the filter the update is based on. If given and the filter was updated since,
+    /// the update is rejected with a conflict. Without it, the update always applies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Error)]
+pub enum InvalidFilterError {
+    #[error("The filter phrase is empty")]
+    EmptyPhrase,
+    #[error("The filter phrase is longer than {FILTER_PHRASE_MAX_LEN} characters")]
+    PhraseTooLong,
+    #[error("The filter regex is invalid: {0}")]
+    Regex(String),
+    #[error("The filter has no contexts")]
+    NoContexts,
+    #[error("The filter context is invalid: {0}")]
+    Context(String),
+    #[error("The filter action is invalid: {0}")]
+    Action(String),
+}
+
+/// Reference to the filter that matched a post.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct FilterMatch {
+    pub filter: Id<FilterMarker>,
+    pub phrase: String,
+}
+
+/// The result of checking content against a [`FilterMatcher`].
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub enum FilterOutcome {
+    Pass,
+    Warn(Vec<FilterMatch>),
+    Hide,
+}
+
+/// Matches content against the active filters of a user in one [`FilterContext`].
+#[derive(Clone, Debug, Default)]
+pub struct FilterMatcher {
+    filters: Vec<CompiledFilter>,
+}
+
+#[derive(Clone, Debug)]
+struct CompiledFilter {
+    id: Id<FilterMarker>,
+    phrase: String,
+    action: FilterAction,
+    regex: Regex,
+}
+
+impl FilterSettings {
+    /// Checks the settings and compiles the pattern they describe.
+    pub fn compile(&self) -> Result<Regex, InvalidFilterError> {
+        if self.phrase.trim().is_empty() {
+            return Err(InvalidFilterError::EmptyPhrase);
+        }
+        if self.phrase.chars().count() > FILTER_PHRASE_MAX_LEN {
+            return Err(InvalidFilterError::PhraseTooLong);
+        }
+        if self.contexts.is_empty() {
+            return Err(InvalidFilterError::NoContexts);
+        }
+
+        let pattern = if self.regex {
+            self.phrase.clone()
+        } else {
+            format!(r"\b{}\b", regex::escape(self.phrase.trim()))
+        };
+
+        RegexBuilder::new(&pattern)
+            .case_insensitive(true)
+            .size_limit(FILTER_REGEX_SIZE_LIMIT)
+            .build()
+            .map_err(|err| InvalidFilterError::Regex(err.to_string()))
+    }
+}
+
+impl FilterMatcher {
+    /// Uses all filters that apply to `context` and are not expired at `now`.
+    /// Filters that fail to compile are ignored.
+    #[must_use]
+    pub fn new<'a>(
+        filters: impl IntoIterator<Item = &'a Filter>,
+        context: FilterContext,
+        now: UtcDateTime,
+    ) -> Self {
+        let filters = filters
+            .into_iter()
+            .filter(|filter| filter.settings.contexts.contains(&context))
+            .filter(|filter| {
+                filter
+                    .settings
+                    .expires_at
+                    .is_none_or(|expires_at| expires_at > now)
+            })
+            .filter_map(|filter| {
+                Some(CompiledFilter {
+                    id: filter.id,
+                    phrase: filter.settings.phrase.clone(),
+                    action: filter.settings.action,
+                    regex: filter.settings.compile().ok()?,
+                })
+            })
+            .collect();
+
+        Self { filters }
+    }
+
+    #[must_use]
+    pub fn check(&self, content: &str) -> FilterOutcome {
+        let mut matches = Vec::new();
+
+        for filter in &self.filters {
+            if !filter.regex.is_match(content) {
+                continue;
+            }
+
+            match filter.action {
+                FilterAction::Hide => return FilterOutcome::Hide,
+                FilterAction::Warn => matches.push(FilterMatch {
+                    filter: filter.id,
+                    phrase: filter.phrase.clone(),
+                }),
+            }
+        }
+
+        if matches.is_empty() {
+            FilterOutcome::Pass
+        } else {
+            FilterOutcome::Warn(matches)
+        }
+    }
+
+    /// Drops hidden posts and marks warned posts as [`Post::filtered`].
+    #[must_use]
+    pub fn apply(&self, posts: Vec<Post>) -> Vec<Post> {
+        if self.filters.is_empty() {
+            return posts;
+        }
+
+        posts
+            .into_iter()
+            .filter_map(|post| self.apply_one(post))
+            .collect()
+    }
+
+    /// Like [`FilterMatcher::apply`], but for a single post.
+    #[must_use]
+    pub fn apply_one(&self, mut post: Post) -> Option<Post> {
+        match self.check(post.content.get()) {
+            FilterOutcome::Pass => Some(post),
+            FilterOutcome::Warn(matches) => {
+                post.filtered = matches;
+                Some(post)
+            }
+            FilterOutcome::Hide => None,
+        }
+    }
+}
+
+macro_rules! str_enum {
+    ($name:ident { $($variant:ident => $str:literal),* $(,)? } else $error:ident) => {
+        impl $name {
+            #[must_use]
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $($name::$variant => $str,)*
+                }
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = InvalidFilterError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $($str => Ok($name::$variant),)*
+                    _ => Err(InvalidFilterError::$error(s.to_owned())),
+                }
+            }
+        }
+    };
+}
+
+str_enum!(FilterContext {
+    Home => "home",
+    Notifications => "notifications",
+    Public => "public",
+} else Context);
+
+str_enum!(FilterAction {
+    Hide => "hide",
+    Warn => "warn",
+} else Action);
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{
+        Id,
+        filter::{
+            Filter, FilterAction, FilterContext, FilterMatch, FilterMatcher, FilterOutcome,
+            FilterSettings, InvalidFilterError,
+        },
+    };
+    use time::{Duration, macros::utc_datetime};
+
+    fn filter(id: u64, phrase: &str, regex: bool, action: FilterAction) -> Filter {
+        Filter {
+            id: Id::from(id),
+            user: Id::from(1),
+            settings: FilterSettings {
+                phrase: phrase.to_owned(),
+                regex,
+                contexts: vec![FilterContext::Public],
+                action,
+                expires_at: None,
+            },
+            version: 0,
+        }
+    }
+
+    #[test]
+    fn matching() {
+        let now = utc_datetime!(2025-11-01 12:00);
+        let mut expired = filter(4, "expired", false, FilterAction::Hide);
+        expired.settings.expires_at = Some(now - Duration::minutes(1));
+        let filters = [
+            filter(1, "Spoiler", false, FilterAction::Warn),
+            filter(2, r"buy\s+now", true, FilterAction::Hide),
+            filter(3, "spoil", false, FilterAction::Warn),
+            expired,
+        ];
+        let matcher = FilterMatcher::new(&filters, FilterContext::Public, now);
+
+        assert_eq!(matcher.check("nothing to see"), FilterOutcome::Pass);
+        assert_eq!(matcher.check("spoiled milk"), FilterOutcome::Pass);
+        assert_eq!(
+            matcher.check("big SPOILER ahead"),
+            FilterOutcome::Warn(vec![FilterMatch {
+                filter: Id::from(1),
+                phrase: "Spoiler".to_owned(),
+            }])
+        );
+        assert_eq!(matcher.check("spoiler: Buy  now!"), FilterOutcome::Hide);
+        assert_eq!(matcher.check("expired"), FilterOutcome::Pass);
+
+        let home_matcher = FilterMatcher::new(&filters, FilterContext::Home, now);
+        assert_eq!(home_matcher.check("spoiler"), FilterOutcome::Pass);
+    }
+
+    #[test]
+    fn validation() {
+        assert_eq!(
+            filter(1, " ", false, FilterAction::Warn)
+                .settings
+                .compile()
+                .err(),
+            Some(InvalidFilterError::EmptyPhrase)
+        );
+        assert!(matches!(
+            filter(1, "(unclosed", true, FilterAction::Warn)
+                .settings
+                .compile(),
+            Err(InvalidFilterError::Regex(_))
+        ));
+        assert!(
+            filter(1, "(unclosed", false, FilterAction::Warn)
+                .settings
+                .compile()
+                .is_ok()
+        );
+    }
+}
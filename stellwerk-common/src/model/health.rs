@@ -0,0 +1,35 @@
+//! The readiness of a server to take requests, as reported at `/readyz`.
+
+use serde::{Deserialize, Serialize};
+
+/// The state of the circuit breaker around the database.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Queries run as usual.
+    Closed,
+    /// The database failed repeatedly, so queries fail right away.
+    Open,
+    /// The breaker was open long enough, and the next query checks whether the database is back.
+    HalfOpen,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct CircuitBreakerStats {
+    pub state: CircuitState,
+    /// Since the last success.
+    pub consecutive_failures: u32,
+    /// Since the server started.
+    pub times_opened: u64,
+    /// Queries failed right away since the server started.
+    pub rejected: u64,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct Readiness {
+    /// Whether the database answered.
+    pub ready: bool,
+    /// `None` if the circuit breaker is disabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_breaker: Option<CircuitBreakerStats>,
+}
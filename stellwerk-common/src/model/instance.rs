@@ -0,0 +1,30 @@
+use crate::model::tenant::RegistrationMode;
+use serde::{Deserialize, Serialize};
+
+/// Publicly visible information about this instance's configuration.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct InstanceInfo {
+    /// Base URL under which the instance is publicly reachable, without a trailing slash.
+    pub public_url: String,
+    pub limits: InstanceLimits,
+    pub features: InstanceFeatures,
+    /// Missing from servers predating it, whose registrations were all closed.
+    #[serde(default)]
+    pub registrations: RegistrationMode,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct InstanceLimits {
+    /// Maximum number of characters in a post's content.
+    pub post_content_max_len: usize,
+    /// Maximum number of posts a user can pin to their profile.
+    pub max_pinned_posts: usize,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct InstanceFeatures {
+    /// Whether `GET /timeline/public` is available.
+    pub public_timeline: bool,
+    /// Whether the Mastodon-compatible client API under `/api/v1` is available.
+    pub mastodon_api: bool,
+}
@@ -0,0 +1,194 @@
+//! Jobs, which are queued in the database and run in the background by any server process.
+//!
+//! A claimed job is hidden from other processes for a visibility timeout, so that it is run again
+//! if the process running it dies. Failed jobs are retried with backoff, and dead-lettered after
+//! too many attempts, to be inspected and retried by administrators.
+
+use crate::{
+    model::{Id, email::Email, search::SearchDocument, user::UserMarker, webhook::WebhookMarker},
+    util::rfc3339,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+use thiserror::Error;
+use time::UtcDateTime;
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
+pub struct JobMarker;
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    FederationDelivery,
+    WebhookDelivery,
+    EmailDigests,
+    SearchIndexing,
+    Email,
+    CdnPurge,
+    ImpersonationNotice,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Error)]
+#[error("The job kind is invalid: {0}")]
+pub struct InvalidJobKindError(String);
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Error)]
+#[error("The job payload is invalid: {0}")]
+pub struct InvalidJobPayloadError(pub String);
+
+/// What a job does. Stored as JSON, so variants must stay compatible with queued jobs.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobPayload {
+    /// Delivers a serialized activity to a remote inbox.
+    FederationDelivery {
+        /// The local user signing the delivery, or the instance actor if [`None`].
+        sender: Option<Id<UserMarker>>,
+        inbox: String,
+        activity: String,
+    },
+    /// Delivers a serialized [`WebhookPayload`](crate::model::webhook::WebhookPayload)
+    /// to a webhook, unless it was deleted or disabled in the meantime.
+    WebhookDelivery {
+        webhook: Id<WebhookMarker>,
+        payload: String,
+    },
+    /// Sends the email digests that are due. Recurring, so there is at most one such job.
+    EmailDigests,
+    /// Updates a document in the external search engine.
+    SearchIndexing { document: SearchDocument },
+    /// Sends an email, see [`Email`].
+    Email { email: Email },
+    /// Purges the URLs from the CDN in front of the server.
+    CdnPurge { urls: Vec<String> },
+    /// Notifies the user that an admin impersonated them, once the impersonation expired.
+    ImpersonationNotice { user: Id<UserMarker> },
+}
+
+/// A job claimed to be run.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Job {
+    pub id: Id<JobMarker>,
+    pub payload: JobPayload,
+    /// The number of failed attempts so far.
+    pub attempts: u32,
+}
+
+/// A job that failed permanently or too often, kept until it is retried or deleted.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct DeadJob {
+    #[serde(flatten, with = "crate::model::id_with_created_at")]
+    pub id: Id<JobMarker>,
+    pub payload: JobPayload,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub dead_at: UtcDateTime,
+}
+
+impl JobKind {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JobKind::FederationDelivery => "federation_delivery",
+            JobKind::WebhookDelivery => "webhook_delivery",
+            JobKind::EmailDigests => "email_digests",
+            JobKind::SearchIndexing => "search_indexing",
+            JobKind::Email => "email",
+            JobKind::CdnPurge => "cdn_purge",
+            JobKind::ImpersonationNotice => "impersonation_notice",
+        }
+    }
+}
+
+impl Display for JobKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for JobKind {
+    type Err = InvalidJobKindError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "federation_delivery" => Ok(JobKind::FederationDelivery),
+            "webhook_delivery" => Ok(JobKind::WebhookDelivery),
+            "email_digests" => Ok(JobKind::EmailDigests),
+            "search_indexing" => Ok(JobKind::SearchIndexing),
+            "email" => Ok(JobKind::Email),
+            "cdn_purge" => Ok(JobKind::CdnPurge),
+            "impersonation_notice" => Ok(JobKind::ImpersonationNotice),
+            _ => Err(InvalidJobKindError(s.to_owned())),
+        }
+    }
+}
+
+impl JobPayload {
+    #[must_use]
+    pub fn kind(&self) -> JobKind {
+        match self {
+            JobPayload::FederationDelivery { .. } => JobKind::FederationDelivery,
+            JobPayload::WebhookDelivery { .. } => JobKind::WebhookDelivery,
+            JobPayload::EmailDigests => JobKind::EmailDigests,
+            JobPayload::SearchIndexing { .. } => JobKind::SearchIndexing,
+            JobPayload::Email { .. } => JobKind::Email,
+            JobPayload::CdnPurge { .. } => JobKind::CdnPurge,
+            JobPayload::ImpersonationNotice { .. } => JobKind::ImpersonationNotice,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::job::{JobKind, JobPayload};
+    use serde_json::json;
+
+    #[test]
+    fn payload_serialization() {
+        let payload = JobPayload::FederationDelivery {
+            sender: None,
+            inbox: "https://example.com/inbox".to_owned(),
+            activity: "{}".to_owned(),
+        };
+        let value = serde_json::to_value(&payload).unwrap();
+
+        assert_eq!(
+            value,
+            json!({
+                "kind": "federation_delivery",
+                "sender": null,
+                "inbox": "https://example.com/inbox",
+                "activity": "{}",
+            })
+        );
+        assert_eq!(value["kind"], payload.kind().as_str());
+        assert_eq!(
+            serde_json::from_value::<JobPayload>(value).unwrap(),
+            payload
+        );
+        assert_eq!(
+            serde_json::to_value(JobPayload::EmailDigests).unwrap(),
+            json!({ "kind": "email_digests" })
+        );
+    }
+
+    #[test]
+    fn job_kind_round_trip() {
+        for kind in [
+            JobKind::FederationDelivery,
+            JobKind::WebhookDelivery,
+            JobKind::EmailDigests,
+            JobKind::SearchIndexing,
+            JobKind::Email,
+            JobKind::CdnPurge,
+            JobKind::ImpersonationNotice,
+        ] {
+            assert_eq!(kind.as_str().parse(), Ok(kind));
+        }
+        assert!("delivery".parse::<JobKind>().is_err());
+    }
+}
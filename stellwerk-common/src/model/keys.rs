@@ -0,0 +1,117 @@
+//! Public keys that clients publish so that others can set up end-to-end encrypted conversations.
+//!
+//! The server only stores and hands out keys. It neither interprets nor verifies them,
+//! so clients are free to choose their cryptographic protocol.
+
+use crate::util::base64_bytes;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+use thiserror::Error;
+
+pub const KEY_MAX_LEN: usize = 1024;
+/// Maximum number of one-time prekeys a user can have stored at once.
+pub const ONE_TIME_PREKEYS_MAX: usize = 100;
+
+/// A public key or signature. Never empty and never longer than [`KEY_MAX_LEN`] bytes.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
+pub struct KeyBytes(Vec<u8>);
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Error)]
+pub enum InvalidKeyError {
+    #[error("The key is empty")]
+    Empty,
+    #[error("The key is longer than {KEY_MAX_LEN} bytes")]
+    TooLong,
+}
+
+/// A medium-term prekey signed with the identity key.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct SignedPrekey {
+    pub key_id: u32,
+    pub public_key: KeyBytes,
+    pub signature: KeyBytes,
+}
+
+/// A prekey that is handed out at most once.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct OneTimePrekey {
+    pub key_id: u32,
+    pub public_key: KeyBytes,
+}
+
+/// Keys uploaded by a user. Replaces the identity key and signed prekey,
+/// and adds the one-time prekeys to the stored ones.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct PublishKeys {
+    pub identity_key: KeyBytes,
+    pub signed_prekey: SignedPrekey,
+    #[serde(default)]
+    pub one_time_prekeys: Vec<OneTimePrekey>,
+}
+
+/// What another user needs to start an encrypted conversation with a user.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct KeyBundle {
+    pub identity_key: KeyBytes,
+    pub signed_prekey: SignedPrekey,
+    /// `None` if the user ran out of one-time prekeys.
+    pub one_time_prekey: Option<OneTimePrekey>,
+}
+
+/// Lets clients know when to upload more one-time prekeys.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct KeyStatus {
+    pub one_time_prekey_count: u64,
+}
+
+impl KeyBytes {
+    pub fn new(bytes: Vec<u8>) -> Result<Self, InvalidKeyError> {
+        if bytes.is_empty() {
+            Err(InvalidKeyError::Empty)
+        } else if bytes.len() > KEY_MAX_LEN {
+            Err(InvalidKeyError::TooLong)
+        } else {
+            Ok(Self(bytes))
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self) -> &[u8] {
+        &self.0
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl Serialize for KeyBytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        base64_bytes::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyBytes {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let inner = base64_bytes::deserialize(deserializer)?;
+        Self::new(inner).map_err(Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::keys::{InvalidKeyError, KEY_MAX_LEN, KeyBytes};
+
+    #[test]
+    fn key_bytes_validation() {
+        assert_eq!(KeyBytes::new(Vec::new()), Err(InvalidKeyError::Empty));
+        assert_eq!(
+            KeyBytes::new(vec![0; KEY_MAX_LEN + 1]),
+            Err(InvalidKeyError::TooLong)
+        );
+        assert_eq!(KeyBytes::new(vec![1, 2, 3]).unwrap().get(), [1, 2, 3]);
+    }
+}
@@ -0,0 +1,374 @@
+//! Entities of the [Mastodon client API](https://docs.joinmastodon.org/entities/),
+//! so that existing Mastodon clients can be used with stellwerk.
+//!
+//! Clients expect every documented property to be present, so features stellwerk does not have,
+//! like media attachments or favourites, are represented by their empty values.
+
+use crate::{
+    model::{
+        activitypub::actor_id,
+        filter::{FilterContext, FilterMatch},
+        oembed::post_url,
+        post::Post,
+        user::UserProfile,
+    },
+    text::absolute_links,
+    util::rfc3339,
+};
+use serde::{Deserialize, Serialize};
+use time::UtcDateTime;
+
+/// An entity stellwerk has no equivalent of, so lists of it are always empty.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize, Serialize)]
+pub enum Unsupported {}
+
+/// A user, see <https://docs.joinmastodon.org/entities/Account/>.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct Account {
+    pub id: String,
+    /// The handle without the domain of remote users.
+    pub username: String,
+    /// The handle, including the domain of remote users.
+    pub acct: String,
+    pub display_name: String,
+    pub locked: bool,
+    pub bot: bool,
+    pub group: bool,
+    #[serde(with = "rfc3339")]
+    pub created_at: UtcDateTime,
+    pub note: String,
+    pub url: String,
+    pub uri: String,
+    pub avatar: String,
+    pub avatar_static: String,
+    pub header: String,
+    pub header_static: String,
+    pub followers_count: u64,
+    pub following_count: u64,
+    pub statuses_count: u64,
+    pub emojis: Vec<Unsupported>,
+    pub fields: Vec<Unsupported>,
+}
+
+impl Account {
+    /// `public_url` must not have a trailing slash.
+    #[must_use]
+    pub fn for_profile(profile: &UserProfile, public_url: &str) -> Self {
+        let user = &profile.user;
+        let handle = user.handle.get();
+        let username = handle.split_once('@').map_or(handle, |(name, _)| name);
+
+        Self {
+            id: user.id.to_string(),
+            username: username.to_owned(),
+            acct: handle.to_owned(),
+            display_name: handle.to_owned(),
+            locked: false,
+            bot: false,
+            group: false,
+            created_at: user.id.snowflake().timestamp().into(),
+            note: String::new(),
+            url: format!("{public_url}/@{handle}"),
+            uri: actor_id(public_url, user.id),
+            avatar: String::new(),
+            avatar_static: String::new(),
+            header: String::new(),
+            header_static: String::new(),
+            followers_count: profile.stats.follower_count,
+            following_count: profile.stats.following_count,
+            statuses_count: profile.stats.post_count,
+            emojis: Vec::new(),
+            fields: Vec::new(),
+        }
+    }
+}
+
+/// The authenticated user's own [`Account`],
+/// see <https://docs.joinmastodon.org/entities/Account/#CredentialAccount>.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct CredentialAccount {
+    #[serde(flatten)]
+    pub account: Account,
+    pub source: AccountSource,
+}
+
+impl CredentialAccount {
+    #[must_use]
+    pub fn new(account: Account) -> Self {
+        Self {
+            account,
+            source: AccountSource {
+                note: String::new(),
+                fields: Vec::new(),
+                privacy: Visibility::Public,
+                sensitive: false,
+                language: None,
+                follow_requests_count: 0,
+            },
+        }
+    }
+}
+
+/// Defaults used when composing statuses.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct AccountSource {
+    pub note: String,
+    pub fields: Vec<Unsupported>,
+    pub privacy: Visibility,
+    pub sensitive: bool,
+    pub language: Option<String>,
+    pub follow_requests_count: u64,
+}
+
+/// All stellwerk posts are public.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    Public,
+}
+
+/// A post, see <https://docs.joinmastodon.org/entities/Status/>.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+#[allow(clippy::struct_excessive_bools)] // The shape is defined by Mastodon.
+pub struct Status {
+    pub id: String,
+    pub uri: String,
+    pub url: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: UtcDateTime,
+    pub account: Account,
+    /// Sanitized HTML, with absolute links.
+    pub content: String,
+    pub visibility: Visibility,
+    pub sensitive: bool,
+    pub spoiler_text: String,
+    pub media_attachments: Vec<Unsupported>,
+    pub mentions: Vec<Unsupported>,
+    pub tags: Vec<Unsupported>,
+    pub emojis: Vec<Unsupported>,
+    pub reblogs_count: u64,
+    pub favourites_count: u64,
+    pub replies_count: u64,
+    pub in_reply_to_id: Option<String>,
+    pub in_reply_to_account_id: Option<String>,
+    pub reblog: Option<Unsupported>,
+    pub language: Option<String>,
+    pub pinned: bool,
+    pub favourited: bool,
+    pub reblogged: bool,
+    pub muted: bool,
+    pub bookmarked: bool,
+    /// The requesting user's warning filters that matched the status.
+    pub filtered: Vec<FilterResult>,
+}
+
+impl Status {
+    /// `account` must be the author of `post`, and `context` is where the post is shown.
+    /// `public_url` must not have a trailing slash.
+    #[must_use]
+    pub fn for_post(
+        post: &Post,
+        account: Account,
+        context: FilterContext,
+        public_url: &str,
+    ) -> Self {
+        let url = post_url(public_url, post.id);
+
+        Self {
+            id: post.id.to_string(),
+            uri: url.clone(),
+            url: Some(url),
+            created_at: post.id.snowflake().timestamp().into(),
+            account,
+            content: absolute_links(&post.content_html, public_url),
+            visibility: Visibility::Public,
+            sensitive: false,
+            spoiler_text: String::new(),
+            media_attachments: Vec::new(),
+            mentions: Vec::new(),
+            tags: Vec::new(),
+            emojis: Vec::new(),
+            reblogs_count: 0,
+            favourites_count: 0,
+            replies_count: 0,
+            in_reply_to_id: None,
+            in_reply_to_account_id: None,
+            reblog: None,
+            language: None,
+            pinned: post.pinned,
+            favourited: false,
+            reblogged: false,
+            muted: false,
+            bookmarked: false,
+            filtered: post
+                .filtered
+                .iter()
+                .map(|filter_match| FilterResult::new(filter_match, context))
+                .collect(),
+        }
+    }
+}
+
+/// See <https://docs.joinmastodon.org/entities/FilterResult/>.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct FilterResult {
+    pub filter: MatchedFilter,
+    pub keyword_matches: Option<Vec<String>>,
+    pub status_matches: Option<Vec<String>>,
+}
+
+impl FilterResult {
+    #[must_use]
+    pub fn new(filter_match: &FilterMatch, context: FilterContext) -> Self {
+        Self {
+            filter: MatchedFilter {
+                id: filter_match.filter.to_string(),
+                title: filter_match.phrase.clone(),
+                context: vec![context],
+                expires_at: None,
+                filter_action: "warn".to_owned(),
+            },
+            keyword_matches: Some(vec![filter_match.phrase.clone()]),
+            status_matches: None,
+        }
+    }
+}
+
+/// The subset of a Mastodon filter known from a [`FilterMatch`].
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct MatchedFilter {
+    pub id: String,
+    pub title: String,
+    pub context: Vec<FilterContext>,
+    #[serde(with = "rfc3339::option")]
+    pub expires_at: Option<UtcDateTime>,
+    pub filter_action: String,
+}
+
+/// See <https://docs.joinmastodon.org/entities/Relationship/>.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+#[allow(clippy::struct_excessive_bools)] // The shape is defined by Mastodon.
+pub struct Relationship {
+    pub id: String,
+    pub following: bool,
+    pub followed_by: bool,
+    pub showing_reblogs: bool,
+    pub notifying: bool,
+    pub requested: bool,
+    pub requested_by: bool,
+    pub blocking: bool,
+    pub blocked_by: bool,
+    pub muting: bool,
+    pub muting_notifications: bool,
+    pub domain_blocking: bool,
+    pub endorsed: bool,
+    pub note: String,
+}
+
+impl Relationship {
+    #[must_use]
+    pub fn new(id: String, following: bool, followed_by: bool) -> Self {
+        Self {
+            id,
+            following,
+            followed_by,
+            showing_reblogs: following,
+            notifying: false,
+            requested: false,
+            requested_by: false,
+            blocking: false,
+            blocked_by: false,
+            muting: false,
+            muting_notifications: false,
+            domain_blocking: false,
+            endorsed: false,
+            note: String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{
+        Id, StellwerkSnowflake,
+        filter::{FilterContext, FilterMatch},
+        mastodon::{Account, Status},
+        post::{Post, PostContent},
+        user::{User, UserHandle, UserProfile, UserStats},
+    };
+    use time::macros::utc_datetime;
+
+    fn profile(handle: &str) -> UserProfile {
+        UserProfile {
+            user: User {
+                id: Id::from(1),
+                handle: UserHandle::new(handle.to_owned()).unwrap(),
+                verified: false,
+            },
+            stats: UserStats {
+                post_count: 3,
+                follower_count: 2,
+                following_count: 1,
+            },
+            website: None,
+        }
+    }
+
+    #[test]
+    fn account() {
+        let account = Account::for_profile(&profile("alice"), "https://stellwerk.example");
+
+        assert_eq!(account.id, "1");
+        assert_eq!(account.username, "alice");
+        assert_eq!(account.acct, "alice");
+        assert_eq!(account.url, "https://stellwerk.example/@alice");
+        assert_eq!(account.uri, "https://stellwerk.example/users/1");
+        assert_eq!(account.statuses_count, 3);
+
+        let remote =
+            Account::for_profile(&profile("bob@remote.example"), "https://stellwerk.example");
+        assert_eq!(remote.username, "bob");
+        assert_eq!(remote.acct, "bob@remote.example");
+    }
+
+    #[test]
+    fn status() {
+        let content = PostContent::new("hi #tag".to_owned()).unwrap();
+        // One second after the epoch.
+        let id = StellwerkSnowflake::new(1000 << 22).into();
+        let profile = profile("alice");
+        let post = Post {
+            id,
+            author: profile.user.clone(),
+            content_html: content.render_html(),
+            content,
+            pinned: true,
+            filtered: vec![FilterMatch {
+                filter: Id::from(5),
+                phrase: "tag".to_owned(),
+            }],
+        };
+        let account = Account::for_profile(&profile, "https://stellwerk.example");
+
+        let status = Status::for_post(
+            &post,
+            account,
+            FilterContext::Home,
+            "https://stellwerk.example",
+        );
+
+        assert_eq!(status.id, id.to_string());
+        assert_eq!(
+            status.url.as_deref(),
+            Some(format!("https://stellwerk.example/posts/{id}").as_str())
+        );
+        assert_eq!(status.created_at, utc_datetime!(2025-01-01 00:00:01));
+        assert_eq!(
+            status.content,
+            "<p>hi <a href=\"https://stellwerk.example/tags/tag\" class=\"hashtag\">#tag</a></p>"
+        );
+        assert!(status.pinned);
+        assert_eq!(status.filtered[0].filter.id, "5");
+        assert_eq!(status.filtered[0].filter.context, [FilterContext::Home]);
+    }
+}
@@ -1,10 +1,67 @@
+pub mod activitypub;
+pub mod admin;
+pub mod analytics;
+pub mod announcement;
+pub mod atproto;
 pub mod auth;
+pub mod conversation;
+pub mod do_not_disturb;
+pub mod email;
+pub mod feature;
+pub mod filter;
+pub mod health;
+pub mod instance;
+pub mod job;
+pub mod keys;
+pub mod mastodon;
+pub mod moderation;
+pub mod notification;
+pub mod oembed;
+pub mod oidc;
+pub mod page;
+pub mod pagination;
+pub mod policy;
 pub mod post;
+pub mod problem;
+pub mod quota;
+pub mod report;
+pub mod search;
+pub mod sitemap;
+pub mod spam;
+pub mod tenant;
 pub mod user;
+pub mod verification;
+pub mod webhook;
 
 use crate::{
-    model::{auth::InvalidAuthTokenHashError, user::InvalidUserHandleError},
-    snowflake::{Epoch, Snowflake, SnowflakeGenerator},
+    model::{
+        analytics::InvalidAnalyticsMetricError,
+        announcement::InvalidAnnouncementError,
+        auth::InvalidAuthTokenHashError,
+        conversation::{InvalidEncryptedPayloadError, InvalidMessageContentError},
+        do_not_disturb::InvalidUtcOffsetError,
+        email::{
+            InvalidDigestFrequencyError, InvalidEmailAddressError, InvalidUnsubscribeTokenError,
+        },
+        feature::InvalidFeatureFlagError,
+        filter::InvalidFilterError,
+        job::{InvalidJobKindError, InvalidJobPayloadError},
+        keys::InvalidKeyError,
+        moderation::InvalidModerationActionError,
+        notification::InvalidNotificationKindError,
+        oidc::{InvalidOidcProviderError, InvalidOidcTokenError},
+        policy::{InvalidPolicyContentError, InvalidPolicyKindError},
+        post::InvalidPostContentError,
+        report::{
+            InvalidReportActionError, InvalidReportCategoryError, InvalidReportCommentError,
+            InvalidReportNoteError,
+        },
+        search::InvalidSearchQueryError,
+        tenant::InvalidTenantIdError,
+        user::{InvalidProfileWebsiteError, InvalidUserHandleError, InvalidUserRoleError},
+        webhook::InvalidWebhookError,
+    },
+    snowflake::{AtomicSnowflakeGenerator, Epoch, Snowflake, SnowflakeGenerator},
     util::NonPositiveDurationError,
 };
 use serde::{Deserialize, Serialize};
@@ -17,9 +74,67 @@ pub enum ModelValidationError {
     #[error(transparent)]
     UserHandle(#[from] InvalidUserHandleError),
     #[error(transparent)]
+    UserRole(#[from] InvalidUserRoleError),
+    #[error(transparent)]
+    PostContent(#[from] InvalidPostContentError),
+    #[error(transparent)]
+    MessageContent(#[from] InvalidMessageContentError),
+    #[error(transparent)]
+    EncryptedPayload(#[from] InvalidEncryptedPayloadError),
+    #[error(transparent)]
+    Key(#[from] InvalidKeyError),
+    #[error(transparent)]
+    ReportCategory(#[from] InvalidReportCategoryError),
+    #[error(transparent)]
+    ReportComment(#[from] InvalidReportCommentError),
+    #[error(transparent)]
+    ReportAction(#[from] InvalidReportActionError),
+    #[error(transparent)]
+    ReportNote(#[from] InvalidReportNoteError),
+    #[error(transparent)]
+    ModerationAction(#[from] InvalidModerationActionError),
+    #[error(transparent)]
+    Filter(#[from] InvalidFilterError),
+    #[error(transparent)]
+    Announcement(#[from] InvalidAnnouncementError),
+    #[error(transparent)]
+    PolicyKind(#[from] InvalidPolicyKindError),
+    #[error(transparent)]
+    PolicyContent(#[from] InvalidPolicyContentError),
+    #[error(transparent)]
+    Webhook(#[from] InvalidWebhookError),
+    #[error(transparent)]
+    FeatureFlag(#[from] InvalidFeatureFlagError),
+    #[error(transparent)]
+    AnalyticsMetric(#[from] InvalidAnalyticsMetricError),
+    #[error(transparent)]
+    JobKind(#[from] InvalidJobKindError),
+    #[error(transparent)]
+    JobPayload(#[from] InvalidJobPayloadError),
+    #[error(transparent)]
+    NotificationKind(#[from] InvalidNotificationKindError),
+    #[error(transparent)]
+    EmailAddress(#[from] InvalidEmailAddressError),
+    #[error(transparent)]
+    DigestFrequency(#[from] InvalidDigestFrequencyError),
+    #[error(transparent)]
+    UnsubscribeToken(#[from] InvalidUnsubscribeTokenError),
+    #[error(transparent)]
+    OidcProvider(#[from] InvalidOidcProviderError),
+    #[error(transparent)]
+    OidcToken(#[from] InvalidOidcTokenError),
+    #[error(transparent)]
     NonPositiveDuration(#[from] NonPositiveDurationError),
     #[error(transparent)]
     TokenHash(#[from] InvalidAuthTokenHashError),
+    #[error(transparent)]
+    TenantId(#[from] InvalidTenantIdError),
+    #[error(transparent)]
+    SearchQuery(#[from] InvalidSearchQueryError),
+    #[error(transparent)]
+    ProfileWebsite(#[from] InvalidProfileWebsiteError),
+    #[error(transparent)]
+    UtcOffset(#[from] InvalidUtcOffsetError),
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
@@ -30,12 +145,16 @@ impl Epoch for StellwerkEpoch {
 
 pub type StellwerkSnowflake = Snowflake<StellwerkEpoch>;
 pub type StellwerkSnowflakeGenerator = SnowflakeGenerator<StellwerkEpoch>;
+pub type StellwerkAtomicSnowflakeGenerator = AtomicSnowflakeGenerator<StellwerkEpoch>;
 
 #[derive(
     Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Serialize, Deserialize,
 )]
 #[serde(transparent)]
-pub struct Id<Marker>(StellwerkSnowflake, #[serde(skip)] PhantomData<Marker>);
+pub struct Id<Marker>(
+    #[serde(with = "crate::snowflake::string")] StellwerkSnowflake,
+    #[serde(skip)] PhantomData<Marker>,
+);
 
 impl<Marker> Id<Marker> {
     #[must_use]
@@ -47,6 +166,54 @@ impl<Marker> Id<Marker> {
     pub fn snowflake(self) -> StellwerkSnowflake {
         self.0
     }
+
+    /// When the object was created, to the millisecond, which the snowflake encodes.
+    #[must_use]
+    pub fn created_at(self) -> UtcDateTime {
+        self.0.timestamp().into()
+    }
+}
+
+/// (De)serializes an [`Id`] as `id`, next to the time it was [created at](Id::created_at) as `created_at`,
+/// so that clients do not have to decode snowflakes. `created_at` is ignored when deserializing,
+/// since it follows from the id.
+///
+/// Use with `#[serde(flatten, with = "crate::model::id_with_created_at")]` on the `id` field.
+pub mod id_with_created_at {
+    use crate::{model::Id, util::rfc3339};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use time::UtcDateTime;
+
+    #[derive(Serialize)]
+    #[serde(bound = "")]
+    struct IdWithCreatedAt<'a, Marker> {
+        id: &'a Id<Marker>,
+        #[serde(with = "rfc3339")]
+        created_at: UtcDateTime,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(bound = "")]
+    struct OnlyId<Marker> {
+        id: Id<Marker>,
+    }
+
+    pub fn serialize<Marker, S: Serializer>(
+        id: &Id<Marker>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        IdWithCreatedAt {
+            id,
+            created_at: id.0.timestamp().into(),
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, Marker, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Id<Marker>, D::Error> {
+        OnlyId::deserialize(deserializer).map(|OnlyId { id }| id)
+    }
 }
 
 impl<Marker> Display for Id<Marker> {
@@ -0,0 +1,166 @@
+//! The audit log of everything moderators did, for admins to review.
+
+use crate::model::{
+    Id,
+    post::PostMarker,
+    report::{ReportAction, ReportMarker},
+    user::UserMarker,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+use thiserror::Error;
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
+pub struct ModerationLogMarker;
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationAction {
+    /// Assigned `report` to `target_user`, or unassigned it if that is `None`.
+    AssignReport,
+    /// Added a note to `report`.
+    AddNote,
+    /// Resolved `report` without consequences.
+    DismissReport,
+    /// Deleted `target_post` of `target_user`, because of `report` if given.
+    DeletePost,
+    /// Restored `target_post` of `target_user`.
+    RestorePost,
+    /// Warned `target_user` because of `report`.
+    WarnUser,
+    /// Suspended `target_user` because of `report`.
+    SuspendUser,
+    /// Shadowbanned `target_user`, because of `report` if given.
+    ShadowbanUser,
+    /// Lifted the shadowban of `target_user`.
+    UnshadowbanUser,
+    /// Got a token to act as `target_user` for support, see
+    /// [`Impersonation`](crate::model::admin::Impersonation).
+    ImpersonateUser,
+    /// Revoked the impersonation tokens of `target_user` before they expired.
+    EndImpersonation,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Error)]
+#[error("The moderation action is invalid: {0}")]
+pub struct InvalidModerationActionError(String);
+
+/// The time of the entry is that of its ID.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct ModerationLogEntry {
+    #[serde(flatten, with = "crate::model::id_with_created_at")]
+    pub id: Id<ModerationLogMarker>,
+    pub moderator: Id<UserMarker>,
+    pub action: ModerationAction,
+    pub report: Option<Id<ReportMarker>>,
+    pub target_user: Option<Id<UserMarker>>,
+    pub target_post: Option<Id<PostMarker>>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct CreateModerationLogEntry {
+    pub moderator: Id<UserMarker>,
+    pub action: ModerationAction,
+    pub report: Option<Id<ReportMarker>>,
+    pub target_user: Option<Id<UserMarker>>,
+    pub target_post: Option<Id<PostMarker>>,
+}
+
+impl CreateModerationLogEntry {
+    /// An entry referring to nothing yet.
+    #[must_use]
+    pub fn new(moderator: Id<UserMarker>, action: ModerationAction) -> Self {
+        Self {
+            moderator,
+            action,
+            report: None,
+            target_user: None,
+            target_post: None,
+        }
+    }
+
+    #[must_use]
+    pub fn with_report(self, report: Id<ReportMarker>) -> Self {
+        Self {
+            report: Some(report),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn with_user(self, user: Id<UserMarker>) -> Self {
+        Self {
+            target_user: Some(user),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn with_post(self, post: Id<PostMarker>) -> Self {
+        Self {
+            target_post: Some(post),
+            ..self
+        }
+    }
+}
+
+impl ModerationAction {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ModerationAction::AssignReport => "assign_report",
+            ModerationAction::AddNote => "add_note",
+            ModerationAction::DismissReport => "dismiss_report",
+            ModerationAction::DeletePost => "delete_post",
+            ModerationAction::RestorePost => "restore_post",
+            ModerationAction::WarnUser => "warn_user",
+            ModerationAction::SuspendUser => "suspend_user",
+            ModerationAction::ShadowbanUser => "shadowban_user",
+            ModerationAction::UnshadowbanUser => "unshadowban_user",
+            ModerationAction::ImpersonateUser => "impersonate_user",
+            ModerationAction::EndImpersonation => "end_impersonation",
+        }
+    }
+}
+
+impl From<ReportAction> for ModerationAction {
+    fn from(action: ReportAction) -> Self {
+        match action {
+            ReportAction::Dismiss => ModerationAction::DismissReport,
+            ReportAction::DeleteContent => ModerationAction::DeletePost,
+            ReportAction::Warn => ModerationAction::WarnUser,
+            ReportAction::Suspend => ModerationAction::SuspendUser,
+            ReportAction::Shadowban => ModerationAction::ShadowbanUser,
+        }
+    }
+}
+
+impl Display for ModerationAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ModerationAction {
+    type Err = InvalidModerationActionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "assign_report" => Ok(ModerationAction::AssignReport),
+            "add_note" => Ok(ModerationAction::AddNote),
+            "dismiss_report" => Ok(ModerationAction::DismissReport),
+            "delete_post" => Ok(ModerationAction::DeletePost),
+            "restore_post" => Ok(ModerationAction::RestorePost),
+            "warn_user" => Ok(ModerationAction::WarnUser),
+            "suspend_user" => Ok(ModerationAction::SuspendUser),
+            "shadowban_user" => Ok(ModerationAction::ShadowbanUser),
+            "unshadowban_user" => Ok(ModerationAction::UnshadowbanUser),
+            "impersonate_user" => Ok(ModerationAction::ImpersonateUser),
+            "end_impersonation" => Ok(ModerationAction::EndImpersonation),
+            _ => Err(InvalidModerationActionError(s.to_owned())),
+        }
+    }
+}
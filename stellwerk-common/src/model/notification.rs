@@ -0,0 +1,95 @@
+use crate::model::{
+    Id,
+    post::PostMarker,
+    user::{User, UserMarker},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+use thiserror::Error;
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
+pub struct NotificationMarker;
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// `actor` followed the user.
+    Follow,
+    /// `actor` mentioned the user in `post`.
+    Mention,
+    /// `actor` liked the user's `post`.
+    Like,
+    /// `actor` shared the user's `post`.
+    Announce,
+    /// A moderator warned the user, about `post` if given.
+    /// `actor` is the user themselves, so that moderators stay anonymous.
+    Warning,
+    /// An admin acted as the user for support, and the impersonation expired.
+    /// `actor` is the user themselves, so that admins stay anonymous.
+    Impersonation,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Error)]
+#[error("The notification kind is invalid: {0}")]
+pub struct InvalidNotificationKindError(String);
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct Notification {
+    pub id: Id<NotificationMarker>,
+    pub kind: NotificationKind,
+    pub actor: User,
+    pub post: Option<Id<PostMarker>>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct CreateNotification {
+    /// The notified user.
+    pub user: Id<UserMarker>,
+    pub kind: NotificationKind,
+    pub actor: Id<UserMarker>,
+    pub post: Option<Id<PostMarker>>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct UnreadNotificationCount {
+    pub count: u64,
+}
+
+impl NotificationKind {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NotificationKind::Follow => "follow",
+            NotificationKind::Mention => "mention",
+            NotificationKind::Like => "like",
+            NotificationKind::Announce => "announce",
+            NotificationKind::Warning => "warning",
+            NotificationKind::Impersonation => "impersonation",
+        }
+    }
+}
+
+impl Display for NotificationKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for NotificationKind {
+    type Err = InvalidNotificationKindError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "follow" => Ok(NotificationKind::Follow),
+            "mention" => Ok(NotificationKind::Mention),
+            "like" => Ok(NotificationKind::Like),
+            "announce" => Ok(NotificationKind::Announce),
+            "warning" => Ok(NotificationKind::Warning),
+            "impersonation" => Ok(NotificationKind::Impersonation),
+            _ => Err(InvalidNotificationKindError(s.to_owned())),
+        }
+    }
+}
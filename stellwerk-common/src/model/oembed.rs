@@ -0,0 +1,155 @@
+//! Responses of the [oEmbed](https://oembed.com) provider endpoint.
+
+use crate::{
+    model::{
+        Id,
+        post::{Post, PostMarker},
+    },
+    text::{absolute_links, escape_html},
+};
+use serde::{Deserialize, Serialize};
+
+pub const OEMBED_VERSION: &str = "1.0";
+pub const OEMBED_DEFAULT_WIDTH: u32 = 500;
+
+/// An oEmbed response of type `rich`.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct OEmbed {
+    pub version: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub provider_name: String,
+    pub provider_url: String,
+    pub author_name: String,
+    pub author_url: String,
+    pub url: String,
+    pub html: String,
+    pub width: u32,
+    /// The height depends on the content, so it is left to the consumer.
+    pub height: Option<u32>,
+}
+
+impl OEmbed {
+    /// Builds the embed of `post`. `public_url` must not have a trailing slash.
+    #[must_use]
+    pub fn for_post(post: &Post, public_url: &str, max_width: Option<u32>) -> Self {
+        let url = post_url(public_url, post.id);
+        let author_name = format!("@{}", post.author.handle.get());
+        let author_url = format!("{public_url}/{author_name}");
+
+        let html = format!(
+            "<blockquote class=\"stellwerk-embed\" cite=\"{url}\">{content}\
+            <footer>&mdash; <a href=\"{author_url}\">{author}</a> \
+            (<a href=\"{url}\">{url}</a>)</footer></blockquote>",
+            url = escape_html(&url),
+            content = absolute_links(&post.content_html, public_url),
+            author_url = escape_html(&author_url),
+            author = escape_html(&author_name),
+        );
+
+        Self {
+            version: OEMBED_VERSION.to_owned(),
+            kind: "rich".to_owned(),
+            provider_name: "Stellwerk".to_owned(),
+            provider_url: public_url.to_owned(),
+            author_name,
+            author_url,
+            url,
+            html,
+            width: max_width.map_or(OEMBED_DEFAULT_WIDTH, |max_width| {
+                max_width.min(OEMBED_DEFAULT_WIDTH)
+            }),
+            height: None,
+        }
+    }
+}
+
+/// The public URL of a post.
+#[must_use]
+pub fn post_url(public_url: &str, id: Id<PostMarker>) -> String {
+    format!("{public_url}/posts/{id}")
+}
+
+/// Extracts the post id from a URL as returned by [`post_url`].
+/// Query and fragment are ignored, and `http` and `https` are treated the same.
+#[must_use]
+pub fn parse_post_url(public_url: &str, url: &str) -> Option<Id<PostMarker>> {
+    fn strip_scheme(url: &str) -> &str {
+        url.strip_prefix("https://")
+            .or_else(|| url.strip_prefix("http://"))
+            .unwrap_or(url)
+    }
+
+    let url = url.split(['?', '#']).next()?;
+    let path = strip_scheme(url).strip_prefix(strip_scheme(public_url))?;
+    let id = path.strip_prefix("/posts/")?.trim_end_matches('/');
+
+    id.parse::<u64>().ok().map(Id::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{
+        Id,
+        oembed::{OEmbed, parse_post_url},
+        post::{Post, PostContent},
+        user::{User, UserHandle},
+    };
+
+    #[test]
+    fn post_urls() {
+        let public_url = "https://stellwerk.example";
+
+        assert_eq!(
+            parse_post_url(public_url, "https://stellwerk.example/posts/123"),
+            Some(Id::from(123))
+        );
+        assert_eq!(
+            parse_post_url(public_url, "http://stellwerk.example/posts/123/?ref=x#top"),
+            Some(Id::from(123))
+        );
+        assert_eq!(
+            parse_post_url(public_url, "https://other.example/posts/123"),
+            None
+        );
+        assert_eq!(
+            parse_post_url(public_url, "https://stellwerk.example/users/123"),
+            None
+        );
+        assert_eq!(
+            parse_post_url(public_url, "https://stellwerk.example/posts/abc"),
+            None
+        );
+    }
+
+    #[test]
+    fn post_embed() {
+        let content = PostContent::new("<hi> #tag".to_owned()).unwrap();
+        let post = Post {
+            id: Id::from(7),
+            author: User {
+                id: Id::from(1),
+                handle: UserHandle::new("alice".to_owned()).unwrap(),
+                verified: false,
+            },
+            content_html: content.render_html(),
+            content,
+            pinned: false,
+            filtered: Vec::new(),
+        };
+
+        let embed = OEmbed::for_post(&post, "https://stellwerk.example", Some(300));
+
+        assert_eq!(embed.author_name, "@alice");
+        assert_eq!(embed.author_url, "https://stellwerk.example/@alice");
+        assert_eq!(embed.width, 300);
+        assert_eq!(
+            embed.html,
+            "<blockquote class=\"stellwerk-embed\" cite=\"https://stellwerk.example/posts/7\">\
+            <p>&lt;hi&gt; <a href=\"https://stellwerk.example/tags/tag\" class=\"hashtag\">#tag</a></p>\
+            <footer>&mdash; <a href=\"https://stellwerk.example/@alice\">@alice</a> \
+            (<a href=\"https://stellwerk.example/posts/7\">https://stellwerk.example/posts/7</a>)\
+            </footer></blockquote>"
+        );
+    }
+}
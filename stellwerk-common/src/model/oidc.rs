@@ -0,0 +1,259 @@
+//! Signing in with an external identity provider, like Google or GitHub.
+//!
+//! A login is started with [`StartOidcLogin`], which returns the URL of the provider to send the
+//! browser to. The provider redirects back to the client with a code and the [`OidcToken`]
+//! state, which the client passes on as [`OidcCallback`] to get an auth token.
+//! Identities are linked to one user each, and unknown identities get a new account if the
+//! registrations of the tenant are open.
+
+use crate::{
+    model::{
+        Id,
+        tenant::TenantId,
+        user::{User, UserHandle, UserMarker},
+    },
+    util::rfc3339,
+};
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use rsa::sha2::{Digest, Sha256};
+use serde::{Deserialize, Deserializer, Serialize, de::Error as _};
+use std::{
+    fmt::{Debug, Display, Formatter},
+    str::FromStr,
+};
+use thiserror::Error;
+use time::UtcDateTime;
+
+const OIDC_TOKEN_LEN: usize = 32;
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OidcProvider {
+    Google,
+    /// Not an OpenID Connect provider, but signs in the same way.
+    Github,
+    /// Any OpenID Connect provider configured by its issuer URL.
+    Custom,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Error)]
+#[error("The identity provider is invalid: {0}")]
+pub struct InvalidOidcProviderError(String);
+
+/// A provider users can sign in with, to show as a button.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct OidcProviderInfo {
+    pub provider: OidcProvider,
+    pub name: String,
+}
+
+/// Starts signing in, or linking the identity to the authenticated user.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct StartOidcLogin {
+    /// The handle of the account created if the identity is unknown.
+    /// Defaults to the username at the provider.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handle: Option<UserHandle>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct OidcAuthorization {
+    /// Where to send the browser to sign in at the provider.
+    pub authorization_url: String,
+}
+
+/// The query parameters the provider redirected back with.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct OidcCallback {
+    pub state: OidcToken,
+    pub code: String,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OidcOutcome {
+    /// The identity was linked to the user before.
+    LoggedIn,
+    /// The identity was linked to the user who started the login.
+    Linked,
+    /// An account was created for the identity.
+    Registered,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct OidcLogin {
+    pub outcome: OidcOutcome,
+    pub user: User,
+    /// A new auth token of the user, unless the identity was [linked](OidcOutcome::Linked)
+    /// by an already authenticated user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+/// An identity linked to the authenticated user.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct OidcIdentity {
+    pub provider: OidcProvider,
+    #[serde(with = "rfc3339")]
+    pub linked_at: UtcDateTime,
+}
+
+/// A started login, until the provider redirects back or it expires.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct PendingOidcLogin {
+    pub tenant: TenantId,
+    pub provider: OidcProvider,
+    /// Ties the ID token to this login.
+    pub nonce: OidcToken,
+    /// The PKCE code verifier, which the provider checks against the challenge.
+    pub code_verifier: OidcToken,
+    pub handle: Option<UserHandle>,
+    /// The user to link the identity to, if they were authenticated when starting the login.
+    pub link_user: Option<Id<UserMarker>>,
+}
+
+/// A random, URL-safe value used as state, nonce, or PKCE code verifier of a login.
+/// Only valid for one login, so it is stored as is.
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct OidcToken(pub [u8; OIDC_TOKEN_LEN]);
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Error)]
+#[error("The login state is invalid")]
+pub struct InvalidOidcTokenError;
+
+impl OidcProvider {
+    pub const ALL: [OidcProvider; 3] = [
+        OidcProvider::Google,
+        OidcProvider::Github,
+        OidcProvider::Custom,
+    ];
+
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OidcProvider::Google => "google",
+            OidcProvider::Github => "github",
+            OidcProvider::Custom => "custom",
+        }
+    }
+}
+
+impl Display for OidcProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for OidcProvider {
+    type Err = InvalidOidcProviderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "google" => Ok(OidcProvider::Google),
+            "github" => Ok(OidcProvider::Github),
+            "custom" => Ok(OidcProvider::Custom),
+            _ => Err(InvalidOidcProviderError(s.to_owned())),
+        }
+    }
+}
+
+impl OidcToken {
+    #[must_use]
+    pub fn generate_random() -> Self {
+        Self(rand::random())
+    }
+
+    #[must_use]
+    pub fn as_token_str(&self) -> String {
+        BASE64_URL_SAFE_NO_PAD.encode(self.0)
+    }
+
+    /// The S256 code challenge of this token as PKCE code verifier.
+    #[must_use]
+    pub fn code_challenge(&self) -> String {
+        BASE64_URL_SAFE_NO_PAD.encode(Sha256::digest(self.as_token_str()))
+    }
+}
+
+impl FromStr for OidcToken {
+    type Err = InvalidOidcTokenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BASE64_URL_SAFE_NO_PAD
+            .decode(s)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(Self)
+            .ok_or(InvalidOidcTokenError)
+    }
+}
+
+impl TryFrom<Box<[u8]>> for OidcToken {
+    type Error = InvalidOidcTokenError;
+
+    fn try_from(value: Box<[u8]>) -> Result<Self, Self::Error> {
+        <[u8; OIDC_TOKEN_LEN]>::try_from(value.as_ref())
+            .map(Self)
+            .map_err(|_| InvalidOidcTokenError)
+    }
+}
+
+impl Debug for OidcToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("OidcToken").field(&"<redacted>").finish()
+    }
+}
+
+impl Serialize for OidcToken {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.as_token_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for OidcToken {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::oidc::{OidcProvider, OidcToken};
+
+    #[test]
+    fn oidc_provider_round_trip() {
+        for provider in OidcProvider::ALL {
+            assert_eq!(provider.as_str().parse(), Ok(provider));
+        }
+        assert!("facebook".parse::<OidcProvider>().is_err());
+    }
+
+    #[test]
+    fn oidc_token_round_trip() {
+        let token = OidcToken::generate_random();
+
+        assert_eq!(token.as_token_str().parse(), Ok(token));
+        assert!("too-short".parse::<OidcToken>().is_err());
+    }
+
+    #[test]
+    fn code_challenge() {
+        // The example of RFC 7636, appendix B.
+        let verifier: OidcToken = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            verifier.code_challenge(),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+}
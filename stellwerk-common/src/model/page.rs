@@ -0,0 +1,262 @@
+//! Minimal server-rendered HTML pages of profiles and posts, with
+//! [OpenGraph](https://ogp.me) and Twitter card meta tags, so that shared links unfurl.
+
+use crate::{
+    model::{
+        Id,
+        oembed::post_url,
+        post::{Post, PostMarker},
+        user::{UserHandle, UserProfile},
+    },
+    text::{absolute_links, escape_html, html_to_text},
+};
+use std::fmt::Write;
+
+pub const SITE_NAME: &str = "Stellwerk";
+/// In characters. Longer descriptions are cut off with an ellipsis.
+pub const DESCRIPTION_MAX_LEN: usize = 200;
+
+/// A page with its meta tags, rendered with [`Page::render`].
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Page {
+    pub title: String,
+    /// Plain text.
+    pub description: String,
+    /// The canonical URL of the page.
+    pub url: String,
+    /// The `OpenGraph` type, like `article` or `profile`.
+    pub kind: &'static str,
+    /// The ActivityPub representation, linked for discovery.
+    pub activity_url: String,
+    /// The oEmbed endpoint of the page, if it can be embedded.
+    pub oembed_url: Option<String>,
+    /// Trusted HTML.
+    pub body: String,
+}
+
+impl Page {
+    /// `public_url` must not have a trailing slash.
+    #[must_use]
+    pub fn for_profile(profile: &UserProfile, public_url: &str) -> Self {
+        let handle = format!("@{}", profile.user.handle.get());
+        let stats = profile.stats;
+        let description = format!(
+            "{} posts, {} followers, {} following",
+            stats.post_count, stats.follower_count, stats.following_count
+        );
+
+        let badge = if profile.user.verified {
+            " <span class=\"verified\" title=\"Verified\">✓</span>"
+        } else {
+            ""
+        };
+        // With rel="me", so that the website can verify the profile the other way around.
+        let website = profile
+            .website
+            .as_ref()
+            .map_or_else(String::new, |website| {
+                let url = escape_html(website.get());
+                format!("<p><a href=\"{url}\" rel=\"me nofollow noopener\">{url}</a></p>")
+            });
+
+        Self {
+            body: format!(
+                "<header><h1>{handle}{badge}</h1><p>{description}</p>{website}</header>",
+                handle = escape_html(&handle),
+                description = escape_html(&description),
+            ),
+            title: handle,
+            description,
+            url: profile_page_url(public_url, &profile.user.handle),
+            kind: "profile",
+            activity_url: format!("{public_url}/users/{}", profile.user.id),
+            oembed_url: None,
+        }
+    }
+
+    /// `public_url` must not have a trailing slash.
+    #[must_use]
+    pub fn for_post(post: &Post, public_url: &str) -> Self {
+        let handle = format!("@{}", post.author.handle.get());
+        let profile_url = profile_page_url(public_url, &post.author.handle);
+        let activity_url = post_url(public_url, post.id);
+
+        Self {
+            body: format!(
+                "<article><header><a href=\"{profile_url}\">{handle}</a></header>\
+                {content}</article>",
+                profile_url = escape_html(&profile_url),
+                handle = escape_html(&handle),
+                content = absolute_links(&post.content_html, public_url),
+            ),
+            title: handle,
+            description: truncate(&html_to_text(&post.content_html)),
+            url: post_page_url(public_url, &post.author.handle, post.id),
+            kind: "article",
+            oembed_url: Some(format!(
+                "{public_url}/oembed?url={}",
+                form_urlencoded::byte_serialize(activity_url.as_bytes()).collect::<String>()
+            )),
+            activity_url,
+        }
+    }
+
+    #[must_use]
+    pub fn render(&self) -> String {
+        let title = escape_html(&self.title);
+        let description = escape_html(&self.description);
+        let url = escape_html(&self.url);
+
+        let mut head = String::new();
+        for (property, content) in [
+            ("og:site_name", SITE_NAME),
+            ("og:type", self.kind),
+            ("og:title", &title),
+            ("og:description", &description),
+            ("og:url", &url),
+        ] {
+            write!(head, "<meta property=\"{property}\" content=\"{content}\">")
+                .expect("Writing to String cannot fail");
+        }
+        for (name, content) in [
+            ("twitter:card", "summary"),
+            ("twitter:title", &title),
+            ("twitter:description", &description),
+        ] {
+            write!(head, "<meta name=\"{name}\" content=\"{content}\">")
+                .expect("Writing to String cannot fail");
+        }
+        write!(
+            head,
+            "<link rel=\"alternate\" type=\"application/activity+json\" href=\"{}\">",
+            escape_html(&self.activity_url)
+        )
+        .expect("Writing to String cannot fail");
+        if let Some(oembed_url) = &self.oembed_url {
+            write!(
+                head,
+                "<link rel=\"alternate\" type=\"application/json+oembed\" href=\"{}\">",
+                escape_html(oembed_url)
+            )
+            .expect("Writing to String cannot fail");
+        }
+
+        format!(
+            "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+            <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+            <title>{title} - {SITE_NAME}</title>\
+            <meta name=\"description\" content=\"{description}\">\
+            <link rel=\"canonical\" href=\"{url}\">{head}</head>\
+            <body>{body}</body></html>",
+            body = self.body,
+        )
+    }
+}
+
+/// The URL of the page of a profile, like `https://stellwerk.example/@alice`.
+#[must_use]
+pub fn profile_page_url(public_url: &str, handle: &UserHandle) -> String {
+    format!("{public_url}/@{}", handle.get())
+}
+
+/// The URL of the page of a post, like `https://stellwerk.example/@alice/123`.
+#[must_use]
+pub fn post_page_url(public_url: &str, author: &UserHandle, id: Id<PostMarker>) -> String {
+    format!("{}/{id}", profile_page_url(public_url, author))
+}
+
+fn truncate(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match text.char_indices().nth(DESCRIPTION_MAX_LEN - 1) {
+        Some((end, _)) => format!("{}…", text[..end].trim_end()),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{
+        Id,
+        page::{DESCRIPTION_MAX_LEN, Page},
+        post::{Post, PostContent},
+        user::{ProfileWebsite, User, UserHandle, UserProfile, UserStats},
+    };
+
+    fn post(content: &str) -> Post {
+        let content = PostContent::new(content.to_owned()).unwrap();
+        Post {
+            id: Id::from(7),
+            author: User {
+                id: Id::from(1),
+                handle: UserHandle::new("alice".to_owned()).unwrap(),
+                verified: false,
+            },
+            content_html: content.render_html(),
+            content,
+            pinned: false,
+            filtered: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn post_page() {
+        let page = Page::for_post(&post("<hi> #tag"), "https://stellwerk.example");
+
+        assert_eq!(page.url, "https://stellwerk.example/@alice/7");
+        assert_eq!(page.description, "<hi> #tag");
+        assert_eq!(
+            page.oembed_url.as_deref(),
+            Some(
+                "https://stellwerk.example/oembed?url=https%3A%2F%2Fstellwerk.example%2Fposts%2F7"
+            )
+        );
+
+        let html = page.render();
+        assert!(html.contains("<meta property=\"og:description\" content=\"&lt;hi&gt; #tag\">"));
+        assert!(html.contains("<meta name=\"twitter:title\" content=\"@alice\">"));
+        assert!(
+            html.contains(
+                "<a href=\"https://stellwerk.example/tags/tag\" class=\"hashtag\">#tag</a>"
+            )
+        );
+        assert!(!html.contains("<hi>"));
+    }
+
+    #[test]
+    fn long_descriptions_are_truncated() {
+        let page = Page::for_post(&post(&"a".repeat(300)), "https://stellwerk.example");
+
+        assert_eq!(page.description.chars().count(), DESCRIPTION_MAX_LEN);
+        assert!(page.description.ends_with('…'));
+    }
+
+    #[test]
+    fn profile_page() {
+        let profile = UserProfile {
+            user: User {
+                id: Id::from(1),
+                handle: UserHandle::new("alice".to_owned()).unwrap(),
+                verified: true,
+            },
+            stats: UserStats {
+                post_count: 3,
+                follower_count: 2,
+                following_count: 1,
+            },
+            website: Some(ProfileWebsite::new("https://alice.example/?a&b".to_owned()).unwrap()),
+        };
+        let page = Page::for_profile(&profile, "https://stellwerk.example");
+
+        assert_eq!(page.url, "https://stellwerk.example/@alice");
+        assert_eq!(page.activity_url, "https://stellwerk.example/users/1");
+        assert_eq!(page.description, "3 posts, 2 followers, 1 following");
+        let html = page.render();
+        assert!(html.contains("<meta property=\"og:type\" content=\"profile\">"));
+        assert!(html.contains("<span class=\"verified\" title=\"Verified\">✓</span>"));
+        assert!(
+            html.contains(
+                "<a href=\"https://alice.example/?a&amp;b\" rel=\"me nofollow noopener\">"
+            )
+        );
+    }
+}
@@ -0,0 +1,227 @@
+//! Pages of items ordered by id, newest first, in the format shared by all paginated routes and their clients.
+
+use crate::model::Id;
+use base64::{Engine, prelude::BASE64_URL_SAFE_NO_PAD};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error as _};
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+use thiserror::Error;
+
+pub const DEFAULT_LIMIT: u32 = 20;
+pub const MAX_LIMIT: u32 = 40;
+
+const OLDER_TAG: u8 = 0;
+const NEWER_TAG: u8 = 1;
+
+/// Where a page continues from. Serialized as an opaque string, which clients only pass on.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Cursor<Marker> {
+    /// The items older than the id.
+    Older(Id<Marker>),
+    /// The items newer than the id.
+    Newer(Id<Marker>),
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Error)]
+#[error("The cursor is invalid")]
+pub struct InvalidCursorError;
+
+/// The query parameters of a page. Deserializing also accepts a `cursor`,
+/// which takes precedence over `max_id` and `since_id`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+#[serde(bound = "", from = "RawPageRequest<Marker>")]
+pub struct PageRequest<Marker> {
+    /// Defaults to [`DEFAULT_LIMIT`], capped at [`MAX_LIMIT`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// Only items older than this.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_id: Option<Id<Marker>>,
+    /// Only items newer than this.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since_id: Option<Id<Marker>>,
+}
+
+#[derive(Deserialize)]
+#[serde(bound = "")]
+struct RawPageRequest<Marker> {
+    limit: Option<u32>,
+    max_id: Option<Id<Marker>>,
+    since_id: Option<Id<Marker>>,
+    cursor: Option<Cursor<Marker>>,
+}
+
+/// A page of items with the cursor of the next, older, page.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+#[serde(bound(serialize = "T: Serialize", deserialize = "T: Deserialize<'de>"))]
+pub struct Page<T, Marker> {
+    pub items: Vec<T>,
+    /// `None` if this is the last page.
+    pub next_cursor: Option<Cursor<Marker>>,
+}
+
+/// The cursor of the page after the one with `ids`, in order, if it was full.
+/// A shorter page is the last one.
+#[must_use]
+pub fn next_cursor<Marker>(limit: u32, ids: &[Id<Marker>]) -> Option<Cursor<Marker>> {
+    if ids.len() >= limit as usize {
+        ids.last().map(|oldest| Cursor::Older(Id::new(oldest.0)))
+    } else {
+        None
+    }
+}
+
+impl<Marker> PageRequest<Marker> {
+    #[must_use]
+    pub fn limit(&self) -> u32 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+}
+
+impl<Marker> From<RawPageRequest<Marker>> for PageRequest<Marker> {
+    fn from(raw: RawPageRequest<Marker>) -> Self {
+        let (max_id, since_id) = match raw.cursor {
+            Some(Cursor::Older(id)) => (Some(id), None),
+            Some(Cursor::Newer(id)) => (None, Some(id)),
+            None => (raw.max_id, raw.since_id),
+        };
+
+        Self {
+            limit: raw.limit,
+            max_id,
+            since_id,
+        }
+    }
+}
+
+impl<Marker> From<Cursor<Marker>> for PageRequest<Marker> {
+    fn from(cursor: Cursor<Marker>) -> Self {
+        RawPageRequest {
+            limit: None,
+            max_id: None,
+            since_id: None,
+            cursor: Some(cursor),
+        }
+        .into()
+    }
+}
+
+impl<T, Marker> Page<T, Marker> {
+    /// The page of `items`, requested with `limit`, whose ids are given by `id`.
+    pub fn new(items: Vec<T>, limit: u32, id: impl Fn(&T) -> Id<Marker>) -> Self {
+        let ids: Vec<_> = items.iter().map(id).collect();
+        let next_cursor = next_cursor(limit, &ids);
+
+        Self { items, next_cursor }
+    }
+}
+
+impl<Marker> Display for Cursor<Marker> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (tag, id) = match self {
+            Cursor::Older(id) => (OLDER_TAG, id),
+            Cursor::Newer(id) => (NEWER_TAG, id),
+        };
+        let mut bytes = [tag; 9];
+        bytes[1..].copy_from_slice(&id.0.get().to_be_bytes());
+
+        f.write_str(&BASE64_URL_SAFE_NO_PAD.encode(bytes))
+    }
+}
+
+impl<Marker> FromStr for Cursor<Marker> {
+    type Err = InvalidCursorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = BASE64_URL_SAFE_NO_PAD
+            .decode(s)
+            .map_err(|_| InvalidCursorError)?;
+        let [tag, id @ ..] = <[u8; 9]>::try_from(bytes).map_err(|_| InvalidCursorError)?;
+        let id = Id::from(u64::from_be_bytes(id));
+
+        match tag {
+            OLDER_TAG => Ok(Cursor::Older(id)),
+            NEWER_TAG => Ok(Cursor::Newer(id)),
+            _ => Err(InvalidCursorError),
+        }
+    }
+}
+
+impl<Marker> Serialize for Cursor<Marker> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de, Marker> Deserialize<'de> for Cursor<Marker> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{
+        Id,
+        pagination::{Cursor, InvalidCursorError, MAX_LIMIT, Page, PageRequest},
+        post::PostMarker,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn cursor_round_trip() {
+        let id = Id::<PostMarker>::from(236_513_256_749_924_352);
+        for cursor in [Cursor::Older(id), Cursor::Newer(id)] {
+            let encoded = cursor.to_string();
+            assert_eq!(encoded.len(), 12);
+            assert_eq!(encoded.parse(), Ok(cursor));
+        }
+
+        assert_eq!(
+            "not a cursor".parse::<Cursor<PostMarker>>(),
+            Err(InvalidCursorError)
+        );
+        assert_eq!(
+            "AgAAAAAAAAAB".parse::<Cursor<PostMarker>>(),
+            Err(InvalidCursorError)
+        );
+    }
+
+    #[test]
+    fn page_request_cursor() {
+        let cursor = Cursor::<PostMarker>::Newer(5.into());
+        let request: PageRequest<PostMarker> = serde_json::from_value(json!({
+            "limit": 100,
+            "max_id": "7",
+            "cursor": cursor,
+        }))
+        .unwrap();
+
+        assert_eq!(request.limit(), MAX_LIMIT);
+        assert_eq!(request.max_id, None);
+        assert_eq!(request.since_id, Some(5.into()));
+        assert_eq!(
+            PageRequest::from(cursor),
+            PageRequest {
+                limit: None,
+                max_id: None,
+                since_id: Some(5.into()),
+            }
+        );
+    }
+
+    #[test]
+    fn page_next_cursor() {
+        let ids: Vec<Id<PostMarker>> = vec![3.into(), 2.into(), 1.into()];
+
+        let full = Page::new(ids.clone(), 3, |id| *id);
+        assert_eq!(full.next_cursor, Some(Cursor::Older(1.into())));
+
+        let last = Page::new(ids, 4, |id| *id);
+        assert_eq!(last.next_cursor, None);
+    }
+}
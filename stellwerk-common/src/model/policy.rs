@@ -0,0 +1,152 @@
+use crate::util::rfc3339;
+use serde::{Deserialize, Deserializer, Serialize, de::Error as _};
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+use thiserror::Error;
+use time::UtcDateTime;
+
+pub const POLICY_CONTENT_MAX_LEN: usize = 100_000;
+
+/// A policy of the instance that users have to accept.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyKind {
+    TermsOfService,
+    Privacy,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Error)]
+#[error("The policy kind is invalid: {0}")]
+pub struct InvalidPolicyKindError(String);
+
+/// Non-empty, trimmed.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Serialize)]
+#[serde(transparent)]
+pub struct PolicyContent(String);
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Error)]
+pub enum InvalidPolicyContentError {
+    #[error("The policy content is empty")]
+    Empty,
+    #[error("The policy content is longer than {POLICY_CONTENT_MAX_LEN} characters")]
+    TooLong,
+}
+
+/// One version of a policy. Versions of each kind count up from 1 and are never changed.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct Policy {
+    pub kind: PolicyKind,
+    pub version: u64,
+    pub content: PolicyContent,
+    #[serde(with = "rfc3339")]
+    pub published_at: UtcDateTime,
+}
+
+/// The body of publishing a new version of a policy.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct PublishPolicy {
+    pub content: PolicyContent,
+}
+
+/// The body of accepting a policy. Only the current version can be accepted,
+/// so that users cannot accept a version they have not seen.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct AcceptPolicy {
+    pub version: u64,
+}
+
+impl PolicyKind {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PolicyKind::TermsOfService => "terms_of_service",
+            PolicyKind::Privacy => "privacy",
+        }
+    }
+}
+
+impl Display for PolicyKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PolicyKind {
+    type Err = InvalidPolicyKindError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "terms_of_service" => Ok(PolicyKind::TermsOfService),
+            "privacy" => Ok(PolicyKind::Privacy),
+            _ => Err(InvalidPolicyKindError(s.to_owned())),
+        }
+    }
+}
+
+impl PolicyContent {
+    /// Trims the content.
+    pub fn new(content: String) -> Result<Self, InvalidPolicyContentError> {
+        let trimmed = content.trim();
+        if trimmed.is_empty() {
+            return Err(InvalidPolicyContentError::Empty);
+        }
+        if trimmed.chars().count() > POLICY_CONTENT_MAX_LEN {
+            return Err(InvalidPolicyContentError::TooLong);
+        }
+
+        if trimmed.len() == content.len() {
+            Ok(Self(content))
+        } else {
+            Ok(Self(trimmed.to_owned()))
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self) -> &str {
+        &self.0
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for PolicyContent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let inner = String::deserialize(deserializer)?;
+        Self::new(inner).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::policy::{InvalidPolicyContentError, PolicyContent, PolicyKind};
+    use serde_json::json;
+
+    #[test]
+    fn policy_kind_round_trip() {
+        for kind in [PolicyKind::TermsOfService, PolicyKind::Privacy] {
+            assert_eq!(kind.as_str().parse(), Ok(kind));
+            assert_eq!(serde_json::to_value(kind).unwrap(), json!(kind.as_str()));
+        }
+        assert!("tos".parse::<PolicyKind>().is_err());
+    }
+
+    #[test]
+    fn policy_content_validation() {
+        assert_eq!(
+            PolicyContent::new("\n# Rules\n".to_owned()).unwrap().get(),
+            "# Rules"
+        );
+        assert_eq!(
+            PolicyContent::new("  ".to_owned()),
+            Err(InvalidPolicyContentError::Empty)
+        );
+    }
+}
@@ -1,27 +1,293 @@
-use crate::model::{
-    Id,
-    user::{User, UserMarker},
+use crate::{
+    model::{
+        Id,
+        filter::FilterMatch,
+        user::{User, UserMarker},
+    },
+    text,
+    util::rfc3339,
 };
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, de::Error};
+use thiserror::Error;
+use time::UtcDateTime;
+
+/// Upper bound for the configurable post length limit.
+/// Content longer than this is never accepted, regardless of instance configuration.
+pub const POST_CONTENT_MAX_LEN: usize = 10_000;
+pub const POST_CONTENT_DEFAULT_MAX_LEN: usize = 2_000;
 
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
 pub struct PostMarker;
 
 #[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
 pub struct Post {
+    #[serde(flatten, with = "crate::model::id_with_created_at")]
     pub id: Id<PostMarker>,
     pub author: User,
-    pub content: String,
+    pub content: PostContent,
+    /// Sanitized HTML rendering of `content`.
+    pub content_html: String,
+    /// Whether the post is pinned to its author's profile.
+    pub pinned: bool,
+    /// The requesting user's warning filters that matched this post.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub filtered: Vec<FilterMatch>,
+}
+
+/// A post as moderators see it, which includes deleted posts until they are purged.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct ModeratedPost {
+    #[serde(flatten)]
+    pub post: Post,
+    #[serde(with = "rfc3339::option")]
+    pub deleted_at: Option<UtcDateTime>,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
 pub struct PartialPost {
+    #[serde(flatten, with = "crate::model::id_with_created_at")]
     pub id: Id<PostMarker>,
-    pub content: String,
+    pub content: PostContent,
+    /// Sanitized HTML rendering of `content`.
+    pub content_html: String,
+    /// Whether the post is pinned to its author's profile.
+    pub pinned: bool,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
 pub struct CreatePost {
     pub author: Id<UserMarker>,
-    pub content: String,
+    pub content: PostContent,
+}
+
+/// Only shown to the author of the post.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct PostStats {
+    /// Approximate, since views are counted once per viewer and day, and a few minutes late.
+    pub views: u64,
+}
+
+/// Counters that increase whenever the representation of a post changes.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
+pub struct PostVersion {
+    pub post: u64,
+    /// The author is part of the representation of the post.
+    pub author: u64,
+}
+
+/// Post content with leading and trailing whitespace removed.
+/// It is never empty and never longer than [`POST_CONTENT_MAX_LEN`] characters.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Serialize)]
+#[serde(transparent)]
+pub struct PostContent(String);
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Error)]
+pub enum InvalidPostContentError {
+    #[error("The post content is empty")]
+    Empty,
+    #[error("The post content is {len} characters long, but at most {max_len} are allowed")]
+    TooLong { len: usize, max_len: usize },
+}
+
+impl CreatePost {
+    /// Validates `content` against [`POST_CONTENT_MAX_LEN`].
+    /// Instances may enforce a stricter limit, see [`CreatePost::with_max_len`].
+    pub fn new(
+        author: Id<UserMarker>,
+        content: impl Into<String>,
+    ) -> Result<Self, InvalidPostContentError> {
+        Ok(Self {
+            author,
+            content: PostContent::new(content.into())?,
+        })
+    }
+
+    /// Like [`CreatePost::new`], with the maximum character count of the instance.
+    pub fn with_max_len(
+        author: Id<UserMarker>,
+        content: impl Into<String>,
+        max_len: usize,
+    ) -> Result<Self, InvalidPostContentError> {
+        Ok(Self {
+            author,
+            content: PostContent::with_max_len(content.into(), max_len)?,
+        })
+    }
+}
+
+impl PostContent {
+    pub fn new(content: String) -> Result<Self, InvalidPostContentError> {
+        Self::with_max_len(content, POST_CONTENT_MAX_LEN)
+    }
+
+    /// Like [`PostContent::new`], but with a custom maximum character count.
+    /// `max_len` is capped at [`POST_CONTENT_MAX_LEN`].
+    pub fn with_max_len(content: String, max_len: usize) -> Result<Self, InvalidPostContentError> {
+        let max_len = max_len.min(POST_CONTENT_MAX_LEN);
+
+        let trimmed = content.trim();
+        if trimmed.is_empty() {
+            return Err(InvalidPostContentError::Empty);
+        }
+
+        let len = trimmed.chars().count();
+        if len > max_len {
+            return Err(InvalidPostContentError::TooLong { len, max_len });
+        }
+
+        if trimmed.len() == content.len() {
+            Ok(Self(content))
+        } else {
+            Ok(Self(trimmed.to_owned()))
+        }
+    }
+
+    /// Checks the content against a limit that may be stricter than [`POST_CONTENT_MAX_LEN`].
+    pub fn check_max_len(&self, max_len: usize) -> Result<(), InvalidPostContentError> {
+        let len = self.0.chars().count();
+        if len > max_len {
+            Err(InvalidPostContentError::TooLong { len, max_len })
+        } else {
+            Ok(())
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self) -> &str {
+        &self.0
+    }
+
+    /// See [`text::render_html`].
+    #[must_use]
+    pub fn render_html(&self) -> String {
+        text::render_html(&self.0)
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for PostContent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let inner = String::deserialize(deserializer)?;
+        Self::new(inner).map_err(Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{
+        post::{
+            CreatePost, InvalidPostContentError, POST_CONTENT_MAX_LEN, PartialPost, Post,
+            PostContent,
+        },
+        user::{User, UserHandle},
+    };
+    use serde_json::json;
+
+    #[test]
+    fn create_post_validation() {
+        let create = CreatePost::new(1.into(), " hello ").unwrap();
+        assert_eq!(create.author, 1.into());
+        assert_eq!(create.content.get(), "hello");
+
+        assert_eq!(
+            CreatePost::new(1.into(), "\n"),
+            Err(InvalidPostContentError::Empty)
+        );
+        assert_eq!(
+            CreatePost::with_max_len(1.into(), "hello", 4),
+            Err(InvalidPostContentError::TooLong { len: 5, max_len: 4 })
+        );
+    }
+
+    #[test]
+    fn normalization() {
+        assert_eq!(
+            PostContent::new("  hello world\n".to_owned())
+                .unwrap()
+                .get(),
+            "hello world"
+        );
+        assert_eq!(
+            PostContent::new("hello\n\nworld".to_owned()).unwrap().get(),
+            "hello\n\nworld"
+        );
+    }
+
+    #[test]
+    fn rejections() {
+        assert_eq!(
+            PostContent::new(String::new()),
+            Err(InvalidPostContentError::Empty)
+        );
+        assert_eq!(
+            PostContent::new(" \n\t ".to_owned()),
+            Err(InvalidPostContentError::Empty)
+        );
+
+        assert!(PostContent::with_max_len("äöü".to_owned(), 3).is_ok());
+        assert_eq!(
+            PostContent::with_max_len("äöüß".to_owned(), 3),
+            Err(InvalidPostContentError::TooLong { len: 4, max_len: 3 })
+        );
+
+        let too_long = "a".repeat(POST_CONTENT_MAX_LEN + 1);
+        assert_eq!(
+            PostContent::with_max_len(too_long, usize::MAX),
+            Err(InvalidPostContentError::TooLong {
+                len: POST_CONTENT_MAX_LEN + 1,
+                max_len: POST_CONTENT_MAX_LEN
+            })
+        );
+    }
+
+    #[test]
+    fn created_at_serialization() {
+        let post = Post {
+            id: 236_513_256_749_924_352.into(),
+            author: User {
+                id: 1.into(),
+                handle: UserHandle::new("alice".to_owned()).unwrap(),
+                verified: false,
+            },
+            content: PostContent::new("hello".to_owned()).unwrap(),
+            content_html: "<p>hello</p>".to_owned(),
+            pinned: false,
+            filtered: Vec::new(),
+        };
+
+        let expected = json!({
+            "id": "236513256749924352",
+            "created_at": "2026-10-15T15:39:14.613Z",
+            "author": {
+                "id": "1",
+                "created_at": "2025-01-01T00:00:00Z",
+                "handle": "alice",
+                "verified": false,
+            },
+            "content": "hello",
+            "content_html": "<p>hello</p>",
+            "pinned": false,
+        });
+        assert_eq!(serde_json::to_value(&post).unwrap(), expected);
+        assert_eq!(serde_json::from_value::<Post>(expected).unwrap(), post);
+
+        let partial: PartialPost = serde_json::from_value(json!({
+            "id": "1",
+            "content": "hello",
+            "content_html": "<p>hello</p>",
+            "pinned": true,
+        }))
+        .unwrap();
+        assert_eq!(
+            serde_json::to_value(&partial).unwrap()["created_at"],
+            "2025-01-01T00:00:00Z"
+        );
+    }
 }
@@ -0,0 +1,318 @@
+//! Error responses as [problem details](https://www.rfc-editor.org/rfc/rfc9457),
+//! extended by a stable [`ErrorCode`] that clients can match on.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// The media type of [`Problem`] responses.
+pub const PROBLEM_JSON: &str = "application/problem+json";
+/// Problems are identified by their [`ErrorCode`], so the `type` member carries no information.
+pub const PROBLEM_TYPE: &str = "about:blank";
+
+/// What went wrong, independent of the human readable message.
+/// Codes are never renamed, new ones may be added.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    UnknownRoute,
+    InvalidPath,
+    InvalidFields,
+    InvalidJson,
+    InvalidQuery,
+    InvalidBody,
+    /// The body is well-formed, but fails validation. See [`Problem::errors`] for details.
+    ValidationFailed,
+    PayloadTooLarge,
+    RateLimited,
+    ReadOnly,
+    /// All database connections are in use, or a query took too long.
+    /// The request may be retried later.
+    DatabaseUnavailable,
+    /// The search engine could not be reached. The request may be retried later.
+    SearchUnavailable,
+    InternalError,
+    AuthenticationRequired,
+    InvalidAuthorizationHeader,
+    InvalidToken,
+    InsufficientRole,
+    /// A policy was updated and has to be accepted before anything else can be changed.
+    PolicyAcceptanceRequired,
+    /// Tokens of an impersonation cannot change how the user logs in or receives data.
+    ImpersonationNotAllowed,
+    InvalidSignature,
+    InvalidActivity,
+    ActorMismatch,
+    InvalidActor,
+    ActorFetchFailed,
+    PostNotFound,
+    UserNotFound,
+    ReportNotFound,
+    ConversationNotFound,
+    KeysNotFound,
+    FilterNotFound,
+    WebhookNotFound,
+    AnnouncementNotFound,
+    PolicyNotFound,
+    JobNotFound,
+    EmailDigestNotFound,
+    UnknownUnsubscribeToken,
+    UnknownOembedUrl,
+    UnsupportedOembedFormat,
+    InvalidLastEventId,
+    PublicTimelineDisabled,
+    MastodonApiDisabled,
+    NotConfigured,
+    NotPostAuthor,
+    NotConversationCreator,
+    PinnedPostLimitReached,
+    ConversationMemberLimitReached,
+    OneTimePrekeyLimitReached,
+    WebhookLimitReached,
+    SelfFollow,
+    HandleTaken,
+    CannotChangeOwnRole,
+    /// Only other local users without the moderator or admin role can be impersonated.
+    CannotImpersonate,
+    /// The report is assigned to another moderator, who can still reassign it.
+    ReportAlreadyClaimed,
+    /// The action only applies to reports about posts.
+    ReportWithoutPost,
+    /// Reports can only be assigned to moderators and admins.
+    AssigneeNotModerator,
+    /// Moderators and admins cannot be suspended.
+    CannotSuspend,
+    /// Moderators and admins cannot be shadowbanned.
+    CannotShadowban,
+    /// The record was updated since the version the request was based on.
+    /// See [`Problem::current_version`].
+    VersionConflict,
+    /// A content filter of the instance rejected the post.
+    /// See [`Problem::rule_category`].
+    ContentRejected,
+    /// The login at the identity provider was not started, already finished, or took too long.
+    OidcLoginExpired,
+    /// The identity provider could not be reached or rejected the login.
+    IdentityProviderFailed,
+    /// The identity is unknown and the instance does not let anyone create an account.
+    RegistrationsClosed,
+    /// No valid handle could be derived for the new account, so the login has to be started again
+    /// with one.
+    HandleRequired,
+    /// The identity, or another identity at the same provider, is linked to an account already.
+    IdentityAlreadyLinked,
+    /// A login linking an identity has to be finished by the user who started it.
+    OidcLinkUserMismatch,
+    OidcIdentityNotFound,
+    /// A daily quota of the user is used up. The `X-Quota-Reset` header tells when it resets.
+    QuotaExceeded,
+    /// Verifying the link back needs a website on the profile of the user.
+    WebsiteRequired,
+    /// The website of the user could not be fetched to verify the link back.
+    WebsiteUnreachable,
+    /// The website of the user does not link back to their profile with `rel="me"`.
+    LinkBackNotFound,
+}
+
+/// A single invalid field of the request body.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize, Serialize)]
+pub struct FieldError {
+    /// The path to the field, like `content` or `members[1]`.
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// The reason phrase of the status.
+    pub title: String,
+    pub status: u16,
+    /// Not given for server errors, to avoid leaking internals.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub code: ErrorCode,
+    /// A message for the `code` to show to users, in the language negotiated from `Accept-Language`.
+    /// Unlike `detail`, it is given for server errors too.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+    /// The `X-Request-Id` of the failed request, to be given when reporting the error.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// The version of the record for [`ErrorCode::VersionConflict`], to base the retry on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_version: Option<u64>,
+    /// The category of the filter rule for [`ErrorCode::ContentRejected`], like `blocked_pattern`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule_category: Option<String>,
+}
+
+impl ErrorCode {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::UnknownRoute => "unknown_route",
+            ErrorCode::InvalidPath => "invalid_path",
+            ErrorCode::InvalidFields => "invalid_fields",
+            ErrorCode::InvalidJson => "invalid_json",
+            ErrorCode::InvalidQuery => "invalid_query",
+            ErrorCode::InvalidBody => "invalid_body",
+            ErrorCode::ValidationFailed => "validation_failed",
+            ErrorCode::PayloadTooLarge => "payload_too_large",
+            ErrorCode::RateLimited => "rate_limited",
+            ErrorCode::ReadOnly => "read_only",
+            ErrorCode::DatabaseUnavailable => "database_unavailable",
+            ErrorCode::SearchUnavailable => "search_unavailable",
+            ErrorCode::InternalError => "internal_error",
+            ErrorCode::AuthenticationRequired => "authentication_required",
+            ErrorCode::InvalidAuthorizationHeader => "invalid_authorization_header",
+            ErrorCode::InvalidToken => "invalid_token",
+            ErrorCode::InsufficientRole => "insufficient_role",
+            ErrorCode::PolicyAcceptanceRequired => "policy_acceptance_required",
+            ErrorCode::ImpersonationNotAllowed => "impersonation_not_allowed",
+            ErrorCode::InvalidSignature => "invalid_signature",
+            ErrorCode::InvalidActivity => "invalid_activity",
+            ErrorCode::ActorMismatch => "actor_mismatch",
+            ErrorCode::InvalidActor => "invalid_actor",
+            ErrorCode::ActorFetchFailed => "actor_fetch_failed",
+            ErrorCode::PostNotFound => "post_not_found",
+            ErrorCode::UserNotFound => "user_not_found",
+            ErrorCode::ReportNotFound => "report_not_found",
+            ErrorCode::ConversationNotFound => "conversation_not_found",
+            ErrorCode::KeysNotFound => "keys_not_found",
+            ErrorCode::FilterNotFound => "filter_not_found",
+            ErrorCode::WebhookNotFound => "webhook_not_found",
+            ErrorCode::AnnouncementNotFound => "announcement_not_found",
+            ErrorCode::PolicyNotFound => "policy_not_found",
+            ErrorCode::JobNotFound => "job_not_found",
+            ErrorCode::EmailDigestNotFound => "email_digest_not_found",
+            ErrorCode::UnknownUnsubscribeToken => "unknown_unsubscribe_token",
+            ErrorCode::UnknownOembedUrl => "unknown_oembed_url",
+            ErrorCode::UnsupportedOembedFormat => "unsupported_oembed_format",
+            ErrorCode::InvalidLastEventId => "invalid_last_event_id",
+            ErrorCode::PublicTimelineDisabled => "public_timeline_disabled",
+            ErrorCode::MastodonApiDisabled => "mastodon_api_disabled",
+            ErrorCode::NotConfigured => "not_configured",
+            ErrorCode::NotPostAuthor => "not_post_author",
+            ErrorCode::NotConversationCreator => "not_conversation_creator",
+            ErrorCode::PinnedPostLimitReached => "pinned_post_limit_reached",
+            ErrorCode::ConversationMemberLimitReached => "conversation_member_limit_reached",
+            ErrorCode::OneTimePrekeyLimitReached => "one_time_prekey_limit_reached",
+            ErrorCode::WebhookLimitReached => "webhook_limit_reached",
+            ErrorCode::SelfFollow => "self_follow",
+            ErrorCode::HandleTaken => "handle_taken",
+            ErrorCode::CannotChangeOwnRole => "cannot_change_own_role",
+            ErrorCode::CannotImpersonate => "cannot_impersonate",
+            ErrorCode::ReportAlreadyClaimed => "report_already_claimed",
+            ErrorCode::ReportWithoutPost => "report_without_post",
+            ErrorCode::AssigneeNotModerator => "assignee_not_moderator",
+            ErrorCode::CannotSuspend => "cannot_suspend",
+            ErrorCode::CannotShadowban => "cannot_shadowban",
+            ErrorCode::VersionConflict => "version_conflict",
+            ErrorCode::ContentRejected => "content_rejected",
+            ErrorCode::OidcLoginExpired => "oidc_login_expired",
+            ErrorCode::IdentityProviderFailed => "identity_provider_failed",
+            ErrorCode::RegistrationsClosed => "registrations_closed",
+            ErrorCode::HandleRequired => "handle_required",
+            ErrorCode::IdentityAlreadyLinked => "identity_already_linked",
+            ErrorCode::OidcLinkUserMismatch => "oidc_link_user_mismatch",
+            ErrorCode::OidcIdentityNotFound => "oidc_identity_not_found",
+            ErrorCode::QuotaExceeded => "quota_exceeded",
+            ErrorCode::WebsiteRequired => "website_required",
+            ErrorCode::WebsiteUnreachable => "website_unreachable",
+            ErrorCode::LinkBackNotFound => "link_back_not_found",
+        }
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Problem {
+    #[must_use]
+    pub fn new(code: ErrorCode, status: u16, title: String) -> Self {
+        Self {
+            kind: PROBLEM_TYPE.to_owned(),
+            title,
+            status,
+            detail: None,
+            code,
+            message: None,
+            errors: Vec::new(),
+            request_id: None,
+            current_version: None,
+            rule_category: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::problem::{ErrorCode, FieldError, Problem};
+    use serde_json::json;
+
+    #[test]
+    fn error_code_serializes_as_str() {
+        for code in [
+            ErrorCode::PostNotFound,
+            ErrorCode::InvalidToken,
+            ErrorCode::UnknownOembedUrl,
+            ErrorCode::OneTimePrekeyLimitReached,
+            ErrorCode::VersionConflict,
+        ] {
+            assert_eq!(serde_json::to_value(code).unwrap(), json!(code.as_str()));
+        }
+    }
+
+    #[test]
+    fn problem_serialization() {
+        let mut problem = Problem::new(
+            ErrorCode::ValidationFailed,
+            422,
+            "Unprocessable Entity".to_owned(),
+        );
+        problem.detail = Some("The post content is empty".to_owned());
+        problem.message = Some("Some of the entered values are invalid.".to_owned());
+        problem.errors.push(FieldError {
+            field: "content".to_owned(),
+            message: "The post content is empty".to_owned(),
+        });
+        problem.request_id = Some("3f2a9c".to_owned());
+
+        let expected = json!({
+            "type": "about:blank",
+            "title": "Unprocessable Entity",
+            "status": 422,
+            "detail": "The post content is empty",
+            "code": "validation_failed",
+            "message": "Some of the entered values are invalid.",
+            "errors": [{ "field": "content", "message": "The post content is empty" }],
+            "request_id": "3f2a9c",
+        });
+        assert_eq!(serde_json::to_value(&problem).unwrap(), expected);
+        assert_eq!(
+            serde_json::from_value::<Problem>(expected).unwrap(),
+            problem
+        );
+
+        let problem = Problem::new(
+            ErrorCode::InternalError,
+            500,
+            "Internal Server Error".to_owned(),
+        );
+        assert_eq!(
+            serde_json::to_value(&problem).unwrap(),
+            json!({
+                "type": "about:blank",
+                "title": "Internal Server Error",
+                "status": 500,
+                "code": "internal_error",
+            })
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Daily limits on how many posts and follows each local user makes.
+//!
+//! Usage is counted per UTC day, so quotas reset at midnight UTC. Users get the quotas of the
+//! instance, unless an administrator overrode them for the user.
+
+use crate::util::rfc3339;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+use thiserror::Error;
+use time::UtcDateTime;
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaKind {
+    Posts,
+    Follows,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Error)]
+#[error("The quota kind is invalid: {0}")]
+pub struct InvalidQuotaKindError(String);
+
+/// Limits per UTC day, where 0 means unlimited.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuotaSettings {
+    pub posts_per_day: u32,
+    pub follows_per_day: u32,
+}
+
+/// The quotas of one user that differ from the instance's. 0 means unlimited here too.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct UserQuotas {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub posts_per_day: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub follows_per_day: Option<u32>,
+}
+
+/// A quota that is used up.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct QuotaUsage {
+    pub kind: QuotaKind,
+    pub limit: u32,
+    #[serde(with = "rfc3339")]
+    pub resets_at: UtcDateTime,
+}
+
+impl QuotaKind {
+    pub const ALL: [QuotaKind; 2] = [QuotaKind::Posts, QuotaKind::Follows];
+
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QuotaKind::Posts => "posts",
+            QuotaKind::Follows => "follows",
+        }
+    }
+}
+
+impl Display for QuotaKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for QuotaKind {
+    type Err = InvalidQuotaKindError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "posts" => Ok(QuotaKind::Posts),
+            "follows" => Ok(QuotaKind::Follows),
+            _ => Err(InvalidQuotaKindError(s.to_owned())),
+        }
+    }
+}
+
+impl QuotaSettings {
+    #[must_use]
+    pub fn limit(self, kind: QuotaKind) -> u32 {
+        match kind {
+            QuotaKind::Posts => self.posts_per_day,
+            QuotaKind::Follows => self.follows_per_day,
+        }
+    }
+
+    /// These settings, with the quotas the user has overrides for replaced.
+    #[must_use]
+    pub fn with_overrides(self, overrides: UserQuotas) -> Self {
+        Self {
+            posts_per_day: overrides.posts_per_day.unwrap_or(self.posts_per_day),
+            follows_per_day: overrides.follows_per_day.unwrap_or(self.follows_per_day),
+        }
+    }
+}
+
+impl QuotaUsage {
+    /// The quota used up at `now`, which resets at the next midnight UTC.
+    #[must_use]
+    pub fn new(kind: QuotaKind, limit: u32, now: UtcDateTime) -> Self {
+        let resets_at = now
+            .date()
+            .next_day()
+            .map_or(UtcDateTime::MAX, |day| day.midnight().as_utc());
+
+        Self {
+            kind,
+            limit,
+            resets_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::quota::{QuotaKind, QuotaSettings, QuotaUsage, UserQuotas};
+    use time::macros::utc_datetime;
+
+    #[test]
+    fn quota_kind_round_trip() {
+        for kind in QuotaKind::ALL {
+            assert_eq!(kind.as_str().parse(), Ok(kind));
+        }
+        assert!("media".parse::<QuotaKind>().is_err());
+    }
+
+    #[test]
+    fn with_overrides() {
+        let settings = QuotaSettings {
+            posts_per_day: 100,
+            follows_per_day: 50,
+        };
+        let overrides = UserQuotas {
+            posts_per_day: Some(0),
+            follows_per_day: None,
+        };
+
+        assert_eq!(
+            settings.with_overrides(overrides),
+            QuotaSettings {
+                posts_per_day: 0,
+                follows_per_day: 50,
+            }
+        );
+    }
+
+    #[test]
+    fn resets_at_midnight() {
+        let usage = QuotaUsage::new(QuotaKind::Posts, 10, utc_datetime!(2025-12-31 23:59:59));
+
+        assert_eq!(usage.resets_at, utc_datetime!(2026-01-01 0:00));
+    }
+}
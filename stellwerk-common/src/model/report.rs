@@ -0,0 +1,372 @@
+use crate::{
+    model::{
+        Id,
+        post::{ModeratedPost, PostMarker},
+        user::UserMarker,
+    },
+    util::rfc3339,
+};
+use serde::{
+    Deserialize, Deserializer, Serialize,
+    de::{Error, Unexpected},
+};
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+use thiserror::Error;
+use time::UtcDateTime;
+
+pub const REPORT_COMMENT_MAX_LEN: usize = 1000;
+pub const REPORT_NOTE_MAX_LEN: usize = 2000;
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
+pub struct ReportMarker;
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
+pub struct ReportNoteMarker;
+
+#[derive(
+    Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Deserialize, Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportCategory {
+    Spam,
+    Harassment,
+    IllegalContent,
+    Impersonation,
+    #[default]
+    Other,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Error)]
+#[error("The report category is invalid: {0}")]
+pub struct InvalidReportCategoryError(String);
+
+/// What moderators did about a report when resolving it.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportAction {
+    /// Nothing, the report was unfounded.
+    Dismiss,
+    /// Deletes the reported post softly. Only for reports about posts.
+    DeleteContent,
+    /// Sends the target user a [warning](crate::model::notification::NotificationKind::Warning).
+    Warn,
+    /// Signs the target user out everywhere and keeps them from authenticating.
+    Suspend,
+    /// Hides the posts of the target user from others, see
+    /// [`ModeratedUser::shadowbanned_at`](crate::model::user::ModeratedUser::shadowbanned_at).
+    Shadowban,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Error)]
+#[error("The report action is invalid: {0}")]
+pub struct InvalidReportActionError(String);
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Serialize)]
+#[serde(transparent)]
+pub struct ReportComment(String);
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Error)]
+#[error("The report comment is longer than {REPORT_COMMENT_MAX_LEN} characters")]
+pub struct InvalidReportCommentError(String);
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct Report {
+    pub id: Id<ReportMarker>,
+    /// `None` for reports created automatically, see [`spam`](crate::model::spam).
+    pub reporter: Option<Id<UserMarker>>,
+    pub target_user: Id<UserMarker>,
+    /// If set, the report is about this post by `target_user`.
+    pub target_post: Option<Id<PostMarker>>,
+    pub category: ReportCategory,
+    pub comment: ReportComment,
+    /// The moderator responsible for handling the report.
+    pub assignee: Option<Id<UserMarker>>,
+    #[serde(with = "rfc3339::option")]
+    pub resolved_at: Option<UtcDateTime>,
+    /// `None` for open reports, and for reports resolved before actions were recorded.
+    #[serde(default)]
+    pub resolution: Option<ReportAction>,
+}
+
+/// An open report with the reported post inlined, as moderators triage them.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct QueuedReport {
+    #[serde(flatten)]
+    pub report: Report,
+    /// `None` for reports about users, and if the post was purged.
+    pub post: Option<ModeratedPost>,
+}
+
+/// Non-empty, internal to moderators.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Serialize)]
+#[serde(transparent)]
+pub struct ReportNoteContent(String);
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Error)]
+pub enum InvalidReportNoteError {
+    #[error("The report note is empty")]
+    Empty,
+    #[error("The report note is longer than {REPORT_NOTE_MAX_LEN} characters")]
+    TooLong,
+}
+
+/// A note of a moderator on a report, never shown to the reporter or target.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct ReportNote {
+    #[serde(flatten, with = "crate::model::id_with_created_at")]
+    pub id: Id<ReportNoteMarker>,
+    pub report: Id<ReportMarker>,
+    pub author: Id<UserMarker>,
+    pub content: ReportNoteContent,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct CreateReportNote {
+    pub report: Id<ReportMarker>,
+    pub author: Id<UserMarker>,
+    pub content: ReportNoteContent,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct CreateReport {
+    pub reporter: Option<Id<UserMarker>>,
+    pub target_user: Id<UserMarker>,
+    pub target_post: Option<Id<PostMarker>>,
+    pub category: ReportCategory,
+    pub comment: ReportComment,
+}
+
+impl CreateReport {
+    /// A report about `target_user` without a comment.
+    #[must_use]
+    pub fn new(
+        reporter: Id<UserMarker>,
+        target_user: Id<UserMarker>,
+        category: ReportCategory,
+    ) -> Self {
+        Self {
+            reporter: Some(reporter),
+            target_user,
+            target_post: None,
+            category,
+            comment: ReportComment::default(),
+        }
+    }
+
+    /// A report about `target_user` without a reporter or comment.
+    #[must_use]
+    pub fn automatic(target_user: Id<UserMarker>, category: ReportCategory) -> Self {
+        Self {
+            reporter: None,
+            target_user,
+            target_post: None,
+            category,
+            comment: ReportComment::default(),
+        }
+    }
+
+    /// Makes the report about `post`, which must be by the target user.
+    #[must_use]
+    pub fn with_post(self, post: Id<PostMarker>) -> Self {
+        Self {
+            target_post: Some(post),
+            ..self
+        }
+    }
+
+    pub fn with_comment(
+        self,
+        comment: impl Into<String>,
+    ) -> Result<Self, InvalidReportCommentError> {
+        Ok(Self {
+            comment: ReportComment::new(comment.into())?,
+            ..self
+        })
+    }
+}
+
+impl ReportCategory {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReportCategory::Spam => "spam",
+            ReportCategory::Harassment => "harassment",
+            ReportCategory::IllegalContent => "illegal_content",
+            ReportCategory::Impersonation => "impersonation",
+            ReportCategory::Other => "other",
+        }
+    }
+}
+
+impl Display for ReportCategory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ReportCategory {
+    type Err = InvalidReportCategoryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "spam" => Ok(ReportCategory::Spam),
+            "harassment" => Ok(ReportCategory::Harassment),
+            "illegal_content" => Ok(ReportCategory::IllegalContent),
+            "impersonation" => Ok(ReportCategory::Impersonation),
+            "other" => Ok(ReportCategory::Other),
+            _ => Err(InvalidReportCategoryError(s.to_owned())),
+        }
+    }
+}
+
+impl ReportAction {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReportAction::Dismiss => "dismiss",
+            ReportAction::DeleteContent => "delete_content",
+            ReportAction::Warn => "warn",
+            ReportAction::Suspend => "suspend",
+            ReportAction::Shadowban => "shadowban",
+        }
+    }
+}
+
+impl Display for ReportAction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ReportAction {
+    type Err = InvalidReportActionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dismiss" => Ok(ReportAction::Dismiss),
+            "delete_content" => Ok(ReportAction::DeleteContent),
+            "warn" => Ok(ReportAction::Warn),
+            "suspend" => Ok(ReportAction::Suspend),
+            "shadowban" => Ok(ReportAction::Shadowban),
+            _ => Err(InvalidReportActionError(s.to_owned())),
+        }
+    }
+}
+
+impl ReportComment {
+    pub fn new(comment: String) -> Result<Self, InvalidReportCommentError> {
+        if comment.chars().count() <= REPORT_COMMENT_MAX_LEN {
+            Ok(ReportComment(comment))
+        } else {
+            Err(InvalidReportCommentError(comment))
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self) -> &str {
+        &self.0
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for ReportComment {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let inner = String::deserialize(deserializer)?;
+        Self::new(inner)
+            .map_err(|err| Error::invalid_value(Unexpected::Str(&err.0), &"ReportComment"))
+    }
+}
+
+impl ReportNoteContent {
+    /// Trims the content.
+    pub fn new(content: String) -> Result<Self, InvalidReportNoteError> {
+        let trimmed = content.trim();
+        if trimmed.is_empty() {
+            return Err(InvalidReportNoteError::Empty);
+        }
+        if trimmed.chars().count() > REPORT_NOTE_MAX_LEN {
+            return Err(InvalidReportNoteError::TooLong);
+        }
+
+        if trimmed.len() == content.len() {
+            Ok(Self(content))
+        } else {
+            Ok(Self(trimmed.to_owned()))
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self) -> &str {
+        &self.0
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for ReportNoteContent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let inner = String::deserialize(deserializer)?;
+        Self::new(inner).map_err(Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::report::{
+        InvalidReportNoteError, REPORT_NOTE_MAX_LEN, ReportAction, ReportNoteContent,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn report_action_round_trip() {
+        for action in [
+            ReportAction::Dismiss,
+            ReportAction::DeleteContent,
+            ReportAction::Warn,
+            ReportAction::Suspend,
+            ReportAction::Shadowban,
+        ] {
+            assert_eq!(action.as_str().parse(), Ok(action));
+            assert_eq!(
+                serde_json::to_value(action).unwrap(),
+                json!(action.as_str())
+            );
+        }
+        assert!("ban".parse::<ReportAction>().is_err());
+    }
+
+    #[test]
+    fn report_note_content_validation() {
+        assert_eq!(
+            ReportNoteContent::new(" checked the history ".to_owned())
+                .unwrap()
+                .get(),
+            "checked the history"
+        );
+        assert_eq!(
+            ReportNoteContent::new(" \n".to_owned()),
+            Err(InvalidReportNoteError::Empty)
+        );
+        assert_eq!(
+            ReportNoteContent::new("a".repeat(REPORT_NOTE_MAX_LEN + 1)),
+            Err(InvalidReportNoteError::TooLong)
+        );
+        assert!(serde_json::from_value::<ReportNoteContent>(json!("")).is_err());
+    }
+}
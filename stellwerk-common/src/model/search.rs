@@ -0,0 +1,147 @@
+//! Searching posts and users of a tenant.
+//!
+//! Search runs on an external engine if one is configured, which is kept up to date through
+//! [jobs](crate::model::job), and on the full-text search of the database otherwise.
+
+use crate::model::{
+    Id,
+    pagination::{DEFAULT_LIMIT, MAX_LIMIT},
+    post::{Post, PostMarker},
+    tenant::TenantId,
+    user::{UserMarker, UserProfile},
+};
+use serde::{
+    Deserialize, Deserializer, Serialize,
+    de::{Error, Unexpected},
+};
+use thiserror::Error;
+
+pub const SEARCH_QUERY_MAX_LEN: usize = 200;
+
+/// Trimmed, never empty, and never longer than [`SEARCH_QUERY_MAX_LEN`] characters.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Serialize)]
+#[serde(transparent)]
+pub struct SearchQuery(String);
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Error)]
+pub enum InvalidSearchQueryError {
+    #[error("The search query is empty")]
+    Empty,
+    #[error("The search query is longer than {SEARCH_QUERY_MAX_LEN} characters")]
+    TooLong(String),
+}
+
+/// The query parameters of a search.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct SearchRequest {
+    pub q: SearchQuery,
+    /// Of each kind of result. Defaults to [`DEFAULT_LIMIT`], capped at [`MAX_LIMIT`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// Only posts by and profiles of [verified](crate::model::verification) users.
+    #[serde(default)]
+    pub verified: bool,
+}
+
+/// What an indexing job updates. The job indexes the current state, so it removes the document
+/// from the index if it was deleted in the meantime.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize, Serialize)]
+#[serde(tag = "type", content = "id", rename_all = "snake_case")]
+pub enum SearchDocument {
+    Post(Id<PostMarker>),
+    User(Id<UserMarker>),
+}
+
+/// The document of a post that is not deleted, as external engines index it.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct IndexedPost {
+    pub id: Id<PostMarker>,
+    pub tenant: TenantId,
+    pub author: Id<UserMarker>,
+    pub content: String,
+}
+
+/// The document of a user, as external engines index it.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct IndexedUser {
+    pub id: Id<UserMarker>,
+    pub tenant: TenantId,
+    pub handle: String,
+}
+
+/// The best matches, best first.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct SearchResults {
+    pub posts: Vec<Post>,
+    pub users: Vec<UserProfile>,
+}
+
+impl SearchQuery {
+    /// Trims the query.
+    pub fn new(query: String) -> Result<Self, InvalidSearchQueryError> {
+        let trimmed = query.trim();
+        if trimmed.is_empty() {
+            return Err(InvalidSearchQueryError::Empty);
+        }
+        if trimmed.chars().count() > SEARCH_QUERY_MAX_LEN {
+            return Err(InvalidSearchQueryError::TooLong(query));
+        }
+
+        Ok(Self(trimmed.to_owned()))
+    }
+
+    #[must_use]
+    pub fn get(&self) -> &str {
+        &self.0
+    }
+}
+
+impl SearchRequest {
+    #[must_use]
+    pub fn limit(&self) -> u32 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+}
+
+impl<'de> Deserialize<'de> for SearchQuery {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let inner = String::deserialize(deserializer)?;
+        Self::new(inner.clone())
+            .map_err(|_| Error::invalid_value(Unexpected::Str(&inner), &"SearchQuery"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{
+        Id,
+        search::{SEARCH_QUERY_MAX_LEN, SearchDocument, SearchQuery},
+    };
+    use serde_json::json;
+
+    #[test]
+    fn search_query_validation() {
+        assert_eq!(
+            SearchQuery::new("  knitting  ".to_owned()).unwrap().get(),
+            "knitting"
+        );
+        assert!(SearchQuery::new(" \n ".to_owned()).is_err());
+        assert!(SearchQuery::new("a".repeat(SEARCH_QUERY_MAX_LEN)).is_ok());
+        assert!(SearchQuery::new("a".repeat(SEARCH_QUERY_MAX_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn search_document_serialization() {
+        let document = SearchDocument::Post(Id::from(42));
+        let value = serde_json::to_value(document).unwrap();
+
+        assert_eq!(value, json!({ "type": "post", "id": "42" }));
+        assert_eq!(
+            serde_json::from_value::<SearchDocument>(value).unwrap(),
+            document
+        );
+    }
+}
@@ -0,0 +1,315 @@
+//! [Sitemaps](https://www.sitemaps.org/protocol.html) of the public profile and post pages, so
+//! that search engines find them.
+//!
+//! `/sitemap.xml` is an index of [`SitemapFile`]s, like `/sitemaps/posts-1.xml`, each listing at
+//! most [`SITEMAP_MAX_URLS`] pages of one [`SitemapKind`], oldest first. Since pages only get
+//! added at the end, the URLs in earlier files stay the same unless users or posts are removed.
+
+use crate::{
+    model::{
+        Id,
+        page::{post_page_url, profile_page_url},
+        post::PostMarker,
+        user::{User, UserHandle},
+    },
+    text::escape_html,
+};
+use serde::{Deserialize, Deserializer, de::Error as _};
+use std::{
+    fmt::{Display, Formatter, Write},
+    num::NonZeroU32,
+    str::FromStr,
+};
+use thiserror::Error;
+use time::UtcDateTime;
+
+/// The most URLs the protocol allows in one file.
+pub const SITEMAP_MAX_URLS: u32 = 50_000;
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
+pub enum SitemapKind {
+    Profiles,
+    Posts,
+}
+
+/// One file of the sitemap, with the URLs of the `page`th [`SITEMAP_MAX_URLS`] pages of `kind`.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
+pub struct SitemapFile {
+    pub kind: SitemapKind,
+    /// Starting at 1.
+    pub page: NonZeroU32,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Error)]
+#[error("The sitemap file name is invalid: {0}")]
+pub struct InvalidSitemapFileError(String);
+
+/// How many profiles and posts are listed in the sitemap.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
+pub struct SitemapCounts {
+    pub profiles: u64,
+    pub posts: u64,
+}
+
+/// A post, as far as its page URL is concerned.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct SitemapPost {
+    pub id: Id<PostMarker>,
+    pub author: UserHandle,
+}
+
+/// Writes a `urlset` one URL at a time, so the listed pages need not be buffered.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct UrlSet {
+    xml: String,
+}
+
+impl SitemapKind {
+    pub const ALL: [SitemapKind; 2] = [SitemapKind::Profiles, SitemapKind::Posts];
+
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SitemapKind::Profiles => "profiles",
+            SitemapKind::Posts => "posts",
+        }
+    }
+}
+
+impl Display for SitemapKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl SitemapFile {
+    /// How many pages of the kind come before the ones in this file.
+    #[must_use]
+    pub fn offset(self) -> u64 {
+        u64::from(self.page.get() - 1) * u64::from(SITEMAP_MAX_URLS)
+    }
+
+    #[must_use]
+    pub fn url(self, public_url: &str) -> String {
+        format!("{public_url}/sitemaps/{self}")
+    }
+}
+
+impl Display for SitemapFile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}.xml", self.kind, self.page)
+    }
+}
+
+impl FromStr for SitemapFile {
+    type Err = InvalidSitemapFileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, page) = s
+            .strip_suffix(".xml")
+            .and_then(|name| name.rsplit_once('-'))
+            .ok_or_else(|| InvalidSitemapFileError(s.to_owned()))?;
+        let kind = SitemapKind::ALL
+            .into_iter()
+            .find(|candidate| candidate.as_str() == kind)
+            .ok_or_else(|| InvalidSitemapFileError(s.to_owned()))?;
+        // Parsing would also accept a leading `+` or zeros, which give duplicate URLs.
+        let page = page
+            .parse()
+            .ok()
+            .filter(|parsed: &NonZeroU32| parsed.to_string() == page)
+            .ok_or_else(|| InvalidSitemapFileError(s.to_owned()))?;
+
+        Ok(Self { kind, page })
+    }
+}
+
+impl<'de> Deserialize<'de> for SitemapFile {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+impl SitemapCounts {
+    #[must_use]
+    pub fn count(self, kind: SitemapKind) -> u64 {
+        match kind {
+            SitemapKind::Profiles => self.profiles,
+            SitemapKind::Posts => self.posts,
+        }
+    }
+
+    /// All files with at least one URL.
+    #[must_use]
+    pub fn files(self) -> Vec<SitemapFile> {
+        SitemapKind::ALL
+            .into_iter()
+            .flat_map(|kind| {
+                let pages = self.count(kind).div_ceil(u64::from(SITEMAP_MAX_URLS));
+                (1..=u32::try_from(pages).unwrap_or(u32::MAX))
+                    .filter_map(NonZeroU32::new)
+                    .map(move |page| SitemapFile { kind, page })
+            })
+            .collect()
+    }
+}
+
+/// Renders the `sitemapindex` listing `files`.
+#[must_use]
+pub fn render_sitemap_index(files: &[SitemapFile], public_url: &str) -> String {
+    let mut xml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+        <sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">"
+        .to_owned();
+    for file in files {
+        write!(
+            xml,
+            "<sitemap><loc>{}</loc></sitemap>",
+            escape_html(&file.url(public_url))
+        )
+        .expect("Writing to String cannot fail");
+    }
+    xml.push_str("</sitemapindex>");
+
+    xml
+}
+
+impl UrlSet {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            xml: "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">"
+                .to_owned(),
+        }
+    }
+
+    /// `last_modified` is written as date only, which is precise enough for crawlers.
+    pub fn push(&mut self, url: &str, last_modified: Option<UtcDateTime>) {
+        write!(self.xml, "<url><loc>{}</loc>", escape_html(url))
+            .expect("Writing to String cannot fail");
+        if let Some(last_modified) = last_modified {
+            write!(self.xml, "<lastmod>{}</lastmod>", last_modified.date())
+                .expect("Writing to String cannot fail");
+        }
+        self.xml.push_str("</url>");
+    }
+
+    pub fn push_profile(&mut self, user: &User, public_url: &str) {
+        self.push(&profile_page_url(public_url, &user.handle), None);
+    }
+
+    /// Posts are not edited often, so they are listed as last modified when created.
+    pub fn push_post(&mut self, post: &SitemapPost, public_url: &str) {
+        self.push(
+            &post_page_url(public_url, &post.author, post.id),
+            Some(post.id.created_at()),
+        );
+    }
+
+    #[must_use]
+    pub fn finish(mut self) -> String {
+        self.xml.push_str("</urlset>");
+        self.xml
+    }
+}
+
+impl Default for UrlSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{
+        Id,
+        sitemap::{
+            SITEMAP_MAX_URLS, SitemapCounts, SitemapFile, SitemapKind, SitemapPost, UrlSet,
+            render_sitemap_index,
+        },
+        user::{User, UserHandle},
+    };
+    use std::num::NonZeroU32;
+
+    fn file(kind: SitemapKind, page: u32) -> SitemapFile {
+        SitemapFile {
+            kind,
+            page: NonZeroU32::new(page).unwrap(),
+        }
+    }
+
+    #[test]
+    fn sitemap_file_round_trip() {
+        for file in [file(SitemapKind::Profiles, 1), file(SitemapKind::Posts, 12)] {
+            assert_eq!(file.to_string().parse(), Ok(file));
+        }
+        assert_eq!(file(SitemapKind::Posts, 3).to_string(), "posts-3.xml");
+        for invalid in [
+            "posts-0.xml",
+            "posts-01.xml",
+            "posts-+1.xml",
+            "posts-1",
+            "media-1.xml",
+        ] {
+            assert!(invalid.parse::<SitemapFile>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn files_cover_counts() {
+        let counts = SitemapCounts {
+            profiles: 3,
+            posts: u64::from(SITEMAP_MAX_URLS) + 1,
+        };
+
+        assert_eq!(
+            counts.files(),
+            [
+                file(SitemapKind::Profiles, 1),
+                file(SitemapKind::Posts, 1),
+                file(SitemapKind::Posts, 2),
+            ]
+        );
+        assert_eq!(file(SitemapKind::Posts, 2).offset(), 50_000);
+        assert!(SitemapCounts::default().files().is_empty());
+    }
+
+    #[test]
+    fn render() {
+        let index =
+            render_sitemap_index(&[file(SitemapKind::Posts, 1)], "https://stellwerk.example");
+        assert!(index.contains(
+            "<sitemap><loc>https://stellwerk.example/sitemaps/posts-1.xml</loc></sitemap>"
+        ));
+
+        let handle = UserHandle::new("alice".to_owned()).unwrap();
+        let mut urls = UrlSet::new();
+        urls.push_profile(
+            &User {
+                id: Id::from(1),
+                handle: handle.clone(),
+                verified: false,
+            },
+            "https://stellwerk.example",
+        );
+        urls.push_post(
+            &SitemapPost {
+                id: Id::from(2),
+                author: handle,
+            },
+            "https://stellwerk.example",
+        );
+        urls.push("https://stellwerk.example/?a=1&b=2", None);
+        let xml = urls.finish();
+
+        assert!(xml.contains("<url><loc>https://stellwerk.example/@alice</loc></url>"));
+        assert!(xml.contains("<url><loc>https://stellwerk.example/@alice/2</loc><lastmod>"));
+        assert!(xml.contains("<loc>https://stellwerk.example/?a=1&amp;b=2</loc>"));
+        assert!(xml.ends_with("</urlset>"));
+    }
+}
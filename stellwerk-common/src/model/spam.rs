@@ -0,0 +1,207 @@
+//! Scoring of accounts that post or follow like spammers.
+//!
+//! Every signal scores 100 once its count is reached within its window, and proportionally
+//! more or less otherwise. The scores of all signals add up, and the highest threshold the sum
+//! reaches decides the [`SpamVerdict`].
+
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// The score of a signal whose count is exactly reached.
+pub const SIGNAL_SCORE: u32 = 100;
+
+/// Counts of 0 disable their signal, and thresholds of 0 their verdict.
+/// Windows are in seconds.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpamSettings {
+    /// Posts by one account within `burst_window`, including the new one.
+    pub burst_posts: u32,
+    pub burst_window: u64,
+    /// Other accounts that posted the same content within `duplicate_window`.
+    pub duplicate_accounts: u32,
+    pub duplicate_window: u64,
+    /// Accounts followed by one account within `follow_window`, including the new one.
+    pub follows: u32,
+    pub follow_window: u64,
+    pub report_score: u32,
+    pub review_score: u32,
+    pub throttle_score: u32,
+}
+
+impl Default for SpamSettings {
+    fn default() -> Self {
+        Self {
+            burst_posts: 10,
+            burst_window: 60,
+            duplicate_accounts: 3,
+            duplicate_window: 60 * 60,
+            follows: 100,
+            follow_window: 60 * 60,
+            report_score: 100,
+            review_score: 200,
+            throttle_score: 300,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpamSignal {
+    BurstPosting,
+    DuplicateContent,
+    MassFollowing,
+}
+
+/// What happens to the post or follow, from the mildest to the harshest.
+#[derive(
+    Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Deserialize, Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SpamVerdict {
+    #[default]
+    Allow,
+    /// Let through, but the account is reported to moderators.
+    Report,
+    /// Accepted, but hidden until a moderator restores it, and reported.
+    /// Follows cannot be held, so they are only reported.
+    Review,
+    /// Rejected as rate limited.
+    Throttle,
+}
+
+/// The signals that scored, with their total.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct SpamAssessment {
+    pub score: u32,
+    pub signals: Vec<SpamSignal>,
+    pub verdict: SpamVerdict,
+}
+
+impl SpamSettings {
+    /// The score of `observed` occurrences of a signal with the given `count`.
+    #[must_use]
+    pub fn signal_score(count: u32, observed: u64) -> u32 {
+        if count == 0 {
+            return 0;
+        }
+
+        let score = observed.saturating_mul(SIGNAL_SCORE.into()) / u64::from(count);
+        u32::try_from(score).unwrap_or(u32::MAX)
+    }
+
+    #[must_use]
+    pub fn verdict(&self, score: u32) -> SpamVerdict {
+        let reached = |threshold: u32| threshold != 0 && score >= threshold;
+
+        if reached(self.throttle_score) {
+            SpamVerdict::Throttle
+        } else if reached(self.review_score) {
+            SpamVerdict::Review
+        } else if reached(self.report_score) {
+            SpamVerdict::Report
+        } else {
+            SpamVerdict::Allow
+        }
+    }
+
+    /// Sums the scores of the observed signals. Signals only count as triggered
+    /// once they reach their count, but their scores count towards the total before.
+    #[must_use]
+    pub fn assess(&self, observations: &[(SpamSignal, u64)]) -> SpamAssessment {
+        let mut score = 0u32;
+        let mut signals = Vec::new();
+        for &(signal, observed) in observations {
+            let signal_score = Self::signal_score(self.count(signal), observed);
+            score = score.saturating_add(signal_score);
+            if signal_score >= SIGNAL_SCORE {
+                signals.push(signal);
+            }
+        }
+
+        SpamAssessment {
+            score,
+            signals,
+            verdict: self.verdict(score),
+        }
+    }
+
+    #[must_use]
+    pub fn count(&self, signal: SpamSignal) -> u32 {
+        match signal {
+            SpamSignal::BurstPosting => self.burst_posts,
+            SpamSignal::DuplicateContent => self.duplicate_accounts,
+            SpamSignal::MassFollowing => self.follows,
+        }
+    }
+}
+
+impl SpamSignal {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SpamSignal::BurstPosting => "burst_posting",
+            SpamSignal::DuplicateContent => "duplicate_content",
+            SpamSignal::MassFollowing => "mass_following",
+        }
+    }
+}
+
+impl Display for SpamSignal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::spam::{SpamSettings, SpamSignal, SpamVerdict};
+
+    #[test]
+    fn signal_scores_are_proportional() {
+        assert_eq!(SpamSettings::signal_score(10, 5), 50);
+        assert_eq!(SpamSettings::signal_score(10, 10), 100);
+        assert_eq!(SpamSettings::signal_score(3, 7), 233);
+        assert_eq!(SpamSettings::signal_score(0, 1000), 0);
+        assert_eq!(SpamSettings::signal_score(1, u64::MAX), u32::MAX);
+    }
+
+    #[test]
+    fn scores_add_up_to_verdicts() {
+        let settings = SpamSettings::default();
+
+        let calm = settings.assess(&[
+            (SpamSignal::BurstPosting, 2),
+            (SpamSignal::DuplicateContent, 0),
+        ]);
+        assert_eq!(calm.score, 20);
+        assert!(calm.signals.is_empty());
+        assert_eq!(calm.verdict, SpamVerdict::Allow);
+
+        let burst = settings.assess(&[(SpamSignal::BurstPosting, 10)]);
+        assert_eq!(burst.signals, [SpamSignal::BurstPosting]);
+        assert_eq!(burst.verdict, SpamVerdict::Report);
+
+        let both = settings.assess(&[
+            (SpamSignal::BurstPosting, 15),
+            (SpamSignal::DuplicateContent, 2),
+        ]);
+        assert_eq!(both.score, 216);
+        assert_eq!(both.signals, [SpamSignal::BurstPosting]);
+        assert_eq!(both.verdict, SpamVerdict::Review);
+
+        let flood = settings.assess(&[(SpamSignal::MassFollowing, 300)]);
+        assert_eq!(flood.verdict, SpamVerdict::Throttle);
+    }
+
+    #[test]
+    fn zero_thresholds_disable_verdicts() {
+        let settings = SpamSettings {
+            throttle_score: 0,
+            review_score: 0,
+            ..SpamSettings::default()
+        };
+
+        assert_eq!(settings.verdict(10_000), SpamVerdict::Report);
+    }
+}
@@ -0,0 +1,153 @@
+//! Tenants, the logical instances one deployment can serve.
+//!
+//! Every user belongs to one tenant, and so do their posts and auth tokens. Requests are
+//! assigned a tenant by their host, and deployments serving a single community only have the
+//! [default tenant](TenantId::DEFAULT).
+
+use serde::{Deserialize, Deserializer, Serialize, de::Error as _};
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+use thiserror::Error;
+
+pub const TENANT_ID_MAX_LEN: usize = 50;
+
+/// Lowercase ASCII letters, digits and dashes, never empty,
+/// and never longer than [`TENANT_ID_MAX_LEN`] characters.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Serialize)]
+#[serde(transparent)]
+pub struct TenantId(String);
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Error)]
+#[error("The tenant id is invalid: {0}")]
+pub struct InvalidTenantIdError(String);
+
+/// Who can create an account on an instance.
+#[derive(
+    Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Deserialize, Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistrationMode {
+    /// Only administrators create accounts.
+    #[default]
+    Closed,
+    /// Anyone can sign up, by logging in with an identity provider.
+    Open,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Error)]
+#[error("The registration mode is invalid: {0}")]
+pub struct InvalidRegistrationModeError(String);
+
+impl TenantId {
+    /// The tenant of requests to hosts without a tenant, and of all existing data.
+    pub const DEFAULT: &'static str = "default";
+
+    pub fn new(id: String) -> Result<Self, InvalidTenantIdError> {
+        let valid = !id.is_empty()
+            && id.len() <= TENANT_ID_MAX_LEN
+            && id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+
+        if valid {
+            Ok(Self(id))
+        } else {
+            Err(InvalidTenantIdError(id))
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self) -> &str {
+        &self.0
+    }
+
+    #[must_use]
+    pub fn is_default(&self) -> bool {
+        self.0 == Self::DEFAULT
+    }
+}
+
+impl Default for TenantId {
+    fn default() -> Self {
+        Self(Self::DEFAULT.to_owned())
+    }
+}
+
+impl Display for TenantId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for TenantId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let inner = String::deserialize(deserializer)?;
+        Self::new(inner).map_err(|error| {
+            D::Error::custom(format!(
+                "Expected lowercase letters, digits and dashes, but got `{}`",
+                error.0
+            ))
+        })
+    }
+}
+
+impl RegistrationMode {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RegistrationMode::Closed => "closed",
+            RegistrationMode::Open => "open",
+        }
+    }
+}
+
+impl Display for RegistrationMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RegistrationMode {
+    type Err = InvalidRegistrationModeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "closed" => Ok(RegistrationMode::Closed),
+            "open" => Ok(RegistrationMode::Open),
+            _ => Err(InvalidRegistrationModeError(s.to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::tenant::{RegistrationMode, TENANT_ID_MAX_LEN, TenantId};
+    use serde_json::json;
+
+    #[test]
+    fn tenant_id_validation() {
+        assert!(TenantId::new("default".to_owned()).is_ok());
+        assert!(TenantId::new("knitting-club-2".to_owned()).is_ok());
+        assert!(TenantId::new(String::new()).is_err());
+        assert!(TenantId::new("Knitting".to_owned()).is_err());
+        assert!(TenantId::new("knitting club".to_owned()).is_err());
+        assert!(TenantId::new("a".repeat(TENANT_ID_MAX_LEN + 1)).is_err());
+
+        assert!(TenantId::default().is_default());
+        assert!(serde_json::from_value::<TenantId>(json!("example.com")).is_err());
+    }
+
+    #[test]
+    fn registration_mode_round_trip() {
+        for mode in [RegistrationMode::Closed, RegistrationMode::Open] {
+            assert_eq!(mode.as_str().parse(), Ok(mode));
+            assert_eq!(serde_json::to_value(mode).unwrap(), json!(mode.as_str()));
+        }
+        assert!("approval".parse::<RegistrationMode>().is_err());
+    }
+}
@@ -1,19 +1,61 @@
-use crate::model::Id;
+use crate::{model::Id, util::rfc3339};
 use serde::{
     Deserialize, Deserializer, Serialize,
     de::{Error, Unexpected},
 };
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
 use thiserror::Error;
+use time::UtcDateTime;
 
 pub const USER_HANDLE_MAX_LEN: usize = 50;
+pub const DISPLAY_NAME_MAX_LEN: usize = 64;
+pub const PROFILE_WEBSITE_MAX_LEN: usize = 200;
 
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
 pub struct UserMarker;
 
 #[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
 pub struct User {
+    #[serde(flatten, with = "crate::model::id_with_created_at")]
     pub id: Id<UserMarker>,
     pub handle: UserHandle,
+    /// Whether an admin granted the user a verified badge, see
+    /// [`verification`](crate::model::verification).
+    #[serde(default)]
+    pub verified: bool,
+}
+
+/// A [`User`] with additional information shown on their profile.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct UserProfile {
+    #[serde(flatten)]
+    pub user: User,
+    pub stats: UserStats,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub website: Option<ProfileWebsite>,
+}
+
+/// A user as moderators see it, with the moderation state only they may know.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct ModeratedUser {
+    #[serde(flatten)]
+    pub user: User,
+    #[serde(with = "rfc3339::option")]
+    pub suspended_at: Option<UtcDateTime>,
+    /// If set, the posts of the user are hidden from public timelines, search, and the
+    /// notifications of others. The user sees everything as before, so they are not told.
+    #[serde(with = "rfc3339::option")]
+    pub shadowbanned_at: Option<UtcDateTime>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct UserStats {
+    pub post_count: u64,
+    pub follower_count: u64,
+    pub following_count: u64,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
@@ -25,10 +67,51 @@ pub struct CreateUser {
 #[serde(transparent)]
 pub struct UserHandle(String);
 
+/// The name shown on a profile instead of the handle. Trimmed, never empty,
+/// never longer than [`DISPLAY_NAME_MAX_LEN`] characters, and without control characters.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Serialize)]
+#[serde(transparent)]
+pub struct DisplayName(String);
+
+/// A website the user lists on their profile. An http or https URL, never longer than
+/// [`PROFILE_WEBSITE_MAX_LEN`] characters.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Serialize)]
+#[serde(transparent)]
+pub struct ProfileWebsite(String);
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct SetProfileWebsite {
+    pub url: ProfileWebsite,
+}
+
+/// Permission level of a user. Each role includes the permissions of the previous ones.
+#[derive(
+    Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Deserialize, Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum UserRole {
+    #[default]
+    User,
+    Moderator,
+    Admin,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Error)]
+#[error("The user role is invalid: {0}")]
+pub struct InvalidUserRoleError(String);
+
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash, Error)]
 #[error("The user handle is invalid: {0}")]
 pub struct InvalidUserHandleError(String);
 
+impl CreateUser {
+    pub fn new(handle: impl Into<String>) -> Result<Self, InvalidUserHandleError> {
+        Ok(Self {
+            handle: UserHandle::new(handle.into())?,
+        })
+    }
+}
+
 impl UserHandle {
     pub fn new(handle: String) -> Result<Self, InvalidUserHandleError> {
         if handle.chars().count() <= USER_HANDLE_MAX_LEN {
@@ -58,3 +141,190 @@ impl<'de> Deserialize<'de> for UserHandle {
         Self::new(inner).map_err(|err| Error::invalid_value(Unexpected::Str(&err.0), &"UserHandle"))
     }
 }
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Error)]
+pub enum InvalidProfileWebsiteError {
+    #[error("The website is not an http or https URL")]
+    UnsupportedUrl,
+    #[error("The website is longer than {PROFILE_WEBSITE_MAX_LEN} characters")]
+    TooLong,
+}
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Error)]
+pub enum InvalidDisplayNameError {
+    #[error("The display name is empty")]
+    Empty,
+    #[error("The display name is longer than {DISPLAY_NAME_MAX_LEN} characters")]
+    TooLong,
+    #[error("The display name contains the control character {0:?}")]
+    ControlCharacter(char),
+}
+
+impl DisplayName {
+    pub fn new(name: String) -> Result<Self, InvalidDisplayNameError> {
+        let trimmed = name.trim();
+        if trimmed.is_empty() {
+            return Err(InvalidDisplayNameError::Empty);
+        }
+        if trimmed.chars().count() > DISPLAY_NAME_MAX_LEN {
+            return Err(InvalidDisplayNameError::TooLong);
+        }
+        if let Some(control) = trimmed.chars().find(|c| c.is_control()) {
+            return Err(InvalidDisplayNameError::ControlCharacter(control));
+        }
+
+        if trimmed.len() == name.len() {
+            Ok(Self(name))
+        } else {
+            Ok(Self(trimmed.to_owned()))
+        }
+    }
+
+    #[must_use]
+    pub fn get(&self) -> &str {
+        &self.0
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for DisplayName {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let inner = String::deserialize(deserializer)?;
+        Self::new(inner).map_err(Error::custom)
+    }
+}
+
+impl ProfileWebsite {
+    pub fn new(url: String) -> Result<Self, InvalidProfileWebsiteError> {
+        if url.chars().count() > PROFILE_WEBSITE_MAX_LEN {
+            return Err(InvalidProfileWebsiteError::TooLong);
+        }
+        let host = url
+            .strip_prefix("https://")
+            .or_else(|| url.strip_prefix("http://"));
+        if host.is_none_or(str::is_empty) || url.contains(char::is_whitespace) {
+            return Err(InvalidProfileWebsiteError::UnsupportedUrl);
+        }
+
+        Ok(Self(url))
+    }
+
+    #[must_use]
+    pub fn get(&self) -> &str {
+        &self.0
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for ProfileWebsite {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let inner = String::deserialize(deserializer)?;
+        Self::new(inner).map_err(Error::custom)
+    }
+}
+
+impl UserRole {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UserRole::User => "user",
+            UserRole::Moderator => "moderator",
+            UserRole::Admin => "admin",
+        }
+    }
+}
+
+impl Display for UserRole {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for UserRole {
+    type Err = InvalidUserRoleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "user" => Ok(UserRole::User),
+            "moderator" => Ok(UserRole::Moderator),
+            "admin" => Ok(UserRole::Admin),
+            _ => Err(InvalidUserRoleError(s.to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::user::{
+        DISPLAY_NAME_MAX_LEN, DisplayName, InvalidDisplayNameError, InvalidProfileWebsiteError,
+        PROFILE_WEBSITE_MAX_LEN, ProfileWebsite,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn display_name_validation() {
+        assert_eq!(
+            DisplayName::new(" Alice Liddell\n".to_owned())
+                .unwrap()
+                .get(),
+            "Alice Liddell"
+        );
+        assert_eq!(
+            DisplayName::new(" \t".to_owned()),
+            Err(InvalidDisplayNameError::Empty)
+        );
+        assert!(DisplayName::new("ä".repeat(DISPLAY_NAME_MAX_LEN)).is_ok());
+        assert_eq!(
+            DisplayName::new("ä".repeat(DISPLAY_NAME_MAX_LEN + 1)),
+            Err(InvalidDisplayNameError::TooLong)
+        );
+        assert_eq!(
+            DisplayName::new("Alice\u{7}Liddell".to_owned()),
+            Err(InvalidDisplayNameError::ControlCharacter('\u{7}'))
+        );
+        assert_eq!(
+            DisplayName::new("Alice\nLiddell".to_owned()),
+            Err(InvalidDisplayNameError::ControlCharacter('\n'))
+        );
+    }
+
+    #[test]
+    fn display_name_deserialization() {
+        assert_eq!(
+            serde_json::from_value::<DisplayName>(json!(" Alice ")).unwrap(),
+            DisplayName::new("Alice".to_owned()).unwrap()
+        );
+        assert!(serde_json::from_value::<DisplayName>(json!("\u{0}")).is_err());
+    }
+
+    #[test]
+    fn profile_website_validation() {
+        assert!(ProfileWebsite::new("https://alice.example".to_owned()).is_ok());
+        assert_eq!(
+            ProfileWebsite::new("ftp://alice.example".to_owned()),
+            Err(InvalidProfileWebsiteError::UnsupportedUrl)
+        );
+        assert_eq!(
+            ProfileWebsite::new("https://".to_owned()),
+            Err(InvalidProfileWebsiteError::UnsupportedUrl)
+        );
+        assert_eq!(
+            ProfileWebsite::new(format!("https://{}", "a".repeat(PROFILE_WEBSITE_MAX_LEN))),
+            Err(InvalidProfileWebsiteError::TooLong)
+        );
+    }
+}
@@ -0,0 +1,129 @@
+//! Verified badges, which admins grant to users whose identity they checked.
+//!
+//! An admin can also require a link back first: the [`ProfileWebsite`] of the user has to link to
+//! their profile page with `rel="me"`, which shows that the user controls the website.
+//! The profile page links to the website with `rel="me"` in turn.
+//!
+//! [`ProfileWebsite`]: crate::model::user::ProfileWebsite
+
+use crate::text::html_to_text;
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct GrantVerification {
+    /// Only grants the badge if the website of the user links back to their profile page.
+    #[serde(default)]
+    pub require_link_back: bool,
+}
+
+/// Whether `html` has an `a` or `link` element with `rel="me"` pointing to `profile_url`.
+/// Trailing slashes are ignored.
+#[must_use]
+pub fn links_back(html: &str, profile_url: &str) -> bool {
+    let profile_url = profile_url.trim_end_matches('/');
+
+    html.split('<').skip(1).any(|tag| {
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+        let (name, attributes) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        if !name.eq_ignore_ascii_case("a") && !name.eq_ignore_ascii_case("link") {
+            return false;
+        }
+
+        let attributes = parse_attributes(attributes);
+        let attribute = |wanted: &str| {
+            attributes
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(wanted))
+                .map(|(_, value)| *value)
+        };
+        let is_me = attribute("rel").is_some_and(|rel| {
+            rel.split_ascii_whitespace()
+                .any(|kind| kind.eq_ignore_ascii_case("me"))
+        });
+
+        is_me
+            && attribute("href")
+                .is_some_and(|href| html_to_text(href).trim_end_matches('/') == profile_url)
+    })
+}
+
+/// The names and values of the attributes of a start tag, with quoted or unquoted values.
+/// Attributes without a value have an empty one.
+fn parse_attributes(mut rest: &str) -> Vec<(&str, &str)> {
+    let mut attributes = Vec::new();
+
+    loop {
+        rest = rest.trim_start();
+        let name_end = rest
+            .find(|c: char| c == '=' || c == '/' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        if name_end == 0 {
+            // A stray `=` or the `/` of a self-closing tag.
+            match rest.chars().next() {
+                Some(c) => rest = &rest[c.len_utf8()..],
+                None => return attributes,
+            }
+            continue;
+        }
+
+        let name = &rest[..name_end];
+        rest = rest[name_end..].trim_start();
+        let Some(value) = rest.strip_prefix('=').map(str::trim_start) else {
+            attributes.push((name, ""));
+            continue;
+        };
+        let (value, after) = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let quoted = &value[1..];
+                let end = quoted.find(quote).unwrap_or(quoted.len());
+                (&quoted[..end], quoted.get(end + 1..).unwrap_or_default())
+            }
+            _ => value.split_at(value.find(char::is_whitespace).unwrap_or(value.len())),
+        };
+        attributes.push((name, value));
+        rest = after;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::verification::links_back;
+
+    const PROFILE: &str = "https://stellwerk.example/@alice";
+
+    #[test]
+    fn finds_rel_me_links() {
+        assert!(links_back(
+            "<p>Find me on <a href=\"https://stellwerk.example/@alice\" rel=\"me\">Stellwerk</a></p>",
+            PROFILE
+        ));
+        assert!(links_back(
+            "<head><LINK REL='me nofollow' HREF='https://stellwerk.example/@alice/'/></head>",
+            PROFILE
+        ));
+        assert!(links_back(
+            "<a class=profile rel=me href=https://stellwerk.example/@alice>",
+            PROFILE
+        ));
+    }
+
+    #[test]
+    fn ignores_other_links() {
+        // Not rel="me".
+        assert!(!links_back(
+            "<a href=\"https://stellwerk.example/@alice\">Stellwerk</a>",
+            PROFILE
+        ));
+        // Another profile.
+        assert!(!links_back(
+            "<a rel=\"me\" href=\"https://stellwerk.example/@alice2\">",
+            PROFILE
+        ));
+        // Not a link.
+        assert!(!links_back(
+            "<img rel=\"me\" src=\"https://stellwerk.example/@alice\">",
+            PROFILE
+        ));
+        assert!(!links_back("https://stellwerk.example/@alice", PROFILE));
+    }
+}
@@ -0,0 +1,295 @@
+//! Webhooks, which users register to be told about events by signed HTTP requests.
+//!
+//! Each request has a [`WebhookPayload`] as JSON body, and the headers
+//! [`WEBHOOK_DELIVERY_HEADER`], [`WEBHOOK_TIMESTAMP_HEADER`] and [`WEBHOOK_SIGNATURE_HEADER`].
+//! Receivers check the signature with [`WebhookSecret::verify`] and the secret they got when
+//! they registered the webhook. Failed requests are retried, so the same delivery may arrive
+//! twice.
+
+use crate::{
+    model::{Id, post::PostMarker, user::UserMarker},
+    util::rfc3339,
+};
+use base64::{
+    Engine,
+    prelude::{BASE64_STANDARD, BASE64_URL_SAFE_NO_PAD},
+};
+use hmac::{Hmac, Mac};
+use rsa::sha2::Sha256;
+use serde::{Deserialize, Deserializer, Serialize, de::Error as _};
+use std::{
+    fmt::{Debug, Display, Formatter},
+    str::FromStr,
+};
+use thiserror::Error;
+use time::UtcDateTime;
+
+pub const WEBHOOK_URL_MAX_LEN: usize = 2000;
+const WEBHOOK_SECRET_LEN: usize = 32;
+/// The id of the delivery job, which stays the same when it is retried.
+pub const WEBHOOK_DELIVERY_HEADER: &str = "stellwerk-delivery";
+/// The Unix time the request was signed at.
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "stellwerk-timestamp";
+/// `sha256=` and the base64 encoded HMAC-SHA256 of the timestamp, a `.` and the body.
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "stellwerk-signature";
+const SIGNATURE_PREFIX: &str = "sha256=";
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Default, Hash)]
+pub struct WebhookMarker;
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventKind {
+    /// A user the owner of the webhook follows created a post.
+    FollowedPost,
+    /// Someone mentioned the owner of the webhook.
+    Mention,
+}
+
+/// An `http` or `https` URL without whitespace.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Serialize)]
+#[serde(transparent)]
+pub struct WebhookUrl(String);
+
+/// Shown once when the webhook is created, and used to sign its requests.
+#[derive(Clone, Eq, PartialEq, Hash, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct WebhookSecret(pub String);
+
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Error)]
+pub enum InvalidWebhookError {
+    #[error("The webhook URL is not an http or https URL")]
+    UnsupportedUrl,
+    #[error("The webhook URL is longer than {WEBHOOK_URL_MAX_LEN} characters")]
+    UrlTooLong,
+    #[error("The webhook has no events")]
+    NoEvents,
+    #[error("The webhook event is invalid: {0}")]
+    Event(String),
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct Webhook {
+    #[serde(flatten, with = "crate::model::id_with_created_at")]
+    pub id: Id<WebhookMarker>,
+    pub url: WebhookUrl,
+    pub events: Vec<WebhookEventKind>,
+    /// Set when too many requests failed in a row. Disabled webhooks get no new deliveries.
+    #[serde(default, with = "rfc3339::option")]
+    pub disabled_at: Option<UtcDateTime>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct CreateWebhook {
+    pub url: WebhookUrl,
+    pub events: Vec<WebhookEventKind>,
+}
+
+/// A [`Webhook`] as returned when it is created, the only time the secret is shown.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub secret: WebhookSecret,
+}
+
+/// The body of a webhook request. Like [`Event`](crate::event::Event)s, payloads only refer
+/// to the objects they are about.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WebhookPayload {
+    FollowedPost {
+        post: Id<PostMarker>,
+        author: Id<UserMarker>,
+    },
+    Mention {
+        post: Id<PostMarker>,
+        actor: Id<UserMarker>,
+    },
+}
+
+/// Where and how the payloads of an enabled webhook are sent.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct WebhookTarget {
+    pub id: Id<WebhookMarker>,
+    pub url: String,
+    pub secret: WebhookSecret,
+}
+
+impl WebhookEventKind {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WebhookEventKind::FollowedPost => "followed_post",
+            WebhookEventKind::Mention => "mention",
+        }
+    }
+}
+
+impl Display for WebhookEventKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for WebhookEventKind {
+    type Err = InvalidWebhookError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "followed_post" => Ok(WebhookEventKind::FollowedPost),
+            "mention" => Ok(WebhookEventKind::Mention),
+            _ => Err(InvalidWebhookError::Event(s.to_owned())),
+        }
+    }
+}
+
+impl WebhookUrl {
+    pub fn new(url: String) -> Result<Self, InvalidWebhookError> {
+        if url.chars().count() > WEBHOOK_URL_MAX_LEN {
+            return Err(InvalidWebhookError::UrlTooLong);
+        }
+        let host = url
+            .strip_prefix("https://")
+            .or_else(|| url.strip_prefix("http://"));
+        if host.is_none_or(str::is_empty) || url.contains(char::is_whitespace) {
+            return Err(InvalidWebhookError::UnsupportedUrl);
+        }
+
+        Ok(Self(url))
+    }
+
+    #[must_use]
+    pub fn get(&self) -> &str {
+        &self.0
+    }
+
+    #[must_use]
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl<'de> Deserialize<'de> for WebhookUrl {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let inner = String::deserialize(deserializer)?;
+        Self::new(inner).map_err(D::Error::custom)
+    }
+}
+
+impl WebhookSecret {
+    #[must_use]
+    pub fn generate_random() -> Self {
+        let bytes: [u8; WEBHOOK_SECRET_LEN] = rand::random();
+        Self(BASE64_URL_SAFE_NO_PAD.encode(bytes))
+    }
+
+    fn mac(&self, timestamp: i64, body: &[u8]) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.0.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(timestamp.to_string().as_bytes());
+        mac.update(b".");
+        mac.update(body);
+        mac
+    }
+
+    /// The value of the [`WEBHOOK_SIGNATURE_HEADER`] for a request signed at `timestamp`.
+    #[must_use]
+    pub fn sign(&self, timestamp: i64, body: &[u8]) -> String {
+        let signature = self.mac(timestamp, body).finalize().into_bytes();
+        format!("{SIGNATURE_PREFIX}{}", BASE64_STANDARD.encode(signature))
+    }
+
+    /// Checks the value of the [`WEBHOOK_SIGNATURE_HEADER`] in constant time.
+    /// Receivers should also reject timestamps that are too old, so that requests cannot be
+    /// replayed.
+    #[must_use]
+    pub fn verify(&self, timestamp: i64, body: &[u8], signature: &str) -> bool {
+        signature
+            .strip_prefix(SIGNATURE_PREFIX)
+            .and_then(|signature| BASE64_STANDARD.decode(signature).ok())
+            .is_some_and(|signature| self.mac(timestamp, body).verify_slice(&signature).is_ok())
+    }
+}
+
+impl Debug for WebhookSecret {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("WebhookSecret").field(&"[redacted]").finish()
+    }
+}
+
+impl CreateWebhook {
+    pub fn validate(&self) -> Result<(), InvalidWebhookError> {
+        if self.events.is_empty() {
+            return Err(InvalidWebhookError::NoEvents);
+        }
+
+        Ok(())
+    }
+}
+
+impl WebhookPayload {
+    #[must_use]
+    pub fn kind(&self) -> WebhookEventKind {
+        match self {
+            WebhookPayload::FollowedPost { .. } => WebhookEventKind::FollowedPost,
+            WebhookPayload::Mention { .. } => WebhookEventKind::Mention,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::webhook::{InvalidWebhookError, WebhookPayload, WebhookSecret, WebhookUrl};
+    use serde_json::json;
+
+    #[test]
+    fn webhook_url_validation() {
+        assert!(WebhookUrl::new("https://example.com/hook".to_owned()).is_ok());
+        assert!(WebhookUrl::new("http://localhost:3000".to_owned()).is_ok());
+        assert_eq!(
+            WebhookUrl::new("ftp://example.com".to_owned()),
+            Err(InvalidWebhookError::UnsupportedUrl)
+        );
+        assert_eq!(
+            WebhookUrl::new("https://".to_owned()),
+            Err(InvalidWebhookError::UnsupportedUrl)
+        );
+        assert_eq!(
+            WebhookUrl::new("https://example.com/a b".to_owned()),
+            Err(InvalidWebhookError::UnsupportedUrl)
+        );
+    }
+
+    #[test]
+    fn signature_round_trip() {
+        let secret = WebhookSecret("secret".to_owned());
+        let body = br#"{"type":"mention"}"#;
+        let signature = secret.sign(1_700_000_000, body);
+
+        assert!(signature.starts_with("sha256="));
+        assert!(secret.verify(1_700_000_000, body, &signature));
+        assert!(!secret.verify(1_700_000_001, body, &signature));
+        assert!(!WebhookSecret("other".to_owned()).verify(1_700_000_000, body, &signature));
+        assert!(!secret.verify(1_700_000_000, body, "sha256=invalid"));
+        assert_ne!(
+            WebhookSecret::generate_random(),
+            WebhookSecret::generate_random()
+        );
+    }
+
+    #[test]
+    fn payload_serialization() {
+        let payload = WebhookPayload::Mention {
+            post: 2.into(),
+            actor: 1.into(),
+        };
+        assert_eq!(
+            serde_json::to_value(payload).unwrap(),
+            json!({ "type": "mention", "post": "2", "actor": "1" })
+        );
+    }
+}
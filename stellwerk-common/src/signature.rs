@@ -0,0 +1,323 @@
+//! [HTTP signatures](https://datatracker.ietf.org/doc/html/draft-cavage-http-signatures-12)
+//! as used to authenticate ActivityPub federation traffic.
+//!
+//! Only the `rsa-sha256` algorithm is supported, since that is what Mastodon and most other
+//! implementations use.
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use rsa::{
+    Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey,
+    pkcs1::DecodeRsaPublicKey,
+    pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey, LineEnding},
+    rand_core::OsRng,
+    sha2::{Digest, Sha256},
+};
+use std::{
+    fmt::{Debug, Display, Formatter},
+    str::FromStr,
+};
+use thiserror::Error;
+
+pub const RSA_KEY_BITS: usize = 2048;
+pub const ALGORITHM: &str = "rsa-sha256";
+/// The pseudo-header standing for the method and path of the request.
+pub const REQUEST_TARGET: &str = "(request-target)";
+
+#[derive(Eq, PartialEq, Debug, Error)]
+pub enum SignatureError {
+    #[error("RSA operation failed: {0}")]
+    Rsa(#[from] rsa::Error),
+    #[error("The private key is not a valid PKCS#8 PEM document: {0}")]
+    PrivateKey(#[from] rsa::pkcs8::Error),
+    #[error("The public key is not a valid PEM document: {0}")]
+    PublicKey(#[from] rsa::pkcs8::spki::Error),
+    #[error("The Signature header is malformed")]
+    MalformedHeader,
+    #[error("The signature algorithm {0} is not supported")]
+    UnsupportedAlgorithm(String),
+    #[error("The signed header {0} is missing from the request")]
+    MissingHeader(String),
+    #[error("The signature does not cover the {0} header")]
+    UncoveredHeader(&'static str),
+    #[error("The signature does not match")]
+    Mismatch,
+}
+
+/// An RSA key pair of an actor, PEM encoded.
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub struct KeyPair {
+    /// SPKI encoded.
+    pub public_key_pem: String,
+    /// PKCS#8 encoded.
+    pub private_key_pem: String,
+}
+
+impl KeyPair {
+    /// Generating a key is slow, so avoid calling this in async contexts directly.
+    pub fn generate() -> Result<Self, SignatureError> {
+        let private_key = RsaPrivateKey::new(&mut OsRng, RSA_KEY_BITS)?;
+        let public_key_pem = private_key
+            .to_public_key()
+            .to_public_key_pem(LineEnding::LF)?;
+        let private_key_pem = private_key.to_pkcs8_pem(LineEnding::LF)?.to_string();
+
+        Ok(Self {
+            public_key_pem,
+            private_key_pem,
+        })
+    }
+}
+
+impl Debug for KeyPair {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyPair")
+            .field("public_key_pem", &self.public_key_pem)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The value of a `Signature` header.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Signature {
+    pub key_id: String,
+    pub algorithm: String,
+    /// Lowercase names of the signed headers, in order.
+    pub headers: Vec<String>,
+    pub signature: Vec<u8>,
+}
+
+impl Signature {
+    /// Signs the headers named in `headers`. `lookup` returns the value of a header by its
+    /// lowercase name, with multiple values joined by `", "`.
+    pub fn sign(
+        key_id: String,
+        private_key_pem: &str,
+        headers: Vec<String>,
+        method: &str,
+        path_and_query: &str,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, SignatureError> {
+        let signing_string = signing_string(&headers, method, path_and_query, lookup)?;
+        let private_key = RsaPrivateKey::from_pkcs8_pem(private_key_pem)?;
+        let signature = private_key.sign(
+            Pkcs1v15Sign::new::<Sha256>(),
+            &Sha256::digest(signing_string),
+        )?;
+
+        Ok(Self {
+            key_id,
+            algorithm: ALGORITHM.to_owned(),
+            headers,
+            signature,
+        })
+    }
+
+    /// Verifies the signature against a request, see [`Signature::sign`].
+    /// Accepts SPKI as well as PKCS#1 encoded public keys.
+    pub fn verify(
+        &self,
+        public_key_pem: &str,
+        method: &str,
+        path_and_query: &str,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), SignatureError> {
+        // `hs2019` leaves the algorithm to the key, which in practice is always RSA.
+        if self.algorithm != ALGORITHM && self.algorithm != "hs2019" {
+            return Err(SignatureError::UnsupportedAlgorithm(self.algorithm.clone()));
+        }
+
+        let signing_string = signing_string(&self.headers, method, path_and_query, lookup)?;
+        let public_key = RsaPublicKey::from_public_key_pem(public_key_pem)
+            .or_else(|error| RsaPublicKey::from_pkcs1_pem(public_key_pem).map_err(|_| error))?;
+
+        public_key
+            .verify(
+                Pkcs1v15Sign::new::<Sha256>(),
+                &Sha256::digest(signing_string),
+                &self.signature,
+            )
+            .map_err(|_| SignatureError::Mismatch)
+    }
+
+    /// Fails with [`SignatureError::UncoveredHeader`] if any of `headers` is not signed.
+    pub fn require_headers(&self, headers: &[&'static str]) -> Result<(), SignatureError> {
+        match headers
+            .iter()
+            .find(|&&header| !self.headers.iter().any(|signed| signed == header))
+        {
+            Some(header) => Err(SignatureError::UncoveredHeader(header)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Display for Signature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "keyId=\"{}\",algorithm=\"{}\",headers=\"{}\",signature=\"{}\"",
+            self.key_id,
+            self.algorithm,
+            self.headers.join(" "),
+            BASE64_STANDARD.encode(&self.signature),
+        )
+    }
+}
+
+impl FromStr for Signature {
+    type Err = SignatureError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut key_id = None;
+        let mut algorithm = None;
+        let mut headers = None;
+        let mut signature = None;
+
+        let mut rest = s.trim();
+        while !rest.is_empty() {
+            let (name, value) = rest
+                .split_once('=')
+                .ok_or(SignatureError::MalformedHeader)?;
+            let value = if let Some(quoted) = value.strip_prefix('"') {
+                let (value, after) = quoted
+                    .split_once('"')
+                    .ok_or(SignatureError::MalformedHeader)?;
+                rest = after;
+                value
+            } else {
+                // Unquoted values like `created` are not relevant to us.
+                let (value, after) = value.split_once(',').unwrap_or((value, ""));
+                rest = after;
+                value
+            };
+            rest = rest.trim_start().trim_start_matches(',').trim_start();
+
+            match name.trim() {
+                "keyId" => key_id = Some(value.to_owned()),
+                "algorithm" => algorithm = Some(value.to_owned()),
+                "headers" => headers = Some(value.to_lowercase()),
+                "signature" => {
+                    signature = Some(
+                        BASE64_STANDARD
+                            .decode(value)
+                            .map_err(|_| SignatureError::MalformedHeader)?,
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            key_id: key_id.ok_or(SignatureError::MalformedHeader)?,
+            algorithm: algorithm.unwrap_or_else(|| ALGORITHM.to_owned()),
+            headers: headers
+                .as_deref()
+                .unwrap_or("date")
+                .split_whitespace()
+                .map(str::to_owned)
+                .collect(),
+            signature: signature.ok_or(SignatureError::MalformedHeader)?,
+        })
+    }
+}
+
+fn signing_string(
+    headers: &[String],
+    method: &str,
+    path_and_query: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String, SignatureError> {
+    headers
+        .iter()
+        .map(|header| {
+            if header == REQUEST_TARGET {
+                Ok(format!(
+                    "{REQUEST_TARGET}: {} {path_and_query}",
+                    method.to_lowercase()
+                ))
+            } else {
+                let value =
+                    lookup(header).ok_or_else(|| SignatureError::MissingHeader(header.clone()))?;
+                Ok(format!("{header}: {}", value.trim()))
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(|lines| lines.join("\n"))
+}
+
+/// The value of a `Digest` header for `body`.
+#[must_use]
+pub fn digest(body: &[u8]) -> String {
+    format!("SHA-256={}", BASE64_STANDARD.encode(Sha256::digest(body)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::signature::{KeyPair, Signature, SignatureError, digest};
+    use std::collections::HashMap;
+
+    #[test]
+    fn header_round_trip() {
+        let header = "keyId=\"https://remote.example/users/a#main-key\", algorithm=\"rsa-sha256\",\
+            headers=\"(request-target) Host date\",signature=\"AAEC\"";
+        let signature: Signature = header.parse().unwrap();
+
+        assert_eq!(signature.key_id, "https://remote.example/users/a#main-key");
+        assert_eq!(signature.headers, ["(request-target)", "host", "date"]);
+        assert_eq!(signature.signature, [0, 1, 2]);
+        assert_eq!(
+            signature.to_string().parse::<Signature>().unwrap(),
+            signature
+        );
+
+        assert_eq!(
+            "algorithm=\"rsa-sha256\"".parse::<Signature>(),
+            Err(SignatureError::MalformedHeader)
+        );
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let key_pair = KeyPair::generate().unwrap();
+        let mut headers = HashMap::from([
+            ("host", "stellwerk.example".to_owned()),
+            ("date", "Tue, 07 Jun 2014 20:51:35 GMT".to_owned()),
+            ("digest", digest(b"{}")),
+        ]);
+        let signed_headers = ["(request-target)", "host", "date", "digest"]
+            .map(str::to_owned)
+            .to_vec();
+
+        let signature = Signature::sign(
+            "key".to_owned(),
+            &key_pair.private_key_pem,
+            signed_headers,
+            "POST",
+            "/inbox",
+            |name| headers.get(name).cloned(),
+        )
+        .unwrap();
+        let verify = |headers: &HashMap<_, String>, path| {
+            signature.verify(&key_pair.public_key_pem, "POST", path, |name| {
+                headers.get(name).cloned()
+            })
+        };
+
+        assert_eq!(verify(&headers, "/inbox"), Ok(()));
+        assert_eq!(signature.require_headers(&["digest"]), Ok(()));
+        assert_eq!(
+            signature.require_headers(&["content-type"]),
+            Err(SignatureError::UncoveredHeader("content-type"))
+        );
+        assert_eq!(verify(&headers, "/outbox"), Err(SignatureError::Mismatch));
+
+        headers.insert("digest", digest(b"{\"forged\":true}"));
+        assert_eq!(verify(&headers, "/inbox"), Err(SignatureError::Mismatch));
+
+        headers.remove("host");
+        assert_eq!(
+            verify(&headers, "/inbox"),
+            Err(SignatureError::MissingHeader("host".to_owned()))
+        );
+    }
+}
@@ -10,10 +10,101 @@ use serde::{
 use std::{
     fmt::{Debug, Display, Formatter},
     marker::PhantomData,
+    ops::Range,
+    sync::atomic::{AtomicU64, Ordering},
 };
 use thiserror::Error;
 use time::{Duration, UtcDateTime};
 
+/// (De)serializes snowflakes, or anything converting from and to them like [`Id`](crate::model::Id),
+/// as decimal strings, since numbers above 2^53 lose precision in JavaScript.
+/// Integers are still accepted when deserializing, for clients sending them before,
+/// and so are [base62](Snowflake::to_base62) strings, for short URLs.
+/// Strings of only digits are always decimal.
+///
+/// Use with `#[serde(with = "snowflake::string")]`. It needs a self-describing format like JSON.
+pub mod string {
+    use serde::{
+        Deserializer, Serializer,
+        de::{Error, Unexpected, Visitor},
+    };
+    use std::fmt::Formatter;
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Copy + Into<u64>,
+        S: Serializer,
+    {
+        serializer.collect_str(&(*value).into())
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: From<u64>,
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(SnowflakeVisitor).map(T::from)
+    }
+
+    struct SnowflakeVisitor;
+
+    impl Visitor<'_> for SnowflakeVisitor {
+        type Value = u64;
+
+        fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+            formatter.write_str("a snowflake as a decimal or base62 string, or an integer")
+        }
+
+        fn visit_u64<E: Error>(self, v: u64) -> Result<Self::Value, E> {
+            Ok(v)
+        }
+
+        fn visit_i64<E: Error>(self, v: i64) -> Result<Self::Value, E> {
+            u64::try_from(v).map_err(|_| E::invalid_value(Unexpected::Signed(v), &self))
+        }
+
+        fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+            let parsed = if v.bytes().all(|byte| byte.is_ascii_digit()) {
+                v.parse().ok()
+            } else {
+                super::from_base62(v).ok()
+            };
+            parsed.ok_or_else(|| E::invalid_value(Unexpected::Str(v), &self))
+        }
+    }
+}
+
+const BASE62_DIGITS: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Error)]
+pub enum InvalidBase62Error {
+    #[error("The base62 string is empty")]
+    Empty,
+    #[error("The base62 string contains the invalid character {0:?}")]
+    InvalidCharacter(char),
+    #[error("The base62 string is too large for a snowflake")]
+    Overflow,
+}
+
+fn from_base62(value: &str) -> Result<u64, InvalidBase62Error> {
+    if value.is_empty() {
+        return Err(InvalidBase62Error::Empty);
+    }
+
+    value.chars().try_fold(0_u64, |number, character| {
+        let digit = match character {
+            '0'..='9' => u64::from(character) - u64::from('0'),
+            'A'..='Z' => u64::from(character) - u64::from('A') + 10,
+            'a'..='z' => u64::from(character) - u64::from('a') + 36,
+            _ => return Err(InvalidBase62Error::InvalidCharacter(character)),
+        };
+        number
+            .checked_mul(62)
+            .and_then(|number| number.checked_add(digit))
+            .ok_or(InvalidBase62Error::Overflow)
+    })
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Error)]
 pub enum SnowflakeTimestampFromDateTimeError {
     #[error("Specified time was before the snowflake epoch.")]
@@ -68,14 +159,21 @@ macro_rules! __snowflake_part_impls {
             pub fn get(self) -> $repr {
                 self.0
             }
+
+            /// The part of `snowflake`, whose bits are masked and shifted down.
+            fn extract(snowflake: u64) -> Self {
+                <$repr>::try_from((snowflake & Self::SNOWFLAKE_BITMASK) >> Self::SNOWFLAKE_OFFSET)
+                    .ok()
+                    .and_then(Self::new)
+                    .expect(concat!("Masked ", stringify!($name), " out of range."))
+            }
         }
 
+        /// Deprecated, use [`Snowflake::decompose`] instead.
+        /// Trait implementations cannot be marked `#[deprecated]`, so this is only documented.
         impl<SnowflakeEpoch> From<Snowflake<SnowflakeEpoch>> for $name$(<$generic>)? {
             fn from(value: Snowflake<SnowflakeEpoch>) -> Self {
-                #[allow(clippy::cast_possible_truncation)]
-                Self::new_unchecked(
-                    ((value.get() & Self::SNOWFLAKE_BITMASK) >> Self::SNOWFLAKE_OFFSET) as $repr
-                )
+                Self::extract(value.get())
             }
         }
 
@@ -106,6 +204,28 @@ snowflake_part!(ProcessId: u8 = snowflake & 0x0000_0000_0001_F000);
 snowflake_part!(SnowflakeIncrement: u16 = snowflake & 0x0000_0000_0000_0FFF);
 snowflake_part!(SnowflakeTimestamp<SnowflakeEpoch>: u64 = snowflake & 0xFFFF_FFFF_FFC0_0000);
 
+/// The parts of a [`Snowflake`], see [`Snowflake::decompose`].
+#[derive_where(Copy, Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub struct SnowflakeParts<SnowflakeEpoch> {
+    pub timestamp: SnowflakeTimestamp<SnowflakeEpoch>,
+    pub worker_id: WorkerId,
+    pub process_id: ProcessId,
+    pub increment: SnowflakeIncrement,
+}
+
+impl<SnowflakeEpoch> SnowflakeParts<SnowflakeEpoch> {
+    /// The snowflake of the parts, the inverse of [`Snowflake::decompose`].
+    #[must_use]
+    pub fn compose(self) -> Snowflake<SnowflakeEpoch> {
+        Snowflake::from_parts(
+            self.timestamp,
+            self.worker_id,
+            self.process_id,
+            self.increment,
+        )
+    }
+}
+
 #[derive_where(
     Copy,
     Clone,
@@ -204,24 +324,96 @@ impl<SnowflakeEpoch> Snowflake<SnowflakeEpoch> {
         self.0
     }
 
+    /// The smallest snowflake with the timestamp of `time`,
+    /// so that exactly the snowflakes generated at or after `time`, to the millisecond, are at least as large.
+    pub fn min_for_time(time: UtcDateTime) -> Result<Self, SnowflakeTimestampFromDateTimeError>
+    where
+        SnowflakeEpoch: Epoch,
+    {
+        Ok(Self::from_parts(
+            time.try_into()?,
+            WorkerId::new_unchecked(0),
+            ProcessId::new_unchecked(0),
+            SnowflakeIncrement::new_unchecked(0),
+        ))
+    }
+
+    /// The largest snowflake with the timestamp of `time`,
+    /// so that exactly the snowflakes generated at or before `time`, to the millisecond, are at most as large.
+    pub fn max_for_time(time: UtcDateTime) -> Result<Self, SnowflakeTimestampFromDateTimeError>
+    where
+        SnowflakeEpoch: Epoch,
+    {
+        Ok(Self::from_parts(
+            time.try_into()?,
+            WorkerId::new_unchecked(WorkerId::MAX_VALUE),
+            ProcessId::new_unchecked(ProcessId::MAX_VALUE),
+            SnowflakeIncrement::new_unchecked(SnowflakeIncrement::MAX_VALUE),
+        ))
+    }
+
+    /// The range of the snowflakes generated within `times`, to the millisecond,
+    /// for finding them by ID instead of by a separate timestamp.
+    pub fn range_for_times(
+        times: Range<UtcDateTime>,
+    ) -> Result<Range<Self>, SnowflakeTimestampFromDateTimeError>
+    where
+        SnowflakeEpoch: Epoch,
+    {
+        Ok(Self::min_for_time(times.start)?..Self::min_for_time(times.end)?)
+    }
+
+    /// The snowflake in base 62, with the digits `0-9A-Za-z`, like `LygHa16AHYF` for `u64::MAX`.
+    #[must_use]
+    pub fn to_base62(self) -> String {
+        let mut number = self.0;
+        let mut digits = Vec::new();
+        loop {
+            digits.push(BASE62_DIGITS[(number % 62) as usize]);
+            number /= 62;
+            if number == 0 {
+                break;
+            }
+        }
+        digits.reverse();
+
+        String::from_utf8(digits).expect("Base62 digits are ASCII")
+    }
+
+    pub fn from_base62(value: &str) -> Result<Self, InvalidBase62Error> {
+        from_base62(value).map(Self::new)
+    }
+
+    /// Splits the snowflake into its parts. Every `u64` is a valid snowflake,
+    /// so this cannot fail, and [composing](SnowflakeParts::compose) the parts gives back the snowflake.
+    #[must_use]
+    pub fn decompose(self) -> SnowflakeParts<SnowflakeEpoch> {
+        SnowflakeParts {
+            timestamp: SnowflakeTimestamp::extract(self.0),
+            worker_id: WorkerId::extract(self.0),
+            process_id: ProcessId::extract(self.0),
+            increment: SnowflakeIncrement::extract(self.0),
+        }
+    }
+
     #[must_use]
     pub fn timestamp(self) -> SnowflakeTimestamp<SnowflakeEpoch> {
-        self.into()
+        self.decompose().timestamp
     }
 
     #[must_use]
     pub fn worker_id(self) -> WorkerId {
-        self.into()
+        self.decompose().worker_id
     }
 
     #[must_use]
     pub fn process_id(self) -> ProcessId {
-        self.into()
+        self.decompose().process_id
     }
 
     #[must_use]
     pub fn increment(self) -> SnowflakeIncrement {
-        self.into()
+        self.decompose().increment
     }
 
     #[must_use]
@@ -233,11 +425,12 @@ impl<SnowflakeEpoch> Snowflake<SnowflakeEpoch> {
         ProcessId,
         SnowflakeIncrement,
     ) {
+        let parts = self.decompose();
         (
-            self.timestamp(),
-            self.worker_id(),
-            self.process_id(),
-            self.increment(),
+            parts.timestamp,
+            parts.worker_id,
+            parts.process_id,
+            parts.increment,
         )
     }
 }
@@ -314,12 +507,165 @@ impl<SnowflakeEpoch> SnowflakeGenerator<SnowflakeEpoch> {
     }
 }
 
+/// The clock was further behind the latest time an [`AtomicSnowflakeGenerator`] saw than it
+/// tolerates, so snowflakes could not be generated without straying far from the current time.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Error)]
+#[error("The clock moved backwards by {behind_ms} ms")]
+pub struct ClockMovedBackwardsError {
+    pub behind_ms: u64,
+}
+
+/// How often the clock was behind the latest time an [`AtomicSnowflakeGenerator`] saw.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Deserialize, Serialize)]
+pub struct ClockRegressions {
+    /// Snowflakes that were generated with the latest time instead.
+    pub tolerated: u64,
+    /// Snowflakes that failed with [`ClockMovedBackwardsError`].
+    pub rejected: u64,
+    /// The furthest the clock was behind, in milliseconds.
+    pub max_behind_ms: u64,
+}
+
+/// A [`SnowflakeGenerator`] that can be shared between threads without a lock.
+///
+/// The timestamp and increment of the next snowflake are packed into a single atomic,
+/// laid out like in the snowflake but without the worker and process ID.
+/// Every snowflake takes the next increment, or increment 0 of the current time if that is later,
+/// so the generated snowflakes are strictly increasing. If more snowflakes than there are increments
+/// are generated within a millisecond, the increment carries over into the next millisecond.
+///
+/// If the clock moves backwards, snowflakes continue with the latest time the generator saw,
+/// as long as the clock is behind by at most the [tolerance](Self::with_clock_regression_tolerance).
+/// Beyond that, generating fails until the clock caught up.
+#[derive_where(Debug)]
+pub struct AtomicSnowflakeGenerator<SnowflakeEpoch> {
+    worker_id: WorkerId,
+    process_id: ProcessId,
+    next: AtomicU64,
+    /// The latest timestamp generating was called with.
+    latest_timestamp: AtomicU64,
+    clock_regression_tolerance_ms: u64,
+    tolerated_regressions: AtomicU64,
+    rejected_regressions: AtomicU64,
+    max_behind_ms: AtomicU64,
+    phantom_data: PhantomData<fn() -> SnowflakeEpoch>,
+}
+
+impl<SnowflakeEpoch> AtomicSnowflakeGenerator<SnowflakeEpoch> {
+    /// How far the clock may be behind by default.
+    pub const DEFAULT_CLOCK_REGRESSION_TOLERANCE: Duration = Duration::SECOND;
+
+    #[must_use]
+    pub fn new(worker_id: WorkerId, process_id: ProcessId) -> Self {
+        Self {
+            worker_id,
+            process_id,
+            next: AtomicU64::new(0),
+            latest_timestamp: AtomicU64::new(0),
+            clock_regression_tolerance_ms: whole_millis(Self::DEFAULT_CLOCK_REGRESSION_TOLERANCE),
+            tolerated_regressions: AtomicU64::new(0),
+            rejected_regressions: AtomicU64::new(0),
+            max_behind_ms: AtomicU64::new(0),
+            phantom_data: PhantomData,
+        }
+    }
+
+    /// Sets how far the clock may be behind the latest time the generator saw before generating
+    /// fails with [`ClockMovedBackwardsError`]. Negative tolerances are treated as 0.
+    #[must_use]
+    pub fn with_clock_regression_tolerance(self, tolerance: Duration) -> Self {
+        Self {
+            clock_regression_tolerance_ms: whole_millis(tolerance),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn worker_id(&self) -> WorkerId {
+        self.worker_id
+    }
+
+    #[must_use]
+    pub fn process_id(&self) -> ProcessId {
+        self.process_id
+    }
+
+    /// Since the generator was created.
+    #[must_use]
+    pub fn clock_regressions(&self) -> ClockRegressions {
+        ClockRegressions {
+            tolerated: self.tolerated_regressions.load(Ordering::Relaxed),
+            rejected: self.rejected_regressions.load(Ordering::Relaxed),
+            max_behind_ms: self.max_behind_ms.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn generate_at(
+        &self,
+        time: UtcDateTime,
+    ) -> Result<Snowflake<SnowflakeEpoch>, ClockMovedBackwardsError>
+    where
+        SnowflakeEpoch: Epoch,
+    {
+        let timestamp = SnowflakeTimestamp::<SnowflakeEpoch>::from_time_unchecked(time).get();
+        let latest_timestamp = self
+            .latest_timestamp
+            .fetch_max(timestamp, Ordering::Relaxed);
+        if timestamp < latest_timestamp {
+            let behind_ms = latest_timestamp - timestamp;
+            self.max_behind_ms.fetch_max(behind_ms, Ordering::Relaxed);
+            if behind_ms > self.clock_regression_tolerance_ms {
+                self.rejected_regressions.fetch_add(1, Ordering::Relaxed);
+                return Err(ClockMovedBackwardsError { behind_ms });
+            }
+            self.tolerated_regressions.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let earliest = timestamp << SnowflakeIncrement::BIT_COUNT;
+        let previous = self
+            .next
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |next| {
+                Some(next.max(earliest) + 1)
+            })
+            .expect("The update always succeeds");
+        let packed = previous.max(earliest);
+        let increment = u16::try_from(packed & u64::from(SnowflakeIncrement::MAX_VALUE))
+            .expect("The increment is masked");
+
+        Ok(Snowflake::from_parts(
+            SnowflakeTimestamp::new_unchecked(packed >> SnowflakeIncrement::BIT_COUNT),
+            self.worker_id,
+            self.process_id,
+            SnowflakeIncrement::new_unchecked(increment),
+        ))
+    }
+
+    pub fn generate(&self) -> Result<Snowflake<SnowflakeEpoch>, ClockMovedBackwardsError>
+    where
+        SnowflakeEpoch: Epoch,
+    {
+        self.generate_at(UtcDateTime::now())
+    }
+}
+
+/// 0 if negative.
+fn whole_millis(duration: Duration) -> u64 {
+    u64::try_from(duration.whole_milliseconds()).unwrap_or(0)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::snowflake::{
-        Epoch, ProcessId, Snowflake, SnowflakeGenerator, SnowflakeIncrement, SnowflakeTimestamp,
-        SnowflakeTimestampFromDateTimeError, WorkerId,
+    use crate::{
+        model::Id,
+        snowflake::{
+            AtomicSnowflakeGenerator, ClockMovedBackwardsError, ClockRegressions, Epoch,
+            InvalidBase62Error, ProcessId, Snowflake, SnowflakeGenerator, SnowflakeIncrement,
+            SnowflakeParts, SnowflakeTimestamp, SnowflakeTimestampFromDateTimeError, WorkerId,
+        },
     };
+    use proptest::{prop_assert_eq, proptest};
+    use serde_json::json;
+    use std::{collections::HashSet, sync::Arc, thread};
     use time::{Duration, UtcDateTime, macros::utc_datetime};
 
     struct MillennialEpoch;
@@ -436,6 +782,37 @@ mod tests {
         assert_eq!(snowflake.increment(), increment);
     }
 
+    proptest! {
+        #[test]
+        fn decompose_compose_round_trip(snowflake: u64) {
+            let snowflake = Snowflake::<MillennialEpoch>::new(snowflake);
+            let (timestamp, worker_id, process_id, increment) = snowflake.into_parts();
+
+            prop_assert_eq!(snowflake.decompose().compose(), snowflake);
+            prop_assert_eq!(
+                Snowflake::from_parts(timestamp, worker_id, process_id, increment),
+                snowflake
+            );
+        }
+
+        #[test]
+        fn compose_decompose_round_trip(
+            timestamp in 0..=0x03FF_FFFF_FFFF_u64,
+            worker_id in 0..=0x1F_u8,
+            process_id in 0..=0x1F_u8,
+            increment in 0..=0x0FFF_u16,
+        ) {
+            let parts = SnowflakeParts::<MillennialEpoch> {
+                timestamp: SnowflakeTimestamp::new_unchecked(timestamp),
+                worker_id: WorkerId::new_unchecked(worker_id),
+                process_id: ProcessId::new_unchecked(process_id),
+                increment: SnowflakeIncrement::new_unchecked(increment),
+            };
+
+            prop_assert_eq!(parts.compose().decompose(), parts);
+        }
+    }
+
     #[test]
     fn snowflake_generator() {
         let worker_id = WorkerId::new_unchecked(10);
@@ -466,4 +843,195 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn atomic_snowflake_generator() {
+        let worker_id = WorkerId::new_unchecked(10);
+        let process_id = ProcessId::new_unchecked(3);
+        let time = utc_datetime!(2025-10-24 10:55);
+        let snowflake = |time, increment| {
+            Snowflake::<MillennialEpoch>::from_parts(
+                SnowflakeTimestamp::from_time_unchecked(time),
+                worker_id,
+                process_id,
+                SnowflakeIncrement::new_unchecked(increment),
+            )
+        };
+
+        let generator = AtomicSnowflakeGenerator::<MillennialEpoch>::new(worker_id, process_id);
+
+        assert_eq!(generator.generate_at(time), Ok(snowflake(time, 0)));
+        assert_eq!(generator.generate_at(time), Ok(snowflake(time, 1)));
+
+        // A later time starts over at increment 0, an earlier one continues with the last time.
+        let later = time + Duration::milliseconds(5);
+        assert_eq!(generator.generate_at(later), Ok(snowflake(later, 0)));
+        assert_eq!(generator.generate_at(time), Ok(snowflake(later, 1)));
+
+        // Running out of increments carries over into the next millisecond.
+        for _ in 2..=0xFFF {
+            generator.generate_at(later).unwrap();
+        }
+        assert_eq!(
+            generator.generate_at(later),
+            Ok(snowflake(later + Duration::milliseconds(1), 0))
+        );
+    }
+
+    #[test]
+    fn clock_regressions() {
+        let generator = AtomicSnowflakeGenerator::<MillennialEpoch>::new(
+            WorkerId::new_unchecked(1),
+            ProcessId::new_unchecked(1),
+        )
+        .with_clock_regression_tolerance(Duration::milliseconds(100));
+        let time = utc_datetime!(2025-10-24 10:55);
+
+        let first = generator.generate_at(time).unwrap();
+        let tolerated = generator
+            .generate_at(time - Duration::milliseconds(100))
+            .unwrap();
+        assert!(tolerated > first);
+        assert_eq!(tolerated.timestamp(), first.timestamp());
+
+        assert_eq!(
+            generator.generate_at(time - Duration::milliseconds(101)),
+            Err(ClockMovedBackwardsError { behind_ms: 101 })
+        );
+        assert!(generator.generate_at(time).unwrap() > tolerated);
+
+        assert_eq!(
+            generator.clock_regressions(),
+            ClockRegressions {
+                tolerated: 1,
+                rejected: 1,
+                max_behind_ms: 101,
+            }
+        );
+    }
+
+    #[test]
+    fn atomic_snowflake_generator_threads() {
+        let generator = Arc::new(AtomicSnowflakeGenerator::<MillennialEpoch>::new(
+            WorkerId::new_unchecked(1),
+            ProcessId::new_unchecked(1),
+        ));
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let generator = generator.clone();
+                thread::spawn(move || {
+                    (0..10_000)
+                        .map(|_| generator.generate().unwrap())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let mut snowflakes = HashSet::new();
+        for thread in threads {
+            let generated = thread.join().unwrap();
+            assert!(generated.is_sorted_by(|a, b| a < b));
+            snowflakes.extend(generated);
+        }
+        assert_eq!(snowflakes.len(), 40_000);
+    }
+
+    #[test]
+    fn string_serialization() {
+        let id = Id::<()>::from(236_513_256_749_924_352);
+
+        assert_eq!(
+            serde_json::to_value(id).unwrap(),
+            json!("236513256749924352")
+        );
+        assert_eq!(
+            serde_json::from_value::<Id<()>>(json!("236513256749924352")).unwrap(),
+            id
+        );
+        assert_eq!(
+            serde_json::from_value::<Id<()>>(json!(236_513_256_749_924_352_u64)).unwrap(),
+            id
+        );
+
+        assert_eq!(
+            serde_json::from_value::<Id<()>>(json!("HTESW517J2")).unwrap(),
+            id
+        );
+        // Only digits are decimal, even though they are valid base62.
+        assert_eq!(
+            serde_json::from_value::<Id<()>>(json!("10")).unwrap(),
+            Id::from(10)
+        );
+
+        for invalid in [
+            json!(-1),
+            json!("-1"),
+            json!("12-a"),
+            json!(""),
+            json!(1.5),
+            json!(null),
+        ] {
+            assert!(serde_json::from_value::<Id<()>>(invalid).is_err());
+        }
+    }
+
+    #[test]
+    fn time_bounds() {
+        let time = utc_datetime!(2000-1-1 00:00:01.5);
+        let min = Snowflake::<MillennialEpoch>::min_for_time(time).unwrap();
+        let max = Snowflake::<MillennialEpoch>::max_for_time(time).unwrap();
+        assert_eq!(min.get(), 1500 << 22);
+        assert_eq!(max.get(), (1501 << 22) - 1);
+        assert_eq!(min.timestamp(), max.timestamp());
+
+        let mut generator = SnowflakeGenerator::<MillennialEpoch>::new(
+            WorkerId::new_unchecked(0b10101),
+            ProcessId::new_unchecked(0b10001),
+        );
+        let snowflake = generator.generate_at(time + Duration::microseconds(999));
+        assert!((min..=max).contains(&snowflake));
+
+        let range = Snowflake::<MillennialEpoch>::range_for_times(
+            time - Duration::seconds(1)..time + Duration::milliseconds(1),
+        )
+        .unwrap();
+        assert_eq!(range.start.get(), 500 << 22);
+        assert_eq!(range.end.get(), 1501 << 22);
+        assert!(range.contains(&snowflake));
+        assert!(!range.contains(&generator.generate_at(time + Duration::milliseconds(1))));
+
+        assert_eq!(
+            Snowflake::<MillennialEpoch>::min_for_time(utc_datetime!(1999-12-31 23:59)),
+            Err(SnowflakeTimestampFromDateTimeError::TimeBeforeEpoch)
+        );
+    }
+
+    #[test]
+    fn base62() {
+        for (number, base62) in [
+            (0, "0"),
+            (61, "z"),
+            (62, "10"),
+            (236_513_256_749_924_352, "HTESW517J2"),
+            (u64::MAX, "LygHa16AHYF"),
+        ] {
+            let snowflake = Snowflake::<MillennialEpoch>::new(number);
+            assert_eq!(snowflake.to_base62(), base62);
+            assert_eq!(Snowflake::from_base62(base62), Ok(snowflake));
+        }
+
+        assert_eq!(
+            Snowflake::<MillennialEpoch>::from_base62(""),
+            Err(InvalidBase62Error::Empty)
+        );
+        assert_eq!(
+            Snowflake::<MillennialEpoch>::from_base62("4fZ-9"),
+            Err(InvalidBase62Error::InvalidCharacter('-'))
+        );
+        assert_eq!(
+            Snowflake::<MillennialEpoch>::from_base62("LygHa16AHYG"),
+            Err(InvalidBase62Error::Overflow)
+        );
+    }
 }
@@ -0,0 +1,358 @@
+//! Rendering of plain post content into sanitized HTML.
+//!
+//! All user provided text is escaped, and the only markup in the output is generated here:
+//! paragraphs, line breaks, links, mentions, hashtags,
+//! and a limited subset of Markdown (`**strong**`, `*emphasis*` and `` `code` ``).
+//!
+//! HTML from other servers goes the opposite way through [`html_to_text`] first.
+
+use std::fmt::Write;
+
+const URL_SCHEMES: [&str; 2] = ["https://", "http://"];
+const URL_TRAILING_PUNCTUATION: &[char] = &['.', ',', ';', ':', '!', '?', ')', '"', '\''];
+
+/// Renders plain text into sanitized HTML.
+#[must_use]
+pub fn render_html(content: &str) -> String {
+    let normalized = content.replace("\r\n", "\n");
+    let mut out = String::with_capacity(normalized.len() * 2);
+
+    for paragraph in normalized
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|paragraph| !paragraph.is_empty())
+    {
+        out.push_str("<p>");
+        for (i, line) in paragraph.lines().enumerate() {
+            if i > 0 {
+                out.push_str("<br>");
+            }
+            render_inline(line, &mut out);
+        }
+        out.push_str("</p>");
+    }
+
+    out
+}
+
+/// Returns the handles mentioned in `content` in order of appearance, without duplicates.
+/// Mentions are recognized like in [`render_html`], except that code spans are not skipped.
+#[must_use]
+pub fn mentions(content: &str) -> Vec<&str> {
+    let mut handles = Vec::new();
+    let mut previous: Option<char> = None;
+
+    for (i, c) in content.char_indices() {
+        let at_boundary = previous.is_none_or(|previous| !is_word_char(previous));
+        previous = Some(c);
+        if !at_boundary || c != '@' {
+            continue;
+        }
+
+        let rest = &content[i + 1..];
+        let handle = &rest[..rest.find(|c| !is_word_char(c)).unwrap_or(rest.len())];
+        if !handle.is_empty() && !handles.contains(&handle) {
+            handles.push(handle);
+        }
+    }
+
+    handles
+}
+
+/// Returns the URLs linked in `content` in order of appearance, without duplicates.
+/// Links are recognized like in [`render_html`], except that code spans are not skipped.
+#[must_use]
+pub fn links(content: &str) -> Vec<&str> {
+    let mut urls = Vec::new();
+    let mut previous: Option<char> = None;
+
+    for (i, c) in content.char_indices() {
+        let at_boundary = previous.is_none_or(|previous| !is_word_char(previous));
+        previous = Some(c);
+        let rest = &content[i..];
+        if !at_boundary || !URL_SCHEMES.iter().any(|scheme| rest.starts_with(scheme)) {
+            continue;
+        }
+
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let url = rest[..end].trim_end_matches(URL_TRAILING_PUNCTUATION);
+        if !URL_SCHEMES.contains(&url) && !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+
+    urls
+}
+
+fn render_inline(text: &str, out: &mut String) {
+    let mut rest = text;
+    let mut previous: Option<char> = None;
+
+    while let Some(next) = rest.chars().next() {
+        let at_boundary = previous.is_none_or(|previous| !is_word_char(previous));
+
+        let consumed = if next == '`' {
+            render_delimited(rest, "`", "code", out, escape_into)
+        } else if rest.starts_with("**") {
+            render_delimited(rest, "**", "strong", out, render_inline)
+        } else if next == '*' {
+            render_delimited(rest, "*", "em", out, render_inline)
+        } else if at_boundary && URL_SCHEMES.iter().any(|scheme| rest.starts_with(scheme)) {
+            render_link(rest, out)
+        } else if at_boundary && next == '@' {
+            render_tag(rest, out, "mention", "/@")
+        } else if at_boundary && next == '#' {
+            render_tag(rest, out, "hashtag", "/tags/")
+        } else {
+            None
+        };
+
+        let consumed = consumed.unwrap_or_else(|| {
+            escape_into(&rest[..next.len_utf8()], out);
+            next.len_utf8()
+        });
+
+        previous = rest[..consumed].chars().next_back();
+        rest = &rest[consumed..];
+    }
+}
+
+/// Renders `text` starting with `delimiter` if there is a matching closing delimiter.
+/// Returns the number of consumed bytes.
+fn render_delimited(
+    text: &str,
+    delimiter: &str,
+    tag: &str,
+    out: &mut String,
+    render_inner: impl FnOnce(&str, &mut String),
+) -> Option<usize> {
+    let after_open = &text[delimiter.len()..];
+    let inner_len = after_open.find(delimiter)?;
+    let inner = &after_open[..inner_len];
+    if inner.is_empty()
+        || inner.starts_with(char::is_whitespace)
+        || inner.ends_with(char::is_whitespace)
+    {
+        return None;
+    }
+
+    write!(out, "<{tag}>").expect("Writing to String cannot fail");
+    render_inner(inner, out);
+    write!(out, "</{tag}>").expect("Writing to String cannot fail");
+
+    Some(delimiter.len() * 2 + inner_len)
+}
+
+fn render_link(text: &str, out: &mut String) -> Option<usize> {
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    let url = text[..end].trim_end_matches(URL_TRAILING_PUNCTUATION);
+    if URL_SCHEMES.contains(&url) {
+        return None;
+    }
+
+    out.push_str("<a href=\"");
+    escape_into(url, out);
+    out.push_str("\" rel=\"nofollow noopener noreferrer\" target=\"_blank\">");
+    escape_into(url, out);
+    out.push_str("</a>");
+
+    Some(url.len())
+}
+
+fn render_tag(text: &str, out: &mut String, class: &str, href_prefix: &str) -> Option<usize> {
+    let sigil_len = 1;
+    let name_len = text[sigil_len..]
+        .find(|c| !is_word_char(c))
+        .unwrap_or(text.len() - sigil_len);
+    if name_len == 0 {
+        return None;
+    }
+    let len = sigil_len + name_len;
+    let name = &text[sigil_len..len];
+
+    write!(out, "<a href=\"{href_prefix}").expect("Writing to String cannot fail");
+    escape_into(name, out);
+    write!(out, "\" class=\"{class}\">").expect("Writing to String cannot fail");
+    escape_into(&text[..len], out);
+    out.push_str("</a>");
+
+    Some(len)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Makes the relative mention and hashtag links of rendered HTML absolute,
+/// for when it is shown outside of this instance.
+/// `public_url` must not have a trailing slash.
+#[must_use]
+pub fn absolute_links(html: &str, public_url: &str) -> String {
+    html.replace("href=\"/", &format!("href=\"{}/", escape_html(public_url)))
+}
+
+/// Converts HTML, like the content of posts from other servers, back into plain text.
+/// Paragraphs and line breaks are kept, all other markup is dropped.
+#[must_use]
+pub fn html_to_text(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = rest.find(['<', '&']) {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if rest.starts_with('<') {
+            let end = rest.find('>').map_or(rest.len(), |end| end + 1);
+            let tag = rest[1..end]
+                .trim_end_matches('>')
+                .trim_end_matches('/')
+                .trim();
+            let name = tag.split_whitespace().next().unwrap_or_default();
+            if name.eq_ignore_ascii_case("br") {
+                out.push('\n');
+            } else if name.eq_ignore_ascii_case("/p") {
+                out.push_str("\n\n");
+            }
+            rest = &rest[end..];
+        } else {
+            let (decoded, len) = decode_entity(rest).unwrap_or(('&', 1));
+            out.push(decoded);
+            rest = &rest[len..];
+        }
+    }
+    out.push_str(rest);
+
+    out.trim().to_owned()
+}
+
+/// Decodes the character reference at the start of `text` and returns it with its length.
+fn decode_entity(text: &str) -> Option<(char, usize)> {
+    let end = text.find(';').filter(|&end| end <= 10)?;
+    let entity = &text[1..end];
+
+    let decoded = match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        _ => {
+            let number = entity.strip_prefix('#')?;
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)?
+        }
+    };
+
+    Some((decoded, end + 1))
+}
+
+/// Escapes text for use in HTML content and quoted attribute values.
+#[must_use]
+pub fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    escape_into(text, &mut out);
+    out
+}
+
+fn escape_into(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::text::{html_to_text, links, mentions, render_html};
+
+    #[test]
+    fn escaping() {
+        assert_eq!(
+            render_html("<script>alert('hi')</script> & \"more\""),
+            "<p>&lt;script&gt;alert(&#39;hi&#39;)&lt;/script&gt; &amp; &quot;more&quot;</p>"
+        );
+    }
+
+    #[test]
+    fn paragraphs_and_line_breaks() {
+        assert_eq!(
+            render_html("first\nline\r\n\r\n\nsecond"),
+            "<p>first<br>line</p><p>second</p>"
+        );
+    }
+
+    #[test]
+    fn links_mentions_hashtags() {
+        assert_eq!(
+            render_html("see https://example.com/a?b=1&c=2."),
+            "<p>see <a href=\"https://example.com/a?b=1&amp;c=2\" \
+            rel=\"nofollow noopener noreferrer\" target=\"_blank\">\
+            https://example.com/a?b=1&amp;c=2</a>.</p>"
+        );
+        assert_eq!(
+            render_html("hi @alice, #rust_lang!"),
+            "<p>hi <a href=\"/@alice\" class=\"mention\">@alice</a>, \
+            <a href=\"/tags/rust_lang\" class=\"hashtag\">#rust_lang</a>!</p>"
+        );
+        assert_eq!(
+            render_html("mail@example.com is not a mention, a#b no hashtag"),
+            "<p>mail@example.com is not a mention, a#b no hashtag</p>"
+        );
+        assert_eq!(
+            render_html("javascript:alert(1) @ #"),
+            "<p>javascript:alert(1) @ #</p>"
+        );
+    }
+
+    #[test]
+    fn markdown() {
+        assert_eq!(
+            render_html("**bold @bob** and *em* and `<code> **x**`"),
+            "<p><strong>bold <a href=\"/@bob\" class=\"mention\">@bob</a></strong> and \
+            <em>em</em> and <code>&lt;code&gt; **x**</code></p>"
+        );
+        assert_eq!(render_html("2 * 3 * 4"), "<p>2 * 3 * 4</p>");
+        assert_eq!(render_html("unclosed **bold"), "<p>unclosed **bold</p>");
+    }
+
+    #[test]
+    fn mention_extraction() {
+        assert_eq!(
+            mentions("@alice hi @bob_2, mail@example.com @alice @"),
+            ["alice", "bob_2"]
+        );
+    }
+
+    #[test]
+    fn link_extraction() {
+        assert_eq!(
+            links(
+                "see https://a.example/x, http://b.example. xhttps://c.example https:// https://a.example/x"
+            ),
+            ["https://a.example/x", "http://b.example"]
+        );
+    }
+
+    #[test]
+    fn html_conversion() {
+        assert_eq!(
+            html_to_text(
+                "<p>Hi <span class=\"h-card\"><a href=\"https://a.example/@b\">@<span>b</span></a></span>,<br />\
+                1 &lt; 2 &amp;&amp; &#39;x&#x27;</p><p>bye &unknown; & more</p>"
+            ),
+            "Hi @b,\n1 < 2 && 'x'\n\nbye &unknown; & more"
+        );
+        assert_eq!(html_to_text("<p>unclosed <b"), "unclosed");
+    }
+}
@@ -32,3 +32,69 @@ impl TryFrom<Duration> for PositiveDuration {
         Self::new(value).ok_or(NonPositiveDurationError(value))
     }
 }
+
+/// Serde helpers (de)serializing [`UtcDateTime`](time::UtcDateTime) as RFC 3339 strings.
+///
+/// Use with `#[serde(with = "rfc3339")]` or `#[serde(with = "rfc3339::option")]`.
+pub mod rfc3339 {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer, de, ser};
+    use time::{UtcDateTime, format_description::well_known::Rfc3339};
+
+    pub fn serialize<S: Serializer>(
+        date_time: &UtcDateTime,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        date_time
+            .format(&Rfc3339)
+            .map_err(ser::Error::custom)?
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<UtcDateTime, D::Error> {
+        let string = String::deserialize(deserializer)?;
+        UtcDateTime::parse(&string, &Rfc3339).map_err(de::Error::custom)
+    }
+
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serializer};
+        use time::UtcDateTime;
+
+        pub fn serialize<S: Serializer>(
+            date_time: &Option<UtcDateTime>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match date_time {
+                Some(date_time) => super::serialize(date_time, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<UtcDateTime>, D::Error> {
+            #[derive(Deserialize)]
+            struct Wrapper(#[serde(with = "super")] UtcDateTime);
+
+            Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(date_time)| date_time))
+        }
+    }
+}
+
+/// Serde helpers (de)serializing byte vectors as standard base64 strings.
+///
+/// Use with `#[serde(with = "base64_bytes")]`.
+pub mod base64_bytes {
+    use base64::{Engine, prelude::BASE64_STANDARD};
+    use serde::{Deserialize, Deserializer, Serializer, de};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64_STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let string = String::deserialize(deserializer)?;
+        BASE64_STANDARD.decode(string).map_err(de::Error::custom)
+    }
+}
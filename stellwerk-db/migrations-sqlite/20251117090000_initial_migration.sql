@@ -0,0 +1,51 @@
+-- The tables of the Store trait, mirroring the Postgres migrations without schemas.
+-- Timestamps are UTC, stored as text.
+
+create table users
+(
+    user_snowflake  integer     not null
+        constraint users_pk
+            primary key,
+    handle          varchar(50) not null
+        constraint users_handle_unique
+            unique,
+    role            varchar(20) default 'user' not null
+        constraint users_role_check
+            check (role in ('user', 'moderator', 'admin')),
+    post_count      integer     default 0 not null,
+    follower_count  integer     default 0 not null,
+    following_count integer     default 0 not null,
+    -- Incremented whenever the profile changes, for ETags
+    profile_version integer     default 0 not null
+);
+
+create table posts
+(
+    post_snowflake integer not null
+        constraint posts_pk
+            primary key,
+    content        text    not null,
+    user_snowflake integer not null
+        constraint posts_users_user_snowflake_fk
+            references users,
+    -- If null, the post is not pinned
+    pinned_at      text,
+    -- Incremented whenever the post changes, for ETags
+    edit_version   integer default 0 not null
+);
+
+create index posts_user_snowflake_index
+    on posts (user_snowflake);
+
+create table auth_tokens
+(
+    token_hash            blob    not null
+        constraint auth_tokens_pk
+            primary key,
+    user_snowflake        integer not null
+        constraint auth_tokens_users_user_snowflake_fk
+            references users,
+    created_at            text    not null,
+    -- If null, the token does not expire
+    expires_after_seconds integer
+);
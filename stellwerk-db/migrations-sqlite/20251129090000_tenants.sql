@@ -0,0 +1,13 @@
+-- Mirrors the Postgres migration. SQLite cannot change constraints, so the handle stays unique
+-- across tenants, and the tenant of posts and auth tokens is only kept in sync by the store.
+alter table users
+    add column tenant varchar(50) default 'default' not null;
+
+alter table posts
+    add column tenant varchar(50) default 'default' not null;
+
+create index posts_tenant_post_snowflake_index
+    on posts (tenant, post_snowflake desc);
+
+alter table auth_tokens
+    add column tenant varchar(50) default 'default' not null;
@@ -0,0 +1,3 @@
+-- Mirrors the Postgres migration, as far as the store uses it.
+alter table auth_tokens
+    add column impersonator_snowflake bigint;
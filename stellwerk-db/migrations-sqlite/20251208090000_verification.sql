@@ -0,0 +1,6 @@
+-- Mirrors the Postgres migration, as far as the store uses it.
+alter table users
+    add column verified_at text;
+
+alter table users
+    add column website varchar(200);
@@ -0,0 +1,4 @@
+alter table posts.posts
+    add column pinned_at timestamp;
+
+comment on column posts.posts.pinned_at is 'UTC. If null, the post is not pinned';
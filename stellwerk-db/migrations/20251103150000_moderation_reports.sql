@@ -0,0 +1,35 @@
+alter table users.users
+    add column role varchar(20) default 'user' not null
+        constraint users_role_check
+            check (role in ('user', 'moderator', 'admin'));
+
+create schema moderation;
+
+create table moderation.reports
+(
+    report_snowflake      bigint       not null
+        constraint reports_pk
+            primary key,
+    reporter_snowflake    bigint       not null
+        constraint reports_users_reporter_fk
+            references users.users,
+    target_user_snowflake bigint       not null
+        constraint reports_users_target_fk
+            references users.users,
+    target_post_snowflake bigint
+        constraint reports_posts_target_fk
+            references posts.posts
+            on delete set null,
+    category              varchar(20)  not null,
+    comment               text         not null,
+    assignee_snowflake    bigint
+        constraint reports_users_assignee_fk
+            references users.users,
+    resolved_at           timestamp
+);
+
+comment on column moderation.reports.resolved_at is 'UTC. If null, the report is still open';
+
+create index reports_open_index
+    on moderation.reports (report_snowflake)
+    where resolved_at is null;
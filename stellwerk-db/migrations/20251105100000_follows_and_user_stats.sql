@@ -0,0 +1,30 @@
+create table users.follows
+(
+    follower_snowflake bigint not null
+        constraint follows_users_follower_fk
+            references users.users,
+    followed_snowflake bigint not null
+        constraint follows_users_followed_fk
+            references users.users,
+    constraint follows_pk
+        primary key (follower_snowflake, followed_snowflake),
+    constraint follows_no_self_follow_check
+        check (follower_snowflake != followed_snowflake)
+);
+
+create index follows_followed_index
+    on users.follows (followed_snowflake);
+
+alter table users.users
+    add column post_count      bigint default 0 not null,
+    add column follower_count  bigint default 0 not null,
+    add column following_count bigint default 0 not null;
+
+comment on column users.users.post_count is 'Maintained in the same transaction as posts.posts';
+
+comment on column users.users.follower_count is 'Maintained in the same transaction as users.follows';
+
+comment on column users.users.following_count is 'Maintained in the same transaction as users.follows';
+
+update users.users
+set post_count = (select count(1) from posts.posts where posts.user_snowflake = users.user_snowflake);
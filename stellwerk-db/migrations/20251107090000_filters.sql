@@ -0,0 +1,19 @@
+create table users.filters
+(
+    filter_snowflake bigint        not null
+        constraint filters_pk
+            primary key,
+    user_snowflake   bigint        not null
+        constraint filters_users_user_snowflake_fk
+            references users.users,
+    phrase           varchar(200)  not null,
+    regex            boolean       not null,
+    contexts         varchar(20)[] not null,
+    action           varchar(20)   not null,
+    expires_at       timestamp
+);
+
+comment on column users.filters.expires_at is 'UTC. If null, the filter does not expire';
+
+create index filters_user_snowflake_index
+    on users.filters (user_snowflake);
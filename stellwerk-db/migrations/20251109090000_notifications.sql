@@ -0,0 +1,26 @@
+create table users.notifications
+(
+    notification_snowflake bigint      not null
+        constraint notifications_pk
+            primary key,
+    user_snowflake         bigint      not null
+        constraint notifications_users_user_snowflake_fk
+            references users.users,
+    kind                   varchar(20) not null
+        constraint notifications_kind_check
+            check (kind in ('follow', 'mention')),
+    actor_snowflake        bigint      not null
+        constraint notifications_users_actor_snowflake_fk
+            references users.users,
+    post_snowflake         bigint
+        constraint notifications_posts_post_snowflake_fk
+            references posts.posts
+);
+
+create index notifications_user_snowflake_index
+    on users.notifications (user_snowflake, notification_snowflake desc);
+
+alter table users.users
+    add column last_read_notification_snowflake bigint;
+
+comment on column users.users.last_read_notification_snowflake is 'Notifications with snowflakes up to this are read. If null, none are';
@@ -0,0 +1,24 @@
+create table users.email_digests
+(
+    user_snowflake              bigint       not null
+        constraint email_digests_pk
+            primary key
+        constraint email_digests_users_user_snowflake_fk
+            references users.users,
+    email                       varchar(254) not null,
+    frequency                   varchar(20)  not null
+        constraint email_digests_frequency_check
+            check (frequency in ('daily', 'weekly')),
+    unsubscribe_token           bytea        not null
+        constraint email_digests_unsubscribe_token_unique
+            unique,
+    next_digest_at              timestamp    not null,
+    last_notification_snowflake bigint
+);
+
+comment on column users.email_digests.next_digest_at is 'UTC';
+
+comment on column users.email_digests.last_notification_snowflake is 'Newest notification included in a digest. If null, none were';
+
+create index email_digests_next_digest_at_index
+    on users.email_digests (next_digest_at);
@@ -0,0 +1,46 @@
+create schema messaging;
+
+create table messaging.conversations
+(
+    conversation_snowflake bigint not null
+        constraint conversations_pk
+            primary key,
+    creator_snowflake      bigint not null
+        constraint conversations_users_creator_fk
+            references users.users
+);
+
+create table messaging.conversation_members
+(
+    conversation_snowflake      bigint not null
+        constraint conversation_members_conversations_fk
+            references messaging.conversations,
+    user_snowflake              bigint not null
+        constraint conversation_members_users_fk
+            references users.users,
+    last_read_message_snowflake bigint,
+    constraint conversation_members_pk
+        primary key (conversation_snowflake, user_snowflake)
+);
+
+comment on column messaging.conversation_members.last_read_message_snowflake is 'Messages with snowflakes up to this are read. If null, none are';
+
+create index conversation_members_user_index
+    on messaging.conversation_members (user_snowflake);
+
+create table messaging.messages
+(
+    message_snowflake      bigint not null
+        constraint messages_pk
+            primary key,
+    conversation_snowflake bigint not null
+        constraint messages_conversations_fk
+            references messaging.conversations,
+    author_snowflake       bigint not null
+        constraint messages_users_author_fk
+            references users.users,
+    content                text   not null
+);
+
+create index messages_conversation_index
+    on messaging.messages (conversation_snowflake, message_snowflake desc);
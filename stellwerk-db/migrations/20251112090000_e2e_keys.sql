@@ -0,0 +1,33 @@
+create table messaging.identity_keys
+(
+    user_snowflake          bigint    not null
+        constraint identity_keys_pk
+            primary key
+        constraint identity_keys_users_fk
+            references users.users,
+    identity_key            bytea     not null,
+    signed_prekey_id        bigint    not null,
+    signed_prekey           bytea     not null,
+    signed_prekey_signature bytea     not null,
+    updated_at              timestamp not null
+);
+
+comment on column messaging.identity_keys.updated_at is 'UTC';
+
+create table messaging.one_time_prekeys
+(
+    user_snowflake bigint not null
+        constraint one_time_prekeys_identity_keys_fk
+            references messaging.identity_keys
+            on delete cascade,
+    key_id         bigint not null,
+    public_key     bytea  not null,
+    constraint one_time_prekeys_pk
+        primary key (user_snowflake, key_id)
+);
+
+alter table messaging.messages
+    alter column content drop not null,
+    add column encrypted_payload bytea,
+    add constraint messages_single_body_check
+        check ((content is null) != (encrypted_payload is null));
@@ -0,0 +1,35 @@
+create schema federation;
+
+create table federation.actor_keys
+(
+    user_snowflake  bigint not null
+        constraint actor_keys_pk
+            primary key
+        constraint actor_keys_users_fk
+            references users.users,
+    public_key_pem  text   not null,
+    private_key_pem text   not null
+);
+
+create table federation.remote_actor_keys
+(
+    key_id         text      not null
+        constraint remote_actor_keys_pk
+            primary key,
+    owner          text      not null,
+    public_key_pem text      not null,
+    fetched_at     timestamp not null
+);
+
+comment on column federation.remote_actor_keys.fetched_at is 'UTC';
+
+create table federation.instance_key
+(
+    singleton       boolean not null default true
+        constraint instance_key_pk
+            primary key
+        constraint instance_key_singleton_check
+            check (singleton),
+    public_key_pem  text    not null,
+    private_key_pem text    not null
+);
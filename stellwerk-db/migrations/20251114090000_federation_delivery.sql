@@ -0,0 +1,58 @@
+alter table users.notifications
+    drop constraint notifications_kind_check;
+
+alter table users.notifications
+    add constraint notifications_kind_check
+        check (kind in ('follow', 'mention', 'like', 'announce'));
+
+create table federation.remote_actors
+(
+    user_snowflake bigint not null
+        constraint remote_actors_pk
+            primary key
+        constraint remote_actors_users_fk
+            references users.users,
+    actor_id       text   not null
+        constraint remote_actors_actor_id_unique
+            unique,
+    inbox          text   not null,
+    shared_inbox   text
+);
+
+comment on table federation.remote_actors is 'Local users standing in for actors of other servers';
+
+create table federation.remote_posts
+(
+    post_snowflake bigint not null
+        constraint remote_posts_pk
+            primary key
+        constraint remote_posts_posts_fk
+            references posts.posts,
+    object_id      text   not null
+        constraint remote_posts_object_id_unique
+            unique
+);
+
+create table federation.deliveries
+(
+    delivery_snowflake bigint    not null
+        constraint deliveries_pk
+            primary key,
+    sender_snowflake   bigint
+        constraint deliveries_users_fk
+            references users.users,
+    inbox              text      not null,
+    activity           text      not null,
+    attempts           integer   not null default 0,
+    next_attempt_at    timestamp not null,
+    last_error         text,
+    dead_at            timestamp
+);
+
+comment on column federation.deliveries.sender_snowflake is 'If null, the delivery is signed by the instance actor';
+comment on column federation.deliveries.next_attempt_at is 'UTC';
+comment on column federation.deliveries.dead_at is 'UTC. If not null, the delivery failed permanently and is kept for inspection';
+
+create index deliveries_next_attempt_at_index
+    on federation.deliveries (next_attempt_at)
+    where dead_at is null;
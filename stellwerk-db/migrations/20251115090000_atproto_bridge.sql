@@ -0,0 +1,30 @@
+create table federation.atproto_accounts
+(
+    user_snowflake bigint not null
+        constraint atproto_accounts_pk
+            primary key
+        constraint atproto_accounts_users_fk
+            references users.users,
+    did            text   not null
+        constraint atproto_accounts_did_unique
+            unique
+);
+
+comment on table federation.atproto_accounts is 'Local users mirroring AT Protocol accounts';
+
+create table federation.atproto_posts
+(
+    post_snowflake bigint    not null
+        constraint atproto_posts_pk
+            primary key
+        constraint atproto_posts_posts_fk
+            references posts.posts,
+    uri            text      not null
+        constraint atproto_posts_uri_unique
+            unique,
+    cid            text      not null,
+    created_at     timestamp not null
+);
+
+comment on column federation.atproto_posts.cid is 'Content hash of the mirrored record';
+comment on column federation.atproto_posts.created_at is 'UTC. When the post was created on its origin';
@@ -0,0 +1,9 @@
+alter table users.users
+    add column profile_version bigint default 0 not null;
+
+comment on column users.users.profile_version is 'Incremented whenever the profile changes, for ETags';
+
+alter table posts.posts
+    add column edit_version bigint default 0 not null;
+
+comment on column posts.posts.edit_version is 'Incremented whenever the post changes, for ETags';
@@ -0,0 +1,27 @@
+alter table posts.posts
+    add column deleted_at timestamp;
+
+comment on column posts.posts.deleted_at is 'UTC. If set, the post is deleted and only visible to moderators until it is purged';
+
+create index posts_deleted_at_index
+    on posts.posts (deleted_at)
+    where deleted_at is not null;
+
+-- Purging a post removes what refers to it.
+alter table users.notifications
+    drop constraint notifications_posts_post_snowflake_fk,
+    add constraint notifications_posts_post_snowflake_fk
+        foreign key (post_snowflake) references posts.posts
+            on delete cascade;
+
+alter table federation.remote_posts
+    drop constraint remote_posts_posts_fk,
+    add constraint remote_posts_posts_fk
+        foreign key (post_snowflake) references posts.posts
+            on delete cascade;
+
+alter table federation.atproto_posts
+    drop constraint atproto_posts_posts_fk,
+    add constraint atproto_posts_posts_fk
+        foreign key (post_snowflake) references posts.posts
+            on delete cascade;
@@ -0,0 +1,4 @@
+alter table users.filters
+    add column version bigint default 0 not null;
+
+comment on column users.filters.version is 'Incremented by every update, so clients can detect concurrent updates';
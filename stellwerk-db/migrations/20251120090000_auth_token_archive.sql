@@ -0,0 +1,24 @@
+create table auth.auth_tokens_archive
+(
+    user_snowflake        bigint    not null,
+    token_hash            bytea     not null
+        constraint auth_tokens_archive_pk
+            primary key,
+    created_at            timestamp not null,
+    expires_after_seconds bigint    not null,
+    archived_at           timestamp not null
+);
+
+comment on table auth.auth_tokens_archive is 'Expired auth tokens, kept for audits and abuse investigations until they are purged';
+
+comment on column auth.auth_tokens_archive.user_snowflake is 'Not a foreign key, so that archived tokens do not keep users from being deleted';
+
+comment on column auth.auth_tokens_archive.created_at is 'UTC';
+
+comment on column auth.auth_tokens_archive.archived_at is 'UTC';
+
+create index auth_tokens_archive_user_snowflake_index
+    on auth.auth_tokens_archive (user_snowflake);
+
+create index auth_tokens_archive_archived_at_index
+    on auth.auth_tokens_archive (archived_at);
@@ -0,0 +1,26 @@
+create schema cluster;
+
+create table cluster.worker_registry
+(
+    worker_id    smallint not null,
+    process_id   smallint not null,
+    lease_token  bigint,
+    leased_until timestamp,
+    constraint worker_registry_pk
+        primary key (worker_id, process_id)
+);
+
+comment on table cluster.worker_registry is 'The snowflake worker and process IDs servers lease when not assigned one';
+
+comment on column cluster.worker_registry.lease_token is 'Random, identifies the holder so that a lease taken over after expiring is left alone';
+
+comment on column cluster.worker_registry.leased_until is 'UTC, free if null or in the past';
+
+-- Process ID 31 is left to stellwerk-db seed
+insert into cluster.worker_registry (worker_id, process_id)
+select worker_id, process_id
+from generate_series(0, 31) worker_id,
+     generate_series(0, 30) process_id;
+
+create index worker_registry_leased_until_index
+    on cluster.worker_registry (leased_until);
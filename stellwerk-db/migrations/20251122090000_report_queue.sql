@@ -0,0 +1,57 @@
+alter table moderation.reports
+    add column resolution varchar(20)
+        constraint reports_resolution_check
+            check (resolution in ('dismiss', 'delete_content', 'warn', 'suspend'));
+
+comment on column moderation.reports.resolution is 'The action taken. Null for open reports and reports resolved before actions were recorded';
+
+create table moderation.report_notes
+(
+    note_snowflake   bigint not null
+        constraint report_notes_pk
+            primary key,
+    report_snowflake bigint not null
+        constraint report_notes_reports_fk
+            references moderation.reports
+            on delete cascade,
+    author_snowflake bigint not null
+        constraint report_notes_users_author_fk
+            references users.users,
+    content          text   not null
+);
+
+comment on table moderation.report_notes is 'Internal notes of moderators on reports, never shown to the reporter or target';
+
+create index report_notes_report_snowflake_index
+    on moderation.report_notes (report_snowflake, note_snowflake);
+
+create table moderation.audit_log
+(
+    entry_snowflake       bigint      not null
+        constraint audit_log_pk
+            primary key,
+    moderator_snowflake   bigint      not null,
+    action                varchar(20) not null
+        constraint audit_log_action_check
+            check (action in ('assign_report', 'add_note', 'dismiss_report', 'delete_post',
+                              'restore_post', 'warn_user', 'suspend_user')),
+    report_snowflake      bigint,
+    target_user_snowflake bigint,
+    target_post_snowflake bigint
+);
+
+comment on table moderation.audit_log is 'Every action of moderators, the time is that of the snowflake';
+
+comment on column moderation.audit_log.moderator_snowflake is 'None of the snowflakes are foreign keys, so that entries outlive what they refer to';
+
+alter table users.users
+    add column suspended_at timestamp;
+
+comment on column users.users.suspended_at is 'UTC. If set, the user cannot authenticate';
+
+alter table users.notifications
+    drop constraint notifications_kind_check;
+
+alter table users.notifications
+    add constraint notifications_kind_check
+        check (kind in ('follow', 'mention', 'like', 'announce', 'warning'));
@@ -0,0 +1,9 @@
+alter table moderation.reports
+    alter column reporter_snowflake drop not null;
+
+comment on column moderation.reports.reporter_snowflake is 'Null for reports created automatically by the spam heuristics';
+
+alter table users.follows
+    add column followed_at timestamp;
+
+comment on column users.follows.followed_at is 'UTC. Null for follows from before it was recorded';
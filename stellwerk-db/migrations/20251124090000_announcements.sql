@@ -0,0 +1,29 @@
+create schema instance;
+
+create table instance.announcements
+(
+    announcement_snowflake bigint       not null
+        constraint announcements_pk
+            primary key,
+    title                  varchar(200) not null,
+    body                   text         not null,
+    expires_at             timestamp
+);
+
+comment on column instance.announcements.expires_at is 'UTC. If null, the announcement does not expire';
+
+create table instance.announcement_states
+(
+    announcement_snowflake bigint  not null
+        constraint announcement_states_announcements_fk
+            references instance.announcements
+            on delete cascade,
+    user_snowflake         bigint  not null
+        constraint announcement_states_users_fk
+            references users.users,
+    dismissed              boolean not null,
+    constraint announcement_states_pk
+        primary key (user_snowflake, announcement_snowflake)
+);
+
+comment on table instance.announcement_states is 'Announcements without a row are unread';
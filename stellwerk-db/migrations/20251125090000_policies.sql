@@ -0,0 +1,33 @@
+create table instance.policies
+(
+    kind         varchar(20) not null
+        constraint policies_kind_check
+            check (kind in ('terms_of_service', 'privacy')),
+    version      bigint      not null
+        constraint policies_version_check
+            check (version > 0),
+    content      text        not null,
+    published_at timestamp   not null,
+    constraint policies_pk
+        primary key (kind, version)
+);
+
+comment on column instance.policies.published_at is 'UTC';
+
+create table instance.policy_acceptances
+(
+    user_snowflake bigint      not null
+        constraint policy_acceptances_users_fk
+            references users.users,
+    kind           varchar(20) not null,
+    version        bigint      not null,
+    accepted_at    timestamp   not null,
+    constraint policy_acceptances_pk
+        primary key (user_snowflake, kind),
+    constraint policy_acceptances_policies_fk
+        foreign key (kind, version) references instance.policies
+);
+
+comment on table instance.policy_acceptances is 'The latest version of each policy kind the user accepted';
+
+comment on column instance.policy_acceptances.accepted_at is 'UTC';
@@ -0,0 +1,47 @@
+create schema webhooks;
+
+create table webhooks.webhooks
+(
+    webhook_snowflake bigint         not null
+        constraint webhooks_pk
+            primary key,
+    user_snowflake    bigint         not null
+        constraint webhooks_users_fk
+            references users.users,
+    url               varchar(2000)  not null,
+    secret            text           not null,
+    events            varchar(20)[]  not null
+        constraint webhooks_events_check
+            check (cardinality(events) > 0 and events <@ array ['followed_post', 'mention']::varchar(20)[]),
+    failures          integer        not null default 0,
+    disabled_at       timestamp
+);
+
+comment on column webhooks.webhooks.failures is 'Failed requests since the last successful one';
+comment on column webhooks.webhooks.disabled_at is 'UTC. If not null, no deliveries are queued or attempted';
+
+create index webhooks_user_snowflake_index
+    on webhooks.webhooks (user_snowflake);
+
+create table webhooks.deliveries
+(
+    delivery_snowflake bigint    not null
+        constraint deliveries_pk
+            primary key,
+    webhook_snowflake  bigint    not null
+        constraint deliveries_webhooks_fk
+            references webhooks.webhooks
+            on delete cascade,
+    payload            text      not null,
+    attempts           integer   not null default 0,
+    next_attempt_at    timestamp not null,
+    last_error         text,
+    dead_at            timestamp
+);
+
+comment on column webhooks.deliveries.next_attempt_at is 'UTC';
+comment on column webhooks.deliveries.dead_at is 'UTC. If not null, the delivery failed permanently and is kept for inspection';
+
+create index deliveries_next_attempt_at_index
+    on webhooks.deliveries (next_attempt_at)
+    where dead_at is null;
@@ -0,0 +1,69 @@
+create schema jobs;
+
+create table jobs.jobs
+(
+    job_snowflake bigint      not null
+        constraint jobs_pk
+            primary key,
+    kind          varchar(50) not null
+        constraint jobs_kind_check
+            check (kind in ('federation_delivery', 'webhook_delivery', 'email_digests')),
+    payload       text        not null,
+    recurring     boolean     not null default false,
+    attempts      integer     not null default 0,
+    run_at        timestamp   not null,
+    last_error    text,
+    dead_at       timestamp
+);
+
+comment on column jobs.jobs.payload is 'JSON, including the kind';
+comment on column jobs.jobs.recurring is 'Recurring jobs are rescheduled instead of deleted when they succeed, and unique per kind';
+comment on column jobs.jobs.run_at is 'UTC. Claiming a job moves this past its visibility timeout, so that it is run again if the claiming process dies';
+comment on column jobs.jobs.dead_at is 'UTC. If not null, the job failed permanently or too often and is kept until it is retried or deleted';
+
+create index jobs_kind_run_at_index
+    on jobs.jobs (kind, run_at)
+    where dead_at is null;
+
+create index jobs_dead_index
+    on jobs.jobs (job_snowflake)
+    where dead_at is not null;
+
+create unique index jobs_recurring_kind_unique
+    on jobs.jobs (kind)
+    where recurring;
+
+insert into jobs.jobs (job_snowflake, kind, payload, attempts, run_at, last_error, dead_at)
+select delivery_snowflake,
+       'federation_delivery',
+       jsonb_build_object(
+               'kind', 'federation_delivery',
+               'sender', sender_snowflake::text,
+               'inbox', inbox,
+               'activity', activity
+       )::text,
+       attempts,
+       next_attempt_at,
+       last_error,
+       dead_at
+from federation.deliveries;
+
+insert into jobs.jobs (job_snowflake, kind, payload, attempts, run_at, last_error, dead_at)
+select delivery_snowflake,
+       'webhook_delivery',
+       jsonb_build_object(
+               'kind', 'webhook_delivery',
+               'webhook', webhook_snowflake::text,
+               'payload', payload
+       )::text,
+       attempts,
+       next_attempt_at,
+       last_error,
+       dead_at
+from webhooks.deliveries;
+
+drop table federation.deliveries;
+
+drop table webhooks.deliveries;
+
+comment on column webhooks.webhooks.disabled_at is 'UTC. If not null, no deliveries are queued, and queued ones are dropped';
@@ -0,0 +1,14 @@
+create table instance.feature_flags
+(
+    flag       varchar(50) not null
+        constraint feature_flags_pk
+            primary key
+        constraint feature_flags_flag_check
+            check (flag in ('public_timeline', 'mastodon_api')),
+    enabled    boolean     not null,
+    updated_at timestamp   not null
+);
+
+comment on table instance.feature_flags is 'Flags without a row follow the configuration of each server';
+
+comment on column instance.feature_flags.updated_at is 'UTC';
@@ -0,0 +1,27 @@
+-- Existing data belongs to the default tenant.
+alter table users.users
+    add column tenant varchar(50) default 'default' not null,
+    drop constraint users_pk_2,
+    add constraint users_tenant_handle_key
+        unique (tenant, handle),
+    -- Referenced together, so that posts and auth tokens have the tenant of their user.
+    add constraint users_user_snowflake_tenant_key
+        unique (user_snowflake, tenant);
+
+comment on column users.users.tenant is 'Handles are unique per tenant. Remote users belong to the default tenant';
+
+alter table posts.posts
+    add column tenant varchar(50) default 'default' not null,
+    drop constraint posts_users_snowflake_fk,
+    add constraint posts_users_snowflake_fk
+        foreign key (user_snowflake, tenant) references users.users (user_snowflake, tenant);
+
+create index posts_tenant_post_snowflake_index
+    on posts.posts (tenant, post_snowflake desc)
+    where deleted_at is null;
+
+alter table auth.auth_tokens
+    add column tenant varchar(50) default 'default' not null,
+    drop constraint auth_tokens_users_user_snowflake_fk,
+    add constraint auth_tokens_users_user_snowflake_fk
+        foreign key (user_snowflake, tenant) references users.users (user_snowflake, tenant);
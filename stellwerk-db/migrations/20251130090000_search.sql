@@ -0,0 +1,17 @@
+alter table jobs.jobs
+    drop constraint jobs_kind_check,
+    add constraint jobs_kind_check
+        check (kind in ('federation_delivery', 'webhook_delivery', 'email_digests', 'search_indexing'));
+
+-- The full-text search of posts if no external search engine is configured.
+-- The simple configuration does not stem, since posts are in many languages.
+alter table posts.posts
+    add column search_vector tsvector generated always as (to_tsvector('simple', content)) stored;
+
+create index posts_search_vector_index
+    on posts.posts using gin (search_vector)
+    where deleted_at is null;
+
+-- Users are searched by handle prefix.
+create index users_tenant_handle_pattern_index
+    on users.users (tenant, handle varchar_pattern_ops);
@@ -0,0 +1,23 @@
+create schema analytics;
+
+create table analytics.rollups
+(
+    metric       varchar(50) not null
+        constraint rollups_metric_check
+            check (metric in ('daily_active_users', 'posts', 'timeline_latency')),
+    bucket_start timestamp   not null,
+    total        bigint      not null,
+    samples      bigint      not null,
+    constraint rollups_pk
+        primary key (metric, bucket_start)
+);
+
+comment on table analytics.rollups is 'Totals of all servers per bucket, never per user';
+comment on column analytics.rollups.bucket_start is 'UTC. Midnight for daily metrics, the full hour for hourly ones';
+comment on column analytics.rollups.total is 'The sum of the values, like milliseconds for latencies';
+comment on column analytics.rollups.samples is 'The number of values summed up';
+
+alter table users.users
+    add column active_on date;
+
+comment on column users.users.active_on is 'UTC. The last day the user made an authenticated request, overwritten daily, to count daily active users';
@@ -0,0 +1,45 @@
+create table auth.oidc_logins
+(
+    state               bytea       not null
+        constraint oidc_logins_pk
+            primary key,
+    tenant              varchar(50) not null,
+    provider            varchar(50) not null
+        constraint oidc_logins_provider_check
+            check (provider in ('google', 'github', 'custom')),
+    nonce               bytea       not null,
+    code_verifier       bytea       not null,
+    handle              varchar(50),
+    link_user_snowflake bigint
+        constraint oidc_logins_users_user_snowflake_fk
+            references users.users
+            on delete cascade,
+    created_at          timestamp   not null
+);
+
+comment on table auth.oidc_logins is 'Logins started at an identity provider, deleted when the provider redirects back or once they expired';
+comment on column auth.oidc_logins.handle is 'The handle of the account created if the identity is unknown';
+comment on column auth.oidc_logins.link_user_snowflake is 'If not null, the identity is linked to this user instead of logging in';
+comment on column auth.oidc_logins.created_at is 'UTC';
+
+create table auth.oidc_identities
+(
+    tenant         varchar(50)  not null,
+    provider       varchar(50)  not null
+        constraint oidc_identities_provider_check
+            check (provider in ('google', 'github', 'custom')),
+    subject        varchar(255) not null,
+    user_snowflake bigint       not null,
+    linked_at      timestamp    not null,
+    constraint oidc_identities_pk
+        primary key (tenant, provider, subject),
+    constraint oidc_identities_user_snowflake_provider_key
+        unique (user_snowflake, provider),
+    constraint oidc_identities_users_user_snowflake_fk
+        foreign key (user_snowflake, tenant) references users.users (user_snowflake, tenant)
+            on delete cascade
+);
+
+comment on table auth.oidc_identities is 'Identities at identity providers users can log in with, at most one per provider and user';
+comment on column auth.oidc_identities.subject is 'The stable id of the identity at the provider, not the username';
+comment on column auth.oidc_identities.linked_at is 'UTC';
@@ -0,0 +1,35 @@
+create table users.quota_overrides
+(
+    user_snowflake  bigint not null
+        constraint quota_overrides_pk
+            primary key
+        constraint quota_overrides_users_user_snowflake_fk
+            references users.users
+            on delete cascade,
+    posts_per_day   integer
+        constraint quota_overrides_posts_per_day_check
+            check (posts_per_day >= 0),
+    follows_per_day integer
+        constraint quota_overrides_follows_per_day_check
+            check (follows_per_day >= 0)
+);
+
+comment on table users.quota_overrides is 'Daily quotas of users that differ from the instance''s, null where they do not';
+
+create table users.quota_usage
+(
+    user_snowflake bigint      not null
+        constraint quota_usage_users_user_snowflake_fk
+            references users.users
+            on delete cascade,
+    kind           varchar(50) not null
+        constraint quota_usage_kind_check
+            check (kind in ('posts', 'follows')),
+    day            date        not null,
+    used           integer     not null,
+    constraint quota_usage_pk
+        primary key (user_snowflake, kind, day)
+);
+
+comment on table users.quota_usage is 'How much of their daily quotas users used, pruned after the day';
+comment on column users.quota_usage.day is 'UTC';
@@ -0,0 +1,5 @@
+alter table jobs.jobs
+    drop constraint jobs_kind_check,
+    add constraint jobs_kind_check
+        check (kind in ('federation_delivery', 'webhook_delivery', 'email_digests', 'search_indexing', 'email',
+                        'cdn_purge'));
@@ -0,0 +1,16 @@
+alter table users.users
+    add column shadowbanned_at timestamp;
+
+comment on column users.users.shadowbanned_at is 'UTC. If set, the posts of the user are hidden from public timelines, search, and the notifications of others, but not from the user';
+
+alter table moderation.reports
+    drop constraint reports_resolution_check,
+    add constraint reports_resolution_check
+        check (resolution in ('dismiss', 'delete_content', 'warn', 'suspend', 'shadowban'));
+
+alter table moderation.audit_log
+    drop constraint audit_log_action_check,
+    add constraint audit_log_action_check
+        check (action in ('assign_report', 'add_note', 'dismiss_report', 'delete_post',
+                          'restore_post', 'warn_user', 'suspend_user', 'shadowban_user',
+                          'unshadowban_user'));
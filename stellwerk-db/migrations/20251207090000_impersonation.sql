@@ -0,0 +1,29 @@
+alter table auth.auth_tokens
+    add column impersonator_snowflake bigint
+        constraint auth_tokens_users_impersonator_snowflake_fk
+            references users.users;
+
+comment on column auth.auth_tokens.impersonator_snowflake is 'The admin acting as the user with this token, for support. If null, the token is the user''s own';
+
+alter table auth.auth_tokens_archive
+    add column impersonator_snowflake bigint;
+
+comment on column auth.auth_tokens_archive.impersonator_snowflake is 'Not a foreign key, like user_snowflake';
+
+alter table users.notifications
+    drop constraint notifications_kind_check,
+    add constraint notifications_kind_check
+        check (kind in ('follow', 'mention', 'like', 'announce', 'warning', 'impersonation'));
+
+alter table moderation.audit_log
+    drop constraint audit_log_action_check,
+    add constraint audit_log_action_check
+        check (action in ('assign_report', 'add_note', 'dismiss_report', 'delete_post',
+                          'restore_post', 'warn_user', 'suspend_user', 'shadowban_user',
+                          'unshadowban_user', 'impersonate_user', 'end_impersonation'));
+
+alter table jobs.jobs
+    drop constraint jobs_kind_check,
+    add constraint jobs_kind_check
+        check (kind in ('federation_delivery', 'webhook_delivery', 'email_digests', 'search_indexing', 'email',
+                        'cdn_purge', 'impersonation_notice'));
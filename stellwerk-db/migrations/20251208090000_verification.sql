@@ -0,0 +1,6 @@
+alter table users.users
+    add column verified_at timestamp,
+    add column website     varchar(200);
+
+comment on column users.users.verified_at is 'UTC. If set, an admin granted the user a verified badge';
+comment on column users.users.website is 'Listed on the profile, and checked for a rel=me link back if an admin asks for it when verifying';
@@ -0,0 +1,13 @@
+create table posts.post_views
+(
+    post_snowflake bigint not null
+        constraint post_views_pk
+            primary key
+        constraint post_views_posts_fk
+            references posts.posts
+            on delete cascade,
+    views          bigint not null
+);
+
+comment on table posts.post_views is 'Added to periodically by every server, no row if the post was never viewed';
+comment on column posts.post_views.views is 'Counted once per viewer and day by each server';
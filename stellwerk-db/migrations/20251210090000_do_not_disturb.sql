@@ -0,0 +1,21 @@
+create table users.do_not_disturb
+(
+    user_snowflake             bigint not null
+        constraint do_not_disturb_pk
+            primary key
+        constraint do_not_disturb_users_user_snowflake_fk
+            references users.users
+            on delete cascade,
+    quiet_hours_start          time,
+    quiet_hours_end            time,
+    quiet_hours_offset_seconds integer,
+    snoozed_until              timestamp,
+    constraint do_not_disturb_quiet_hours_check
+        check ((quiet_hours_start is null) = (quiet_hours_end is null)
+            and (quiet_hours_start is null) = (quiet_hours_offset_seconds is null))
+);
+
+comment on table users.do_not_disturb is 'While active, webhooks about mentions and email digests of the user are held back, but notifications are still created';
+comment on column users.do_not_disturb.quiet_hours_start is 'Local time of the daily window, which spans midnight if it is after quiet_hours_end';
+comment on column users.do_not_disturb.quiet_hours_offset_seconds is 'The offset of the local time from UTC';
+comment on column users.do_not_disturb.snoozed_until is 'UTC';
@@ -0,0 +1,128 @@
+//! An optional circuit breaker around the connections of [`DbClient`](crate::client::DbClient)
+//! to the primary.
+//!
+//! After [`BreakerSettings::failure_threshold`] connection attempts in a row failed, including
+//! ones that timed out, the breaker opens and queries fail right away with
+//! [`DbError::Unavailable`] instead of each waiting for the acquire timeout.
+//! Once it was open for [`BreakerSettings::open_duration`], the next query is let through to
+//! check whether the database is back, closing the breaker if it succeeds and opening it again
+//! if it fails. If that query never finishes, another one is let through after another
+//! `open_duration`.
+//!
+//! Reads from replicas are not counted, since they fall back to the primary.
+
+use crate::client::{DbError, Result};
+use std::{
+    sync::nonpoison::Mutex,
+    time::{Duration, Instant},
+};
+use stellwerk_common::model::health::{CircuitBreakerStats, CircuitState};
+use tracing::{info, warn};
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct BreakerSettings {
+    /// Must be positive.
+    pub failure_threshold: u32,
+    pub open_duration: Duration,
+}
+
+/// See the [module documentation](self).
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    settings: BreakerSettings,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: CircuitState,
+    consecutive_failures: u32,
+    /// When the next query is let through while open or half-open.
+    retry_at: Option<Instant>,
+    times_opened: u64,
+    rejected: u64,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(settings: BreakerSettings) -> Self {
+        Self {
+            settings,
+            inner: Mutex::new(Inner {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                retry_at: None,
+                times_opened: 0,
+                rejected: 0,
+            }),
+        }
+    }
+
+    /// Fails with [`DbError::Unavailable`] if the connection attempt should not be made.
+    fn allow(&self, now: Instant) -> Result<()> {
+        let mut inner = self.inner.lock();
+        match inner.retry_at {
+            None => Ok(()),
+            Some(retry_at) if retry_at <= now => {
+                inner.state = CircuitState::HalfOpen;
+                inner.retry_at = Some(now + self.settings.open_duration);
+                Ok(())
+            }
+            Some(_) => {
+                inner.rejected += 1;
+                Err(DbError::Unavailable)
+            }
+        }
+    }
+
+    fn record(&self, succeeded: bool, now: Instant) {
+        let mut inner = self.inner.lock();
+        if succeeded {
+            if inner.state != CircuitState::Closed {
+                info!("Closing the database circuit breaker, since the database answered again");
+            }
+            inner.state = CircuitState::Closed;
+            inner.consecutive_failures = 0;
+            inner.retry_at = None;
+            return;
+        }
+
+        inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+        let open = match inner.state {
+            CircuitState::Closed => inner.consecutive_failures >= self.settings.failure_threshold,
+            CircuitState::HalfOpen => true,
+            CircuitState::Open => false,
+        };
+        if open {
+            warn!(
+                consecutive_failures = inner.consecutive_failures,
+                open_duration = ?self.settings.open_duration,
+                "Opening the database circuit breaker"
+            );
+            inner.state = CircuitState::Open;
+            inner.retry_at = Some(now + self.settings.open_duration);
+            inner.times_opened += 1;
+        }
+    }
+
+    /// Runs the connection attempt `connect` unless the breaker is open, and records its outcome.
+    pub(crate) async fn call<T>(
+        &self,
+        connect: impl Future<Output = Result<T, sqlx::Error>>,
+    ) -> Result<T> {
+        self.allow(Instant::now())?;
+        let result = connect.await;
+        self.record(result.is_ok(), Instant::now());
+
+        result.map_err(DbError::from)
+    }
+
+    pub(crate) fn stats(&self) -> CircuitBreakerStats {
+        let inner = self.inner.lock();
+        CircuitBreakerStats {
+            state: inner.state,
+            consecutive_failures: inner.consecutive_failures,
+            times_opened: inner.times_opened,
+            rejected: inner.rejected,
+        }
+    }
+}
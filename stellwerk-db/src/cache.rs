@@ -0,0 +1,412 @@
+//! An optional read-through cache of users, posts, and auth tokens for
+//! [`DbClient`](crate::client::DbClient).
+//!
+//! Its `fetch_user`, `fetch_post`, and `fetch_auth` are answered from the cache if possible,
+//! and its writes invalidate the entries they change.
+//! The cache is in memory, or with the `redis` feature in Redis, where it is shared by all
+//! instances. Changes made through other instances with an in-memory cache, and the handles of
+//! authors in cached posts, are only updated once the entries expired.
+//!
+//! Handles of transactions do not read from the cache, so that they see their own
+//! uncommitted writes.
+
+use crate::client::Result;
+use moka::{Expiry, future::Cache};
+use std::time::{Duration, Instant};
+use stellwerk_common::model::{
+    Id,
+    auth::{AuthTokenHash, Authentication},
+    post::{Post, PostMarker},
+    user::{User, UserMarker},
+};
+use tracing::warn;
+
+/// How long entries of each type are cached. Entries of a type are not cached if `None`.
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub struct CacheSettings {
+    pub backend: CacheBackend,
+    pub user_ttl: Option<Duration>,
+    pub post_ttl: Option<Duration>,
+    pub auth_ttl: Option<Duration>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub enum CacheBackend {
+    /// In the memory of this process, with at most `capacity` entries.
+    Memory { capacity: u64 },
+    /// In the Redis server at `url`.
+    #[cfg(feature = "redis")]
+    Redis { url: Box<str> },
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+enum Key {
+    User(Id<UserMarker>),
+    Post(Id<PostMarker>),
+    Auth(AuthTokenHash),
+}
+
+#[derive(Clone, Debug)]
+enum Value {
+    User(User),
+    Post(Post),
+    Auth(Authentication),
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+struct Ttls {
+    user: Option<Duration>,
+    post: Option<Duration>,
+    auth: Option<Duration>,
+}
+
+impl Ttls {
+    fn get(&self, key: &Key) -> Option<Duration> {
+        match key {
+            Key::User(_) => self.user,
+            Key::Post(_) => self.post,
+            Key::Auth(_) => self.auth,
+        }
+    }
+}
+
+impl Expiry<Key, Value> for Ttls {
+    fn expire_after_create(&self, key: &Key, _: &Value, _: Instant) -> Option<Duration> {
+        self.get(key)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Backend {
+    Memory(Cache<Key, Value>),
+    #[cfg(feature = "redis")]
+    Redis(redis_backend::RedisCache),
+}
+
+/// See the [module documentation](self).
+#[derive(Clone, Debug)]
+pub struct DbCache {
+    backend: Backend,
+    ttls: Ttls,
+}
+
+impl DbCache {
+    /// Connects to Redis, if that is the backend.
+    #[cfg_attr(
+        not(feature = "redis"),
+        expect(clippy::unused_async, reason = "Only the Redis backend connects")
+    )]
+    pub async fn new(settings: &CacheSettings) -> Result<Self> {
+        let ttls = Ttls {
+            user: settings.user_ttl,
+            post: settings.post_ttl,
+            auth: settings.auth_ttl,
+        };
+
+        let backend = match &settings.backend {
+            CacheBackend::Memory { capacity } => Backend::Memory(
+                Cache::builder()
+                    .max_capacity(*capacity)
+                    .expire_after(ttls)
+                    .support_invalidation_closures()
+                    .build(),
+            ),
+            #[cfg(feature = "redis")]
+            CacheBackend::Redis { url } => {
+                Backend::Redis(redis_backend::RedisCache::connect(url).await?)
+            }
+        };
+
+        Ok(Self { backend, ttls })
+    }
+
+    async fn get(&self, key: Key) -> Option<Value> {
+        self.ttls.get(&key)?;
+
+        match &self.backend {
+            Backend::Memory(cache) => cache.get(&key).await,
+            #[cfg(feature = "redis")]
+            Backend::Redis(cache) => cache.get(&key).await,
+        }
+    }
+
+    async fn insert(&self, key: Key, value: Value) {
+        let Some(ttl) = self.ttls.get(&key) else {
+            return;
+        };
+
+        match &self.backend {
+            // The entry expires through the `Expiry` of the cache.
+            Backend::Memory(cache) => {
+                let _ = ttl;
+                cache.insert(key, value).await;
+            }
+            #[cfg(feature = "redis")]
+            Backend::Redis(cache) => cache.insert(&key, &value, ttl).await,
+        }
+    }
+
+    async fn invalidate(&self, key: Key) {
+        match &self.backend {
+            Backend::Memory(cache) => cache.invalidate(&key).await,
+            #[cfg(feature = "redis")]
+            Backend::Redis(cache) => cache.invalidate(&key).await,
+        }
+    }
+
+    pub(crate) async fn user(&self, user_id: Id<UserMarker>) -> Option<User> {
+        match self.get(Key::User(user_id)).await? {
+            Value::User(user) => Some(user),
+            _ => None,
+        }
+    }
+
+    pub(crate) async fn insert_user(&self, user: &User) {
+        self.insert(Key::User(user.id), Value::User(user.clone()))
+            .await;
+    }
+
+    pub(crate) async fn invalidate_user(&self, user_id: Id<UserMarker>) {
+        self.invalidate(Key::User(user_id)).await;
+    }
+
+    pub(crate) async fn post(&self, post_id: Id<PostMarker>) -> Option<Post> {
+        match self.get(Key::Post(post_id)).await? {
+            Value::Post(post) => Some(post),
+            _ => None,
+        }
+    }
+
+    pub(crate) async fn insert_post(&self, post: &Post) {
+        self.insert(Key::Post(post.id), Value::Post(post.clone()))
+            .await;
+    }
+
+    pub(crate) async fn invalidate_post(&self, post_id: Id<PostMarker>) {
+        self.invalidate(Key::Post(post_id)).await;
+    }
+
+    pub(crate) async fn auth(&self, token_hash: &AuthTokenHash) -> Option<Authentication> {
+        match self.get(Key::Auth(token_hash.clone())).await? {
+            Value::Auth(authentication) => Some(authentication),
+            _ => None,
+        }
+    }
+
+    pub(crate) async fn insert_auth(&self, authentication: &Authentication) {
+        self.insert(
+            Key::Auth(authentication.token_hash.clone()),
+            Value::Auth(authentication.clone()),
+        )
+        .await;
+    }
+
+    /// Invalidates all cached tokens of the user.
+    #[cfg_attr(
+        not(feature = "redis"),
+        expect(clippy::unused_async, reason = "Only the Redis backend awaits")
+    )]
+    pub(crate) async fn invalidate_user_auths(&self, user_id: Id<UserMarker>) {
+        match &self.backend {
+            Backend::Memory(cache) => {
+                let invalidated = cache.invalidate_entries_if(move |_, value| {
+                    matches!(value, Value::Auth(authentication) if authentication.user == user_id)
+                });
+                if let Err(error) = invalidated {
+                    warn!(%error, %user_id, "Could not invalidate the cached tokens of the user");
+                }
+            }
+            #[cfg(feature = "redis")]
+            Backend::Redis(cache) => cache.invalidate_user_auths(user_id).await,
+        }
+    }
+}
+
+#[cfg(feature = "redis")]
+mod redis_backend {
+    //! Values are stored as JSON, with keys prefixed by `stellwerk:`.
+    //! The keys of a user's tokens are also stored in a set, so that they can be invalidated
+    //! together.
+    //!
+    //! Failing requests are logged, and treated as misses.
+
+    use super::{Key, Value};
+    use crate::client::Result;
+    use redis::{AsyncCommands, aio::ConnectionManager};
+    use serde::{Deserialize, Serialize};
+    use std::{fmt::Write, time::Duration};
+    use stellwerk_common::{
+        model::{
+            Id,
+            auth::{AuthTokenHash, Authentication},
+            post::Post,
+            tenant::TenantId,
+            user::{User, UserMarker},
+        },
+        util::{PositiveDuration, rfc3339},
+    };
+    use time::UtcDateTime;
+    use tracing::warn;
+
+    #[derive(Clone)]
+    pub(super) struct RedisCache(ConnectionManager);
+
+    impl std::fmt::Debug for RedisCache {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_tuple("RedisCache").finish_non_exhaustive()
+        }
+    }
+
+    /// [`Authentication`] without the token hash, which is part of the key.
+    #[derive(Serialize, Deserialize)]
+    struct StoredAuthentication {
+        user: Id<UserMarker>,
+        /// Missing in entries of servers without tenants, whose users all had the default one.
+        #[serde(default)]
+        tenant: TenantId,
+        #[serde(with = "rfc3339")]
+        created_at: UtcDateTime,
+        expires_after_seconds: Option<i64>,
+        /// Missing in entries of servers without impersonation, whose tokens were all users' own.
+        #[serde(default)]
+        impersonator: Option<Id<UserMarker>>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    enum StoredValue {
+        User(User),
+        Post(Post),
+        Auth(StoredAuthentication),
+    }
+
+    fn key_string(key: &Key) -> String {
+        match key {
+            Key::User(user_id) => format!("stellwerk:user:{user_id}"),
+            Key::Post(post_id) => format!("stellwerk:post:{post_id}"),
+            Key::Auth(AuthTokenHash(hash)) => {
+                hash.iter()
+                    .fold(String::from("stellwerk:auth:"), |mut key, byte| {
+                        let _ = write!(key, "{byte:02x}");
+                        key
+                    })
+            }
+        }
+    }
+
+    fn user_auths_key(user_id: Id<UserMarker>) -> String {
+        format!("stellwerk:user_auths:{user_id}")
+    }
+
+    fn to_stored(value: &Value) -> StoredValue {
+        match value {
+            Value::User(user) => StoredValue::User(user.clone()),
+            Value::Post(post) => StoredValue::Post(post.clone()),
+            Value::Auth(authentication) => StoredValue::Auth(StoredAuthentication {
+                user: authentication.user,
+                tenant: authentication.tenant.clone(),
+                created_at: authentication.created_at,
+                expires_after_seconds: authentication
+                    .expires_after
+                    .map(|expires_after| expires_after.get().whole_seconds()),
+                impersonator: authentication.impersonator,
+            }),
+        }
+    }
+
+    fn from_stored(key: &Key, value: StoredValue) -> Option<Value> {
+        match (key, value) {
+            (Key::User(_), StoredValue::User(user)) => Some(Value::User(user)),
+            (Key::Post(_), StoredValue::Post(post)) => Some(Value::Post(post)),
+            (Key::Auth(token_hash), StoredValue::Auth(authentication)) => {
+                let expires_after = match authentication.expires_after_seconds {
+                    Some(seconds) => Some(PositiveDuration::new(time::Duration::seconds(seconds))?),
+                    None => None,
+                };
+
+                Some(Value::Auth(Authentication {
+                    user: authentication.user,
+                    tenant: authentication.tenant,
+                    token_hash: token_hash.clone(),
+                    created_at: authentication.created_at,
+                    expires_after,
+                    impersonator: authentication.impersonator,
+                }))
+            }
+            _ => None,
+        }
+    }
+
+    impl RedisCache {
+        pub(super) async fn connect(url: &str) -> Result<Self> {
+            let client = redis::Client::open(url)?;
+            let connection = ConnectionManager::new(client).await?;
+
+            Ok(Self(connection))
+        }
+
+        pub(super) async fn get(&self, key: &Key) -> Option<Value> {
+            let json: Option<String> = match self.0.clone().get(key_string(key)).await {
+                Ok(json) => json,
+                Err(error) => {
+                    warn!(%error, "Error reading from the Redis cache");
+                    return None;
+                }
+            };
+
+            let value = serde_json::from_str(&json?)
+                .inspect_err(|error| warn!(%error, "Invalid entry in the Redis cache"))
+                .ok()?;
+            from_stored(key, value)
+        }
+
+        pub(super) async fn insert(&self, key: &Key, value: &Value, ttl: Duration) {
+            let json = match serde_json::to_string(&to_stored(value)) {
+                Ok(json) => json,
+                Err(error) => {
+                    warn!(%error, "Error serializing an entry for the Redis cache");
+                    return;
+                }
+            };
+            let key = key_string(key);
+            let seconds = ttl.as_secs().max(1);
+
+            let mut pipeline = redis::pipe();
+            pipeline.set_ex(&key, json, seconds).ignore();
+            if let Value::Auth(authentication) = value {
+                let user_auths = user_auths_key(authentication.user);
+                pipeline
+                    .sadd(&user_auths, &key)
+                    .ignore()
+                    .expire(&user_auths, seconds.cast_signed())
+                    .ignore();
+            }
+
+            let result: redis::RedisResult<()> = pipeline.query_async(&mut self.0.clone()).await;
+            if let Err(error) = result {
+                warn!(%error, "Error writing to the Redis cache");
+            }
+        }
+
+        pub(super) async fn invalidate(&self, key: &Key) {
+            let result: redis::RedisResult<()> = self.0.clone().del(key_string(key)).await;
+            if let Err(error) = result {
+                warn!(%error, "Error invalidating an entry of the Redis cache");
+            }
+        }
+
+        pub(super) async fn invalidate_user_auths(&self, user_id: Id<UserMarker>) {
+            let user_auths = user_auths_key(user_id);
+            let mut connection = self.0.clone();
+
+            let result: redis::RedisResult<()> = async {
+                let mut keys: Vec<String> = connection.smembers(&user_auths).await?;
+                keys.push(user_auths);
+                connection.del(keys).await
+            }
+            .await;
+            if let Err(error) = result {
+                warn!(%error, %user_id, "Error invalidating the cached tokens of the user");
+            }
+        }
+    }
+}
@@ -1,251 +1,7010 @@
-use crate::record::{AuthenticationRecord, FullPostRecord, PartialPostRecord, UserRecord};
-use sqlx::{PgPool, migrate, migrate::MigrateError, query, query_as, query_scalar};
-use std::sync::nonpoison::Mutex;
+use crate::{
+    breaker::{BreakerSettings, CircuitBreaker},
+    cache::DbCache,
+    events::{self, DbEventListener},
+    metrics::{Measured, MeasuredStream, QueryMetrics},
+    record::{
+        AnalyticsRollupRecord, AnnouncementRecord, AuthenticationRecord, ConversationMemberRecord,
+        ConversationRecord, DeadJobRecord, EmailDigestRecord, FilterRecord, FullPostRecord,
+        IndexedPostRecord, IndexedUserRecord, JobRecord, KeyPairRecord, MessageRecord,
+        ModeratedPostRecord, ModeratedUserRecord, ModerationLogRecord, NotificationRecord,
+        OidcIdentityRecord, PartialPostRecord, PendingOidcLoginRecord, PolicyRecord,
+        RemoteActorKeyRecord, ReportNoteRecord, ReportRecord, UserAccountRecord,
+        UserAnnouncementRecord, UserProfileRecord, UserRecord, WebhookRecord, WebhookTargetRecord,
+    },
+};
+use async_stream::try_stream;
+use futures_util::{Stream, TryStreamExt};
+use sqlx::{
+    Connection, Executor, PgConnection, PgExecutor, PgPool, Postgres, Transaction, migrate,
+    migrate::{MigrateError, Migrator},
+    pool::PoolConnection,
+    postgres::PgPoolOptions,
+    query, query_as, query_scalar,
+};
+use std::{
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+        nonpoison::Mutex,
+    },
+    time::{Duration, Instant},
+};
 use stellwerk_common::{
+    event::{FollowCreated, NotificationCreated, PostCreated, PostDeleted},
     model::{
-        Id, ModelValidationError, StellwerkSnowflakeGenerator,
+        Id, ModelValidationError, StellwerkAtomicSnowflakeGenerator, StellwerkSnowflake,
+        activitypub::{PublicKey, RemoteActor},
+        admin::{
+            ActivityStats, ActivityWindow, CreateUserAccount, InstanceStats, QueryStats,
+            UserAccount,
+        },
+        analytics::{AnalyticsMetric, AnalyticsRollup},
+        announcement::{Announcement, AnnouncementMarker, CreateAnnouncement, UserAnnouncement},
         auth::{AuthTokenHash, Authentication},
-        post::{CreatePost, PartialPost, Post, PostMarker},
-        user::{CreateUser, User, UserHandle, UserMarker},
+        conversation::{
+            Conversation, ConversationMarker, CreateMessage, Message, MessageBody, MessageMarker,
+        },
+        do_not_disturb::{DoNotDisturb, QuietHours},
+        email::{
+            Email, EmailAddress, EmailDigestSettings, EmailDigestSubscription, UnsubscribeToken,
+        },
+        feature::FeatureFlag,
+        filter::{Filter, FilterMarker, FilterSettings},
+        health::CircuitBreakerStats,
+        job::{DeadJob, Job, JobKind, JobMarker, JobPayload},
+        keys::{KeyBundle, KeyBytes, KeyStatus, OneTimePrekey, PublishKeys, SignedPrekey},
+        moderation::{CreateModerationLogEntry, ModerationLogEntry, ModerationLogMarker},
+        notification::{CreateNotification, Notification, NotificationKind, NotificationMarker},
+        oidc::{OidcIdentity, OidcProvider, OidcToken, PendingOidcLogin},
+        policy::{Policy, PolicyContent, PolicyKind},
+        post::{
+            CreatePost, ModeratedPost, PartialPost, Post, PostContent, PostMarker, PostStats,
+            PostVersion,
+        },
+        quota::{QuotaKind, UserQuotas},
+        report::{
+            CreateReport, CreateReportNote, Report, ReportAction, ReportMarker, ReportNote,
+            ReportNoteMarker,
+        },
+        search::{IndexedPost, IndexedUser, SearchDocument, SearchQuery},
+        sitemap::{SitemapCounts, SitemapPost},
+        tenant::TenantId,
+        user::{
+            CreateUser, ModeratedUser, ProfileWebsite, User, UserHandle, UserMarker, UserProfile,
+            UserRole,
+        },
+        webhook::{
+            CreateWebhook, Webhook, WebhookMarker, WebhookPayload, WebhookSecret, WebhookTarget,
+        },
     },
-    snowflake::{ProcessId, WorkerId},
+    signature::KeyPair,
+    snowflake::{ClockMovedBackwardsError, ProcessId, WorkerId},
 };
 use thiserror::Error;
-use time::{PrimitiveDateTime, UtcDateTime};
+use time::{Date, PrimitiveDateTime, UtcDateTime};
+use tokio::{
+    sync::{MappedMutexGuard, Mutex as AsyncMutex, MutexGuard},
+    time::{sleep, timeout},
+};
+use tracing::{debug, warn};
+
+pub type Result<T, E = DbError> = std::result::Result<T, E>;
+
+#[derive(Debug, Error)]
+pub enum DbError {
+    #[error("Database migration failed: {0}")]
+    Migrate(#[from] MigrateError),
+    #[error("An object in the database was invalid: {0}")]
+    Data(#[from] ModelValidationError),
+    /// All connections of the pool stayed in use for the whole acquire timeout.
+    #[error("No database connection became available in time")]
+    PoolExhausted,
+    /// A statement ran longer than [`PoolSettings::statement_timeout`], or was cancelled otherwise.
+    #[error("A database statement was cancelled for running too long")]
+    StatementTimeout,
+    /// The [circuit breaker](crate::breaker) is open, so no connection was attempted.
+    #[error("The database is unavailable")]
+    Unavailable,
+    /// A handle of a [`DbClient::transaction`] was used after the transaction ended.
+    #[error("The transaction already ended")]
+    TransactionEnded,
+    #[cfg(feature = "redis")]
+    #[error("Connecting to the Redis cache failed: {0}")]
+    Redis(#[from] redis::RedisError),
+    /// A compare-and-swap update found the record at another version than expected.
+    #[error("The record was changed concurrently, its current version is {current_version}")]
+    Conflict { current_version: u64 },
+    #[error(transparent)]
+    ClockMovedBackwards(#[from] ClockMovedBackwardsError),
+    #[error(transparent)]
+    Sqlx(sqlx::Error),
+}
+
+impl DbError {
+    /// Whether running the statement again may succeed: for serialization failures, deadlocks,
+    /// and lost connections.
+    #[must_use]
+    pub fn is_transient(&self) -> bool {
+        match self {
+            DbError::Sqlx(sqlx::Error::Io(_)) => true,
+            DbError::Sqlx(sqlx::Error::Database(error)) => error.code().is_some_and(|code| {
+                // serialization_failure, deadlock_detected, admin_shutdown, connection_exception
+                matches!(&*code, "40001" | "40P01" | "57P01") || code.starts_with("08")
+            }),
+            _ => false,
+        }
+    }
+}
+
+impl From<sqlx::Error> for DbError {
+    fn from(error: sqlx::Error) -> Self {
+        match error {
+            sqlx::Error::PoolTimedOut => Self::PoolExhausted,
+            // query_canceled
+            sqlx::Error::Database(error) if error.code().as_deref() == Some("57014") => {
+                Self::StatementTimeout
+            }
+            error => Self::Sqlx(error),
+        }
+    }
+}
+
+/// The migrations in `stellwerk-db/migrations`, which the `stellwerk-db` binary also runs
+/// and reverts separately from the server.
+pub static MIGRATOR: Migrator = migrate!();
+
+/// Settings of the connection pools of the primary and the replicas.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct PoolSettings {
+    pub max_connections: u32,
+    /// Kept open even when idle.
+    pub min_connections: u32,
+    /// How long to wait for a connection before failing with [`DbError::PoolExhausted`].
+    /// Replicas wait at most [`REPLICA_ACQUIRE_TIMEOUT`].
+    pub acquire_timeout: Duration,
+    /// Idle connections above `min_connections` are closed after this long, if set.
+    pub idle_timeout: Option<Duration>,
+    /// Connections are closed after this long, if set.
+    pub max_lifetime: Option<Duration>,
+    /// Whether connections are checked to be alive before they are used.
+    pub test_before_acquire: bool,
+    /// Statements running longer are cancelled with [`DbError::StatementTimeout`], if set.
+    /// Migrations are not limited.
+    pub statement_timeout: Option<Duration>,
+}
+
+/// Retries of idempotent statements that failed with a [transient](DbError::is_transient) error,
+/// with exponential backoff and full jitter. Statements in transactions are not retried,
+/// since their failure aborts the transaction.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct RetryPolicy {
+    /// Statements are not retried if 0.
+    pub max_retries: u32,
+    /// The longest delay before the first retry, doubling with every further retry.
+    pub base_delay: Duration,
+    /// The longest delay before any retry.
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// The delay before the `retry`th retry, counting from 1.
+    fn backoff(&self, retry: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2_u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_delay);
+        ceiling.mul_f64(rand::random())
+    }
+}
+
+/// A claim on a worker and process ID pair, see [`DbClient::lease_worker_id`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct WorkerLease {
+    pub worker_id: WorkerId,
+    pub process_id: ProcessId,
+    /// Identifies this holder, so that a lease taken over after expiring is not renewed or released.
+    token: i64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(50),
+            max_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+/// The defaults of sqlx.
+impl Default for PoolSettings {
+    fn default() -> Self {
+        Self {
+            max_connections: 10,
+            min_connections: 0,
+            acquire_timeout: Duration::from_secs(30),
+            idle_timeout: Some(Duration::from_mins(10)),
+            max_lifetime: Some(Duration::from_mins(30)),
+            test_before_acquire: true,
+            statement_timeout: None,
+        }
+    }
+}
+
+impl PoolSettings {
+    fn options(&self) -> PgPoolOptions {
+        let statement_timeout = self.statement_timeout;
+        PgPoolOptions::new()
+            .max_connections(self.max_connections)
+            .min_connections(self.min_connections)
+            .acquire_timeout(self.acquire_timeout)
+            .idle_timeout(self.idle_timeout)
+            .max_lifetime(self.max_lifetime)
+            .test_before_acquire(self.test_before_acquire)
+            .after_connect(move |connection, _| {
+                Box::pin(configure_connection(connection, statement_timeout))
+            })
+            .after_release(|connection, _| Box::pin(check_released_connection(connection)))
+    }
+}
+
+/// How often the server checks whether the client of a running statement closed the connection,
+/// to cancel the statement, see [`check_released_connection`].
+const CLIENT_CONNECTION_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// How long a connection returned to the pool may take to become ready for the next statement.
+const RELEASE_TIMEOUT: Duration = Duration::from_secs(1);
+
+async fn configure_connection(
+    connection: &mut PgConnection,
+    statement_timeout: Option<Duration>,
+) -> Result<(), sqlx::Error> {
+    if let Some(statement_timeout) = statement_timeout {
+        connection
+            .execute(format!("SET statement_timeout = {}", statement_timeout.as_millis()).as_str())
+            .await?;
+    }
+    // Only supported since PostgreSQL 14.
+    if connection
+        .server_version_num()
+        .is_some_and(|version| version >= 140_000)
+    {
+        connection
+            .execute(
+                format!(
+                    "SET client_connection_check_interval = {}",
+                    CLIENT_CONNECTION_CHECK_INTERVAL.as_millis()
+                )
+                .as_str(),
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Closes connections that are still running a statement when they are returned to the pool,
+/// because the future running it was dropped, like that of a request whose client went away.
+/// Otherwise the pool would wait for the statement to finish before reusing the connection.
+/// The server cancels the statement once it notices the connection was closed.
+async fn check_released_connection(connection: &mut PgConnection) -> Result<bool, sqlx::Error> {
+    let Ok(result) = timeout(RELEASE_TIMEOUT, connection.ping()).await else {
+        debug!("Closing a connection released while running a statement");
+        return Ok(false);
+    };
+
+    result.map(|()| true)
+}
+
+/// How long to wait for a connection to a replica before falling back to the primary.
+pub const REPLICA_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long an unreachable replica is skipped, so that not every read waits for it to time out.
+const REPLICA_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Read-only `fetch_*` queries go to the replicas, if any, in turn.
+/// Unreachable replicas are skipped for a while, and queries fall back to the primary
+/// if all are unreachable.
+///
+/// Replicas may lag behind the primary, so reads right after writes should use
+/// [`DbClient::read_your_writes`].
+#[derive(Clone, Debug)]
+pub struct DbClient {
+    pool: PgPool,
+    /// `None` if reads go to the primary.
+    replicas: Option<Arc<Replicas>>,
+    /// Set on the handles of [`DbClient::transaction`], whose queries all run in it.
+    /// `None` inside once the transaction ended.
+    transaction: Option<Arc<AsyncMutex<Option<Transaction<'static, Postgres>>>>>,
+    /// See [`cache`](crate::cache).
+    cache: Option<Arc<DbCache>>,
+    /// See [`metrics`](crate::metrics).
+    metrics: Arc<QueryMetrics>,
+    /// See [`breaker`](crate::breaker).
+    breaker: Option<Arc<CircuitBreaker>>,
+    retry_policy: RetryPolicy,
+    /// Whether [`DbClient::drop_expired_tokens`] moves the tokens to the archive.
+    archive_expired_tokens: bool,
+    /// Whether changes of posts and users queue [`JobPayload::SearchIndexing`] jobs.
+    index_search: bool,
+    snowflake_generator: Arc<StellwerkAtomicSnowflakeGenerator>,
+}
+
+/// A connection of the pool, or the connection of the transaction of a handle.
+enum DbConnection<'a> {
+    Pool(PoolConnection<Postgres>),
+    Transaction(MappedMutexGuard<'a, Transaction<'static, Postgres>>),
+}
+
+impl Deref for DbConnection<'_> {
+    type Target = PgConnection;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            DbConnection::Pool(connection) => connection,
+            DbConnection::Transaction(transaction) => transaction,
+        }
+    }
+}
+
+impl DerefMut for DbConnection<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            DbConnection::Pool(connection) => connection,
+            DbConnection::Transaction(transaction) => transaction,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Replicas {
+    replicas: Box<[Replica]>,
+    next: AtomicUsize,
+}
+
+#[derive(Debug)]
+struct Replica {
+    pool: PgPool,
+    /// Set when acquiring a connection failed.
+    skipped_until: Mutex<Option<Instant>>,
+}
+
+impl DbClient {
+    /// Connects like [`DbClient::connect`] and runs the pending [migrations](MIGRATOR).
+    pub async fn connect_and_migrate(
+        url: &str,
+        replica_urls: &[impl AsRef<str>],
+        pool_settings: &PoolSettings,
+        worker_id: WorkerId,
+        process_id: ProcessId,
+    ) -> Result<Self> {
+        let client = Self::connect(url, replica_urls, pool_settings, worker_id, process_id).await?;
+        client.migrate().await?;
+
+        Ok(client)
+    }
+
+    /// Connections to the replicas are established lazily, so that unreachable replicas
+    /// do not prevent startup.
+    pub async fn connect(
+        url: &str,
+        replica_urls: &[impl AsRef<str>],
+        pool_settings: &PoolSettings,
+        worker_id: WorkerId,
+        process_id: ProcessId,
+    ) -> Result<Self> {
+        let pool = pool_settings.options().connect(url).await?;
+
+        let replicas = replica_urls
+            .iter()
+            .map(|url| {
+                pool_settings
+                    .options()
+                    .acquire_timeout(pool_settings.acquire_timeout.min(REPLICA_ACQUIRE_TIMEOUT))
+                    .connect_lazy(url.as_ref())
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self::new(pool, worker_id, process_id).with_replicas(replicas))
+    }
+
+    /// Runs the pending [migrations](MIGRATOR) on the primary,
+    /// on a connection without the statement timeout.
+    pub async fn migrate(&self) -> Result<()> {
+        let mut connection = self.pool.acquire().await?.detach();
+        connection.execute("SET statement_timeout = 0").await?;
+        MIGRATOR.run_direct(&mut connection).await?;
+        connection.close().await?;
+
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn new(pool: PgPool, worker_id: WorkerId, process_id: ProcessId) -> Self {
+        let snowflake_generator = Arc::new(StellwerkAtomicSnowflakeGenerator::new(
+            worker_id, process_id,
+        ));
+
+        Self {
+            pool,
+            replicas: None,
+            transaction: None,
+            cache: None,
+            metrics: Arc::default(),
+            breaker: None,
+            retry_policy: RetryPolicy::default(),
+            archive_expired_tokens: false,
+            index_search: false,
+            snowflake_generator,
+        }
+    }
+
+    /// Routes reads to `replicas`. Reads go to the primary if `replicas` is empty.
+    #[must_use]
+    pub fn with_replicas(self, replicas: Vec<PgPool>) -> Self {
+        let replicas = (!replicas.is_empty()).then(|| {
+            Arc::new(Replicas {
+                replicas: replicas
+                    .into_iter()
+                    .map(|pool| Replica {
+                        pool,
+                        skipped_until: Mutex::new(None),
+                    })
+                    .collect(),
+                next: AtomicUsize::new(0),
+            })
+        });
+
+        Self { replicas, ..self }
+    }
+
+    /// Answers hot lookups from `cache`, see [`cache`](crate::cache).
+    #[must_use]
+    pub fn with_cache(self, cache: DbCache) -> Self {
+        Self {
+            cache: Some(Arc::new(cache)),
+            ..self
+        }
+    }
+
+    /// Logs queries that take at least `threshold` with a warning.
+    #[must_use]
+    pub fn with_slow_query_threshold(self, threshold: Duration) -> Self {
+        Self {
+            metrics: Arc::new(QueryMetrics::new(Some(threshold))),
+            ..self
+        }
+    }
+
+    /// Fails fast while the primary is unreachable, see [`breaker`](crate::breaker).
+    #[must_use]
+    pub fn with_circuit_breaker(self, settings: BreakerSettings) -> Self {
+        Self {
+            breaker: Some(Arc::new(CircuitBreaker::new(settings))),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn with_retry_policy(self, retry_policy: RetryPolicy) -> Self {
+        Self {
+            retry_policy,
+            ..self
+        }
+    }
+
+    /// Moves expired tokens to `auth.auth_tokens_archive` instead of deleting them,
+    /// see [`DbClient::drop_expired_tokens`].
+    #[must_use]
+    pub fn with_token_archive(self) -> Self {
+        Self {
+            archive_expired_tokens: true,
+            ..self
+        }
+    }
+
+    /// Queues a [`JobPayload::SearchIndexing`] job in the transaction of every change of a post
+    /// or user, for an external search engine.
+    #[must_use]
+    pub fn with_search_indexing(self) -> Self {
+        Self {
+            index_search: true,
+            ..self
+        }
+    }
+
+    /// Generates IDs with `worker_id` and `process_id` instead of the ones given on creation,
+    /// for example after [leasing](DbClient::lease_worker_id) them.
+    #[must_use]
+    pub fn with_snowflake_ids(self, worker_id: WorkerId, process_id: ProcessId) -> Self {
+        Self {
+            snowflake_generator: Arc::new(StellwerkAtomicSnowflakeGenerator::new(
+                worker_id, process_id,
+            )),
+            ..self
+        }
+    }
+
+    /// The generator of the IDs of this client, to share with other subsystems
+    /// so that their IDs do not collide with those of the database.
+    #[must_use]
+    pub fn snowflake_generator(&self) -> Arc<StellwerkAtomicSnowflakeGenerator> {
+        self.snowflake_generator.clone()
+    }
+
+    /// Statistics of the queries of this client and the clients sharing its connections,
+    /// sorted by name.
+    #[must_use]
+    pub fn query_stats(&self) -> Vec<QueryStats> {
+        self.metrics.snapshot()
+    }
+
+    /// `None` if there is no circuit breaker.
+    #[must_use]
+    pub fn circuit_breaker_stats(&self) -> Option<CircuitBreakerStats> {
+        self.breaker.as_ref().map(|breaker| breaker.stats())
+    }
+
+    /// Checks that the primary answers, through the circuit breaker if there is one.
+    pub async fn ping(&self) -> Result<()> {
+        self.writer().await?.ping().await?;
+
+        Ok(())
+    }
+
+    /// The cache to read from, which transactions bypass.
+    fn read_cache(&self) -> Option<&DbCache> {
+        self.cache.as_deref().filter(|_| self.transaction.is_none())
+    }
+
+    async fn invalidate_cached_post(&self, post_id: Id<PostMarker>) {
+        if let Some(cache) = &self.cache {
+            cache.invalidate_post(post_id).await;
+        }
+    }
+
+    /// A client sharing this one's connections whose reads all go to the primary,
+    /// so that they see the writes made just before.
+    #[must_use]
+    pub fn read_your_writes(&self) -> Self {
+        Self {
+            replicas: None,
+            ..self.clone()
+        }
+    }
+
+    /// Runs `operation` in a transaction, which is committed if it succeeds
+    /// and rolled back if it fails.
+    ///
+    /// `operation` receives a handle with the methods of the client, whose queries all run in
+    /// the transaction, including reads. Methods that use transactions themselves use savepoints.
+    /// If this client is already a handle, `operation` runs in its transaction.
+    pub async fn transaction<T, E>(
+        &self,
+        operation: impl AsyncFnOnce(&DbClient) -> Result<T, E>,
+    ) -> Result<T, E>
+    where
+        E: From<DbError>,
+    {
+        if self.transaction.is_some() {
+            return operation(self).await;
+        }
+
+        let transaction = Arc::new(AsyncMutex::new(Some(
+            self.guarded(self.pool.begin()).await?,
+        )));
+        let handle = Self {
+            replicas: None,
+            transaction: Some(transaction.clone()),
+            ..self.clone()
+        };
+
+        let result = operation(&handle).await;
+        let transaction = transaction
+            .lock()
+            .await
+            .take()
+            .ok_or(DbError::TransactionEnded)?;
+        match result {
+            Ok(value) => {
+                transaction.commit().await.map_err(DbError::from)?;
+                Ok(value)
+            }
+            Err(error) => {
+                transaction.rollback().await.map_err(DbError::from)?;
+                Err(error)
+            }
+        }
+    }
+
+    /// Runs the idempotent `statement` named `name`, retrying it according to the [`RetryPolicy`]
+    /// if it fails transiently outside a transaction.
+    async fn idempotent<T, F>(&self, name: &'static str, statement: impl Fn() -> F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let mut retries = 0;
+        loop {
+            match statement().await {
+                Err(error)
+                    if self.transaction.is_none()
+                        && retries < self.retry_policy.max_retries
+                        && error.is_transient() =>
+                {
+                    retries += 1;
+                    let delay = self.retry_policy.backoff(retries);
+                    warn!(query = name, retries, ?delay, %error, "Retrying database query");
+                    self.metrics.record_retry(name);
+                    sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// The connection of the transaction, or a connection to the primary.
+    async fn writer(&self) -> Result<DbConnection<'_>> {
+        match &self.transaction {
+            Some(transaction) => {
+                let transaction = MutexGuard::try_map(transaction.lock().await, Option::as_mut)
+                    .map_err(|_| DbError::TransactionEnded)?;
+                Ok(DbConnection::Transaction(transaction))
+            }
+            None => Ok(DbConnection::Pool(self.guarded(self.pool.acquire()).await?)),
+        }
+    }
+
+    /// Connects to the primary through the circuit breaker, if there is one.
+    async fn guarded<T>(&self, connect: impl Future<Output = Result<T, sqlx::Error>>) -> Result<T> {
+        match &self.breaker {
+            Some(breaker) => breaker.call(connect).await,
+            None => Ok(connect.await?),
+        }
+    }
+
+    /// A connection to the next reachable replica, or to the primary if there is none.
+    async fn reader(&self) -> Result<DbConnection<'_>> {
+        if let Some(Replicas { replicas, next }) = self.replicas.as_deref() {
+            let start = next.fetch_add(1, Ordering::Relaxed);
+            for offset in 0..replicas.len() {
+                let index = (start + offset) % replicas.len();
+                let replica = &replicas[index];
+                if replica
+                    .skipped_until
+                    .lock()
+                    .is_some_and(|skipped_until| skipped_until > Instant::now())
+                {
+                    continue;
+                }
+
+                match replica.pool.acquire().await {
+                    Ok(connection) => return Ok(DbConnection::Pool(connection)),
+                    Err(error) => {
+                        warn!(%error, replica = index, "Skipping unreachable replica");
+                        *replica.skipped_until.lock() = Some(Instant::now() + REPLICA_RETRY_DELAY);
+                    }
+                }
+            }
+        }
+
+        self.writer().await
+    }
+
+    /// Starts listening for [`Event`](stellwerk_common::event::Event)s on a dedicated connection.
+    pub async fn listen(&self) -> Result<DbEventListener> {
+        DbEventListener::connect(&self.pool).await
+    }
+
+    pub async fn fetch_user(&self, user_id: Id<UserMarker>) -> Result<Option<User>> {
+        if let Some(cache) = self.read_cache()
+            && let Some(user) = cache.user(user_id).await
+        {
+            return Ok(Some(user));
+        }
+
+        let record = self
+            .idempotent("fetch_user", || async move {
+                query_as!(
+                    UserRecord,
+                    r#"
+                    SELECT
+                        users.user_snowflake,
+                        users.handle,
+                        users.verified_at IS NOT NULL AS "verified!"
+                    FROM
+                        users.users
+                    WHERE
+                        users.user_snowflake = $1
+                    "#,
+                    user_id.snowflake().get().cast_signed(),
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_user")
+                .await
+            })
+            .await?;
+
+        let user = record.map(User::try_from).transpose()?;
+        if let Some(cache) = self.read_cache()
+            && let Some(user) = &user
+        {
+            cache.insert_user(user).await;
+        }
+
+        Ok(user)
+    }
+
+    pub async fn fetch_user_by_handle(
+        &self,
+        tenant: &TenantId,
+        handle: &UserHandle,
+    ) -> Result<Option<User>> {
+        let record = self
+            .idempotent("fetch_user_by_handle", || async move {
+                query_as!(
+                    UserRecord,
+                    r#"
+                    SELECT
+                        users.user_snowflake,
+                        users.handle,
+                        users.verified_at IS NOT NULL AS "verified!"
+                    FROM
+                        users.users
+                    WHERE
+                        users.tenant = $1 AND users.handle = $2
+                    "#,
+                    tenant.get(),
+                    handle.get(),
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_user_by_handle")
+                .await
+            })
+            .await?;
+
+        let user = record.map(User::try_from).transpose()?;
+        Ok(user)
+    }
+
+    pub async fn fetch_user_profile(&self, user_id: Id<UserMarker>) -> Result<Option<UserProfile>> {
+        let record = self
+            .idempotent("fetch_user_profile", || async move {
+                query_as!(
+                    UserProfileRecord,
+                    r#"
+                    SELECT
+                        users.user_snowflake,
+                        users.handle,
+                        users.verified_at IS NOT NULL AS "verified!",
+                        users.website,
+                        users.post_count,
+                        users.follower_count,
+                        users.following_count
+                    FROM
+                        users.users
+                    WHERE
+                        users.user_snowflake = $1
+                    "#,
+                    user_id.snowflake().get().cast_signed(),
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_user_profile")
+                .await
+            })
+            .await?;
+
+        let profile = record.map(UserProfile::try_from).transpose()?;
+        Ok(profile)
+    }
+
+    /// Increases whenever the profile of the user changes.
+    pub async fn fetch_user_version(&self, user_id: Id<UserMarker>) -> Result<Option<u64>> {
+        let version = self
+            .idempotent("fetch_user_version", || async move {
+                query_scalar!(
+                    "
+                    SELECT users.profile_version
+                    FROM users.users
+                    WHERE users.user_snowflake = $1
+                    ",
+                    user_id.snowflake().get().cast_signed(),
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_user_version")
+                .await
+            })
+            .await?;
+
+        Ok(version.map(i64::cast_unsigned))
+    }
+
+    /// The profiles of those of `user_ids` that exist, in no particular order.
+    pub async fn fetch_user_profiles(
+        &self,
+        user_ids: &[Id<UserMarker>],
+    ) -> Result<Vec<UserProfile>> {
+        let snowflakes: &[_] = &user_ids
+            .iter()
+            .map(|id| id.snowflake().get().cast_signed())
+            .collect::<Vec<_>>();
+
+        let records = self
+            .idempotent("fetch_user_profiles", || async move {
+                query_as!(
+                    UserProfileRecord,
+                    r#"
+                    SELECT
+                        users.user_snowflake,
+                        users.handle,
+                        users.verified_at IS NOT NULL AS "verified!",
+                        users.website,
+                        users.post_count,
+                        users.follower_count,
+                        users.following_count
+                    FROM
+                        users.users
+                    WHERE
+                        users.user_snowflake = ANY($1)
+                    "#,
+                    snowflakes,
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_user_profiles")
+                .await
+            })
+            .await?;
+
+        let profiles = records
+            .into_iter()
+            .map(UserProfile::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(profiles)
+    }
+
+    pub async fn is_following(
+        &self,
+        follower: Id<UserMarker>,
+        target: Id<UserMarker>,
+    ) -> Result<bool> {
+        let following = self
+            .idempotent("is_following", || async move {
+                query_scalar!(
+                    r#"
+                    SELECT EXISTS(
+                        SELECT FROM users.follows
+                        WHERE
+                            follows.follower_snowflake = $1
+                            AND follows.followed_snowflake = $2
+                    ) as "exists!"
+                    "#,
+                    follower.snowflake().get().cast_signed(),
+                    target.snowflake().get().cast_signed(),
+                )
+                .fetch_one(&mut *self.writer().await?)
+                .measured_one(&self.metrics, "is_following")
+                .await
+            })
+            .await?;
+
+        Ok(following)
+    }
+
+    /// Makes `follower` follow `target`.
+    /// Returns `false` if `follower` already follows `target`.
+    pub async fn follow_user(
+        &self,
+        follower: Id<UserMarker>,
+        target: Id<UserMarker>,
+    ) -> Result<bool> {
+        let now_utc = UtcDateTime::now();
+        let now_primitive = PrimitiveDateTime::new(now_utc.date(), now_utc.time());
+
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+
+        let rows_affected = query!(
+            "
+            INSERT INTO users.follows (follower_snowflake, followed_snowflake, followed_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING
+            ",
+            follower.snowflake().get().cast_signed(),
+            target.snowflake().get().cast_signed(),
+            now_primitive,
+        )
+        .execute(&mut *transaction)
+        .measured(&self.metrics, "follow_user")
+        .await?
+        .rows_affected();
+
+        if rows_affected == 0 {
+            return Ok(false);
+        }
+
+        self.update_follow_counts(&mut transaction, follower, target, 1)
+            .await?;
+        events::notify(
+            FollowCreated {
+                follower,
+                followed: target,
+            },
+            &mut *transaction,
+        )
+        .await?;
+        transaction.commit().await?;
+
+        Ok(true)
+    }
+
+    /// Returns `false` if `follower` did not follow `target`.
+    pub async fn unfollow_user(
+        &self,
+        follower: Id<UserMarker>,
+        target: Id<UserMarker>,
+    ) -> Result<bool> {
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+
+        let rows_affected = query!(
+            "
+            DELETE FROM users.follows
+            WHERE
+                follows.follower_snowflake = $1
+                AND follows.followed_snowflake = $2
+            ",
+            follower.snowflake().get().cast_signed(),
+            target.snowflake().get().cast_signed(),
+        )
+        .execute(&mut *transaction)
+        .measured(&self.metrics, "unfollow_user")
+        .await?
+        .rows_affected();
+
+        if rows_affected == 0 {
+            return Ok(false);
+        }
+
+        self.update_follow_counts(&mut transaction, follower, target, -1)
+            .await?;
+        transaction.commit().await?;
+
+        Ok(true)
+    }
+
+    /// Returns the ids of all users `follower` follows.
+    pub async fn fetch_followed_ids(
+        &self,
+        follower: Id<UserMarker>,
+    ) -> Result<Vec<Id<UserMarker>>> {
+        let snowflakes = self
+            .idempotent("fetch_followed_ids", || async move {
+                query_scalar!(
+                    "
+                    SELECT follows.followed_snowflake
+                    FROM users.follows
+                    WHERE follows.follower_snowflake = $1
+                    ",
+                    follower.snowflake().get().cast_signed(),
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_followed_ids")
+                .await
+            })
+            .await?;
+
+        Ok(snowflakes
+            .into_iter()
+            .map(|snowflake| snowflake.cast_unsigned().into())
+            .collect())
+    }
+
+    async fn update_follow_counts(
+        &self,
+        transaction: &mut Transaction<'_, Postgres>,
+        follower: Id<UserMarker>,
+        target: Id<UserMarker>,
+        delta: i64,
+    ) -> Result<()> {
+        query!(
+            "
+            UPDATE users.users
+            SET
+                following_count = users.following_count
+                    + CASE WHEN users.user_snowflake = $1 THEN $3::bigint ELSE 0 END,
+                follower_count = users.follower_count
+                    + CASE WHEN users.user_snowflake = $2 THEN $3::bigint ELSE 0 END,
+                profile_version = users.profile_version + 1
+            WHERE users.user_snowflake IN ($1, $2)
+            ",
+            follower.snowflake().get().cast_signed(),
+            target.snowflake().get().cast_signed(),
+            delta,
+        )
+        .execute(&mut **transaction)
+        .measured(&self.metrics, "update_follow_counts")
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn fetch_user_role(&self, user_id: Id<UserMarker>) -> Result<Option<UserRole>> {
+        let role = self
+            .idempotent("fetch_user_role", || async move {
+                query_scalar!(
+                    "
+                    SELECT users.role
+                    FROM users.users
+                    WHERE users.user_snowflake = $1
+                    ",
+                    user_id.snowflake().get().cast_signed(),
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_user_role")
+                .await
+            })
+            .await?;
+
+        let role = role
+            .map(|role| role.parse())
+            .transpose()
+            .map_err(ModelValidationError::from)?;
+        Ok(role)
+    }
+
+    pub async fn fetch_user_tenant(&self, user_id: Id<UserMarker>) -> Result<Option<TenantId>> {
+        let tenant = self
+            .idempotent("fetch_user_tenant", || async move {
+                query_scalar!(
+                    "
+                    SELECT users.tenant
+                    FROM users.users
+                    WHERE users.user_snowflake = $1
+                    ",
+                    user_id.snowflake().get().cast_signed(),
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_user_tenant")
+                .await
+            })
+            .await?;
+
+        let tenant = tenant
+            .map(TenantId::new)
+            .transpose()
+            .map_err(ModelValidationError::from)?;
+        Ok(tenant)
+    }
+
+    /// Returns the user's posts, newest first, or `None` if the user does not exist.
+    /// `max_id` and `since_id` are exclusive bounds.
+    ///
+    /// Pinned posts are only returned on the first page (if neither bound is given),
+    /// where they are prepended to the up to `limit` unpinned posts.
+    pub async fn fetch_user_posts(
+        &self,
+        user_id: Id<UserMarker>,
+        max_id: Option<Id<PostMarker>>,
+        since_id: Option<Id<PostMarker>>,
+        limit: u32,
+    ) -> Result<Option<Vec<PartialPost>>> {
+        let mut connection = self.reader().await?;
+        let mut transaction = connection.begin().await?;
+
+        let user_exists = query_scalar!(
+            r#"
+            SELECT count(1) as "c!"
+            FROM users.users
+            WHERE users.user_snowflake = $1
+            "#,
+            user_id.snowflake().get().cast_signed(),
+        )
+        .fetch_one(&mut *transaction)
+        .measured_one(&self.metrics, "fetch_user_posts.user_exists")
+        .await?
+            != 0;
+
+        if !user_exists {
+            return Ok(None);
+        }
+
+        let mut records = if max_id.is_none() && since_id.is_none() {
+            query_as!(
+                PartialPostRecord,
+                r#"
+                SELECT
+                    posts.post_snowflake,
+                    posts.content,
+                    true as "pinned!"
+                FROM
+                    posts.posts
+                WHERE
+                    posts.user_snowflake = $1
+                    AND posts.pinned_at IS NOT NULL
+                    AND posts.deleted_at IS NULL
+                ORDER BY
+                    posts.pinned_at DESC
+                "#,
+                user_id.snowflake().get().cast_signed(),
+            )
+            .fetch_all(&mut *transaction)
+            .measured(&self.metrics, "fetch_user_posts.pinned")
+            .await?
+        } else {
+            Vec::new()
+        };
+
+        let unpinned_records = query_as!(
+            PartialPostRecord,
+            r#"
+            SELECT
+                posts.post_snowflake,
+                posts.content,
+                false as "pinned!"
+            FROM
+                posts.posts
+            WHERE
+                posts.user_snowflake = $1
+                AND posts.pinned_at IS NULL
+                AND posts.deleted_at IS NULL
+                AND ($2::bigint IS NULL OR posts.post_snowflake < $2)
+                AND ($3::bigint IS NULL OR posts.post_snowflake > $3)
+            ORDER BY
+                posts.post_snowflake DESC
+            LIMIT $4
+            "#,
+            user_id.snowflake().get().cast_signed(),
+            max_id.map(|id| id.snowflake().get().cast_signed()),
+            since_id.map(|id| id.snowflake().get().cast_signed()),
+            i64::from(limit),
+        )
+        .fetch_all(&mut *transaction)
+        .measured(&self.metrics, "fetch_user_posts.unpinned")
+        .await?;
+        records.extend(unpinned_records);
+
+        let posts = records
+            .into_iter()
+            .map(PartialPost::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Some(posts))
+    }
+
+    /// Returns all posts of `user_id`, oldest first, without buffering them, for exports.
+    /// The stream keeps a connection until it is dropped, and is not retried.
+    pub fn fetch_user_posts_stream(
+        &self,
+        user_id: Id<UserMarker>,
+    ) -> impl Stream<Item = Result<PartialPost>> + Send + '_ {
+        try_stream! {
+            let mut connection = self.reader().await?;
+            let mut records = query_as!(
+                PartialPostRecord,
+                r#"
+                SELECT
+                    posts.post_snowflake,
+                    posts.content,
+                    posts.pinned_at IS NOT NULL as "pinned!"
+                FROM
+                    posts.posts
+                WHERE
+                    posts.user_snowflake = $1
+                    AND posts.deleted_at IS NULL
+                ORDER BY
+                    posts.post_snowflake ASC
+                "#,
+                user_id.snowflake().get().cast_signed(),
+            )
+            .fetch(&mut *connection);
+
+            while let Some(record) = records.try_next().await? {
+                yield PartialPost::try_from(record)?;
+            }
+        }
+        .measured_stream(&self.metrics, "fetch_user_posts_stream")
+    }
+
+    pub async fn create_user(
+        &self,
+        tenant: &TenantId,
+        user: &CreateUser,
+    ) -> Result<Id<UserMarker>> {
+        let user_snowflake = self.snowflake_generator.generate()?;
+
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+        let returned_snowflake = query_scalar!(
+            "
+            INSERT INTO users.users (user_snowflake, handle, tenant)
+            VALUES ($1, $2, $3)
+            RETURNING users.user_snowflake
+            ",
+            user_snowflake.get().cast_signed(),
+            user.handle.get(),
+            tenant.get(),
+        )
+        .fetch_one(&mut *transaction)
+        .measured_one(&self.metrics, "create_user")
+        .await?;
+
+        let returned_id: Id<UserMarker> = returned_snowflake.cast_unsigned().into();
+        debug_assert_eq!(returned_id.snowflake(), user_snowflake);
+        self.enqueue_search_indexing(&mut transaction, SearchDocument::User(returned_id))
+            .await?;
+        transaction.commit().await?;
+
+        Ok(returned_id)
+    }
+
+    /// Returns the accounts of all users of the tenant, including remote ones, newest first.
+    /// `max_id` and `since_id` are exclusive bounds.
+    pub async fn fetch_user_accounts(
+        &self,
+        tenant: &TenantId,
+        max_id: Option<Id<UserMarker>>,
+        since_id: Option<Id<UserMarker>>,
+        limit: u32,
+    ) -> Result<Vec<UserAccount>> {
+        let records = self
+            .idempotent("fetch_user_accounts", || async move {
+                query_as!(
+                    UserAccountRecord,
+                    r#"
+                    SELECT
+                        users.user_snowflake,
+                        users.handle,
+                        users.verified_at IS NOT NULL AS "verified!",
+                        users.role,
+                        EXISTS(
+                            SELECT FROM federation.remote_actors
+                            WHERE remote_actors.user_snowflake = users.user_snowflake
+                        ) OR EXISTS(
+                            SELECT FROM federation.atproto_accounts
+                            WHERE atproto_accounts.user_snowflake = users.user_snowflake
+                        ) as "remote!"
+                    FROM
+                        users.users
+                    WHERE
+                        users.tenant = $1
+                        AND ($2::bigint IS NULL OR users.user_snowflake < $2)
+                        AND ($3::bigint IS NULL OR users.user_snowflake > $3)
+                    ORDER BY
+                        users.user_snowflake DESC
+                    LIMIT $4
+                    "#,
+                    tenant.get(),
+                    max_id.map(|id| id.snowflake().get().cast_signed()),
+                    since_id.map(|id| id.snowflake().get().cast_signed()),
+                    i64::from(limit),
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_user_accounts")
+                .await
+            })
+            .await?;
+
+        let accounts = records
+            .into_iter()
+            .map(UserAccount::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(accounts)
+    }
+
+    pub async fn fetch_user_account(&self, user_id: Id<UserMarker>) -> Result<Option<UserAccount>> {
+        let record = self
+            .idempotent("fetch_user_account", || async move {
+                query_as!(
+                    UserAccountRecord,
+                    r#"
+                    SELECT
+                        users.user_snowflake,
+                        users.handle,
+                        users.verified_at IS NOT NULL AS "verified!",
+                        users.role,
+                        EXISTS(
+                            SELECT FROM federation.remote_actors
+                            WHERE remote_actors.user_snowflake = users.user_snowflake
+                        ) OR EXISTS(
+                            SELECT FROM federation.atproto_accounts
+                            WHERE atproto_accounts.user_snowflake = users.user_snowflake
+                        ) as "remote!"
+                    FROM
+                        users.users
+                    WHERE
+                        users.user_snowflake = $1
+                    "#,
+                    user_id.snowflake().get().cast_signed(),
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_user_account")
+                .await
+            })
+            .await?;
+
+        let account = record.map(UserAccount::try_from).transpose()?;
+        Ok(account)
+    }
+
+    /// Returns `None` if the handle is taken in the tenant.
+    pub async fn create_user_account(
+        &self,
+        tenant: &TenantId,
+        account: &CreateUserAccount,
+    ) -> Result<Option<Id<UserMarker>>> {
+        let user_snowflake = self.snowflake_generator.generate()?;
+
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+        let Some(returned_snowflake) = query_scalar!(
+            "
+            INSERT INTO users.users (user_snowflake, handle, role, tenant)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (tenant, handle) DO NOTHING
+            RETURNING users.user_snowflake
+            ",
+            user_snowflake.get().cast_signed(),
+            account.handle.get(),
+            account.role.as_str(),
+            tenant.get(),
+        )
+        .fetch_optional(&mut *transaction)
+        .measured(&self.metrics, "create_user_account")
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let id = returned_snowflake.cast_unsigned().into();
+        self.enqueue_search_indexing(&mut transaction, SearchDocument::User(id))
+            .await?;
+        transaction.commit().await?;
+
+        Ok(Some(id))
+    }
+
+    /// Returns `false` if the user does not exist.
+    pub async fn set_user_role(&self, user_id: Id<UserMarker>, role: UserRole) -> Result<bool> {
+        let rows_affected = query!(
+            "
+            UPDATE users.users
+            SET role = $2
+            WHERE users.user_snowflake = $1
+            ",
+            user_id.snowflake().get().cast_signed(),
+            role.as_str(),
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "set_user_role")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected != 0)
+    }
+
+    /// Grants or revokes the verified badge. Returns whether the user exists.
+    pub async fn set_user_verified(&self, user_id: Id<UserMarker>, verified: bool) -> Result<bool> {
+        let now_utc = UtcDateTime::now();
+        let now_primitive = PrimitiveDateTime::new(now_utc.date(), now_utc.time());
+
+        let rows_affected = query!(
+            "
+            UPDATE users.users
+            SET
+                verified_at = CASE WHEN $2 THEN coalesce(users.verified_at, $3) END,
+                profile_version = users.profile_version
+                    + ((users.verified_at IS NOT NULL) != $2)::int
+            WHERE users.user_snowflake = $1
+            ",
+            user_id.snowflake().get().cast_signed(),
+            verified,
+            now_primitive,
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "set_user_verified")
+        .await?
+        .rows_affected();
+
+        if let Some(cache) = &self.cache {
+            cache.invalidate_user(user_id).await;
+        }
+
+        Ok(rows_affected != 0)
+    }
+
+    /// Sets or removes the website on the profile of the user.
+    pub async fn set_profile_website(
+        &self,
+        user_id: Id<UserMarker>,
+        website: Option<&ProfileWebsite>,
+    ) -> Result<()> {
+        query!(
+            "
+            UPDATE users.users
+            SET
+                website = $2::varchar,
+                profile_version = users.profile_version
+                    + (users.website IS DISTINCT FROM $2::varchar)::int
+            WHERE users.user_snowflake = $1
+            ",
+            user_id.snowflake().get().cast_signed(),
+            website.map(ProfileWebsite::get),
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "set_profile_website")
+        .await?;
+
+        Ok(())
+    }
+
+    /// The overrides of the instance's quotas, none if the user has none or does not exist.
+    pub async fn fetch_user_quotas(&self, user_id: Id<UserMarker>) -> Result<UserQuotas> {
+        let record = self
+            .idempotent("fetch_user_quotas", || async move {
+                query!(
+                    "
+                    SELECT
+                        quota_overrides.posts_per_day,
+                        quota_overrides.follows_per_day
+                    FROM
+                        users.quota_overrides
+                    WHERE
+                        quota_overrides.user_snowflake = $1
+                    ",
+                    user_id.snowflake().get().cast_signed(),
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_user_quotas")
+                .await
+            })
+            .await?;
+
+        let quotas = record.map_or_else(UserQuotas::default, |record| UserQuotas {
+            posts_per_day: record.posts_per_day.map(i32::cast_unsigned),
+            follows_per_day: record.follows_per_day.map(i32::cast_unsigned),
+        });
+        Ok(quotas)
+    }
+
+    /// Replaces the overrides of the user's quotas.
+    pub async fn set_user_quotas(&self, user_id: Id<UserMarker>, quotas: UserQuotas) -> Result<()> {
+        let to_column =
+            |limit: Option<u32>| limit.map(|limit| i32::try_from(limit).unwrap_or(i32::MAX));
+
+        query!(
+            "
+            INSERT INTO users.quota_overrides (user_snowflake, posts_per_day, follows_per_day)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_snowflake) DO UPDATE
+                SET posts_per_day = excluded.posts_per_day,
+                    follows_per_day = excluded.follows_per_day
+            ",
+            user_id.snowflake().get().cast_signed(),
+            to_column(quotas.posts_per_day),
+            to_column(quotas.follows_per_day),
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "set_user_quotas")
+        .await?;
+
+        Ok(())
+    }
+
+    /// The default if the user has not set do not disturb or does not exist.
+    pub async fn fetch_do_not_disturb(&self, user_id: Id<UserMarker>) -> Result<DoNotDisturb> {
+        self.idempotent("fetch_do_not_disturb", || async move {
+            self.select_do_not_disturb(&mut *self.reader().await?, user_id)
+                .await
+        })
+        .await
+    }
+
+    /// Like [`DbClient::fetch_do_not_disturb`], but on `connection`, so that it can be read in a
+    /// transaction.
+    async fn select_do_not_disturb(
+        &self,
+        connection: &mut PgConnection,
+        user_id: Id<UserMarker>,
+    ) -> Result<DoNotDisturb> {
+        let record = query!(
+            "
+            SELECT
+                do_not_disturb.quiet_hours_start,
+                do_not_disturb.quiet_hours_end,
+                do_not_disturb.quiet_hours_offset_seconds,
+                do_not_disturb.snoozed_until
+            FROM
+                users.do_not_disturb
+            WHERE
+                do_not_disturb.user_snowflake = $1
+            ",
+            user_id.snowflake().get().cast_signed(),
+        )
+        .fetch_optional(connection)
+        .measured(&self.metrics, "fetch_do_not_disturb")
+        .await?;
+
+        let Some(record) = record else {
+            return Ok(DoNotDisturb::default());
+        };
+        let quiet_hours = match (
+            record.quiet_hours_start,
+            record.quiet_hours_end,
+            record.quiet_hours_offset_seconds,
+        ) {
+            (Some(start), Some(end), Some(offset_seconds)) => Some(QuietHours {
+                start,
+                end,
+                utc_offset: QuietHours::utc_offset_from_seconds(offset_seconds)
+                    .map_err(ModelValidationError::from)?,
+            }),
+            _ => None,
+        };
+
+        Ok(DoNotDisturb {
+            quiet_hours,
+            snoozed_until: record.snoozed_until.map(PrimitiveDateTime::as_utc),
+        })
+    }
+
+    /// Replaces the do not disturb settings of the user.
+    pub async fn set_do_not_disturb(
+        &self,
+        user_id: Id<UserMarker>,
+        do_not_disturb: DoNotDisturb,
+    ) -> Result<()> {
+        let quiet_hours = do_not_disturb.quiet_hours;
+        let snoozed_until = do_not_disturb
+            .snoozed_until
+            .map(|until| PrimitiveDateTime::new(until.date(), until.time()));
+
+        query!(
+            "
+            INSERT INTO users.do_not_disturb
+                (user_snowflake, quiet_hours_start, quiet_hours_end, quiet_hours_offset_seconds,
+                 snoozed_until)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (user_snowflake) DO UPDATE
+                SET quiet_hours_start = excluded.quiet_hours_start,
+                    quiet_hours_end = excluded.quiet_hours_end,
+                    quiet_hours_offset_seconds = excluded.quiet_hours_offset_seconds,
+                    snoozed_until = excluded.snoozed_until
+            ",
+            user_id.snowflake().get().cast_signed(),
+            quiet_hours.map(|hours| hours.start),
+            quiet_hours.map(|hours| hours.end),
+            quiet_hours.map(|hours| hours.utc_offset.whole_seconds()),
+            snoozed_until,
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "set_do_not_disturb")
+        .await?;
+
+        Ok(())
+    }
+
+    /// Returns whether the user had set do not disturb.
+    pub async fn delete_do_not_disturb(&self, user_id: Id<UserMarker>) -> Result<bool> {
+        let rows_affected = query!(
+            "
+            DELETE FROM users.do_not_disturb
+            WHERE do_not_disturb.user_snowflake = $1
+            ",
+            user_id.snowflake().get().cast_signed(),
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "delete_do_not_disturb")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected != 0)
+    }
+
+    /// Counts one use of the quota on `day`, unless `limit` uses were counted already.
+    /// Returns whether it was counted.
+    pub async fn consume_quota(
+        &self,
+        user_id: Id<UserMarker>,
+        kind: QuotaKind,
+        day: Date,
+        limit: u32,
+    ) -> Result<bool> {
+        let used = query_scalar!(
+            "
+            INSERT INTO users.quota_usage (user_snowflake, kind, day, used)
+            VALUES ($1, $2, $3, 1)
+            ON CONFLICT (user_snowflake, kind, day) DO UPDATE
+                SET used = quota_usage.used + 1
+                WHERE quota_usage.used < $4
+            RETURNING quota_usage.used
+            ",
+            user_id.snowflake().get().cast_signed(),
+            kind.as_str(),
+            day,
+            i32::try_from(limit).unwrap_or(i32::MAX),
+        )
+        .fetch_optional(&mut *self.writer().await?)
+        .measured(&self.metrics, "consume_quota")
+        .await?;
+
+        Ok(used.is_some())
+    }
+
+    /// Deletes the usage of days before `before`, which no longer counts.
+    /// Returns number of affected rows
+    pub async fn purge_quota_usage(&self, before: Date) -> Result<u64> {
+        let rows_affected = query!(
+            "
+            DELETE FROM users.quota_usage
+            WHERE quota_usage.day < $1
+            ",
+            before,
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "purge_quota_usage")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected)
+    }
+
+    pub async fn fetch_post(&self, post_id: Id<PostMarker>) -> Result<Option<Post>> {
+        if let Some(cache) = self.read_cache()
+            && let Some(post) = cache.post(post_id).await
+        {
+            return Ok(Some(post));
+        }
+
+        let record = self
+            .idempotent("fetch_post", || async move {
+                query_as!(
+                    FullPostRecord,
+                    r#"
+                    SELECT
+                        posts.post_snowflake,
+                        posts.content,
+                        posts.pinned_at IS NOT NULL as "pinned!",
+                        users.user_snowflake,
+                        users.handle,
+                        users.verified_at IS NOT NULL AS "verified!"
+                    FROM
+                        posts.posts NATURAL JOIN users.users
+                    WHERE
+                        posts.post_snowflake = $1
+                        AND posts.deleted_at IS NULL
+                    "#,
+                    post_id.snowflake().get().cast_signed(),
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_post")
+                .await
+            })
+            .await?;
+
+        let post = record.map(Post::try_from).transpose()?;
+        if let Some(cache) = self.read_cache()
+            && let Some(post) = &post
+        {
+            cache.insert_post(post).await;
+        }
+
+        Ok(post)
+    }
+
+    pub async fn fetch_post_version(&self, post_id: Id<PostMarker>) -> Result<Option<PostVersion>> {
+        let record = self
+            .idempotent("fetch_post_version", || async move {
+                query!(
+                    r#"
+                    SELECT
+                        posts.edit_version,
+                        users.profile_version
+                    FROM
+                        posts.posts NATURAL JOIN users.users
+                    WHERE
+                        posts.post_snowflake = $1
+                        AND posts.deleted_at IS NULL
+                    "#,
+                    post_id.snowflake().get().cast_signed(),
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_post_version")
+                .await
+            })
+            .await?;
+
+        Ok(record.map(|record| PostVersion {
+            post: record.edit_version.cast_unsigned(),
+            author: record.profile_version.cast_unsigned(),
+        }))
+    }
+
+    /// Returns the newest posts of all not suspended users of the tenant, newest first.
+    /// Posts of shadowbanned users are only returned to themselves as `viewer`.
+    /// `max_id` and `since_id` are exclusive bounds.
+    pub async fn fetch_public_posts(
+        &self,
+        tenant: &TenantId,
+        viewer: Option<Id<UserMarker>>,
+        max_id: Option<Id<PostMarker>>,
+        since_id: Option<Id<PostMarker>>,
+        limit: u32,
+    ) -> Result<Vec<Post>> {
+        let records = self
+            .idempotent("fetch_public_posts", || async move {
+                query_as!(
+                    FullPostRecord,
+                    r#"
+                    SELECT
+                        posts.post_snowflake,
+                        posts.content,
+                        posts.pinned_at IS NOT NULL as "pinned!",
+                        users.user_snowflake,
+                        users.handle,
+                        users.verified_at IS NOT NULL AS "verified!"
+                    FROM
+                        posts.posts NATURAL JOIN users.users
+                    WHERE
+                        posts.tenant = $1
+                        AND posts.deleted_at IS NULL
+                        AND users.suspended_at IS NULL
+                        AND (users.shadowbanned_at IS NULL OR users.user_snowflake = $5)
+                        AND ($2::bigint IS NULL OR posts.post_snowflake < $2)
+                        AND ($3::bigint IS NULL OR posts.post_snowflake > $3)
+                    ORDER BY
+                        posts.post_snowflake DESC
+                    LIMIT $4
+                    "#,
+                    tenant.get(),
+                    max_id.map(|id| id.snowflake().get().cast_signed()),
+                    since_id.map(|id| id.snowflake().get().cast_signed()),
+                    i64::from(limit),
+                    viewer.map(|id| id.snowflake().get().cast_signed()),
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_public_posts")
+                .await
+            })
+            .await?;
+
+        let posts = records
+            .into_iter()
+            .map(Post::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(posts)
+    }
+
+    /// Returns all posts of the tenant that are on its public timeline, oldest first, without
+    /// buffering them, for feeds and backfills. Like [`Self::fetch_public_posts`], posts of
+    /// suspended and shadowbanned users are left out.
+    /// The stream keeps a connection until it is dropped, and is not retried.
+    pub fn fetch_all_public_posts_stream<'a>(
+        &'a self,
+        tenant: &'a TenantId,
+    ) -> impl Stream<Item = Result<Post>> + Send + 'a {
+        try_stream! {
+            let mut connection = self.reader().await?;
+            let mut records = query_as!(
+                FullPostRecord,
+                r#"
+                SELECT
+                    posts.post_snowflake,
+                    posts.content,
+                    posts.pinned_at IS NOT NULL as "pinned!",
+                    users.user_snowflake,
+                    users.handle,
+                    users.verified_at IS NOT NULL AS "verified!"
+                FROM
+                    posts.posts NATURAL JOIN users.users
+                WHERE
+                    posts.tenant = $1
+                    AND posts.deleted_at IS NULL
+                    AND users.suspended_at IS NULL
+                    AND users.shadowbanned_at IS NULL
+                ORDER BY
+                    posts.post_snowflake ASC
+                "#,
+                tenant.get(),
+            )
+            .fetch(&mut *connection);
+
+            while let Some(record) = records.try_next().await? {
+                yield Post::try_from(record)?;
+            }
+        }
+        .measured_stream(&self.metrics, "fetch_all_public_posts_stream")
+    }
+
+    /// Counts the profiles and posts of local, not suspended users of `tenant`, which are listed
+    /// in its sitemap.
+    pub async fn fetch_sitemap_counts(&self, tenant: &TenantId) -> Result<SitemapCounts> {
+        let record = self
+            .idempotent("fetch_sitemap_counts", || async move {
+                query!(
+                    r#"
+                    WITH listed_users AS (
+                        SELECT users.user_snowflake FROM users.users
+                        WHERE
+                            users.tenant = $1
+                            AND users.suspended_at IS NULL
+                            AND users.shadowbanned_at IS NULL
+                            AND NOT EXISTS(
+                                SELECT FROM federation.remote_actors
+                                WHERE remote_actors.user_snowflake = users.user_snowflake
+                            )
+                            AND NOT EXISTS(
+                                SELECT FROM federation.atproto_accounts
+                                WHERE atproto_accounts.user_snowflake = users.user_snowflake
+                            )
+                    )
+                    SELECT
+                        (SELECT count(*) FROM listed_users) as "profile_count!",
+                        (
+                            SELECT count(*) FROM posts.posts NATURAL JOIN listed_users
+                            WHERE posts.deleted_at IS NULL
+                        ) as "post_count!"
+                    "#,
+                    tenant.get(),
+                )
+                .fetch_one(&mut *self.reader().await?)
+                .measured_one(&self.metrics, "fetch_sitemap_counts")
+                .await
+            })
+            .await?;
+
+        Ok(SitemapCounts {
+            profiles: record.profile_count.cast_unsigned(),
+            posts: record.post_count.cast_unsigned(),
+        })
+    }
+
+    /// Returns the local, not suspended users of `tenant`, oldest first, skipping `offset` of them,
+    /// without buffering them, for sitemaps.
+    /// The stream keeps a connection until it is dropped, and is not retried.
+    pub fn fetch_sitemap_users_stream<'a>(
+        &'a self,
+        tenant: &'a TenantId,
+        offset: u64,
+        limit: u32,
+    ) -> impl Stream<Item = Result<User>> + Send + 'a {
+        try_stream! {
+            let mut connection = self.reader().await?;
+            let mut records = query_as!(
+                UserRecord,
+                r#"
+                SELECT
+                    users.user_snowflake,
+                    users.handle,
+                    users.verified_at IS NOT NULL AS "verified!"
+                FROM users.users
+                WHERE
+                    users.tenant = $1
+                    AND users.suspended_at IS NULL
+                    AND users.shadowbanned_at IS NULL
+                    AND NOT EXISTS(
+                        SELECT FROM federation.remote_actors
+                        WHERE remote_actors.user_snowflake = users.user_snowflake
+                    )
+                    AND NOT EXISTS(
+                        SELECT FROM federation.atproto_accounts
+                        WHERE atproto_accounts.user_snowflake = users.user_snowflake
+                    )
+                ORDER BY users.user_snowflake ASC
+                OFFSET $2
+                LIMIT $3
+                "#,
+                tenant.get(),
+                offset.cast_signed(),
+                i64::from(limit),
+            )
+            .fetch(&mut *connection);
+
+            while let Some(record) = records.try_next().await? {
+                yield User::try_from(record)?;
+            }
+        }
+        .measured_stream(&self.metrics, "fetch_sitemap_users_stream")
+    }
+
+    /// Returns the posts listed in the sitemap of `tenant`, oldest first, skipping `offset` of them,
+    /// without buffering them. See [`DbClient::fetch_sitemap_users_stream`] for which users are
+    /// listed.
+    /// The stream keeps a connection until it is dropped, and is not retried.
+    pub fn fetch_sitemap_posts_stream<'a>(
+        &'a self,
+        tenant: &'a TenantId,
+        offset: u64,
+        limit: u32,
+    ) -> impl Stream<Item = Result<SitemapPost>> + Send + 'a {
+        try_stream! {
+            let mut connection = self.reader().await?;
+            let mut records = query!(
+                "
+                SELECT posts.post_snowflake, users.handle
+                FROM posts.posts NATURAL JOIN users.users
+                WHERE
+                    posts.tenant = $1
+                    AND posts.deleted_at IS NULL
+                    AND users.suspended_at IS NULL
+                    AND users.shadowbanned_at IS NULL
+                    AND NOT EXISTS(
+                        SELECT FROM federation.remote_actors
+                        WHERE remote_actors.user_snowflake = users.user_snowflake
+                    )
+                    AND NOT EXISTS(
+                        SELECT FROM federation.atproto_accounts
+                        WHERE atproto_accounts.user_snowflake = users.user_snowflake
+                    )
+                ORDER BY posts.post_snowflake ASC
+                OFFSET $2
+                LIMIT $3
+                ",
+                tenant.get(),
+                offset.cast_signed(),
+                i64::from(limit),
+            )
+            .fetch(&mut *connection);
+
+            while let Some(record) = records.try_next().await? {
+                yield SitemapPost {
+                    id: record.post_snowflake.cast_unsigned().into(),
+                    author: UserHandle::new(record.handle).map_err(ModelValidationError::from)?,
+                };
+            }
+        }
+        .measured_stream(&self.metrics, "fetch_sitemap_posts_stream")
+    }
+
+    /// Returns the newest posts of `user_id` and the users they follow, newest first.
+    /// `max_id` and `since_id` are exclusive bounds.
+    pub async fn fetch_home_posts(
+        &self,
+        user_id: Id<UserMarker>,
+        max_id: Option<Id<PostMarker>>,
+        since_id: Option<Id<PostMarker>>,
+        limit: u32,
+    ) -> Result<Vec<Post>> {
+        let records = self
+            .idempotent("fetch_home_posts", || async move {
+                query_as!(
+                    FullPostRecord,
+                    r#"
+                    SELECT
+                        posts.post_snowflake,
+                        posts.content,
+                        posts.pinned_at IS NOT NULL as "pinned!",
+                        users.user_snowflake,
+                        users.handle,
+                        users.verified_at IS NOT NULL AS "verified!"
+                    FROM
+                        posts.posts NATURAL JOIN users.users
+                    WHERE
+                        (
+                            posts.user_snowflake = $1
+                            OR posts.user_snowflake IN (
+                                SELECT follows.followed_snowflake
+                                FROM users.follows
+                                WHERE follows.follower_snowflake = $1
+                            )
+                        )
+                        AND posts.deleted_at IS NULL
+                        AND ($2::bigint IS NULL OR posts.post_snowflake < $2)
+                        AND ($3::bigint IS NULL OR posts.post_snowflake > $3)
+                    ORDER BY
+                        posts.post_snowflake DESC
+                    LIMIT $4
+                    "#,
+                    user_id.snowflake().get().cast_signed(),
+                    max_id.map(|id| id.snowflake().get().cast_signed()),
+                    since_id.map(|id| id.snowflake().get().cast_signed()),
+                    i64::from(limit),
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_home_posts")
+                .await
+            })
+            .await?;
+
+        let posts = records
+            .into_iter()
+            .map(Post::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(posts)
+    }
+
+    pub async fn create_post(&self, post: &CreatePost) -> Result<Id<PostMarker>> {
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+        let id = self.insert_post(&mut transaction, post).await?;
+        transaction.commit().await?;
+
+        Ok(id)
+    }
+
+    /// Creates a post received from another server, identified by its ActivityPub `object_id`.
+    /// Returns [`None`] if the post was already received.
+    pub async fn create_remote_post(
+        &self,
+        post: &CreatePost,
+        object_id: &str,
+    ) -> Result<Option<Id<PostMarker>>> {
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+
+        let exists = query_scalar!(
+            r#"
+            SELECT EXISTS(
+                SELECT FROM federation.remote_posts
+                WHERE remote_posts.object_id = $1
+            ) as "exists!"
+            "#,
+            object_id,
+        )
+        .fetch_one(&mut *transaction)
+        .measured_one(&self.metrics, "create_remote_post.exists")
+        .await?;
+
+        if exists {
+            return Ok(None);
+        }
+
+        let id = self.insert_post(&mut transaction, post).await?;
+        query!(
+            "
+            INSERT INTO federation.remote_posts (post_snowflake, object_id)
+            VALUES ($1, $2)
+            ",
+            id.snowflake().get().cast_signed(),
+            object_id,
+        )
+        .execute(&mut *transaction)
+        .measured(&self.metrics, "create_remote_post.insert")
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(Some(id))
+    }
+
+    /// Creates a post mirroring the AT Protocol post `uri`,
+    /// or returns `None` if it was already mirrored.
+    pub async fn create_atproto_post(
+        &self,
+        post: &CreatePost,
+        uri: &str,
+        cid: &str,
+        created_at: UtcDateTime,
+    ) -> Result<Option<Id<PostMarker>>> {
+        let created_at = PrimitiveDateTime::new(created_at.date(), created_at.time());
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+
+        let exists = query_scalar!(
+            r#"
+            SELECT EXISTS(
+                SELECT FROM federation.atproto_posts
+                WHERE atproto_posts.uri = $1
+            ) as "exists!"
+            "#,
+            uri,
+        )
+        .fetch_one(&mut *transaction)
+        .measured_one(&self.metrics, "create_atproto_post.exists")
+        .await?;
+
+        if exists {
+            return Ok(None);
+        }
+
+        let id = self.insert_post(&mut transaction, post).await?;
+        query!(
+            "
+            INSERT INTO federation.atproto_posts (post_snowflake, uri, cid, created_at)
+            VALUES ($1, $2, $3, $4)
+            ",
+            id.snowflake().get().cast_signed(),
+            uri,
+            cid,
+            created_at,
+        )
+        .execute(&mut *transaction)
+        .measured(&self.metrics, "create_atproto_post.insert")
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(Some(id))
+    }
+
+    async fn insert_post(
+        &self,
+        transaction: &mut Transaction<'_, Postgres>,
+        post: &CreatePost,
+    ) -> Result<Id<PostMarker>> {
+        let post_snowflake = self.snowflake_generator.generate()?;
+
+        let record = query!(
+            "
+            INSERT INTO posts.posts (post_snowflake, content, user_snowflake, tenant)
+            VALUES (
+                $1,
+                $2,
+                $3,
+                (SELECT users.tenant FROM users.users WHERE users.user_snowflake = $3)
+            )
+            RETURNING posts.post_snowflake, posts.tenant
+            ",
+            post_snowflake.get().cast_signed(),
+            post.content.get(),
+            post.author.snowflake().get().cast_signed(),
+        )
+        .fetch_one(&mut **transaction)
+        .measured_one(&self.metrics, "insert_post")
+        .await?;
+
+        query!(
+            "
+            UPDATE users.users
+            SET post_count = users.post_count + 1,
+                profile_version = users.profile_version + 1
+            WHERE users.user_snowflake = $1
+            ",
+            post.author.snowflake().get().cast_signed(),
+        )
+        .execute(&mut **transaction)
+        .measured(&self.metrics, "insert_post.post_count")
+        .await?;
+
+        let id = record.post_snowflake.cast_unsigned().into();
+        events::notify(
+            PostCreated {
+                post: id,
+                author: post.author,
+                tenant: TenantId::new(record.tenant).map_err(ModelValidationError::from)?,
+            },
+            &mut **transaction,
+        )
+        .await?;
+        self.enqueue_webhook_deliveries(
+            transaction,
+            post.author,
+            WebhookPayload::FollowedPost {
+                post: id,
+                author: post.author,
+            },
+            UtcDateTime::now(),
+        )
+        .await?;
+        self.enqueue_search_indexing(transaction, SearchDocument::Post(id))
+            .await?;
+
+        Ok(id)
+    }
+
+    /// Pins the post to its author's profile.
+    /// Returns `false` if the author already has `max_pinned` pinned posts.
+    /// Pinning an already pinned or nonexistent post succeeds without changes.
+    pub async fn pin_post(&self, post_id: Id<PostMarker>, max_pinned: usize) -> Result<bool> {
+        let now_utc = UtcDateTime::now();
+        let now_primitive = PrimitiveDateTime::new(now_utc.date(), now_utc.time());
+
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+
+        // Locking the author row serializes concurrent pins by the same user.
+        let Some(author_snowflake) = query_scalar!(
+            "
+            SELECT users.user_snowflake
+            FROM posts.posts NATURAL JOIN users.users
+            WHERE posts.post_snowflake = $1 AND posts.deleted_at IS NULL
+            FOR UPDATE OF users
+            ",
+            post_id.snowflake().get().cast_signed(),
+        )
+        .fetch_optional(&mut *transaction)
+        .measured(&self.metrics, "pin_post.author")
+        .await?
+        else {
+            return Ok(true);
+        };
+
+        let pinned_count = query_scalar!(
+            r#"
+            SELECT count(1) as "c!"
+            FROM posts.posts
+            WHERE
+                posts.user_snowflake = $1
+                AND posts.pinned_at IS NOT NULL
+                AND posts.post_snowflake != $2
+            "#,
+            author_snowflake,
+            post_id.snowflake().get().cast_signed(),
+        )
+        .fetch_one(&mut *transaction)
+        .measured_one(&self.metrics, "pin_post.pinned_count")
+        .await?;
+
+        if usize::try_from(pinned_count).is_ok_and(|pinned_count| pinned_count >= max_pinned) {
+            return Ok(false);
+        }
+
+        query!(
+            "
+            UPDATE posts.posts
+            SET pinned_at = $2,
+                edit_version = posts.edit_version + 1
+            WHERE posts.post_snowflake = $1 AND posts.pinned_at IS NULL
+            ",
+            post_id.snowflake().get().cast_signed(),
+            now_primitive,
+        )
+        .execute(&mut *transaction)
+        .measured(&self.metrics, "pin_post.update")
+        .await?;
+
+        transaction.commit().await?;
+        self.invalidate_cached_post(post_id).await;
+
+        Ok(true)
+    }
+
+    pub async fn unpin_post(&self, post_id: Id<PostMarker>) -> Result<()> {
+        query!(
+            "
+            UPDATE posts.posts
+            SET pinned_at = NULL,
+                edit_version = posts.edit_version + 1
+            WHERE posts.post_snowflake = $1 AND posts.pinned_at IS NOT NULL
+            ",
+            post_id.snowflake().get().cast_signed(),
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "unpin_post")
+        .await?;
+        self.invalidate_cached_post(post_id).await;
+
+        Ok(())
+    }
+
+    /// Deletes the post softly: it disappears from everything but
+    /// [`DbClient::fetch_moderated_post`] until it is restored or purged.
+    /// Deleting a post unpins it. Returns `false` if the post does not exist or is already deleted.
+    pub async fn delete_post(&self, post_id: Id<PostMarker>) -> Result<bool> {
+        let now_utc = UtcDateTime::now();
+        let now_primitive = PrimitiveDateTime::new(now_utc.date(), now_utc.time());
+
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+
+        let Some(author_snowflake) = query_scalar!(
+            "
+            UPDATE posts.posts
+            SET deleted_at = $2,
+                pinned_at = NULL,
+                edit_version = posts.edit_version + 1
+            WHERE posts.post_snowflake = $1 AND posts.deleted_at IS NULL
+            RETURNING posts.user_snowflake
+            ",
+            post_id.snowflake().get().cast_signed(),
+            now_primitive,
+        )
+        .fetch_optional(&mut *transaction)
+        .measured(&self.metrics, "delete_post.update")
+        .await?
+        else {
+            return Ok(false);
+        };
+
+        query!(
+            "
+            UPDATE users.users
+            SET post_count = users.post_count - 1,
+                profile_version = users.profile_version + 1
+            WHERE users.user_snowflake = $1
+            ",
+            author_snowflake,
+        )
+        .execute(&mut *transaction)
+        .measured(&self.metrics, "delete_post.post_count")
+        .await?;
+
+        events::notify(
+            PostDeleted {
+                post: post_id,
+                author: author_snowflake.cast_unsigned().into(),
+            },
+            &mut *transaction,
+        )
+        .await?;
+        self.enqueue_search_indexing(&mut transaction, SearchDocument::Post(post_id))
+            .await?;
+        transaction.commit().await?;
+        self.invalidate_cached_post(post_id).await;
+
+        Ok(true)
+    }
+
+    /// Undoes [`DbClient::delete_post`], unless the post was purged in the meantime.
+    /// Returns `false` if the post does not exist or is not deleted.
+    pub async fn restore_post(&self, post_id: Id<PostMarker>) -> Result<bool> {
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+
+        let Some(author_snowflake) = query_scalar!(
+            "
+            UPDATE posts.posts
+            SET deleted_at = NULL,
+                edit_version = posts.edit_version + 1
+            WHERE posts.post_snowflake = $1 AND posts.deleted_at IS NOT NULL
+            RETURNING posts.user_snowflake
+            ",
+            post_id.snowflake().get().cast_signed(),
+        )
+        .fetch_optional(&mut *transaction)
+        .measured(&self.metrics, "restore_post.update")
+        .await?
+        else {
+            return Ok(false);
+        };
+
+        query!(
+            "
+            UPDATE users.users
+            SET post_count = users.post_count + 1,
+                profile_version = users.profile_version + 1
+            WHERE users.user_snowflake = $1
+            ",
+            author_snowflake,
+        )
+        .execute(&mut *transaction)
+        .measured(&self.metrics, "restore_post.post_count")
+        .await?;
+
+        self.enqueue_search_indexing(&mut transaction, SearchDocument::Post(post_id))
+            .await?;
+        transaction.commit().await?;
+        self.invalidate_cached_post(post_id).await;
+
+        Ok(true)
+    }
+
+    /// Like [`DbClient::fetch_post`], but also returns deleted posts that were not purged yet.
+    pub async fn fetch_moderated_post(
+        &self,
+        post_id: Id<PostMarker>,
+    ) -> Result<Option<ModeratedPost>> {
+        let record = self
+            .idempotent("fetch_moderated_post", || async move {
+                query_as!(
+                    ModeratedPostRecord,
+                    r#"
+                    SELECT
+                        posts.post_snowflake,
+                        posts.content,
+                        posts.pinned_at IS NOT NULL as "pinned!",
+                        users.user_snowflake,
+                        users.handle,
+                        users.verified_at IS NOT NULL AS "verified!",
+                        posts.deleted_at
+                    FROM
+                        posts.posts NATURAL JOIN users.users
+                    WHERE
+                        posts.post_snowflake = $1
+                    "#,
+                    post_id.snowflake().get().cast_signed(),
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_moderated_post")
+                .await
+            })
+            .await?;
+
+        let post = record.map(ModeratedPost::try_from).transpose()?;
+        Ok(post)
+    }
+
+    /// Those of `post_ids` that exist, including deleted ones, in no particular order.
+    pub async fn fetch_moderated_posts(
+        &self,
+        post_ids: &[Id<PostMarker>],
+    ) -> Result<Vec<ModeratedPost>> {
+        let snowflakes: &[_] = &post_ids
+            .iter()
+            .map(|id| id.snowflake().get().cast_signed())
+            .collect::<Vec<_>>();
+
+        let records = self
+            .idempotent("fetch_moderated_posts", || async move {
+                query_as!(
+                    ModeratedPostRecord,
+                    r#"
+                    SELECT
+                        posts.post_snowflake,
+                        posts.content,
+                        posts.pinned_at IS NOT NULL as "pinned!",
+                        users.user_snowflake,
+                        users.handle,
+                        users.verified_at IS NOT NULL AS "verified!",
+                        posts.deleted_at
+                    FROM
+                        posts.posts NATURAL JOIN users.users
+                    WHERE
+                        posts.post_snowflake = ANY($1)
+                    "#,
+                    snowflakes,
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_moderated_posts")
+                .await
+            })
+            .await?;
+
+        let posts = records
+            .into_iter()
+            .map(ModeratedPost::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(posts)
+    }
+
+    /// Permanently removes posts deleted before `deleted_before`,
+    /// along with the notifications about them.
+    /// Returns number of affected rows
+    pub async fn purge_deleted_posts(&self, deleted_before: UtcDateTime) -> Result<u64> {
+        let deleted_before = PrimitiveDateTime::new(deleted_before.date(), deleted_before.time());
+
+        let rows_affected = query!(
+            "
+            DELETE FROM posts.posts
+            WHERE posts.deleted_at < $1
+            ",
+            deleted_before,
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "purge_deleted_posts")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected)
+    }
+
+    /// Adds the views to the counts of the posts, skipping posts that were purged in the meantime.
+    /// This is not idempotent, so it is not retried.
+    pub async fn record_post_views(&self, views: &[(Id<PostMarker>, u64)]) -> Result<()> {
+        let (snowflakes, counts): (Vec<_>, Vec<_>) = views
+            .iter()
+            .map(|(post_id, count)| (post_id.snowflake().get().cast_signed(), count.cast_signed()))
+            .unzip();
+
+        query!(
+            "
+            INSERT INTO posts.post_views (post_snowflake, views)
+            SELECT post_snowflake, new.views
+            FROM
+                UNNEST($1::bigint[], $2::bigint[]) AS new (post_snowflake, views)
+                NATURAL JOIN posts.posts
+            ON CONFLICT (post_snowflake) DO UPDATE
+                SET views = post_views.views + excluded.views
+            ",
+            &snowflakes,
+            &counts,
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "record_post_views")
+        .await?;
+
+        Ok(())
+    }
+
+    /// Zero views if the post does not exist.
+    pub async fn fetch_post_stats(&self, post_id: Id<PostMarker>) -> Result<PostStats> {
+        let views = self
+            .idempotent("fetch_post_stats", || async move {
+                query_scalar!(
+                    "
+                    SELECT post_views.views
+                    FROM posts.post_views
+                    WHERE post_views.post_snowflake = $1
+                    ",
+                    post_id.snowflake().get().cast_signed(),
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_post_stats")
+                .await
+            })
+            .await?;
+
+        Ok(PostStats {
+            views: views.unwrap_or_default().cast_unsigned(),
+        })
+    }
+
+    pub async fn fetch_auth(&self, token_hash: &AuthTokenHash) -> Result<Option<Authentication>> {
+        if let Some(cache) = self.read_cache()
+            && let Some(authentication) = cache.auth(token_hash).await
+        {
+            return Ok(Some(authentication));
+        }
+
+        let record = self
+            .idempotent("fetch_auth", || async move {
+                query_as!(
+                    AuthenticationRecord,
+                    "
+                    SELECT
+                        auth_tokens.user_snowflake,
+                        auth_tokens.tenant,
+                        auth_tokens.token_hash,
+                        auth_tokens.created_at,
+                        auth_tokens.expires_after_seconds,
+                        auth_tokens.impersonator_snowflake
+                    FROM
+                        auth.auth_tokens
+                        JOIN users.users ON users.user_snowflake = auth_tokens.user_snowflake
+                    WHERE
+                        auth_tokens.token_hash = $1 AND users.suspended_at IS NULL
+                    ",
+                    &token_hash.0,
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_auth")
+                .await
+            })
+            .await?;
+
+        let authentication = record.map(Authentication::try_from).transpose()?;
+        if let Some(cache) = self.read_cache()
+            && let Some(authentication) = &authentication
+        {
+            cache.insert_auth(authentication).await;
+        }
+
+        Ok(authentication)
+    }
+
+    /// Deletes expired tokens, or moves them to the archive if the client was built
+    /// [`with_token_archive`](DbClient::with_token_archive).
+    /// Returns number of affected rows
+    pub async fn drop_expired_tokens(&self) -> Result<u64> {
+        let now_utc = UtcDateTime::now();
+        let now_primitive = PrimitiveDateTime::new(now_utc.date(), now_utc.time());
+
+        if self.archive_expired_tokens {
+            return self.archive_expired_tokens(now_primitive).await;
+        }
+
+        let rows_affected = query!(
+            "
+            DELETE FROM auth.auth_tokens
+            WHERE auth_tokens.created_at
+                      + make_interval(secs := auth_tokens.expires_after_seconds)
+                      < $1
+            ",
+            now_primitive,
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "drop_expired_tokens")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected)
+    }
+
+    async fn archive_expired_tokens(&self, now: PrimitiveDateTime) -> Result<u64> {
+        let rows_affected = query!(
+            "
+            WITH expired AS (
+                DELETE FROM auth.auth_tokens
+                WHERE auth_tokens.created_at
+                          + make_interval(secs := auth_tokens.expires_after_seconds)
+                          < $1
+                RETURNING
+                    auth_tokens.user_snowflake,
+                    auth_tokens.token_hash,
+                    auth_tokens.created_at,
+                    auth_tokens.expires_after_seconds,
+                    auth_tokens.impersonator_snowflake
+            )
+            INSERT INTO auth.auth_tokens_archive (
+                user_snowflake,
+                token_hash,
+                created_at,
+                expires_after_seconds,
+                archived_at,
+                impersonator_snowflake
+            )
+            SELECT
+                expired.user_snowflake,
+                expired.token_hash,
+                expired.created_at,
+                expired.expires_after_seconds,
+                $1,
+                expired.impersonator_snowflake
+            FROM
+                expired
+            ",
+            now,
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "drop_expired_tokens.archive")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected)
+    }
+
+    /// Permanently removes tokens archived before `archived_before`.
+    /// Returns number of affected rows
+    pub async fn purge_archived_tokens(&self, archived_before: UtcDateTime) -> Result<u64> {
+        let archived_before =
+            PrimitiveDateTime::new(archived_before.date(), archived_before.time());
+
+        let rows_affected = query!(
+            "
+            DELETE FROM auth.auth_tokens_archive
+            WHERE auth_tokens_archive.archived_at < $1
+            ",
+            archived_before,
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "purge_archived_tokens")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected)
+    }
+
+    /// Suspends the user, so that none of their tokens authenticate, and deletes their tokens.
+    /// Returns `false` if the user does not exist. Suspending a suspended user keeps the original
+    /// suspension time.
+    pub async fn suspend_user(&self, user_id: Id<UserMarker>) -> Result<bool> {
+        let now_utc = UtcDateTime::now();
+        let now_primitive = PrimitiveDateTime::new(now_utc.date(), now_utc.time());
+
+        let rows_affected = query!(
+            "
+            UPDATE users.users
+            SET suspended_at = coalesce(users.suspended_at, $2)
+            WHERE users.user_snowflake = $1
+            ",
+            user_id.snowflake().get().cast_signed(),
+            now_primitive,
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "suspend_user")
+        .await?
+        .rows_affected();
+        if rows_affected == 0 {
+            return Ok(false);
+        }
+
+        self.delete_user_tokens(user_id).await?;
+
+        Ok(true)
+    }
+
+    pub async fn fetch_moderated_user(
+        &self,
+        user_id: Id<UserMarker>,
+    ) -> Result<Option<ModeratedUser>> {
+        let record = self
+            .idempotent("fetch_moderated_user", || async move {
+                query_as!(
+                    ModeratedUserRecord,
+                    r#"
+                    SELECT
+                        users.user_snowflake,
+                        users.handle,
+                        users.verified_at IS NOT NULL AS "verified!",
+                        users.suspended_at,
+                        users.shadowbanned_at
+                    FROM users.users
+                    WHERE users.user_snowflake = $1
+                    "#,
+                    user_id.snowflake().get().cast_signed(),
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_moderated_user")
+                .await
+            })
+            .await?;
+
+        let user = record.map(ModeratedUser::try_from).transpose()?;
+        Ok(user)
+    }
+
+    /// Hides the posts of the user from public timelines, search, and the notifications of
+    /// others. Returns whether the user was not shadowbanned yet, so a shadowban keeps its
+    /// original time.
+    pub async fn shadowban_user(&self, user_id: Id<UserMarker>) -> Result<bool> {
+        let now_utc = UtcDateTime::now();
+        let now_primitive = PrimitiveDateTime::new(now_utc.date(), now_utc.time());
+
+        let rows_affected = query!(
+            "
+            UPDATE users.users
+            SET shadowbanned_at = $2
+            WHERE users.user_snowflake = $1 AND users.shadowbanned_at IS NULL
+            ",
+            user_id.snowflake().get().cast_signed(),
+            now_primitive,
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "shadowban_user")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Returns whether the user was shadowbanned.
+    pub async fn unshadowban_user(&self, user_id: Id<UserMarker>) -> Result<bool> {
+        let rows_affected = query!(
+            "
+            UPDATE users.users
+            SET shadowbanned_at = NULL
+            WHERE users.user_snowflake = $1 AND users.shadowbanned_at IS NOT NULL
+            ",
+            user_id.snowflake().get().cast_signed(),
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "unshadowban_user")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    pub async fn is_shadowbanned(&self, user_id: Id<UserMarker>) -> Result<bool> {
+        Ok(!self
+            .fetch_shadowbanned_user_ids(&[user_id])
+            .await?
+            .is_empty())
+    }
+
+    /// Those of `user_ids` that are shadowbanned, in no particular order.
+    pub async fn fetch_shadowbanned_user_ids(
+        &self,
+        user_ids: &[Id<UserMarker>],
+    ) -> Result<Vec<Id<UserMarker>>> {
+        let snowflakes: &[_] = &user_ids
+            .iter()
+            .map(|id| id.snowflake().get().cast_signed())
+            .collect::<Vec<_>>();
+
+        let snowflakes = self
+            .idempotent("fetch_shadowbanned_user_ids", || async move {
+                query_scalar!(
+                    "
+                    SELECT users.user_snowflake
+                    FROM users.users
+                    WHERE
+                        users.user_snowflake = ANY($1)
+                        AND users.shadowbanned_at IS NOT NULL
+                    ",
+                    snowflakes,
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_shadowbanned_user_ids")
+                .await
+            })
+            .await?;
+
+        Ok(snowflakes
+            .into_iter()
+            .map(|snowflake| snowflake.cast_unsigned().into())
+            .collect())
+    }
+
+    /// Deletes all tokens of the user, signing them out everywhere.
+    /// Returns number of affected rows
+    pub async fn delete_user_tokens(&self, user_id: Id<UserMarker>) -> Result<u64> {
+        let rows_affected = query!(
+            "
+            DELETE FROM auth.auth_tokens
+            WHERE auth_tokens.user_snowflake = $1
+            ",
+            user_id.snowflake().get().cast_signed(),
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "delete_user_tokens")
+        .await?
+        .rows_affected();
+
+        if let Some(cache) = &self.cache {
+            cache.invalidate_user_auths(user_id).await;
+        }
+
+        Ok(rows_affected)
+    }
+
+    /// Deletes the tokens admins got to impersonate the user, ending the impersonations.
+    /// Returns number of affected rows
+    pub async fn delete_impersonation_tokens(&self, user_id: Id<UserMarker>) -> Result<u64> {
+        let rows_affected = query!(
+            "
+            DELETE FROM auth.auth_tokens
+            WHERE
+                auth_tokens.user_snowflake = $1
+                AND auth_tokens.impersonator_snowflake IS NOT NULL
+            ",
+            user_id.snowflake().get().cast_signed(),
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "delete_impersonation_tokens")
+        .await?
+        .rows_affected();
+
+        if let Some(cache) = &self.cache {
+            cache.invalidate_user_auths(user_id).await;
+        }
+
+        Ok(rows_affected)
+    }
+
+    /// Stores an auth token, for example after logging in.
+    pub async fn create_auth(&self, authentication: &Authentication) -> Result<()> {
+        let created_at = PrimitiveDateTime::new(
+            authentication.created_at.date(),
+            authentication.created_at.time(),
+        );
+
+        query!(
+            "
+            INSERT INTO auth.auth_tokens (
+                token_hash, user_snowflake, tenant, created_at, expires_after_seconds,
+                impersonator_snowflake
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ",
+            &authentication.token_hash.0,
+            authentication.user.snowflake().get().cast_signed(),
+            authentication.tenant.get(),
+            created_at,
+            authentication
+                .expires_after
+                .map(|duration| duration.get().whole_seconds()),
+            authentication
+                .impersonator
+                .map(|impersonator| impersonator.snowflake().get().cast_signed()),
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "create_auth")
+        .await?;
+
+        Ok(())
+    }
+
+    /// Stores a started login, and deletes logins created before `expired_before`.
+    pub async fn create_oidc_login(
+        &self,
+        state: &OidcToken,
+        login: &PendingOidcLogin,
+        now: UtcDateTime,
+        expired_before: UtcDateTime,
+    ) -> Result<()> {
+        let now = PrimitiveDateTime::new(now.date(), now.time());
+        let expired_before = PrimitiveDateTime::new(expired_before.date(), expired_before.time());
+
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+        query!(
+            "
+            DELETE FROM auth.oidc_logins
+            WHERE oidc_logins.created_at < $1
+            ",
+            expired_before,
+        )
+        .execute(&mut *transaction)
+        .measured(&self.metrics, "create_oidc_login")
+        .await?;
+        query!(
+            "
+            INSERT INTO auth.oidc_logins (
+                state, tenant, provider, nonce, code_verifier, handle, link_user_snowflake,
+                created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ",
+            &state.0,
+            login.tenant.get(),
+            login.provider.as_str(),
+            &login.nonce.0,
+            &login.code_verifier.0,
+            login.handle.as_ref().map(UserHandle::get),
+            login
+                .link_user
+                .map(|user_id| user_id.snowflake().get().cast_signed()),
+            now,
+        )
+        .execute(&mut *transaction)
+        .measured(&self.metrics, "create_oidc_login")
+        .await?;
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    /// Deletes and returns the login, unless it was created before `expired_before`.
+    /// A login can only be taken once.
+    pub async fn take_oidc_login(
+        &self,
+        state: &OidcToken,
+        expired_before: UtcDateTime,
+    ) -> Result<Option<PendingOidcLogin>> {
+        let expired_before = PrimitiveDateTime::new(expired_before.date(), expired_before.time());
+
+        let record = query_as!(
+            PendingOidcLoginRecord,
+            "
+            DELETE FROM auth.oidc_logins
+            WHERE oidc_logins.state = $1
+            RETURNING
+                oidc_logins.tenant,
+                oidc_logins.provider,
+                oidc_logins.nonce,
+                oidc_logins.code_verifier,
+                oidc_logins.handle,
+                oidc_logins.link_user_snowflake,
+                oidc_logins.created_at
+            ",
+            &state.0,
+        )
+        .fetch_optional(&mut *self.writer().await?)
+        .measured(&self.metrics, "take_oidc_login")
+        .await?;
+
+        let login = record
+            .filter(|record| record.created_at >= expired_before)
+            .map(PendingOidcLogin::try_from)
+            .transpose()?;
+        Ok(login)
+    }
+
+    /// The user the identity is linked to.
+    pub async fn fetch_oidc_identity_user(
+        &self,
+        tenant: &TenantId,
+        provider: OidcProvider,
+        subject: &str,
+    ) -> Result<Option<User>> {
+        let record = self
+            .idempotent("fetch_oidc_identity_user", || async move {
+                query_as!(
+                    UserRecord,
+                    r#"
+                    SELECT
+                        users.user_snowflake,
+                        users.handle,
+                        users.verified_at IS NOT NULL AS "verified!"
+                    FROM
+                        auth.oidc_identities
+                        JOIN users.users ON users.user_snowflake = oidc_identities.user_snowflake
+                    WHERE
+                        oidc_identities.tenant = $1
+                        AND oidc_identities.provider = $2
+                        AND oidc_identities.subject = $3
+                    "#,
+                    tenant.get(),
+                    provider.as_str(),
+                    subject,
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_oidc_identity_user")
+                .await
+            })
+            .await?;
+
+        let user = record.map(User::try_from).transpose()?;
+        Ok(user)
+    }
+
+    /// Returns `false` if the identity or another identity at the provider is already linked.
+    pub async fn link_oidc_identity(
+        &self,
+        tenant: &TenantId,
+        provider: OidcProvider,
+        subject: &str,
+        user_id: Id<UserMarker>,
+        linked_at: UtcDateTime,
+    ) -> Result<bool> {
+        self.insert_oidc_identity(
+            &mut *self.writer().await?,
+            tenant,
+            provider,
+            subject,
+            user_id,
+            linked_at,
+        )
+        .await
+    }
+
+    async fn insert_oidc_identity(
+        &self,
+        connection: &mut PgConnection,
+        tenant: &TenantId,
+        provider: OidcProvider,
+        subject: &str,
+        user_id: Id<UserMarker>,
+        linked_at: UtcDateTime,
+    ) -> Result<bool> {
+        let linked_at = PrimitiveDateTime::new(linked_at.date(), linked_at.time());
+
+        let rows_affected = query!(
+            "
+            INSERT INTO auth.oidc_identities (tenant, provider, subject, user_snowflake, linked_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT DO NOTHING
+            ",
+            tenant.get(),
+            provider.as_str(),
+            subject,
+            user_id.snowflake().get().cast_signed(),
+            linked_at,
+        )
+        .execute(connection)
+        .measured(&self.metrics, "insert_oidc_identity")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Creates a user with the identity linked.
+    /// Returns `None` if the handle is taken in the tenant, or the identity was linked meanwhile.
+    pub async fn create_oidc_user(
+        &self,
+        tenant: &TenantId,
+        handle: &UserHandle,
+        provider: OidcProvider,
+        subject: &str,
+        now: UtcDateTime,
+    ) -> Result<Option<Id<UserMarker>>> {
+        let user_snowflake = self.snowflake_generator.generate()?;
+
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+        let Some(returned_snowflake) = query_scalar!(
+            "
+            INSERT INTO users.users (user_snowflake, handle, tenant)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (tenant, handle) DO NOTHING
+            RETURNING users.user_snowflake
+            ",
+            user_snowflake.get().cast_signed(),
+            handle.get(),
+            tenant.get(),
+        )
+        .fetch_optional(&mut *transaction)
+        .measured(&self.metrics, "create_oidc_user")
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let id = returned_snowflake.cast_unsigned().into();
+        if !self
+            .insert_oidc_identity(&mut transaction, tenant, provider, subject, id, now)
+            .await?
+        {
+            return Ok(None);
+        }
+        self.enqueue_search_indexing(&mut transaction, SearchDocument::User(id))
+            .await?;
+        transaction.commit().await?;
+
+        Ok(Some(id))
+    }
+
+    pub async fn fetch_oidc_identities(
+        &self,
+        user_id: Id<UserMarker>,
+    ) -> Result<Vec<OidcIdentity>> {
+        let records = self
+            .idempotent("fetch_oidc_identities", || async move {
+                query_as!(
+                    OidcIdentityRecord,
+                    "
+                    SELECT
+                        oidc_identities.provider,
+                        oidc_identities.linked_at
+                    FROM
+                        auth.oidc_identities
+                    WHERE
+                        oidc_identities.user_snowflake = $1
+                    ORDER BY
+                        oidc_identities.linked_at
+                    ",
+                    user_id.snowflake().get().cast_signed(),
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_oidc_identities")
+                .await
+            })
+            .await?;
+
+        let identities = records
+            .into_iter()
+            .map(OidcIdentity::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(identities)
+    }
+
+    /// Returns `false` if the user has no identity at the provider.
+    pub async fn unlink_oidc_identity(
+        &self,
+        user_id: Id<UserMarker>,
+        provider: OidcProvider,
+    ) -> Result<bool> {
+        let rows_affected = query!(
+            "
+            DELETE FROM auth.oidc_identities
+            WHERE oidc_identities.user_snowflake = $1 AND oidc_identities.provider = $2
+            ",
+            user_id.snowflake().get().cast_signed(),
+            provider.as_str(),
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "unlink_oidc_identity")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    pub async fn create_report(&self, report: &CreateReport) -> Result<Id<ReportMarker>> {
+        let report_snowflake = self.snowflake_generator.generate()?;
+
+        let returned_snowflake = query_scalar!(
+            "
+            INSERT INTO moderation.reports (
+                report_snowflake,
+                reporter_snowflake,
+                target_user_snowflake,
+                target_post_snowflake,
+                category,
+                comment
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING reports.report_snowflake
+            ",
+            report_snowflake.get().cast_signed(),
+            report
+                .reporter
+                .map(|reporter| reporter.snowflake().get().cast_signed()),
+            report.target_user.snowflake().get().cast_signed(),
+            report
+                .target_post
+                .map(|post| post.snowflake().get().cast_signed()),
+            report.category.as_str(),
+            report.comment.get(),
+        )
+        .fetch_one(&mut *self.writer().await?)
+        .measured_one(&self.metrics, "create_report")
+        .await?;
+
+        Ok(returned_snowflake.cast_unsigned().into())
+    }
+
+    /// Only finds reports about users of the tenant.
+    pub async fn fetch_report(
+        &self,
+        tenant: &TenantId,
+        report_id: Id<ReportMarker>,
+    ) -> Result<Option<Report>> {
+        let record = self
+            .idempotent("fetch_report", || async move {
+                query_as!(
+                    ReportRecord,
+                    "
+                    SELECT
+                        reports.report_snowflake,
+                        reports.reporter_snowflake,
+                        reports.target_user_snowflake,
+                        reports.target_post_snowflake,
+                        reports.category,
+                        reports.comment,
+                        reports.assignee_snowflake,
+                        reports.resolved_at,
+                        reports.resolution
+                    FROM
+                        moderation.reports
+                        JOIN users.users ON users.user_snowflake = reports.target_user_snowflake
+                    WHERE
+                        reports.report_snowflake = $1 AND users.tenant = $2
+                    ",
+                    report_id.snowflake().get().cast_signed(),
+                    tenant.get(),
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_report")
+                .await
+            })
+            .await?;
+
+        let report = record.map(Report::try_from).transpose()?;
+        Ok(report)
+    }
+
+    /// Whether an unresolved report about the user was created automatically.
+    pub async fn has_open_automatic_report(&self, user_id: Id<UserMarker>) -> Result<bool> {
+        let exists = self
+            .idempotent("has_open_automatic_report", || async move {
+                query_scalar!(
+                    r#"
+                    SELECT EXISTS(
+                        SELECT FROM moderation.reports
+                        WHERE
+                            reports.target_user_snowflake = $1
+                            AND reports.reporter_snowflake IS NULL
+                            AND reports.resolved_at IS NULL
+                    ) as "exists!"
+                    "#,
+                    user_id.snowflake().get().cast_signed(),
+                )
+                .fetch_one(&mut *self.reader().await?)
+                .measured_one(&self.metrics, "has_open_automatic_report")
+                .await
+            })
+            .await?;
+
+        Ok(exists)
+    }
+
+    /// Counts the posts of the user since `since`, including deleted ones.
+    pub async fn count_posts_since(
+        &self,
+        user_id: Id<UserMarker>,
+        since: UtcDateTime,
+    ) -> Result<u64> {
+        // The epoch is long past, so this is never before it.
+        let since_snowflake = StellwerkSnowflake::min_for_time(since)
+            .unwrap_or_default()
+            .get()
+            .cast_signed();
+
+        let count = self
+            .idempotent("count_posts_since", || async move {
+                query_scalar!(
+                    r#"
+                    SELECT count(*) as "count!"
+                    FROM posts.posts
+                    WHERE posts.user_snowflake = $1 AND posts.post_snowflake >= $2
+                    "#,
+                    user_id.snowflake().get().cast_signed(),
+                    since_snowflake,
+                )
+                .fetch_one(&mut *self.reader().await?)
+                .measured_one(&self.metrics, "count_posts_since")
+                .await
+            })
+            .await?;
+
+        Ok(count.cast_unsigned())
+    }
+
+    /// Counts the users other than `author` who posted exactly `content` since `since`,
+    /// including deleted posts.
+    pub async fn count_duplicate_posters(
+        &self,
+        author: Id<UserMarker>,
+        content: &PostContent,
+        since: UtcDateTime,
+    ) -> Result<u64> {
+        // The epoch is long past, so this is never before it.
+        let since_snowflake = StellwerkSnowflake::min_for_time(since)
+            .unwrap_or_default()
+            .get()
+            .cast_signed();
+
+        let count = self
+            .idempotent("count_duplicate_posters", || async move {
+                query_scalar!(
+                    r#"
+                    SELECT count(DISTINCT posts.user_snowflake) as "count!"
+                    FROM posts.posts
+                    WHERE
+                        posts.post_snowflake >= $3
+                        AND posts.content = $2
+                        AND posts.user_snowflake != $1
+                    "#,
+                    author.snowflake().get().cast_signed(),
+                    content.get(),
+                    since_snowflake,
+                )
+                .fetch_one(&mut *self.reader().await?)
+                .measured_one(&self.metrics, "count_duplicate_posters")
+                .await
+            })
+            .await?;
+
+        Ok(count.cast_unsigned())
+    }
+
+    /// Counts the users the user followed since `since`, and still follows.
+    pub async fn count_follows_since(
+        &self,
+        user_id: Id<UserMarker>,
+        since: UtcDateTime,
+    ) -> Result<u64> {
+        let since_primitive = PrimitiveDateTime::new(since.date(), since.time());
+
+        let count = self
+            .idempotent("count_follows_since", || async move {
+                query_scalar!(
+                    r#"
+                    SELECT count(*) as "count!"
+                    FROM users.follows
+                    WHERE follows.follower_snowflake = $1 AND follows.followed_at >= $2
+                    "#,
+                    user_id.snowflake().get().cast_signed(),
+                    since_primitive,
+                )
+                .fetch_one(&mut *self.reader().await?)
+                .measured_one(&self.metrics, "count_follows_since")
+                .await
+            })
+            .await?;
+
+        Ok(count.cast_unsigned())
+    }
+
+    /// Returns all unresolved reports about users of the tenant, oldest first.
+    pub async fn fetch_open_reports(&self, tenant: &TenantId) -> Result<Vec<Report>> {
+        let records = self
+            .idempotent("fetch_open_reports", || async move {
+                query_as!(
+                    ReportRecord,
+                    "
+                    SELECT
+                        reports.report_snowflake,
+                        reports.reporter_snowflake,
+                        reports.target_user_snowflake,
+                        reports.target_post_snowflake,
+                        reports.category,
+                        reports.comment,
+                        reports.assignee_snowflake,
+                        reports.resolved_at,
+                        reports.resolution
+                    FROM
+                        moderation.reports
+                        JOIN users.users ON users.user_snowflake = reports.target_user_snowflake
+                    WHERE
+                        reports.resolved_at IS NULL AND users.tenant = $1
+                    ORDER BY
+                        reports.report_snowflake
+                    ",
+                    tenant.get(),
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_open_reports")
+                .await
+            })
+            .await?;
+
+        let reports = records
+            .into_iter()
+            .map(Report::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(reports)
+    }
+
+    /// Returns `false` if the report does not exist.
+    pub async fn assign_report(
+        &self,
+        report_id: Id<ReportMarker>,
+        assignee: Option<Id<UserMarker>>,
+    ) -> Result<bool> {
+        let rows_affected = query!(
+            "
+            UPDATE moderation.reports
+            SET assignee_snowflake = $2
+            WHERE reports.report_snowflake = $1
+            ",
+            report_id.snowflake().get().cast_signed(),
+            assignee.map(|assignee| assignee.snowflake().get().cast_signed()),
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "assign_report")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected != 0)
+    }
+
+    /// Assigns the report to `moderator`, unless it is assigned to another moderator.
+    /// Returns `false` if the report does not exist or is assigned to another moderator.
+    pub async fn claim_report(
+        &self,
+        report_id: Id<ReportMarker>,
+        moderator: Id<UserMarker>,
+    ) -> Result<bool> {
+        let rows_affected = query!(
+            "
+            UPDATE moderation.reports
+            SET assignee_snowflake = $2
+            WHERE
+                reports.report_snowflake = $1
+                AND (reports.assignee_snowflake IS NULL OR reports.assignee_snowflake = $2)
+            ",
+            report_id.snowflake().get().cast_signed(),
+            moderator.snowflake().get().cast_signed(),
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "claim_report")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected != 0)
+    }
+
+    /// Returns `false` if the report does not exist or is already resolved,
+    /// in which case the original resolution is kept.
+    pub async fn resolve_report(
+        &self,
+        report_id: Id<ReportMarker>,
+        resolution: ReportAction,
+    ) -> Result<bool> {
+        let now_utc = UtcDateTime::now();
+        let now_primitive = PrimitiveDateTime::new(now_utc.date(), now_utc.time());
+
+        let rows_affected = query!(
+            "
+            UPDATE moderation.reports
+            SET resolved_at = $2, resolution = $3
+            WHERE reports.report_snowflake = $1 AND reports.resolved_at IS NULL
+            ",
+            report_id.snowflake().get().cast_signed(),
+            now_primitive,
+            resolution.as_str(),
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "resolve_report")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected != 0)
+    }
+
+    pub async fn create_report_note(
+        &self,
+        note: &CreateReportNote,
+    ) -> Result<Id<ReportNoteMarker>> {
+        let note_snowflake = self.snowflake_generator.generate()?;
+
+        let returned_snowflake = query_scalar!(
+            "
+            INSERT INTO moderation.report_notes
+                (note_snowflake, report_snowflake, author_snowflake, content)
+            VALUES ($1, $2, $3, $4)
+            RETURNING report_notes.note_snowflake
+            ",
+            note_snowflake.get().cast_signed(),
+            note.report.snowflake().get().cast_signed(),
+            note.author.snowflake().get().cast_signed(),
+            note.content.get(),
+        )
+        .fetch_one(&mut *self.writer().await?)
+        .measured_one(&self.metrics, "create_report_note")
+        .await?;
+
+        Ok(returned_snowflake.cast_unsigned().into())
+    }
+
+    /// Returns the notes on the report, oldest first.
+    pub async fn fetch_report_notes(&self, report_id: Id<ReportMarker>) -> Result<Vec<ReportNote>> {
+        let records = self
+            .idempotent("fetch_report_notes", || async move {
+                query_as!(
+                    ReportNoteRecord,
+                    "
+                    SELECT
+                        report_notes.note_snowflake,
+                        report_notes.report_snowflake,
+                        report_notes.author_snowflake,
+                        report_notes.content
+                    FROM
+                        moderation.report_notes
+                    WHERE
+                        report_notes.report_snowflake = $1
+                    ORDER BY
+                        report_notes.note_snowflake
+                    ",
+                    report_id.snowflake().get().cast_signed(),
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_report_notes")
+                .await
+            })
+            .await?;
+
+        let notes = records
+            .into_iter()
+            .map(ReportNote::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(notes)
+    }
+
+    pub async fn create_moderation_log_entry(
+        &self,
+        entry: &CreateModerationLogEntry,
+    ) -> Result<Id<ModerationLogMarker>> {
+        let entry_snowflake = self.snowflake_generator.generate()?;
+
+        let returned_snowflake = query_scalar!(
+            "
+            INSERT INTO moderation.audit_log (
+                entry_snowflake,
+                moderator_snowflake,
+                action,
+                report_snowflake,
+                target_user_snowflake,
+                target_post_snowflake
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING audit_log.entry_snowflake
+            ",
+            entry_snowflake.get().cast_signed(),
+            entry.moderator.snowflake().get().cast_signed(),
+            entry.action.as_str(),
+            entry
+                .report
+                .map(|report| report.snowflake().get().cast_signed()),
+            entry
+                .target_user
+                .map(|user| user.snowflake().get().cast_signed()),
+            entry
+                .target_post
+                .map(|post| post.snowflake().get().cast_signed()),
+        )
+        .fetch_one(&mut *self.writer().await?)
+        .measured_one(&self.metrics, "create_moderation_log_entry")
+        .await?;
+
+        Ok(returned_snowflake.cast_unsigned().into())
+    }
+
+    /// Returns entries of the moderation log by moderators of the tenant, newest first.
+    /// `max_id` and `since_id` are exclusive bounds.
+    pub async fn fetch_moderation_log(
+        &self,
+        tenant: &TenantId,
+        max_id: Option<Id<ModerationLogMarker>>,
+        since_id: Option<Id<ModerationLogMarker>>,
+        limit: u32,
+    ) -> Result<Vec<ModerationLogEntry>> {
+        let records = self
+            .idempotent("fetch_moderation_log", || async move {
+                query_as!(
+                    ModerationLogRecord,
+                    "
+                    SELECT
+                        audit_log.entry_snowflake,
+                        audit_log.moderator_snowflake,
+                        audit_log.action,
+                        audit_log.report_snowflake,
+                        audit_log.target_user_snowflake,
+                        audit_log.target_post_snowflake
+                    FROM
+                        moderation.audit_log
+                        JOIN users.users ON users.user_snowflake = audit_log.moderator_snowflake
+                    WHERE
+                        users.tenant = $1
+                        AND ($2::bigint IS NULL OR audit_log.entry_snowflake < $2)
+                        AND ($3::bigint IS NULL OR audit_log.entry_snowflake > $3)
+                    ORDER BY
+                        audit_log.entry_snowflake DESC
+                    LIMIT $4
+                    ",
+                    tenant.get(),
+                    max_id.map(|id| id.snowflake().get().cast_signed()),
+                    since_id.map(|id| id.snowflake().get().cast_signed()),
+                    i64::from(limit),
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_moderation_log")
+                .await
+            })
+            .await?;
+
+        let entries = records
+            .into_iter()
+            .map(ModerationLogEntry::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(entries)
+    }
+
+    /// Returns reports about users of the tenant, newest first, optionally only resolved or
+    /// unresolved ones. `max_id` and `since_id` are exclusive bounds.
+    pub async fn fetch_reports(
+        &self,
+        tenant: &TenantId,
+        resolved: Option<bool>,
+        max_id: Option<Id<ReportMarker>>,
+        since_id: Option<Id<ReportMarker>>,
+        limit: u32,
+    ) -> Result<Vec<Report>> {
+        let records = self
+            .idempotent("fetch_reports", || async move {
+                query_as!(
+                    ReportRecord,
+                    "
+                    SELECT
+                        reports.report_snowflake,
+                        reports.reporter_snowflake,
+                        reports.target_user_snowflake,
+                        reports.target_post_snowflake,
+                        reports.category,
+                        reports.comment,
+                        reports.assignee_snowflake,
+                        reports.resolved_at,
+                        reports.resolution
+                    FROM
+                        moderation.reports
+                        JOIN users.users ON users.user_snowflake = reports.target_user_snowflake
+                    WHERE
+                        users.tenant = $1
+                        AND ($2::boolean IS NULL OR (reports.resolved_at IS NOT NULL) = $2)
+                        AND ($3::bigint IS NULL OR reports.report_snowflake < $3)
+                        AND ($4::bigint IS NULL OR reports.report_snowflake > $4)
+                    ORDER BY
+                        reports.report_snowflake DESC
+                    LIMIT $5
+                    ",
+                    tenant.get(),
+                    resolved,
+                    max_id.map(|id| id.snowflake().get().cast_signed()),
+                    since_id.map(|id| id.snowflake().get().cast_signed()),
+                    i64::from(limit),
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_reports")
+                .await
+            })
+            .await?;
+
+        let reports = records
+            .into_iter()
+            .map(Report::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(reports)
+    }
+
+    /// Returns `false` if the report does not exist.
+    pub async fn delete_report(&self, report_id: Id<ReportMarker>) -> Result<bool> {
+        let rows_affected = query!(
+            "
+            DELETE FROM moderation.reports
+            WHERE reports.report_snowflake = $1
+            ",
+            report_id.snowflake().get().cast_signed(),
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "delete_report")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected != 0)
+    }
+
+    /// Returns all filters of the user, including expired ones.
+    pub async fn fetch_filters(&self, user_id: Id<UserMarker>) -> Result<Vec<Filter>> {
+        let records = self
+            .idempotent("fetch_filters", || async move {
+                query_as!(
+                    FilterRecord,
+                    "
+                    SELECT
+                        filters.filter_snowflake,
+                        filters.user_snowflake,
+                        filters.phrase,
+                        filters.regex,
+                        filters.contexts,
+                        filters.action,
+                        filters.expires_at,
+                        filters.version
+                    FROM
+                        users.filters
+                    WHERE
+                        filters.user_snowflake = $1
+                    ORDER BY
+                        filters.filter_snowflake
+                    ",
+                    user_id.snowflake().get().cast_signed(),
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_filters")
+                .await
+            })
+            .await?;
+
+        let filters = records
+            .into_iter()
+            .map(Filter::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(filters)
+    }
+
+    pub async fn fetch_filter(&self, filter_id: Id<FilterMarker>) -> Result<Option<Filter>> {
+        let record = self
+            .idempotent("fetch_filter", || async move {
+                query_as!(
+                    FilterRecord,
+                    "
+                    SELECT
+                        filters.filter_snowflake,
+                        filters.user_snowflake,
+                        filters.phrase,
+                        filters.regex,
+                        filters.contexts,
+                        filters.action,
+                        filters.expires_at,
+                        filters.version
+                    FROM
+                        users.filters
+                    WHERE
+                        filters.filter_snowflake = $1
+                    ",
+                    filter_id.snowflake().get().cast_signed(),
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_filter")
+                .await
+            })
+            .await?;
+
+        let filter = record.map(Filter::try_from).transpose()?;
+        Ok(filter)
+    }
+
+    pub async fn create_filter(
+        &self,
+        user_id: Id<UserMarker>,
+        settings: &FilterSettings,
+    ) -> Result<Id<FilterMarker>> {
+        let filter_snowflake = self.snowflake_generator.generate()?;
+        let contexts: Vec<_> = settings
+            .contexts
+            .iter()
+            .map(|context| context.as_str())
+            .collect();
+
+        let returned_snowflake = query_scalar!(
+            "
+            INSERT INTO users.filters (
+                filter_snowflake,
+                user_snowflake,
+                phrase,
+                regex,
+                contexts,
+                action,
+                expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING filters.filter_snowflake
+            ",
+            filter_snowflake.get().cast_signed(),
+            user_id.snowflake().get().cast_signed(),
+            settings.phrase,
+            settings.regex,
+            &contexts as &[&str],
+            settings.action.as_str(),
+            settings
+                .expires_at
+                .map(|expires_at| PrimitiveDateTime::new(expires_at.date(), expires_at.time())),
+        )
+        .fetch_one(&mut *self.writer().await?)
+        .measured_one(&self.metrics, "create_filter")
+        .await?;
+
+        Ok(returned_snowflake.cast_unsigned().into())
+    }
+
+    /// Returns the new version, or `None` if the user has no filter with this id.
+    ///
+    /// If `expected_version` is given, the filter is only updated if it is at that version,
+    /// and [`DbError::Conflict`] is returned otherwise.
+    pub async fn update_filter(
+        &self,
+        user_id: Id<UserMarker>,
+        filter_id: Id<FilterMarker>,
+        settings: &FilterSettings,
+        expected_version: Option<u64>,
+    ) -> Result<Option<u64>> {
+        let contexts: Vec<_> = settings
+            .contexts
+            .iter()
+            .map(|context| context.as_str())
+            .collect();
+
+        let version = query_scalar!(
+            "
+            UPDATE users.filters
+            SET
+                phrase = $3,
+                regex = $4,
+                contexts = $5,
+                action = $6,
+                expires_at = $7,
+                version = filters.version + 1
+            WHERE
+                filters.filter_snowflake = $1
+                AND filters.user_snowflake = $2
+                AND ($8::bigint IS NULL OR filters.version = $8)
+            RETURNING filters.version
+            ",
+            filter_id.snowflake().get().cast_signed(),
+            user_id.snowflake().get().cast_signed(),
+            settings.phrase,
+            settings.regex,
+            &contexts as &[&str],
+            settings.action.as_str(),
+            settings
+                .expires_at
+                .map(|expires_at| PrimitiveDateTime::new(expires_at.date(), expires_at.time())),
+            expected_version.map(u64::cast_signed),
+        )
+        .fetch_optional(&mut *self.writer().await?)
+        .measured(&self.metrics, "update_filter")
+        .await?;
+        if let Some(version) = version {
+            return Ok(Some(version.cast_unsigned()));
+        }
+
+        // Either the filter does not exist or it is at another version.
+        let current_version = query_scalar!(
+            "
+            SELECT filters.version
+            FROM users.filters
+            WHERE
+                filters.filter_snowflake = $1
+                AND filters.user_snowflake = $2
+            ",
+            filter_id.snowflake().get().cast_signed(),
+            user_id.snowflake().get().cast_signed(),
+        )
+        .fetch_optional(&mut *self.writer().await?)
+        .measured(&self.metrics, "update_filter.version")
+        .await?;
+
+        match current_version {
+            Some(current_version) => Err(DbError::Conflict {
+                current_version: current_version.cast_unsigned(),
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns `false` if the user has no filter with this id.
+    pub async fn delete_filter(
+        &self,
+        user_id: Id<UserMarker>,
+        filter_id: Id<FilterMarker>,
+    ) -> Result<bool> {
+        let rows_affected = query!(
+            "
+            DELETE FROM users.filters
+            WHERE
+                filters.filter_snowflake = $1
+                AND filters.user_snowflake = $2
+            ",
+            filter_id.snowflake().get().cast_signed(),
+            user_id.snowflake().get().cast_signed(),
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "delete_filter")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected != 0)
+    }
+
+    pub async fn create_notification(
+        &self,
+        notification: &CreateNotification,
+    ) -> Result<Id<NotificationMarker>> {
+        let notification_snowflake = self.snowflake_generator.generate()?;
+
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+
+        let returned_snowflake = query_scalar!(
+            "
+            INSERT INTO users.notifications
+                (notification_snowflake, user_snowflake, kind, actor_snowflake, post_snowflake)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING notifications.notification_snowflake
+            ",
+            notification_snowflake.get().cast_signed(),
+            notification.user.snowflake().get().cast_signed(),
+            notification.kind.as_str(),
+            notification.actor.snowflake().get().cast_signed(),
+            notification
+                .post
+                .map(|post| post.snowflake().get().cast_signed()),
+        )
+        .fetch_one(&mut *transaction)
+        .measured_one(&self.metrics, "create_notification")
+        .await?;
+
+        let id = returned_snowflake.cast_unsigned().into();
+        events::notify(
+            NotificationCreated {
+                user: notification.user,
+                notification: id,
+            },
+            &mut *transaction,
+        )
+        .await?;
+        // The notification is hidden from the user, so they should not learn about it otherwise.
+        if notification.kind == NotificationKind::Mention
+            && let Some(post) = notification.post
+            && !self.is_shadowbanned(notification.actor).await?
+        {
+            // Held back until do not disturb ends, as it is set now.
+            let now = UtcDateTime::now();
+            let run_at = self
+                .select_do_not_disturb(&mut transaction, notification.user)
+                .await?
+                .active_until(now)
+                .unwrap_or(now);
+            self.enqueue_webhook_deliveries(
+                &mut transaction,
+                notification.user,
+                WebhookPayload::Mention {
+                    post,
+                    actor: notification.actor,
+                },
+                run_at,
+            )
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(id)
+    }
+
+    pub async fn fetch_notification(
+        &self,
+        notification_id: Id<NotificationMarker>,
+    ) -> Result<Option<Notification>> {
+        let record = self
+            .idempotent("fetch_notification", || async move {
+                query_as!(
+                    NotificationRecord,
+                    r#"
+                    SELECT
+                        notifications.notification_snowflake,
+                        notifications.kind,
+                        notifications.post_snowflake,
+                        users.user_snowflake,
+                        users.handle,
+                        users.verified_at IS NOT NULL AS "verified!"
+                    FROM
+                        users.notifications
+                        JOIN users.users ON users.user_snowflake = notifications.actor_snowflake
+                    WHERE
+                        notifications.notification_snowflake = $1
+                        AND (
+                            users.shadowbanned_at IS NULL
+                            OR notifications.actor_snowflake = notifications.user_snowflake
+                        )
+                        AND NOT EXISTS(
+                            SELECT FROM posts.posts
+                            WHERE
+                                posts.post_snowflake = notifications.post_snowflake
+                                AND posts.deleted_at IS NOT NULL
+                        )
+                    "#,
+                    notification_id.snowflake().get().cast_signed(),
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_notification")
+                .await
+            })
+            .await?;
+
+        let notification = record.map(Notification::try_from).transpose()?;
+        Ok(notification)
+    }
+
+    /// Returns the newest notifications of `user_id`, newest first, except those caused by other,
+    /// shadowbanned users.
+    /// `max_id` and `since_id` are exclusive bounds.
+    pub async fn fetch_notifications(
+        &self,
+        user_id: Id<UserMarker>,
+        max_id: Option<Id<NotificationMarker>>,
+        since_id: Option<Id<NotificationMarker>>,
+        limit: u32,
+    ) -> Result<Vec<Notification>> {
+        let records = self
+            .idempotent("fetch_notifications", || async move {
+                query_as!(
+                    NotificationRecord,
+                    r#"
+                    SELECT
+                        notifications.notification_snowflake,
+                        notifications.kind,
+                        notifications.post_snowflake,
+                        users.user_snowflake,
+                        users.handle,
+                        users.verified_at IS NOT NULL AS "verified!"
+                    FROM
+                        users.notifications
+                        JOIN users.users ON users.user_snowflake = notifications.actor_snowflake
+                    WHERE
+                        notifications.user_snowflake = $1
+                        AND (
+                            users.shadowbanned_at IS NULL
+                            OR notifications.actor_snowflake = notifications.user_snowflake
+                        )
+                        AND NOT EXISTS(
+                            SELECT FROM posts.posts
+                            WHERE
+                                posts.post_snowflake = notifications.post_snowflake
+                                AND posts.deleted_at IS NOT NULL
+                        )
+                        AND ($2::bigint IS NULL OR notifications.notification_snowflake < $2)
+                        AND ($3::bigint IS NULL OR notifications.notification_snowflake > $3)
+                    ORDER BY
+                        notifications.notification_snowflake DESC
+                    LIMIT $4
+                    "#,
+                    user_id.snowflake().get().cast_signed(),
+                    max_id.map(|id| id.snowflake().get().cast_signed()),
+                    since_id.map(|id| id.snowflake().get().cast_signed()),
+                    i64::from(limit),
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_notifications")
+                .await
+            })
+            .await?;
+
+        let notifications = records
+            .into_iter()
+            .map(Notification::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(notifications)
+    }
+
+    /// Returns the number of notifications newer than the user's read marker.
+    pub async fn fetch_unread_notification_count(&self, user_id: Id<UserMarker>) -> Result<u64> {
+        let count = self
+            .idempotent("fetch_unread_notification_count", || async move {
+                query_scalar!(
+                    r#"
+                    SELECT count(1) as "count!"
+                    FROM
+                        users.notifications
+                        JOIN users.users ON users.user_snowflake = notifications.user_snowflake
+                        JOIN users.users AS actors
+                            ON actors.user_snowflake = notifications.actor_snowflake
+                    WHERE
+                        notifications.user_snowflake = $1
+                        AND (
+                            actors.shadowbanned_at IS NULL
+                            OR notifications.actor_snowflake = notifications.user_snowflake
+                        )
+                        AND (
+                            users.last_read_notification_snowflake IS NULL
+                            OR notifications.notification_snowflake > users.last_read_notification_snowflake
+                        )
+                        AND NOT EXISTS(
+                            SELECT FROM posts.posts
+                            WHERE
+                                posts.post_snowflake = notifications.post_snowflake
+                                AND posts.deleted_at IS NOT NULL
+                        )
+                    "#,
+                    user_id.snowflake().get().cast_signed(),
+                )
+                .fetch_one(&mut *self.reader().await?)
+                .measured_one(&self.metrics, "fetch_unread_notification_count")
+                .await
+            })
+            .await?;
+
+        Ok(count.cast_unsigned())
+    }
+
+    /// Marks notifications up to and including `max_id` as read, or all of them if `max_id` is `None`.
+    /// The read marker never moves backwards.
+    pub async fn mark_notifications_read(
+        &self,
+        user_id: Id<UserMarker>,
+        max_id: Option<Id<NotificationMarker>>,
+    ) -> Result<()> {
+        query!(
+            "
+            UPDATE users.users
+            SET last_read_notification_snowflake = GREATEST(
+                users.last_read_notification_snowflake,
+                COALESCE(
+                    $2,
+                    (
+                        SELECT max(notifications.notification_snowflake)
+                        FROM users.notifications
+                        WHERE notifications.user_snowflake = $1
+                    )
+                )
+            )
+            WHERE users.user_snowflake = $1
+            ",
+            user_id.snowflake().get().cast_signed(),
+            max_id.map(|id| id.snowflake().get().cast_signed()),
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "mark_notifications_read")
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn create_announcement(
+        &self,
+        announcement: &CreateAnnouncement,
+    ) -> Result<Id<AnnouncementMarker>> {
+        let announcement_snowflake = self.snowflake_generator.generate()?;
+
+        let returned_snowflake = query_scalar!(
+            "
+            INSERT INTO instance.announcements
+                (announcement_snowflake, title, body, expires_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING announcements.announcement_snowflake
+            ",
+            announcement_snowflake.get().cast_signed(),
+            announcement.title.get(),
+            announcement.body.get(),
+            announcement
+                .expires_at
+                .map(|expires_at| PrimitiveDateTime::new(expires_at.date(), expires_at.time())),
+        )
+        .fetch_one(&mut *self.writer().await?)
+        .measured_one(&self.metrics, "create_announcement")
+        .await?;
+
+        Ok(returned_snowflake.cast_unsigned().into())
+    }
+
+    /// Returns all announcements, including expired ones, newest first.
+    /// `max_id` and `since_id` are exclusive bounds.
+    pub async fn fetch_announcements(
+        &self,
+        max_id: Option<Id<AnnouncementMarker>>,
+        since_id: Option<Id<AnnouncementMarker>>,
+        limit: u32,
+    ) -> Result<Vec<Announcement>> {
+        let records = self
+            .idempotent("fetch_announcements", || async move {
+                query_as!(
+                    AnnouncementRecord,
+                    "
+                    SELECT
+                        announcements.announcement_snowflake,
+                        announcements.title,
+                        announcements.body,
+                        announcements.expires_at
+                    FROM
+                        instance.announcements
+                    WHERE
+                        ($1::bigint IS NULL OR announcements.announcement_snowflake < $1)
+                        AND ($2::bigint IS NULL OR announcements.announcement_snowflake > $2)
+                    ORDER BY
+                        announcements.announcement_snowflake DESC
+                    LIMIT $3
+                    ",
+                    max_id.map(|id| id.snowflake().get().cast_signed()),
+                    since_id.map(|id| id.snowflake().get().cast_signed()),
+                    i64::from(limit),
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_announcements")
+                .await
+            })
+            .await?;
+
+        let announcements = records
+            .into_iter()
+            .map(Announcement::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(announcements)
+    }
+
+    /// Returns the announcements that have not expired with the state of the user, newest first.
+    /// Dismissed announcements are only included if `include_dismissed` is set.
+    pub async fn fetch_user_announcements(
+        &self,
+        user_id: Id<UserMarker>,
+        include_dismissed: bool,
+    ) -> Result<Vec<UserAnnouncement>> {
+        let now_utc = UtcDateTime::now();
+        let now_primitive = PrimitiveDateTime::new(now_utc.date(), now_utc.time());
+
+        let records = self
+            .idempotent("fetch_user_announcements", || async move {
+                query_as!(
+                    UserAnnouncementRecord,
+                    r#"
+                    SELECT
+                        announcements.announcement_snowflake,
+                        announcements.title,
+                        announcements.body,
+                        announcements.expires_at,
+                        announcement_states.user_snowflake IS NOT NULL AS "read!",
+                        COALESCE(announcement_states.dismissed, false) AS "dismissed!"
+                    FROM
+                        instance.announcements
+                        LEFT JOIN instance.announcement_states
+                            ON announcement_states.announcement_snowflake
+                                = announcements.announcement_snowflake
+                            AND announcement_states.user_snowflake = $1
+                    WHERE
+                        (announcements.expires_at IS NULL OR announcements.expires_at > $2)
+                        AND ($3 OR announcement_states.dismissed IS NOT TRUE)
+                    ORDER BY
+                        announcements.announcement_snowflake DESC
+                    "#,
+                    user_id.snowflake().get().cast_signed(),
+                    now_primitive,
+                    include_dismissed,
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_user_announcements")
+                .await
+            })
+            .await?;
+
+        let announcements = records
+            .into_iter()
+            .map(UserAnnouncement::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(announcements)
+    }
+
+    /// Marks the announcement as read by the user.
+    /// Returns `false` if it does not exist or expired.
+    pub async fn read_announcement(
+        &self,
+        user_id: Id<UserMarker>,
+        announcement_id: Id<AnnouncementMarker>,
+    ) -> Result<bool> {
+        self.set_announcement_state(user_id, announcement_id, false)
+            .await
+    }
+
+    /// Marks the announcement as read and dismissed by the user, which is not undone.
+    /// Returns `false` if it does not exist or expired.
+    pub async fn dismiss_announcement(
+        &self,
+        user_id: Id<UserMarker>,
+        announcement_id: Id<AnnouncementMarker>,
+    ) -> Result<bool> {
+        self.set_announcement_state(user_id, announcement_id, true)
+            .await
+    }
+
+    async fn set_announcement_state(
+        &self,
+        user_id: Id<UserMarker>,
+        announcement_id: Id<AnnouncementMarker>,
+        dismissed: bool,
+    ) -> Result<bool> {
+        let now_utc = UtcDateTime::now();
+        let now_primitive = PrimitiveDateTime::new(now_utc.date(), now_utc.time());
+
+        let rows_affected = query!(
+            "
+            INSERT INTO instance.announcement_states
+                (announcement_snowflake, user_snowflake, dismissed)
+            SELECT announcements.announcement_snowflake, $2, $3
+            FROM instance.announcements
+            WHERE
+                announcements.announcement_snowflake = $1
+                AND (announcements.expires_at IS NULL OR announcements.expires_at > $4)
+            ON CONFLICT (user_snowflake, announcement_snowflake) DO UPDATE
+                SET dismissed = announcement_states.dismissed OR excluded.dismissed
+            ",
+            announcement_id.snowflake().get().cast_signed(),
+            user_id.snowflake().get().cast_signed(),
+            dismissed,
+            now_primitive,
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "set_announcement_state")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected != 0)
+    }
+
+    /// Deletes the announcement with the read states of all users.
+    pub async fn delete_announcement(
+        &self,
+        announcement_id: Id<AnnouncementMarker>,
+    ) -> Result<bool> {
+        let rows_affected = query!(
+            "
+            DELETE FROM instance.announcements
+            WHERE announcements.announcement_snowflake = $1
+            ",
+            announcement_id.snowflake().get().cast_signed(),
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "delete_announcement")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected != 0)
+    }
+
+    /// Publishes the content as the next version of the policy, which all users have to accept.
+    pub async fn publish_policy(
+        &self,
+        kind: PolicyKind,
+        content: &PolicyContent,
+    ) -> Result<Policy> {
+        let now_utc = UtcDateTime::now();
+        let now_primitive = PrimitiveDateTime::new(now_utc.date(), now_utc.time());
+
+        let record = query_as!(
+            PolicyRecord,
+            "
+            INSERT INTO instance.policies
+                (kind, version, content, published_at)
+            SELECT $1::varchar, COALESCE(MAX(policies.version), 0) + 1, $2, $3
+            FROM instance.policies
+            WHERE policies.kind = $1
+            RETURNING kind, version, content, published_at
+            ",
+            kind.as_str(),
+            content.get(),
+            now_primitive,
+        )
+        .fetch_one(&mut *self.writer().await?)
+        .measured_one(&self.metrics, "publish_policy")
+        .await?;
+
+        Ok(record.try_into()?)
+    }
+
+    /// Returns the latest version of each published policy.
+    pub async fn fetch_current_policies(&self) -> Result<Vec<Policy>> {
+        let records = self
+            .idempotent("fetch_current_policies", || async move {
+                query_as!(
+                    PolicyRecord,
+                    "
+                    SELECT DISTINCT ON (policies.kind)
+                        policies.kind,
+                        policies.version,
+                        policies.content,
+                        policies.published_at
+                    FROM
+                        instance.policies
+                    ORDER BY
+                        policies.kind, policies.version DESC
+                    ",
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_current_policies")
+                .await
+            })
+            .await?;
+
+        let policies = records
+            .into_iter()
+            .map(Policy::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(policies)
+    }
+
+    /// Returns the latest version of each policy the user has not accepted yet.
+    pub async fn fetch_pending_policies(&self, user_id: Id<UserMarker>) -> Result<Vec<Policy>> {
+        let records = self
+            .idempotent("fetch_pending_policies", || async move {
+                query_as!(
+                    PolicyRecord,
+                    r#"
+                    SELECT
+                        current.kind AS "kind!",
+                        current.version AS "version!",
+                        current.content AS "content!",
+                        current.published_at AS "published_at!"
+                    FROM
+                        (
+                            SELECT DISTINCT ON (policies.kind)
+                                policies.kind,
+                                policies.version,
+                                policies.content,
+                                policies.published_at
+                            FROM
+                                instance.policies
+                            ORDER BY
+                                policies.kind, policies.version DESC
+                        ) AS current
+                        LEFT JOIN instance.policy_acceptances
+                            ON policy_acceptances.kind = current.kind
+                            AND policy_acceptances.user_snowflake = $1
+                    WHERE
+                        policy_acceptances.version IS DISTINCT FROM current.version
+                    ORDER BY
+                        current.kind
+                    "#,
+                    user_id.snowflake().get().cast_signed(),
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_pending_policies")
+                .await
+            })
+            .await?;
+
+        let policies = records
+            .into_iter()
+            .map(Policy::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(policies)
+    }
+
+    /// Whether the user has not accepted the latest version of some policy yet.
+    pub async fn has_pending_policies(&self, user_id: Id<UserMarker>) -> Result<bool> {
+        let pending = self
+            .idempotent("has_pending_policies", || async move {
+                query_scalar!(
+                    r#"
+                    SELECT EXISTS (
+                        SELECT
+                        FROM
+                            instance.policies
+                            LEFT JOIN instance.policy_acceptances
+                                ON policy_acceptances.kind = policies.kind
+                                AND policy_acceptances.user_snowflake = $1
+                        WHERE
+                            policy_acceptances.version IS NULL
+                            OR policy_acceptances.version < policies.version
+                    ) AS "pending!"
+                    "#,
+                    user_id.snowflake().get().cast_signed(),
+                )
+                .fetch_one(&mut *self.reader().await?)
+                .measured_one(&self.metrics, "has_pending_policies")
+                .await
+            })
+            .await?;
+
+        Ok(pending)
+    }
+
+    /// Records that the user accepted the version of the policy.
+    /// Returns `false` if no version of the policy was published.
+    ///
+    /// Only the latest version can be accepted, [`DbError::Conflict`] is returned for older ones.
+    pub async fn accept_policy(
+        &self,
+        user_id: Id<UserMarker>,
+        kind: PolicyKind,
+        version: u64,
+    ) -> Result<bool> {
+        let now_utc = UtcDateTime::now();
+        let now_primitive = PrimitiveDateTime::new(now_utc.date(), now_utc.time());
+
+        let current_version = query_scalar!(
+            "
+            WITH current AS (
+                SELECT MAX(policies.version) AS version
+                FROM instance.policies
+                WHERE policies.kind = $2
+            ), accepted AS (
+                INSERT INTO instance.policy_acceptances
+                    (user_snowflake, kind, version, accepted_at)
+                SELECT $1, $2::varchar, current.version, $4
+                FROM current
+                WHERE current.version = $3
+                ON CONFLICT (user_snowflake, kind) DO UPDATE
+                    SET version = excluded.version, accepted_at = excluded.accepted_at
+            )
+            SELECT current.version FROM current
+            ",
+            user_id.snowflake().get().cast_signed(),
+            kind.as_str(),
+            version.cast_signed(),
+            now_primitive,
+        )
+        .fetch_one(&mut *self.writer().await?)
+        .measured_one(&self.metrics, "accept_policy")
+        .await?;
+
+        match current_version {
+            None => Ok(false),
+            Some(current_version) if current_version.cast_unsigned() == version => Ok(true),
+            Some(current_version) => Err(DbError::Conflict {
+                current_version: current_version.cast_unsigned(),
+            }),
+        }
+    }
+
+    /// Returns the feature flags set by administrators, which override the configuration.
+    pub async fn fetch_feature_flags(&self) -> Result<HashMap<FeatureFlag, bool>> {
+        let records = self
+            .idempotent("fetch_feature_flags", || async move {
+                query!(
+                    "
+                    SELECT feature_flags.flag, feature_flags.enabled
+                    FROM instance.feature_flags
+                    ",
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_feature_flags")
+                .await
+            })
+            .await?;
+
+        let flags = records
+            .into_iter()
+            .map(|record| Ok((record.flag.parse()?, record.enabled)))
+            .collect::<Result<_, ModelValidationError>>()?;
+
+        Ok(flags)
+    }
+
+    /// Overrides the configuration of the flag on all servers.
+    pub async fn set_feature_flag(&self, flag: FeatureFlag, enabled: bool) -> Result<()> {
+        let now_utc = UtcDateTime::now();
+        let now_primitive = PrimitiveDateTime::new(now_utc.date(), now_utc.time());
+
+        self.idempotent("set_feature_flag", || async move {
+            query!(
+                "
+                INSERT INTO instance.feature_flags (flag, enabled, updated_at)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (flag) DO UPDATE
+                    SET enabled = excluded.enabled, updated_at = excluded.updated_at
+                ",
+                flag.as_str(),
+                enabled,
+                now_primitive,
+            )
+            .execute(&mut *self.writer().await?)
+            .measured(&self.metrics, "set_feature_flag")
+            .await
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Lets the flag follow the configuration of each server again.
+    pub async fn reset_feature_flag(&self, flag: FeatureFlag) -> Result<()> {
+        self.idempotent("reset_feature_flag", || async move {
+            query!(
+                "
+                DELETE FROM instance.feature_flags
+                WHERE feature_flags.flag = $1
+                ",
+                flag.as_str(),
+            )
+            .execute(&mut *self.writer().await?)
+            .measured(&self.metrics, "reset_feature_flag")
+            .await
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn fetch_email_digest_settings(
+        &self,
+        user_id: Id<UserMarker>,
+    ) -> Result<Option<EmailDigestSettings>> {
+        let record = self
+            .idempotent("fetch_email_digest_settings", || async move {
+                query!(
+                    "
+                    SELECT
+                        email_digests.email,
+                        email_digests.frequency
+                    FROM
+                        users.email_digests
+                    WHERE
+                        email_digests.user_snowflake = $1
+                    ",
+                    user_id.snowflake().get().cast_signed(),
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_email_digest_settings")
+                .await
+            })
+            .await?;
+
+        let settings = record
+            .map(|record| -> Result<_, ModelValidationError> {
+                Ok(EmailDigestSettings {
+                    email: EmailAddress::new(record.email)?,
+                    frequency: record.frequency.parse()?,
+                })
+            })
+            .transpose()?;
+        Ok(settings)
+    }
+
+    /// Subscribes the user to digests or changes their settings.
+    /// The next digest is scheduled one period from now.
+    pub async fn set_email_digest_settings(
+        &self,
+        user_id: Id<UserMarker>,
+        settings: &EmailDigestSettings,
+    ) -> Result<()> {
+        let next_utc = UtcDateTime::now() + settings.frequency.period();
+        let next_primitive = PrimitiveDateTime::new(next_utc.date(), next_utc.time());
+
+        self.idempotent("set_email_digest_settings", || async move {
+            query!(
+                "
+                    INSERT INTO users.email_digests
+                        (user_snowflake, email, frequency, unsubscribe_token, next_digest_at)
+                    VALUES ($1, $2, $3, $4, $5)
+                    ON CONFLICT (user_snowflake) DO UPDATE
+                    SET
+                        email = excluded.email,
+                        frequency = excluded.frequency,
+                        next_digest_at = excluded.next_digest_at
+                    ",
+                user_id.snowflake().get().cast_signed(),
+                settings.email.get(),
+                settings.frequency.as_str(),
+                &UnsubscribeToken::generate_random().0,
+                next_primitive,
+            )
+            .execute(&mut *self.writer().await?)
+            .measured(&self.metrics, "set_email_digest_settings")
+            .await
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Returns `false` if the user was not subscribed.
+    pub async fn delete_email_digest(&self, user_id: Id<UserMarker>) -> Result<bool> {
+        let rows_affected = query!(
+            "
+            DELETE FROM users.email_digests
+            WHERE email_digests.user_snowflake = $1
+            ",
+            user_id.snowflake().get().cast_signed(),
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "delete_email_digest")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Returns `false` if no subscription has this token.
+    pub async fn unsubscribe_email_digest(&self, token: &UnsubscribeToken) -> Result<bool> {
+        let rows_affected = query!(
+            "
+            DELETE FROM users.email_digests
+            WHERE email_digests.unsubscribe_token = $1
+            ",
+            &token.0,
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "unsubscribe_email_digest")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    /// Returns the subscriptions whose next digest is due at `now`.
+    pub async fn fetch_due_email_digests(
+        &self,
+        now: UtcDateTime,
+        limit: u32,
+    ) -> Result<Vec<EmailDigestSubscription>> {
+        let now_primitive = PrimitiveDateTime::new(now.date(), now.time());
+
+        let records = self
+            .idempotent("fetch_due_email_digests", || async move {
+                query_as!(
+                    EmailDigestRecord,
+                    "
+                    SELECT
+                        email_digests.user_snowflake,
+                        email_digests.email,
+                        email_digests.frequency,
+                        email_digests.unsubscribe_token,
+                        GREATEST(
+                            email_digests.last_notification_snowflake,
+                            users.last_read_notification_snowflake
+                        ) as digested_up_to
+                    FROM
+                        users.email_digests NATURAL JOIN users.users
+                    WHERE
+                        email_digests.next_digest_at <= $1
+                    ORDER BY
+                        email_digests.next_digest_at
+                    LIMIT $2
+                    ",
+                    now_primitive,
+                    i64::from(limit),
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_due_email_digests")
+                .await
+            })
+            .await?;
+
+        let subscriptions = records
+            .into_iter()
+            .map(EmailDigestSubscription::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(subscriptions)
+    }
+
+    /// Schedules the next digest. If a digest was sent, queues its email and records that the
+    /// notifications up to the given one were digested, in the same transaction.
+    pub async fn finish_email_digest(
+        &self,
+        user_id: Id<UserMarker>,
+        digest: Option<(Id<NotificationMarker>, Email)>,
+        next_digest_at: UtcDateTime,
+    ) -> Result<()> {
+        let next_primitive = PrimitiveDateTime::new(next_digest_at.date(), next_digest_at.time());
+        let (up_to, jobs) = match digest {
+            Some((up_to, email)) => (Some(up_to), vec![JobPayload::Email { email }]),
+            None => (None, Vec::new()),
+        };
+
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+
+        query!(
+            "
+            UPDATE users.email_digests
+            SET
+                next_digest_at = $2,
+                last_notification_snowflake = GREATEST(
+                    email_digests.last_notification_snowflake,
+                    $3
+                )
+            WHERE email_digests.user_snowflake = $1
+            ",
+            user_id.snowflake().get().cast_signed(),
+            next_primitive,
+            up_to.map(|id| id.snowflake().get().cast_signed()),
+        )
+        .execute(&mut *transaction)
+        .measured(&self.metrics, "finish_email_digest")
+        .await?;
+
+        self.insert_jobs(&mut transaction, &jobs, UtcDateTime::now())
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    /// Creates a conversation with `creator` and `members` as members.
+    pub async fn create_conversation(
+        &self,
+        creator: Id<UserMarker>,
+        members: &[Id<UserMarker>],
+    ) -> Result<Id<ConversationMarker>> {
+        let conversation_snowflake = self.snowflake_generator.generate()?;
+        let member_snowflakes: Vec<_> = members
+            .iter()
+            .chain([&creator])
+            .map(|member| member.snowflake().get().cast_signed())
+            .collect();
+
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+
+        let returned_snowflake = query_scalar!(
+            "
+            INSERT INTO messaging.conversations (conversation_snowflake, creator_snowflake)
+            VALUES ($1, $2)
+            RETURNING conversations.conversation_snowflake
+            ",
+            conversation_snowflake.get().cast_signed(),
+            creator.snowflake().get().cast_signed(),
+        )
+        .fetch_one(&mut *transaction)
+        .measured_one(&self.metrics, "create_conversation.insert")
+        .await?;
+
+        query!(
+            "
+            INSERT INTO messaging.conversation_members (conversation_snowflake, user_snowflake)
+            SELECT $1, members.user_snowflake
+            FROM UNNEST($2::bigint[]) AS members (user_snowflake)
+            ON CONFLICT DO NOTHING
+            ",
+            returned_snowflake,
+            &member_snowflakes,
+        )
+        .execute(&mut *transaction)
+        .measured(&self.metrics, "create_conversation.members")
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(returned_snowflake.cast_unsigned().into())
+    }
+
+    /// Returns the conversations `member` is a member of, most recently active first.
+    pub async fn fetch_conversations(&self, member: Id<UserMarker>) -> Result<Vec<Conversation>> {
+        self.fetch_conversations_filtered(member, None).await
+    }
+
+    /// Returns `None` if the conversation does not exist or `member` is not a member of it.
+    pub async fn fetch_conversation(
+        &self,
+        conversation_id: Id<ConversationMarker>,
+        member: Id<UserMarker>,
+    ) -> Result<Option<Conversation>> {
+        let mut conversations = self
+            .fetch_conversations_filtered(member, Some(conversation_id))
+            .await?;
+
+        Ok(conversations.pop())
+    }
+
+    async fn fetch_conversations_filtered(
+        &self,
+        member: Id<UserMarker>,
+        conversation_id: Option<Id<ConversationMarker>>,
+    ) -> Result<Vec<Conversation>> {
+        let records = self
+            .idempotent("fetch_conversations_filtered", || async move {
+                query_as!(
+                    ConversationRecord,
+                    r#"
+                    SELECT
+                        conversations.conversation_snowflake,
+                        conversations.creator_snowflake,
+                        (
+                            SELECT count(1)
+                            FROM messaging.messages
+                            WHERE
+                                messages.conversation_snowflake = conversations.conversation_snowflake
+                                AND messages.author_snowflake != $1
+                                AND (
+                                    conversation_members.last_read_message_snowflake IS NULL
+                                    OR messages.message_snowflake
+                                        > conversation_members.last_read_message_snowflake
+                                )
+                        ) as "unread_count!",
+                        last_message.message_snowflake as "last_message_snowflake?",
+                        last_message.author_snowflake as "last_message_author_snowflake?",
+                        last_message.content as "last_message_content?",
+                        last_message.encrypted_payload as "last_message_encrypted_payload?"
+                    FROM
+                        messaging.conversation_members
+                        NATURAL JOIN messaging.conversations
+                        LEFT JOIN LATERAL (
+                            SELECT
+                                messages.message_snowflake,
+                                messages.author_snowflake,
+                                messages.content,
+                                messages.encrypted_payload
+                            FROM messaging.messages
+                            WHERE messages.conversation_snowflake = conversations.conversation_snowflake
+                            ORDER BY messages.message_snowflake DESC
+                            LIMIT 1
+                        ) AS last_message ON true
+                    WHERE
+                        conversation_members.user_snowflake = $1
+                        AND ($2::bigint IS NULL OR conversations.conversation_snowflake = $2)
+                    ORDER BY
+                        COALESCE(last_message.message_snowflake, conversations.conversation_snowflake) DESC
+                    "#,
+                    member.snowflake().get().cast_signed(),
+                    conversation_id.map(|id| id.snowflake().get().cast_signed()),
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_conversations_filtered")
+                .await
+            })
+            .await?;
+
+        let conversation_snowflakes: Vec<_> = records
+            .iter()
+            .map(|record| record.conversation_snowflake)
+            .collect();
+        let mut members = self
+            .fetch_conversation_members(&conversation_snowflakes)
+            .await?;
+
+        let conversations = records
+            .into_iter()
+            .map(|record| {
+                let conversation_members = members
+                    .remove(&record.conversation_snowflake)
+                    .unwrap_or_default();
+                record.into_conversation(conversation_members)
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(conversations)
+    }
+
+    /// Returns the members of each conversation, keyed by conversation snowflake.
+    async fn fetch_conversation_members(
+        &self,
+        conversation_snowflakes: &[i64],
+    ) -> Result<HashMap<i64, Vec<User>>> {
+        let records = self
+            .idempotent("fetch_conversation_members", || async move {
+                query_as!(
+                    ConversationMemberRecord,
+                    r#"
+                    SELECT
+                        conversation_members.conversation_snowflake,
+                        users.user_snowflake,
+                        users.handle,
+                        users.verified_at IS NOT NULL AS "verified!"
+                    FROM
+                        messaging.conversation_members NATURAL JOIN users.users
+                    WHERE
+                        conversation_members.conversation_snowflake = ANY($1)
+                    ORDER BY
+                        users.user_snowflake
+                    "#,
+                    conversation_snowflakes,
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_conversation_members")
+                .await
+            })
+            .await?;
+
+        let mut members: HashMap<i64, Vec<User>> = HashMap::new();
+        for record in records {
+            members
+                .entry(record.conversation_snowflake)
+                .or_default()
+                .push(User {
+                    id: record.user_snowflake.cast_unsigned().into(),
+                    handle: UserHandle::new(record.handle).map_err(ModelValidationError::from)?,
+                    verified: record.verified,
+                });
+        }
+
+        Ok(members)
+    }
+
+    /// Adds `user` to the conversation.
+    /// Returns `false` if the conversation already has `max_members` members.
+    /// Adding an existing member succeeds without changes.
+    pub async fn add_conversation_member(
+        &self,
+        conversation_id: Id<ConversationMarker>,
+        user_id: Id<UserMarker>,
+        max_members: usize,
+    ) -> Result<bool> {
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+
+        // Serializes concurrent additions so the limit cannot be exceeded.
+        query!(
+            "
+            SELECT conversations.conversation_snowflake
+            FROM messaging.conversations
+            WHERE conversations.conversation_snowflake = $1
+            FOR UPDATE
+            ",
+            conversation_id.snowflake().get().cast_signed(),
+        )
+        .fetch_optional(&mut *transaction)
+        .measured(&self.metrics, "add_conversation_member.conversation")
+        .await?;
+
+        let member_count = query_scalar!(
+            r#"
+            SELECT count(1) as "count!"
+            FROM messaging.conversation_members
+            WHERE conversation_members.conversation_snowflake = $1
+            "#,
+            conversation_id.snowflake().get().cast_signed(),
+        )
+        .fetch_one(&mut *transaction)
+        .measured_one(&self.metrics, "add_conversation_member.member_count")
+        .await?;
+
+        let rows_affected = if member_count.cast_unsigned() < max_members as u64 {
+            query!(
+                "
+                INSERT INTO messaging.conversation_members (conversation_snowflake, user_snowflake)
+                VALUES ($1, $2)
+                ON CONFLICT DO NOTHING
+                ",
+                conversation_id.snowflake().get().cast_signed(),
+                user_id.snowflake().get().cast_signed(),
+            )
+            .execute(&mut *transaction)
+            .measured(&self.metrics, "add_conversation_member.insert")
+            .await?
+            .rows_affected()
+        } else {
+            0
+        };
+
+        let is_member = rows_affected > 0
+            || self
+                .is_conversation_member(&mut *transaction, conversation_id, user_id)
+                .await?;
+        transaction.commit().await?;
+
+        Ok(is_member)
+    }
+
+    async fn is_conversation_member(
+        &self,
+        executor: impl PgExecutor<'_>,
+        conversation_id: Id<ConversationMarker>,
+        user_id: Id<UserMarker>,
+    ) -> Result<bool> {
+        let is_member = query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT
+                FROM messaging.conversation_members
+                WHERE
+                    conversation_members.conversation_snowflake = $1
+                    AND conversation_members.user_snowflake = $2
+            ) as "is_member!"
+            "#,
+            conversation_id.snowflake().get().cast_signed(),
+            user_id.snowflake().get().cast_signed(),
+        )
+        .fetch_one(executor)
+        .measured_one(&self.metrics, "is_conversation_member")
+        .await?;
+
+        Ok(is_member)
+    }
+
+    /// Returns `false` if `user_id` was not a member.
+    pub async fn remove_conversation_member(
+        &self,
+        conversation_id: Id<ConversationMarker>,
+        user_id: Id<UserMarker>,
+    ) -> Result<bool> {
+        let rows_affected = query!(
+            "
+            DELETE FROM messaging.conversation_members
+            WHERE
+                conversation_members.conversation_snowflake = $1
+                AND conversation_members.user_snowflake = $2
+            ",
+            conversation_id.snowflake().get().cast_signed(),
+            user_id.snowflake().get().cast_signed(),
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "remove_conversation_member")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    pub async fn create_message(&self, message: &CreateMessage) -> Result<Id<MessageMarker>> {
+        let message_snowflake = self.snowflake_generator.generate()?;
+        let (content, encrypted_payload) = match &message.body {
+            MessageBody::Content(content) => (Some(content.get()), None),
+            MessageBody::Encrypted(payload) => (None, Some(payload.get())),
+        };
+
+        let returned_snowflake = query_scalar!(
+            "
+            INSERT INTO messaging.messages
+                (message_snowflake, conversation_snowflake, author_snowflake, content, encrypted_payload)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING messages.message_snowflake
+            ",
+            message_snowflake.get().cast_signed(),
+            message.conversation.snowflake().get().cast_signed(),
+            message.author.snowflake().get().cast_signed(),
+            content,
+            encrypted_payload,
+        )
+        .fetch_one(&mut *self.writer().await?)
+        .measured_one(&self.metrics, "create_message")
+        .await?;
+
+        Ok(returned_snowflake.cast_unsigned().into())
+    }
+
+    /// Returns the newest messages of the conversation, newest first.
+    /// `max_id` and `since_id` are exclusive bounds.
+    pub async fn fetch_messages(
+        &self,
+        conversation_id: Id<ConversationMarker>,
+        max_id: Option<Id<MessageMarker>>,
+        since_id: Option<Id<MessageMarker>>,
+        limit: u32,
+    ) -> Result<Vec<Message>> {
+        let records = self
+            .idempotent("fetch_messages", || async move {
+                query_as!(
+                    MessageRecord,
+                    "
+                    SELECT
+                        messages.message_snowflake,
+                        messages.author_snowflake,
+                        messages.content,
+                        messages.encrypted_payload
+                    FROM
+                        messaging.messages
+                    WHERE
+                        messages.conversation_snowflake = $1
+                        AND ($2::bigint IS NULL OR messages.message_snowflake < $2)
+                        AND ($3::bigint IS NULL OR messages.message_snowflake > $3)
+                    ORDER BY
+                        messages.message_snowflake DESC
+                    LIMIT $4
+                    ",
+                    conversation_id.snowflake().get().cast_signed(),
+                    max_id.map(|id| id.snowflake().get().cast_signed()),
+                    since_id.map(|id| id.snowflake().get().cast_signed()),
+                    i64::from(limit),
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_messages")
+                .await
+            })
+            .await?;
+
+        let messages = records
+            .into_iter()
+            .map(Message::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(messages)
+    }
+
+    /// Marks messages up to and including `max_id` as read by `user_id`,
+    /// or all of them if `max_id` is `None`. The read marker never moves backwards.
+    pub async fn mark_conversation_read(
+        &self,
+        conversation_id: Id<ConversationMarker>,
+        user_id: Id<UserMarker>,
+        max_id: Option<Id<MessageMarker>>,
+    ) -> Result<()> {
+        query!(
+            "
+            UPDATE messaging.conversation_members
+            SET last_read_message_snowflake = GREATEST(
+                conversation_members.last_read_message_snowflake,
+                COALESCE(
+                    $3,
+                    (
+                        SELECT max(messages.message_snowflake)
+                        FROM messaging.messages
+                        WHERE messages.conversation_snowflake = $1
+                    )
+                )
+            )
+            WHERE
+                conversation_members.conversation_snowflake = $1
+                AND conversation_members.user_snowflake = $2
+            ",
+            conversation_id.snowflake().get().cast_signed(),
+            user_id.snowflake().get().cast_signed(),
+            max_id.map(|id| id.snowflake().get().cast_signed()),
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "mark_conversation_read")
+        .await?;
+
+        Ok(())
+    }
+
+    /// Replaces the identity key and signed prekey of the user and adds the one-time prekeys.
+    /// One-time prekeys with an existing key id are replaced.
+    /// Returns `false` if this would leave more than `max_one_time_prekeys` one-time prekeys.
+    pub async fn publish_keys(
+        &self,
+        user_id: Id<UserMarker>,
+        keys: &PublishKeys,
+        max_one_time_prekeys: usize,
+    ) -> Result<bool> {
+        let now_utc = UtcDateTime::now();
+        let now_primitive = PrimitiveDateTime::new(now_utc.date(), now_utc.time());
+        let (key_ids, public_keys): (Vec<_>, Vec<_>) = keys
+            .one_time_prekeys
+            .iter()
+            .map(|prekey| (i64::from(prekey.key_id), prekey.public_key.get()))
+            .unzip();
+
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+
+        query!(
+            "
+            INSERT INTO messaging.identity_keys (
+                user_snowflake,
+                identity_key,
+                signed_prekey_id,
+                signed_prekey,
+                signed_prekey_signature,
+                updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (user_snowflake) DO UPDATE
+            SET
+                identity_key = excluded.identity_key,
+                signed_prekey_id = excluded.signed_prekey_id,
+                signed_prekey = excluded.signed_prekey,
+                signed_prekey_signature = excluded.signed_prekey_signature,
+                updated_at = excluded.updated_at
+            ",
+            user_id.snowflake().get().cast_signed(),
+            keys.identity_key.get(),
+            i64::from(keys.signed_prekey.key_id),
+            keys.signed_prekey.public_key.get(),
+            keys.signed_prekey.signature.get(),
+            now_primitive,
+        )
+        .execute(&mut *transaction)
+        .measured(&self.metrics, "publish_keys.identity_keys")
+        .await?;
+
+        query!(
+            "
+            INSERT INTO messaging.one_time_prekeys (user_snowflake, key_id, public_key)
+            SELECT $1, prekeys.key_id, prekeys.public_key
+            FROM UNNEST($2::bigint[], $3::bytea[]) AS prekeys (key_id, public_key)
+            ON CONFLICT (user_snowflake, key_id) DO UPDATE
+            SET public_key = excluded.public_key
+            ",
+            user_id.snowflake().get().cast_signed(),
+            &key_ids,
+            &public_keys as &[&[u8]],
+        )
+        .execute(&mut *transaction)
+        .measured(&self.metrics, "publish_keys.one_time_prekeys")
+        .await?;
+
+        let prekey_count = self
+            .count_one_time_prekeys(&mut *transaction, user_id)
+            .await?;
+        if prekey_count > max_one_time_prekeys as u64 {
+            return Ok(false);
+        }
+
+        transaction.commit().await?;
+
+        Ok(true)
+    }
+
+    /// Returns `None` if the user has not published keys.
+    pub async fn fetch_key_status(&self, user_id: Id<UserMarker>) -> Result<Option<KeyStatus>> {
+        let has_keys = self
+            .idempotent("fetch_key_status", || async move {
+                query_scalar!(
+                    r#"
+                    SELECT EXISTS (
+                        SELECT
+                        FROM messaging.identity_keys
+                        WHERE identity_keys.user_snowflake = $1
+                    ) as "has_keys!"
+                    "#,
+                    user_id.snowflake().get().cast_signed(),
+                )
+                .fetch_one(&mut *self.reader().await?)
+                .measured_one(&self.metrics, "fetch_key_status")
+                .await
+            })
+            .await?;
+        if !has_keys {
+            return Ok(None);
+        }
+
+        let one_time_prekey_count = self
+            .count_one_time_prekeys(&mut *self.writer().await?, user_id)
+            .await?;
+        Ok(Some(KeyStatus {
+            one_time_prekey_count,
+        }))
+    }
+
+    async fn count_one_time_prekeys(
+        &self,
+        executor: impl PgExecutor<'_>,
+        user_id: Id<UserMarker>,
+    ) -> Result<u64> {
+        let count = query_scalar!(
+            r#"
+            SELECT count(1) as "count!"
+            FROM messaging.one_time_prekeys
+            WHERE one_time_prekeys.user_snowflake = $1
+            "#,
+            user_id.snowflake().get().cast_signed(),
+        )
+        .fetch_one(executor)
+        .measured_one(&self.metrics, "count_one_time_prekeys")
+        .await?;
+
+        Ok(count.cast_unsigned())
+    }
+
+    /// Returns the key bundle of the user, removing the one-time prekey it contains.
+    /// Returns `None` if the user has not published keys.
+    pub async fn claim_key_bundle(&self, user_id: Id<UserMarker>) -> Result<Option<KeyBundle>> {
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+
+        let Some(record) = query!(
+            "
+            SELECT
+                identity_keys.identity_key,
+                identity_keys.signed_prekey_id,
+                identity_keys.signed_prekey,
+                identity_keys.signed_prekey_signature
+            FROM messaging.identity_keys
+            WHERE identity_keys.user_snowflake = $1
+            ",
+            user_id.snowflake().get().cast_signed(),
+        )
+        .fetch_optional(&mut *transaction)
+        .measured(&self.metrics, "claim_key_bundle.identity_keys")
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let one_time_prekey = query!(
+            "
+            DELETE FROM messaging.one_time_prekeys
+            WHERE (one_time_prekeys.user_snowflake, one_time_prekeys.key_id) = (
+                SELECT claimed.user_snowflake, claimed.key_id
+                FROM messaging.one_time_prekeys AS claimed
+                WHERE claimed.user_snowflake = $1
+                ORDER BY claimed.key_id
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING one_time_prekeys.key_id, one_time_prekeys.public_key
+            ",
+            user_id.snowflake().get().cast_signed(),
+        )
+        .fetch_optional(&mut *transaction)
+        .measured(&self.metrics, "claim_key_bundle.one_time_prekey")
+        .await?;
+
+        transaction.commit().await?;
+
+        let bundle = (|| -> Result<_, ModelValidationError> {
+            Ok(KeyBundle {
+                identity_key: KeyBytes::new(record.identity_key)?,
+                signed_prekey: SignedPrekey {
+                    key_id: key_id_from_db(record.signed_prekey_id),
+                    public_key: KeyBytes::new(record.signed_prekey)?,
+                    signature: KeyBytes::new(record.signed_prekey_signature)?,
+                },
+                one_time_prekey: one_time_prekey
+                    .map(|prekey| -> Result<_, ModelValidationError> {
+                        Ok(OneTimePrekey {
+                            key_id: key_id_from_db(prekey.key_id),
+                            public_key: KeyBytes::new(prekey.public_key)?,
+                        })
+                    })
+                    .transpose()?,
+            })
+        })()?;
+
+        Ok(Some(bundle))
+    }
+
+    pub async fn fetch_actor_key_pair(&self, user_id: Id<UserMarker>) -> Result<Option<KeyPair>> {
+        let key_pair = self
+            .idempotent("fetch_actor_key_pair", || async move {
+                query_as!(
+                    KeyPairRecord,
+                    "
+                    SELECT public_key_pem, private_key_pem
+                    FROM federation.actor_keys
+                    WHERE actor_keys.user_snowflake = $1
+                    ",
+                    user_id.snowflake().get().cast_signed(),
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_actor_key_pair")
+                .await
+            })
+            .await?;
+
+        Ok(key_pair.map(KeyPair::from))
+    }
+
+    /// Stores `key_pair` unless the user already has one. Returns the key pair that is stored.
+    pub async fn insert_actor_key_pair(
+        &self,
+        user_id: Id<UserMarker>,
+        key_pair: &KeyPair,
+    ) -> Result<KeyPair> {
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+
+        query!(
+            "
+            INSERT INTO federation.actor_keys (user_snowflake, public_key_pem, private_key_pem)
+            VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING
+            ",
+            user_id.snowflake().get().cast_signed(),
+            key_pair.public_key_pem,
+            key_pair.private_key_pem,
+        )
+        .execute(&mut *transaction)
+        .measured(&self.metrics, "insert_actor_key_pair.insert")
+        .await?;
+
+        let key_pair = query_as!(
+            KeyPairRecord,
+            "
+            SELECT public_key_pem, private_key_pem
+            FROM federation.actor_keys
+            WHERE actor_keys.user_snowflake = $1
+            ",
+            user_id.snowflake().get().cast_signed(),
+        )
+        .fetch_one(&mut *transaction)
+        .measured_one(&self.metrics, "insert_actor_key_pair.select")
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(key_pair.into())
+    }
+
+    pub async fn fetch_instance_key_pair(&self) -> Result<Option<KeyPair>> {
+        let key_pair = self
+            .idempotent("fetch_instance_key_pair", || async move {
+                query_as!(
+                    KeyPairRecord,
+                    "
+                    SELECT public_key_pem, private_key_pem
+                    FROM federation.instance_key
+                    ",
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_instance_key_pair")
+                .await
+            })
+            .await?;
+
+        Ok(key_pair.map(KeyPair::from))
+    }
+
+    /// Stores `key_pair` unless there already is one. Returns the key pair that is stored.
+    pub async fn insert_instance_key_pair(&self, key_pair: &KeyPair) -> Result<KeyPair> {
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+
+        query!(
+            "
+            INSERT INTO federation.instance_key (public_key_pem, private_key_pem)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+            ",
+            key_pair.public_key_pem,
+            key_pair.private_key_pem,
+        )
+        .execute(&mut *transaction)
+        .measured(&self.metrics, "insert_instance_key_pair.insert")
+        .await?;
+
+        let key_pair = query_as!(
+            KeyPairRecord,
+            "
+            SELECT public_key_pem, private_key_pem
+            FROM federation.instance_key
+            ",
+        )
+        .fetch_one(&mut *transaction)
+        .measured_one(&self.metrics, "insert_instance_key_pair.select")
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(key_pair.into())
+    }
+
+    pub async fn fetch_remote_actor_key(&self, key_id: &str) -> Result<Option<PublicKey>> {
+        let public_key = self
+            .idempotent("fetch_remote_actor_key", || async move {
+                query_as!(
+                    RemoteActorKeyRecord,
+                    "
+                    SELECT key_id, owner, public_key_pem
+                    FROM federation.remote_actor_keys
+                    WHERE remote_actor_keys.key_id = $1
+                    ",
+                    key_id,
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_remote_actor_key")
+                .await
+            })
+            .await?;
+
+        Ok(public_key.map(PublicKey::from))
+    }
+
+    pub async fn upsert_remote_actor_key(
+        &self,
+        public_key: &PublicKey,
+        fetched_at: UtcDateTime,
+    ) -> Result<()> {
+        let fetched_at = PrimitiveDateTime::new(fetched_at.date(), fetched_at.time());
+
+        self.idempotent("upsert_remote_actor_key", || async move {
+            query!(
+                "
+                    INSERT INTO federation.remote_actor_keys (key_id, owner, public_key_pem, fetched_at)
+                    VALUES ($1, $2, $3, $4)
+                    ON CONFLICT (key_id) DO UPDATE
+                    SET owner = excluded.owner,
+                        public_key_pem = excluded.public_key_pem,
+                        fetched_at = excluded.fetched_at
+                    ",
+                public_key.id,
+                public_key.owner,
+                public_key.public_key_pem,
+                fetched_at,
+            )
+            .execute(&mut *self.writer().await?)
+            .measured(&self.metrics, "upsert_remote_actor_key")
+            .await
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// The local user standing in for the remote actor with the ActivityPub id `actor_id`.
+    pub async fn fetch_remote_actor_user(&self, actor_id: &str) -> Result<Option<User>> {
+        let record = self
+            .idempotent("fetch_remote_actor_user", || async move {
+                query_as!(
+                    UserRecord,
+                    r#"
+                    SELECT
+                        users.user_snowflake,
+                        users.handle,
+                        users.verified_at IS NOT NULL AS "verified!"
+                    FROM
+                        federation.remote_actors NATURAL JOIN users.users
+                    WHERE
+                        remote_actors.actor_id = $1
+                    "#,
+                    actor_id,
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_remote_actor_user")
+                .await
+            })
+            .await?;
+
+        let user = record.map(User::try_from).transpose()?;
+        Ok(user)
+    }
+
+    /// Updates the inboxes of a remote actor,
+    /// creating a local user with `handle` to stand in for it if there is none yet.
+    pub async fn upsert_remote_actor(
+        &self,
+        actor: &RemoteActor,
+        handle: &UserHandle,
+    ) -> Result<User> {
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+
+        let existing = query_scalar!(
+            "
+            UPDATE federation.remote_actors
+            SET inbox = $2,
+                shared_inbox = $3
+            WHERE remote_actors.actor_id = $1
+            RETURNING remote_actors.user_snowflake
+            ",
+            actor.id,
+            actor.inbox,
+            actor.endpoints.shared_inbox,
+        )
+        .fetch_optional(&mut *transaction)
+        .measured(&self.metrics, "upsert_remote_actor.update")
+        .await?;
+
+        let user_snowflake = if let Some(user_snowflake) = existing {
+            user_snowflake
+        } else {
+            let user_snowflake = self.snowflake_generator.generate()?.get().cast_signed();
+
+            query!(
+                "
+                INSERT INTO users.users (user_snowflake, handle)
+                VALUES ($1, $2)
+                ",
+                user_snowflake,
+                handle.get(),
+            )
+            .execute(&mut *transaction)
+            .measured(&self.metrics, "upsert_remote_actor.insert_user")
+            .await?;
+
+            query!(
+                "
+                INSERT INTO federation.remote_actors (user_snowflake, actor_id, inbox, shared_inbox)
+                VALUES ($1, $2, $3, $4)
+                ",
+                user_snowflake,
+                actor.id,
+                actor.inbox,
+                actor.endpoints.shared_inbox,
+            )
+            .execute(&mut *transaction)
+            .measured(&self.metrics, "upsert_remote_actor.insert_actor")
+            .await?;
+
+            user_snowflake
+        };
+
+        let record = query_as!(
+            UserRecord,
+            r#"
+            SELECT
+                users.user_snowflake,
+                users.handle,
+                users.verified_at IS NOT NULL AS "verified!"
+            FROM
+                users.users
+            WHERE
+                users.user_snowflake = $1
+            "#,
+            user_snowflake,
+        )
+        .fetch_one(&mut *transaction)
+        .measured_one(&self.metrics, "upsert_remote_actor.user")
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(record.try_into()?)
+    }
+
+    /// The local user mirroring the AT Protocol account `did`,
+    /// created with `handle` if there is none yet.
+    ///
+    /// Unlike ActivityPub usernames, AT Protocol handles are expected to change,
+    /// so the handle of an existing user is updated.
+    pub async fn upsert_atproto_account(&self, did: &str, handle: &UserHandle) -> Result<User> {
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+
+        let existing = query_scalar!(
+            "
+            SELECT atproto_accounts.user_snowflake
+            FROM federation.atproto_accounts
+            WHERE atproto_accounts.did = $1
+            ",
+            did,
+        )
+        .fetch_optional(&mut *transaction)
+        .measured(&self.metrics, "upsert_atproto_account.existing")
+        .await?;
+
+        let record = if let Some(user_snowflake) = existing {
+            query_as!(
+                UserRecord,
+                r#"
+                UPDATE users.users
+                SET handle = $2::text,
+                    profile_version = users.profile_version + (users.handle != $2::text)::int
+                WHERE users.user_snowflake = $1
+                RETURNING
+                    users.user_snowflake,
+                    users.handle,
+                    users.verified_at IS NOT NULL AS "verified!"
+                "#,
+                user_snowflake,
+                handle.get(),
+            )
+            .fetch_one(&mut *transaction)
+            .measured_one(&self.metrics, "upsert_atproto_account.update_user")
+            .await?
+        } else {
+            let user_snowflake = self.snowflake_generator.generate()?.get().cast_signed();
+
+            let record = query_as!(
+                UserRecord,
+                r#"
+                INSERT INTO users.users (user_snowflake, handle)
+                VALUES ($1, $2)
+                RETURNING
+                    users.user_snowflake,
+                    users.handle,
+                    users.verified_at IS NOT NULL AS "verified!"
+                "#,
+                user_snowflake,
+                handle.get(),
+            )
+            .fetch_one(&mut *transaction)
+            .measured_one(&self.metrics, "upsert_atproto_account.insert_user")
+            .await?;
+
+            query!(
+                "
+                INSERT INTO federation.atproto_accounts (user_snowflake, did)
+                VALUES ($1, $2)
+                ",
+                user_snowflake,
+                did,
+            )
+            .execute(&mut *transaction)
+            .measured(&self.metrics, "upsert_atproto_account.insert_account")
+            .await?;
+
+            record
+        };
+
+        transaction.commit().await?;
+
+        let user = User::try_from(record)?;
+        if let Some(cache) = &self.cache {
+            cache.invalidate_user(user.id).await;
+        }
+
+        Ok(user)
+    }
+
+    /// Whether the user stands in for an actor of another server or mirrors an account of
+    /// another network.
+    pub async fn is_remote_user(&self, user_id: Id<UserMarker>) -> Result<bool> {
+        let is_remote = self
+            .idempotent("is_remote_user", || async move {
+                query_scalar!(
+                    r#"
+                    SELECT EXISTS(
+                        SELECT FROM federation.remote_actors
+                        WHERE remote_actors.user_snowflake = $1
+                    ) OR EXISTS(
+                        SELECT FROM federation.atproto_accounts
+                        WHERE atproto_accounts.user_snowflake = $1
+                    ) as "exists!"
+                    "#,
+                    user_id.snowflake().get().cast_signed(),
+                )
+                .fetch_one(&mut *self.writer().await?)
+                .measured_one(&self.metrics, "is_remote_user")
+                .await
+            })
+            .await?;
+
+        Ok(is_remote)
+    }
+
+    pub async fn fetch_instance_stats(&self) -> Result<InstanceStats> {
+        // The epoch is long past, so this is never before it.
+        let day_ago =
+            StellwerkSnowflake::min_for_time(UtcDateTime::now() - Duration::from_hours(24))
+                .unwrap_or_default()
+                .get()
+                .cast_signed();
+
+        let record = self
+            .idempotent("fetch_instance_stats", || async move {
+                query!(
+                    r#"
+                    SELECT
+                        (SELECT count(*) FROM users.users) as "user_count!",
+                        (
+                            SELECT count(*) FROM users.users
+                            WHERE EXISTS(
+                                SELECT FROM federation.remote_actors
+                                WHERE remote_actors.user_snowflake = users.user_snowflake
+                            ) OR EXISTS(
+                                SELECT FROM federation.atproto_accounts
+                                WHERE atproto_accounts.user_snowflake = users.user_snowflake
+                            )
+                        ) as "remote_user_count!",
+                        (SELECT count(*) FROM posts.posts WHERE posts.deleted_at IS NULL) as "post_count!",
+                        (
+                            SELECT count(*) FROM posts.posts
+                            WHERE posts.post_snowflake >= $1 AND posts.deleted_at IS NULL
+                        ) as "recent_post_count!",
+                        (
+                            SELECT count(*) FROM moderation.reports
+                            WHERE reports.resolved_at IS NULL
+                        ) as "open_report_count!"
+                    "#,
+                    day_ago,
+                )
+                .fetch_one(&mut *self.reader().await?)
+                .measured_one(&self.metrics, "fetch_instance_stats")
+                .await
+            })
+            .await?;
+
+        Ok(InstanceStats {
+            user_count: record.user_count.cast_unsigned(),
+            remote_user_count: record.remote_user_count.cast_unsigned(),
+            post_count: record.post_count.cast_unsigned(),
+            recent_post_count: record.recent_post_count.cast_unsigned(),
+            open_report_count: record.open_report_count.cast_unsigned(),
+        })
+    }
+
+    /// Computed from the posts, users and tokens on every call, so it is not meant to be polled.
+    pub async fn fetch_activity_stats(&self) -> Result<ActivityStats> {
+        let now_utc = UtcDateTime::now();
+        let now_primitive = PrimitiveDateTime::new(now_utc.date(), now_utc.time());
+
+        let day = self
+            .fetch_activity_window(now_utc - Duration::from_hours(24))
+            .await?;
+        let week = self
+            .fetch_activity_window(now_utc - Duration::from_hours(7 * 24))
+            .await?;
+
+        let record = self
+            .idempotent("fetch_activity_stats", || async move {
+                query!(
+                    r#"
+                    SELECT
+                        (
+                            SELECT count(*) FROM auth.auth_tokens
+                            WHERE auth_tokens.expires_after_seconds IS NULL
+                               OR auth_tokens.created_at
+                                      + make_interval(secs := auth_tokens.expires_after_seconds)
+                                      >= $1
+                        ) as "active_token_count!",
+                        (SELECT count(*) FROM auth.auth_tokens_archive) as "archived_token_count!"
+                    "#,
+                    now_primitive,
+                )
+                .fetch_one(&mut *self.reader().await?)
+                .measured_one(&self.metrics, "fetch_activity_stats")
+                .await
+            })
+            .await?;
+
+        Ok(ActivityStats {
+            day,
+            week,
+            active_token_count: record.active_token_count.cast_unsigned(),
+            archived_token_count: record.archived_token_count.cast_unsigned(),
+        })
+    }
+
+    /// Marks the users active on the day starting at `day_start`, and adds those who were not
+    /// active on it yet to its [`AnalyticsMetric::DailyActiveUsers`] rollup, so that every server
+    /// can report the users it saw. Returns how many users were added.
+    pub async fn record_active_users(
+        &self,
+        user_ids: &[Id<UserMarker>],
+        day_start: UtcDateTime,
+    ) -> Result<u64> {
+        let snowflakes: &[_] = &user_ids
+            .iter()
+            .map(|id| id.snowflake().get().cast_signed())
+            .collect::<Vec<_>>();
+
+        let mut connection = self.writer().await?;
+        let mut transaction = connection.begin().await?;
+
+        // Later days are kept, in case this server reports late.
+        let newly_active = query!(
+            "
+            UPDATE users.users
+            SET active_on = $2
+            WHERE users.user_snowflake = ANY($1)
+              AND (users.active_on IS NULL OR users.active_on < $2)
+            ",
+            snowflakes,
+            day_start.date(),
+        )
+        .execute(&mut *transaction)
+        .measured(&self.metrics, "record_active_users")
+        .await?
+        .rows_affected();
+
+        if newly_active > 0 {
+            self.add_to_rollup(
+                &mut transaction,
+                AnalyticsMetric::DailyActiveUsers,
+                day_start,
+                newly_active,
+                newly_active,
+            )
+            .await?;
+        }
+        transaction.commit().await?;
+
+        Ok(newly_active)
+    }
+
+    /// Adds `total` and `samples` to the rollup of `metric` in the bucket starting at
+    /// `bucket_start`, which is not idempotent, so it is not retried.
+    pub async fn record_analytics(
+        &self,
+        metric: AnalyticsMetric,
+        bucket_start: UtcDateTime,
+        total: u64,
+        samples: u64,
+    ) -> Result<()> {
+        self.add_to_rollup(
+            &mut *self.writer().await?,
+            metric,
+            bucket_start,
+            total,
+            samples,
+        )
+        .await
+    }
+
+    async fn add_to_rollup(
+        &self,
+        connection: &mut PgConnection,
+        metric: AnalyticsMetric,
+        bucket_start: UtcDateTime,
+        total: u64,
+        samples: u64,
+    ) -> Result<()> {
+        let bucket_start = PrimitiveDateTime::new(bucket_start.date(), bucket_start.time());
+
+        query!(
+            "
+            INSERT INTO analytics.rollups (metric, bucket_start, total, samples)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (metric, bucket_start) DO UPDATE
+                SET total = rollups.total + excluded.total,
+                    samples = rollups.samples + excluded.samples
+            ",
+            metric.as_str(),
+            bucket_start,
+            total.cast_signed(),
+            samples.cast_signed(),
+        )
+        .execute(connection)
+        .measured(&self.metrics, "add_to_rollup")
+        .await?;
+
+        Ok(())
+    }
+
+    /// The rollups of buckets starting at or after `since`, of `metric` or all metrics,
+    /// by metric and oldest first.
+    pub async fn fetch_analytics(
+        &self,
+        metric: Option<AnalyticsMetric>,
+        since: UtcDateTime,
+    ) -> Result<Vec<AnalyticsRollup>> {
+        let since = PrimitiveDateTime::new(since.date(), since.time());
+        let metric = metric.map(AnalyticsMetric::as_str);
+
+        let records = self
+            .idempotent("fetch_analytics", || async move {
+                query_as!(
+                    AnalyticsRollupRecord,
+                    "
+                    SELECT rollups.metric, rollups.bucket_start, rollups.total, rollups.samples
+                    FROM analytics.rollups
+                    WHERE rollups.bucket_start >= $1
+                      AND ($2::text IS NULL OR rollups.metric = $2)
+                    ORDER BY rollups.metric, rollups.bucket_start
+                    ",
+                    since,
+                    metric,
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_analytics")
+                .await
+            })
+            .await?;
 
-pub type Result<T, E = DbError> = std::result::Result<T, E>;
+        let rollups = records
+            .into_iter()
+            .map(AnalyticsRollup::try_from)
+            .collect::<Result<_, _>>()?;
 
-#[derive(Debug, Error)]
-pub enum DbError {
-    #[error("Database migration failed: {0}")]
-    Migrate(#[from] MigrateError),
-    #[error("An object in the database was invalid: {0}")]
-    Data(#[from] ModelValidationError),
-    #[error(transparent)]
-    Sqlx(#[from] sqlx::Error),
-}
+        Ok(rollups)
+    }
 
-#[derive(Debug)]
-pub struct DbClient {
-    pool: PgPool,
-    snowflake_generator: Mutex<StellwerkSnowflakeGenerator>,
-}
+    async fn fetch_activity_window(&self, since: UtcDateTime) -> Result<ActivityWindow> {
+        // The epoch is long past, so this is never before it.
+        let since_snowflake = StellwerkSnowflake::min_for_time(since)
+            .unwrap_or_default()
+            .get()
+            .cast_signed();
+        let since_primitive = PrimitiveDateTime::new(since.date(), since.time());
 
-impl DbClient {
-    pub async fn connect_and_migrate(
-        url: &str,
-        worker_id: WorkerId,
-        process_id: ProcessId,
-    ) -> Result<Self> {
-        let pool = PgPool::connect(url).await?;
-        migrate!().run(&pool).await?;
+        let record = self
+            .idempotent("fetch_activity_window", || async move {
+                query!(
+                    r#"
+                    SELECT
+                        (
+                            SELECT count(*) FROM users.users
+                            WHERE users.user_snowflake >= $1
+                              AND NOT EXISTS(
+                                  SELECT FROM federation.remote_actors
+                                  WHERE remote_actors.user_snowflake = users.user_snowflake
+                              )
+                              AND NOT EXISTS(
+                                  SELECT FROM federation.atproto_accounts
+                                  WHERE atproto_accounts.user_snowflake = users.user_snowflake
+                              )
+                        ) as "signup_count!",
+                        (
+                            SELECT count(*) FROM (
+                                SELECT posts.user_snowflake FROM posts.posts
+                                WHERE posts.post_snowflake >= $1
+                                UNION
+                                SELECT auth_tokens.user_snowflake FROM auth.auth_tokens
+                                WHERE auth_tokens.created_at >= $2
+                                UNION
+                                SELECT auth_tokens_archive.user_snowflake
+                                FROM auth.auth_tokens_archive
+                                WHERE auth_tokens_archive.created_at >= $2
+                            ) as active_users
+                        ) as "active_user_count!",
+                        (
+                            SELECT count(*) FROM posts.posts
+                            WHERE posts.post_snowflake >= $1
+                        ) as "post_count!",
+                        (
+                            SELECT count(*) FROM auth.auth_tokens
+                            WHERE auth_tokens.created_at >= $2
+                        ) + (
+                            SELECT count(*) FROM auth.auth_tokens_archive
+                            WHERE auth_tokens_archive.created_at >= $2
+                        ) as "created_token_count!"
+                    "#,
+                    since_snowflake,
+                    since_primitive,
+                )
+                .fetch_one(&mut *self.reader().await?)
+                .measured_one(&self.metrics, "fetch_activity_window")
+                .await
+            })
+            .await?;
 
-        Ok(Self::new(pool, worker_id, process_id))
+        Ok(ActivityWindow {
+            signup_count: record.signup_count.cast_unsigned(),
+            active_user_count: record.active_user_count.cast_unsigned(),
+            post_count: record.post_count.cast_unsigned(),
+            created_token_count: record.created_token_count.cast_unsigned(),
+        })
     }
 
-    #[must_use]
-    pub fn new(pool: PgPool, worker_id: WorkerId, process_id: ProcessId) -> Self {
-        let snowflake_generator =
-            Mutex::new(StellwerkSnowflakeGenerator::new(worker_id, process_id));
+    /// The inboxes of the remote followers of `user_id`, with shared inboxes deduplicated.
+    pub async fn fetch_remote_follower_inboxes(
+        &self,
+        user_id: Id<UserMarker>,
+    ) -> Result<Vec<String>> {
+        let inboxes = self
+            .idempotent("fetch_remote_follower_inboxes", || async move {
+                query_scalar!(
+                    r#"
+                    SELECT DISTINCT
+                        COALESCE(remote_actors.shared_inbox, remote_actors.inbox) as "inbox!"
+                    FROM
+                        users.follows
+                        JOIN federation.remote_actors
+                            ON remote_actors.user_snowflake = follows.follower_snowflake
+                    WHERE
+                        follows.followed_snowflake = $1
+                    "#,
+                    user_id.snowflake().get().cast_signed(),
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_remote_follower_inboxes")
+                .await
+            })
+            .await?;
 
-        Self {
-            pool,
-            snowflake_generator,
-        }
+        Ok(inboxes)
     }
 
-    pub async fn fetch_user(&self, user_id: Id<UserMarker>) -> Result<Option<User>> {
+    /// Registers a webhook, unless the user already has `max_webhooks`.
+    pub async fn create_webhook(
+        &self,
+        user_id: Id<UserMarker>,
+        webhook: &CreateWebhook,
+        secret: &WebhookSecret,
+        max_webhooks: usize,
+    ) -> Result<Option<Webhook>> {
+        let webhook_snowflake = self.snowflake_generator.generate()?;
+        let events: Vec<_> = webhook
+            .events
+            .iter()
+            .map(|event| event.as_str().to_owned())
+            .collect();
+
         let record = query_as!(
-            UserRecord,
+            WebhookRecord,
             "
-            SELECT
-                users.user_snowflake,
-                users.handle
-            FROM
-                users.users
-            WHERE
-                users.user_snowflake = $1
+            INSERT INTO webhooks.webhooks
+                (webhook_snowflake, user_snowflake, url, secret, events)
+            SELECT $1, $2, $3, $4, $5
+            WHERE (
+                SELECT COUNT(*)
+                FROM webhooks.webhooks
+                WHERE webhooks.user_snowflake = $2
+            ) < $6
+            RETURNING
+                webhooks.webhook_snowflake,
+                webhooks.url,
+                webhooks.events,
+                webhooks.disabled_at
             ",
+            webhook_snowflake.get().cast_signed(),
             user_id.snowflake().get().cast_signed(),
+            webhook.url.get(),
+            secret.0,
+            &events,
+            i64::try_from(max_webhooks).unwrap_or(i64::MAX),
         )
-        .fetch_optional(&self.pool)
+        .fetch_optional(&mut *self.writer().await?)
+        .measured(&self.metrics, "create_webhook")
         .await?;
 
-        let user = record.map(User::try_from).transpose()?;
-        Ok(user)
+        Ok(record.map(Webhook::try_from).transpose()?)
     }
 
-    pub async fn fetch_user_by_handle(&self, handle: &UserHandle) -> Result<Option<User>> {
-        let record = query_as!(
-            UserRecord,
+    /// Returns the webhooks of the user, newest first.
+    pub async fn fetch_webhooks(&self, user_id: Id<UserMarker>) -> Result<Vec<Webhook>> {
+        let records = self
+            .idempotent("fetch_webhooks", || async move {
+                query_as!(
+                    WebhookRecord,
+                    "
+                    SELECT
+                        webhooks.webhook_snowflake,
+                        webhooks.url,
+                        webhooks.events,
+                        webhooks.disabled_at
+                    FROM
+                        webhooks.webhooks
+                    WHERE
+                        webhooks.user_snowflake = $1
+                    ORDER BY
+                        webhooks.webhook_snowflake DESC
+                    ",
+                    user_id.snowflake().get().cast_signed(),
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_webhooks")
+                .await
+            })
+            .await?;
+
+        let webhooks = records
+            .into_iter()
+            .map(Webhook::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(webhooks)
+    }
+
+    /// Deletes the webhook. Deliveries already queued for it are dropped.
+    /// Returns `false` if the user has no webhook with this id.
+    pub async fn delete_webhook(
+        &self,
+        user_id: Id<UserMarker>,
+        webhook_id: Id<WebhookMarker>,
+    ) -> Result<bool> {
+        let rows_affected = query!(
             "
-            SELECT
-                users.user_snowflake,
-                users.handle
-            FROM
-                users.users
-            WHERE
-                users.handle = $1
+            DELETE FROM webhooks.webhooks
+            WHERE webhooks.webhook_snowflake = $1 AND webhooks.user_snowflake = $2
             ",
-            handle.get(),
+            webhook_id.snowflake().get().cast_signed(),
+            user_id.snowflake().get().cast_signed(),
         )
-        .fetch_optional(&self.pool)
-        .await?;
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "delete_webhook")
+        .await?
+        .rows_affected();
 
-        let user = record.map(User::try_from).transpose()?;
-        Ok(user)
+        Ok(rows_affected != 0)
     }
 
-    pub async fn fetch_user_posts(
+    /// Enables a disabled webhook again, and resets its failures.
+    /// Returns `false` if the user has no webhook with this id.
+    pub async fn enable_webhook(
         &self,
         user_id: Id<UserMarker>,
-    ) -> Result<Option<Vec<PartialPost>>> {
-        let mut transaction = self.pool.begin().await?;
-
-        let user_exists = query_scalar!(
-            r#"
-            SELECT count(1) as "c!"
-            FROM users.users
-            WHERE users.user_snowflake = $1
-            "#,
+        webhook_id: Id<WebhookMarker>,
+    ) -> Result<bool> {
+        let rows_affected = query!(
+            "
+            UPDATE webhooks.webhooks
+            SET disabled_at = NULL,
+                failures = 0
+            WHERE webhooks.webhook_snowflake = $1 AND webhooks.user_snowflake = $2
+            ",
+            webhook_id.snowflake().get().cast_signed(),
             user_id.snowflake().get().cast_signed(),
         )
-        .fetch_one(&mut *transaction)
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "enable_webhook")
         .await?
-            != 0;
+        .rows_affected();
 
-        if !user_exists {
-            return Ok(None);
-        }
+        Ok(rows_affected != 0)
+    }
 
-        let records = query_as!(
-            PartialPostRecord,
+    /// Queues the payload for every enabled webhook subscribed to its kind, in the transaction of
+    /// the change it is about, so that it is delivered if and only if the change is committed.
+    ///
+    /// `user_id` is the mentioned user for mentions, and the author for followed posts.
+    /// The deliveries are not attempted before `run_at`.
+    async fn enqueue_webhook_deliveries(
+        &self,
+        connection: &mut PgConnection,
+        user_id: Id<UserMarker>,
+        payload: WebhookPayload,
+        run_at: UtcDateTime,
+    ) -> Result<()> {
+        let webhook_snowflakes = query_scalar!(
             "
-            SELECT
-                posts.post_snowflake,
-                posts.content
-            FROM
-                posts.posts
+            SELECT webhooks.webhook_snowflake
+            FROM webhooks.webhooks
             WHERE
-                posts.user_snowflake = $1
+                webhooks.disabled_at IS NULL
+                AND $2 = ANY(webhooks.events)
+                AND CASE $2
+                    WHEN 'mention' THEN webhooks.user_snowflake = $1
+                    ELSE EXISTS(
+                        SELECT FROM users.follows
+                        WHERE
+                            follows.follower_snowflake = webhooks.user_snowflake
+                            AND follows.followed_snowflake = $1
+                    )
+                END
             ",
             user_id.snowflake().get().cast_signed(),
+            payload.kind().as_str(),
         )
-        .fetch_all(&mut *transaction)
+        .fetch_all(&mut *connection)
+        .measured(&self.metrics, "enqueue_webhook_deliveries.webhooks")
+        .await?;
+
+        let payload = serde_json::to_string(&payload).expect("payloads serialize infallibly");
+        let jobs: Vec<_> = webhook_snowflakes
+            .into_iter()
+            .map(|snowflake| JobPayload::WebhookDelivery {
+                webhook: snowflake.cast_unsigned().into(),
+                payload: payload.clone(),
+            })
+            .collect();
+
+        self.insert_jobs(connection, &jobs, run_at).await
+    }
+
+    /// The URL and secret of the webhook, or `None` if it was deleted or is disabled.
+    pub async fn fetch_webhook_target(
+        &self,
+        webhook_id: Id<WebhookMarker>,
+    ) -> Result<Option<WebhookTarget>> {
+        let record = self
+            .idempotent("fetch_webhook_target", || async move {
+                query_as!(
+                    WebhookTargetRecord,
+                    "
+                    SELECT webhooks.webhook_snowflake, webhooks.url, webhooks.secret
+                    FROM webhooks.webhooks
+                    WHERE webhooks.webhook_snowflake = $1 AND webhooks.disabled_at IS NULL
+                    ",
+                    webhook_id.snowflake().get().cast_signed(),
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_webhook_target")
+                .await
+            })
+            .await?;
+
+        Ok(record.map(WebhookTarget::from))
+    }
+
+    /// Records a successful request to the webhook, resetting its failures.
+    pub async fn reset_webhook_failures(&self, webhook_id: Id<WebhookMarker>) -> Result<()> {
+        self.idempotent("reset_webhook_failures", || async move {
+            query!(
+                "
+                UPDATE webhooks.webhooks
+                SET failures = 0
+                WHERE webhooks.webhook_snowflake = $1 AND webhooks.failures != 0
+                ",
+                webhook_id.snowflake().get().cast_signed(),
+            )
+            .execute(&mut *self.writer().await?)
+            .measured(&self.metrics, "reset_webhook_failures")
+            .await
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Records a failed request to the webhook, which is disabled once `disable_after` requests
+    /// failed in a row. Returns whether it is disabled.
+    pub async fn record_webhook_failure(
+        &self,
+        webhook_id: Id<WebhookMarker>,
+        now: UtcDateTime,
+        disable_after: u32,
+    ) -> Result<bool> {
+        let now = PrimitiveDateTime::new(now.date(), now.time());
+
+        let disabled = query_scalar!(
+            r#"
+            UPDATE webhooks.webhooks
+            SET failures = webhooks.failures + 1,
+                disabled_at = CASE
+                    WHEN webhooks.failures + 1 >= $3 THEN COALESCE(webhooks.disabled_at, $2)
+                    ELSE webhooks.disabled_at
+                END
+            WHERE webhooks.webhook_snowflake = $1
+            RETURNING webhooks.disabled_at IS NOT NULL AS "disabled!"
+            "#,
+            webhook_id.snowflake().get().cast_signed(),
+            now,
+            i32::try_from(disable_after).unwrap_or(i32::MAX),
+        )
+        .fetch_optional(&mut *self.writer().await?)
+        .measured(&self.metrics, "record_webhook_failure")
         .await?;
 
+        Ok(disabled.unwrap_or(false))
+    }
+
+    /// Queues updating the document in the external search engine in the transaction of the
+    /// change, if [`DbClient::with_search_indexing`] was used.
+    async fn enqueue_search_indexing(
+        &self,
+        connection: &mut PgConnection,
+        document: SearchDocument,
+    ) -> Result<()> {
+        if !self.index_search {
+            return Ok(());
+        }
+
+        self.insert_jobs(
+            connection,
+            &[JobPayload::SearchIndexing { document }],
+            UtcDateTime::now(),
+        )
+        .await
+    }
+
+    /// The post as external search engines index it, or `None` if it does not exist or is deleted.
+    pub async fn fetch_indexed_post(&self, post_id: Id<PostMarker>) -> Result<Option<IndexedPost>> {
+        let record = self
+            .idempotent("fetch_indexed_post", || async move {
+                query_as!(
+                    IndexedPostRecord,
+                    "
+                    SELECT posts.post_snowflake, posts.tenant, posts.user_snowflake, posts.content
+                    FROM posts.posts
+                    WHERE posts.post_snowflake = $1 AND posts.deleted_at IS NULL
+                    ",
+                    post_id.snowflake().get().cast_signed(),
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_indexed_post")
+                .await
+            })
+            .await?;
+
+        Ok(record.map(IndexedPost::try_from).transpose()?)
+    }
+
+    /// The user as external search engines index them, or `None` if they do not exist.
+    pub async fn fetch_indexed_user(&self, user_id: Id<UserMarker>) -> Result<Option<IndexedUser>> {
+        let record = self
+            .idempotent("fetch_indexed_user", || async move {
+                query_as!(
+                    IndexedUserRecord,
+                    "
+                    SELECT users.user_snowflake, users.tenant, users.handle
+                    FROM users.users
+                    WHERE users.user_snowflake = $1
+                    ",
+                    user_id.snowflake().get().cast_signed(),
+                )
+                .fetch_optional(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_indexed_user")
+                .await
+            })
+            .await?;
+
+        Ok(record.map(IndexedUser::try_from).transpose()?)
+    }
+
+    /// Full-text search of the posts of the tenant, best matches first, and newest first among
+    /// equally good ones. The query supports quotes, `or`, and `-`, like web search engines.
+    pub async fn search_post_ids(
+        &self,
+        tenant: &TenantId,
+        query: &SearchQuery,
+        limit: u32,
+    ) -> Result<Vec<Id<PostMarker>>> {
+        let snowflakes = self
+            .idempotent("search_post_ids", || async move {
+                query_scalar!(
+                    "
+                    SELECT posts.post_snowflake
+                    FROM posts.posts, websearch_to_tsquery('simple', $2) AS query
+                    WHERE
+                        posts.tenant = $1
+                        AND posts.deleted_at IS NULL
+                        AND posts.search_vector @@ query
+                    ORDER BY
+                        ts_rank(posts.search_vector, query) DESC,
+                        posts.post_snowflake DESC
+                    LIMIT $3
+                    ",
+                    tenant.get(),
+                    query.get(),
+                    i64::from(limit),
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "search_post_ids")
+                .await
+            })
+            .await?;
+
+        Ok(snowflakes
+            .into_iter()
+            .map(|snowflake| snowflake.cast_unsigned().into())
+            .collect())
+    }
+
+    /// The users of the tenant whose handle starts with the query, shortest handles first.
+    pub async fn search_user_ids(
+        &self,
+        tenant: &TenantId,
+        query: &SearchQuery,
+        limit: u32,
+    ) -> Result<Vec<Id<UserMarker>>> {
+        let pattern = &format!(
+            "{}%",
+            query
+                .get()
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+
+        let snowflakes = self
+            .idempotent("search_user_ids", || async move {
+                query_scalar!(
+                    "
+                    SELECT users.user_snowflake
+                    FROM users.users
+                    WHERE users.tenant = $1 AND users.handle LIKE $2
+                    ORDER BY length(users.handle), users.handle
+                    LIMIT $3
+                    ",
+                    tenant.get(),
+                    pattern,
+                    i64::from(limit),
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "search_user_ids")
+                .await
+            })
+            .await?;
+
+        Ok(snowflakes
+            .into_iter()
+            .map(|snowflake| snowflake.cast_unsigned().into())
+            .collect())
+    }
+
+    /// Those of `post_ids` that exist and are not deleted, in no particular order.
+    pub async fn fetch_posts(&self, post_ids: &[Id<PostMarker>]) -> Result<Vec<Post>> {
+        let snowflakes: &[_] = &post_ids
+            .iter()
+            .map(|id| id.snowflake().get().cast_signed())
+            .collect::<Vec<_>>();
+
+        let records = self
+            .idempotent("fetch_posts", || async move {
+                query_as!(
+                    FullPostRecord,
+                    r#"
+                    SELECT
+                        posts.post_snowflake,
+                        posts.content,
+                        posts.pinned_at IS NOT NULL as "pinned!",
+                        users.user_snowflake,
+                        users.handle,
+                        users.verified_at IS NOT NULL AS "verified!"
+                    FROM
+                        posts.posts NATURAL JOIN users.users
+                    WHERE
+                        posts.post_snowflake = ANY($1)
+                        AND posts.deleted_at IS NULL
+                    "#,
+                    snowflakes,
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_posts")
+                .await
+            })
+            .await?;
+
         let posts = records
             .into_iter()
-            .map(PartialPost::try_from)
+            .map(Post::try_from)
             .collect::<Result<_, _>>()?;
 
-        Ok(Some(posts))
+        Ok(posts)
     }
 
-    pub async fn create_user(&self, user: &CreateUser) -> Result<Id<UserMarker>> {
-        let user_snowflake = self.snowflake_generator.lock().generate();
+    /// Queues jobs to be run at `run_at`.
+    pub async fn enqueue_jobs(&self, payloads: &[JobPayload], run_at: UtcDateTime) -> Result<()> {
+        let mut connection = self.writer().await?;
 
-        let returned_snowflake = query_scalar!(
+        self.insert_jobs(&mut connection, payloads, run_at).await
+    }
+
+    async fn insert_jobs(
+        &self,
+        connection: &mut PgConnection,
+        payloads: &[JobPayload],
+        run_at: UtcDateTime,
+    ) -> Result<()> {
+        if payloads.is_empty() {
+            return Ok(());
+        }
+
+        let run_at = PrimitiveDateTime::new(run_at.date(), run_at.time());
+        let snowflakes: Vec<_> = payloads
+            .iter()
+            .map(|_| {
+                self.snowflake_generator
+                    .generate()
+                    .map(|snowflake| snowflake.get().cast_signed())
+            })
+            .collect::<Result<_, _>>()?;
+        let kinds: Vec<_> = payloads
+            .iter()
+            .map(|payload| payload.kind().as_str())
+            .collect();
+        let payloads: Vec<_> = payloads
+            .iter()
+            .map(|payload| serde_json::to_string(payload).expect("payloads serialize infallibly"))
+            .collect();
+
+        query!(
             "
-            INSERT INTO users.users (user_snowflake, handle)
-            VALUES ($1, $2)
-            RETURNING users.user_snowflake
+            INSERT INTO jobs.jobs (job_snowflake, kind, payload, run_at)
+            SELECT job_snowflake, kind, payload, $4
+            FROM UNNEST($1::bigint[], $2::varchar[], $3::text[]) as new(job_snowflake, kind, payload)
             ",
-            user_snowflake.get().cast_signed(),
-            user.handle.get(),
+            &snowflakes,
+            &kinds as &[&str],
+            &payloads,
+            run_at,
         )
-        .fetch_one(&self.pool)
+        .execute(&mut *connection)
+        .measured(&self.metrics, "insert_jobs")
         .await?;
 
-        let returned_id: Id<UserMarker> = returned_snowflake.cast_unsigned().into();
-        debug_assert_eq!(returned_id.snowflake(), user_snowflake);
+        Ok(())
+    }
 
-        Ok(returned_id)
+    /// Queues a recurring job to be run at `run_at`, unless one of its kind is already queued.
+    pub async fn schedule_recurring_job(
+        &self,
+        payload: &JobPayload,
+        run_at: UtcDateTime,
+    ) -> Result<()> {
+        let run_at = PrimitiveDateTime::new(run_at.date(), run_at.time());
+        let snowflake = self.snowflake_generator.generate()?;
+
+        query!(
+            "
+            INSERT INTO jobs.jobs (job_snowflake, kind, payload, recurring, run_at)
+            VALUES ($1, $2, $3, TRUE, $4)
+            ON CONFLICT (kind) WHERE recurring DO NOTHING
+            ",
+            snowflake.get().cast_signed(),
+            payload.kind().as_str(),
+            serde_json::to_string(payload).expect("payloads serialize infallibly"),
+            run_at,
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "schedule_recurring_job")
+        .await?;
+
+        Ok(())
     }
 
-    pub async fn fetch_post(&self, post_id: Id<PostMarker>) -> Result<Option<Post>> {
-        let record = query_as!(
-            FullPostRecord,
+    /// Returns up to `limit` jobs of `kind` that are due at `now`, and hides them until
+    /// `visible_at` so that other processes do not run them concurrently.
+    /// Jobs that are neither finished nor failed by then are run again.
+    pub async fn claim_jobs(
+        &self,
+        kind: JobKind,
+        now: UtcDateTime,
+        visible_at: UtcDateTime,
+        limit: u32,
+    ) -> Result<Vec<Job>> {
+        let now = PrimitiveDateTime::new(now.date(), now.time());
+        let visible_at = PrimitiveDateTime::new(visible_at.date(), visible_at.time());
+
+        let records = query_as!(
+            JobRecord,
             "
-            SELECT
-                posts.post_snowflake,
-                posts.content,
-                users.user_snowflake,
-                users.handle
-            FROM
-                posts.posts NATURAL JOIN users.users
-            WHERE
-                posts.post_snowflake = $1
+            UPDATE jobs.jobs
+            SET run_at = $3
+            WHERE jobs.job_snowflake IN (
+                SELECT job_snowflake
+                FROM jobs.jobs
+                WHERE
+                    jobs.kind = $1
+                    AND jobs.dead_at IS NULL
+                    AND jobs.run_at <= $2
+                ORDER BY jobs.run_at
+                LIMIT $4
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING jobs.job_snowflake, jobs.payload, jobs.attempts
             ",
-            post_id.snowflake().get().cast_signed(),
+            kind.as_str(),
+            now,
+            visible_at,
+            i64::from(limit),
         )
-        .fetch_optional(&self.pool)
+        .fetch_all(&mut *self.writer().await?)
+        .measured(&self.metrics, "claim_jobs")
         .await?;
 
-        let post = record.map(Post::try_from).transpose()?;
-        Ok(post)
+        let jobs = records
+            .into_iter()
+            .map(Job::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(jobs)
     }
 
-    pub async fn create_post(&self, post: &CreatePost) -> Result<Id<PostMarker>> {
-        let post_snowflake = self.snowflake_generator.lock().generate();
+    /// Removes a job that succeeded.
+    pub async fn finish_job(&self, job_id: Id<JobMarker>) -> Result<()> {
+        self.idempotent("finish_job", || async move {
+            query!(
+                "
+                DELETE FROM jobs.jobs
+                WHERE jobs.job_snowflake = $1
+                ",
+                job_id.snowflake().get().cast_signed(),
+            )
+            .execute(&mut *self.writer().await?)
+            .measured(&self.metrics, "finish_job")
+            .await
+        })
+        .await?;
 
-        let returned_snowflake = query_scalar!(
+        Ok(())
+    }
+
+    /// Schedules the next run of a recurring job that succeeded.
+    pub async fn reschedule_job(&self, job_id: Id<JobMarker>, run_at: UtcDateTime) -> Result<()> {
+        let run_at = PrimitiveDateTime::new(run_at.date(), run_at.time());
+
+        self.idempotent("reschedule_job", || async move {
+            query!(
+                "
+                UPDATE jobs.jobs
+                SET attempts = 0,
+                    last_error = NULL,
+                    run_at = $2
+                WHERE jobs.job_snowflake = $1
+                ",
+                job_id.snowflake().get().cast_signed(),
+                run_at,
+            )
+            .execute(&mut *self.writer().await?)
+            .measured(&self.metrics, "reschedule_job")
+            .await
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Records a failed attempt and schedules the next one.
+    pub async fn retry_job(
+        &self,
+        job_id: Id<JobMarker>,
+        error: &str,
+        run_at: UtcDateTime,
+    ) -> Result<()> {
+        let run_at = PrimitiveDateTime::new(run_at.date(), run_at.time());
+
+        query!(
             "
-            INSERT INTO posts.posts (post_snowflake, content, user_snowflake)
-            VALUES ($1, $2, $3)
-            RETURNING posts.post_snowflake
+            UPDATE jobs.jobs
+            SET attempts = jobs.attempts + 1,
+                last_error = $2,
+                run_at = $3
+            WHERE jobs.job_snowflake = $1
             ",
-            post_snowflake.get().cast_signed(),
-            post.content,
-            post.author.snowflake().get().cast_signed(),
+            job_id.snowflake().get().cast_signed(),
+            error,
+            run_at,
         )
-        .fetch_one(&self.pool)
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "retry_job")
         .await?;
 
-        Ok(returned_snowflake.cast_unsigned().into())
+        Ok(())
     }
 
-    pub async fn fetch_auth(&self, token_hash: &AuthTokenHash) -> Result<Option<Authentication>> {
-        let record = query_as!(
-            AuthenticationRecord,
+    /// Records a failed attempt and gives up on the job, keeping it for inspection.
+    pub async fn dead_letter_job(
+        &self,
+        job_id: Id<JobMarker>,
+        error: &str,
+        now: UtcDateTime,
+    ) -> Result<()> {
+        let now = PrimitiveDateTime::new(now.date(), now.time());
+
+        query!(
             "
-            SELECT
-                auth_tokens.user_snowflake,
-                auth_tokens.token_hash,
-                auth_tokens.created_at,
-                auth_tokens.expires_after_seconds
-            FROM
-                auth.auth_tokens
-            WHERE
-                auth_tokens.token_hash = $1
+            UPDATE jobs.jobs
+            SET attempts = jobs.attempts + 1,
+                last_error = $2,
+                dead_at = $3
+            WHERE jobs.job_snowflake = $1
             ",
-            &token_hash.0,
+            job_id.snowflake().get().cast_signed(),
+            error,
+            now,
         )
-        .fetch_optional(&self.pool)
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "dead_letter_job")
         .await?;
 
-        let authentication = record.map(Authentication::try_from).transpose()?;
-        Ok(authentication)
+        Ok(())
     }
 
-    /// Returns number of affected rows
-    pub async fn drop_expired_tokens(&self) -> Result<u64> {
-        let now_utc = UtcDateTime::now();
-        let now_primitive = PrimitiveDateTime::new(now_utc.date(), now_utc.time());
+    /// Returns dead-lettered jobs, newest first.
+    /// `max_id` and `since_id` are exclusive bounds.
+    pub async fn fetch_dead_jobs(
+        &self,
+        max_id: Option<Id<JobMarker>>,
+        since_id: Option<Id<JobMarker>>,
+        limit: u32,
+    ) -> Result<Vec<DeadJob>> {
+        let records = self
+            .idempotent("fetch_dead_jobs", || async move {
+                query_as!(
+                    DeadJobRecord,
+                    r#"
+                    SELECT
+                        jobs.job_snowflake,
+                        jobs.payload,
+                        jobs.attempts,
+                        jobs.last_error,
+                        jobs.dead_at as "dead_at!"
+                    FROM
+                        jobs.jobs
+                    WHERE
+                        jobs.dead_at IS NOT NULL
+                        AND ($1::bigint IS NULL OR jobs.job_snowflake < $1)
+                        AND ($2::bigint IS NULL OR jobs.job_snowflake > $2)
+                    ORDER BY
+                        jobs.job_snowflake DESC
+                    LIMIT $3
+                    "#,
+                    max_id.map(|id| id.snowflake().get().cast_signed()),
+                    since_id.map(|id| id.snowflake().get().cast_signed()),
+                    i64::from(limit),
+                )
+                .fetch_all(&mut *self.reader().await?)
+                .measured(&self.metrics, "fetch_dead_jobs")
+                .await
+            })
+            .await?;
+
+        let jobs = records
+            .into_iter()
+            .map(DeadJob::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(jobs)
+    }
+
+    /// Queues a dead-lettered job again, to be run at `now` with all its attempts.
+    /// Returns `false` if there is no dead-lettered job with this id.
+    pub async fn retry_dead_job(&self, job_id: Id<JobMarker>, now: UtcDateTime) -> Result<bool> {
+        let now = PrimitiveDateTime::new(now.date(), now.time());
 
         let rows_affected = query!(
             "
-            DELETE FROM auth.auth_tokens
-            WHERE auth_tokens.created_at
-                      + make_interval(secs := auth_tokens.expires_after_seconds)
-                      < $1
+            UPDATE jobs.jobs
+            SET attempts = 0,
+                run_at = $2,
+                dead_at = NULL
+            WHERE jobs.job_snowflake = $1 AND jobs.dead_at IS NOT NULL
             ",
-            now_primitive,
+            job_id.snowflake().get().cast_signed(),
+            now,
         )
-        .execute(&self.pool)
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "retry_dead_job")
         .await?
         .rows_affected();
 
-        Ok(rows_affected)
+        Ok(rows_affected != 0)
+    }
+
+    /// Returns `false` if there is no dead-lettered job with this id.
+    pub async fn delete_dead_job(&self, job_id: Id<JobMarker>) -> Result<bool> {
+        let rows_affected = query!(
+            "
+            DELETE FROM jobs.jobs
+            WHERE jobs.job_snowflake = $1 AND jobs.dead_at IS NOT NULL
+            ",
+            job_id.snowflake().get().cast_signed(),
+        )
+        .execute(&mut *self.writer().await?)
+        .measured(&self.metrics, "delete_dead_job")
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected != 0)
+    }
+
+    /// Atomically claims a worker and process ID pair no other server holds a lease on,
+    /// so that servers do not have to be assigned one.
+    /// The lease has to be [renewed](DbClient::renew_worker_lease) before `lease_until`.
+    /// Returns `None` if all pairs are leased.
+    pub async fn lease_worker_id(
+        &self,
+        now: UtcDateTime,
+        lease_until: UtcDateTime,
+    ) -> Result<Option<WorkerLease>> {
+        let now = PrimitiveDateTime::new(now.date(), now.time());
+        let lease_until = PrimitiveDateTime::new(lease_until.date(), lease_until.time());
+        let token = rand::random::<i64>();
+
+        let record = query!(
+            "
+            UPDATE cluster.worker_registry
+            SET lease_token = $1,
+                leased_until = $3
+            WHERE (worker_registry.worker_id, worker_registry.process_id) IN (
+                SELECT worker_id, process_id
+                FROM cluster.worker_registry
+                WHERE
+                    worker_registry.leased_until IS NULL
+                    OR worker_registry.leased_until <= $2
+                ORDER BY worker_registry.worker_id, worker_registry.process_id
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING
+                worker_registry.worker_id,
+                worker_registry.process_id
+            ",
+            token,
+            now,
+            lease_until,
+        )
+        .fetch_optional(&mut *self.writer().await?)
+        .measured(&self.metrics, "lease_worker_id")
+        .await?;
+
+        Ok(record.map(|record| WorkerLease {
+            worker_id: WorkerId::new(snowflake_part_from_db(record.worker_id))
+                .expect("Only valid worker ids are stored"),
+            process_id: ProcessId::new(snowflake_part_from_db(record.process_id))
+                .expect("Only valid process ids are stored"),
+            token,
+        }))
+    }
+
+    /// Extends the lease until `lease_until`.
+    /// Returns `false` if another server took it over after it expired.
+    pub async fn renew_worker_lease(
+        &self,
+        lease: &WorkerLease,
+        lease_until: UtcDateTime,
+    ) -> Result<bool> {
+        let lease_until = PrimitiveDateTime::new(lease_until.date(), lease_until.time());
+
+        let rows_affected = self
+            .idempotent("renew_worker_lease", || async move {
+                query!(
+                    "
+                    UPDATE cluster.worker_registry
+                    SET leased_until = $4
+                    WHERE
+                        worker_registry.worker_id = $1
+                        AND worker_registry.process_id = $2
+                        AND worker_registry.lease_token = $3
+                    ",
+                    i16::from(lease.worker_id.get()),
+                    i16::from(lease.process_id.get()),
+                    lease.token,
+                    lease_until,
+                )
+                .execute(&mut *self.writer().await?)
+                .measured(&self.metrics, "renew_worker_lease")
+                .await
+            })
+            .await?
+            .rows_affected();
+
+        Ok(rows_affected == 1)
     }
+
+    /// Frees the worker and process ID pair for other servers, unless it was taken over already.
+    pub async fn release_worker_lease(&self, lease: &WorkerLease) -> Result<()> {
+        self.idempotent("release_worker_lease", || async move {
+            query!(
+                "
+                UPDATE cluster.worker_registry
+                SET lease_token = NULL,
+                    leased_until = NULL
+                WHERE
+                    worker_registry.worker_id = $1
+                    AND worker_registry.process_id = $2
+                    AND worker_registry.lease_token = $3
+                ",
+                i16::from(lease.worker_id.get()),
+                i16::from(lease.process_id.get()),
+                lease.token,
+            )
+            .execute(&mut *self.writer().await?)
+            .measured(&self.metrics, "release_worker_lease")
+            .await
+        })
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Worker and process ids are `u8`, but stored as `smallint` since Postgres has no `tinyint`.
+fn snowflake_part_from_db(part: i16) -> u8 {
+    u8::try_from(part).expect("Only u8 snowflake parts are stored")
+}
+
+/// Key ids are `u32`, but stored as `bigint` since Postgres has no unsigned integers.
+fn key_id_from_db(key_id: i64) -> u32 {
+    u32::try_from(key_id).expect("Only u32 key ids are stored")
 }
@@ -0,0 +1,49 @@
+//! [`Event`]s broadcast between API instances through Postgres `LISTEN`/`NOTIFY`.
+//!
+//! Notifications are sent in the same transaction as the change they describe,
+//! so listeners only learn about committed changes.
+
+use crate::client::Result;
+use sqlx::{PgExecutor, PgPool, postgres::PgListener, query};
+use stellwerk_common::event::Event;
+use tracing::debug;
+
+const EVENT_CHANNEL: &str = "stellwerk_events";
+
+/// Receives [`Event`]s written by any instance, including this one.
+///
+/// If the connection is lost, it is reestablished on the next [`DbEventListener::recv`].
+/// Events sent in the meantime are lost.
+#[derive(Debug)]
+pub struct DbEventListener(PgListener);
+
+/// Sends `event` as the JSON payload of a notification.
+pub(crate) async fn notify(event: impl Into<Event>, executor: impl PgExecutor<'_>) -> Result<()> {
+    let payload = serde_json::to_string(&event.into()).expect("events serialize infallibly");
+    query!("SELECT pg_notify($1, $2)", EVENT_CHANNEL, payload)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+impl DbEventListener {
+    pub(crate) async fn connect(pool: &PgPool) -> Result<Self> {
+        let mut listener = PgListener::connect_with(pool).await?;
+        listener.listen(EVENT_CHANNEL).await?;
+
+        Ok(Self(listener))
+    }
+
+    /// Waits for the next event. Payloads that are not valid events, for example
+    /// those sent by newer instances during a rolling upgrade, are skipped.
+    pub async fn recv(&mut self) -> Result<Event> {
+        loop {
+            let notification = self.0.recv().await?;
+            match serde_json::from_str(notification.payload()) {
+                Ok(event) => return Ok(event),
+                Err(error) => debug!(%error, "Skipping invalid event payload"),
+            }
+        }
+    }
+}
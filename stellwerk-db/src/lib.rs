@@ -1,5 +1,16 @@
 #![feature(sync_nonpoison)]
 #![feature(nonpoison_mutex)]
 
+pub mod breaker;
+pub mod cache;
 pub mod client;
+pub mod events;
+pub mod memory;
+mod metrics;
 mod record;
+pub mod seed;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod store;
+#[cfg(feature = "test-util")]
+pub mod test_util;
@@ -0,0 +1,239 @@
+//! Runs the [migrations](stellwerk_db::client::MIGRATOR) of the database separately from
+//! `stellwerk-api`, which can then start with `DATABASE_SKIP_MIGRATIONS`,
+//! and [seeds](stellwerk_db::seed) databases for development.
+//!
+//! ```text
+//! stellwerk-db migrate run [--database-url <URL>]     applies all pending migrations
+//! stellwerk-db migrate info [--database-url <URL>]    lists the migrations and whether they are applied
+//! stellwerk-db migrate revert [--database-url <URL>]  reverts the latest applied migration
+//! stellwerk-db seed [--users <N>] [--follows <N>] [--posts <N>] [--seed <N>] [--database-url <URL>]
+//!                                                     applies all pending migrations and seeds
+//! ```
+//!
+//! The URL defaults to the `DATABASE_URL` environment variable.
+
+use sqlx::{
+    Connection, PgConnection,
+    migrate::{Migrate, MigrateError},
+};
+use std::{collections::HashMap, env, process::ExitCode, str::FromStr};
+use stellwerk_common::snowflake::{ProcessId, WorkerId};
+use stellwerk_db::{
+    client::{DbClient, DbError, MIGRATOR, PoolSettings},
+    seed::{self, SeedSettings},
+};
+use thiserror::Error;
+
+const USAGE: &str = "\
+Usage: stellwerk-db migrate <run|info|revert> [--database-url <URL>]
+       stellwerk-db seed [--users <N>] [--follows <N>] [--posts <N>] [--seed <N>] [--database-url <URL>]";
+
+/// The last process ID, so that seeded IDs do not collide with those of servers
+/// using the other process IDs.
+const SEED_PROCESS_ID: u8 = 31;
+
+#[derive(Debug, Error)]
+enum CliError {
+    #[error("{USAGE}")]
+    Usage,
+    #[error("No database URL given, pass --database-url or set DATABASE_URL")]
+    MissingUrl,
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Migrating failed: {0}")]
+    Migrate(#[from] MigrateError),
+    #[error("Seeding failed: {0}")]
+    Seed(#[from] DbError),
+    #[error("No migration is applied")]
+    NothingToRevert,
+    #[error("Migration {version} ({description}) cannot be reverted, it has no down migration")]
+    Irreversible { version: i64, description: String },
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+enum Command {
+    Migrate(MigrateCommand),
+    Seed(SeedSettings),
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+enum MigrateCommand {
+    Run,
+    Info,
+    Revert,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run() -> Result<(), CliError> {
+    let mut args = env::args().skip(1);
+    let command = match args.next().as_deref() {
+        Some("migrate") => match args.next().as_deref() {
+            Some("run") => Command::Migrate(MigrateCommand::Run),
+            Some("info") => Command::Migrate(MigrateCommand::Info),
+            Some("revert") => Command::Migrate(MigrateCommand::Revert),
+            _ => return Err(CliError::Usage),
+        },
+        Some("seed") => Command::Seed(SeedSettings::default()),
+        _ => return Err(CliError::Usage),
+    };
+
+    let mut options = parse_options(args)?;
+    let url = options.remove("--database-url");
+    let command = match command {
+        Command::Seed(defaults) => Command::Seed(SeedSettings {
+            users: take_number(&mut options, "--users")?.unwrap_or(defaults.users),
+            follows: take_number(&mut options, "--follows")?.unwrap_or(defaults.follows),
+            posts: take_number(&mut options, "--posts")?.unwrap_or(defaults.posts),
+            seed: take_number(&mut options, "--seed")?.unwrap_or(defaults.seed),
+        }),
+        command @ Command::Migrate(_) => command,
+    };
+    if !options.is_empty() {
+        return Err(CliError::Usage);
+    }
+    let url = match url {
+        Some(url) => url,
+        None => env::var("DATABASE_URL").map_err(|_| CliError::MissingUrl)?,
+    };
+
+    match command {
+        Command::Migrate(command) => migrate(&url, command).await,
+        Command::Seed(settings) => seed(&url, &settings).await,
+    }
+}
+
+async fn migrate(url: &str, command: MigrateCommand) -> Result<(), CliError> {
+    let mut connection = PgConnection::connect(url).await?;
+    match command {
+        MigrateCommand::Run => {
+            MIGRATOR.run_direct(&mut connection).await?;
+            println!("All migrations are applied");
+        }
+        MigrateCommand::Info => info(&mut connection).await?,
+        MigrateCommand::Revert => revert(&mut connection).await?,
+    }
+    connection.close().await?;
+
+    Ok(())
+}
+
+/// The values of `--name value` pairs by name.
+fn parse_options(
+    mut args: impl Iterator<Item = String>,
+) -> Result<HashMap<String, String>, CliError> {
+    let mut options = HashMap::new();
+    while let Some(name) = args.next() {
+        let value = args.next().ok_or(CliError::Usage)?;
+        if !name.starts_with("--") || options.insert(name, value).is_some() {
+            return Err(CliError::Usage);
+        }
+    }
+
+    Ok(options)
+}
+
+fn take_number<T: FromStr>(
+    options: &mut HashMap<String, String>,
+    name: &str,
+) -> Result<Option<T>, CliError> {
+    options
+        .remove(name)
+        .map(|value| value.parse().map_err(|_| CliError::Usage))
+        .transpose()
+}
+
+async fn seed(url: &str, settings: &SeedSettings) -> Result<(), CliError> {
+    let db = DbClient::connect_and_migrate(
+        url,
+        &[] as &[&str],
+        &PoolSettings::default(),
+        WorkerId::default(),
+        ProcessId::new_unchecked(SEED_PROCESS_ID),
+    )
+    .await?;
+
+    let summary = seed::seed(&db, settings).await?;
+    println!(
+        "Seeded {} users, {} follows, and {} posts",
+        summary.users, summary.follows, summary.posts
+    );
+
+    Ok(())
+}
+
+async fn info(connection: &mut PgConnection) -> Result<(), CliError> {
+    connection.ensure_migrations_table().await?;
+    let applied: HashMap<_, _> = connection
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|migration| (migration.version, migration.checksum))
+        .collect();
+    let dirty_version = connection.dirty_version().await?;
+
+    for migration in MIGRATOR
+        .iter()
+        .filter(|migration| migration.migration_type.is_up_migration())
+    {
+        let status = match applied.get(&migration.version) {
+            _ if dirty_version == Some(migration.version) => "failed",
+            Some(checksum) if *checksum != migration.checksum => "applied, changed since",
+            Some(_) => "applied",
+            None => "pending",
+        };
+        println!("{} {} ({status})", migration.version, migration.description);
+    }
+
+    let mut unknown: Vec<_> = applied
+        .keys()
+        .filter(|version| !MIGRATOR.version_exists(**version))
+        .collect();
+    unknown.sort_unstable();
+    for version in unknown {
+        println!("{version} (applied, missing from this build)");
+    }
+
+    Ok(())
+}
+
+async fn revert(connection: &mut PgConnection) -> Result<(), CliError> {
+    connection.ensure_migrations_table().await?;
+    let mut applied: Vec<_> = connection
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|migration| migration.version)
+        .collect();
+    applied.sort_unstable();
+    let latest = applied.pop().ok_or(CliError::NothingToRevert)?;
+
+    let Some(down) = MIGRATOR.iter().find(|migration| {
+        migration.version == latest && migration.migration_type.is_down_migration()
+    }) else {
+        let description = MIGRATOR
+            .iter()
+            .find(|migration| migration.version == latest)
+            .map_or_else(String::new, |migration| {
+                migration.description.clone().into_owned()
+            });
+        return Err(CliError::Irreversible {
+            version: latest,
+            description,
+        });
+    };
+
+    connection.revert(down).await?;
+    println!("Reverted {} {}", down.version, down.description);
+
+    Ok(())
+}
@@ -0,0 +1,364 @@
+//! A [`Store`] in memory, so that handlers can be tested without a database.
+
+use crate::{
+    client::{DbError, Result},
+    store::Store,
+};
+use async_trait::async_trait;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::nonpoison::Mutex,
+};
+use stellwerk_common::model::{
+    Id,
+    auth::{AuthTokenHash, Authentication},
+    post::{CreatePost, PartialPost, Post, PostContent, PostMarker, PostVersion},
+    tenant::TenantId,
+    user::{CreateUser, User, UserHandle, UserMarker, UserProfile, UserRole, UserStats},
+};
+
+#[derive(Clone, Debug)]
+struct MemoryUser {
+    tenant: TenantId,
+    handle: UserHandle,
+    role: UserRole,
+    remote: bool,
+    profile_version: u64,
+}
+
+#[derive(Clone, Debug)]
+struct MemoryPost {
+    author: Id<UserMarker>,
+    content: PostContent,
+    /// The value of the ID counter when the post was pinned, so that pins are ordered.
+    pinned_at: Option<u64>,
+    edit_version: u64,
+}
+
+#[derive(Debug, Default)]
+struct Data {
+    /// Generates increasing IDs, like snowflakes.
+    next_id: u64,
+    users: BTreeMap<Id<UserMarker>, MemoryUser>,
+    posts: BTreeMap<Id<PostMarker>, MemoryPost>,
+    auths: HashMap<AuthTokenHash, Authentication>,
+}
+
+impl Data {
+    fn generate_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Users cannot be verified here.
+    fn user(&self, user_id: Id<UserMarker>) -> Option<User> {
+        let user = self.users.get(&user_id)?;
+        Some(User {
+            id: user_id,
+            handle: user.handle.clone(),
+            verified: false,
+        })
+    }
+
+    fn post(&self, post_id: Id<PostMarker>) -> Option<Post> {
+        let post = self.posts.get(&post_id)?;
+        let author = self.user(post.author)?;
+
+        Some(Post {
+            id: post_id,
+            author,
+            content: post.content.clone(),
+            content_html: post.content.render_html(),
+            pinned: post.pinned_at.is_some(),
+            filtered: Vec::new(),
+        })
+    }
+}
+
+/// Users have no followers, and are only remote or privileged if set so here.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    data: Mutex<Data>,
+}
+
+impl MemoryStore {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert_auth(&self, authentication: Authentication) {
+        self.data
+            .lock()
+            .auths
+            .insert(authentication.token_hash.clone(), authentication);
+    }
+
+    /// Returns `false` if the user does not exist.
+    pub fn set_user_role(&self, user_id: Id<UserMarker>, role: UserRole) -> bool {
+        self.data
+            .lock()
+            .users
+            .get_mut(&user_id)
+            .map(|user| user.role = role)
+            .is_some()
+    }
+
+    /// Returns `false` if the user does not exist.
+    pub fn set_remote(&self, user_id: Id<UserMarker>, remote: bool) -> bool {
+        self.data
+            .lock()
+            .users
+            .get_mut(&user_id)
+            .map(|user| user.remote = remote)
+            .is_some()
+    }
+}
+
+#[async_trait]
+impl Store for MemoryStore {
+    async fn fetch_user(&self, user_id: Id<UserMarker>) -> Result<Option<User>> {
+        Ok(self.data.lock().user(user_id))
+    }
+
+    async fn fetch_user_by_handle(
+        &self,
+        tenant: &TenantId,
+        handle: &UserHandle,
+    ) -> Result<Option<User>> {
+        let data = self.data.lock();
+        let user = data
+            .users
+            .iter()
+            .find(|(_, user)| user.tenant == *tenant && user.handle == *handle)
+            .map(|(&id, user)| User {
+                id,
+                handle: user.handle.clone(),
+                verified: false,
+            });
+
+        Ok(user)
+    }
+
+    async fn fetch_user_profile(&self, user_id: Id<UserMarker>) -> Result<Option<UserProfile>> {
+        let data = self.data.lock();
+        let Some(user) = data.user(user_id) else {
+            return Ok(None);
+        };
+        let post_count = data
+            .posts
+            .values()
+            .filter(|post| post.author == user_id)
+            .count();
+
+        Ok(Some(UserProfile {
+            user,
+            stats: UserStats {
+                post_count: post_count as u64,
+                follower_count: 0,
+                following_count: 0,
+            },
+            website: None,
+        }))
+    }
+
+    async fn fetch_user_version(&self, user_id: Id<UserMarker>) -> Result<Option<u64>> {
+        let data = self.data.lock();
+        Ok(data.users.get(&user_id).map(|user| user.profile_version))
+    }
+
+    async fn fetch_user_role(&self, user_id: Id<UserMarker>) -> Result<Option<UserRole>> {
+        let data = self.data.lock();
+        Ok(data.users.get(&user_id).map(|user| user.role))
+    }
+
+    async fn is_remote_user(&self, user_id: Id<UserMarker>) -> Result<bool> {
+        let data = self.data.lock();
+        Ok(data.users.get(&user_id).is_some_and(|user| user.remote))
+    }
+
+    /// Fails if the handle is taken in the tenant, like the unique constraint of the database.
+    async fn create_user(&self, tenant: &TenantId, user: &CreateUser) -> Result<Id<UserMarker>> {
+        let mut data = self.data.lock();
+        if data
+            .users
+            .values()
+            .any(|other| other.tenant == *tenant && other.handle == user.handle)
+        {
+            return Err(DbError::Sqlx(sqlx::Error::Protocol(format!(
+                "The handle {} is taken",
+                user.handle.get()
+            ))));
+        }
+
+        let id = data.generate_id().into();
+        data.users.insert(
+            id,
+            MemoryUser {
+                tenant: tenant.clone(),
+                handle: user.handle.clone(),
+                role: UserRole::default(),
+                remote: false,
+                profile_version: 0,
+            },
+        );
+
+        Ok(id)
+    }
+
+    async fn fetch_user_posts(
+        &self,
+        user_id: Id<UserMarker>,
+        max_id: Option<Id<PostMarker>>,
+        since_id: Option<Id<PostMarker>>,
+        limit: u32,
+    ) -> Result<Option<Vec<PartialPost>>> {
+        let data = self.data.lock();
+        if !data.users.contains_key(&user_id) {
+            return Ok(None);
+        }
+
+        let partial = |(&id, post): (&Id<PostMarker>, &MemoryPost)| PartialPost {
+            id,
+            content: post.content.clone(),
+            content_html: post.content.render_html(),
+            pinned: post.pinned_at.is_some(),
+        };
+        let own_posts = || data.posts.iter().filter(|(_, post)| post.author == user_id);
+
+        let mut posts = Vec::new();
+        if max_id.is_none() && since_id.is_none() {
+            let mut pinned: Vec<_> = own_posts()
+                .filter(|(_, post)| post.pinned_at.is_some())
+                .collect();
+            pinned.sort_by_key(|(_, post)| std::cmp::Reverse(post.pinned_at));
+            posts.extend(pinned.into_iter().map(partial));
+        }
+
+        posts.extend(
+            own_posts()
+                .rev()
+                .filter(|(_, post)| post.pinned_at.is_none())
+                .filter(|&(&id, _)| max_id.is_none_or(|max_id| id < max_id))
+                .filter(|&(&id, _)| since_id.is_none_or(|since_id| id > since_id))
+                .take(limit as usize)
+                .map(partial),
+        );
+
+        Ok(Some(posts))
+    }
+
+    async fn fetch_post(&self, post_id: Id<PostMarker>) -> Result<Option<Post>> {
+        Ok(self.data.lock().post(post_id))
+    }
+
+    async fn fetch_post_version(&self, post_id: Id<PostMarker>) -> Result<Option<PostVersion>> {
+        let data = self.data.lock();
+        let version = data.posts.get(&post_id).and_then(|post| {
+            let author = data.users.get(&post.author)?;
+            Some(PostVersion {
+                post: post.edit_version,
+                author: author.profile_version,
+            })
+        });
+
+        Ok(version)
+    }
+
+    /// Users cannot be shadowbanned here, so `viewer` does not matter.
+    async fn fetch_public_posts(
+        &self,
+        tenant: &TenantId,
+        _viewer: Option<Id<UserMarker>>,
+        max_id: Option<Id<PostMarker>>,
+        since_id: Option<Id<PostMarker>>,
+        limit: u32,
+    ) -> Result<Vec<Post>> {
+        let data = self.data.lock();
+        let posts = data
+            .posts
+            .iter()
+            .rev()
+            .filter(|(_, post)| {
+                data.users
+                    .get(&post.author)
+                    .is_some_and(|author| author.tenant == *tenant)
+            })
+            .map(|(&id, _)| id)
+            .filter(|&id| max_id.is_none_or(|max_id| id < max_id))
+            .filter(|&id| since_id.is_none_or(|since_id| id > since_id))
+            .filter_map(|id| data.post(id))
+            .take(limit as usize)
+            .collect();
+
+        Ok(posts)
+    }
+
+    async fn create_post(&self, post: &CreatePost) -> Result<Id<PostMarker>> {
+        let mut data = self.data.lock();
+        let id = data.generate_id().into();
+        data.posts.insert(
+            id,
+            MemoryPost {
+                author: post.author,
+                content: post.content.clone(),
+                pinned_at: None,
+                edit_version: 0,
+            },
+        );
+        if let Some(author) = data.users.get_mut(&post.author) {
+            author.profile_version += 1;
+        }
+
+        Ok(id)
+    }
+
+    async fn pin_post(&self, post_id: Id<PostMarker>, max_pinned: usize) -> Result<bool> {
+        let mut data = self.data.lock();
+        let Some(author) = data.posts.get(&post_id).map(|post| post.author) else {
+            return Ok(true);
+        };
+
+        let pinned_count = data
+            .posts
+            .iter()
+            .filter(|&(&id, post)| {
+                post.author == author && post.pinned_at.is_some() && id != post_id
+            })
+            .count();
+        if pinned_count >= max_pinned {
+            return Ok(false);
+        }
+
+        let pinned_at = data.generate_id();
+        if let Some(post) = data.posts.get_mut(&post_id)
+            && post.pinned_at.is_none()
+        {
+            post.pinned_at = Some(pinned_at);
+            post.edit_version += 1;
+        }
+
+        Ok(true)
+    }
+
+    async fn unpin_post(&self, post_id: Id<PostMarker>) -> Result<()> {
+        let mut data = self.data.lock();
+        if let Some(post) = data.posts.get_mut(&post_id)
+            && post.pinned_at.is_some()
+        {
+            post.pinned_at = None;
+            post.edit_version += 1;
+        }
+
+        Ok(())
+    }
+
+    async fn fetch_auth(&self, token_hash: &AuthTokenHash) -> Result<Option<Authentication>> {
+        Ok(self.data.lock().auths.get(token_hash).cloned())
+    }
+
+    /// No policies are published in memory.
+    async fn has_pending_policies(&self, _user_id: Id<UserMarker>) -> Result<bool> {
+        Ok(false)
+    }
+}
@@ -0,0 +1,172 @@
+//! Statistics of the queries of [`DbClient`](crate::client::DbClient) by static name,
+//! and warnings about slow queries.
+//!
+//! Queries are named after the method running them, followed by the query within the method
+//! if it runs several, like `fetch_user_posts.pinned`.
+//! Durations only include running the query, not waiting for a connection.
+
+use crate::client::{DbError, Result};
+use async_stream::stream;
+use futures_util::{Stream, StreamExt};
+use sqlx::postgres::PgQueryResult;
+use std::{
+    collections::BTreeMap,
+    pin::pin,
+    sync::nonpoison::Mutex,
+    time::{Duration, Instant},
+};
+use stellwerk_common::model::admin::QueryStats;
+use tracing::warn;
+
+#[derive(Debug, Default)]
+pub(crate) struct QueryMetrics {
+    /// Queries taking at least this long are logged, if set.
+    slow_query_threshold: Option<Duration>,
+    stats: Mutex<BTreeMap<&'static str, QueryStats>>,
+}
+
+impl QueryMetrics {
+    pub(crate) fn new(slow_query_threshold: Option<Duration>) -> Self {
+        Self {
+            slow_query_threshold,
+            stats: Mutex::default(),
+        }
+    }
+
+    /// `rows` is `None` if the query failed.
+    fn record(&self, name: &'static str, elapsed: Duration, rows: Option<u64>) {
+        let slow = self
+            .slow_query_threshold
+            .is_some_and(|threshold| elapsed >= threshold);
+        if slow {
+            warn!(
+                query = name,
+                elapsed_ms = elapsed.as_millis(),
+                rows,
+                "Slow database query"
+            );
+        }
+
+        let elapsed_us = u64::try_from(elapsed.as_micros()).unwrap_or(u64::MAX);
+        let mut stats = self.stats.lock();
+        let stats = entry(&mut stats, name);
+        stats.calls += 1;
+        stats.slow_calls += u64::from(slow);
+        stats.total_duration_us = stats.total_duration_us.saturating_add(elapsed_us);
+        stats.max_duration_us = stats.max_duration_us.max(elapsed_us);
+        match rows {
+            Some(rows) => stats.rows += rows,
+            None => stats.errors += 1,
+        }
+    }
+
+    /// Counts a retry of a query that failed transiently, see [`RetryPolicy`](crate::client::RetryPolicy).
+    pub(crate) fn record_retry(&self, name: &'static str) {
+        let mut stats = self.stats.lock();
+        let stats = entry(&mut stats, name);
+        stats.retries += 1;
+    }
+
+    /// By name.
+    pub(crate) fn snapshot(&self) -> Vec<QueryStats> {
+        self.stats.lock().values().cloned().collect()
+    }
+}
+
+fn entry<'a>(
+    stats: &'a mut BTreeMap<&'static str, QueryStats>,
+    name: &'static str,
+) -> &'a mut QueryStats {
+    stats.entry(name).or_insert_with(|| QueryStats {
+        query: name.to_owned(),
+        ..QueryStats::default()
+    })
+}
+
+/// The number of rows a query returned or affected.
+pub(crate) trait RowCount {
+    fn row_count(&self) -> u64;
+}
+
+impl<T> RowCount for Option<T> {
+    fn row_count(&self) -> u64 {
+        u64::from(self.is_some())
+    }
+}
+
+impl<T> RowCount for Vec<T> {
+    fn row_count(&self) -> u64 {
+        self.len() as u64
+    }
+}
+
+impl RowCount for PgQueryResult {
+    fn row_count(&self) -> u64 {
+        self.rows_affected()
+    }
+}
+
+/// Records running a query in [`QueryMetrics`], converting its error into a [`DbError`].
+pub(crate) trait Measured<T>: Future<Output = Result<T, sqlx::Error>> + Sized {
+    async fn measured(self, metrics: &QueryMetrics, name: &'static str) -> Result<T>
+    where
+        T: RowCount,
+    {
+        let start = Instant::now();
+        let result = self.await;
+        metrics.record(
+            name,
+            start.elapsed(),
+            result.as_ref().ok().map(RowCount::row_count),
+        );
+
+        result.map_err(DbError::from)
+    }
+
+    /// For `fetch_one`, which returns exactly one row if it succeeds.
+    async fn measured_one(self, metrics: &QueryMetrics, name: &'static str) -> Result<T> {
+        let start = Instant::now();
+        let result = self.await;
+        metrics.record(name, start.elapsed(), result.as_ref().ok().map(|_| 1));
+
+        result.map_err(DbError::from)
+    }
+}
+
+impl<T, F: Future<Output = Result<T, sqlx::Error>>> Measured<T> for F {}
+
+/// Records streaming a query in [`QueryMetrics`] once the stream ended, with the time spent
+/// waiting for rows, but not the time the consumer took between them.
+/// Streams dropped before they ended are not recorded.
+pub(crate) trait MeasuredStream<T>: Stream<Item = Result<T>> + Sized {
+    fn measured_stream<'a>(
+        self,
+        metrics: &'a QueryMetrics,
+        name: &'static str,
+    ) -> impl Stream<Item = Result<T>> + 'a
+    where
+        Self: 'a,
+        T: 'a,
+    {
+        stream! {
+            let mut rows = pin!(self);
+            let mut elapsed = Duration::ZERO;
+            let mut row_count = 0;
+            let mut failed = false;
+            loop {
+                let start = Instant::now();
+                let row = rows.next().await;
+                elapsed += start.elapsed();
+                let Some(row) = row else {
+                    break;
+                };
+                row_count += 1;
+                failed |= row.is_err();
+                yield row;
+            }
+            metrics.record(name, elapsed, (!failed).then_some(row_count));
+        }
+    }
+}
+
+impl<T, S: Stream<Item = Result<T>>> MeasuredStream<T> for S {}
@@ -1,37 +1,336 @@
-use stellwerk_common::model::{
-    ModelValidationError,
-    auth::Authentication,
-    post::{PartialPost, Post},
-    user::{User, UserHandle},
+use stellwerk_common::{
+    model::{
+        ModelValidationError,
+        activitypub::PublicKey,
+        admin::UserAccount,
+        analytics::{AnalyticsMetric, AnalyticsRollup},
+        announcement::{Announcement, AnnouncementBody, AnnouncementTitle, UserAnnouncement},
+        auth::Authentication,
+        conversation::{Conversation, EncryptedPayload, Message, MessageBody, MessageContent},
+        email::{EmailAddress, EmailDigestSettings, EmailDigestSubscription},
+        filter::{Filter, FilterSettings},
+        job::{DeadJob, InvalidJobPayloadError, Job, JobPayload},
+        moderation::ModerationLogEntry,
+        notification::Notification,
+        oidc::{OidcIdentity, PendingOidcLogin},
+        policy::{Policy, PolicyContent},
+        post::{ModeratedPost, PartialPost, Post, PostContent},
+        report::{Report, ReportComment, ReportNote, ReportNoteContent},
+        search::{IndexedPost, IndexedUser},
+        tenant::TenantId,
+        user::{ModeratedUser, ProfileWebsite, User, UserHandle, UserProfile, UserStats},
+        webhook::{Webhook, WebhookSecret, WebhookTarget, WebhookUrl},
+    },
+    signature::KeyPair,
 };
 use time::{Duration, PrimitiveDateTime};
 
 #[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+#[cfg_attr(feature = "sqlite", derive(sqlx::FromRow))]
 pub(crate) struct UserRecord {
     pub user_snowflake: i64,
     pub handle: String,
+    pub verified: bool,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub(crate) struct ModeratedUserRecord {
+    pub user_snowflake: i64,
+    pub handle: String,
+    pub verified: bool,
+    pub suspended_at: Option<PrimitiveDateTime>,
+    pub shadowbanned_at: Option<PrimitiveDateTime>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+#[cfg_attr(feature = "sqlite", derive(sqlx::FromRow))]
+pub(crate) struct UserProfileRecord {
+    pub user_snowflake: i64,
+    pub handle: String,
+    pub verified: bool,
+    pub post_count: i64,
+    pub follower_count: i64,
+    pub following_count: i64,
+    pub website: Option<String>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub(crate) struct UserAccountRecord {
+    pub user_snowflake: i64,
+    pub handle: String,
+    pub verified: bool,
+    pub role: String,
+    pub remote: bool,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+#[cfg_attr(feature = "sqlite", derive(sqlx::FromRow))]
 pub(crate) struct FullPostRecord {
     pub post_snowflake: i64,
     pub content: String,
+    pub pinned: bool,
+    pub user_snowflake: i64,
+    pub handle: String,
+    pub verified: bool,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub(crate) struct ModeratedPostRecord {
+    pub post_snowflake: i64,
+    pub content: String,
+    pub pinned: bool,
     pub user_snowflake: i64,
     pub handle: String,
+    pub verified: bool,
+    pub deleted_at: Option<PrimitiveDateTime>,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+#[cfg_attr(feature = "sqlite", derive(sqlx::FromRow))]
 pub(crate) struct PartialPostRecord {
     pub post_snowflake: i64,
     pub content: String,
+    pub pinned: bool,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub(crate) struct IndexedPostRecord {
+    pub post_snowflake: i64,
+    pub tenant: String,
+    pub user_snowflake: i64,
+    pub content: String,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub(crate) struct IndexedUserRecord {
+    pub user_snowflake: i64,
+    pub tenant: String,
+    pub handle: String,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub(crate) struct AnalyticsRollupRecord {
+    pub metric: String,
+    pub bucket_start: PrimitiveDateTime,
+    pub total: i64,
+    pub samples: i64,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub(crate) struct PendingOidcLoginRecord {
+    pub tenant: String,
+    pub provider: String,
+    pub nonce: Box<[u8]>,
+    pub code_verifier: Box<[u8]>,
+    pub handle: Option<String>,
+    pub link_user_snowflake: Option<i64>,
+    pub created_at: PrimitiveDateTime,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub(crate) struct OidcIdentityRecord {
+    pub provider: String,
+    pub linked_at: PrimitiveDateTime,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+#[cfg_attr(feature = "sqlite", derive(sqlx::FromRow))]
 pub(crate) struct AuthenticationRecord {
     pub user_snowflake: i64,
+    pub tenant: String,
     pub token_hash: Box<[u8]>,
     pub created_at: PrimitiveDateTime,
     pub expires_after_seconds: Option<i64>,
+    pub impersonator_snowflake: Option<i64>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub(crate) struct ReportRecord {
+    pub report_snowflake: i64,
+    pub reporter_snowflake: Option<i64>,
+    pub target_user_snowflake: i64,
+    pub target_post_snowflake: Option<i64>,
+    pub category: String,
+    pub comment: String,
+    pub assignee_snowflake: Option<i64>,
+    pub resolved_at: Option<PrimitiveDateTime>,
+    pub resolution: Option<String>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub(crate) struct ReportNoteRecord {
+    pub note_snowflake: i64,
+    pub report_snowflake: i64,
+    pub author_snowflake: i64,
+    pub content: String,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub(crate) struct ModerationLogRecord {
+    pub entry_snowflake: i64,
+    pub moderator_snowflake: i64,
+    pub action: String,
+    pub report_snowflake: Option<i64>,
+    pub target_user_snowflake: Option<i64>,
+    pub target_post_snowflake: Option<i64>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub(crate) struct FilterRecord {
+    pub filter_snowflake: i64,
+    pub user_snowflake: i64,
+    pub phrase: String,
+    pub regex: bool,
+    pub contexts: Vec<String>,
+    pub action: String,
+    pub expires_at: Option<PrimitiveDateTime>,
+    pub version: i64,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub(crate) struct AnnouncementRecord {
+    pub announcement_snowflake: i64,
+    pub title: String,
+    pub body: String,
+    pub expires_at: Option<PrimitiveDateTime>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub(crate) struct UserAnnouncementRecord {
+    pub announcement_snowflake: i64,
+    pub title: String,
+    pub body: String,
+    pub expires_at: Option<PrimitiveDateTime>,
+    pub read: bool,
+    pub dismissed: bool,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub(crate) struct PolicyRecord {
+    pub kind: String,
+    pub version: i64,
+    pub content: String,
+    pub published_at: PrimitiveDateTime,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub(crate) struct NotificationRecord {
+    pub notification_snowflake: i64,
+    pub kind: String,
+    pub post_snowflake: Option<i64>,
+    pub user_snowflake: i64,
+    pub handle: String,
+    pub verified: bool,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub(crate) struct EmailDigestRecord {
+    pub user_snowflake: i64,
+    pub email: String,
+    pub frequency: String,
+    pub unsubscribe_token: Box<[u8]>,
+    pub digested_up_to: Option<i64>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub(crate) struct ConversationRecord {
+    pub conversation_snowflake: i64,
+    pub creator_snowflake: i64,
+    pub unread_count: i64,
+    pub last_message_snowflake: Option<i64>,
+    pub last_message_author_snowflake: Option<i64>,
+    pub last_message_content: Option<String>,
+    pub last_message_encrypted_payload: Option<Vec<u8>>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub(crate) struct ConversationMemberRecord {
+    pub conversation_snowflake: i64,
+    pub user_snowflake: i64,
+    pub handle: String,
+    pub verified: bool,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub(crate) struct MessageRecord {
+    pub message_snowflake: i64,
+    pub author_snowflake: i64,
+    pub content: Option<String>,
+    pub encrypted_payload: Option<Vec<u8>>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub(crate) struct KeyPairRecord {
+    pub public_key_pem: String,
+    pub private_key_pem: String,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub(crate) struct RemoteActorKeyRecord {
+    pub key_id: String,
+    pub owner: String,
+    pub public_key_pem: String,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub(crate) struct JobRecord {
+    pub job_snowflake: i64,
+    pub payload: String,
+    pub attempts: i32,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub(crate) struct DeadJobRecord {
+    pub job_snowflake: i64,
+    pub payload: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub dead_at: PrimitiveDateTime,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Hash)]
+pub(crate) struct WebhookRecord {
+    pub webhook_snowflake: i64,
+    pub url: String,
+    pub events: Vec<String>,
+    pub disabled_at: Option<PrimitiveDateTime>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub(crate) struct WebhookTargetRecord {
+    pub webhook_snowflake: i64,
+    pub url: String,
+    pub secret: String,
+}
+
+impl ConversationRecord {
+    pub fn into_conversation(
+        self,
+        members: Vec<User>,
+    ) -> Result<Conversation, ModelValidationError> {
+        let last_message = match (
+            self.last_message_snowflake,
+            self.last_message_author_snowflake,
+        ) {
+            (Some(message_snowflake), Some(author_snowflake)) => {
+                Some(Message::try_from(MessageRecord {
+                    message_snowflake,
+                    author_snowflake,
+                    content: self.last_message_content,
+                    encrypted_payload: self.last_message_encrypted_payload,
+                })?)
+            }
+            _ => None,
+        };
+
+        Ok(Conversation {
+            id: self.conversation_snowflake.cast_unsigned().into(),
+            creator: self.creator_snowflake.cast_unsigned().into(),
+            members,
+            last_message,
+            unread_count: self.unread_count.cast_unsigned(),
+        })
+    }
 }
 
 impl TryFrom<UserRecord> for User {
@@ -41,6 +340,43 @@ impl TryFrom<UserRecord> for User {
         Ok(Self {
             id: value.user_snowflake.cast_unsigned().into(),
             handle: UserHandle::new(value.handle)?,
+            verified: value.verified,
+        })
+    }
+}
+
+impl TryFrom<UserAccountRecord> for UserAccount {
+    type Error = ModelValidationError;
+
+    fn try_from(value: UserAccountRecord) -> Result<Self, Self::Error> {
+        Ok(Self {
+            user: User {
+                id: value.user_snowflake.cast_unsigned().into(),
+                handle: UserHandle::new(value.handle)?,
+                verified: value.verified,
+            },
+            role: value.role.parse()?,
+            remote: value.remote,
+        })
+    }
+}
+
+impl TryFrom<UserProfileRecord> for UserProfile {
+    type Error = ModelValidationError;
+
+    fn try_from(value: UserProfileRecord) -> Result<Self, Self::Error> {
+        Ok(Self {
+            user: User {
+                id: value.user_snowflake.cast_unsigned().into(),
+                handle: UserHandle::new(value.handle)?,
+                verified: value.verified,
+            },
+            stats: UserStats {
+                post_count: value.post_count.cast_unsigned(),
+                follower_count: value.follower_count.cast_unsigned(),
+                following_count: value.following_count.cast_unsigned(),
+            },
+            website: value.website.map(ProfileWebsite::new).transpose()?,
         })
     }
 }
@@ -49,9 +385,13 @@ impl TryFrom<PartialPostRecord> for PartialPost {
     type Error = ModelValidationError;
 
     fn try_from(value: PartialPostRecord) -> Result<Self, Self::Error> {
+        let content = PostContent::new(value.content)?;
+
         Ok(Self {
             id: value.post_snowflake.cast_unsigned().into(),
-            content: value.content,
+            content_html: content.render_html(),
+            content,
+            pinned: value.pinned,
         })
     }
 }
@@ -60,13 +400,131 @@ impl TryFrom<FullPostRecord> for Post {
     type Error = ModelValidationError;
 
     fn try_from(value: FullPostRecord) -> Result<Self, Self::Error> {
+        let content = PostContent::new(value.content)?;
+
         Ok(Self {
             id: value.post_snowflake.cast_unsigned().into(),
             author: User {
                 id: value.user_snowflake.cast_unsigned().into(),
                 handle: UserHandle::new(value.handle)?,
+                verified: value.verified,
             },
+            content_html: content.render_html(),
+            content,
+            pinned: value.pinned,
+            filtered: Vec::new(),
+        })
+    }
+}
+
+impl TryFrom<ModeratedUserRecord> for ModeratedUser {
+    type Error = ModelValidationError;
+
+    fn try_from(value: ModeratedUserRecord) -> Result<Self, Self::Error> {
+        let user = UserRecord {
+            user_snowflake: value.user_snowflake,
+            handle: value.handle,
+            verified: value.verified,
+        };
+
+        Ok(Self {
+            user: user.try_into()?,
+            suspended_at: value.suspended_at.map(PrimitiveDateTime::as_utc),
+            shadowbanned_at: value.shadowbanned_at.map(PrimitiveDateTime::as_utc),
+        })
+    }
+}
+
+impl TryFrom<ModeratedPostRecord> for ModeratedPost {
+    type Error = ModelValidationError;
+
+    fn try_from(value: ModeratedPostRecord) -> Result<Self, Self::Error> {
+        let post = FullPostRecord {
+            post_snowflake: value.post_snowflake,
             content: value.content,
+            pinned: value.pinned,
+            user_snowflake: value.user_snowflake,
+            handle: value.handle,
+            verified: value.verified,
+        };
+
+        Ok(Self {
+            post: post.try_into()?,
+            deleted_at: value.deleted_at.map(PrimitiveDateTime::as_utc),
+        })
+    }
+}
+
+impl TryFrom<IndexedPostRecord> for IndexedPost {
+    type Error = ModelValidationError;
+
+    fn try_from(value: IndexedPostRecord) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: value.post_snowflake.cast_unsigned().into(),
+            tenant: TenantId::new(value.tenant)?,
+            author: value.user_snowflake.cast_unsigned().into(),
+            content: value.content,
+        })
+    }
+}
+
+impl TryFrom<IndexedUserRecord> for IndexedUser {
+    type Error = ModelValidationError;
+
+    fn try_from(value: IndexedUserRecord) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: value.user_snowflake.cast_unsigned().into(),
+            tenant: TenantId::new(value.tenant)?,
+            handle: value.handle,
+        })
+    }
+}
+
+impl TryFrom<AnalyticsRollupRecord> for AnalyticsRollup {
+    type Error = ModelValidationError;
+
+    fn try_from(value: AnalyticsRollupRecord) -> Result<Self, Self::Error> {
+        let metric: AnalyticsMetric = value.metric.parse()?;
+        let total = value.total.cast_unsigned();
+        let samples = value.samples.cast_unsigned();
+        let value_of_bucket = match metric {
+            AnalyticsMetric::DailyActiveUsers | AnalyticsMetric::Posts => total,
+            AnalyticsMetric::TimelineLatency => total.checked_div(samples).unwrap_or_default(),
+        };
+
+        Ok(Self {
+            metric,
+            bucket_start: value.bucket_start.as_utc(),
+            value: value_of_bucket,
+            samples,
+        })
+    }
+}
+
+impl TryFrom<PendingOidcLoginRecord> for PendingOidcLogin {
+    type Error = ModelValidationError;
+
+    fn try_from(value: PendingOidcLoginRecord) -> Result<Self, Self::Error> {
+        Ok(Self {
+            tenant: TenantId::new(value.tenant)?,
+            provider: value.provider.parse()?,
+            nonce: value.nonce.try_into()?,
+            code_verifier: value.code_verifier.try_into()?,
+            handle: value.handle.map(UserHandle::new).transpose()?,
+            link_user: value
+                .link_user_snowflake
+                .map(|snowflake| snowflake.cast_unsigned().into()),
+        })
+    }
+}
+
+impl TryFrom<OidcIdentityRecord> for OidcIdentity {
+    type Error = ModelValidationError;
+
+    fn try_from(value: OidcIdentityRecord) -> Result<Self, Self::Error> {
+        Ok(Self {
+            provider: value.provider.parse()?,
+            linked_at: value.linked_at.as_utc(),
         })
     }
 }
@@ -77,12 +535,271 @@ impl TryFrom<AuthenticationRecord> for Authentication {
     fn try_from(value: AuthenticationRecord) -> Result<Self, Self::Error> {
         Ok(Self {
             user: value.user_snowflake.cast_unsigned().into(),
+            tenant: TenantId::new(value.tenant)?,
             token_hash: value.token_hash.try_into()?,
             created_at: value.created_at.as_utc(),
             expires_after: value
                 .expires_after_seconds
                 .map(|seconds| Duration::seconds(seconds).try_into())
                 .transpose()?,
+            impersonator: value
+                .impersonator_snowflake
+                .map(|snowflake| snowflake.cast_unsigned().into()),
+        })
+    }
+}
+
+impl TryFrom<ReportRecord> for Report {
+    type Error = ModelValidationError;
+
+    fn try_from(value: ReportRecord) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: value.report_snowflake.cast_unsigned().into(),
+            reporter: value
+                .reporter_snowflake
+                .map(|snowflake| snowflake.cast_unsigned().into()),
+            target_user: value.target_user_snowflake.cast_unsigned().into(),
+            target_post: value
+                .target_post_snowflake
+                .map(|snowflake| snowflake.cast_unsigned().into()),
+            category: value.category.parse()?,
+            comment: ReportComment::new(value.comment)?,
+            assignee: value
+                .assignee_snowflake
+                .map(|snowflake| snowflake.cast_unsigned().into()),
+            resolved_at: value.resolved_at.map(PrimitiveDateTime::as_utc),
+            resolution: value.resolution.as_deref().map(str::parse).transpose()?,
+        })
+    }
+}
+
+impl TryFrom<ReportNoteRecord> for ReportNote {
+    type Error = ModelValidationError;
+
+    fn try_from(value: ReportNoteRecord) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: value.note_snowflake.cast_unsigned().into(),
+            report: value.report_snowflake.cast_unsigned().into(),
+            author: value.author_snowflake.cast_unsigned().into(),
+            content: ReportNoteContent::new(value.content)?,
+        })
+    }
+}
+
+impl TryFrom<ModerationLogRecord> for ModerationLogEntry {
+    type Error = ModelValidationError;
+
+    fn try_from(value: ModerationLogRecord) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: value.entry_snowflake.cast_unsigned().into(),
+            moderator: value.moderator_snowflake.cast_unsigned().into(),
+            action: value.action.parse()?,
+            report: value
+                .report_snowflake
+                .map(|snowflake| snowflake.cast_unsigned().into()),
+            target_user: value
+                .target_user_snowflake
+                .map(|snowflake| snowflake.cast_unsigned().into()),
+            target_post: value
+                .target_post_snowflake
+                .map(|snowflake| snowflake.cast_unsigned().into()),
+        })
+    }
+}
+
+impl TryFrom<FilterRecord> for Filter {
+    type Error = ModelValidationError;
+
+    fn try_from(value: FilterRecord) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: value.filter_snowflake.cast_unsigned().into(),
+            user: value.user_snowflake.cast_unsigned().into(),
+            settings: FilterSettings {
+                phrase: value.phrase,
+                regex: value.regex,
+                contexts: value
+                    .contexts
+                    .iter()
+                    .map(|context| context.parse())
+                    .collect::<Result<_, _>>()?,
+                action: value.action.parse()?,
+                expires_at: value.expires_at.map(PrimitiveDateTime::as_utc),
+            },
+            version: value.version.cast_unsigned(),
+        })
+    }
+}
+
+impl TryFrom<AnnouncementRecord> for Announcement {
+    type Error = ModelValidationError;
+
+    fn try_from(value: AnnouncementRecord) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: value.announcement_snowflake.cast_unsigned().into(),
+            title: AnnouncementTitle::new(value.title)?,
+            body: AnnouncementBody::new(value.body)?,
+            expires_at: value.expires_at.map(PrimitiveDateTime::as_utc),
+        })
+    }
+}
+
+impl TryFrom<UserAnnouncementRecord> for UserAnnouncement {
+    type Error = ModelValidationError;
+
+    fn try_from(value: UserAnnouncementRecord) -> Result<Self, Self::Error> {
+        Ok(Self {
+            announcement: Announcement::try_from(AnnouncementRecord {
+                announcement_snowflake: value.announcement_snowflake,
+                title: value.title,
+                body: value.body,
+                expires_at: value.expires_at,
+            })?,
+            read: value.read,
+            dismissed: value.dismissed,
+        })
+    }
+}
+
+impl TryFrom<PolicyRecord> for Policy {
+    type Error = ModelValidationError;
+
+    fn try_from(value: PolicyRecord) -> Result<Self, Self::Error> {
+        Ok(Self {
+            kind: value.kind.parse()?,
+            version: value.version.cast_unsigned(),
+            content: PolicyContent::new(value.content)?,
+            published_at: value.published_at.as_utc(),
+        })
+    }
+}
+
+impl TryFrom<NotificationRecord> for Notification {
+    type Error = ModelValidationError;
+
+    fn try_from(value: NotificationRecord) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: value.notification_snowflake.cast_unsigned().into(),
+            kind: value.kind.parse()?,
+            actor: User {
+                id: value.user_snowflake.cast_unsigned().into(),
+                handle: UserHandle::new(value.handle)?,
+                verified: value.verified,
+            },
+            post: value
+                .post_snowflake
+                .map(|snowflake| snowflake.cast_unsigned().into()),
+        })
+    }
+}
+
+impl TryFrom<EmailDigestRecord> for EmailDigestSubscription {
+    type Error = ModelValidationError;
+
+    fn try_from(value: EmailDigestRecord) -> Result<Self, Self::Error> {
+        Ok(Self {
+            user: value.user_snowflake.cast_unsigned().into(),
+            settings: EmailDigestSettings {
+                email: EmailAddress::new(value.email)?,
+                frequency: value.frequency.parse()?,
+            },
+            unsubscribe_token: value.unsubscribe_token.try_into()?,
+            digested_up_to: value
+                .digested_up_to
+                .map(|snowflake| snowflake.cast_unsigned().into()),
+        })
+    }
+}
+
+impl TryFrom<MessageRecord> for Message {
+    type Error = ModelValidationError;
+
+    fn try_from(value: MessageRecord) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: value.message_snowflake.cast_unsigned().into(),
+            author: value.author_snowflake.cast_unsigned().into(),
+            // The database ensures that exactly one of them is set.
+            body: match value.encrypted_payload {
+                Some(payload) => MessageBody::Encrypted(EncryptedPayload::new(payload)?),
+                None => {
+                    MessageBody::Content(MessageContent::new(value.content.unwrap_or_default())?)
+                }
+            },
+        })
+    }
+}
+
+impl From<KeyPairRecord> for KeyPair {
+    fn from(value: KeyPairRecord) -> Self {
+        Self {
+            public_key_pem: value.public_key_pem,
+            private_key_pem: value.private_key_pem,
+        }
+    }
+}
+
+impl From<RemoteActorKeyRecord> for PublicKey {
+    fn from(value: RemoteActorKeyRecord) -> Self {
+        Self {
+            id: value.key_id,
+            owner: value.owner,
+            public_key_pem: value.public_key_pem,
+        }
+    }
+}
+
+impl TryFrom<WebhookRecord> for Webhook {
+    type Error = ModelValidationError;
+
+    fn try_from(value: WebhookRecord) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: value.webhook_snowflake.cast_unsigned().into(),
+            url: WebhookUrl::new(value.url)?,
+            events: value
+                .events
+                .iter()
+                .map(|event| event.parse())
+                .collect::<Result<_, _>>()?,
+            disabled_at: value.disabled_at.map(PrimitiveDateTime::as_utc),
+        })
+    }
+}
+
+impl From<WebhookTargetRecord> for WebhookTarget {
+    fn from(value: WebhookTargetRecord) -> Self {
+        Self {
+            id: value.webhook_snowflake.cast_unsigned().into(),
+            url: value.url,
+            secret: WebhookSecret(value.secret),
+        }
+    }
+}
+
+fn parse_job_payload(payload: &str) -> Result<JobPayload, InvalidJobPayloadError> {
+    serde_json::from_str(payload).map_err(|error| InvalidJobPayloadError(error.to_string()))
+}
+
+impl TryFrom<JobRecord> for Job {
+    type Error = ModelValidationError;
+
+    fn try_from(value: JobRecord) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: value.job_snowflake.cast_unsigned().into(),
+            payload: parse_job_payload(&value.payload)?,
+            attempts: value.attempts.cast_unsigned(),
+        })
+    }
+}
+
+impl TryFrom<DeadJobRecord> for DeadJob {
+    type Error = ModelValidationError;
+
+    fn try_from(value: DeadJobRecord) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: value.job_snowflake.cast_unsigned().into(),
+            payload: parse_job_payload(&value.payload)?,
+            attempts: value.attempts.cast_unsigned(),
+            last_error: value.last_error,
+            dead_at: value.dead_at.as_utc(),
         })
     }
 }
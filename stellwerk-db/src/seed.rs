@@ -0,0 +1,181 @@
+//! Fake users, follows, and posts for local development and load testing.
+//!
+//! Handles, the follow graph, and post contents are deterministic from the seed,
+//! but IDs are generated as usual. Handles contain the seed, so seeding again with the same seed
+//! fails because they are taken.
+
+use crate::{
+    client::{DbClient, Result},
+    store::Store,
+};
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::IndexedRandom};
+use std::collections::HashSet;
+use stellwerk_common::model::{
+    Id,
+    post::{CreatePost, PostContent},
+    tenant::TenantId,
+    user::{CreateUser, UserHandle, UserMarker},
+};
+
+const WORDS: &[&str] = &[
+    "signal",
+    "track",
+    "switch",
+    "platform",
+    "train",
+    "station",
+    "timetable",
+    "delay",
+    "junction",
+    "freight",
+    "express",
+    "tunnel",
+    "bridge",
+    "depot",
+    "conductor",
+    "ticket",
+    "carriage",
+    "locomotive",
+    "siding",
+    "interlocking",
+    "today",
+    "again",
+    "finally",
+    "late",
+    "early",
+    "the",
+    "a",
+    "on",
+    "at",
+    "with",
+    "and",
+    "but",
+    "so",
+    "very",
+    "quite",
+    "not",
+];
+
+/// How much to seed.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct SeedSettings {
+    pub users: u32,
+    /// Between random pairs of the seeded users, capped at the number of possible pairs.
+    pub follows: u32,
+    /// By random seeded users. Posts are not seeded without users.
+    pub posts: u32,
+    pub seed: u64,
+}
+
+impl Default for SeedSettings {
+    fn default() -> Self {
+        Self {
+            users: 100,
+            follows: 1_000,
+            posts: 1_000,
+            seed: 0,
+        }
+    }
+}
+
+/// What was seeded.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash)]
+pub struct SeedSummary {
+    pub users: u64,
+    pub follows: u64,
+    pub posts: u64,
+}
+
+/// Creates users named `seed{seed}_{n}`, then follows between them, then posts by them.
+pub async fn seed(db: &DbClient, settings: &SeedSettings) -> Result<SeedSummary> {
+    let mut rng = StdRng::seed_from_u64(settings.seed);
+    let mut summary = SeedSummary::default();
+
+    let users = seed_users(db, settings, &mut summary).await?;
+
+    let user_count = users.len() as u64;
+    let max_follows = user_count * user_count.saturating_sub(1);
+    let mut follows = HashSet::new();
+    while summary.follows < u64::from(settings.follows).min(max_follows) {
+        let (follower, target) = random_pair(&mut rng, &users);
+        if follows.insert((follower, target)) {
+            db.follow_user(follower, target).await?;
+            summary.follows += 1;
+        }
+    }
+
+    seed_posts(db, settings, &mut rng, &users, &mut summary).await?;
+
+    Ok(summary)
+}
+
+/// Like [`seed`], but into any [`Store`]. Stores have no follows, so `settings.follows` is ignored,
+/// and the posts differ from those [`seed`] creates with the same seed.
+pub async fn seed_store(store: &dyn Store, settings: &SeedSettings) -> Result<SeedSummary> {
+    let mut rng = StdRng::seed_from_u64(settings.seed);
+    let mut summary = SeedSummary::default();
+
+    let users = seed_users(store, settings, &mut summary).await?;
+    seed_posts(store, settings, &mut rng, &users, &mut summary).await?;
+
+    Ok(summary)
+}
+
+async fn seed_users(
+    store: &dyn Store,
+    settings: &SeedSettings,
+    summary: &mut SeedSummary,
+) -> Result<Vec<Id<UserMarker>>> {
+    let mut users = Vec::with_capacity(settings.users as usize);
+    for n in 0..settings.users {
+        let handle = UserHandle::new(format!("seed{}_{n}", settings.seed))
+            .expect("Seeded handles are short");
+        users.push(
+            store
+                .create_user(&TenantId::default(), &CreateUser { handle })
+                .await?,
+        );
+        summary.users += 1;
+    }
+
+    Ok(users)
+}
+
+async fn seed_posts(
+    store: &dyn Store,
+    settings: &SeedSettings,
+    rng: &mut StdRng,
+    users: &[Id<UserMarker>],
+    summary: &mut SeedSummary,
+) -> Result<()> {
+    if users.is_empty() {
+        return Ok(());
+    }
+
+    for _ in 0..settings.posts {
+        let author = *users.choose(rng).expect("Users are not empty");
+        let content =
+            PostContent::new(random_content(rng)).expect("Seeded contents are short and not empty");
+        store.create_post(&CreatePost { author, content }).await?;
+        summary.posts += 1;
+    }
+
+    Ok(())
+}
+
+/// Two different users of `users`, which has at least two.
+fn random_pair(rng: &mut StdRng, users: &[Id<UserMarker>]) -> (Id<UserMarker>, Id<UserMarker>) {
+    let follower = rng.random_range(0..users.len());
+    let target = (follower + rng.random_range(1..users.len())) % users.len();
+
+    (users[follower], users[target])
+}
+
+fn random_content(rng: &mut StdRng) -> String {
+    let len = rng.random_range(3..=40);
+    let words: Vec<_> = (0..len)
+        .map(|_| *WORDS.choose(rng).expect("Words are not empty"))
+        .collect();
+
+    words.join(" ")
+}
@@ -0,0 +1,488 @@
+//! A [`Store`] in `SQLite`, for small deployments and tests without Postgres.
+//!
+//! It has its own migrations in `migrations-sqlite`, and shares the records of the Postgres
+//! queries. Since federation and policies need Postgres, no user is remote or has policies
+//! to accept. Handles are unique across all tenants, not per tenant.
+//! Unlike [`DbClient`](crate::client::DbClient), it does not emit
+//! [`Event`](stellwerk_common::event::Event)s.
+
+use crate::{
+    client::Result,
+    record::{
+        AuthenticationRecord, FullPostRecord, PartialPostRecord, UserProfileRecord, UserRecord,
+    },
+    store::Store,
+};
+use async_trait::async_trait;
+use sqlx::{SqlitePool, migrate, query, query_as, query_scalar};
+use stellwerk_common::{
+    model::{
+        Id, ModelValidationError, StellwerkAtomicSnowflakeGenerator,
+        auth::{AuthTokenHash, Authentication},
+        post::{CreatePost, PartialPost, Post, PostMarker, PostVersion},
+        tenant::TenantId,
+        user::{CreateUser, User, UserHandle, UserMarker, UserProfile, UserRole},
+    },
+    snowflake::{ProcessId, WorkerId},
+};
+use time::{PrimitiveDateTime, UtcDateTime};
+
+#[derive(Debug)]
+pub struct SqliteStore {
+    pool: SqlitePool,
+    snowflake_generator: StellwerkAtomicSnowflakeGenerator,
+}
+
+impl SqliteStore {
+    /// `url` is like `sqlite://stellwerk.db?mode=rwc`.
+    /// In-memory databases are only shared by a single connection, so tests should migrate a
+    /// pool with one connection themselves and use [`SqliteStore::new`].
+    pub async fn connect_and_migrate(
+        url: &str,
+        worker_id: WorkerId,
+        process_id: ProcessId,
+    ) -> Result<Self> {
+        let pool = SqlitePool::connect(url).await?;
+        migrate!("./migrations-sqlite").run(&pool).await?;
+
+        Ok(Self::new(pool, worker_id, process_id))
+    }
+
+    #[must_use]
+    pub fn new(pool: SqlitePool, worker_id: WorkerId, process_id: ProcessId) -> Self {
+        let snowflake_generator = StellwerkAtomicSnowflakeGenerator::new(worker_id, process_id);
+
+        Self {
+            pool,
+            snowflake_generator,
+        }
+    }
+
+    /// Stores an auth token, which is otherwise only done by the Postgres login flow.
+    pub async fn insert_auth(&self, authentication: &Authentication) -> Result<()> {
+        let created_at = PrimitiveDateTime::new(
+            authentication.created_at.date(),
+            authentication.created_at.time(),
+        );
+
+        query(
+            "
+            INSERT INTO auth_tokens (
+                token_hash, user_snowflake, tenant, created_at, expires_after_seconds
+            )
+            VALUES ($1, $2, $3, $4, $5)
+            ",
+        )
+        .bind(authentication.token_hash.0.as_slice())
+        .bind(authentication.user.snowflake().get().cast_signed())
+        .bind(authentication.tenant.get())
+        .bind(created_at)
+        .bind(
+            authentication
+                .expires_after
+                .map(|duration| duration.get().whole_seconds()),
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Store for SqliteStore {
+    async fn fetch_user(&self, user_id: Id<UserMarker>) -> Result<Option<User>> {
+        let record = query_as::<_, UserRecord>(
+            "
+            SELECT user_snowflake, handle, verified_at IS NOT NULL AS verified
+            FROM users
+            WHERE user_snowflake = $1
+            ",
+        )
+        .bind(user_id.snowflake().get().cast_signed())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let user = record.map(User::try_from).transpose()?;
+        Ok(user)
+    }
+
+    async fn fetch_user_by_handle(
+        &self,
+        tenant: &TenantId,
+        handle: &UserHandle,
+    ) -> Result<Option<User>> {
+        let record = query_as::<_, UserRecord>(
+            "
+            SELECT user_snowflake, handle, verified_at IS NOT NULL AS verified
+            FROM users
+            WHERE tenant = $1 AND handle = $2
+            ",
+        )
+        .bind(tenant.get())
+        .bind(handle.get())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let user = record.map(User::try_from).transpose()?;
+        Ok(user)
+    }
+
+    async fn fetch_user_profile(&self, user_id: Id<UserMarker>) -> Result<Option<UserProfile>> {
+        let record = query_as::<_, UserProfileRecord>(
+            "
+            SELECT
+                user_snowflake, handle, verified_at IS NOT NULL AS verified, website, post_count,
+                follower_count, following_count
+            FROM users
+            WHERE user_snowflake = $1
+            ",
+        )
+        .bind(user_id.snowflake().get().cast_signed())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let profile = record.map(UserProfile::try_from).transpose()?;
+        Ok(profile)
+    }
+
+    async fn fetch_user_version(&self, user_id: Id<UserMarker>) -> Result<Option<u64>> {
+        let version: Option<i64> = query_scalar(
+            "
+            SELECT profile_version
+            FROM users
+            WHERE user_snowflake = $1
+            ",
+        )
+        .bind(user_id.snowflake().get().cast_signed())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(version.map(i64::cast_unsigned))
+    }
+
+    async fn fetch_user_role(&self, user_id: Id<UserMarker>) -> Result<Option<UserRole>> {
+        let role: Option<String> = query_scalar(
+            "
+            SELECT role
+            FROM users
+            WHERE user_snowflake = $1
+            ",
+        )
+        .bind(user_id.snowflake().get().cast_signed())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let role = role
+            .map(|role| role.parse())
+            .transpose()
+            .map_err(ModelValidationError::from)?;
+        Ok(role)
+    }
+
+    async fn is_remote_user(&self, _user_id: Id<UserMarker>) -> Result<bool> {
+        Ok(false)
+    }
+
+    async fn create_user(&self, tenant: &TenantId, user: &CreateUser) -> Result<Id<UserMarker>> {
+        let user_snowflake = self.snowflake_generator.generate()?;
+
+        query(
+            "
+            INSERT INTO users (user_snowflake, handle, tenant)
+            VALUES ($1, $2, $3)
+            ",
+        )
+        .bind(user_snowflake.get().cast_signed())
+        .bind(user.handle.get())
+        .bind(tenant.get())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(Id::new(user_snowflake))
+    }
+
+    async fn fetch_user_posts(
+        &self,
+        user_id: Id<UserMarker>,
+        max_id: Option<Id<PostMarker>>,
+        since_id: Option<Id<PostMarker>>,
+        limit: u32,
+    ) -> Result<Option<Vec<PartialPost>>> {
+        let mut transaction = self.pool.begin().await?;
+
+        let user_exists: bool = query_scalar(
+            "
+            SELECT EXISTS(SELECT 1 FROM users WHERE user_snowflake = $1)
+            ",
+        )
+        .bind(user_id.snowflake().get().cast_signed())
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        if !user_exists {
+            return Ok(None);
+        }
+
+        let mut records = if max_id.is_none() && since_id.is_none() {
+            query_as::<_, PartialPostRecord>(
+                "
+                SELECT post_snowflake, content, true as pinned
+                FROM posts
+                WHERE user_snowflake = $1 AND pinned_at IS NOT NULL
+                ORDER BY pinned_at DESC
+                ",
+            )
+            .bind(user_id.snowflake().get().cast_signed())
+            .fetch_all(&mut *transaction)
+            .await?
+        } else {
+            Vec::new()
+        };
+
+        let unpinned_records = query_as::<_, PartialPostRecord>(
+            "
+            SELECT post_snowflake, content, false as pinned
+            FROM posts
+            WHERE
+                user_snowflake = $1
+                AND pinned_at IS NULL
+                AND ($2 IS NULL OR post_snowflake < $2)
+                AND ($3 IS NULL OR post_snowflake > $3)
+            ORDER BY post_snowflake DESC
+            LIMIT $4
+            ",
+        )
+        .bind(user_id.snowflake().get().cast_signed())
+        .bind(max_id.map(|id| id.snowflake().get().cast_signed()))
+        .bind(since_id.map(|id| id.snowflake().get().cast_signed()))
+        .bind(i64::from(limit))
+        .fetch_all(&mut *transaction)
+        .await?;
+        records.extend(unpinned_records);
+
+        let posts = records
+            .into_iter()
+            .map(PartialPost::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Some(posts))
+    }
+
+    async fn fetch_post(&self, post_id: Id<PostMarker>) -> Result<Option<Post>> {
+        let record = query_as::<_, FullPostRecord>(
+            "
+            SELECT
+                posts.post_snowflake,
+                posts.content,
+                posts.pinned_at IS NOT NULL as pinned,
+                users.user_snowflake,
+                users.handle,
+                users.verified_at IS NOT NULL AS verified
+            FROM
+                posts NATURAL JOIN users
+            WHERE
+                posts.post_snowflake = $1
+            ",
+        )
+        .bind(post_id.snowflake().get().cast_signed())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let post = record.map(Post::try_from).transpose()?;
+        Ok(post)
+    }
+
+    async fn fetch_post_version(&self, post_id: Id<PostMarker>) -> Result<Option<PostVersion>> {
+        let record: Option<(i64, i64)> = query_as(
+            "
+            SELECT posts.edit_version, users.profile_version
+            FROM posts NATURAL JOIN users
+            WHERE posts.post_snowflake = $1
+            ",
+        )
+        .bind(post_id.snowflake().get().cast_signed())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(record.map(|(post, author)| PostVersion {
+            post: post.cast_unsigned(),
+            author: author.cast_unsigned(),
+        }))
+    }
+
+    /// Users cannot be shadowbanned here, so `viewer` does not matter.
+    async fn fetch_public_posts(
+        &self,
+        tenant: &TenantId,
+        _viewer: Option<Id<UserMarker>>,
+        max_id: Option<Id<PostMarker>>,
+        since_id: Option<Id<PostMarker>>,
+        limit: u32,
+    ) -> Result<Vec<Post>> {
+        let records = query_as::<_, FullPostRecord>(
+            "
+            SELECT
+                posts.post_snowflake,
+                posts.content,
+                posts.pinned_at IS NOT NULL as pinned,
+                users.user_snowflake,
+                users.handle,
+                users.verified_at IS NOT NULL AS verified
+            FROM
+                posts NATURAL JOIN users
+            WHERE
+                posts.tenant = $1
+                AND ($2 IS NULL OR posts.post_snowflake < $2)
+                AND ($3 IS NULL OR posts.post_snowflake > $3)
+            ORDER BY
+                posts.post_snowflake DESC
+            LIMIT $4
+            ",
+        )
+        .bind(tenant.get())
+        .bind(max_id.map(|id| id.snowflake().get().cast_signed()))
+        .bind(since_id.map(|id| id.snowflake().get().cast_signed()))
+        .bind(i64::from(limit))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let posts = records
+            .into_iter()
+            .map(Post::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(posts)
+    }
+
+    async fn create_post(&self, post: &CreatePost) -> Result<Id<PostMarker>> {
+        let post_snowflake = self.snowflake_generator.generate()?;
+
+        let mut transaction = self.pool.begin().await?;
+
+        query(
+            "
+            INSERT INTO posts (post_snowflake, content, user_snowflake, tenant)
+            VALUES ($1, $2, $3, (SELECT tenant FROM users WHERE user_snowflake = $3))
+            ",
+        )
+        .bind(post_snowflake.get().cast_signed())
+        .bind(post.content.get())
+        .bind(post.author.snowflake().get().cast_signed())
+        .execute(&mut *transaction)
+        .await?;
+
+        query(
+            "
+            UPDATE users
+            SET post_count = post_count + 1,
+                profile_version = profile_version + 1
+            WHERE user_snowflake = $1
+            ",
+        )
+        .bind(post.author.snowflake().get().cast_signed())
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(Id::new(post_snowflake))
+    }
+
+    async fn pin_post(&self, post_id: Id<PostMarker>, max_pinned: usize) -> Result<bool> {
+        let now_utc = UtcDateTime::now();
+        let now_primitive = PrimitiveDateTime::new(now_utc.date(), now_utc.time());
+
+        // SQLite serializes writes, so of concurrent pins that both passed the check,
+        // all but one fail instead of exceeding the limit.
+        let mut transaction = self.pool.begin().await?;
+
+        let Some(author_snowflake): Option<i64> = query_scalar(
+            "
+            SELECT user_snowflake
+            FROM posts
+            WHERE post_snowflake = $1
+            ",
+        )
+        .bind(post_id.snowflake().get().cast_signed())
+        .fetch_optional(&mut *transaction)
+        .await?
+        else {
+            return Ok(true);
+        };
+
+        let pinned_count: i64 = query_scalar(
+            "
+            SELECT count(1)
+            FROM posts
+            WHERE
+                user_snowflake = $1
+                AND pinned_at IS NOT NULL
+                AND post_snowflake != $2
+            ",
+        )
+        .bind(author_snowflake)
+        .bind(post_id.snowflake().get().cast_signed())
+        .fetch_one(&mut *transaction)
+        .await?;
+
+        if usize::try_from(pinned_count).is_ok_and(|pinned_count| pinned_count >= max_pinned) {
+            return Ok(false);
+        }
+
+        query(
+            "
+            UPDATE posts
+            SET pinned_at = $2,
+                edit_version = edit_version + 1
+            WHERE post_snowflake = $1 AND pinned_at IS NULL
+            ",
+        )
+        .bind(post_id.snowflake().get().cast_signed())
+        .bind(now_primitive)
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(true)
+    }
+
+    async fn unpin_post(&self, post_id: Id<PostMarker>) -> Result<()> {
+        query(
+            "
+            UPDATE posts
+            SET pinned_at = NULL,
+                edit_version = edit_version + 1
+            WHERE post_snowflake = $1 AND pinned_at IS NOT NULL
+            ",
+        )
+        .bind(post_id.snowflake().get().cast_signed())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn fetch_auth(&self, token_hash: &AuthTokenHash) -> Result<Option<Authentication>> {
+        let record = query_as::<_, AuthenticationRecord>(
+            "
+            SELECT
+                user_snowflake, tenant, token_hash, created_at, expires_after_seconds,
+                impersonator_snowflake
+            FROM auth_tokens
+            WHERE token_hash = $1
+            ",
+        )
+        .bind(token_hash.0.as_slice())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let authentication = record.map(Authentication::try_from).transpose()?;
+        Ok(authentication)
+    }
+
+    async fn has_pending_policies(&self, _user_id: Id<UserMarker>) -> Result<bool> {
+        Ok(false)
+    }
+}
@@ -0,0 +1,166 @@
+//! The storage the core of the API depends on, so that it can run without a database.
+//!
+//! [`DbClient`] stores in Postgres, and [`MemoryStore`](crate::memory::MemoryStore) in memory,
+//! for unit tests. See the methods of [`DbClient`] for what the methods do.
+
+use crate::client::{DbClient, Result};
+use async_trait::async_trait;
+use std::fmt::Debug;
+use stellwerk_common::model::{
+    Id,
+    auth::{AuthTokenHash, Authentication},
+    post::{CreatePost, PartialPost, Post, PostMarker, PostVersion},
+    tenant::TenantId,
+    user::{CreateUser, User, UserHandle, UserMarker, UserProfile, UserRole},
+};
+
+/// Users, posts, and auth tokens.
+#[async_trait]
+pub trait Store: Debug + Send + Sync {
+    async fn fetch_user(&self, user_id: Id<UserMarker>) -> Result<Option<User>>;
+
+    async fn fetch_user_by_handle(
+        &self,
+        tenant: &TenantId,
+        handle: &UserHandle,
+    ) -> Result<Option<User>>;
+
+    async fn fetch_user_profile(&self, user_id: Id<UserMarker>) -> Result<Option<UserProfile>>;
+
+    /// Increases whenever the profile of the user changes.
+    async fn fetch_user_version(&self, user_id: Id<UserMarker>) -> Result<Option<u64>>;
+
+    async fn fetch_user_role(&self, user_id: Id<UserMarker>) -> Result<Option<UserRole>>;
+
+    /// Whether the user stands in for an actor of another server or mirrors an account of
+    /// another network.
+    async fn is_remote_user(&self, user_id: Id<UserMarker>) -> Result<bool>;
+
+    async fn create_user(&self, tenant: &TenantId, user: &CreateUser) -> Result<Id<UserMarker>>;
+
+    /// Returns the user's posts, newest first, or `None` if the user does not exist.
+    /// `max_id` and `since_id` are exclusive bounds.
+    ///
+    /// Pinned posts are only returned on the first page (if neither bound is given),
+    /// where they are prepended to the up to `limit` unpinned posts.
+    async fn fetch_user_posts(
+        &self,
+        user_id: Id<UserMarker>,
+        max_id: Option<Id<PostMarker>>,
+        since_id: Option<Id<PostMarker>>,
+        limit: u32,
+    ) -> Result<Option<Vec<PartialPost>>>;
+
+    async fn fetch_post(&self, post_id: Id<PostMarker>) -> Result<Option<Post>>;
+
+    async fn fetch_post_version(&self, post_id: Id<PostMarker>) -> Result<Option<PostVersion>>;
+
+    /// Returns the newest posts of all users of the tenant, newest first.
+    /// Posts of shadowbanned users are only returned to themselves as `viewer`.
+    /// `max_id` and `since_id` are exclusive bounds.
+    async fn fetch_public_posts(
+        &self,
+        tenant: &TenantId,
+        viewer: Option<Id<UserMarker>>,
+        max_id: Option<Id<PostMarker>>,
+        since_id: Option<Id<PostMarker>>,
+        limit: u32,
+    ) -> Result<Vec<Post>>;
+
+    async fn create_post(&self, post: &CreatePost) -> Result<Id<PostMarker>>;
+
+    /// Pins the post to its author's profile.
+    /// Returns `false` if the author already has `max_pinned` pinned posts.
+    /// Pinning an already pinned or nonexistent post succeeds without changes.
+    async fn pin_post(&self, post_id: Id<PostMarker>, max_pinned: usize) -> Result<bool>;
+
+    async fn unpin_post(&self, post_id: Id<PostMarker>) -> Result<()>;
+
+    async fn fetch_auth(&self, token_hash: &AuthTokenHash) -> Result<Option<Authentication>>;
+
+    async fn has_pending_policies(&self, user_id: Id<UserMarker>) -> Result<bool>;
+}
+
+#[async_trait]
+impl Store for DbClient {
+    async fn fetch_user(&self, user_id: Id<UserMarker>) -> Result<Option<User>> {
+        DbClient::fetch_user(self, user_id).await
+    }
+
+    async fn fetch_user_by_handle(
+        &self,
+        tenant: &TenantId,
+        handle: &UserHandle,
+    ) -> Result<Option<User>> {
+        DbClient::fetch_user_by_handle(self, tenant, handle).await
+    }
+
+    async fn fetch_user_profile(&self, user_id: Id<UserMarker>) -> Result<Option<UserProfile>> {
+        DbClient::fetch_user_profile(self, user_id).await
+    }
+
+    async fn fetch_user_version(&self, user_id: Id<UserMarker>) -> Result<Option<u64>> {
+        DbClient::fetch_user_version(self, user_id).await
+    }
+
+    async fn fetch_user_role(&self, user_id: Id<UserMarker>) -> Result<Option<UserRole>> {
+        DbClient::fetch_user_role(self, user_id).await
+    }
+
+    async fn is_remote_user(&self, user_id: Id<UserMarker>) -> Result<bool> {
+        DbClient::is_remote_user(self, user_id).await
+    }
+
+    async fn create_user(&self, tenant: &TenantId, user: &CreateUser) -> Result<Id<UserMarker>> {
+        DbClient::create_user(self, tenant, user).await
+    }
+
+    async fn fetch_user_posts(
+        &self,
+        user_id: Id<UserMarker>,
+        max_id: Option<Id<PostMarker>>,
+        since_id: Option<Id<PostMarker>>,
+        limit: u32,
+    ) -> Result<Option<Vec<PartialPost>>> {
+        DbClient::fetch_user_posts(self, user_id, max_id, since_id, limit).await
+    }
+
+    async fn fetch_post(&self, post_id: Id<PostMarker>) -> Result<Option<Post>> {
+        DbClient::fetch_post(self, post_id).await
+    }
+
+    async fn fetch_post_version(&self, post_id: Id<PostMarker>) -> Result<Option<PostVersion>> {
+        DbClient::fetch_post_version(self, post_id).await
+    }
+
+    async fn fetch_public_posts(
+        &self,
+        tenant: &TenantId,
+        viewer: Option<Id<UserMarker>>,
+        max_id: Option<Id<PostMarker>>,
+        since_id: Option<Id<PostMarker>>,
+        limit: u32,
+    ) -> Result<Vec<Post>> {
+        DbClient::fetch_public_posts(self, tenant, viewer, max_id, since_id, limit).await
+    }
+
+    async fn create_post(&self, post: &CreatePost) -> Result<Id<PostMarker>> {
+        DbClient::create_post(self, post).await
+    }
+
+    async fn pin_post(&self, post_id: Id<PostMarker>, max_pinned: usize) -> Result<bool> {
+        DbClient::pin_post(self, post_id, max_pinned).await
+    }
+
+    async fn unpin_post(&self, post_id: Id<PostMarker>) -> Result<()> {
+        DbClient::unpin_post(self, post_id).await
+    }
+
+    async fn fetch_auth(&self, token_hash: &AuthTokenHash) -> Result<Option<Authentication>> {
+        DbClient::fetch_auth(self, token_hash).await
+    }
+
+    async fn has_pending_policies(&self, user_id: Id<UserMarker>) -> Result<bool> {
+        DbClient::has_pending_policies(self, user_id).await
+    }
+}
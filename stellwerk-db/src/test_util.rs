@@ -0,0 +1,148 @@
+//! Disposable databases for tests, with the `test-util` feature.
+//!
+//! Every [`TestDatabase`] is a new database on the server at `TEST_DATABASE_URL`, or `DATABASE_URL`
+//! if that is not set, whose user must be allowed to create databases.
+//! They are cloned from the template database `stellwerk_test_template`, which is migrated once
+//! per process, so tests can run in parallel, also in several processes, without seeing each
+//! other's data.
+//!
+//! ```no_run
+//! # async fn test() {
+//! use stellwerk_db::test_util::TestDatabase;
+//!
+//! let database = TestDatabase::new().await;
+//! let stats = database.client().fetch_instance_stats().await.unwrap();
+//! assert_eq!(stats.user_count, 0);
+//! database.remove().await;
+//! # }
+//! ```
+
+use crate::client::{DbClient, MIGRATOR};
+use sqlx::{
+    Connection, Executor, PgConnection, PgPool,
+    postgres::{PgConnectOptions, PgPoolOptions},
+    query,
+};
+use std::{
+    env,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use stellwerk_common::snowflake::{ProcessId, WorkerId};
+
+const TEMPLATE: &str = "stellwerk_test_template";
+/// Serializes migrating the template and cloning it, which fails while the template is in use.
+const TEMPLATE_LOCK: i64 = 0x5465_6D70_6C61_7465;
+
+static TEMPLATE_MIGRATED: AtomicBool = AtomicBool::new(false);
+
+/// A database of its own for a test.
+///
+/// It is only removed by [`TestDatabase::remove`], so the databases of failed tests
+/// stay around for inspection, named `stellwerk_test_{random}`.
+#[derive(Debug)]
+pub struct TestDatabase {
+    client: DbClient,
+    pool: PgPool,
+    options: PgConnectOptions,
+    name: String,
+}
+
+impl TestDatabase {
+    /// # Panics
+    /// If the database server cannot be reached, or the template cannot be migrated or cloned.
+    pub async fn new() -> Self {
+        let url = env::var("TEST_DATABASE_URL")
+            .or_else(|_| env::var("DATABASE_URL"))
+            .expect("TEST_DATABASE_URL or DATABASE_URL must be set for database tests");
+        let options: PgConnectOptions = url.parse().expect("The test database URL is invalid");
+        let name = format!("stellwerk_test_{:016x}", rand::random::<u64>());
+
+        let mut connection = PgConnection::connect_with(&options.clone().database("postgres"))
+            .await
+            .expect("Connecting to the test database server failed");
+        query("SELECT pg_advisory_lock($1)")
+            .bind(TEMPLATE_LOCK)
+            .execute(&mut connection)
+            .await
+            .expect("Locking the template database failed");
+
+        if !TEMPLATE_MIGRATED.load(Ordering::Acquire) {
+            migrate_template(&mut connection, &options).await;
+            TEMPLATE_MIGRATED.store(true, Ordering::Release);
+        }
+        connection
+            .execute(format!(r#"CREATE DATABASE "{name}" TEMPLATE "{TEMPLATE}""#).as_str())
+            .await
+            .expect("Cloning the template database failed");
+
+        query("SELECT pg_advisory_unlock($1)")
+            .bind(TEMPLATE_LOCK)
+            .execute(&mut connection)
+            .await
+            .expect("Unlocking the template database failed");
+        connection.close().await.ok();
+
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect_with(options.clone().database(&name))
+            .await
+            .expect("Connecting to the test database failed");
+        let client = DbClient::new(pool.clone(), WorkerId::default(), ProcessId::default());
+
+        Self {
+            client,
+            pool,
+            options,
+            name,
+        }
+    }
+
+    /// A client of the database, with the default settings and without replicas or a cache.
+    #[must_use]
+    pub fn client(&self) -> &DbClient {
+        &self.client
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Closes the connections of the clients and removes the database.
+    pub async fn remove(self) {
+        self.pool.close().await;
+
+        let mut connection = PgConnection::connect_with(&self.options.database("postgres"))
+            .await
+            .expect("Connecting to the test database server failed");
+        connection
+            .execute(format!(r#"DROP DATABASE "{}" WITH (FORCE)"#, self.name).as_str())
+            .await
+            .expect("Dropping the test database failed");
+        connection.close().await.ok();
+    }
+}
+
+async fn migrate_template(connection: &mut PgConnection, options: &PgConnectOptions) {
+    let exists = query("SELECT FROM pg_database WHERE datname = $1")
+        .bind(TEMPLATE)
+        .fetch_optional(&mut *connection)
+        .await
+        .expect("Looking up the template database failed")
+        .is_some();
+    if !exists {
+        connection
+            .execute(format!(r#"CREATE DATABASE "{TEMPLATE}""#).as_str())
+            .await
+            .expect("Creating the template database failed");
+    }
+
+    let mut template = PgConnection::connect_with(&options.clone().database(TEMPLATE))
+        .await
+        .expect("Connecting to the template database failed");
+    MIGRATOR
+        .run_direct(&mut template)
+        .await
+        .expect("Migrating the template database failed");
+    template.close().await.ok();
+}
@@ -0,0 +1,357 @@
+//! Tests of the [`DbClient`](stellwerk_db::client::DbClient) against a database of their own,
+//! see [`TestDatabase`]. Only built with the `test-util` feature.
+
+use futures_util::TryStreamExt;
+use stellwerk_common::model::{
+    Id,
+    do_not_disturb::DoNotDisturb,
+    filter::{FilterAction, FilterContext, FilterSettings},
+    job::JobKind,
+    notification::{CreateNotification, NotificationKind},
+    post::{CreatePost, PartialPost, Post, PostContent, PostMarker},
+    report::{CreateReport, ReportCategory, ReportComment},
+    tenant::TenantId,
+    user::{CreateUser, UserHandle, UserMarker},
+    webhook::{CreateWebhook, WebhookEventKind, WebhookSecret, WebhookUrl},
+};
+use stellwerk_db::{client::DbError, test_util::TestDatabase};
+use time::{Duration, UtcDateTime};
+
+async fn create_user(database: &TestDatabase, handle: &str) -> Id<UserMarker> {
+    database
+        .client()
+        .create_user(
+            &TenantId::default(),
+            &CreateUser {
+                handle: UserHandle::new(handle.to_owned()).unwrap(),
+            },
+        )
+        .await
+        .unwrap()
+}
+
+async fn create_post(database: &TestDatabase, author: Id<UserMarker>) -> Id<PostMarker> {
+    database
+        .client()
+        .create_post(&CreatePost {
+            author,
+            content: PostContent::new("Hello".to_owned()).unwrap(),
+        })
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn pinned_posts_are_limited() {
+    let database = TestDatabase::new().await;
+    let db = database.client();
+    let alice = create_user(&database, "alice").await;
+    let first = create_post(&database, alice).await;
+    let second = create_post(&database, alice).await;
+
+    assert!(db.pin_post(first, 1).await.unwrap());
+    // Pinning again changes nothing.
+    assert!(db.pin_post(first, 1).await.unwrap());
+    assert!(!db.pin_post(second, 1).await.unwrap());
+    db.unpin_post(first).await.unwrap();
+    assert!(db.pin_post(second, 1).await.unwrap());
+
+    database.remove().await;
+}
+
+#[tokio::test]
+async fn follows_are_counted() {
+    let database = TestDatabase::new().await;
+    let db = database.client();
+    let alice = create_user(&database, "alice").await;
+    let bob = create_user(&database, "bob").await;
+
+    assert!(db.follow_user(alice, bob).await.unwrap());
+    assert!(!db.follow_user(alice, bob).await.unwrap());
+    let alice_stats = db.fetch_user_profile(alice).await.unwrap().unwrap().stats;
+    let bob_stats = db.fetch_user_profile(bob).await.unwrap().unwrap().stats;
+    assert_eq!(
+        (alice_stats.following_count, alice_stats.follower_count),
+        (1, 0)
+    );
+    assert_eq!(
+        (bob_stats.following_count, bob_stats.follower_count),
+        (0, 1)
+    );
+
+    assert!(db.unfollow_user(alice, bob).await.unwrap());
+    assert!(!db.unfollow_user(alice, bob).await.unwrap());
+    let bob_stats = db.fetch_user_profile(bob).await.unwrap().unwrap().stats;
+    assert_eq!(bob_stats.follower_count, 0);
+
+    database.remove().await;
+}
+
+#[tokio::test]
+async fn user_posts_are_paginated() {
+    let database = TestDatabase::new().await;
+    let db = database.client();
+    let alice = create_user(&database, "alice").await;
+    let mut posts = Vec::new();
+    for _ in 0..5 {
+        posts.push(create_post(&database, alice).await);
+    }
+    posts.reverse();
+    let ids = |page: Vec<PartialPost>| page.into_iter().map(|post| post.id).collect::<Vec<_>>();
+
+    let first_page = db.fetch_user_posts(alice, None, None, 2).await.unwrap();
+    assert_eq!(ids(first_page.unwrap()), posts[..2]);
+    let second_page = db
+        .fetch_user_posts(alice, Some(posts[1]), None, 2)
+        .await
+        .unwrap();
+    assert_eq!(ids(second_page.unwrap()), posts[2..4]);
+    let newer = db
+        .fetch_user_posts(alice, None, Some(posts[2]), 10)
+        .await
+        .unwrap();
+    assert_eq!(ids(newer.unwrap()), posts[..2]);
+
+    let missing = Id::from(alice.snowflake().get() + 1);
+    assert!(
+        db.fetch_user_posts(missing, None, None, 2)
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    database.remove().await;
+}
+
+#[tokio::test]
+async fn post_streams_skip_hidden_posts() {
+    let database = TestDatabase::new().await;
+    let db = database.client();
+    let alice = create_user(&database, "alice").await;
+    let bob = create_user(&database, "bob").await;
+    let first = create_post(&database, alice).await;
+    let deleted = create_post(&database, alice).await;
+    let second = create_post(&database, bob).await;
+    let third = create_post(&database, alice).await;
+    let carol = create_user(&database, "carol").await;
+    create_post(&database, carol).await;
+    let dave = create_user(&database, "dave").await;
+    create_post(&database, dave).await;
+    assert!(db.delete_post(deleted).await.unwrap());
+    db.suspend_user(carol).await.unwrap();
+    db.shadowban_user(dave).await.unwrap();
+
+    let user_posts: Vec<PartialPost> = db
+        .fetch_user_posts_stream(alice)
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(
+        user_posts
+            .into_iter()
+            .map(|post| post.id)
+            .collect::<Vec<_>>(),
+        [first, third]
+    );
+    let public_posts: Vec<Post> = db
+        .fetch_all_public_posts_stream(&TenantId::default())
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(
+        public_posts
+            .iter()
+            .map(|post| (post.id, post.author.id))
+            .collect::<Vec<_>>(),
+        [(first, alice), (second, bob), (third, alice)]
+    );
+
+    database.remove().await;
+}
+
+#[tokio::test]
+async fn filter_updates_check_the_version() {
+    let database = TestDatabase::new().await;
+    let db = database.client();
+    let alice = create_user(&database, "alice").await;
+    let mut settings = FilterSettings {
+        phrase: "spoiler".to_owned(),
+        regex: false,
+        contexts: vec![FilterContext::Home],
+        action: FilterAction::default(),
+        expires_at: None,
+    };
+    let filter = db.create_filter(alice, &settings).await.unwrap();
+    let version = db.fetch_filter(filter).await.unwrap().unwrap().version;
+
+    settings.phrase = "spoilers".to_owned();
+    let updated = db
+        .update_filter(alice, filter, &settings, Some(version))
+        .await
+        .unwrap();
+    assert_eq!(updated, Some(version + 1));
+    assert!(matches!(
+        db.update_filter(alice, filter, &settings, Some(version)).await,
+        Err(DbError::Conflict { current_version }) if current_version == version + 1
+    ));
+
+    database.remove().await;
+}
+
+#[tokio::test]
+async fn mention_webhooks_wait_for_do_not_disturb() {
+    let database = TestDatabase::new().await;
+    let db = database.client();
+    let alice = create_user(&database, "alice").await;
+    let bob = create_user(&database, "bob").await;
+    let post = create_post(&database, bob).await;
+    db.create_webhook(
+        alice,
+        &CreateWebhook {
+            url: WebhookUrl::new("https://alice.example/hook".to_owned()).unwrap(),
+            events: vec![WebhookEventKind::Mention],
+        },
+        &WebhookSecret::generate_random(),
+        1,
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    let now = UtcDateTime::now();
+    let snoozed_until = now + Duration::hours(1);
+    db.set_do_not_disturb(
+        alice,
+        DoNotDisturb {
+            quiet_hours: None,
+            snoozed_until: Some(snoozed_until),
+        },
+    )
+    .await
+    .unwrap();
+
+    db.create_notification(&CreateNotification {
+        user: alice,
+        kind: NotificationKind::Mention,
+        actor: bob,
+        post: Some(post),
+    })
+    .await
+    .unwrap();
+    let claim = |now: UtcDateTime| {
+        db.claim_jobs(
+            JobKind::WebhookDelivery,
+            now,
+            now + Duration::minutes(1),
+            10,
+        )
+    };
+    assert!(claim(now).await.unwrap().is_empty());
+    assert_eq!(claim(snoozed_until).await.unwrap().len(), 1);
+
+    database.remove().await;
+}
+
+#[tokio::test]
+async fn shadowbans_report_changes() {
+    let database = TestDatabase::new().await;
+    let db = database.client();
+    let alice = create_user(&database, "alice").await;
+
+    assert!(db.shadowban_user(alice).await.unwrap());
+    let shadowbanned_at = db
+        .fetch_moderated_user(alice)
+        .await
+        .unwrap()
+        .unwrap()
+        .shadowbanned_at;
+    assert!(!db.shadowban_user(alice).await.unwrap());
+    let user = db.fetch_moderated_user(alice).await.unwrap().unwrap();
+    assert_eq!(user.shadowbanned_at, shadowbanned_at);
+    assert!(db.unshadowban_user(alice).await.unwrap());
+    assert!(!db.unshadowban_user(alice).await.unwrap());
+
+    database.remove().await;
+}
+
+#[tokio::test]
+async fn public_posts_leave_out_suspended_users() {
+    let database = TestDatabase::new().await;
+    let db = database.client();
+    let alice = create_user(&database, "alice").await;
+    let bob = create_user(&database, "bob").await;
+    let alice_post = create_post(&database, alice).await;
+    create_post(&database, bob).await;
+    db.suspend_user(bob).await.unwrap();
+
+    let posts = db
+        .fetch_public_posts(&TenantId::default(), Some(bob), None, None, 10)
+        .await
+        .unwrap();
+    assert_eq!(
+        posts.into_iter().map(|post| post.id).collect::<Vec<_>>(),
+        [alice_post]
+    );
+
+    database.remove().await;
+}
+
+#[tokio::test]
+async fn users_and_reports_are_listed_per_tenant() {
+    let database = TestDatabase::new().await;
+    let db = database.client();
+    let alice = create_user(&database, "alice").await;
+    let knitting = TenantId::new("knitting".to_owned()).unwrap();
+    let bob = db
+        .create_user(
+            &knitting,
+            &CreateUser {
+                handle: UserHandle::new("bob".to_owned()).unwrap(),
+            },
+        )
+        .await
+        .unwrap();
+    let report = db
+        .create_report(&CreateReport {
+            reporter: Some(alice),
+            target_user: bob,
+            target_post: None,
+            category: ReportCategory::Other,
+            comment: ReportComment::default(),
+        })
+        .await
+        .unwrap();
+
+    let accounts = db
+        .fetch_user_accounts(&TenantId::default(), None, None, 10)
+        .await
+        .unwrap();
+    assert_eq!(
+        accounts
+            .into_iter()
+            .map(|account| account.user.id)
+            .collect::<Vec<_>>(),
+        [alice]
+    );
+    assert!(
+        db.fetch_report(&TenantId::default(), report)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(db.fetch_report(&knitting, report).await.unwrap().is_some());
+    assert!(
+        db.fetch_open_reports(&TenantId::default())
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(
+        db.fetch_reports(&knitting, None, None, None, 10)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+
+    database.remove().await;
+}
@@ -0,0 +1,347 @@
+//! Drives a weighted mix of requests against a running instance and reports their latencies.
+//!
+//! Each worker sends one request at a time, so `--concurrency` is the number of requests in flight.
+//! Timeline reads page through the public timeline with the cursors of the previous page,
+//! restarting at the newest page when they reach the end.
+//! Latency percentiles are of successful requests, failed ones are counted by their error code.
+
+use clap::Parser;
+use reqwest::Url;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+use stellwerk_client::client::{Client, ClientError};
+use stellwerk_common::model::{
+    admin::CreateUserAccount,
+    pagination::{Cursor, PageRequest},
+    post::{PostContent, PostMarker},
+};
+use tokio::task::JoinSet;
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    /// The base URL of the instance.
+    #[arg(long, env = "STELLWERK_URL", default_value = "http://localhost:8080")]
+    url: Url,
+    /// The auth token to send requests with. Posting needs a user, registering an admin.
+    #[arg(long, env = "STELLWERK_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    /// Number of workers sending requests concurrently.
+    #[arg(long, default_value_t = 8)]
+    concurrency: u16,
+    /// How long to send requests, in seconds.
+    #[arg(long, default_value_t = 30)]
+    duration: u64,
+    /// Relative weight of reading pages of the public timeline.
+    #[arg(long, default_value_t = 6)]
+    timeline: u32,
+    /// Relative weight of reading pages of the posts of `--user`.
+    #[arg(long, default_value_t = 3)]
+    user_posts: u32,
+    /// Relative weight of creating posts.
+    #[arg(long, default_value_t = 1)]
+    post: u32,
+    /// Relative weight of creating users through the admin API.
+    #[arg(long, default_value_t = 0)]
+    register: u32,
+    /// The user whose posts are read.
+    #[arg(long, default_value_t = 1)]
+    user: u64,
+    /// The page size of reads, defaults to that of the server.
+    #[arg(long)]
+    limit: Option<u32>,
+    /// Print the report as JSON.
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Operation {
+    Timeline,
+    UserPosts,
+    Post,
+    Register,
+}
+
+/// The outcomes of one operation, merged over all workers.
+#[derive(Clone, Debug, Default)]
+struct Samples {
+    latencies: Vec<Duration>,
+    /// Counts of failed requests by their error code, or status if they have none.
+    errors: BTreeMap<String, u64>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct OperationReport {
+    requests: usize,
+    errors: BTreeMap<String, u64>,
+    requests_per_second: f64,
+    p50_ms: f64,
+    p90_ms: f64,
+    p99_ms: f64,
+    max_ms: f64,
+}
+
+/// The state of one worker.
+struct Worker {
+    client: Client,
+    /// Pairs of operations and their weights, none of them zero.
+    mix: Vec<(Operation, u32)>,
+    args: WorkerArgs,
+    timeline_cursor: Option<Cursor<PostMarker>>,
+    user_posts_cursor: Option<Cursor<PostMarker>>,
+    samples: BTreeMap<Operation, Samples>,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct WorkerArgs {
+    user: u64,
+    limit: Option<u32>,
+}
+
+impl Operation {
+    fn as_str(self) -> &'static str {
+        match self {
+            Operation::Timeline => "timeline",
+            Operation::UserPosts => "user_posts",
+            Operation::Post => "post",
+            Operation::Register => "register",
+        }
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Samples {
+    fn report(&mut self, elapsed: Duration) -> OperationReport {
+        self.latencies.sort_unstable();
+        let percentile = |percentile: f64| {
+            // Nearest rank, which is always one of the samples.
+            #[allow(
+                clippy::cast_possible_truncation,
+                clippy::cast_sign_loss,
+                clippy::cast_precision_loss
+            )]
+            let rank = (percentile * self.latencies.len() as f64).ceil() as usize;
+            self.latencies
+                .get(rank.saturating_sub(1))
+                .map_or(0.0, |latency| latency.as_secs_f64() * 1000.0)
+        };
+
+        #[allow(clippy::cast_precision_loss)]
+        let requests_per_second = self.latencies.len() as f64 / elapsed.as_secs_f64();
+        OperationReport {
+            requests: self.latencies.len(),
+            errors: std::mem::take(&mut self.errors),
+            requests_per_second,
+            p50_ms: percentile(0.5),
+            p90_ms: percentile(0.9),
+            p99_ms: percentile(0.99),
+            max_ms: percentile(1.0),
+        }
+    }
+}
+
+impl Worker {
+    fn choose_operation(&self) -> Operation {
+        let total: u32 = self.mix.iter().map(|&(_, weight)| weight).sum();
+        let mut choice = rand::random_range(0..total);
+        for &(operation, weight) in &self.mix {
+            if choice < weight {
+                return operation;
+            }
+            choice -= weight;
+        }
+        unreachable!("The choice is less than the total weight")
+    }
+
+    fn page_request(&self, cursor: Option<Cursor<PostMarker>>) -> PageRequest<PostMarker> {
+        let page = cursor.map_or(
+            PageRequest {
+                limit: None,
+                max_id: None,
+                since_id: None,
+            },
+            PageRequest::from,
+        );
+        PageRequest {
+            limit: self.args.limit,
+            ..page
+        }
+    }
+
+    async fn run_operation(&mut self, operation: Operation) -> Result<(), ClientError> {
+        match operation {
+            Operation::Timeline => {
+                let page = self.page_request(self.timeline_cursor);
+                self.timeline_cursor = self.client.public_timeline(&page).await?.next_cursor;
+            }
+            Operation::UserPosts => {
+                let page = self.page_request(self.user_posts_cursor);
+                self.user_posts_cursor = self
+                    .client
+                    .user_posts(self.args.user.into(), &page)
+                    .await?
+                    .next_cursor;
+            }
+            Operation::Post => {
+                let content = format!("Load test post {:016x}", rand::random::<u64>());
+                let content = PostContent::new(content).expect("The post content is valid");
+                self.client.create_post(&content).await?;
+            }
+            Operation::Register => {
+                let handle = format!("loadtest_{:016x}", rand::random::<u64>());
+                let account = CreateUserAccount::new(handle).expect("The handle is valid");
+                self.client.create_user(&account).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn run(mut self, deadline: Instant) -> BTreeMap<Operation, Samples> {
+        while Instant::now() < deadline {
+            let operation = self.choose_operation();
+            let start = Instant::now();
+            let result = self.run_operation(operation).await;
+            let latency = start.elapsed();
+
+            let samples = self.samples.entry(operation).or_default();
+            match result {
+                Ok(()) => samples.latencies.push(latency),
+                Err(error) => *samples.errors.entry(error_kind(&error)).or_default() += 1,
+            }
+        }
+
+        self.samples
+    }
+}
+
+/// What went wrong, coarsely enough to be counted.
+fn error_kind(error: &ClientError) -> String {
+    match error {
+        ClientError::Problem(problem) => problem.code.to_string(),
+        ClientError::Status(status) => status.to_string(),
+        ClientError::Http(error) if error.is_timeout() => "timeout".to_owned(),
+        ClientError::Http(error) if error.is_connect() => "connection_failed".to_owned(),
+        error => error.to_string(),
+    }
+}
+
+fn print_report(reports: &BTreeMap<Operation, OperationReport>) {
+    println!(
+        "{:<12} {:>9} {:>7} {:>9} {:>9} {:>9} {:>9} {:>9}",
+        "operation", "requests", "errors", "req/s", "p50 ms", "p90 ms", "p99 ms", "max ms"
+    );
+    for (operation, report) in reports {
+        let errors: u64 = report.errors.values().sum();
+        println!(
+            "{:<12} {:>9} {:>7} {:>9.1} {:>9.2} {:>9.2} {:>9.2} {:>9.2}",
+            operation.as_str(),
+            report.requests,
+            errors,
+            report.requests_per_second,
+            report.p50_ms,
+            report.p90_ms,
+            report.p99_ms,
+            report.max_ms,
+        );
+    }
+
+    for (operation, report) in reports {
+        for (kind, count) in &report.errors {
+            println!("{operation} failed {count} times with {kind}");
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let mix: Vec<_> = [
+        (Operation::Timeline, args.timeline),
+        (Operation::UserPosts, args.user_posts),
+        (Operation::Post, args.post),
+        (Operation::Register, args.register),
+    ]
+    .into_iter()
+    .filter(|&(_, weight)| weight > 0)
+    .collect();
+    if mix.is_empty() {
+        eprintln!("Error: All operations have a weight of 0");
+        return ExitCode::FAILURE;
+    }
+
+    let mut client = match Client::new(args.url) {
+        Ok(client) => client,
+        Err(error) => {
+            eprintln!("Error: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Some(token) = args.token {
+        client = client.with_token(token);
+    }
+
+    let start = Instant::now();
+    let deadline = start + Duration::from_secs(args.duration);
+    let mut workers = JoinSet::new();
+    for _ in 0..args.concurrency {
+        let worker = Worker {
+            client: client.clone(),
+            mix: mix.clone(),
+            args: WorkerArgs {
+                user: args.user,
+                limit: args.limit,
+            },
+            timeline_cursor: None,
+            user_posts_cursor: None,
+            samples: BTreeMap::new(),
+        };
+        workers.spawn(worker.run(deadline));
+    }
+
+    let mut samples = BTreeMap::<Operation, Samples>::new();
+    while let Some(worker_samples) = workers.join_next().await {
+        let worker_samples = worker_samples.expect("Workers do not panic");
+        for (operation, worker_samples) in worker_samples {
+            let samples = samples.entry(operation).or_default();
+            samples.latencies.extend(worker_samples.latencies);
+            for (kind, count) in worker_samples.errors {
+                *samples.errors.entry(kind).or_default() += count;
+            }
+        }
+    }
+    let elapsed = start.elapsed();
+
+    let reports: BTreeMap<_, _> = samples
+        .into_iter()
+        .map(|(operation, mut samples)| (operation, samples.report(elapsed)))
+        .collect();
+    if args.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&reports).expect("Reports serialize infallibly")
+        );
+    } else {
+        print_report(&reports);
+    }
+
+    let failed = reports.values().any(|report| !report.errors.is_empty());
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,404 @@
+//! A stand-in for the API that serves its user, post, and timeline routes from memory,
+//! so that frontends can be developed without Postgres.
+//!
+//! The data is seeded deterministically: with the same arguments, users and posts have the same
+//! IDs and contents on every start. Changes are lost on exit.
+//!
+//! Any well-formed auth token of an existing user authenticates as that user, the rest of the
+//! token is not checked. Routes that are not mocked reply with `unknown_route`.
+
+use axum::{
+    Json, Router,
+    extract::{
+        FromRequestParts, OriginalUri, Path, Query, State,
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+    },
+    http::{
+        HeaderMap, HeaderValue, StatusCode, Uri,
+        header::{AUTHORIZATION, CONTENT_TYPE, LINK},
+        request::Parts,
+    },
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use clap::Parser;
+use serde::Deserialize;
+use std::{net::SocketAddr, process::ExitCode, sync::Arc};
+use stellwerk_common::model::{
+    Id,
+    auth::AuthToken,
+    pagination::{Cursor, PageRequest, next_cursor},
+    post::{CreatePost, PartialPost, Post, PostContent, PostMarker},
+    problem::{ErrorCode, PROBLEM_JSON, Problem},
+    tenant::TenantId,
+    user::{UserMarker, UserProfile},
+};
+use stellwerk_db::{
+    client::DbError,
+    memory::MemoryStore,
+    seed::{self, SeedSettings},
+    store::Store,
+};
+use thiserror::Error;
+
+/// Like the default limit of the API.
+const MAX_PINNED_POSTS: usize = 5;
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Args {
+    #[arg(long, env = "MOCK_ADDRESS", default_value = "127.0.0.1:8080")]
+    address: SocketAddr,
+    /// Seeds the handles and post contents.
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    #[arg(long, default_value_t = 10)]
+    users: u32,
+    #[arg(long, default_value_t = 200)]
+    posts: u32,
+}
+
+type MockState = Arc<MemoryStore>;
+
+#[derive(Debug, Error)]
+enum MockError {
+    #[error("Unknown route requested: {0}")]
+    UnknownRoute(Uri),
+    #[error("Path rejected: {0}")]
+    Path(#[from] PathRejection),
+    #[error("Query string rejected: {0}")]
+    Query(#[from] QueryRejection),
+    #[error("Incoming JSON rejected: {0}")]
+    Json(#[from] JsonRejection),
+    #[error("Authorization header was missing")]
+    AuthenticationRequired,
+    #[error("Authorization header was invalid")]
+    InvalidAuthorizationHeader,
+    #[error("Provided token was invalid")]
+    InvalidToken,
+    #[error(transparent)]
+    Store(#[from] DbError),
+    #[error("Post with id {0} was not found.")]
+    PostByIdNotFound(Id<PostMarker>),
+    #[error("User with id {0} was not found.")]
+    UserByIdNotFound(Id<UserMarker>),
+    #[error("The authenticated user is not the author of post {0}.")]
+    NotPostAuthor(Id<PostMarker>),
+    #[error("At most {0} posts can be pinned.")]
+    PinnedPostLimitReached(usize),
+}
+
+type Result<T, E = MockError> = std::result::Result<T, E>;
+
+/// The status and code of each error are those the API replies with.
+impl MockError {
+    fn status(&self) -> StatusCode {
+        match self {
+            MockError::UnknownRoute(_)
+            | MockError::Path(_)
+            | MockError::PostByIdNotFound(_)
+            | MockError::UserByIdNotFound(_) => StatusCode::NOT_FOUND,
+            MockError::Json(JsonRejection::JsonDataError(_)) => StatusCode::UNPROCESSABLE_ENTITY,
+            MockError::Query(_) | MockError::Json(_) | MockError::InvalidAuthorizationHeader => {
+                StatusCode::BAD_REQUEST
+            }
+            MockError::AuthenticationRequired | MockError::InvalidToken => StatusCode::UNAUTHORIZED,
+            MockError::NotPostAuthor(_) => StatusCode::FORBIDDEN,
+            MockError::PinnedPostLimitReached(_) => StatusCode::CONFLICT,
+            MockError::Store(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn code(&self) -> ErrorCode {
+        match self {
+            MockError::UnknownRoute(_) => ErrorCode::UnknownRoute,
+            MockError::Path(_) => ErrorCode::InvalidPath,
+            MockError::Query(_) => ErrorCode::InvalidQuery,
+            MockError::Json(JsonRejection::JsonDataError(_)) => ErrorCode::ValidationFailed,
+            MockError::Json(_) => ErrorCode::InvalidJson,
+            MockError::AuthenticationRequired => ErrorCode::AuthenticationRequired,
+            MockError::InvalidAuthorizationHeader => ErrorCode::InvalidAuthorizationHeader,
+            MockError::InvalidToken => ErrorCode::InvalidToken,
+            MockError::Store(_) => ErrorCode::InternalError,
+            MockError::PostByIdNotFound(_) => ErrorCode::PostNotFound,
+            MockError::UserByIdNotFound(_) => ErrorCode::UserNotFound,
+            MockError::NotPostAuthor(_) => ErrorCode::NotPostAuthor,
+            MockError::PinnedPostLimitReached(_) => ErrorCode::PinnedPostLimitReached,
+        }
+    }
+}
+
+impl IntoResponse for MockError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        let mut problem = Problem::new(
+            self.code(),
+            status.as_u16(),
+            status.canonical_reason().unwrap_or_default().to_owned(),
+        );
+        problem.detail = Some(self.to_string());
+
+        let body = serde_json::to_vec(&problem).expect("Problem is always serializable");
+        (
+            status,
+            [(CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON))],
+            body,
+        )
+            .into_response()
+    }
+}
+
+/// The user of any well-formed auth token whose user exists.
+#[derive(Copy, Clone, Debug)]
+struct AuthenticatedUser(Id<UserMarker>);
+
+impl FromRequestParts<MockState> for AuthenticatedUser {
+    type Rejection = MockError;
+
+    async fn from_request_parts(parts: &mut Parts, store: &MockState) -> Result<Self> {
+        let header = parts
+            .headers
+            .get(AUTHORIZATION)
+            .ok_or(MockError::AuthenticationRequired)?;
+        let token = header
+            .to_str()
+            .ok()
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .ok_or(MockError::InvalidAuthorizationHeader)?;
+        let token: AuthToken = token.parse().map_err(|_| MockError::InvalidToken)?;
+
+        if store.fetch_user(token.user_id).await?.is_none() {
+            return Err(MockError::InvalidToken);
+        }
+
+        Ok(Self(token.user_id))
+    }
+}
+
+/// The `Link` header of the API, referencing the next (older) page.
+fn link_headers(path: &str, limit: u32, ids: &[Id<PostMarker>]) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Some(Cursor::Older(oldest)) = next_cursor(limit, ids) {
+        let value = HeaderValue::try_from(format!(
+            "<{path}?limit={limit}&max_id={oldest}>; rel=\"next\""
+        ))
+        .expect("Link header consists of a path and ascii characters");
+        headers.insert(LINK, value);
+    }
+
+    headers
+}
+
+async fn get_user(
+    path: Result<Path<Id<UserMarker>>, PathRejection>,
+    State(store): State<MockState>,
+) -> Result<Json<UserProfile>> {
+    let Path(id) = path?;
+    let profile = store
+        .fetch_user_profile(id)
+        .await?
+        .ok_or(MockError::UserByIdNotFound(id))?;
+
+    Ok(Json(profile))
+}
+
+async fn get_user_posts(
+    path: Result<Path<Id<UserMarker>>, PathRejection>,
+    OriginalUri(uri): OriginalUri,
+    query: Result<Query<PageRequest<PostMarker>>, QueryRejection>,
+    State(store): State<MockState>,
+) -> Result<(HeaderMap, Json<Vec<PartialPost>>)> {
+    let Path(id) = path?;
+    let Query(query) = query?;
+    let limit = query.limit();
+    let posts = store
+        .fetch_user_posts(id, query.max_id, query.since_id, limit)
+        .await?
+        .ok_or(MockError::UserByIdNotFound(id))?;
+
+    let ids: Vec<_> = posts
+        .iter()
+        .filter(|post| !post.pinned)
+        .map(|post| post.id)
+        .collect();
+
+    Ok((link_headers(uri.path(), limit, &ids), Json(posts)))
+}
+
+async fn get_post(
+    path: Result<Path<Id<PostMarker>>, PathRejection>,
+    State(store): State<MockState>,
+) -> Result<Json<Post>> {
+    let Path(id) = path?;
+    let post = store
+        .fetch_post(id)
+        .await?
+        .ok_or(MockError::PostByIdNotFound(id))?;
+
+    Ok(Json(post))
+}
+
+#[derive(Deserialize)]
+struct CreatePostBody {
+    content: PostContent,
+}
+
+async fn create_post(
+    AuthenticatedUser(author): AuthenticatedUser,
+    State(store): State<MockState>,
+    body: Result<Json<CreatePostBody>, JsonRejection>,
+) -> Result<(StatusCode, Json<PartialPost>)> {
+    let Json(CreatePostBody { content }) = body?;
+    let id = store.create_post(&CreatePost { author, content }).await?;
+    let post = store
+        .fetch_post(id)
+        .await?
+        .ok_or(MockError::PostByIdNotFound(id))?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(PartialPost {
+            id: post.id,
+            content: post.content,
+            content_html: post.content_html,
+            pinned: false,
+        }),
+    ))
+}
+
+async fn check_post_author(
+    store: &MemoryStore,
+    AuthenticatedUser(user): AuthenticatedUser,
+    id: Id<PostMarker>,
+) -> Result<()> {
+    let post = store
+        .fetch_post(id)
+        .await?
+        .ok_or(MockError::PostByIdNotFound(id))?;
+    if post.author.id != user {
+        return Err(MockError::NotPostAuthor(id));
+    }
+
+    Ok(())
+}
+
+async fn pin_post(
+    path: Result<Path<Id<PostMarker>>, PathRejection>,
+    user: AuthenticatedUser,
+    State(store): State<MockState>,
+) -> Result<StatusCode> {
+    let Path(id) = path?;
+    check_post_author(&store, user, id).await?;
+    if !store.pin_post(id, MAX_PINNED_POSTS).await? {
+        return Err(MockError::PinnedPostLimitReached(MAX_PINNED_POSTS));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn unpin_post(
+    path: Result<Path<Id<PostMarker>>, PathRejection>,
+    user: AuthenticatedUser,
+    State(store): State<MockState>,
+) -> Result<StatusCode> {
+    let Path(id) = path?;
+    check_post_author(&store, user, id).await?;
+    store.unpin_post(id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_public_timeline(
+    OriginalUri(uri): OriginalUri,
+    query: Result<Query<PageRequest<PostMarker>>, QueryRejection>,
+    State(store): State<MockState>,
+) -> Result<(HeaderMap, Json<Vec<Post>>)> {
+    let Query(query) = query?;
+    let limit = query.limit();
+    let posts = store
+        .fetch_public_posts(
+            &TenantId::default(),
+            None,
+            query.max_id,
+            query.since_id,
+            limit,
+        )
+        .await?;
+
+    let ids: Vec<_> = posts.iter().map(|post| post.id).collect();
+
+    Ok((link_headers(uri.path(), limit, &ids), Json(posts)))
+}
+
+async fn fallback(uri: Uri) -> MockError {
+    MockError::UnknownRoute(uri)
+}
+
+fn routes(store: MockState) -> Router {
+    let v1 = Router::new()
+        .route("/users/{id}", get(get_user))
+        .route("/users/{id}/posts", get(get_user_posts))
+        .route("/posts", post(create_post))
+        .route("/posts/{id}", get(get_post))
+        .route("/posts/{id}/pin", post(pin_post).delete(unpin_post))
+        .route("/timeline/public", get(get_public_timeline));
+
+    Router::new()
+        .nest("/v1", v1)
+        .fallback(fallback)
+        .with_state(store)
+}
+
+/// An auth token of `user`, the same on every start.
+fn example_token(user: Id<UserMarker>) -> String {
+    AuthToken {
+        user_id: user,
+        core: [0; _],
+        salt: [0; _],
+    }
+    .as_token_str()
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let store = Arc::new(MemoryStore::new());
+    let settings = SeedSettings {
+        users: args.users,
+        follows: 0,
+        posts: args.posts,
+        seed: args.seed,
+    };
+    let summary = match seed::seed_store(&*store, &settings).await {
+        Ok(summary) => summary,
+        Err(error) => {
+            eprintln!("Error: Seeding failed: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("Seeded {} users and {} posts", summary.users, summary.posts);
+
+    // The seeded users are the first IDs of the store.
+    for user in (1..=summary.users).map(Id::from) {
+        if let Ok(Some(user)) = store.fetch_user(user).await {
+            println!("@{}  {}", user.handle.get(), example_token(user.id));
+        }
+    }
+
+    let listener = match tokio::net::TcpListener::bind(args.address).await {
+        Ok(listener) => listener,
+        Err(error) => {
+            eprintln!("Error: Binding {} failed: {error}", args.address);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("Listening on http://{}", args.address);
+
+    if let Err(error) = axum::serve(listener, routes(store)).await {
+        eprintln!("Error: {error}");
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}